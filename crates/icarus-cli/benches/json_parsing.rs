@@ -0,0 +1,60 @@
+//! Benchmarks of parsing canister responses in the bridge.
+//!
+//! Run with `cargo bench -p icarus-cli --bench json_parsing`, once without and
+//! once with `--features simd-json`. `serde_json` is always measured as the
+//! baseline, so the second run shows what `simd-json` gains on this machine.
+
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use icarus_cli::utils::json;
+use serde_json::{json, Value};
+
+/// A `tools/call` response with `items` text and image content items, the
+/// shape that makes responses large.
+fn call_tool_response(items: usize) -> String {
+    let content: Vec<Value> = (0..items)
+        .map(|i| {
+            if i % 4 == 0 {
+                json!({ "type": "image", "mimeType": "image/png", "data": "iVBORw0KGgo".repeat(400) })
+            } else {
+                json!({ "type": "text", "text": format!("Record {i}: {}", "lorem ipsum \"dolor\" ".repeat(40)) })
+            }
+        })
+        .collect();
+    json!({
+        "jsonrpc": "2.0",
+        "id": "1",
+        "result": { "content": content, "isError": false }
+    })
+    .to_string()
+}
+
+fn bench_parsing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("call_tool_response");
+    for items in [16, 256, 2048] {
+        let response = call_tool_response(items);
+        group.throughput(Throughput::Bytes(response.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("serde_json", items), &response, |b, r| {
+            b.iter(|| serde_json::from_str::<Value>(black_box(r)).unwrap());
+        });
+        let name = if json::SIMD_ENABLED {
+            "bridge_simd"
+        } else {
+            "bridge_fallback"
+        };
+        group.bench_with_input(BenchmarkId::new(name, items), &response, |b, r| {
+            // The bridge owns each response, so copying it is not measured
+            b.iter_batched(
+                || r.clone(),
+                |text| json::from_string::<Value>(text).unwrap(),
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parsing);
+criterion_main!(benches);
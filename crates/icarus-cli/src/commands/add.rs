@@ -0,0 +1,297 @@
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use std::path::Path;
+use tokio::fs;
+use tracing::{info, warn};
+
+use crate::commands::{AddArgs, AddToolArgs, ToolAuth};
+use crate::utils::{candid, cargo, project};
+use crate::Cli;
+
+pub(crate) async fn execute(args: AddArgs, cli: &Cli) -> Result<()> {
+    match args {
+        AddArgs::Tool(args) => add_tool(args, cli).await,
+    }
+}
+
+async fn add_tool(args: AddToolArgs, cli: &Cli) -> Result<()> {
+    let stub = ToolStub::from_args(&args)?;
+
+    let project_root = project::find_project_root()?;
+    let lib_path = project_root.join("src").join("lib.rs");
+    let source = fs::read_to_string(&lib_path)
+        .await
+        .with_context(|| format!("Failed to read {}", lib_path.display()))?;
+
+    let updated = insert_tool(&source, &stub)?;
+    fs::write(&lib_path, updated)
+        .await
+        .with_context(|| format!("Failed to write {}", lib_path.display()))?;
+
+    if !cli.quiet {
+        println!(
+            "{} Added tool {} to {}",
+            "✅".green(),
+            stub.name.bright_white().bold(),
+            lib_path.display()
+        );
+    }
+    info!("Added tool {} to {}", stub.name, lib_path.display());
+
+    if !args.no_candid {
+        regenerate_candid(&project_root, cli).await?;
+    }
+
+    Ok(())
+}
+
+/// A tool function to insert into lib.rs
+#[derive(Debug)]
+struct ToolStub {
+    name: String,
+    params: Vec<(String, String)>,
+    returns: String,
+    auth: Option<ToolAuth>,
+    description: String,
+}
+
+impl ToolStub {
+    fn from_args(args: &AddToolArgs) -> Result<Self> {
+        syn::parse_str::<syn::Ident>(&args.name)
+            .map_err(|_| anyhow!("'{}' is not a valid function name", args.name))?;
+        syn::parse_str::<syn::Type>(&args.returns)
+            .map_err(|_| anyhow!("'{}' is not a valid return type", args.returns))?;
+
+        Ok(Self {
+            name: args.name.clone(),
+            params: parse_params(&args.params)?,
+            returns: args.returns.trim().to_string(),
+            auth: args.auth,
+            description: args
+                .description
+                .clone()
+                .unwrap_or_else(|| format!("TODO: describe {}", args.name)),
+        })
+    }
+
+    /// Renders the function; `attribute` is the path of the tool macro
+    fn render(&self, attribute: &str) -> String {
+        let mut attribute_args = format!("{:?}", self.description);
+        if let Some(auth) = self.auth {
+            attribute_args.push_str(&format!(", auth = \"{}\"", auth));
+        }
+
+        let params = self
+            .params
+            .iter()
+            .map(|(name, ty)| format!("{}: {}", name, ty))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        format!(
+            "/// {description}\n#[{attribute}({attribute_args})]\nfn {name}({params}) -> {returns} {{\n    todo!(\"implement {name}\")\n}}\n",
+            description = self.description,
+            name = self.name,
+            returns = self.returns,
+        )
+    }
+}
+
+/// Parses `a:f64,b:Vec<String>` into name/type pairs
+fn parse_params(spec: &str) -> Result<Vec<(String, String)>> {
+    let mut params: Vec<(String, String)> = Vec::new();
+
+    for param in split_top_level(spec) {
+        let param = param.trim();
+        if param.is_empty() {
+            continue;
+        }
+
+        let (name, ty) = param
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Parameter '{}' must be written as name:type", param))?;
+        let (name, ty) = (name.trim(), ty.trim());
+
+        syn::parse_str::<syn::Ident>(name)
+            .map_err(|_| anyhow!("'{}' is not a valid parameter name", name))?;
+        syn::parse_str::<syn::Type>(ty)
+            .map_err(|_| anyhow!("'{}' is not a valid type for parameter '{}'", ty, name))?;
+
+        if params.iter().any(|(existing, _)| existing == name) {
+            return Err(anyhow!("Parameter '{}' is listed twice", name));
+        }
+        params.push((name.to_string(), ty.to_string()));
+    }
+
+    Ok(params)
+}
+
+/// Splits on commas that are not nested in `<>`, `()` or `[]`
+fn split_top_level(spec: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+
+    for (index, c) in spec.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&spec[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&spec[start..]);
+    parts
+}
+
+/// Inserts the tool before `export_candid!()`, or at the end of the file
+fn insert_tool(source: &str, stub: &ToolStub) -> Result<String> {
+    let file = syn::parse_file(source).context("src/lib.rs does not parse as Rust")?;
+
+    let exists = file.items.iter().any(|item| match item {
+        syn::Item::Fn(function) => function.sig.ident == stub.name,
+        _ => false,
+    });
+    if exists {
+        return Err(anyhow!("src/lib.rs already defines fn {}", stub.name));
+    }
+
+    // Follow the file's style: `#[tool]` when the macro is imported
+    let attribute = if file.items.iter().any(imports_tool_macro) {
+        "tool"
+    } else {
+        "icarus::tool"
+    };
+    let rendered = stub.render(attribute);
+
+    let lines: Vec<&str> = source.lines().collect();
+    let Some(mut at) = lines
+        .iter()
+        .position(|line| line.contains("export_candid!"))
+    else {
+        let mut updated = source.trim_end().to_string();
+        updated.push_str("\n\n");
+        updated.push_str(&rendered);
+        return Ok(updated);
+    };
+
+    // Keep a comment directly above `export_candid!()` attached to it
+    while at > 0 && lines[at - 1].trim_start().starts_with("//") {
+        at -= 1;
+    }
+
+    let mut updated = lines[..at].join("\n");
+    if !updated.is_empty() {
+        updated.push('\n');
+    }
+    updated.push_str(&rendered);
+    updated.push('\n');
+    updated.push_str(&lines[at..].join("\n"));
+    updated.push('\n');
+    Ok(updated)
+}
+
+/// Returns whether an item is `use icarus::tool;` or `use icarus::{.., tool, ..};`
+fn imports_tool_macro(item: &syn::Item) -> bool {
+    let syn::Item::Use(item_use) = item else {
+        return false;
+    };
+    let syn::UseTree::Path(path) = &item_use.tree else {
+        return false;
+    };
+    if path.ident != "icarus" {
+        return false;
+    }
+
+    match path.tree.as_ref() {
+        syn::UseTree::Name(name) => name.ident == "tool",
+        syn::UseTree::Group(group) => group
+            .items
+            .iter()
+            .any(|tree| matches!(tree, syn::UseTree::Name(name) if name.ident == "tool")),
+        _ => false,
+    }
+}
+
+/// Rebuilds the wasm and extracts its Candid interface
+async fn regenerate_candid(project_root: &Path, cli: &Cli) -> Result<()> {
+    if !candid::is_extractor_available() {
+        warn!(
+            "candid-extractor not found, skipping Candid regeneration \
+             (install it with `cargo install candid-extractor`)"
+        );
+        return Ok(());
+    }
+
+    let config = project::load_project_config(project_root).await?;
+    if !cli.quiet {
+        println!("{}", "  Regenerating Candid interface...".bright_blue());
+    }
+
+    cargo::build_project(project_root, Some("wasm32-unknown-unknown"), true, &[]).await?;
+
+    let refresh = candid::refresh(project_root, &config.name, "release", false).await?;
+    info!("Updated {}", refresh.did_path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stub(params: &str, auth: Option<ToolAuth>) -> ToolStub {
+        ToolStub::from_args(&AddToolArgs {
+            name: "divide".to_string(),
+            params: params.to_string(),
+            returns: "Result<f64, String>".to_string(),
+            auth,
+            description: Some("Divides a by b".to_string()),
+            no_candid: true,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_params() {
+        let params = parse_params("a:f64, tags: HashMap<String, u32>").unwrap();
+        assert_eq!(
+            params,
+            vec![
+                ("a".to_string(), "f64".to_string()),
+                ("tags".to_string(), "HashMap<String, u32>".to_string()),
+            ]
+        );
+
+        assert!(parse_params("").unwrap().is_empty());
+        assert!(parse_params("a").is_err());
+        assert!(parse_params("a:f64,a:f64").is_err());
+        assert!(parse_params("1a:f64").is_err());
+    }
+
+    #[test]
+    fn test_insert_before_export_candid() {
+        let source = "use icarus::tool;\n\n#[tool(\"Echo\")]\nfn echo(s: String) -> String {\n    s\n}\n\n// Export candid interface\nic_cdk::export_candid!();\n";
+
+        let updated = insert_tool(source, &stub("a:f64,b:f64", Some(ToolAuth::User))).unwrap();
+
+        let expected_stub = "/// Divides a by b\n#[tool(\"Divides a by b\", auth = \"user\")]\nfn divide(a: f64, b: f64) -> Result<f64, String> {\n    todo!(\"implement divide\")\n}\n\n// Export candid interface\nic_cdk::export_candid!();\n";
+        assert!(updated.ends_with(expected_stub), "{updated}");
+        assert!(syn::parse_file(&updated).is_ok());
+    }
+
+    #[test]
+    fn test_insert_uses_qualified_attribute_without_import() {
+        let updated = insert_tool("fn helper() {}\n", &stub("", None)).unwrap();
+        assert!(updated.contains("#[icarus::tool(\"Divides a by b\")]\nfn divide() ->"));
+    }
+
+    #[test]
+    fn test_insert_rejects_duplicates_and_invalid_source() {
+        let source = "fn divide() {}\n";
+        assert!(insert_tool(source, &stub("", None)).is_err());
+        assert!(insert_tool("fn broken( {", &stub("", None)).is_err());
+    }
+}
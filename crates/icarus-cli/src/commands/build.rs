@@ -6,12 +6,19 @@ use std::time::Duration;
 use tokio::process::Command;
 use tracing::{info, warn};
 
-use crate::utils::project;
-use crate::{commands::BuildArgs, Cli};
+use crate::utils::{candid, project, wasi, wasm_size};
+use crate::{commands, commands::BuildArgs, Cli};
 
-pub(crate) async fn execute(args: BuildArgs, cli: &Cli) -> Result<()> {
+/// Target of WASM component builds
+const COMPONENT_TARGET: &str = "wasm32-wasip2";
+
+pub(crate) async fn execute(mut args: BuildArgs, cli: &Cli) -> Result<()> {
     info!("Building Icarus MCP canister project");
 
+    if args.component {
+        apply_component_mode(&mut args)?;
+    }
+
     // Verify we're in a valid project directory
     let project_root = project::find_project_root()?;
     let project_config = project::load_project_config(&project_root).await?;
@@ -24,6 +31,33 @@ pub(crate) async fn execute(args: BuildArgs, cli: &Cli) -> Result<()> {
         );
     }
 
+    // Parse the size budget before spending time on the build
+    let max_size = args
+        .max_size
+        .as_deref()
+        .map(wasm_size::parse_size)
+        .transpose()?;
+
+    // Dependencies that need WASI are built for wasm32-wasip1 and adapted
+    // with wasi2ic; an explicit --target is left alone
+    let wasi_needs = if args.target.is_none() {
+        wasi::scan(&project_root).await.unwrap_or_else(|e| {
+            warn!("Skipping WASI detection: {}", e);
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+    let wasi_build = !wasi_needs.is_empty();
+    if wasi_build && !cli.quiet {
+        print_wasi_report(&wasi_needs);
+    }
+    let target = match args.target.as_deref() {
+        Some(target) => target,
+        None if wasi_build => wasi::WASI_TARGET,
+        None => "wasm32-unknown-unknown",
+    };
+
     // Create progress spinner
     let spinner = if !cli.quiet {
         let pb = ProgressBar::new_spinner();
@@ -43,7 +77,34 @@ pub(crate) async fn execute(args: BuildArgs, cli: &Cli) -> Result<()> {
     if let Some(ref pb) = spinner {
         pb.set_message("Building Rust code...");
     }
-    build_rust_code(&args, &project_root).await?;
+    build_rust_code(&args, target, &project_root).await?;
+    if wasi_build {
+        if let Some(ref pb) = spinner {
+            pb.set_message("Adapting WASI imports with wasi2ic...");
+        }
+        wasi::transform(&project_root, &project_config.name, &args.mode).await?;
+    }
+
+    // Keep the Candid file in step with the module just built
+    let wasm_target = args
+        .target
+        .as_deref()
+        .map_or(true, |target| target == "wasm32-unknown-unknown");
+    if wasm_target && !args.no_candid {
+        if candid::is_extractor_available() {
+            if let Some(ref pb) = spinner {
+                pb.set_message("Regenerating Candid interface...");
+            }
+            let refresh =
+                candid::refresh(&project_root, &project_config.name, &args.mode, false).await?;
+            match spinner {
+                Some(ref pb) => pb.suspend(|| commands::candid::report(&refresh, cli)),
+                None => commands::candid::report(&refresh, cli),
+            }
+        } else {
+            warn!("candid-extractor not found, skipping Candid regeneration");
+        }
+    }
 
     // Step 2: Generate canister declarations if requested
     if args.generate_declarations {
@@ -66,22 +127,94 @@ pub(crate) async fn execute(args: BuildArgs, cli: &Cli) -> Result<()> {
         if let Some(ref pb) = spinner {
             pb.set_message("Copying build artifacts...");
         }
-        copy_artifacts(&project_root, output_dir).await?;
+        let target = args.target.as_deref().unwrap_or("wasm32-unknown-unknown");
+        copy_artifacts(&project_root, target, output_dir).await?;
     }
 
+    // Step 5: Check the module against the size budget
+    let gzipped_size = match max_size {
+        Some(max_size) => {
+            if let Some(ref pb) = spinner {
+                pb.set_message("Checking WASM size budget...");
+            }
+            let size = check_size_budget(
+                &args,
+                &project_root,
+                &project_config.name,
+                max_size,
+                spinner.as_ref(),
+            )
+            .await?;
+            Some(size)
+        }
+        None => None,
+    };
+
     if let Some(pb) = spinner {
         pb.finish_with_message("Build completed successfully! ✅");
     }
 
     if !cli.quiet {
-        print_build_summary(&args, &project_root);
+        print_build_summary(
+            &args,
+            &project_root,
+            &project_config.name,
+            wasi_build,
+            gzipped_size,
+        );
     }
 
     info!("Build completed successfully");
     Ok(())
 }
 
-async fn build_rust_code(args: &BuildArgs, project_root: &Path) -> Result<()> {
+/// Turns a `--component` build into a wasm32-wasip2 build with the
+/// `component` feature of `icarus` on. A component has no Candid interface
+/// or canister declarations.
+fn apply_component_mode(args: &mut BuildArgs) -> Result<()> {
+    if let Some(target) = args.target.as_deref() {
+        if target != COMPONENT_TARGET {
+            return Err(anyhow!(
+                "--component builds for {COMPONENT_TARGET}, not --target {target}"
+            ));
+        }
+    }
+    if args.max_size.is_some() {
+        return Err(anyhow!("--max-size does not apply to component builds"));
+    }
+
+    args.target = Some(COMPONENT_TARGET.to_string());
+    if !args
+        .features
+        .iter()
+        .any(|feature| feature == "icarus/component")
+    {
+        args.features.push("icarus/component".to_string());
+    }
+    args.generate_declarations = false;
+    args.no_candid = true;
+    Ok(())
+}
+
+/// Explains why the build goes through WASI
+fn print_wasi_report(needs: &[wasi::WasiNeed]) {
+    println!(
+        "{} Building for {} with wasi2ic, since these dependencies need WASI:",
+        "→".bright_blue(),
+        wasi::WASI_TARGET.bright_cyan()
+    );
+    for need in needs {
+        println!(
+            "  {} {} {}: {}",
+            "•".bright_yellow(),
+            need.package.bright_white(),
+            need.version,
+            need.reason
+        );
+    }
+}
+
+async fn build_rust_code(args: &BuildArgs, target: &str, project_root: &Path) -> Result<()> {
     let mut cmd = Command::new("cargo");
     cmd.arg("build");
     cmd.current_dir(project_root);
@@ -102,13 +235,7 @@ async fn build_rust_code(args: &BuildArgs, project_root: &Path) -> Result<()> {
         }
     }
 
-    // Set target if specified
-    if let Some(ref target) = args.target {
-        cmd.arg("--target").arg(target);
-    } else {
-        // Default to WASM target for IC canisters
-        cmd.arg("--target").arg("wasm32-unknown-unknown");
-    }
+    cmd.arg("--target").arg(target);
 
     // Enable features
     if !args.features.is_empty() {
@@ -126,6 +253,103 @@ async fn build_rust_code(args: &BuildArgs, project_root: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Checks the gzipped module of `package` against `max_size`, returning its
+/// size or an error with the largest crates and functions if it is too big
+async fn check_size_budget(
+    args: &BuildArgs,
+    project_root: &Path,
+    package: &str,
+    max_size: u64,
+    spinner: Option<&ProgressBar>,
+) -> Result<u64> {
+    if args
+        .target
+        .as_deref()
+        .is_some_and(|target| target != "wasm32-unknown-unknown")
+    {
+        return Err(anyhow!(
+            "--max-size only applies to wasm32-unknown-unknown builds"
+        ));
+    }
+
+    let wasm_path = project_root
+        .join("target")
+        .join("wasm32-unknown-unknown")
+        .join(&args.mode)
+        .join(format!("{}.wasm", package.replace('-', "_")));
+    let wasm = tokio::fs::read(&wasm_path)
+        .await
+        .with_context(|| format!("Failed to read WASM module: {}", wasm_path.display()))?;
+
+    let size = wasm_size::gzipped_size(&wasm)?;
+    if size <= max_size {
+        return Ok(size);
+    }
+
+    match spinner {
+        Some(pb) => pb.suspend(|| print_size_report(&wasm, size, max_size)),
+        None => print_size_report(&wasm, size, max_size),
+    }
+    Err(anyhow!(
+        "WASM module is {} gzipped, over the {} budget",
+        wasm_size::format_size(size),
+        wasm_size::format_size(max_size)
+    ))
+}
+
+fn print_size_report(wasm: &[u8], size: u64, max_size: u64) {
+    const TOP: usize = 10;
+
+    println!(
+        "\n{} {} gzipped, budget {} ({} over)",
+        "WASM size:".bright_red().bold(),
+        wasm_size::format_size(size).bright_red(),
+        wasm_size::format_size(max_size),
+        wasm_size::format_size(size - max_size)
+    );
+
+    let report = match wasm_size::analyze(wasm) {
+        Ok(report) => report,
+        Err(e) => {
+            warn!("Could not attribute the module's size: {}", e);
+            return;
+        }
+    };
+
+    let share = |bytes: u64| {
+        if report.code_size == 0 {
+            0.0
+        } else {
+            bytes as f64 * 100.0 / report.code_size as f64
+        }
+    };
+
+    println!(
+        "\n{} (code section: {})",
+        "Largest crates".bright_white().bold(),
+        wasm_size::format_size(report.code_size)
+    );
+    for (name, bytes) in report.crates.iter().take(TOP) {
+        println!(
+            "  {:>10} {:>5.1}%  {}",
+            wasm_size::format_size(*bytes),
+            share(*bytes),
+            name.bright_yellow()
+        );
+    }
+
+    println!("\n{}", "Largest functions".bright_white().bold());
+    for (name, bytes) in report.functions.iter().take(TOP) {
+        println!(
+            "  {:>10} {:>5.1}%  {}",
+            wasm_size::format_size(*bytes),
+            share(*bytes),
+            name
+        );
+    }
+    println!();
+}
+
 async fn generate_declarations(project_root: &Path) -> Result<()> {
     // Check if dfx.json exists
     let dfx_config_path = project_root.join("dfx.json");
@@ -180,7 +404,7 @@ async fn run_tests(args: &BuildArgs, project_root: &Path) -> Result<()> {
     Ok(())
 }
 
-async fn copy_artifacts(project_root: &Path, output_dir: &Path) -> Result<()> {
+async fn copy_artifacts(project_root: &Path, target: &str, output_dir: &Path) -> Result<()> {
     use tokio::fs;
 
     // Create output directory
@@ -192,10 +416,7 @@ async fn copy_artifacts(project_root: &Path, output_dir: &Path) -> Result<()> {
     })?;
 
     // Find and copy WASM files
-    let target_dir = project_root
-        .join("target")
-        .join("wasm32-unknown-unknown")
-        .join("release");
+    let target_dir = project_root.join("target").join(target).join("release");
 
     if target_dir.exists() {
         let mut entries = fs::read_dir(&target_dir).await?;
@@ -247,7 +468,13 @@ async fn copy_candid_files(candid_dir: &Path, output_dir: &Path) -> Result<()> {
     Ok(())
 }
 
-fn print_build_summary(args: &BuildArgs, project_root: &Path) {
+fn print_build_summary(
+    args: &BuildArgs,
+    project_root: &Path,
+    package: &str,
+    wasi_build: bool,
+    gzipped_size: Option<u64>,
+) {
     println!("\n{}", "📦 Build Summary".bright_white().bold());
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 
@@ -255,6 +482,12 @@ fn print_build_summary(args: &BuildArgs, project_root: &Path) {
 
     if let Some(ref target) = args.target {
         println!("{} {}", "Target:".bright_white(), target.bright_cyan());
+    } else if wasi_build {
+        println!(
+            "{} {} (via wasi2ic)",
+            "Target:".bright_white(),
+            wasi::WASI_TARGET.bright_cyan()
+        );
     } else {
         println!(
             "{} {}",
@@ -289,6 +522,28 @@ fn print_build_summary(args: &BuildArgs, project_root: &Path) {
         );
     }
 
+    if let (Some(size), Some(ref max_size)) = (gzipped_size, &args.max_size) {
+        println!(
+            "{} {} gzipped (budget {})",
+            "WASM size:".bright_white(),
+            wasm_size::format_size(size).bright_green(),
+            max_size
+        );
+    }
+
+    if args.component {
+        let component = project_root
+            .join("target")
+            .join(COMPONENT_TARGET)
+            .join(&args.mode)
+            .join(format!("{}.wasm", package.replace('-', "_")));
+        println!(
+            "{} {}",
+            "Component:".bright_white(),
+            component.display().to_string().bright_cyan()
+        );
+    }
+
     if let Some(ref output_dir) = args.output {
         println!(
             "{} {}",
@@ -323,7 +578,9 @@ mod tests {
             .unwrap();
 
         // Test copying artifacts
-        copy_artifacts(project_root, &output_dir).await.unwrap();
+        copy_artifacts(project_root, "wasm32-unknown-unknown", &output_dir)
+            .await
+            .unwrap();
 
         // Verify the file was copied
         assert!(output_dir.join("test.wasm").exists());
@@ -341,9 +598,38 @@ mod tests {
                 test: false,
                 generate_declarations: false,
                 output: None,
+                max_size: None,
+                no_candid: false,
+                component: false,
             };
             // If this compiles, the mode format is valid
             assert!(args.mode == mode);
         }
     }
+
+    #[test]
+    fn test_component_mode() {
+        let mut args = BuildArgs {
+            target: None,
+            mode: "release".to_string(),
+            features: vec!["search".to_string()],
+            test: false,
+            generate_declarations: true,
+            output: None,
+            max_size: None,
+            no_candid: false,
+            component: true,
+        };
+        apply_component_mode(&mut args).unwrap();
+        assert_eq!(args.target.as_deref(), Some("wasm32-wasip2"));
+        assert_eq!(args.features, ["search", "icarus/component"]);
+        assert!(!args.generate_declarations);
+        assert!(args.no_candid);
+
+        args.target = Some("wasm32-unknown-unknown".to_string());
+        let err = apply_component_mode(&mut args).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("--component builds for wasm32-wasip2"));
+    }
 }
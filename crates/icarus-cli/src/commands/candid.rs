@@ -0,0 +1,78 @@
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use tracing::info;
+
+use crate::utils::candid::{self, CandidRefresh, CandidStatus};
+use crate::utils::{cargo, project};
+use crate::{commands::CandidArgs, Cli};
+
+pub(crate) async fn execute(args: CandidArgs, cli: &Cli) -> Result<()> {
+    if !candid::is_extractor_available() {
+        return Err(anyhow!(
+            "candid-extractor not found. Install it with `cargo install candid-extractor`"
+        ));
+    }
+
+    let project_root = project::find_project_root()?;
+    let config = project::load_project_config(&project_root).await?;
+
+    if !args.no_build {
+        if !cli.quiet {
+            println!(
+                "{} Building {}...",
+                "→".bright_blue(),
+                config.name.bright_cyan()
+            );
+        }
+        cargo::build_project(&project_root, Some("wasm32-unknown-unknown"), true, &[]).await?;
+    }
+
+    let refresh = candid::refresh(&project_root, &config.name, "release", args.check).await?;
+    report(&refresh, cli);
+
+    match refresh.status {
+        CandidStatus::OutOfDate { .. } => Err(anyhow!(
+            "{} is out of date. Run `icarus candid` to regenerate it",
+            refresh.did_path.display()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Prints the outcome of a refresh, with the diff when the file changed
+pub(crate) fn report(refresh: &CandidRefresh, cli: &Cli) {
+    let path = refresh.did_path.display().to_string();
+    match &refresh.status {
+        CandidStatus::UpToDate => {
+            if !cli.quiet {
+                println!("{} {} is up to date", "✅".green(), path.bright_white());
+            }
+        }
+        CandidStatus::Updated { diff } => {
+            if !cli.quiet {
+                println!("{} Updated {}", "✅".green(), path.bright_white());
+                print_diff(diff);
+            }
+            info!("Updated {}", path);
+        }
+        // Printed even when quiet, since the command fails because of it
+        CandidStatus::OutOfDate { diff } => {
+            println!(
+                "{} {} does not match the compiled interface",
+                "❌".red(),
+                path.bright_white()
+            );
+            print_diff(diff);
+        }
+    }
+}
+
+fn print_diff(diff: &str) {
+    for line in diff.lines() {
+        match line.chars().next() {
+            Some('-') => println!("  {}", line.red()),
+            Some('+') => println!("  {}", line.green()),
+            _ => println!("  {}", line.dimmed()),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+
+use crate::commands::{ConfigArgs, ConfigValidateArgs};
+use crate::config::icarus_toml::IcarusToml;
+use crate::utils::project;
+use crate::Cli;
+
+pub(crate) async fn execute(args: ConfigArgs, cli: &Cli) -> Result<()> {
+    match args {
+        ConfigArgs::Validate(args) => validate(args, cli).await,
+    }
+}
+
+async fn validate(args: ConfigValidateArgs, cli: &Cli) -> Result<()> {
+    let path = match args.path {
+        Some(path) => path,
+        None => IcarusToml::path(&project::find_project_root()?),
+    };
+
+    let config = IcarusToml::load_from(&path)
+        .await?
+        .ok_or_else(|| anyhow!("{} not found", path.display()))?;
+
+    let problems = config.validate();
+    if !problems.is_empty() {
+        for problem in &problems {
+            println!("  {} {}", "✗".red(), problem);
+        }
+        return Err(anyhow!(
+            "{} has {} problem(s)",
+            path.display(),
+            problems.len()
+        ));
+    }
+
+    if !cli.quiet {
+        println!(
+            "{} {} is valid",
+            "✅".green(),
+            path.display().to_string().bright_white()
+        );
+    }
+    Ok(())
+}
@@ -2,11 +2,14 @@ use anyhow::{anyhow, Result};
 use colored::Colorize;
 use dialoguer::{theme::ColorfulTheme, Confirm};
 use indicatif::{ProgressBar, ProgressStyle};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tokio::process::Command;
 use tracing::{info, warn};
 
+use crate::config::icarus_toml::IcarusToml;
+use crate::utils::dfx_config::{CanisterIds, DfxJson};
+use crate::utils::pocket_ic::{self, PocketIcDeployment};
 use crate::utils::project;
 use crate::{commands::DeployArgs, Cli};
 
@@ -16,15 +19,22 @@ struct DeploymentSummary {
     network: String,
     mode: String,
     cycles_used: Option<u64>,
+    endpoint: Option<String>,
 }
 
-pub(crate) async fn execute(args: DeployArgs, cli: &Cli) -> Result<()> {
+pub(crate) async fn execute(mut args: DeployArgs, cli: &Cli) -> Result<()> {
     info!("Deploying Icarus MCP canister project");
 
     // Verify we're in a valid project directory
     let project_root = project::find_project_root()?;
     let project_config = project::load_project_config(&project_root).await?;
 
+    // Flags win over the [deploy] defaults in icarus.toml
+    if let Some(config) = IcarusToml::load(&project_root).await? {
+        args.network = args.network.or(config.deploy.network);
+        args.with_cycles = args.with_cycles.or(config.deploy.with_cycles);
+    }
+
     if !cli.quiet {
         println!(
             "{} Deploying project: {}",
@@ -34,12 +44,17 @@ pub(crate) async fn execute(args: DeployArgs, cli: &Cli) -> Result<()> {
         println!(
             "{} Network: {}",
             "→".bright_blue(),
-            args.network.bright_cyan()
+            args.network().bright_cyan()
         );
     }
 
     // Validate network
-    validate_network(&args.network)?;
+    validate_network(args.network())?;
+
+    // PocketIC needs neither dfx nor a replica, so it has its own flow
+    if args.network() == pocket_ic::NETWORK {
+        return deploy_to_pocket_ic(&args, &project_root, cli).await;
+    }
 
     // Pre-deployment checks
     pre_deployment_checks(&args, &project_root).await?;
@@ -65,7 +80,7 @@ pub(crate) async fn execute(args: DeployArgs, cli: &Cli) -> Result<()> {
     };
 
     // Start local dfx replica if deploying to local network
-    if args.network == "local" {
+    if args.network() == "local" {
         if let Some(ref pb) = spinner {
             pb.set_message("Starting local IC replica...");
         }
@@ -106,9 +121,9 @@ pub(crate) async fn execute(args: DeployArgs, cli: &Cli) -> Result<()> {
 
 fn validate_network(network: &str) -> Result<()> {
     match network {
-        "local" | "ic" | "testnet" => Ok(()),
+        "local" | "ic" | "testnet" | pocket_ic::NETWORK => Ok(()),
         _ => Err(anyhow!(
-            "Invalid network: {}. Valid options: local, ic, testnet",
+            "Invalid network: {}. Valid options: local, ic, testnet, pocket-ic",
             network
         )),
     }
@@ -122,16 +137,24 @@ async fn pre_deployment_checks(args: &DeployArgs, project_root: &Path) -> Result
         ));
     }
 
-    // Check if dfx.json exists
-    let dfx_config = project_root.join("dfx.json");
-    if !dfx_config.exists() {
+    // Check if dfx.json exists and defines the canister being deployed
+    if !DfxJson::path(project_root).exists() {
         return Err(anyhow!(
             "dfx.json not found. This doesn't appear to be a valid dfx project."
         ));
     }
+    let dfx_config = DfxJson::load(project_root).await?;
+    if let Some(ref canister) = args.canister {
+        if dfx_config.canister(canister).is_none() {
+            return Err(anyhow!(
+                "Canister '{}' is not defined in dfx.json",
+                canister
+            ));
+        }
+    }
 
     // Check if wallet is configured for ic network
-    if args.network == "ic" {
+    if args.network() == "ic" {
         check_wallet_configuration().await?;
     }
 
@@ -162,13 +185,13 @@ async fn check_wallet_configuration() -> Result<()> {
 fn confirm_deployment(args: &DeployArgs) -> Result<()> {
     let theme = ColorfulTheme::default();
 
-    let prompt = if args.network == "ic" {
+    let prompt = if args.network() == "ic" {
         format!(
             "Deploy to {} network? This will use real cycles.",
-            args.network.bright_red()
+            args.network().bright_red()
         )
     } else {
-        format!("Deploy to {} network?", args.network.bright_cyan())
+        format!("Deploy to {} network?", args.network().bright_cyan())
     };
 
     let confirmed = Confirm::with_theme(&theme)
@@ -242,7 +265,7 @@ async fn build_for_deployment(_args: &DeployArgs, project_root: &Path) -> Result
 async fn deploy_canisters(args: &DeployArgs, project_root: &Path) -> Result<DeploymentSummary> {
     let mut cmd = Command::new("dfx");
     cmd.arg("deploy");
-    cmd.arg("--network").arg(&args.network);
+    cmd.arg("--network").arg(args.network());
     cmd.current_dir(project_root);
 
     // Set deployment mode
@@ -276,18 +299,157 @@ async fn deploy_canisters(args: &DeployArgs, project_root: &Path) -> Result<Depl
         return Err(anyhow!("Deployment failed: {}", stderr));
     }
 
-    // Parse deployment output to extract canister IDs
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let canister_ids = parse_canister_ids(&stdout);
+    // Read the IDs dfx recorded, falling back to its output
+    let mut canister_ids = deployed_canister_ids(args, project_root).await?;
+    if canister_ids.is_empty() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        canister_ids = parse_canister_ids(&stdout);
+    }
 
     Ok(DeploymentSummary {
         canister_ids,
-        network: args.network.clone(),
+        network: args.network().to_string(),
         mode: args.mode.clone(),
         cycles_used: args.with_cycles,
+        endpoint: None,
     })
 }
 
+async fn deploy_to_pocket_ic(args: &DeployArgs, project_root: &Path, cli: &Cli) -> Result<()> {
+    if !DfxJson::path(project_root).exists() {
+        return Err(anyhow!(
+            "dfx.json not found. This doesn't appear to be a valid dfx project."
+        ));
+    }
+    let dfx_config = DfxJson::load(project_root).await?;
+    let targets = pocket_ic_targets(&dfx_config, args.canister.as_deref(), project_root)?;
+
+    if !cli.quiet {
+        println!("{} Building project...", "→".bright_blue());
+    }
+    build_for_deployment(args, project_root).await?;
+
+    if !cli.quiet {
+        println!("{} Starting PocketIC...", "→".bright_blue());
+    }
+    let mut deployment = PocketIcDeployment::start().await?;
+    let cycles = args
+        .with_cycles
+        .map_or(pocket_ic::DEFAULT_CYCLES, u128::from);
+
+    let installed = async {
+        for (name, wasm_path) in &targets {
+            info!("Installing {} from {}", name, wasm_path.display());
+            deployment.install(name, wasm_path, cycles).await?;
+        }
+        deployment.serve(args.pocket_ic_port).await
+    }
+    .await;
+    let endpoint = match installed {
+        Ok(endpoint) => endpoint,
+        Err(e) => {
+            deployment.shutdown().await;
+            return Err(e);
+        }
+    };
+
+    let summary = DeploymentSummary {
+        canister_ids: deployment
+            .canisters()
+            .iter()
+            .map(|(name, id)| (name.clone(), id.to_text()))
+            .collect(),
+        network: args.network().to_string(),
+        mode: "install".to_string(),
+        cycles_used: args.with_cycles,
+        endpoint: Some(endpoint.to_string()),
+    };
+    if !cli.quiet {
+        print_deployment_summary(&summary);
+    }
+
+    if args.keep_alive {
+        println!(
+            "\n{} PocketIC is serving at {}. Press Ctrl+C to stop it.",
+            "→".bright_blue(),
+            endpoint.as_str().bright_cyan()
+        );
+        tokio::signal::ctrl_c().await?;
+    } else if !cli.quiet {
+        println!(
+            "\n{} PocketIC shuts down now; pass --keep-alive to keep it running.",
+            "→".bright_blue()
+        );
+    }
+
+    deployment.shutdown().await;
+    info!("PocketIC instance stopped");
+    Ok(())
+}
+
+/// `(name, WASM path)` of each canister to install on PocketIC
+///
+/// Canisters without a WASM module of their own, such as asset canisters,
+/// are skipped. A Rust canister without a `wasm` entry is expected where
+/// cargo puts the release build of its package.
+fn pocket_ic_targets(
+    dfx_config: &DfxJson,
+    only: Option<&str>,
+    project_root: &Path,
+) -> Result<Vec<(String, PathBuf)>> {
+    if let Some(name) = only {
+        if dfx_config.canister(name).is_none() {
+            return Err(anyhow!("Canister '{}' is not defined in dfx.json", name));
+        }
+    }
+
+    let mut targets = Vec::new();
+    for (name, canister) in &dfx_config.canisters {
+        if only.map_or(false, |only| only != name) {
+            continue;
+        }
+        let wasm = match (&canister.wasm, canister.canister_type.as_str()) {
+            (Some(wasm), "rust" | "custom") => wasm.clone(),
+            (None, "rust") => format!(
+                "target/wasm32-unknown-unknown/release/{}.wasm",
+                canister
+                    .package
+                    .as_deref()
+                    .unwrap_or(name)
+                    .replace('-', "_")
+            ),
+            _ => {
+                warn!(
+                    "Skipping {} canister '{}' on PocketIC",
+                    canister.canister_type, name
+                );
+                continue;
+            }
+        };
+        targets.push((name.clone(), project_root.join(wasm)));
+    }
+
+    if targets.is_empty() {
+        return Err(anyhow!(
+            "No canister in dfx.json can be installed on PocketIC"
+        ));
+    }
+    Ok(targets)
+}
+
+async fn deployed_canister_ids(
+    args: &DeployArgs,
+    project_root: &Path,
+) -> Result<Vec<(String, String)>> {
+    let ids = CanisterIds::load(project_root, args.network()).await?;
+
+    Ok(ids
+        .on_network(args.network())
+        .filter(|(name, _)| args.canister.as_deref().map_or(true, |c| c == *name))
+        .map(|(name, id)| (name.to_string(), id.to_string()))
+        .collect())
+}
+
 fn parse_canister_ids(output: &str) -> Vec<(String, String)> {
     let mut canister_ids = Vec::new();
     let re = regex::Regex::new(r"(\w+):\s+(\w+-\w+-\w+-\w+-\w+)")
@@ -364,9 +526,23 @@ fn print_deployment_summary(summary: &DeploymentSummary) {
             .bold()
     );
 
+    if let Some(ref endpoint) = summary.endpoint {
+        println!("{} {}", "Endpoint:".bright_white(), endpoint.bright_cyan());
+    }
+
     // Print next steps
     println!("\n{}", "Next steps:".bright_white().bold());
-    if summary.network == "local" {
+    if let Some(ref endpoint) = summary.endpoint {
+        println!(
+            "  {} Register with MCP clients: icarus mcp add <canister-id> --client <client> --network {}",
+            "1.".bright_yellow(),
+            endpoint
+        );
+        println!(
+            "  {} Start the bridge while PocketIC runs: icarus mcp start",
+            "2.".bright_yellow()
+        );
+    } else if summary.network == "local" {
         println!(
             "  {} View Candid UI: http://localhost:4943/",
             "1.".bright_yellow()
@@ -390,12 +566,14 @@ fn print_deployment_summary(summary: &DeploymentSummary) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::dfx_config::DfxCanister;
 
     #[test]
     fn test_validate_network() {
         assert!(validate_network("local").is_ok());
         assert!(validate_network("ic").is_ok());
         assert!(validate_network("testnet").is_ok());
+        assert!(validate_network("pocket-ic").is_ok());
         assert!(validate_network("invalid").is_err());
     }
 
@@ -432,10 +610,52 @@ URLs:
             network: "local".to_string(),
             mode: "install".to_string(),
             cycles_used: Some(1_000_000),
+            endpoint: None,
         };
 
         assert_eq!(summary.canister_ids.len(), 2);
         assert_eq!(summary.network, "local");
         assert_eq!(summary.cycles_used, Some(1_000_000));
     }
+
+    #[test]
+    fn test_pocket_ic_targets() {
+        let root = Path::new("/project");
+        let mut dfx_config = DfxJson::for_rust_canister("weather-bot");
+        dfx_config.add_canister(
+            "frontend",
+            DfxCanister {
+                canister_type: "assets".to_string(),
+                ..DfxCanister::default()
+            },
+        );
+        dfx_config.add_canister(
+            "indexer",
+            DfxCanister {
+                canister_type: "rust".to_string(),
+                package: Some("indexer-core".to_string()),
+                ..DfxCanister::default()
+            },
+        );
+
+        let targets = pocket_ic_targets(&dfx_config, None, root).unwrap();
+        assert_eq!(
+            targets,
+            vec![
+                (
+                    "indexer".to_string(),
+                    root.join("target/wasm32-unknown-unknown/release/indexer_core.wasm")
+                ),
+                (
+                    "weather-bot".to_string(),
+                    root.join("target/wasm32-unknown-unknown/release/weather_bot.wasm")
+                ),
+            ]
+        );
+
+        let only = pocket_ic_targets(&dfx_config, Some("indexer"), root).unwrap();
+        assert_eq!(only.len(), 1);
+        assert!(pocket_ic_targets(&dfx_config, Some("missing"), root).is_err());
+        assert!(pocket_ic_targets(&dfx_config, Some("frontend"), root).is_err());
+    }
 }
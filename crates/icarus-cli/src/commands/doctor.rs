@@ -0,0 +1,741 @@
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tracing::info;
+
+use icarus_core::Tool;
+
+use crate::commands::generate::{fetch_server_info, fetch_tools, resolve_target};
+use crate::commands::mcp::add::parse_zed_settings;
+use crate::commands::DoctorArgs;
+use crate::config::mcp::McpConfig;
+use crate::types::{CanisterId, Network};
+use crate::utils::{client_detector, dfx};
+use crate::Cli;
+
+/// How long the bridge may take to answer `initialize`
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tool names clients accept, e.g. Claude's `^[a-zA-Z0-9_-]{1,64}$`
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// The outcome of one check, with a fix for anything that is not a pass
+#[derive(Debug)]
+struct Check {
+    name: String,
+    status: Status,
+    detail: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// An Icarus server found in an AI client's configuration
+#[derive(Debug, Clone, PartialEq)]
+struct ClientEntry {
+    client: String,
+    config_path: PathBuf,
+    name: String,
+    /// Command the client launches, for stdio servers
+    command: Option<String>,
+    args: Vec<String>,
+    env: HashMap<String, String>,
+    canister_id: Option<String>,
+    network: Option<String>,
+}
+
+impl ClientEntry {
+    fn label(&self) -> String {
+        format!("{} → {}", self.client, self.name)
+    }
+}
+
+pub(crate) async fn execute(args: DoctorArgs, cli: &Cli) -> Result<()> {
+    let target = match args.canister_id {
+        Some(ref id) => Some(resolve_target(id, &args.network).await),
+        None => None,
+    };
+    let network = target
+        .as_ref()
+        .map_or(args.network.as_str(), |(_, network)| network.as_str());
+
+    if !cli.quiet {
+        println!("{} Diagnosing MCP setup...\n", "→".bright_blue());
+    }
+
+    let mut checks = check_dfx().await;
+    if network == "local" {
+        checks.push(check_replica().await);
+    }
+    if let Some((ref canister_id, ref network)) = target {
+        checks.extend(check_canister(canister_id, network).await);
+    }
+
+    let (entries, config_checks) = load_client_entries().await;
+    checks.extend(config_checks);
+    let entries: Vec<ClientEntry> = entries
+        .into_iter()
+        .filter(|entry| {
+            target.as_ref().map_or(true, |(canister_id, _)| {
+                entry.canister_id.as_deref() == Some(canister_id.as_str())
+            })
+        })
+        .collect();
+    checks.extend(check_registrations(&entries, target.as_ref()).await);
+
+    for entry in &entries {
+        checks.extend(check_entry(entry).await);
+        if entry.command.is_some() && !args.skip_handshake {
+            checks.push(check_handshake(entry).await);
+        }
+    }
+
+    print_report(&checks, cli);
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    info!("Doctor finished with {} failed check(s)", failed);
+    if failed > 0 {
+        return Err(anyhow!("{} check(s) failed", failed));
+    }
+    Ok(())
+}
+
+async fn check_dfx() -> Vec<Check> {
+    if !dfx::is_dfx_available().await {
+        return vec![Check::fail(
+            "dfx",
+            "dfx is not installed or not on PATH",
+            "Install it with: sh -ci \"$(curl -fsSL https://internetcomputer.org/install.sh)\"",
+        )];
+    }
+
+    let version = dfx::get_dfx_version()
+        .await
+        .unwrap_or_else(|_| "unknown version".to_string());
+    let mut checks = vec![Check::pass("dfx", version)];
+
+    checks.push(match dfx::get_current_identity().await {
+        Ok(identity) if identity == "anonymous" => Check::warn(
+            "identity",
+            "dfx is using the anonymous identity, which auth-enabled canisters reject",
+            "Create and select an identity: dfx identity new <name> && dfx identity use <name>",
+        ),
+        Ok(identity) => {
+            let principal = dfx::get_principal().await.unwrap_or_default();
+            Check::pass("identity", format!("{identity} ({principal})"))
+        }
+        Err(e) => Check::fail(
+            "identity",
+            e.to_string(),
+            "Run `dfx identity whoami` to see what dfx reports",
+        ),
+    });
+
+    checks
+}
+
+async fn check_replica() -> Check {
+    let project_path = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    match dfx::is_replica_running(&project_path).await {
+        Ok(true) => Check::pass("local replica", "running"),
+        _ => Check::fail(
+            "local replica",
+            "not running",
+            "Start it with `dfx start --background`, or pass --network ic for mainnet canisters",
+        ),
+    }
+}
+
+async fn check_canister(canister_id: &str, network: &str) -> Vec<Check> {
+    let name = format!("canister {canister_id}");
+    let info = match fetch_server_info(canister_id, network).await {
+        Ok((service, version)) => Check::pass(
+            &name,
+            format!("reachable on {network}: {service} {version}"),
+        ),
+        Err(e) => {
+            let fix = if network == "local" {
+                "Deploy it with `icarus deploy` and check the ID with `dfx canister id <name>`"
+                    .to_string()
+            } else {
+                format!("Check the canister ID, and that it is deployed on {network} (--network)")
+            };
+            return vec![Check::fail(&name, format!("unreachable: {e}"), fix)];
+        }
+    };
+
+    let tools = match fetch_tools(canister_id, network).await {
+        Ok(tools) => tools,
+        Err(e) => {
+            return vec![
+                info,
+                Check::fail(
+                    "mcp_list_tools",
+                    e.to_string(),
+                    "Make sure the canister declares its tools with mcp!{} and is upgraded to the latest build",
+                ),
+            ];
+        }
+    };
+
+    let problems = tool_problems(&tools);
+    let tools_check = if tools.is_empty() {
+        Check::warn(
+            "mcp_list_tools",
+            "the canister exposes no tools",
+            "Annotate functions with #[tool] and make sure no tools are disabled",
+        )
+    } else if !problems.is_empty() {
+        Check::fail(
+            "mcp_list_tools",
+            problems.join("; "),
+            "Rename the tools and give them object input schemas, then redeploy",
+        )
+    } else {
+        let undocumented = tools.iter().filter(|t| t.description.is_none()).count();
+        if undocumented > 0 {
+            Check::warn(
+                "mcp_list_tools",
+                format!(
+                    "{undocumented} of {} tools have no description",
+                    tools.len()
+                ),
+                "Add doc comments to the #[tool] functions so models know when to use them",
+            )
+        } else {
+            Check::pass("mcp_list_tools", format!("{} valid tools", tools.len()))
+        }
+    };
+    vec![info, tools_check]
+}
+
+/// Returns what clients would reject in a tool list
+fn tool_problems(tools: &[Tool]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for tool in tools {
+        let name = tool.name.as_ref();
+        if name.is_empty() || name.len() > MAX_TOOL_NAME_LEN {
+            problems.push(format!(
+                "tool name '{name}' must be 1-{MAX_TOOL_NAME_LEN} characters"
+            ));
+        } else if !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            problems.push(format!(
+                "tool name '{name}' may only contain letters, digits, '_' and '-'"
+            ));
+        }
+        if !seen.insert(name) {
+            problems.push(format!("tool '{name}' is listed twice"));
+        }
+        if tool.input_schema.get("type").and_then(|t| t.as_str()) != Some("object") {
+            problems.push(format!("tool '{name}' has no object input schema"));
+        }
+    }
+    problems
+}
+
+/// Reads the Icarus servers out of every client configuration that exists
+async fn load_client_entries() -> (Vec<ClientEntry>, Vec<Check>) {
+    let mut entries = Vec::new();
+    let mut checks = Vec::new();
+
+    for (client, path) in client_detector::get_all_client_configs() {
+        let Ok(path) = path else { continue };
+        let Ok(content) = tokio::fs::read_to_string(&path).await else {
+            continue;
+        };
+
+        let parsed = if client == "zed" {
+            parse_zed_settings(&content)
+        } else {
+            serde_json::from_str(&content).map_err(Into::into)
+        };
+        match parsed {
+            Ok(config) => entries.extend(client_entries(&client, &path, &config)),
+            Err(e) => checks.push(Check::fail(
+                format!("{client} config"),
+                format!("{} is not valid JSON: {e}", path.display()),
+                "Fix the syntax error (clients silently ignore broken configs), or move the file aside and re-run `icarus mcp add`",
+            )),
+        }
+    }
+
+    (entries, checks)
+}
+
+/// Finds the Icarus servers in a client configuration: stdio servers under
+/// `mcpServers` or Zed's `context_servers`, and URL servers under `mcp`
+fn client_entries(client: &str, path: &Path, config: &serde_json::Value) -> Vec<ClientEntry> {
+    let string = |value: &serde_json::Value, key: &str| {
+        value.get(key).and_then(|v| v.as_str()).map(str::to_string)
+    };
+    let mut entries = Vec::new();
+
+    for key in ["mcpServers", "context_servers"] {
+        let Some(servers) = config.get(key).and_then(|s| s.as_object()) else {
+            continue;
+        };
+        for (name, server) in servers {
+            let command = string(server, "command");
+            let env: HashMap<String, String> = server
+                .get("env")
+                .and_then(|e| e.as_object())
+                .map(|env| {
+                    env.iter()
+                        .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let is_icarus = env.contains_key("ICARUS_CANISTER_ID")
+                || command.as_deref().is_some_and(|command| {
+                    Path::new(command)
+                        .file_stem()
+                        .is_some_and(|stem| stem == "icarus")
+                });
+            if !is_icarus {
+                continue;
+            }
+
+            entries.push(ClientEntry {
+                client: client.to_string(),
+                config_path: path.to_path_buf(),
+                name: name.clone(),
+                command,
+                args: server
+                    .get("args")
+                    .and_then(|a| a.as_array())
+                    .map(|args| {
+                        args.iter()
+                            .map(|a| a.as_str().map_or_else(|| a.to_string(), str::to_string))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                canister_id: env.get("ICARUS_CANISTER_ID").cloned(),
+                network: env.get("ICARUS_NETWORK").cloned(),
+                env,
+            });
+        }
+    }
+
+    // ChatGPT keeps an object and Continue an array of URL servers
+    let url_servers: Vec<(String, &serde_json::Value)> = match config.get("mcp") {
+        Some(serde_json::Value::Object(servers)) => {
+            servers.iter().map(|(k, v)| (k.clone(), v)).collect()
+        }
+        Some(serde_json::Value::Array(servers)) => servers
+            .iter()
+            .map(|v| (string(v, "name").unwrap_or_default(), v))
+            .collect(),
+        _ => Vec::new(),
+    };
+    for (name, server) in url_servers {
+        if server.get("canister_id").is_none() {
+            continue;
+        }
+        entries.push(ClientEntry {
+            client: client.to_string(),
+            config_path: path.to_path_buf(),
+            name,
+            command: None,
+            args: Vec::new(),
+            env: HashMap::new(),
+            canister_id: string(server, "canister_id"),
+            network: string(server, "network"),
+        });
+    }
+
+    entries
+}
+
+/// Returns the configuration mistakes in an entry, each with its fix
+fn entry_problems(entry: &ClientEntry) -> Vec<(String, String)> {
+    let reregister = format!(
+        "Re-register it: icarus mcp add <canister-id> --client {} --name {}",
+        entry.client, entry.name
+    );
+    let mut problems = Vec::new();
+
+    match entry.canister_id.as_deref() {
+        None => problems.push((
+            "no canister ID is configured".to_string(),
+            reregister.clone(),
+        )),
+        Some(id) => {
+            if let Err(e) = CanisterId::new(id) {
+                problems.push((e.to_string(), reregister.clone()));
+            }
+        }
+    }
+    if let Some(network) = entry.network.as_deref() {
+        if network.parse::<Network>().is_err() {
+            problems.push((
+                format!("unknown network '{network}'"),
+                "Set ICARUS_NETWORK to local, ic or testnet".to_string(),
+            ));
+        }
+    }
+    if entry.command.is_some()
+        && !entry
+            .args
+            .starts_with(&["mcp".to_string(), "start".to_string()])
+    {
+        problems.push((
+            format!("args {:?} do not start the bridge", entry.args),
+            "Set \"args\" to [\"mcp\", \"start\", ...] or re-register the server".to_string(),
+        ));
+    }
+
+    problems
+}
+
+/// Returns the value following `flag` in a command line
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+async fn check_entry(entry: &ClientEntry) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    if let Some(ref command) = entry.command {
+        let found = if Path::new(command).components().count() > 1 {
+            Path::new(command).is_file()
+        } else {
+            which::which(command).is_ok()
+        };
+        if !found {
+            let current = std::env::current_exe()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|_| "/path/to/icarus".to_string());
+            checks.push(Check::fail(
+                entry.label(),
+                format!("command `{command}` was not found"),
+                format!(
+                    "Clients do not load your shell's PATH; set \"command\" to \"{current}\" in {}",
+                    entry.config_path.display()
+                ),
+            ));
+        }
+
+        if let Some(identity) = flag_value(&entry.args, "--identity") {
+            let known = dfx::list_identities().await.unwrap_or_default();
+            if !known.iter().any(|i| i == identity) {
+                checks.push(Check::fail(
+                    entry.label(),
+                    format!("dfx identity '{identity}' does not exist"),
+                    format!("Create it with `dfx identity new {identity}` or re-register with another --identity"),
+                ));
+            }
+        }
+    }
+
+    for (problem, fix) in entry_problems(entry) {
+        checks.push(Check::fail(entry.label(), problem, fix));
+    }
+
+    if checks.is_empty() {
+        checks.push(Check::pass(
+            entry.label(),
+            format!("configured in {}", entry.config_path.display()),
+        ));
+    }
+    checks
+}
+
+/// Compares the servers registered with `icarus mcp add` against the
+/// client configurations
+async fn check_registrations(
+    entries: &[ClientEntry],
+    target: Option<&(String, String)>,
+) -> Vec<Check> {
+    let mcp_config = McpConfig::load().await.unwrap_or_default();
+    let known_clients: Vec<String> = client_detector::get_all_client_configs()
+        .into_iter()
+        .map(|(client, _)| client)
+        .collect();
+    let mut checks = Vec::new();
+
+    for server in &mcp_config.servers {
+        if target.is_some_and(|(canister_id, _)| server.canister_id != *canister_id) {
+            continue;
+        }
+        if !server.enabled || !known_clients.contains(&server.client) {
+            continue;
+        }
+        let configured = entries
+            .iter()
+            .any(|e| e.client == server.client && server.name == e.name);
+        if !configured {
+            checks.push(Check::warn(
+                format!("{} → {}", server.client, server.name),
+                format!("registered, but missing from the {} config", server.client),
+                format!(
+                    "Re-register it: icarus mcp add {} --client {} --name {}",
+                    server.canister_id, server.client, server.name
+                ),
+            ));
+        }
+    }
+
+    if entries.is_empty() && checks.is_empty() {
+        checks.push(Check::warn(
+            "client configs",
+            "no AI client is configured to use an Icarus canister",
+            "Register one: icarus mcp add <canister-id> --client claude-desktop",
+        ));
+    }
+    checks
+}
+
+/// Launches an entry's command the way its client does and sends the MCP
+/// `initialize` request over stdio
+async fn check_handshake(entry: &ClientEntry) -> Check {
+    let name = format!("{} handshake", entry.label());
+    let Some(ref command) = entry.command else {
+        return Check::pass(name, "not a stdio server");
+    };
+
+    let child = tokio::process::Command::new(command)
+        .args(&entry.args)
+        .envs(&entry.env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            return Check::fail(
+                name,
+                format!("could not launch `{command}`: {e}"),
+                "Fix the command path reported above",
+            );
+        }
+    };
+
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": "2025-06-18",
+            "capabilities": {},
+            "clientInfo": { "name": "icarus-doctor", "version": env!("CARGO_PKG_VERSION") }
+        }
+    });
+    let (Some(mut stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+        return Check::fail(name, "no stdio pipes", "Re-run icarus doctor");
+    };
+    let _ = stdin.write_all(format!("{request}\n").as_bytes()).await;
+    let _ = stdin.flush().await;
+
+    let mut line = String::new();
+    let read = tokio::time::timeout(
+        HANDSHAKE_TIMEOUT,
+        BufReader::new(stdout).read_line(&mut line),
+    )
+    .await;
+    let _ = child.kill().await;
+
+    let manual = format!("{command} {}", entry.args.join(" "));
+    match read {
+        Err(_) => Check::fail(
+            name,
+            format!(
+                "no reply to initialize within {}s",
+                HANDSHAKE_TIMEOUT.as_secs()
+            ),
+            format!("Run `{manual}` in a terminal and paste an initialize request to see where it stops"),
+        ),
+        Ok(Err(e)) => Check::fail(name, format!("reading stdout failed: {e}"), format!("Run `{manual}` in a terminal")),
+        Ok(Ok(0)) => Check::fail(
+            name,
+            "the bridge exited without replying",
+            format!("Run `{manual}` in a terminal to see its error"),
+        ),
+        Ok(Ok(_)) => match handshake_reply(&line) {
+            Ok(server) => Check::pass(name, format!("initialized {server}")),
+            Err(problem) if problem.starts_with("non-JSON") => Check::fail(
+                name,
+                problem,
+                "Clients read MCP messages from stdout; add \"--quiet\" to the args so nothing else is printed there",
+            ),
+            Err(problem) => Check::fail(name, problem, format!("Run `{manual}` in a terminal")),
+        },
+    }
+}
+
+/// Checks the first line the bridge printed, returning the server's name
+fn handshake_reply(line: &str) -> Result<String, String> {
+    let line = line.trim();
+    let Ok(reply) = serde_json::from_str::<serde_json::Value>(line) else {
+        let preview: String = line.chars().take(60).collect();
+        return Err(format!("non-JSON output on stdout: '{preview}'"));
+    };
+    if let Some(error) = reply.get("error") {
+        let message = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .map_or_else(|| error.to_string(), str::to_string);
+        return Err(format!("initialize failed: {message}"));
+    }
+    reply["result"]["serverInfo"]["name"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "the reply to initialize has no serverInfo".to_string())
+}
+
+fn print_report(checks: &[Check], cli: &Cli) {
+    for check in checks {
+        if cli.quiet && check.status == Status::Pass {
+            continue;
+        }
+        let mark = match check.status {
+            Status::Pass => "✅".to_string(),
+            Status::Warn => "⚠️ ".yellow().to_string(),
+            Status::Fail => "❌".red().to_string(),
+        };
+        println!(
+            "{} {} {}",
+            mark,
+            check.name.bright_white().bold(),
+            check.detail
+        );
+        if let Some(ref fix) = check.fix {
+            println!("   {} {}", "fix:".bright_cyan(), fix);
+        }
+    }
+
+    if !cli.quiet {
+        let count = |status| checks.iter().filter(|c| c.status == status).count();
+        println!(
+            "\n{} passed, {} warnings, {} failed",
+            count(Status::Pass).to_string().green(),
+            count(Status::Warn).to_string().yellow(),
+            count(Status::Fail).to_string().red()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn tool(name: &str, schema: serde_json::Value) -> Tool {
+        let serde_json::Value::Object(schema) = schema else {
+            unreachable!()
+        };
+        Tool::new(name.to_string(), "A tool", Arc::new(schema))
+    }
+
+    #[test]
+    fn test_tool_problems() {
+        let object = serde_json::json!({ "type": "object" });
+        assert!(tool_problems(&[tool("notes_add", object.clone())]).is_empty());
+
+        let problems = tool_problems(&[
+            tool("add note", object.clone()),
+            tool("dup", object.clone()),
+            tool("dup", object),
+            tool("bare", serde_json::json!({})),
+        ]);
+        assert_eq!(problems.len(), 3);
+        assert!(problems[0].contains("add note"));
+        assert!(problems[1].contains("listed twice"));
+        assert!(problems[2].contains("bare"));
+    }
+
+    #[test]
+    fn test_client_entries() {
+        let config = serde_json::json!({
+            "mcpServers": {
+                "notes": {
+                    "command": "/usr/local/bin/icarus",
+                    "args": ["mcp", "start", "--port", 3000],
+                    "env": { "ICARUS_CANISTER_ID": "rdmx6-jaaaa-aaaaa-aaadq-cai", "ICARUS_NETWORK": "ic" }
+                },
+                "filesystem": { "command": "npx", "args": ["fs-server"] }
+            },
+            "mcp": [{ "name": "calc", "url": "http://x", "canister_id": "bad" }]
+        });
+        let entries = client_entries("cursor", Path::new("mcp.json"), &config);
+        assert_eq!(entries.len(), 2);
+
+        let notes = &entries[0];
+        assert_eq!(notes.args, ["mcp", "start", "--port", "3000"]);
+        assert_eq!(notes.network.as_deref(), Some("ic"));
+        assert!(entry_problems(notes).is_empty());
+
+        let calc = &entries[1];
+        assert!(calc.command.is_none());
+        assert_eq!(entry_problems(calc).len(), 1);
+
+        let mut broken = notes.clone();
+        broken.args = vec!["start".to_string()];
+        broken.network = Some("main".to_string());
+        broken.canister_id = None;
+        assert_eq!(entry_problems(&broken).len(), 3);
+    }
+
+    #[test]
+    fn test_handshake_reply() {
+        let ok = r#"{"jsonrpc":"2.0","id":1,"result":{"serverInfo":{"name":"icarus-bridge"}}}"#;
+        assert_eq!(handshake_reply(ok).unwrap(), "icarus-bridge");
+        assert!(handshake_reply("██╗ ██████╗")
+            .unwrap_err()
+            .starts_with("non-JSON"));
+        assert!(handshake_reply(r#"{"error":{"message":"boom"}}"#)
+            .unwrap_err()
+            .contains("boom"));
+        assert!(handshake_reply("{}").is_err());
+        assert_eq!(
+            flag_value(&["--identity".to_string(), "ci".to_string()], "--identity"),
+            Some("ci")
+        );
+    }
+}
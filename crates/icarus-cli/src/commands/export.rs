@@ -0,0 +1,59 @@
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use icarus_core::tool_export::{self, ToolFormat};
+
+use crate::commands::generate::{fetch_tools, resolve_target};
+use crate::commands::{ExportArgs, ExportToolsArgs};
+use crate::Cli;
+
+pub(crate) async fn execute(args: ExportArgs, cli: &Cli) -> Result<()> {
+    match args {
+        ExportArgs::Tools(args) => export_tools(args, cli).await,
+    }
+}
+
+async fn export_tools(args: ExportToolsArgs, cli: &Cli) -> Result<()> {
+    let format: ToolFormat = args.format.parse().map_err(|e| anyhow!("{e}"))?;
+    let (canister_id, network) = resolve_target(&args.canister_id, &args.network).await;
+    let output = args
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("{format}-tools.json")));
+
+    if !cli.quiet {
+        println!(
+            "{}",
+            format!("  Fetching tools from {} on {}...", canister_id, network).bright_blue()
+        );
+    }
+    let tools = fetch_tools(&canister_id, &network).await?;
+    if tools.is_empty() {
+        warn!("Canister {} exposes no tools", canister_id);
+    }
+
+    let declarations = tool_export::export(&tools, format);
+    let json = serde_json::to_string_pretty(&declarations)?;
+    tokio::fs::write(&output, format!("{json}\n"))
+        .await
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+
+    if !cli.quiet {
+        println!(
+            "{} Wrote {} {} tool declaration(s) to {}",
+            "✅".green(),
+            tools.len(),
+            format,
+            output.display()
+        );
+    }
+
+    info!(
+        "Exported {} tools of {} to {}",
+        format,
+        canister_id,
+        output.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use icarus_core::Tool;
+
+use crate::commands::{GenerateArgs, OpenApiArgs, TsClientArgs};
+use crate::config::mcp::McpConfig;
+use crate::templates::ts_client;
+use crate::utils::dfx;
+use crate::Cli;
+
+pub(crate) async fn execute(args: GenerateArgs, cli: &Cli) -> Result<()> {
+    match args {
+        GenerateArgs::TsClient(args) => generate_ts_client(args, cli).await,
+        GenerateArgs::Openapi(args) => generate_openapi(args, cli).await,
+    }
+}
+
+async fn generate_ts_client(args: TsClientArgs, cli: &Cli) -> Result<()> {
+    let (canister_id, network) = resolve_target(&args.canister_id, &args.network).await;
+    let output_path = args
+        .output
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(&args.package_name));
+
+    if output_path.exists() && output_path.read_dir()?.next().is_some() {
+        if !cli.force {
+            return Err(anyhow!(
+                "Directory '{}' already exists and is not empty. Use --force to overwrite.",
+                output_path.display()
+            ));
+        }
+        warn!("Directory exists, will overwrite due to --force flag");
+    }
+
+    if !cli.quiet {
+        println!(
+            "{}",
+            format!("  Fetching tools from {} on {}...", canister_id, network).bright_blue()
+        );
+    }
+    let tools = fetch_tools(&canister_id, &network).await?;
+    if tools.is_empty() {
+        warn!("Canister {} exposes no tools", canister_id);
+    }
+
+    ts_client::generate_package(
+        &args.package_name,
+        &canister_id,
+        &network,
+        &tools,
+        &output_path,
+    )
+    .await
+    .with_context(|| "Failed to generate TypeScript client")?;
+
+    if !cli.quiet {
+        println!(
+            "{} Generated {} with {} tool(s) in {}",
+            "✅".green(),
+            args.package_name.bright_white().bold(),
+            tools.len(),
+            output_path.display()
+        );
+        println!("\n{}", "Next steps:".bright_white().bold());
+        println!("  cd {} && npm install", output_path.display());
+        println!(
+            "  import {{ IcarusClient }} from \"{}\";",
+            args.package_name
+        );
+    }
+
+    info!(
+        "Generated TypeScript client for {} at {}",
+        canister_id,
+        output_path.display()
+    );
+    Ok(())
+}
+
+async fn generate_openapi(args: OpenApiArgs, cli: &Cli) -> Result<()> {
+    let (canister_id, network) = resolve_target(&args.canister_id, &args.network).await;
+
+    if !cli.quiet {
+        println!(
+            "{}",
+            format!("  Fetching tools from {} on {}...", canister_id, network).bright_blue()
+        );
+    }
+    let tools = fetch_tools(&canister_id, &network).await?;
+    let (title, version) = fetch_server_info(&canister_id, &network).await?;
+
+    let document = icarus_core::openapi::document(&title, &version, &tools);
+    let json = serde_json::to_string_pretty(&document)?;
+    tokio::fs::write(&args.output, format!("{json}\n"))
+        .await
+        .with_context(|| format!("Failed to write {}", args.output.display()))?;
+
+    if !cli.quiet {
+        println!(
+            "{} Wrote OpenAPI document for {} tool(s) to {}",
+            "✅".green(),
+            tools.len(),
+            args.output.display()
+        );
+        println!(
+            "  Calls need the canister built with {} and an API key",
+            "mcp! { http_tools = true }".bright_white()
+        );
+    }
+
+    info!(
+        "Generated OpenAPI document for {} at {}",
+        canister_id,
+        args.output.display()
+    );
+    Ok(())
+}
+
+/// Resolves a registered server name to its canister and network
+pub(crate) async fn resolve_target(canister_id: &str, network: &str) -> (String, String) {
+    let mcp_config = McpConfig::load().await.unwrap_or_default();
+
+    mcp_config
+        .servers
+        .iter()
+        .find(|s| s.name == canister_id || s.canister_id == canister_id)
+        .map(|s| (s.canister_id.to_string(), s.network.to_string()))
+        .unwrap_or_else(|| (canister_id.to_string(), network.to_string()))
+}
+
+/// Returns the service name and version from `mcp_server_info`
+pub(crate) async fn fetch_server_info(
+    canister_id: &str,
+    network: &str,
+) -> Result<(String, String)> {
+    let reply = dfx::query_canister_raw(canister_id, "mcp_server_info", "()", network).await?;
+    let json: String =
+        candid::decode_one(&reply).context("Failed to decode mcp_server_info reply")?;
+
+    parse_server_info(&json)
+}
+
+/// Parses the name and version out of `mcp_server_info`
+fn parse_server_info(json: &str) -> Result<(String, String)> {
+    let info: serde_json::Value =
+        serde_json::from_str(json).context("Invalid server info returned by mcp_server_info")?;
+    let field = |name: &str| info[name].as_str().unwrap_or("unknown").to_string();
+    Ok((field("name"), field("version")))
+}
+
+pub(crate) async fn fetch_tools(canister_id: &str, network: &str) -> Result<Vec<Tool>> {
+    let reply = dfx::query_canister_raw(canister_id, "mcp_list_tools", "()", network).await?;
+    let json: String =
+        candid::decode_one(&reply).context("Failed to decode mcp_list_tools reply")?;
+
+    parse_tool_list(&json)
+}
+
+/// Parses the `{"tools": [...]}` document returned by `mcp_list_tools`
+fn parse_tool_list(json: &str) -> Result<Vec<Tool>> {
+    #[derive(serde::Deserialize)]
+    struct ToolList {
+        tools: Vec<Tool>,
+    }
+
+    let list: ToolList =
+        serde_json::from_str(json).context("Invalid tool list returned by mcp_list_tools")?;
+    Ok(list.tools)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tool_list() {
+        let json =
+            r#"{"tools":[{"name":"add","description":"Adds","inputSchema":{"type":"object"}}]}"#;
+        let tools = parse_tool_list(json).unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "add");
+
+        assert!(parse_tool_list("[]").is_err());
+    }
+
+    #[test]
+    fn test_parse_server_info() {
+        let json = r#"{"name":"notes","version":"1.2.0","tool_prefix":""}"#;
+        assert_eq!(
+            parse_server_info(json).unwrap(),
+            ("notes".to_string(), "1.2.0".to_string())
+        );
+        assert_eq!(parse_server_info("{}").unwrap().0, "unknown");
+        assert!(parse_server_info("not json").is_err());
+    }
+}
@@ -6,7 +6,10 @@ use std::path::PathBuf;
 use tracing::{info, warn};
 
 use crate::config::mcp::{McpConfig, McpServerConfig};
+use crate::config::profiles::IdentityProfiles;
+use crate::config::tool_snapshot::ToolSnapshot;
 use crate::utils::client_detector;
+use crate::utils::rmcp_bridge::{BridgeConfig, IcarusBridge};
 use crate::{commands::mcp::AddArgs, Cli};
 
 pub(crate) async fn execute(args: AddArgs, cli: &Cli) -> Result<()> {
@@ -32,6 +35,13 @@ pub(crate) async fn execute(args: AddArgs, cli: &Cli) -> Result<()> {
             "Network:".bright_white(),
             args.network.bright_cyan()
         );
+        if let Some(identity) = &args.identity {
+            println!(
+                "  {} {}",
+                "Identity:".bright_white(),
+                identity.bright_cyan()
+            );
+        }
     }
 
     // Validate canister ID format
@@ -73,11 +83,40 @@ pub(crate) async fn execute(args: AddArgs, cli: &Cli) -> Result<()> {
     mcp_config.add_server(server_config.clone())?;
 
     // Register with AI client
-    register_with_client(&server_config, &client_config, &args.client).await?;
+    register_with_client(
+        &server_config,
+        &client_config,
+        &args.client,
+        args.identity.as_deref(),
+    )
+    .await?;
 
     // Save updated configuration
     mcp_config.save().await?;
 
+    // Remember which identity the bridge uses for this canister
+    if let Some(identity) = &args.identity {
+        let mut profiles = IdentityProfiles::load().await.unwrap_or_default();
+        profiles.set(&args.canister_id, &args.network, identity);
+        profiles.save().await?;
+    }
+
+    // A missing snapshot only makes the bridge's first listing slower
+    if !args.skip_verify && !args.no_snapshot {
+        match snapshot_tools(&args).await {
+            Ok(count) => {
+                if !cli.quiet {
+                    println!(
+                        "  {} Saved {} tools for the bridge to start from",
+                        "✓".green(),
+                        count
+                    );
+                }
+            }
+            Err(e) => warn!("Could not save a snapshot of the canister's tools: {}", e),
+        }
+    }
+
     if !cli.quiet {
         print_success_message(&server_config, &client_config);
     }
@@ -150,6 +189,21 @@ async fn detect_client_config(args: &AddArgs, cli: &Cli) -> Result<ClientConfig>
                 install_path: None, // VS Code extension
             })
         }
+        crate::commands::mcp::McpClient::Cursor => Ok(ClientConfig {
+            name: client_name,
+            config_path: client_detector::get_cursor_config_path()?,
+            install_path: None,
+        }),
+        crate::commands::mcp::McpClient::Windsurf => Ok(ClientConfig {
+            name: client_name,
+            config_path: client_detector::get_windsurf_config_path()?,
+            install_path: None,
+        }),
+        crate::commands::mcp::McpClient::Zed => Ok(ClientConfig {
+            name: client_name,
+            config_path: client_detector::get_zed_config_path()?,
+            install_path: None,
+        }),
         crate::commands::mcp::McpClient::Custom => {
             if !cli.quiet {
                 warn!("Custom client selected. Manual configuration required.");
@@ -163,6 +217,25 @@ async fn detect_client_config(args: &AddArgs, cli: &Cli) -> Result<ClientConfig>
     }
 }
 
+/// Lists the canister's tools through the bridge, which saves them to the
+/// canister's snapshot file, and returns how many there are.
+async fn snapshot_tools(args: &AddArgs) -> Result<usize> {
+    let config = BridgeConfig {
+        canister_id: args.canister_id.clone(),
+        network: args.network.clone(),
+        identity: args.identity.clone(),
+        tool_snapshot: Some(ToolSnapshot::snapshot_path(
+            &args.canister_id,
+            &args.network,
+        )?),
+        ..BridgeConfig::default()
+    };
+    let tools = IcarusBridge::new(config, McpConfig::default())
+        .list_canister_tools()
+        .await?;
+    Ok(tools.len())
+}
+
 async fn verify_canister_accessibility(args: &AddArgs) -> Result<()> {
     // Construct canister URL based on network
     let base_url = match args.network.as_str() {
@@ -225,13 +298,14 @@ async fn register_with_client(
     server_config: &McpServerConfig,
     client_config: &ClientConfig,
     client_type: &crate::commands::mcp::McpClient,
+    identity: Option<&str>,
 ) -> Result<()> {
     match client_type {
         crate::commands::mcp::McpClient::ClaudeDesktop => {
-            register_claude_desktop(server_config, client_config).await
+            register_claude_desktop(server_config, client_config, identity).await
         }
         crate::commands::mcp::McpClient::ClaudeCode => {
-            register_claude_code(server_config, client_config).await
+            register_claude_code(server_config, client_config, identity).await
         }
         crate::commands::mcp::McpClient::ChatgptDesktop => {
             register_chatgpt_desktop(server_config, client_config).await
@@ -239,6 +313,13 @@ async fn register_with_client(
         crate::commands::mcp::McpClient::Continue => {
             register_continue(server_config, client_config).await
         }
+        crate::commands::mcp::McpClient::Cursor | crate::commands::mcp::McpClient::Windsurf => {
+            // Same `mcpServers` format as Claude Desktop
+            register_claude_desktop(server_config, client_config, identity).await
+        }
+        crate::commands::mcp::McpClient::Zed => {
+            register_zed(server_config, client_config, identity).await
+        }
         crate::commands::mcp::McpClient::Custom => {
             // Custom clients require manual configuration
             Ok(())
@@ -249,6 +330,7 @@ async fn register_with_client(
 async fn register_claude_desktop(
     server_config: &McpServerConfig,
     client_config: &ClientConfig,
+    identity: Option<&str>,
 ) -> Result<()> {
     use tokio::fs;
 
@@ -269,7 +351,7 @@ async fn register_claude_desktop(
     // Add our server configuration
     config["mcpServers"][server_config.name.as_str()] = serde_json::json!({
         "command": "icarus",
-        "args": ["mcp", "start", "--port", server_config.port.unwrap_or(3000)],
+        "args": bridge_args(server_config, identity),
         "env": {
             "ICARUS_CANISTER_ID": server_config.canister_id.as_str(),
             "ICARUS_NETWORK": server_config.network.as_str()
@@ -277,18 +359,133 @@ async fn register_claude_desktop(
     });
 
     // Write updated configuration
+    if let Some(parent) = client_config.config_path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let updated_config = serde_json::to_string_pretty(&config)?;
+    fs::write(&client_config.config_path, updated_config).await?;
+
+    Ok(())
+}
+
+/// Adds the server to Zed's `context_servers` setting.
+///
+/// Zed's settings.json allows comments and trailing commas, which are lost
+/// when the file is rewritten.
+async fn register_zed(
+    server_config: &McpServerConfig,
+    client_config: &ClientConfig,
+    identity: Option<&str>,
+) -> Result<()> {
+    use tokio::fs;
+
+    let config_content = if client_config.config_path.exists() {
+        fs::read_to_string(&client_config.config_path).await?
+    } else {
+        "{}".to_string()
+    };
+
+    let mut config = parse_zed_settings(&config_content).with_context(|| {
+        format!(
+            "Failed to parse Zed settings at {}",
+            client_config.config_path.display()
+        )
+    })?;
+
+    if config.get("context_servers").is_none() {
+        config["context_servers"] = serde_json::json!({});
+    }
+
+    config["context_servers"][server_config.name.as_str()] = serde_json::json!({
+        "source": "custom",
+        "command": "icarus",
+        "args": bridge_args(server_config, identity),
+        "env": {
+            "ICARUS_CANISTER_ID": server_config.canister_id.as_str(),
+            "ICARUS_NETWORK": server_config.network.as_str()
+        }
+    });
+
+    if let Some(parent) = client_config.config_path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
     let updated_config = serde_json::to_string_pretty(&config)?;
     fs::write(&client_config.config_path, updated_config).await?;
 
     Ok(())
 }
 
+/// Parses Zed's settings, which may contain comments and trailing commas.
+pub(crate) fn parse_zed_settings(content: &str) -> Result<Value> {
+    if content.trim().is_empty() {
+        return Ok(serde_json::json!({}));
+    }
+
+    let mut json = String::with_capacity(content.len());
+    let mut chars = content.char_indices().peekable();
+    let mut in_string = false;
+    while let Some((i, c)) = chars.next() {
+        if in_string {
+            json.push(c);
+            match c {
+                '\\' => json.extend(chars.next().map(|(_, c)| c)),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                json.push(c);
+            }
+            '/' if matches!(chars.peek(), Some((_, '/' | '*'))) => {
+                let rest = skip_comments(&content[i..]);
+                let skipped = content.len() - i - rest.len();
+                chars.nth(skipped - 2);
+                json.push(' ');
+            }
+            // Trailing commas before a closing bracket
+            ',' if skip_comments(&content[i + 1..]).starts_with(['}', ']']) => {}
+            _ => json.push(c),
+        }
+    }
+
+    serde_json::from_str(&json).map_err(Into::into)
+}
+
+/// Skips leading whitespace and comments.
+fn skip_comments(mut rest: &str) -> &str {
+    loop {
+        rest = rest.trim_start();
+        if let Some(comment) = rest.strip_prefix("//") {
+            rest = comment.find('\n').map_or("", |end| &comment[end..]);
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.find("*/").map_or("", |end| &comment[end + 2..]);
+        } else {
+            return rest;
+        }
+    }
+}
+
 async fn register_claude_code(
     server_config: &McpServerConfig,
     client_config: &ClientConfig,
+    identity: Option<&str>,
 ) -> Result<()> {
     // Similar to Claude Desktop but with different configuration format
-    register_claude_desktop(server_config, client_config).await
+    register_claude_desktop(server_config, client_config, identity).await
+}
+
+/// Arguments the client launches `icarus` with to start the bridge.
+fn bridge_args(server_config: &McpServerConfig, identity: Option<&str>) -> Value {
+    let mut args =
+        serde_json::json!(["mcp", "start", "--port", server_config.port.unwrap_or(3000)]);
+    if let (Some(identity), Some(args)) = (identity, args.as_array_mut()) {
+        args.push("--identity".into());
+        args.push(identity.into());
+    }
+    args
 }
 
 async fn register_chatgpt_desktop(
@@ -440,6 +637,8 @@ mod tests {
             network: "local".to_string(),
             name: Some("test-server".to_string()),
             skip_verify: false,
+            identity: None,
+            no_snapshot: false,
         };
 
         let client_config = ClientConfig {
@@ -455,4 +654,111 @@ mod tests {
         assert_eq!(server_config.network, "local");
         assert_eq!(server_config.port, Some(3000));
     }
+
+    #[test]
+    fn test_bridge_args_with_identity() {
+        let args = AddArgs {
+            canister_id: "rdmx6-jaaaa-aaaaa-aaadq-cai".to_string(),
+            client: crate::commands::mcp::McpClient::ClaudeCode,
+            client_name: None,
+            port: Some(3100),
+            network: "ic".to_string(),
+            name: None,
+            skip_verify: true,
+            identity: Some("alice".to_string()),
+            no_snapshot: true,
+        };
+        let client_config = ClientConfig {
+            name: "claude-code".to_string(),
+            config_path: PathBuf::from("/tmp/config.json"),
+            install_path: None,
+        };
+        let server_config = create_server_config(&args, &client_config).unwrap();
+
+        assert_eq!(
+            bridge_args(&server_config, args.identity.as_deref()),
+            serde_json::json!(["mcp", "start", "--port", 3100, "--identity", "alice"])
+        );
+        assert_eq!(
+            bridge_args(&server_config, None),
+            serde_json::json!(["mcp", "start", "--port", 3100])
+        );
+    }
+
+    #[test]
+    fn test_parse_zed_settings_with_comments() {
+        let settings = r#"// Zed settings
+        {
+            "theme": "One Dark", // inline
+            /* block */
+            "url": "http://example.com//path",
+            "languages": { "Rust": { "tab_size": 4, }, },
+        }"#;
+        let parsed = parse_zed_settings(settings).unwrap();
+        assert_eq!(parsed["theme"], "One Dark");
+        assert_eq!(parsed["url"], "http://example.com//path");
+        assert_eq!(parsed["languages"]["Rust"]["tab_size"], 4);
+
+        assert_eq!(parse_zed_settings("").unwrap(), serde_json::json!({}));
+        assert!(parse_zed_settings("{ not json").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_register_editors() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let args = AddArgs {
+            canister_id: "rdmx6-jaaaa-aaaaa-aaadq-cai".to_string(),
+            client: crate::commands::mcp::McpClient::Zed,
+            client_name: None,
+            port: Some(3000),
+            network: "local".to_string(),
+            name: Some("notes".to_string()),
+            skip_verify: true,
+            identity: None,
+            no_snapshot: true,
+        };
+
+        let zed = ClientConfig {
+            name: "zed".to_string(),
+            config_path: temp_dir.path().join("zed").join("settings.json"),
+            install_path: None,
+        };
+        std::fs::create_dir_all(temp_dir.path().join("zed")).unwrap();
+        std::fs::write(
+            &zed.config_path,
+            "// Zed settings\n{ \"theme\": \"One Dark\", }",
+        )
+        .unwrap();
+        let server_config = create_server_config(&args, &zed).unwrap();
+        register_with_client(&server_config, &zed, &args.client, None)
+            .await
+            .unwrap();
+
+        let settings: Value =
+            serde_json::from_str(&std::fs::read_to_string(&zed.config_path).unwrap()).unwrap();
+        assert_eq!(settings["theme"], "One Dark");
+        assert_eq!(settings["context_servers"]["notes"]["source"], "custom");
+        assert_eq!(settings["context_servers"]["notes"]["command"], "icarus");
+
+        // Cursor's directory does not exist yet
+        let cursor = ClientConfig {
+            name: "cursor".to_string(),
+            config_path: temp_dir.path().join(".cursor").join("mcp.json"),
+            install_path: None,
+        };
+        register_with_client(
+            &server_config,
+            &cursor,
+            &crate::commands::mcp::McpClient::Cursor,
+            None,
+        )
+        .await
+        .unwrap();
+        let config: Value =
+            serde_json::from_str(&std::fs::read_to_string(&cursor.config_path).unwrap()).unwrap();
+        assert_eq!(
+            config["mcpServers"]["notes"]["env"]["ICARUS_CANISTER_ID"],
+            "rdmx6-jaaaa-aaaaa-aaadq-cai"
+        );
+    }
 }
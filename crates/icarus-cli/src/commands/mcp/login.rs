@@ -0,0 +1,43 @@
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use std::time::Duration;
+use tracing::info;
+
+use crate::utils::delegation::{self, DelegationStore};
+use crate::{commands::mcp::LoginArgs, Cli};
+
+pub(crate) async fn execute(args: LoginArgs, cli: &Cli) -> Result<()> {
+    let store = DelegationStore::default_location()?;
+
+    if args.logout {
+        store.clear().await?;
+        if !cli.quiet {
+            println!("{} Internet Identity delegation removed", "✅".green());
+        }
+        return Ok(());
+    }
+
+    if args.ttl_hours == 0 {
+        return Err(anyhow!("--ttl-hours must be at least 1"));
+    }
+
+    info!("Logging in with {}", args.identity_provider);
+    let ttl = Duration::from_secs(args.ttl_hours * 60 * 60);
+    let delegation = delegation::login(&args.identity_provider, ttl).await?;
+    let principal = delegation.principal()?;
+    store.save(&delegation).await?;
+
+    if !cli.quiet {
+        println!(
+            "{} Logged in as {}",
+            "✅".green(),
+            principal.to_text().bright_cyan()
+        );
+        println!(
+            "  {} The bridge signs canister calls with this identity until it expires",
+            "→".bright_blue()
+        );
+    }
+
+    Ok(())
+}
@@ -2,7 +2,9 @@ use clap::{Args, Subcommand};
 
 pub(crate) mod add;
 pub(crate) mod list;
+pub(crate) mod login;
 pub(crate) mod remove;
+pub(crate) mod repl;
 pub(crate) mod start;
 pub(crate) mod status;
 pub(crate) mod stop;
@@ -25,6 +27,10 @@ pub(crate) enum McpCommand {
     Start(StartArgs),
     /// Stop MCP bridge server
     Stop(StopArgs),
+    /// Interactively list and call a canister's tools
+    Repl(ReplArgs),
+    /// Log in with Internet Identity for bridging without dfx
+    Login(LoginArgs),
 }
 
 /// Arguments for the `mcp add` command
@@ -56,6 +62,14 @@ pub struct AddArgs {
     /// Skip verification of canister accessibility
     #[arg(long)]
     pub skip_verify: bool,
+
+    /// dfx identity the bridge uses for this canister (recorded in the profiles file)
+    #[arg(long)]
+    pub identity: Option<String>,
+
+    /// Don't save the canister's tool list for the bridge to start from
+    #[arg(long)]
+    pub no_snapshot: bool,
 }
 
 /// Arguments for the `mcp list` command
@@ -126,6 +140,52 @@ pub struct StartArgs {
     /// Configuration file path
     #[arg(short, long)]
     pub config: Option<std::path::PathBuf>,
+
+    /// Record every request/response pair to a JSON Lines file
+    #[arg(long)]
+    pub record: Option<std::path::PathBuf>,
+
+    /// dfx identity to call canisters with (defaults to each canister's profile)
+    #[arg(long)]
+    pub identity: Option<String>,
+
+    /// Restart the bridge when it exits with an error or stops accepting connections
+    #[arg(long)]
+    pub supervise: bool,
+
+    /// Restarts allowed within ten minutes before the supervisor gives up
+    #[arg(long, default_value = "5")]
+    pub max_restarts: u32,
+    /// Serve only this registered canister, as its own daemon instance
+    #[arg(long)]
+    pub canister_id: Option<String>,
+
+    /// Directory for daemon logs (defaults to the Icarus config directory)
+    #[arg(long)]
+    pub log_dir: Option<std::path::PathBuf>,
+    /// Log every JSON-RPC exchange, with secrets redacted, to a JSON Lines file
+    #[arg(long)]
+    pub log_rpc: Option<std::path::PathBuf>,
+
+    /// JSONPath of a field to redact from the RPC log (repeatable)
+    #[arg(long = "log-rpc-redact", value_name = "JSONPATH")]
+    pub log_rpc_redact: Vec<String>,
+
+    /// Fraction of successful exchanges to log, from 0 to 1 (errors are always logged)
+    #[arg(long, default_value = "1.0")]
+    pub log_rpc_sample: f64,
+
+    /// Requests and responses larger than this are logged truncated
+    #[arg(long, default_value = "65536")]
+    pub log_rpc_max_bytes: usize,
+
+    /// Results of read-only tools kept for repeated calls (0 disables the cache)
+    #[arg(long, default_value = "0")]
+    pub cache_size: usize,
+
+    /// Seconds a cached result is kept, for tools that declare no TTL of their own
+    #[arg(long, default_value = "60")]
+    pub cache_ttl: u64,
 }
 
 /// Arguments for the `mcp stop` command
@@ -138,6 +198,36 @@ pub struct StopArgs {
     /// Stop all MCP processes
     #[arg(long)]
     pub all: bool,
+    /// Stop the daemon serving this canister
+    #[arg(long, conflicts_with = "all")]
+    pub canister_id: Option<String>,
+}
+
+/// Arguments for the `mcp repl` command
+#[derive(Args, Clone)]
+pub struct ReplArgs {
+    /// Canister ID or registered server name
+    pub identifier: String,
+
+    /// Network the canister is deployed on (ignored for registered servers)
+    #[arg(short, long, default_value = "local")]
+    pub network: String,
+}
+
+/// Arguments for the `mcp login` command
+#[derive(Args, Clone)]
+pub struct LoginArgs {
+    /// Internet Identity provider URL
+    #[arg(long, default_value = crate::utils::delegation::II_URL)]
+    pub identity_provider: String,
+
+    /// How long the delegation stays valid, in hours
+    #[arg(long, default_value = "8")]
+    pub ttl_hours: u64,
+
+    /// Delete the stored delegation instead of logging in
+    #[arg(long)]
+    pub logout: bool,
 }
 
 /// Supported AI clients
@@ -151,6 +241,12 @@ pub enum McpClient {
     ChatgptDesktop,
     /// Continue VS Code extension
     Continue,
+    /// Cursor editor
+    Cursor,
+    /// Windsurf editor
+    Windsurf,
+    /// Zed editor
+    Zed,
     /// Custom client configuration
     Custom,
 }
@@ -162,6 +258,9 @@ impl std::fmt::Display for McpClient {
             McpClient::ClaudeCode => write!(f, "claude-code"),
             McpClient::ChatgptDesktop => write!(f, "chatgpt-desktop"),
             McpClient::Continue => write!(f, "continue"),
+            McpClient::Cursor => write!(f, "cursor"),
+            McpClient::Windsurf => write!(f, "windsurf"),
+            McpClient::Zed => write!(f, "zed"),
             McpClient::Custom => write!(f, "custom"),
         }
     }
@@ -188,6 +287,8 @@ pub(crate) async fn execute(mcp_args: crate::commands::McpArgs, cli: &Cli) -> Re
         crate::commands::McpArgs::Status(args) => status::execute(args, cli).await,
         crate::commands::McpArgs::Start(args) => start::execute(args, cli).await,
         crate::commands::McpArgs::Stop(args) => stop::execute(args, cli).await,
+        crate::commands::McpArgs::Repl(args) => repl::execute(args, cli).await,
+        crate::commands::McpArgs::Login(args) => login::execute(args, cli).await,
     }
 }
 
@@ -201,6 +302,9 @@ mod tests {
         assert_eq!(McpClient::ClaudeCode.to_string(), "claude-code");
         assert_eq!(McpClient::ChatgptDesktop.to_string(), "chatgpt-desktop");
         assert_eq!(McpClient::Continue.to_string(), "continue");
+        assert_eq!(McpClient::Cursor.to_string(), "cursor");
+        assert_eq!(McpClient::Windsurf.to_string(), "windsurf");
+        assert_eq!(McpClient::Zed.to_string(), "zed");
         assert_eq!(McpClient::Custom.to_string(), "custom");
     }
 }
@@ -103,6 +103,9 @@ async fn remove_from_client(
             remove_from_chatgpt_desktop(server).await
         }
         crate::commands::mcp::McpClient::Continue => remove_from_continue(server).await,
+        crate::commands::mcp::McpClient::Cursor => remove_from_cursor(server).await,
+        crate::commands::mcp::McpClient::Windsurf => remove_from_windsurf(server).await,
+        crate::commands::mcp::McpClient::Zed => remove_from_zed(server).await,
         crate::commands::mcp::McpClient::Custom => {
             // Custom clients require manual configuration
             Ok(())
@@ -116,14 +119,61 @@ async fn remove_from_all_clients(server: &crate::config::mcp::McpServerConfig) -
     let _ = remove_from_claude_code(server).await;
     let _ = remove_from_chatgpt_desktop(server).await;
     let _ = remove_from_continue(server).await;
+    let _ = remove_from_cursor(server).await;
+    let _ = remove_from_windsurf(server).await;
+    let _ = remove_from_zed(server).await;
     Ok(())
 }
 
 async fn remove_from_claude_desktop(server: &crate::config::mcp::McpServerConfig) -> Result<()> {
-    use crate::utils::client_detector;
+    let config_path = crate::utils::client_detector::get_claude_desktop_config_path()?;
+    remove_from_mcp_servers(&config_path, server).await
+}
+
+async fn remove_from_cursor(server: &crate::config::mcp::McpServerConfig) -> Result<()> {
+    let config_path = crate::utils::client_detector::get_cursor_config_path()?;
+    remove_from_mcp_servers(&config_path, server).await
+}
+
+async fn remove_from_windsurf(server: &crate::config::mcp::McpServerConfig) -> Result<()> {
+    let config_path = crate::utils::client_detector::get_windsurf_config_path()?;
+    remove_from_mcp_servers(&config_path, server).await
+}
+
+async fn remove_from_zed(server: &crate::config::mcp::McpServerConfig) -> Result<()> {
+    use tokio::fs;
+
+    let config_path = crate::utils::client_detector::get_zed_config_path()?;
+    if !config_path.exists() {
+        return Ok(());
+    }
+
+    let config_content = fs::read_to_string(&config_path).await?;
+    let mut config = super::add::parse_zed_settings(&config_content)?;
+
+    // Remove server from context_servers
+    let removed = config
+        .get_mut("context_servers")
+        .and_then(|servers| servers.as_object_mut())
+        .is_some_and(|obj| obj.remove(server.name.as_str()).is_some());
+    if !removed {
+        return Ok(()); // Leave the settings file and its comments untouched
+    }
+
+    let updated_config = serde_json::to_string_pretty(&config)?;
+    fs::write(&config_path, updated_config).await?;
+
+    Ok(())
+}
+
+/// Removes a server from a config file with a Claude Desktop style
+/// `mcpServers` object.
+async fn remove_from_mcp_servers(
+    config_path: &std::path::Path,
+    server: &crate::config::mcp::McpServerConfig,
+) -> Result<()> {
     use tokio::fs;
 
-    let config_path = client_detector::get_claude_desktop_config_path()?;
     if !config_path.exists() {
         return Ok(()); // No config file, nothing to remove
     }
@@ -140,7 +190,7 @@ async fn remove_from_claude_desktop(server: &crate::config::mcp::McpServerConfig
 
     // Write updated configuration
     let updated_config = serde_json::to_string_pretty(&config)?;
-    fs::write(&config_path, updated_config).await?;
+    fs::write(config_path, updated_config).await?;
 
     Ok(())
 }
@@ -256,4 +306,42 @@ mod tests {
         // Should be identifiable by canister ID
         assert_eq!(server.canister_id, "rdmx6-jaaaa-aaaaa-aaadq-cai");
     }
+
+    #[tokio::test]
+    async fn test_remove_from_mcp_servers() {
+        use crate::types::{CanisterId, Network, ServerName};
+
+        let server = McpServerConfig {
+            name: ServerName::new("notes").unwrap(),
+            canister_id: CanisterId::new("rdmx6-jaaaa-aaaaa-aaadq-cai").unwrap(),
+            network: Network::Local,
+            url: "http://localhost:3000/mcp".to_string(),
+            client: "cursor".to_string(),
+            port: Some(3000),
+            enabled: true,
+            created_at: Utc::now(),
+            last_updated: Utc::now(),
+        };
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("mcp.json");
+        std::fs::write(
+            &config_path,
+            r#"{"mcpServers": {"notes": {"command": "icarus"}, "other": {"command": "x"}}}"#,
+        )
+        .unwrap();
+
+        remove_from_mcp_servers(&config_path, &server)
+            .await
+            .unwrap();
+        let config: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+        assert!(config["mcpServers"].get("notes").is_none());
+        assert!(config["mcpServers"].get("other").is_some());
+
+        // Missing files are left alone
+        remove_from_mcp_servers(&temp_dir.path().join("missing.json"), &server)
+            .await
+            .unwrap();
+    }
 }
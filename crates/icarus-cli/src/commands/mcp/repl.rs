@@ -0,0 +1,408 @@
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, BasicHistory, Completion, Input};
+use serde_json::Value;
+use std::time::Instant;
+use tracing::{debug, info};
+
+use icarus_core::Tool;
+
+use crate::config::mcp::McpConfig;
+use crate::utils::dfx;
+use crate::{commands::mcp::ReplArgs, Cli};
+
+/// Commands understood by the prompt, offered for completion
+const COMMANDS: [&str; 7] = ["call", "describe", "tools", "info", "help", "exit", "quit"];
+
+/// Commands whose first argument is a tool name
+const TOOL_COMMANDS: [&str; 2] = ["call", "describe"];
+
+#[derive(Debug, PartialEq)]
+enum ReplCommand {
+    Empty,
+    Tools,
+    Describe(String),
+    Call { tool: String, arguments: Value },
+    Info,
+    Help,
+    Exit,
+}
+
+pub(crate) async fn execute(args: ReplArgs, cli: &Cli) -> Result<()> {
+    let (canister_id, network) = resolve_target(&args).await;
+    let mut session = Session {
+        canister_id,
+        network,
+        tools: Vec::new(),
+        next_id: 0,
+    };
+    session.refresh_tools().await?;
+
+    if !cli.quiet {
+        println!(
+            "{} Connected to {} on {} ({} tools). Type {} for commands.",
+            "→".bright_blue(),
+            session.canister_id.bright_cyan(),
+            session.network,
+            session.tools.len(),
+            "help".bold()
+        );
+    }
+
+    let theme = ColorfulTheme::default();
+    let mut history = BasicHistory::new().max_entries(200).no_duplicates(true);
+
+    loop {
+        let completion = ToolCompletion {
+            tool_names: session.tool_names(),
+        };
+        let line = match Input::<String>::with_theme(&theme)
+            .with_prompt("mcp")
+            .allow_empty(true)
+            .history_with(&mut history)
+            .completion_with(&completion)
+            .interact_text()
+        {
+            Ok(line) => line,
+            // Ctrl-C, Ctrl-D or no terminal
+            Err(e) => {
+                debug!("Prompt closed: {}", e);
+                break;
+            }
+        };
+
+        let command = match parse_command(&line) {
+            Ok(command) => command,
+            Err(e) => {
+                println!("{}", e.to_string().red());
+                continue;
+            }
+        };
+
+        let result = match command {
+            ReplCommand::Empty => Ok(()),
+            ReplCommand::Exit => break,
+            ReplCommand::Help => {
+                print_help();
+                Ok(())
+            }
+            ReplCommand::Tools => session.list_tools().await,
+            ReplCommand::Describe(tool) => session.describe(&tool),
+            ReplCommand::Info => session.info().await,
+            ReplCommand::Call { tool, arguments } => session.call(&tool, arguments).await,
+        };
+
+        if let Err(e) = result {
+            println!("{} {:#}", "error:".red().bold(), e);
+        }
+    }
+
+    info!("REPL session for {} ended", session.canister_id);
+    Ok(())
+}
+
+/// Resolves a registered server name to its canister and network
+async fn resolve_target(args: &ReplArgs) -> (String, String) {
+    let mcp_config = McpConfig::load().await.unwrap_or_default();
+
+    mcp_config
+        .servers
+        .iter()
+        .find(|s| s.name == args.identifier || s.canister_id == args.identifier)
+        .map(|s| (s.canister_id.to_string(), s.network.to_string()))
+        .unwrap_or_else(|| (args.identifier.clone(), args.network.clone()))
+}
+
+struct Session {
+    canister_id: String,
+    network: String,
+    tools: Vec<Tool>,
+    next_id: u64,
+}
+
+impl Session {
+    fn tool_names(&self) -> Vec<String> {
+        self.tools
+            .iter()
+            .map(|tool| tool.name.to_string())
+            .collect()
+    }
+
+    /// Calls a method that takes no arguments and returns a string
+    async fn query_string(&self, method: &str) -> Result<String> {
+        let args = candid::Encode!().context("Failed to encode arguments")?;
+        let reply =
+            dfx::call_canister_raw(&self.canister_id, method, &args, &self.network, true).await?;
+        candid::decode_one(&reply).with_context(|| format!("Failed to decode {} reply", method))
+    }
+
+    async fn refresh_tools(&mut self) -> Result<()> {
+        let json = self.query_string("mcp_list_tools").await?;
+        let list: Value = serde_json::from_str(&json).context("Invalid mcp_list_tools reply")?;
+        self.tools = serde_json::from_value(list.get("tools").cloned().unwrap_or_default())
+            .context("Invalid tool list in mcp_list_tools reply")?;
+        Ok(())
+    }
+
+    async fn list_tools(&mut self) -> Result<()> {
+        // The canister may have been redeployed since the last listing
+        self.refresh_tools().await?;
+
+        if self.tools.is_empty() {
+            println!("{}", "The canister exposes no tools.".yellow());
+        }
+        for tool in &self.tools {
+            println!(
+                "  {:<24} {}",
+                tool.name.bright_cyan(),
+                tool.description.as_deref().unwrap_or_default()
+            );
+        }
+        Ok(())
+    }
+
+    fn describe(&self, name: &str) -> Result<()> {
+        let tool = self
+            .tools
+            .iter()
+            .find(|tool| tool.name == name)
+            .ok_or_else(|| anyhow!("Unknown tool '{}'; run `tools` to refresh the list", name))?;
+
+        println!("{}", tool.name.bright_cyan().bold());
+        if let Some(description) = tool.description.as_deref() {
+            println!("{}", description);
+        }
+        println!(
+            "{}",
+            serde_json::to_string_pretty(tool.input_schema.as_ref())?
+        );
+        Ok(())
+    }
+
+    async fn info(&self) -> Result<()> {
+        let json = self.query_string("mcp_server_info").await?;
+        print_json(&serde_json::from_str(&json).unwrap_or(Value::String(json)));
+        Ok(())
+    }
+
+    async fn call(&mut self, tool: &str, arguments: Value) -> Result<()> {
+        self.next_id += 1;
+        let request = call_request(self.next_id, tool, arguments);
+        let args = candid::Encode!(&request).context("Failed to encode request")?;
+
+        let started = Instant::now();
+        let reply = dfx::call_canister_raw(
+            &self.canister_id,
+            "mcp_call_tool",
+            &args,
+            &self.network,
+            false,
+        )
+        .await?;
+        let elapsed = started.elapsed();
+
+        let response: String =
+            candid::decode_one(&reply).context("Failed to decode mcp_call_tool reply")?;
+        let response: Value =
+            serde_json::from_str(&response).context("mcp_call_tool returned invalid JSON")?;
+
+        if let Some(error) = response.get("error") {
+            println!("{}", "Tool call failed".red().bold());
+            print_json(error);
+        } else {
+            print_json(response.get("result").unwrap_or(&response));
+        }
+        println!("{}", format!("({} ms)", elapsed.as_millis()).bright_black());
+        Ok(())
+    }
+}
+
+/// Builds the JSON-RPC `tools/call` request sent to `mcp_call_tool`
+fn call_request(id: u64, tool: &str, arguments: Value) -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id.to_string(),
+        "method": "tools/call",
+        "params": {
+            "name": tool,
+            "arguments": arguments
+        }
+    })
+    .to_string()
+}
+
+fn parse_command(line: &str) -> Result<ReplCommand> {
+    let line = line.trim();
+    let (command, rest) = line
+        .split_once(char::is_whitespace)
+        .map_or((line, ""), |(command, rest)| (command, rest.trim()));
+
+    match command {
+        "" => Ok(ReplCommand::Empty),
+        "tools" | "ls" => Ok(ReplCommand::Tools),
+        "info" => Ok(ReplCommand::Info),
+        "help" | "?" => Ok(ReplCommand::Help),
+        "exit" | "quit" => Ok(ReplCommand::Exit),
+        "describe" | "schema" if !rest.is_empty() => Ok(ReplCommand::Describe(rest.to_string())),
+        "call" if !rest.is_empty() => {
+            let (tool, json) = rest
+                .split_once(char::is_whitespace)
+                .map_or((rest, ""), |(tool, json)| (tool, json.trim()));
+
+            let arguments = if json.is_empty() {
+                Value::Object(serde_json::Map::new())
+            } else {
+                serde_json::from_str(json).map_err(|e| anyhow!("Invalid JSON arguments: {}", e))?
+            };
+            if !arguments.is_object() {
+                return Err(anyhow!(
+                    "Arguments must be a JSON object, e.g. {{\"a\": 1}}"
+                ));
+            }
+
+            Ok(ReplCommand::Call {
+                tool: tool.to_string(),
+                arguments,
+            })
+        }
+        "describe" | "schema" | "call" => Err(anyhow!("Usage: {} <tool> ...", command)),
+        _ => Err(anyhow!(
+            "Unknown command '{}'. Type `help` for commands.",
+            command
+        )),
+    }
+}
+
+fn print_help() {
+    println!("  {:<28} {}", "tools".bold(), "List the canister's tools");
+    println!(
+        "  {:<28} {}",
+        "describe <tool>".bold(),
+        "Show a tool's description and input schema"
+    );
+    println!(
+        "  {:<28} {}",
+        "call <tool> [json]".bold(),
+        "Call a tool, e.g. call add {\"a\": 1, \"b\": 2}"
+    );
+    println!("  {:<28} {}", "info".bold(), "Show server information");
+    println!("  {:<28} {}", "exit".bold(), "Leave the REPL");
+    println!(
+        "{}",
+        "Use ↑/↓ for history and Tab to complete commands and tool names.".bright_black()
+    );
+}
+
+fn print_json(value: &Value) {
+    match serde_json::to_string_pretty(value) {
+        Ok(pretty) => println!("{}", pretty),
+        Err(_) => println!("{}", value),
+    }
+}
+
+/// Completes command names and tool names after `call` or `describe`
+struct ToolCompletion {
+    tool_names: Vec<String>,
+}
+
+impl Completion for ToolCompletion {
+    fn get(&self, input: &str) -> Option<String> {
+        complete(input, &self.tool_names)
+    }
+}
+
+fn complete(input: &str, tool_names: &[String]) -> Option<String> {
+    match input.split_once(' ') {
+        None => complete_word(input, COMMANDS.iter().copied()).map(|word| word.to_string()),
+        Some((command, partial))
+            if TOOL_COMMANDS.contains(&command) && !partial.contains(char::is_whitespace) =>
+        {
+            complete_word(partial, tool_names.iter().map(String::as_str))
+                .map(|word| format!("{} {}", command, word))
+        }
+        Some(_) => None,
+    }
+}
+
+/// Completes `partial` to the single candidate it prefixes (followed by a
+/// space), or to the longest prefix shared by several candidates
+fn complete_word<'a>(partial: &str, candidates: impl Iterator<Item = &'a str>) -> Option<String> {
+    let matches: Vec<&str> = candidates.filter(|c| c.starts_with(partial)).collect();
+
+    match matches.as_slice() {
+        [] => None,
+        [single] => Some(format!("{} ", single)),
+        [first, rest @ ..] => {
+            let common = rest.iter().fold(first.len(), |len, candidate| {
+                first
+                    .bytes()
+                    .zip(candidate.bytes())
+                    .take(len)
+                    .take_while(|(a, b)| a == b)
+                    .count()
+            });
+            (common > partial.len()).then(|| first[..common].to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse_command("  ").unwrap(), ReplCommand::Empty);
+        assert_eq!(parse_command("tools").unwrap(), ReplCommand::Tools);
+        assert_eq!(
+            parse_command("describe memorize").unwrap(),
+            ReplCommand::Describe("memorize".to_string())
+        );
+        assert_eq!(
+            parse_command(r#"call memorize {"content": "hi"}"#).unwrap(),
+            ReplCommand::Call {
+                tool: "memorize".to_string(),
+                arguments: serde_json::json!({ "content": "hi" }),
+            }
+        );
+        assert_eq!(
+            parse_command("call list_memories").unwrap(),
+            ReplCommand::Call {
+                tool: "list_memories".to_string(),
+                arguments: serde_json::json!({}),
+            }
+        );
+
+        assert!(parse_command("call").is_err());
+        assert!(parse_command("call memorize [1]").is_err());
+        assert!(parse_command("call memorize {oops").is_err());
+        assert!(parse_command("frobnicate").is_err());
+    }
+
+    #[test]
+    fn test_completion() {
+        let tools = vec![
+            "memorize".to_string(),
+            "memory_stats".to_string(),
+            "forget".to_string(),
+        ];
+
+        assert_eq!(complete("to", &tools), Some("tools ".to_string()));
+        assert_eq!(complete("call f", &tools), Some("call forget ".to_string()));
+        assert_eq!(complete("call mem", &tools), Some("call memor".to_string()));
+        assert_eq!(complete("call memor", &tools), None);
+        assert_eq!(complete("call forget {", &tools), None);
+        assert_eq!(complete("e", &tools), Some("exit ".to_string()));
+    }
+
+    #[test]
+    fn test_call_request() {
+        let request: Value =
+            serde_json::from_str(&call_request(7, "echo", serde_json::json!({ "s": "hi" })))
+                .unwrap();
+        assert_eq!(request["id"], "7");
+        assert_eq!(request["method"], "tools/call");
+        assert_eq!(request["params"]["name"], "echo");
+        assert_eq!(request["params"]["arguments"]["s"], "hi");
+    }
+}
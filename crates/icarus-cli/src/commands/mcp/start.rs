@@ -1,13 +1,48 @@
+use std::time::{Duration, Instant};
+
 use anyhow::{anyhow, Result};
 use colored::Colorize;
 use tokio::process::Command;
-use tracing::{info, warn};
+use tracing::{error, info, warn};
 
+use crate::commands::mcp::stop::is_process_running;
+use crate::config::daemon::{self, DaemonState};
+use crate::config::icarus_toml::IcarusToml;
 use crate::config::mcp::McpConfig;
+use crate::config::profiles::IdentityProfiles;
 use crate::utils::bridge::{McpBridgeServer, SimpleBridgeServer};
+use crate::utils::project;
+use crate::utils::rmcp_bridge::BridgeConfig;
+use crate::utils::rpc_log::{RpcLogConfig, RpcLogger};
+use crate::utils::session_recorder::SessionRecorder;
+use crate::utils::watchdog::{RestartPolicy, Watchdog};
 use crate::{commands::mcp::StartArgs, Cli};
 
-pub(crate) async fn execute(args: StartArgs, cli: &Cli) -> Result<()> {
+/// How often the supervisor checks that the bridge accepts connections.
+const PROBE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Failed probes after which the supervisor restarts the bridge.
+const PROBE_THRESHOLD: u32 = 3;
+
+/// Window over which `--max-restarts` is counted.
+const RESTART_WINDOW: Duration = Duration::from_secs(600);
+
+pub(crate) async fn execute(mut args: StartArgs, cli: &Cli) -> Result<()> {
+    // Inside a project, icarus.toml can name the identity to use and
+    // fields to redact from the RPC log
+    if let Ok(project_root) = project::find_project_root() {
+        if let Some(config) = IcarusToml::load(&project_root).await? {
+            if args.identity.is_none() {
+                args.identity = config.bridge.identity;
+            }
+            for rule in config.bridge.redact.unwrap_or_default() {
+                if !args.log_rpc_redact.contains(&rule) {
+                    args.log_rpc_redact.push(rule);
+                }
+            }
+        }
+    }
+
     info!("Starting MCP bridge server on {}:{}", args.host, args.port);
 
     if !cli.quiet {
@@ -18,10 +53,30 @@ pub(crate) async fn execute(args: StartArgs, cli: &Cli) -> Result<()> {
             args.host.bright_cyan(),
             args.port.to_string().bright_cyan()
         );
+        if let Some(identity) = &args.identity {
+            println!(
+                "  {} {}",
+                "Identity:".bright_white(),
+                identity.bright_cyan()
+            );
+        }
     }
 
     // Load MCP configuration
-    let mcp_config = McpConfig::load().await.unwrap_or_default();
+    let mut mcp_config = McpConfig::load().await.unwrap_or_default();
+
+    if let Some(ref canister_id) = args.canister_id {
+        mcp_config
+            .servers
+            .retain(|server| server.canister_id == *canister_id);
+        if mcp_config.servers.is_empty() {
+            return Err(anyhow!(
+                "Canister {} is not registered. Use 'icarus mcp add {} --client <client>' first.",
+                canister_id,
+                canister_id
+            ));
+        }
+    }
 
     if mcp_config.servers.is_empty() {
         warn!("No MCP servers registered. Use 'icarus mcp add' to register servers.");
@@ -41,9 +96,30 @@ pub(crate) async fn execute(args: StartArgs, cli: &Cli) -> Result<()> {
 
     // Start the bridge server
     if args.daemon {
-        start_daemon_server(&args, &mcp_config, cli).await
+        return start_daemon_server(&args, &mcp_config, cli).await;
+    }
+    let result = if args.supervise {
+        run_supervisor(&args, cli).await
     } else {
         start_foreground_server(&args, &mcp_config, cli).await
+    };
+
+    // Running as a daemon, report the error to `icarus mcp status`
+    if let Err(ref e) = result {
+        record_daemon_error(&args, &e.to_string(), false).await;
+    }
+    result
+}
+
+/// Records `error` in the state of the daemon running as this process, if any.
+async fn record_daemon_error(args: &StartArgs, error: &str, restarted: bool) {
+    let instance = daemon::instance_name(args.canister_id.as_deref());
+    let recorded = match DaemonState::state_dir() {
+        Ok(dir) => DaemonState::record_error_in(&dir, &instance, error, restarted).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = recorded {
+        warn!("Failed to record daemon error: {}", e);
     }
 }
 
@@ -83,27 +159,40 @@ async fn start_daemon_server(args: &StartArgs, _mcp_config: &McpConfig, cli: &Cl
         println!("{} Starting MCP bridge in daemon mode", "→".bright_blue());
     }
 
-    // For daemon mode, we'll spawn a background process
-    let mut cmd = Command::new("icarus");
-    cmd.args(&[
-        "mcp",
-        "start",
-        "--host",
-        &args.host,
-        "--port",
-        &args.port.to_string(),
-    ]);
-
-    if let Some(ref config_path) = args.config {
-        cmd.args(&["--config", &config_path.to_string_lossy()]);
+    let state_dir = DaemonState::state_dir()?;
+    let instance = daemon::instance_name(args.canister_id.as_deref());
+    if let Some(state) = DaemonState::load_in(&state_dir, &instance).await? {
+        if is_process_running(state.pid) {
+            return Err(anyhow!(
+                "MCP bridge daemon {} is already running (PID: {}). Use 'icarus mcp stop' first.",
+                instance,
+                state.pid
+            ));
+        }
     }
 
+    let log_dir = match args.log_dir {
+        Some(ref log_dir) => log_dir.clone(),
+        None => DaemonState::default_log_dir()?,
+    };
+    let log_path = daemon::rotate_logs(&log_dir, &instance).await?;
+    let log = std::fs::File::create(&log_path)?;
+
+    // For daemon mode, we'll spawn a background process
+    let mut cmd = Command::new(std::env::current_exe()?);
+    cmd.args(bridge_command_args(args, args.supervise))
+        .stdin(std::process::Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+
     // Spawn the daemon process
     let child = cmd.spawn()?;
     let pid = child.id().expect("Failed to get process ID");
 
-    // Save PID for later management
-    save_daemon_pid(pid).await?;
+    // Save the daemon's state for later management
+    let mut state = DaemonState::new(args.canister_id.as_deref(), pid, &args.host, args.port);
+    state.log_file = Some(log_path.clone());
+    state.save_in(&state_dir).await?;
 
     if !cli.quiet {
         println!(
@@ -115,18 +204,195 @@ async fn start_daemon_server(args: &StartArgs, _mcp_config: &McpConfig, cli: &Cl
             "  {} Use 'icarus mcp stop' to stop the server",
             "→".bright_blue()
         );
-        println!("  {} Logs: /tmp/icarus-mcp-bridge.log", "→".bright_blue());
+        println!("  {} Logs: {}", "→".bright_blue(), log_path.display());
     }
 
     info!("MCP bridge daemon started with PID: {}", pid);
     Ok(())
 }
 
+/// Returns the `icarus` arguments that start the bridge of `args` in the
+/// foreground, under a supervisor when `supervise` is set.
+fn bridge_command_args(args: &StartArgs, supervise: bool) -> Vec<String> {
+    let mut command = vec![
+        "mcp".to_string(),
+        "start".to_string(),
+        "--host".to_string(),
+        args.host.clone(),
+        "--port".to_string(),
+        args.port.to_string(),
+    ];
+
+    if let Some(ref config_path) = args.config {
+        command.push("--config".to_string());
+        command.push(config_path.to_string_lossy().into_owned());
+    }
+
+    if let Some(ref record_path) = args.record {
+        command.push("--record".to_string());
+        command.push(record_path.to_string_lossy().into_owned());
+    }
+
+    if let Some(ref identity) = args.identity {
+        command.push("--identity".to_string());
+        command.push(identity.clone());
+    }
+
+    if let Some(ref canister_id) = args.canister_id {
+        command.push("--canister-id".to_string());
+        command.push(canister_id.clone());
+    }
+
+    if let Some(ref log_rpc) = args.log_rpc {
+        command.push("--log-rpc".to_string());
+        command.push(log_rpc.to_string_lossy().into_owned());
+        for rule in &args.log_rpc_redact {
+            command.push("--log-rpc-redact".to_string());
+            command.push(rule.clone());
+        }
+        command.push("--log-rpc-sample".to_string());
+        command.push(args.log_rpc_sample.to_string());
+        command.push("--log-rpc-max-bytes".to_string());
+        command.push(args.log_rpc_max_bytes.to_string());
+    }
+
+    if args.cache_size > 0 {
+        command.push("--cache-size".to_string());
+        command.push(args.cache_size.to_string());
+        command.push("--cache-ttl".to_string());
+        command.push(args.cache_ttl.to_string());
+    }
+
+    if supervise {
+        command.push("--supervise".to_string());
+        command.push("--max-restarts".to_string());
+        command.push(args.max_restarts.to_string());
+    }
+
+    command
+}
+
+/// Runs the bridge as a child process, restarting it when it exits with an
+/// error or stops accepting connections, until it exits cleanly, the
+/// supervisor is stopped or the bridge fails too often.
+async fn run_supervisor(args: &StartArgs, cli: &Cli) -> Result<()> {
+    let exe = std::env::current_exe()?;
+    let mut policy = RestartPolicy::new(args.max_restarts, RESTART_WINDOW);
+
+    if !cli.quiet {
+        println!(
+            "{} Supervising MCP bridge (up to {} restarts in {} minutes)",
+            "→".bright_blue(),
+            args.max_restarts,
+            RESTART_WINDOW.as_secs() / 60
+        );
+    }
+
+    loop {
+        let mut child = Command::new(&exe)
+            .args(bridge_command_args(args, false))
+            .arg("--quiet")
+            .kill_on_drop(true)
+            .spawn()?;
+        info!("Supervised MCP bridge started with PID: {:?}", child.id());
+
+        let mut watchdog = Watchdog::new(PROBE_THRESHOLD);
+        let mut probe = tokio::time::interval(PROBE_INTERVAL);
+        // The first tick completes immediately, before the bridge listens
+        probe.tick().await;
+
+        let failure = loop {
+            tokio::select! {
+                status = child.wait() => {
+                    let status = status?;
+                    if status.success() {
+                        info!("Supervised MCP bridge exited");
+                        return Ok(());
+                    }
+                    break format!("exited with {status}");
+                }
+                _ = probe.tick() => {
+                    let accepting = tokio::time::timeout(
+                        PROBE_INTERVAL,
+                        tokio::net::TcpStream::connect((args.host.as_str(), args.port)),
+                    )
+                    .await
+                    .is_ok_and(|connected| connected.is_ok());
+                    if watchdog.record(accepting) {
+                        let _ = child.kill().await;
+                        break format!("stopped accepting connections on port {}", args.port);
+                    }
+                }
+                _ = terminate_signal() => {
+                    info!("MCP bridge supervisor shutdown requested");
+                    let _ = child.kill().await;
+                    return Ok(());
+                }
+            }
+        };
+
+        let Some(delay) = policy.next_restart(Instant::now()) else {
+            return Err(anyhow!(
+                "MCP bridge {}; giving up after {} restarts",
+                failure,
+                args.max_restarts
+            ));
+        };
+        error!("MCP bridge {}, restarting in {:?}", failure, delay);
+        record_daemon_error(args, &format!("MCP bridge {failure}"), true).await;
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Completes on Ctrl+C, or on SIGTERM from `icarus mcp stop`.
+async fn terminate_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = terminate.recv() => {}
+                _ = tokio::signal::ctrl_c() => {}
+            }
+            return;
+        }
+    }
+
+    let _ = tokio::signal::ctrl_c().await;
+}
+
 async fn create_bridge_server(
     args: &StartArgs,
     mcp_config: &McpConfig,
 ) -> Result<Box<dyn McpBridgeServer>> {
-    let bridge = SimpleBridgeServer::new(&args.host, args.port, mcp_config.clone())?;
+    let profiles = IdentityProfiles::load().await.unwrap_or_default();
+    let bridge_config = BridgeConfig {
+        identity: args.identity.clone(),
+        cache_size: args.cache_size,
+        cache_ttl: Duration::from_secs(args.cache_ttl),
+        ..BridgeConfig::default()
+    };
+    let mut bridge = SimpleBridgeServer::new(&args.host, args.port, mcp_config.clone())?
+        .with_identity(args.identity.clone(), profiles)
+        .with_cache(&bridge_config);
+
+    if let Some(ref record_path) = args.record {
+        let recorder = SessionRecorder::create(record_path)?;
+        info!("Recording session to {}", recorder.path().display());
+        bridge = bridge.with_recorder(recorder);
+    }
+
+    if let Some(ref log_path) = args.log_rpc {
+        let config = RpcLogConfig::from_args(
+            &args.log_rpc_redact,
+            args.log_rpc_sample,
+            args.log_rpc_max_bytes,
+        )?;
+        let rpc_log = RpcLogger::open(log_path, config)?;
+        info!("Logging JSON-RPC traffic to {}", rpc_log.path().display());
+        bridge = bridge.with_rpc_log(rpc_log);
+    }
 
     Ok(Box::new(bridge))
 }
@@ -194,18 +460,9 @@ async fn run_bridge_server(
     Ok(())
 }
 
-async fn save_daemon_pid(pid: u32) -> Result<()> {
-    use tokio::fs;
-
-    let pid_file = "/tmp/icarus-mcp-bridge.pid";
-    fs::write(pid_file, pid.to_string()).await?;
-    Ok(())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempfile::TempDir;
 
     #[tokio::test]
     async fn test_port_availability_check() {
@@ -218,22 +475,6 @@ mod tests {
         assert!(invalid); // Should fail to bind to invalid host
     }
 
-    #[tokio::test]
-    #[serial_test::serial]
-    async fn test_daemon_pid_save() {
-        let result = save_daemon_pid(12345).await;
-        assert!(result.is_ok());
-
-        // Check if PID file was created
-        let pid_content = tokio::fs::read_to_string("/tmp/icarus-mcp-bridge.pid")
-            .await
-            .unwrap();
-        assert_eq!(pid_content, "12345");
-
-        // Clean up
-        let _ = tokio::fs::remove_file("/tmp/icarus-mcp-bridge.pid").await;
-    }
-
     #[test]
     fn test_start_args_validation() {
         let args = StartArgs {
@@ -241,10 +482,91 @@ mod tests {
             host: "localhost".to_string(),
             daemon: false,
             config: None,
+            record: None,
+            identity: None,
+            supervise: false,
+            max_restarts: 5,
+            canister_id: None,
+            log_dir: None,
+            log_rpc: None,
+            log_rpc_redact: Vec::new(),
+            log_rpc_sample: 1.0,
+            log_rpc_max_bytes: 65536,
+            cache_size: 0,
+            cache_ttl: 60,
         };
 
         assert_eq!(args.port, 3000);
         assert_eq!(args.host, "localhost");
         assert!(!args.daemon);
     }
+
+    #[test]
+    fn test_bridge_command_args() {
+        let args = StartArgs {
+            port: 4000,
+            host: "127.0.0.1".to_string(),
+            daemon: true,
+            config: None,
+            record: None,
+            identity: Some("deployer".to_string()),
+            supervise: true,
+            max_restarts: 2,
+            canister_id: Some("rdmx6-jaaaa-aaaaa-aaadq-cai".to_string()),
+            log_dir: None,
+            log_rpc: None,
+            log_rpc_redact: vec!["$..card".to_string()],
+            log_rpc_sample: 0.5,
+            log_rpc_max_bytes: 1024,
+            cache_size: 0,
+            cache_ttl: 60,
+        };
+
+        assert_eq!(
+            bridge_command_args(&args, false),
+            [
+                "mcp",
+                "start",
+                "--host",
+                "127.0.0.1",
+                "--port",
+                "4000",
+                "--identity",
+                "deployer",
+                "--canister-id",
+                "rdmx6-jaaaa-aaaaa-aaadq-cai"
+            ]
+        );
+
+        let mut logged = args.clone();
+        logged.log_rpc = Some("rpc.jsonl".into());
+        let command = bridge_command_args(&logged, false);
+        assert!(command.ends_with(&[
+            "--log-rpc".to_string(),
+            "rpc.jsonl".to_string(),
+            "--log-rpc-redact".to_string(),
+            "$..card".to_string(),
+            "--log-rpc-sample".to_string(),
+            "0.5".to_string(),
+            "--log-rpc-max-bytes".to_string(),
+            "1024".to_string()
+        ]));
+
+        let cached = StartArgs {
+            cache_size: 128,
+            cache_ttl: 15,
+            ..args.clone()
+        };
+        assert!(bridge_command_args(&cached, false).ends_with(&[
+            "--cache-size".to_string(),
+            "128".to_string(),
+            "--cache-ttl".to_string(),
+            "15".to_string()
+        ]));
+        assert!(bridge_command_args(&args, true).ends_with(&[
+            "--supervise".to_string(),
+            "--max-restarts".to_string(),
+            "2".to_string()
+        ]));
+    }
 }
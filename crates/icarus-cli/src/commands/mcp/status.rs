@@ -5,6 +5,9 @@ use std::time::Duration;
 use tokio::time::timeout;
 use tracing::info;
 
+use crate::commands::mcp::stop::is_process_running;
+use crate::commands::monitor::format_uptime;
+use crate::config::daemon::DaemonState;
 use crate::config::mcp::McpConfig;
 use crate::{commands::mcp::StatusArgs, Cli};
 
@@ -53,6 +56,16 @@ impl std::fmt::Display for HealthStatus {
 }
 
 pub(crate) async fn execute(args: StatusArgs, cli: &Cli) -> Result<()> {
+    if !cli.quiet {
+        let daemons = match DaemonState::state_dir() {
+            Ok(dir) => DaemonState::load_all_in(&dir).await.unwrap_or_default(),
+            Err(_) => Vec::new(),
+        };
+        if !daemons.is_empty() {
+            print_daemon_table(&daemons);
+        }
+    }
+
     let mcp_config = McpConfig::load().await.unwrap_or_default();
 
     if mcp_config.servers.is_empty() {
@@ -255,6 +268,57 @@ async fn check_ic_canister_health(canister_id: &str, network: &str) -> Result<()
     }
 }
 
+/// Returns the instance, PID, address, state, uptime, restarts and last
+/// error of a daemon
+fn daemon_row(
+    daemon: &DaemonState,
+    running: bool,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Vec<String> {
+    let (state, uptime) = if running {
+        ("running", format_uptime(daemon.uptime_secs(now)))
+    } else {
+        ("stopped", "-".to_string())
+    };
+    vec![
+        daemon.instance.clone(),
+        daemon.pid.to_string(),
+        format!("{}:{}", daemon.host, daemon.port),
+        state.to_string(),
+        uptime,
+        daemon.restarts.to_string(),
+        daemon.last_error.clone().unwrap_or_else(|| "-".to_string()),
+    ]
+}
+
+fn print_daemon_table(daemons: &[DaemonState]) {
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+
+    table.set_header(vec![
+        "Instance".bright_white().bold(),
+        "PID".bright_white().bold(),
+        "Address".bright_white().bold(),
+        "State".bright_white().bold(),
+        "Uptime".bright_white().bold(),
+        "Restarts".bright_white().bold(),
+        "Last Error".bright_white().bold(),
+    ]);
+
+    let now = chrono::Utc::now();
+    for daemon in daemons {
+        table.add_row(daemon_row(daemon, is_process_running(daemon.pid), now));
+    }
+
+    println!("\n{}", "🛰  MCP Bridge Daemons".bright_white().bold());
+    println!("{}", table);
+    if let Some(log_file) = daemons.iter().find_map(|d| d.log_file.as_ref()) {
+        if let Some(log_dir) = log_file.parent() {
+            println!("Logs: {}", log_dir.display());
+        }
+    }
+}
+
 fn print_status_table(statuses: &[ServerStatus]) {
     let mut table = Table::new();
     table.load_preset(UTF8_FULL);
@@ -368,6 +432,25 @@ mod tests {
         assert_eq!(HealthStatus::Timeout.to_string(), "⏰ Timeout");
     }
 
+    #[test]
+    fn test_daemon_row() {
+        let mut daemon =
+            DaemonState::new(Some("rdmx6-jaaaa-aaaaa-aaadq-cai"), 42, "localhost", 3000);
+        let now = daemon.started_at + chrono::Duration::seconds(3_660);
+
+        let row = daemon_row(&daemon, true, now);
+        assert_eq!(row[2], "localhost:3000");
+        assert_eq!(row[3], "running");
+        assert_eq!(row[4], "1h 1m");
+        assert_eq!(row[6], "-");
+
+        daemon.last_error = Some("exited with exit status: 1".to_string());
+        let row = daemon_row(&daemon, false, now);
+        assert_eq!(row[3], "stopped");
+        assert_eq!(row[4], "-");
+        assert_eq!(row[6], "exited with exit status: 1");
+    }
+
     #[tokio::test]
     async fn test_status_check_nonexistent_server() {
         let args = StatusArgs {
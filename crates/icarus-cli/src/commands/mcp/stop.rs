@@ -1,8 +1,8 @@
 use anyhow::{anyhow, Result};
 use colored::Colorize;
-use tokio::fs;
 use tracing::info;
 
+use crate::config::daemon::{self, DaemonState};
 use crate::{commands::mcp::StopArgs, Cli};
 
 pub(crate) async fn execute(args: StopArgs, cli: &Cli) -> Result<()> {
@@ -15,32 +15,37 @@ pub(crate) async fn execute(args: StopArgs, cli: &Cli) -> Result<()> {
     if args.all {
         stop_all_processes(args.force, cli).await
     } else {
-        stop_daemon_process(args.force, cli).await
+        stop_daemon_process(args.canister_id.as_deref(), args.force, cli).await
     }
 }
 
-async fn stop_daemon_process(force: bool, cli: &Cli) -> Result<()> {
-    let pid_file = "/tmp/icarus-mcp-bridge.pid";
+async fn stop_daemon_process(canister_id: Option<&str>, force: bool, cli: &Cli) -> Result<()> {
+    let state_dir = DaemonState::state_dir()?;
+    let states = DaemonState::load_all_in(&state_dir).await?;
 
-    // Check if PID file exists
-    if !std::path::Path::new(pid_file).exists() {
-        if !cli.quiet {
-            println!("{}", "No running MCP bridge daemon found.".yellow());
+    let state = match select_daemon(states, canister_id)? {
+        Some(state) => state,
+        None => {
+            if !cli.quiet {
+                match canister_id {
+                    Some(canister_id) => println!(
+                        "{}",
+                        format!("No MCP bridge daemon found for canister {}.", canister_id)
+                            .yellow()
+                    ),
+                    None => println!("{}", "No running MCP bridge daemon found.".yellow()),
+                }
+            }
+            return Ok(());
         }
-        return Ok(());
-    }
-
-    // Read PID from file
-    let pid_str = fs::read_to_string(pid_file).await?;
-    let pid: u32 = pid_str
-        .trim()
-        .parse()
-        .map_err(|_| anyhow!("Invalid PID in file: {}", pid_str))?;
+    };
+    let pid = state.pid;
 
     if !cli.quiet {
         println!(
-            "  {} Stopping daemon (PID: {})",
+            "  {} Stopping daemon {} (PID: {})",
             "→".bright_blue(),
+            state.instance.bright_cyan(),
             pid.to_string().bright_cyan()
         );
     }
@@ -50,10 +55,10 @@ async fn stop_daemon_process(force: bool, cli: &Cli) -> Result<()> {
         if !cli.quiet {
             println!(
                 "{}",
-                "Process is not running, cleaning up PID file.".yellow()
+                "Process is not running, cleaning up daemon state.".yellow()
             );
         }
-        fs::remove_file(pid_file).await?;
+        DaemonState::remove_in(&state_dir, &state.instance).await?;
         return Ok(());
     }
 
@@ -82,8 +87,8 @@ async fn stop_daemon_process(force: bool, cli: &Cli) -> Result<()> {
         }
     }
 
-    // Clean up PID file
-    fs::remove_file(pid_file).await?;
+    // Clean up the daemon's state
+    DaemonState::remove_in(&state_dir, &state.instance).await?;
 
     if !cli.quiet {
         println!("{} MCP bridge server stopped", "✅".green());
@@ -93,13 +98,50 @@ async fn stop_daemon_process(force: bool, cli: &Cli) -> Result<()> {
     Ok(())
 }
 
+/// Picks the daemon to stop: the one serving `canister_id`, or the only one
+/// running when no canister is given.
+fn select_daemon(
+    mut states: Vec<DaemonState>,
+    canister_id: Option<&str>,
+) -> Result<Option<DaemonState>> {
+    if let Some(canister_id) = canister_id {
+        let instance = daemon::instance_name(Some(canister_id));
+        return Ok(states.into_iter().find(|state| state.instance == instance));
+    }
+
+    match states.len() {
+        0 | 1 => Ok(states.pop()),
+        _ => Err(anyhow!(
+            "{} MCP bridge daemons are running ({}). Use --canister-id to pick one or --all to stop them all.",
+            states.len(),
+            states
+                .iter()
+                .map(|state| state.instance.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
 async fn stop_all_processes(force: bool, cli: &Cli) -> Result<()> {
     if !cli.quiet {
         println!("  {} Stopping all MCP bridge processes", "→".bright_blue());
     }
 
-    // Find all icarus MCP processes
-    let processes = find_icarus_mcp_processes().await?;
+    // Find all icarus MCP processes, including daemons started from another binary
+    let state_dir = DaemonState::state_dir()?;
+    let states = DaemonState::load_all_in(&state_dir).await?;
+    let mut processes = find_icarus_mcp_processes().await?;
+    for state in &states {
+        if !processes.contains(&state.pid) && is_process_running(state.pid) {
+            processes.push(state.pid);
+        }
+    }
+
+    // Clean up daemon state
+    for state in &states {
+        DaemonState::remove_in(&state_dir, &state.instance).await?;
+    }
 
     if processes.is_empty() {
         if !cli.quiet {
@@ -132,9 +174,6 @@ async fn stop_all_processes(force: bool, cli: &Cli) -> Result<()> {
         }
     }
 
-    // Clean up PID file if it exists
-    let _ = fs::remove_file("/tmp/icarus-mcp-bridge.pid").await;
-
     if !cli.quiet {
         println!(
             "{} Stopped {} MCP bridge processes",
@@ -147,7 +186,7 @@ async fn stop_all_processes(force: bool, cli: &Cli) -> Result<()> {
     Ok(())
 }
 
-fn is_process_running(pid: u32) -> bool {
+pub(crate) fn is_process_running(pid: u32) -> bool {
     #[cfg(unix)]
     {
         use nix::sys::signal::kill;
@@ -305,19 +344,34 @@ mod tests {
         assert!(processes.len() >= 0);
     }
 
+    #[test]
+    fn test_select_daemon() {
+        let daemon =
+            |canister_id: Option<&str>| DaemonState::new(canister_id, 1, "localhost", 3000);
+        let canister = "rdmx6-jaaaa-aaaaa-aaadq-cai";
+
+        assert!(select_daemon(Vec::new(), None).unwrap().is_none());
+        let only = select_daemon(vec![daemon(None)], None).unwrap().unwrap();
+        assert_eq!(only.instance, daemon::ALL_CANISTERS);
+
+        let both = vec![daemon(None), daemon(Some(canister))];
+        assert!(select_daemon(both.clone(), None).is_err());
+        let picked = select_daemon(both.clone(), Some(canister))
+            .unwrap()
+            .unwrap();
+        assert_eq!(picked.canister_id.as_deref(), Some(canister));
+        assert!(select_daemon(both, Some("ryjl3-tyaaa-aaaaa-aaaba-cai"))
+            .unwrap()
+            .is_none());
+    }
+
     #[tokio::test]
     #[serial_test::serial]
     async fn test_stop_nonexistent_daemon() {
-        // Clean up leftover test PID file if it contains test data
-        if let Ok(content) = tokio::fs::read_to_string("/tmp/icarus-mcp-bridge.pid").await {
-            if content.trim() == "12345" {
-                let _ = tokio::fs::remove_file("/tmp/icarus-mcp-bridge.pid").await;
-            }
-        }
-
         let args = StopArgs {
             force: false,
             all: false,
+            canister_id: Some("ryjl3-tyaaa-aaaaa-aaaba-cai".to_string()),
         };
 
         let cli = crate::Cli {
@@ -1,9 +1,18 @@
 use clap::{Args, Subcommand};
 
+pub(crate) mod add;
 pub(crate) mod build;
+pub(crate) mod candid;
+pub(crate) mod config;
 pub(crate) mod deploy;
+pub(crate) mod doctor;
+pub(crate) mod export;
+pub(crate) mod generate;
 pub(crate) mod mcp;
+pub(crate) mod monitor;
 pub(crate) mod new;
+pub(crate) mod serve;
+pub(crate) mod template;
 
 /// Arguments for the `new` command
 #[derive(Args, Clone)]
@@ -22,6 +31,11 @@ pub struct NewArgs {
     /// Skip dependency installation
     #[arg(long)]
     pub no_install: bool,
+
+    /// Template to start from: `basic`, `agent-memory`, a registered template name,
+    /// `gh:<owner>/<repo>[#ref]`, a git URL or a local directory
+    #[arg(short, long, default_value = "basic")]
+    pub template: String,
 }
 
 /// Arguments for the `build` command
@@ -50,20 +64,45 @@ pub struct BuildArgs {
     /// Output directory for build artifacts
     #[arg(short, long)]
     pub output: Option<std::path::PathBuf>,
+
+    /// Fail if the gzipped WASM module is larger than this, e.g. 2MB
+    #[arg(long)]
+    pub max_size: Option<String>,
+
+    /// Skip regenerating the Candid file from the built module
+    #[arg(long)]
+    pub no_candid: bool,
+
+    /// Build a WASM component exporting the tools for non-IC hosts (wasm32-wasip2)
+    #[arg(long)]
+    pub component: bool,
+}
+
+/// Arguments for the `candid` command
+#[derive(Args, Clone)]
+pub struct CandidArgs {
+    /// Fail with a diff instead of rewriting an out-of-date .did file
+    #[arg(long)]
+    pub check: bool,
+
+    /// Use the existing release build instead of building first
+    #[arg(long)]
+    pub no_build: bool,
 }
 
 /// Arguments for the `deploy` command
 #[derive(Args, Clone)]
 pub struct DeployArgs {
-    /// Network to deploy to (local, ic, testnet)
-    #[arg(short, long, default_value = "local")]
-    pub network: String,
+    /// Network to deploy to (local, ic, testnet, pocket-ic) [default: local,
+    /// or `[deploy].network` in icarus.toml]
+    #[arg(short, long)]
+    pub network: Option<String>,
 
     /// Canister name to deploy (if not specified, deploys all)
     #[arg(short, long)]
     pub canister: Option<String>,
 
-    /// Deploy with arguments
+    /// Cycles to create canisters with (defaults to `[deploy].with_cycles` in icarus.toml)
     #[arg(long)]
     pub with_cycles: Option<u64>,
 
@@ -78,6 +117,254 @@ pub struct DeployArgs {
     /// Post-deployment verification
     #[arg(long, default_value = "true")]
     pub verify: bool,
+
+    /// Keep the PocketIC instance running until Ctrl+C (pocket-ic network only)
+    #[arg(long)]
+    pub keep_alive: bool,
+
+    /// Port the PocketIC gateway listens on (pocket-ic network only)
+    #[arg(long)]
+    pub pocket_ic_port: Option<u16>,
+}
+
+impl DeployArgs {
+    /// Network to deploy to, `local` unless given by flag or icarus.toml
+    pub fn network(&self) -> &str {
+        self.network.as_deref().unwrap_or("local")
+    }
+}
+
+/// Arguments for the `doctor` command
+#[derive(Args, Clone)]
+pub struct DoctorArgs {
+    /// Canister ID or registered server name to check
+    #[arg(long)]
+    pub canister_id: Option<String>,
+
+    /// Network the canister is deployed on (ignored for registered servers)
+    #[arg(short, long, default_value = "local")]
+    pub network: String,
+
+    /// Skip launching the bridge commands found in client configs
+    #[arg(long)]
+    pub skip_handshake: bool,
+}
+
+/// Arguments for the `monitor` command
+#[derive(Args, Clone)]
+pub struct MonitorArgs {
+    /// Canister ID or registered server name
+    pub identifier: String,
+
+    /// Network the canister is deployed on (ignored for registered servers)
+    #[arg(short, long, default_value = "local")]
+    pub network: String,
+
+    /// Time window to report, e.g. 30m, 24h, 7d or all
+    #[arg(short, long, default_value = "24h")]
+    pub window: String,
+
+    /// Refresh every N seconds instead of printing once
+    #[arg(long)]
+    pub watch: Option<u64>,
+
+    /// Print the raw statistics as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Show the canister's health report instead of tool usage
+    #[arg(long)]
+    pub health: bool,
+}
+
+/// Arguments for the `serve` command
+#[derive(Args, Clone)]
+pub struct ServeArgs {
+    /// Run the project's tools in a native process instead of a canister
+    #[arg(long)]
+    pub native: bool,
+
+    /// Principal every call comes from; it is also made an admin
+    /// [default: the anonymous principal]
+    #[arg(long)]
+    pub caller: Option<String>,
+
+    /// Build the server in release mode
+    #[arg(long)]
+    pub release: bool,
+}
+
+/// Arguments for the `add tool` command
+#[derive(Args, Clone)]
+pub struct AddToolArgs {
+    /// Name of the tool function (snake_case)
+    pub name: String,
+
+    /// Parameters as comma-separated `name:type` pairs, e.g. "a:f64,b:f64"
+    #[arg(long, default_value = "")]
+    pub params: String,
+
+    /// Return type of the tool
+    #[arg(long, default_value = "String")]
+    pub returns: String,
+
+    /// Role required to call the tool
+    #[arg(long, value_enum)]
+    pub auth: Option<ToolAuth>,
+
+    /// Description shown to AI clients
+    #[arg(short, long)]
+    pub description: Option<String>,
+
+    /// Skip regenerating the Candid file
+    #[arg(long)]
+    pub no_candid: bool,
+}
+
+/// Caller roles a tool can require
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ToolAuth {
+    /// Any authorized user
+    User,
+    /// Admins only
+    Admin,
+}
+
+impl std::fmt::Display for ToolAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToolAuth::User => write!(f, "user"),
+            ToolAuth::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+/// Scaffolding commands for an existing project
+#[derive(Subcommand, Clone)]
+pub enum AddArgs {
+    /// Add a tool function stub to src/lib.rs
+    Tool(AddToolArgs),
+}
+
+/// Arguments for the `generate ts-client` command
+#[derive(Args, Clone)]
+pub struct TsClientArgs {
+    /// Canister ID or registered server name to generate the client for
+    #[arg(long)]
+    pub canister_id: String,
+
+    /// Network the canister is deployed on (ignored for registered servers)
+    #[arg(short, long, default_value = "local")]
+    pub network: String,
+
+    /// npm package name
+    #[arg(long, default_value = "icarus-client")]
+    pub package_name: String,
+
+    /// Directory to write the package to (defaults to the package name)
+    #[arg(short, long)]
+    pub output: Option<std::path::PathBuf>,
+}
+
+/// Arguments for the `generate openapi` command
+#[derive(Args, Clone)]
+pub struct OpenApiArgs {
+    /// Canister ID or registered server name to describe
+    #[arg(long)]
+    pub canister_id: String,
+
+    /// Network the canister is deployed on (ignored for registered servers)
+    #[arg(short, long, default_value = "local")]
+    pub network: String,
+
+    /// File to write the document to
+    #[arg(short, long, default_value = "openapi.json")]
+    pub output: std::path::PathBuf,
+}
+
+/// Arguments for the `export tools` command
+#[derive(Args, Clone)]
+pub struct ExportToolsArgs {
+    /// Canister ID or registered server name whose tools to export
+    #[arg(long)]
+    pub canister_id: String,
+
+    /// Network the canister is deployed on (ignored for registered servers)
+    #[arg(short, long, default_value = "local")]
+    pub network: String,
+
+    /// Declaration format: `openai` or `gemini`
+    #[arg(long)]
+    pub format: String,
+
+    /// File to write the declarations to (defaults to `<format>-tools.json`)
+    #[arg(short, long)]
+    pub output: Option<std::path::PathBuf>,
+}
+
+/// Arguments for the `config validate` command
+#[derive(Args, Clone)]
+pub struct ConfigValidateArgs {
+    /// File to validate (defaults to icarus.toml in the project root)
+    pub path: Option<std::path::PathBuf>,
+}
+
+/// Project configuration commands
+#[derive(Subcommand, Clone)]
+pub enum ConfigArgs {
+    /// Check icarus.toml against its schema
+    Validate(ConfigValidateArgs),
+}
+
+/// Arguments for the `template add` command
+#[derive(Args, Clone)]
+pub struct TemplateAddArgs {
+    /// Name to register the template under
+    pub name: String,
+
+    /// `gh:<owner>/<repo>[#ref]`, a git URL or a local directory
+    pub source: String,
+
+    /// Short description shown by `icarus template list`
+    #[arg(short, long)]
+    pub description: Option<String>,
+}
+
+/// Arguments for the `template remove` command
+#[derive(Args, Clone)]
+pub struct TemplateRemoveArgs {
+    /// Name of the registered template
+    pub name: String,
+}
+
+/// Project template registry commands
+#[derive(Subcommand, Clone)]
+pub enum TemplateArgs {
+    /// Register a template for `icarus new --template <name>`
+    Add(TemplateAddArgs),
+
+    /// List registered templates
+    List,
+
+    /// Remove a registered template
+    Remove(TemplateRemoveArgs),
+}
+
+/// Code generation commands
+#[derive(Subcommand, Clone)]
+pub enum GenerateArgs {
+    /// Generate a TypeScript client package for the canister's tools
+    TsClient(TsClientArgs),
+
+    /// Write an OpenAPI 3.1 document for the canister's HTTP tool API
+    Openapi(OpenApiArgs),
+}
+
+/// Export commands
+#[derive(Subcommand, Clone)]
+pub enum ExportArgs {
+    /// Write a canister's tools as OpenAI or Gemini tool declarations
+    Tools(ExportToolsArgs),
 }
 
 /// MCP server management commands
@@ -100,4 +387,10 @@ pub enum McpArgs {
 
     /// Stop MCP bridge server
     Stop(mcp::StopArgs),
+
+    /// Interactively list and call a canister's tools
+    Repl(mcp::ReplArgs),
+
+    /// Log in with Internet Identity for bridging without dfx
+    Login(mcp::LoginArgs),
 }
@@ -0,0 +1,285 @@
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use comfy_table::{presets::UTF8_FULL, Table};
+use std::time::Duration;
+
+use icarus_core::health::HealthStatus;
+use icarus_core::metrics::{UsageEntry, UsageStats};
+
+use crate::commands::MonitorArgs;
+use crate::config::mcp::McpConfig;
+use crate::utils::dfx;
+use crate::Cli;
+
+pub(crate) async fn execute(args: MonitorArgs, cli: &Cli) -> Result<()> {
+    let window_secs = parse_window(&args.window)?;
+    let (canister_id, network) = resolve_target(&args).await;
+
+    loop {
+        if args.health {
+            let health = fetch_health(&canister_id, &network).await?;
+
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&health)?);
+            } else {
+                print_health(&health, &canister_id);
+            }
+        } else {
+            let stats = fetch_usage_stats(&canister_id, &network, window_secs).await?;
+
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            } else {
+                if args.watch.is_some() && !cli.quiet {
+                    // Clear the screen between refreshes
+                    print!("\x1B[2J\x1B[1;1H");
+                }
+                print_stats(&stats, &canister_id, &args.window);
+            }
+        }
+
+        match args.watch {
+            Some(interval) => tokio::time::sleep(Duration::from_secs(interval.max(1))).await,
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Resolves a registered server name to its canister and network
+async fn resolve_target(args: &MonitorArgs) -> (String, String) {
+    let mcp_config = McpConfig::load().await.unwrap_or_default();
+
+    mcp_config
+        .servers
+        .iter()
+        .find(|s| s.name == args.identifier || s.canister_id == args.identifier)
+        .map(|s| (s.canister_id.to_string(), s.network.to_string()))
+        .unwrap_or_else(|| (args.identifier.clone(), args.network.clone()))
+}
+
+async fn fetch_usage_stats(
+    canister_id: &str,
+    network: &str,
+    window_secs: Option<u64>,
+) -> Result<UsageStats> {
+    let arg = match window_secs {
+        Some(secs) => format!("(opt ({} : nat64))", secs),
+        None => "(null)".to_string(),
+    };
+
+    let reply = dfx::query_canister_raw(canister_id, "mcp_usage_stats", &arg, network).await?;
+    let result: Result<UsageStats, String> =
+        candid::decode_one(&reply).context("Failed to decode mcp_usage_stats reply")?;
+
+    result.map_err(|e| anyhow!("mcp_usage_stats rejected the request: {}", e))
+}
+
+/// Reads the `mcp_health` report of a canister
+async fn fetch_health(canister_id: &str, network: &str) -> Result<HealthStatus> {
+    let reply = dfx::query_canister_raw(canister_id, "mcp_health", "()", network).await?;
+    let json: String = candid::decode_one(&reply).context("Failed to decode mcp_health reply")?;
+
+    serde_json::from_str(&json).context("Invalid health report returned by mcp_health")
+}
+
+fn print_health(health: &HealthStatus, canister_id: &str) {
+    let state = if health.ready {
+        "✅ Ready".green()
+    } else {
+        "❌ Not ready".red()
+    };
+    println!(
+        "\n{} {} {}",
+        "🩺 Health of".bright_white().bold(),
+        canister_id.bright_blue(),
+        state
+    );
+    println!("  {} {}", "Version:".bright_white(), health.version);
+    if let Some(secs) = health.uptime_secs {
+        println!("  {} {}", "Uptime:".bright_white(), format_uptime(secs));
+    }
+    println!("  {} {}", "Tools:".bright_white(), health.tool_count);
+    println!(
+        "  {} {} bytes ({:.1}%)",
+        "Stable memory:".bright_white(),
+        health.memory_bytes,
+        health.memory_usage_percent
+    );
+    let cycles = health.cycles.to_string();
+    println!(
+        "  {} {}",
+        "Cycles:".bright_white(),
+        if health.low_cycles {
+            cycles.yellow()
+        } else {
+            cycles.normal()
+        }
+    );
+    for issue in &health.issues {
+        println!("  {} {}", "⚠️ ".yellow(), issue);
+    }
+}
+
+/// Formats an uptime as days, hours and minutes, e.g. `2d 3h 15m`
+pub(crate) fn format_uptime(secs: u64) -> String {
+    match (secs / 86_400, secs % 86_400 / 3_600, secs % 3_600 / 60) {
+        (0, 0, minutes) => format!("{}m", minutes),
+        (0, hours, minutes) => format!("{}h {}m", hours, minutes),
+        (days, hours, minutes) => format!("{}d {}h {}m", days, hours, minutes),
+    }
+}
+
+/// Parses a window such as `30m`, `24h`, `7d`, `3600` (seconds) or `all`
+fn parse_window(window: &str) -> Result<Option<u64>> {
+    let window = window.trim().to_lowercase();
+    if window == "all" {
+        return Ok(None);
+    }
+
+    let (digits, multiplier) = match window.chars().last() {
+        Some('s') => (&window[..window.len() - 1], 1),
+        Some('m') => (&window[..window.len() - 1], 60),
+        Some('h') => (&window[..window.len() - 1], 3_600),
+        Some('d') => (&window[..window.len() - 1], 86_400),
+        _ => (window.as_str(), 1),
+    };
+
+    let value: u64 = digits
+        .parse()
+        .map_err(|_| anyhow!("Invalid window '{}': use e.g. 30m, 24h, 7d or all", window))?;
+
+    Ok(Some(value.saturating_mul(multiplier)))
+}
+
+/// Estimates a latency percentile as the upper bound of the histogram bucket
+/// that contains it
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn percentile_label(entry: &UsageEntry, buckets_ms: &[u64], percentile: f64) -> String {
+    if entry.calls == 0 {
+        return "-".to_string();
+    }
+
+    let target = (entry.calls as f64 * percentile).ceil() as u64;
+    let mut seen = 0;
+    for (index, count) in entry.latency_histogram.iter().enumerate() {
+        seen += count;
+        if seen >= target {
+            return match buckets_ms.get(index) {
+                Some(bound) => format!("<{}ms", bound),
+                None => format!(">{}ms", buckets_ms.last().copied().unwrap_or_default()),
+            };
+        }
+    }
+
+    "-".to_string()
+}
+
+fn error_rate_cell(entry: &UsageEntry) -> String {
+    let rate = format!("{:.1}%", entry.error_rate * 100.0);
+    if entry.error_rate >= 0.1 {
+        rate.red().to_string()
+    } else if entry.error_rate > 0.0 {
+        rate.yellow().to_string()
+    } else {
+        rate.green().to_string()
+    }
+}
+
+fn usage_table(label: &str, entries: &[UsageEntry], buckets_ms: &[u64]) -> Table {
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec![
+        label.bright_white().bold(),
+        "Calls".bright_white().bold(),
+        "Errors".bright_white().bold(),
+        "Error rate".bright_white().bold(),
+        "Avg latency".bright_white().bold(),
+        "p50".bright_white().bold(),
+        "p95".bright_white().bold(),
+    ]);
+
+    for entry in entries {
+        table.add_row(vec![
+            entry.name.bright_cyan().to_string(),
+            entry.calls.to_string(),
+            entry.errors.to_string(),
+            error_rate_cell(entry),
+            format!("{:.2}ms", entry.avg_latency_ms),
+            percentile_label(entry, buckets_ms, 0.5),
+            percentile_label(entry, buckets_ms, 0.95),
+        ]);
+    }
+
+    table
+}
+
+fn print_stats(stats: &UsageStats, canister_id: &str, window: &str) {
+    println!(
+        "\n{} {} {}",
+        "📈 Usage for".bright_white().bold(),
+        canister_id.bright_blue(),
+        format!("(window: {})", window).bright_black()
+    );
+    println!(
+        "{} calls, {} errors\n",
+        stats.total_calls().to_string().bright_cyan(),
+        stats.total_errors().to_string().bright_red()
+    );
+
+    if stats.tools.is_empty() {
+        println!("{}", "No tool calls recorded in this window.".yellow());
+        return;
+    }
+
+    println!(
+        "{}",
+        usage_table("Tool", &stats.tools, &stats.latency_buckets_ms)
+    );
+    println!(
+        "{}",
+        usage_table("Caller", &stats.callers, &stats.latency_buckets_ms)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(histogram: Vec<u64>) -> UsageEntry {
+        UsageEntry {
+            name: "search".to_string(),
+            calls: histogram.iter().sum(),
+            errors: 0,
+            error_rate: 0.0,
+            avg_latency_ms: 0.0,
+            latency_histogram: histogram,
+        }
+    }
+
+    #[test]
+    fn test_format_uptime() {
+        assert_eq!(format_uptime(59), "0m");
+        assert_eq!(format_uptime(3_660), "1h 1m");
+        assert_eq!(format_uptime(2 * 86_400 + 3 * 3_600 + 15 * 60), "2d 3h 15m");
+    }
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!(parse_window("30m").unwrap(), Some(1_800));
+        assert_eq!(parse_window("24h").unwrap(), Some(86_400));
+        assert_eq!(parse_window("7d").unwrap(), Some(604_800));
+        assert_eq!(parse_window("90").unwrap(), Some(90));
+        assert_eq!(parse_window("all").unwrap(), None);
+        assert!(parse_window("soon").is_err());
+    }
+
+    #[test]
+    fn test_percentile_label() {
+        let buckets = [1, 5, 10];
+        let search = entry(vec![5, 4, 0, 1]);
+        assert_eq!(percentile_label(&search, &buckets, 0.5), "<1ms");
+        assert_eq!(percentile_label(&search, &buckets, 0.9), "<5ms");
+        assert_eq!(percentile_label(&search, &buckets, 0.95), ">10ms");
+        assert_eq!(percentile_label(&entry(vec![]), &buckets, 0.5), "-");
+    }
+}
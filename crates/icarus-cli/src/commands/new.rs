@@ -4,7 +4,8 @@ use std::path::{Path, PathBuf};
 use tokio::fs;
 use tracing::{info, warn};
 
-use crate::templates::basic;
+use crate::templates::custom::{self, TemplateSource, TemplateVariables};
+use crate::templates::{agent_memory, basic};
 use crate::utils::git;
 use crate::{commands::NewArgs, Cli};
 
@@ -13,6 +14,7 @@ pub(crate) async fn execute(args: NewArgs, cli: &Cli) -> Result<()> {
 
     // Validate project name
     validate_project_name(&args.name)?;
+    let template = TemplateSource::parse(&args.template)?;
 
     // Determine project path
     let project_path = determine_project_path(&args)?;
@@ -40,10 +42,24 @@ pub(crate) async fn execute(args: NewArgs, cli: &Cli) -> Result<()> {
         println!("{}", "  Generating project files...".bright_blue());
     }
 
-    // Generate basic project template
-    basic::generate_project(&args.name, &project_path)
-        .await
-        .with_context(|| "Failed to generate project files")?;
+    match template {
+        TemplateSource::Builtin(agent_memory::NAME) => {
+            agent_memory::generate_project(&args.name, &project_path)
+                .await
+                .with_context(|| "Failed to generate project files")?;
+        }
+        TemplateSource::Builtin(_) => {
+            basic::generate_project(&args.name, &project_path)
+                .await
+                .with_context(|| "Failed to generate project files")?;
+        }
+        _ => {
+            let vars = TemplateVariables::for_project(&args.name).await;
+            custom::generate_project(&template, &vars, &project_path)
+                .await
+                .with_context(|| format!("Failed to generate project from '{}'", args.template))?;
+        }
+    }
 
     // Initialize git repository if requested
     if !args.no_git {
@@ -193,6 +209,7 @@ mod tests {
             path: Some(temp_dir.path().to_path_buf()),
             no_git: false,
             no_install: false,
+            template: "basic".to_string(),
         };
 
         let project_path = determine_project_path(&args).unwrap();
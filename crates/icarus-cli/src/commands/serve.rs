@@ -0,0 +1,372 @@
+//! `icarus serve --native`: the project's tools as a local MCP server.
+//!
+//! The project's library is built for the host instead of wasm32, linked
+//! into a small generated binary under `target/icarus-native/` and run with
+//! stdio inherited, so an AI client can launch `icarus serve --native` as
+//! an ordinary stdio MCP server. No canister, dfx or bridge is involved.
+//!
+//! Stdout carries the MCP messages; everything this command prints goes to
+//! stderr.
+
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+use std::path::Path;
+use tokio::process::Command;
+use toml::{map::Map, Value};
+
+use crate::commands::ServeArgs;
+use crate::utils::project;
+use crate::Cli;
+
+/// Directory of the generated server crate, under the project's `target/`
+const HARNESS_DIR: &str = "target/icarus-native";
+
+/// candid version added when the project does not depend on it directly
+const CANDID_VERSION: &str = "0.10";
+
+pub(crate) async fn execute(args: ServeArgs, cli: &Cli) -> Result<()> {
+    if !args.native {
+        bail!(
+            "`icarus serve` runs the project's tools natively; pass --native. \
+             To serve a deployed canister, use `icarus mcp start`"
+        );
+    }
+    if let Some(caller) = &args.caller {
+        candid::Principal::from_text(caller)
+            .map_err(|e| anyhow!("Invalid --caller principal '{caller}': {e}"))?;
+    }
+
+    let project_root = project::find_project_root()?;
+    let manifest_path = project_root.join("Cargo.toml");
+    let manifest = tokio::fs::read_to_string(&manifest_path)
+        .await
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+    let manifest: Value = toml::from_str(&manifest).context("Failed to parse Cargo.toml")?;
+
+    let harness = NativeHarness::new(&manifest, &project_root, args.caller.as_deref())?;
+    let harness_dir = project_root.join(HARNESS_DIR);
+    harness.write(&harness_dir).await?;
+
+    if !cli.quiet {
+        eprintln!(
+            "{} Serving the tools of {} over stdio",
+            "→".bright_blue(),
+            harness.package.bright_cyan()
+        );
+    }
+
+    let mut command = Command::new("cargo");
+    command
+        .arg("run")
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(harness_dir.join("Cargo.toml"))
+        .env("CARGO_TARGET_DIR", harness_dir.join("target"));
+    if args.release {
+        command.arg("--release");
+    }
+    let status = command
+        .status()
+        .await
+        .context("Failed to run cargo. Is it installed?")?;
+    if !status.success() {
+        bail!("The native server exited with {status}");
+    }
+    Ok(())
+}
+
+/// The generated crate that serves a project's tools.
+#[derive(Debug)]
+struct NativeHarness {
+    /// Name of the project package
+    package: String,
+    /// Contents of the generated Cargo.toml
+    manifest: String,
+    /// Contents of the generated src/main.rs
+    main_rs: String,
+}
+
+impl NativeHarness {
+    /// Builds the server crate for the project with manifest `project`.
+    ///
+    /// The project's library becomes the library of the server crate, with
+    /// the same dependencies and features, so no change to the project is
+    /// needed.
+    fn new(project: &Value, project_root: &Path, caller: Option<&str>) -> Result<Self> {
+        let package = project
+            .get("package")
+            .ok_or_else(|| anyhow!("No [package] section found in Cargo.toml"))?;
+        let name = package
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("No package name found in Cargo.toml"))?;
+        let version = package
+            .get("version")
+            .and_then(Value::as_str)
+            .unwrap_or("0.1.0");
+        let edition = package
+            .get("edition")
+            .and_then(Value::as_str)
+            .unwrap_or("2021");
+
+        let lib = project.get("lib");
+        let lib_name = lib
+            .and_then(|lib| lib.get("name"))
+            .and_then(Value::as_str)
+            .map_or_else(|| name.replace('-', "_"), str::to_string);
+        let lib_path = lib
+            .and_then(|lib| lib.get("path"))
+            .and_then(Value::as_str)
+            .unwrap_or("src/lib.rs");
+        let lib_path = project_root.join(lib_path);
+
+        let dependencies = native_dependencies(project, project_root)?;
+        let bin_name = format!("{name}-native");
+
+        let mut manifest = Map::new();
+        manifest.insert(
+            "package".to_string(),
+            table([
+                ("name", Value::from(bin_name.as_str())),
+                ("version", Value::from(version)),
+                ("edition", Value::from(edition)),
+                ("publish", Value::from(false)),
+            ]),
+        );
+        manifest.insert(
+            "lib".to_string(),
+            table([
+                ("name", Value::from(lib_name.as_str())),
+                ("path", path_value(&lib_path)),
+                ("crate-type", Value::from(vec!["rlib"])),
+            ]),
+        );
+        manifest.insert(
+            "bin".to_string(),
+            Value::Array(vec![table([
+                ("name", Value::from(bin_name.as_str())),
+                ("path", Value::from("src/main.rs")),
+            ])]),
+        );
+        manifest.insert("dependencies".to_string(), Value::Table(dependencies));
+        if let Some(features) = project.get("features") {
+            manifest.insert("features".to_string(), features.clone());
+        }
+        // Not a member of any workspace above the project's target directory
+        manifest.insert("workspace".to_string(), Value::Table(Map::new()));
+
+        let owner = caller
+            .map(|caller| {
+                format!(
+                    "\n        .with_owner(candid::Principal::from_text(\"{caller}\").expect(\"checked by icarus serve\"))"
+                )
+            })
+            .unwrap_or_default();
+        let main_rs = format!(
+            "//! Generated by `icarus serve --native`; changes are overwritten.\n\
+             \n\
+             // Links the project's tools into the registry\n\
+             use {lib_name} as _;\n\
+             \n\
+             fn main() -> std::io::Result<()> {{\n    \
+                 icarus::native::NativeServer::new(\"{name}\", \"{version}\"){owner}\n        \
+                 .serve_stdio()\n\
+             }}\n"
+        );
+
+        Ok(Self {
+            package: name.to_string(),
+            manifest: toml::to_string(&manifest)?,
+            main_rs,
+        })
+    }
+
+    /// Writes the crate to `dir`, leaving files that did not change untouched
+    /// so cargo does not rebuild them.
+    async fn write(&self, dir: &Path) -> Result<()> {
+        let src = dir.join("src");
+        tokio::fs::create_dir_all(&src)
+            .await
+            .with_context(|| format!("Failed to create {}", src.display()))?;
+        for (path, contents) in [
+            (dir.join("Cargo.toml"), &self.manifest),
+            (src.join("main.rs"), &self.main_rs),
+        ] {
+            if tokio::fs::read_to_string(&path).await.ok().as_ref() == Some(contents) {
+                continue;
+            }
+            tokio::fs::write(&path, contents)
+                .await
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the project's dependencies with paths made absolute, the `native`
+/// feature of `icarus` on and `candid` present.
+fn native_dependencies(project: &Value, project_root: &Path) -> Result<Map<String, Value>> {
+    let mut dependencies = project
+        .get("dependencies")
+        .and_then(Value::as_table)
+        .cloned()
+        .unwrap_or_default();
+
+    for (name, spec) in &mut dependencies {
+        let Some(spec) = spec.as_table_mut() else {
+            continue;
+        };
+        if spec.contains_key("workspace") {
+            bail!(
+                "Dependency `{name}` is inherited from a workspace, which `icarus serve --native` \
+                 cannot resolve; give it a version or path in the project's Cargo.toml"
+            );
+        }
+        if let Some(path) = spec.get("path").and_then(Value::as_str) {
+            let path = project_root.join(path);
+            spec.insert("path".to_string(), path_value(&path));
+        }
+    }
+
+    let icarus = dependencies
+        .get_mut("icarus")
+        .ok_or_else(|| anyhow!("`icarus` is not a dependency of this project"))?;
+    if let Value::String(version) = icarus {
+        *icarus = table([("version", Value::from(version.as_str()))]);
+    }
+    if let Some(spec) = icarus.as_table_mut() {
+        let features = spec
+            .entry("features")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Some(features) = features.as_array_mut() {
+            if !features
+                .iter()
+                .any(|feature| feature.as_str() == Some("native"))
+            {
+                features.push(Value::from("native"));
+            }
+        }
+    }
+
+    dependencies
+        .entry("candid")
+        .or_insert_with(|| Value::from(CANDID_VERSION));
+    Ok(dependencies)
+}
+
+fn table<const N: usize>(entries: [(&str, Value); N]) -> Value {
+    Value::Table(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+fn path_value(path: &Path) -> Value {
+    Value::from(path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(dependencies: &str) -> Value {
+        toml::from_str(&format!(
+            r#"
+[package]
+name = "notes-app"
+version = "0.2.0"
+edition = "2021"
+
+[lib]
+crate-type = ["cdylib"]
+
+[dependencies]
+{dependencies}
+
+[features]
+search = []
+"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_harness_links_the_project_library() {
+        let root = Path::new("/work/notes-app");
+        let harness = NativeHarness::new(
+            &project(
+                "icarus = \"0.9\"\nshared = { path = \"../shared\" }\nserde = { version = \"1\" }",
+            ),
+            root,
+            None,
+        )
+        .unwrap();
+        let manifest: Value = toml::from_str(&harness.manifest).unwrap();
+
+        assert_eq!(
+            manifest["package"]["name"].as_str(),
+            Some("notes-app-native")
+        );
+        assert_eq!(manifest["lib"]["name"].as_str(), Some("notes_app"));
+        assert_eq!(
+            manifest["lib"]["path"].as_str(),
+            Some("/work/notes-app/src/lib.rs")
+        );
+        assert_eq!(manifest["lib"]["crate-type"][0].as_str(), Some("rlib"));
+        assert_eq!(
+            manifest["dependencies"]["shared"]["path"].as_str(),
+            Some("/work/notes-app/../shared")
+        );
+        assert_eq!(
+            manifest["dependencies"]["icarus"]["features"][0].as_str(),
+            Some("native")
+        );
+        assert_eq!(manifest["dependencies"]["candid"].as_str(), Some("0.10"));
+        assert!(manifest["features"].get("search").is_some());
+        assert!(manifest["workspace"].as_table().unwrap().is_empty());
+
+        assert!(harness.main_rs.contains("use notes_app as _;"));
+        assert!(harness
+            .main_rs
+            .contains("NativeServer::new(\"notes-app\", \"0.2.0\")"));
+        assert!(!harness.main_rs.contains("with_owner"));
+    }
+
+    #[test]
+    fn test_harness_calls_as_the_owner() {
+        let harness = NativeHarness::new(
+            &project("icarus = { version = \"0.9\", features = [\"macros\"] }"),
+            Path::new("/work/notes-app"),
+            Some("aaaaa-aa"),
+        )
+        .unwrap();
+        let manifest: Value = toml::from_str(&harness.manifest).unwrap();
+
+        let features = manifest["dependencies"]["icarus"]["features"]
+            .as_array()
+            .unwrap();
+        assert_eq!(features.len(), 2);
+        assert!(harness
+            .main_rs
+            .contains("with_owner(candid::Principal::from_text(\"aaaaa-aa\")"));
+    }
+
+    #[test]
+    fn test_harness_requirements() {
+        let root = Path::new("/work/notes-app");
+        let err = NativeHarness::new(&project("serde = \"1\""), root, None).unwrap_err();
+        assert!(err.to_string().contains("`icarus` is not a dependency"));
+
+        let err = NativeHarness::new(
+            &project("icarus = \"0.9\"\nserde = { workspace = true }"),
+            root,
+            None,
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("`serde` is inherited from a workspace"));
+    }
+}
@@ -0,0 +1,143 @@
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use comfy_table::{presets::UTF8_FULL, Table};
+use tokio::fs;
+use tracing::info;
+
+use crate::commands::{TemplateAddArgs, TemplateArgs, TemplateRemoveArgs};
+use crate::config::templates::{TemplateEntry, TemplateRegistry};
+use crate::templates::custom::{self, TemplateSource};
+use crate::utils::git;
+use crate::Cli;
+
+pub(crate) async fn execute(args: TemplateArgs, cli: &Cli) -> Result<()> {
+    match args {
+        TemplateArgs::Add(args) => add(args, cli).await,
+        TemplateArgs::List => list(cli).await,
+        TemplateArgs::Remove(args) => remove(args, cli).await,
+    }
+}
+
+async fn add(args: TemplateAddArgs, cli: &Cli) -> Result<()> {
+    if custom::BUILTINS.contains(&args.name.as_str()) {
+        return Err(anyhow!(
+            "'{}' is the name of the builtin template",
+            args.name
+        ));
+    }
+
+    let mut registry = TemplateRegistry::load().await?;
+    if registry.get(&args.name).is_some() && !cli.force {
+        return Err(anyhow!(
+            "Template '{}' is already registered. Use --force to replace it.",
+            args.name
+        ));
+    }
+
+    let path = match TemplateSource::parse(&args.source)? {
+        TemplateSource::Git { url, reference } => {
+            let dir = TemplateRegistry::checkout_dir(&args.name)?;
+            if dir.exists() {
+                fs::remove_dir_all(&dir)
+                    .await
+                    .with_context(|| format!("Failed to remove {}", dir.display()))?;
+            }
+            if !cli.quiet {
+                println!("{} Cloning {}...", "→".bright_blue(), url.bright_cyan());
+            }
+            git::clone_repository(&url, &dir, reference.as_deref()).await?;
+            dir
+        }
+        TemplateSource::Local(dir) => {
+            if !dir.is_dir() {
+                return Err(anyhow!("Template directory not found: {}", dir.display()));
+            }
+            dir.canonicalize()
+                .with_context(|| format!("Failed to resolve {}", dir.display()))?
+        }
+        TemplateSource::Builtin(_) | TemplateSource::Registered(_) => {
+            return Err(anyhow!(
+                "'{}' is not a template source: expected gh:<owner>/<repo>, a git URL or a directory",
+                args.source
+            ));
+        }
+    };
+
+    registry.insert(
+        &args.name,
+        TemplateEntry {
+            source: args.source.clone(),
+            description: args.description,
+            path,
+        },
+    );
+    registry.save().await?;
+
+    info!("Registered template {} from {}", args.name, args.source);
+    if !cli.quiet {
+        println!(
+            "{} Registered template {}",
+            "✅".green(),
+            args.name.bright_cyan()
+        );
+        println!(
+            "  {} icarus new <name> --template {}",
+            "→".bright_blue(),
+            args.name
+        );
+    }
+    Ok(())
+}
+
+async fn list(cli: &Cli) -> Result<()> {
+    let registry = TemplateRegistry::load().await?;
+
+    if registry.templates.is_empty() {
+        if !cli.quiet {
+            println!("{}", "No templates registered.".yellow());
+            println!("Use 'icarus template add <name> <source>' to register one.");
+        }
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec![
+        "Name".bright_white().bold(),
+        "Source".bright_white().bold(),
+        "Description".bright_white().bold(),
+    ]);
+    for (name, entry) in &registry.templates {
+        table.add_row(vec![
+            name.clone(),
+            entry.source.clone(),
+            entry.description.clone().unwrap_or_default(),
+        ]);
+    }
+    println!("{table}");
+    Ok(())
+}
+
+async fn remove(args: TemplateRemoveArgs, cli: &Cli) -> Result<()> {
+    let mut registry = TemplateRegistry::load().await?;
+    let entry = registry
+        .remove(&args.name)
+        .ok_or_else(|| anyhow!("Template '{}' is not registered", args.name))?;
+
+    // Only clones made by `template add` are deleted, never a user's directory
+    if entry.path == TemplateRegistry::checkout_dir(&args.name)? && entry.path.exists() {
+        fs::remove_dir_all(&entry.path)
+            .await
+            .with_context(|| format!("Failed to remove {}", entry.path.display()))?;
+    }
+    registry.save().await?;
+
+    if !cli.quiet {
+        println!(
+            "{} Removed template {}",
+            "✅".green(),
+            args.name.bright_cyan()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,311 @@
+//! State of bridges started with `icarus mcp start --daemon`
+//!
+//! Each daemon is an instance named after the canister it serves, or
+//! `bridge` when it serves every registered canister. Its state file sits
+//! next to the other Icarus config and records the PID, address, start time,
+//! log file and the last error it reported, for `icarus mcp status` and
+//! `icarus mcp stop --canister-id` to find it.
+//!
+//! ```json
+//! {
+//!   "instance": "rdmx6-jaaaa-aaaaa-aaadq-cai",
+//!   "canister_id": "rdmx6-jaaaa-aaaaa-aaadq-cai",
+//!   "pid": 4242,
+//!   "host": "localhost",
+//!   "port": 3000,
+//!   "started_at": "2026-10-16T09:30:00Z",
+//!   "log_file": "/home/me/.config/icarus/logs/rdmx6-jaaaa-aaaaa-aaadq-cai.log",
+//!   "last_error": null,
+//!   "restarts": 0
+//! }
+//! ```
+//!
+//! Daemon output goes to `<instance>.log` in the log directory. Each start
+//! rotates the previous logs to `<instance>.log.1`, `<instance>.log.2` and so
+//! on, keeping [`KEPT_LOGS`] of them.
+
+#![allow(dead_code)]
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Instance name of a daemon serving every registered canister
+pub const ALL_CANISTERS: &str = "bridge";
+
+/// Rotated logs kept per instance, besides the current one
+pub const KEPT_LOGS: u32 = 5;
+
+/// A running (or crashed) bridge daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonState {
+    /// Name of the instance, also the name of its state and log files
+    pub instance: String,
+    /// Canister the daemon serves, if only one
+    pub canister_id: Option<String>,
+    /// Process ID of the daemon
+    pub pid: u32,
+    /// Host the bridge listens on
+    pub host: String,
+    /// Port the bridge listens on
+    pub port: u16,
+    /// When the daemon was started
+    pub started_at: DateTime<Utc>,
+    /// File the daemon writes its output to
+    pub log_file: Option<PathBuf>,
+    /// Last error the daemon reported
+    pub last_error: Option<String>,
+    /// Times the supervisor restarted the bridge
+    #[serde(default)]
+    pub restarts: u32,
+}
+
+impl DaemonState {
+    pub(crate) fn new(canister_id: Option<&str>, pid: u32, host: &str, port: u16) -> Self {
+        Self {
+            instance: instance_name(canister_id),
+            canister_id: canister_id.map(str::to_string),
+            pid,
+            host: host.to_string(),
+            port,
+            started_at: Utc::now(),
+            log_file: None,
+            last_error: None,
+            restarts: 0,
+        }
+    }
+
+    /// Seconds since the daemon was started
+    pub(crate) fn uptime_secs(&self, now: DateTime<Utc>) -> u64 {
+        u64::try_from((now - self.started_at).num_seconds()).unwrap_or(0)
+    }
+
+    pub(crate) async fn save_in(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("Failed to create daemon directory: {}", dir.display()))?;
+
+        let content = serde_json::to_string_pretty(self)
+            .with_context(|| "Failed to serialize daemon state")?;
+
+        let path = state_file(dir, &self.instance);
+        fs::write(&path, content)
+            .await
+            .with_context(|| format!("Failed to write daemon state: {}", path.display()))
+    }
+
+    /// Loads the state of `instance`, or `None` if it has none
+    pub(crate) async fn load_in(dir: &Path, instance: &str) -> Result<Option<Self>> {
+        let path = state_file(dir, instance);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read daemon state: {}", path.display()))?;
+
+        serde_json::from_str(&content)
+            .map(Some)
+            .with_context(|| format!("Failed to parse daemon state: {}", path.display()))
+    }
+
+    /// Loads the state of every daemon, sorted by instance, skipping
+    /// unreadable files
+    pub(crate) async fn load_all_in(dir: &Path) -> Result<Vec<Self>> {
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut states = Vec::new();
+        let mut entries = fs::read_dir(dir)
+            .await
+            .with_context(|| format!("Failed to read daemon directory: {}", dir.display()))?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(&path).await else {
+                continue;
+            };
+            if let Ok(state) = serde_json::from_str::<Self>(&content) {
+                states.push(state);
+            }
+        }
+
+        states.sort_by(|a, b| a.instance.cmp(&b.instance));
+        Ok(states)
+    }
+
+    /// Deletes the state of `instance`, if any
+    pub(crate) async fn remove_in(dir: &Path, instance: &str) -> Result<()> {
+        match fs::remove_file(state_file(dir, instance)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to remove daemon state of {instance}"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Records an error reported by the daemon of `instance` running as
+    /// this process, counting it as a restart if `restarted`
+    pub(crate) async fn record_error_in(
+        dir: &Path,
+        instance: &str,
+        error: &str,
+        restarted: bool,
+    ) -> Result<()> {
+        let Some(mut state) = Self::load_in(dir, instance).await? else {
+            return Ok(());
+        };
+        if state.pid != std::process::id() {
+            return Ok(());
+        }
+
+        state.last_error = Some(error.to_string());
+        if restarted {
+            state.restarts += 1;
+        }
+        state.save_in(dir).await
+    }
+
+    /// Get the directory of daemon state files
+    pub(crate) fn state_dir() -> Result<PathBuf> {
+        Ok(icarus_dir()?.join("daemons"))
+    }
+
+    /// Get the default directory of daemon logs
+    pub(crate) fn default_log_dir() -> Result<PathBuf> {
+        Ok(icarus_dir()?.join("logs"))
+    }
+}
+
+/// Returns the instance name of a daemon serving `canister_id`, or every
+/// registered canister
+pub(crate) fn instance_name(canister_id: Option<&str>) -> String {
+    match canister_id {
+        Some(canister_id) => canister_id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect(),
+        None => ALL_CANISTERS.to_string(),
+    }
+}
+
+/// Returns the current log file of `instance` in `log_dir`
+pub(crate) fn log_file(log_dir: &Path, instance: &str) -> PathBuf {
+    log_dir.join(format!("{instance}.log"))
+}
+
+/// Moves the logs of `instance` one generation back, dropping the oldest,
+/// and returns the now free current log file
+pub(crate) async fn rotate_logs(log_dir: &Path, instance: &str) -> Result<PathBuf> {
+    fs::create_dir_all(log_dir)
+        .await
+        .with_context(|| format!("Failed to create log directory: {}", log_dir.display()))?;
+
+    let current = log_file(log_dir, instance);
+    let generation = |n: u32| log_dir.join(format!("{instance}.log.{n}"));
+
+    let _ = fs::remove_file(generation(KEPT_LOGS)).await;
+    for n in (1..KEPT_LOGS).rev() {
+        let from = generation(n);
+        if from.exists() {
+            fs::rename(&from, generation(n + 1)).await?;
+        }
+    }
+    if current.exists() {
+        fs::rename(&current, generation(1))
+            .await
+            .map_err(|e| anyhow!("Failed to rotate {}: {}", current.display(), e))?;
+    }
+
+    Ok(current)
+}
+
+fn icarus_dir() -> Result<PathBuf> {
+    let config_dir =
+        dirs::config_dir().ok_or_else(|| anyhow!("Could not determine config directory"))?;
+    Ok(config_dir.join("icarus"))
+}
+
+fn state_file(dir: &Path, instance: &str) -> PathBuf {
+    dir.join(format!("{instance}.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const CANISTER: &str = "rdmx6-jaaaa-aaaaa-aaadq-cai";
+
+    #[tokio::test]
+    async fn test_state_round_trip() {
+        let dir = TempDir::new().unwrap();
+        assert!(DaemonState::load_all_in(dir.path())
+            .await
+            .unwrap()
+            .is_empty());
+
+        let state = DaemonState::new(Some(CANISTER), std::process::id(), "localhost", 3000);
+        assert_eq!(state.instance, CANISTER);
+        state.save_in(dir.path()).await.unwrap();
+        DaemonState::new(None, 1, "localhost", 3001)
+            .save_in(dir.path())
+            .await
+            .unwrap();
+
+        DaemonState::record_error_in(dir.path(), CANISTER, "Port closed", true)
+            .await
+            .unwrap();
+        // Errors of other processes are not recorded
+        DaemonState::record_error_in(dir.path(), ALL_CANISTERS, "Port closed", true)
+            .await
+            .unwrap();
+
+        let states = DaemonState::load_all_in(dir.path()).await.unwrap();
+        assert_eq!(states.len(), 2);
+        assert_eq!(states[0].instance, ALL_CANISTERS);
+        assert_eq!(states[0].last_error, None);
+        assert_eq!(states[1].last_error.as_deref(), Some("Port closed"));
+        assert_eq!(states[1].restarts, 1);
+
+        DaemonState::remove_in(dir.path(), CANISTER).await.unwrap();
+        DaemonState::remove_in(dir.path(), CANISTER).await.unwrap();
+        assert!(DaemonState::load_in(dir.path(), CANISTER)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rotate_logs_keeps_generations() {
+        let dir = TempDir::new().unwrap();
+        for start in 0..=KEPT_LOGS + 1 {
+            let current = rotate_logs(dir.path(), "bridge").await.unwrap();
+            assert!(!current.exists());
+            fs::write(&current, start.to_string()).await.unwrap();
+        }
+
+        let newest = fs::read_to_string(dir.path().join("bridge.log.1"))
+            .await
+            .unwrap();
+        assert_eq!(newest, KEPT_LOGS.to_string());
+        let oldest = dir.path().join(format!("bridge.log.{KEPT_LOGS}"));
+        assert_eq!(fs::read_to_string(oldest).await.unwrap(), "1");
+        assert!(!dir
+            .path()
+            .join(format!("bridge.log.{}", KEPT_LOGS + 1))
+            .exists());
+    }
+}
@@ -0,0 +1,278 @@
+//! Project configuration in icarus.toml
+//!
+//! One file next to the project's Cargo.toml holds the settings that used to
+//! be split between `mcp!{}` arguments and CLI flags. The `mcp!{}` macro
+//! reads `[service]`, `[features]` and `[limits]` at compile time; the CLI reads
+//! `[deploy]` and `[bridge]` for defaults of flags that were not given.
+//!
+//! ```toml
+//! [service]
+//! name = "weather"
+//! description = "Forecasts for AI assistants"
+//! version = "1.2.0"
+//! prefix = "weather_"
+//!
+//! [features]
+//! auth = true
+//! rate_limit = true
+//!
+//! [limits]
+//! max_request_bytes = 262144
+//!
+//! [deploy]
+//! network = "ic"
+//! with_cycles = 2000000000000
+//!
+//! [bridge]
+//! identity = "weather-ops"
+//! redact = ["$.params.arguments.api_token"]
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+use crate::utils::rpc_log::JsonPath;
+
+/// Name of the project configuration file
+pub const FILE_NAME: &str = "icarus.toml";
+
+/// Networks `icarus deploy` accepts
+const NETWORKS: [&str; 4] = ["local", "ic", "testnet", "pocket-ic"];
+
+/// Size limit of Internet Computer messages, which caps `[limits]`
+const MAX_MESSAGE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Contents of icarus.toml
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IcarusToml {
+    #[serde(default)]
+    pub service: ServiceSection,
+    #[serde(default)]
+    pub features: FeaturesSection,
+    #[serde(default)]
+    pub limits: LimitsSection,
+    #[serde(default)]
+    pub deploy: DeploySection,
+    #[serde(default)]
+    pub bridge: BridgeSection,
+}
+
+/// `[service]`: how the canister describes itself to MCP clients
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServiceSection {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    /// Prepended to every tool name
+    pub prefix: Option<String>,
+}
+
+/// `[features]`: the optional parts of `mcp!{}`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeaturesSection {
+    pub auth: Option<bool>,
+    pub rate_limit: Option<bool>,
+    pub metrics: Option<bool>,
+    pub blobs: Option<bool>,
+    pub events: Option<bool>,
+    pub jobs: Option<bool>,
+    pub secrets: Option<bool>,
+    pub signing: Option<bool>,
+    pub encryption: Option<bool>,
+    pub inspect: Option<bool>,
+    pub http_tools: Option<bool>,
+}
+
+/// `[limits]`: size limits of tool calls in `mcp!{}`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitsSection {
+    /// Largest `tools/call` request the canister accepts, in bytes
+    pub max_request_bytes: Option<u64>,
+    /// Largest `tools/call` response the canister returns, in bytes
+    pub max_response_bytes: Option<u64>,
+}
+
+/// `[deploy]`: defaults for `icarus deploy`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeploySection {
+    pub network: Option<String>,
+    pub with_cycles: Option<u64>,
+}
+
+/// `[bridge]`: defaults for `icarus mcp start`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BridgeSection {
+    /// dfx identity the bridge calls canisters with
+    pub identity: Option<String>,
+    /// JSONPath rules of fields to redact from `--log-rpc` logs
+    pub redact: Option<Vec<String>>,
+}
+
+impl IcarusToml {
+    /// Path of icarus.toml in `project_root`
+    pub fn path(project_root: &Path) -> PathBuf {
+        project_root.join(FILE_NAME)
+    }
+
+    /// Loads icarus.toml from `project_root`, or `None` if there is none
+    ///
+    /// The file is parsed against the schema, so unknown keys and values of
+    /// the wrong type are errors; see [`IcarusToml::validate`] for the rest.
+    pub async fn load(project_root: &Path) -> Result<Option<Self>> {
+        Self::load_from(&Self::path(project_root)).await
+    }
+
+    pub async fn load_from(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        Self::parse(&content)
+            .map(Some)
+            .with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    /// Problems the schema cannot express, one message per problem
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self
+            .service
+            .name
+            .as_deref()
+            .is_some_and(|name| name.trim().is_empty())
+        {
+            problems.push("service.name must not be empty".to_string());
+        }
+        if let Some(version) = &self.service.version {
+            if !is_semver(version) {
+                problems.push(format!(
+                    "service.version '{version}' is not a MAJOR.MINOR.PATCH version"
+                ));
+            }
+        }
+        if let Some(prefix) = &self.service.prefix {
+            if !is_valid_prefix(prefix) {
+                problems.push(format!(
+                    "service.prefix '{prefix}' must start with an ASCII letter and contain \
+                     only ASCII letters, digits, '_', '.' or '-'"
+                ));
+            }
+        }
+        for (key, bytes) in [
+            ("limits.max_request_bytes", self.limits.max_request_bytes),
+            ("limits.max_response_bytes", self.limits.max_response_bytes),
+        ] {
+            if bytes.is_some_and(|bytes| bytes == 0 || bytes > MAX_MESSAGE_BYTES) {
+                problems.push(format!(
+                    "{key} must be between 1 and {MAX_MESSAGE_BYTES} bytes"
+                ));
+            }
+        }
+        if let Some(network) = &self.deploy.network {
+            if !NETWORKS.contains(&network.as_str()) {
+                problems.push(format!(
+                    "deploy.network '{network}' is not one of {}",
+                    NETWORKS.join(", ")
+                ));
+            }
+        }
+        if self
+            .bridge
+            .identity
+            .as_deref()
+            .is_some_and(|identity| identity.trim().is_empty())
+        {
+            problems.push("bridge.identity must not be empty".to_string());
+        }
+        for rule in self.bridge.redact.iter().flatten() {
+            if let Err(e) = JsonPath::parse(rule) {
+                problems.push(format!("bridge.redact: {e}"));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Same rule `mcp!{}` applies, so a valid file compiles
+fn is_valid_prefix(prefix: &str) -> bool {
+    prefix.starts_with(|c: char| c.is_ascii_alphabetic())
+        && prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+fn is_semver(version: &str) -> bool {
+    let core = version.split(['-', '+']).next().unwrap_or_default();
+    let parts: Vec<&str> = core.split('.').collect();
+    parts.len() == 3
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_load_parses_all_sections() {
+        let dir = TempDir::new().unwrap();
+        assert!(IcarusToml::load(dir.path()).await.unwrap().is_none());
+
+        fs::write(
+            IcarusToml::path(dir.path()),
+            "[service]\nname = \"weather\"\nversion = \"1.2.0-beta.1\"\n\n\
+             [features]\nauth = true\n\n[deploy]\nnetwork = \"ic\"\n\n\
+             [bridge]\nidentity = \"ops\"\n",
+        )
+        .await
+        .unwrap();
+
+        let config = IcarusToml::load(dir.path()).await.unwrap().unwrap();
+        assert_eq!(config.service.name.as_deref(), Some("weather"));
+        assert_eq!(config.features.auth, Some(true));
+        assert_eq!(config.deploy.network.as_deref(), Some("ic"));
+        assert_eq!(config.bridge.identity.as_deref(), Some("ops"));
+        assert!(config.validate().is_empty());
+    }
+
+    #[test]
+    fn test_schema_and_validation_errors() {
+        assert!(IcarusToml::parse("[service]\ntitle = \"weather\"\n").is_err());
+        assert!(IcarusToml::parse("[features]\nauth = \"yes\"\n").is_err());
+        assert!(IcarusToml::parse("[storage]\n").is_err());
+
+        let config = IcarusToml::parse(
+            "[service]\nversion = \"1.2\"\nprefix = \"1x\"\n\n[limits]\nmax_response_bytes = 0\n\n\
+             [deploy]\nnetwork = \"mainnet\"\n\n\
+             [bridge]\nredact = [\"$..token\", \"params.key\"]\n",
+        )
+        .unwrap();
+        let problems = config.validate();
+        assert_eq!(problems.len(), 5);
+        assert!(problems[0].contains("service.version"));
+        assert!(problems[1].contains("service.prefix"));
+        assert!(problems[2].contains("limits.max_response_bytes"));
+        assert!(problems[3].contains("deploy.network"));
+        assert!(problems[4].contains("params.key"));
+    }
+}
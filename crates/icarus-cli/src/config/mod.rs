@@ -1,2 +1,12 @@
 #[doc(hidden)]
+pub mod daemon;
+#[doc(hidden)]
+pub mod icarus_toml;
+#[doc(hidden)]
 pub mod mcp;
+#[doc(hidden)]
+pub mod profiles;
+#[doc(hidden)]
+pub mod templates;
+#[doc(hidden)]
+pub mod tool_snapshot;
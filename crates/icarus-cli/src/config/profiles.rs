@@ -0,0 +1,172 @@
+//! Identity profiles: which dfx identity the bridge uses for each canister
+//!
+//! The profiles file lets one machine bridge to canisters owned by different
+//! identities without switching the global dfx identity. `icarus mcp add
+//! --identity <name>` records an entry, and `icarus mcp start` falls back to
+//! it when no `--identity` is given.
+//!
+//! ```json
+//! {
+//!   "profiles": {
+//!     "rdmx6-jaaaa-aaaaa-aaadq-cai": { "identity": "alice", "network": "ic" }
+//!   }
+//! }
+//! ```
+
+#![allow(dead_code)]
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tokio::fs;
+
+/// The identity to use for one canister
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentityProfile {
+    /// dfx identity name
+    pub identity: String,
+    /// Network the canister is deployed on
+    pub network: String,
+}
+
+/// Identity profiles, keyed by canister ID
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IdentityProfiles {
+    pub profiles: BTreeMap<String, IdentityProfile>,
+}
+
+impl IdentityProfiles {
+    /// Load profiles from file, or an empty set if there is none
+    pub(crate) async fn load() -> Result<Self> {
+        Self::load_from(&Self::profiles_path()?).await
+    }
+
+    /// Save profiles to file
+    pub(crate) async fn save(&self) -> Result<()> {
+        self.save_to(&Self::profiles_path()?).await
+    }
+
+    pub(crate) async fn load_from(path: &std::path::Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read profiles file: {}", path.display()))?;
+
+        serde_json::from_str(&content).with_context(|| "Failed to parse identity profiles")
+    }
+
+    pub(crate) async fn save_to(&self, path: &std::path::Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await.with_context(|| {
+                format!("Failed to create config directory: {}", parent.display())
+            })?;
+        }
+
+        let content = serde_json::to_string_pretty(self)
+            .with_context(|| "Failed to serialize identity profiles")?;
+
+        fs::write(path, content)
+            .await
+            .with_context(|| format!("Failed to write profiles file: {}", path.display()))
+    }
+
+    /// Get the profiles file path
+    pub(crate) fn profiles_path() -> Result<PathBuf> {
+        let config_dir =
+            dirs::config_dir().ok_or_else(|| anyhow!("Could not determine config directory"))?;
+
+        Ok(config_dir.join("icarus").join("profiles.json"))
+    }
+
+    /// Records `identity` for a canister, replacing any previous choice
+    pub fn set(&mut self, canister_id: &str, network: &str, identity: &str) {
+        self.profiles.insert(
+            canister_id.to_string(),
+            IdentityProfile {
+                identity: identity.to_string(),
+                network: network.to_string(),
+            },
+        );
+    }
+
+    /// Forgets the identity of a canister
+    pub fn remove(&mut self, canister_id: &str) -> Option<IdentityProfile> {
+        self.profiles.remove(canister_id)
+    }
+
+    /// The identity recorded for a canister on `network`
+    pub fn identity_for(&self, canister_id: &str, network: &str) -> Option<&str> {
+        self.profiles
+            .get(canister_id)
+            .filter(|profile| profile.network == network)
+            .map(|profile| profile.identity.as_str())
+    }
+
+    /// The identity to use: `explicit` if given, otherwise the profile's
+    pub fn resolve(
+        &self,
+        explicit: Option<&str>,
+        canister_id: &str,
+        network: &str,
+    ) -> Option<String> {
+        explicit
+            .or_else(|| self.identity_for(canister_id, network))
+            .map(str::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const CANISTER: &str = "rdmx6-jaaaa-aaaaa-aaadq-cai";
+
+    #[test]
+    fn test_resolve_identity() {
+        let mut profiles = IdentityProfiles::default();
+        profiles.set(CANISTER, "ic", "alice");
+
+        assert_eq!(profiles.identity_for(CANISTER, "ic"), Some("alice"));
+        assert_eq!(profiles.identity_for(CANISTER, "local"), None);
+        assert_eq!(
+            profiles.resolve(None, CANISTER, "ic").as_deref(),
+            Some("alice")
+        );
+        assert_eq!(
+            profiles.resolve(Some("bob"), CANISTER, "ic").as_deref(),
+            Some("bob")
+        );
+        assert!(profiles
+            .resolve(None, "rrkah-fqaaa-aaaaa-aaaaq-cai", "ic")
+            .is_none());
+
+        profiles.set(CANISTER, "ic", "carol");
+        assert_eq!(profiles.identity_for(CANISTER, "ic"), Some("carol"));
+        assert!(profiles.remove(CANISTER).is_some());
+        assert!(profiles.identity_for(CANISTER, "ic").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_profiles_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("icarus").join("profiles.json");
+
+        assert!(IdentityProfiles::load_from(&path)
+            .await
+            .unwrap()
+            .profiles
+            .is_empty());
+
+        let mut profiles = IdentityProfiles::default();
+        profiles.set(CANISTER, "local", "dev");
+        profiles.save_to(&path).await.unwrap();
+
+        let loaded = IdentityProfiles::load_from(&path).await.unwrap();
+        assert_eq!(loaded.identity_for(CANISTER, "local"), Some("dev"));
+    }
+}
@@ -0,0 +1,149 @@
+//! Registry of user-defined project templates
+//!
+//! `icarus template add <name> <source>` records a template so that `icarus
+//! new --template <name>` can use it. Git sources are cloned into the
+//! registry's directory when added, so creating a project from them needs no
+//! network; local directories are used in place.
+//!
+//! ```json
+//! {
+//!   "templates": {
+//!     "team-starter": {
+//!       "source": "gh:acme/icarus-starter",
+//!       "description": "Acme's canister layout"
+//!     }
+//!   }
+//! }
+//! ```
+
+#![allow(dead_code)]
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// One registered template
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateEntry {
+    /// Source as given to `icarus template add`
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Directory holding the template's files
+    pub path: PathBuf,
+}
+
+/// Registered templates, keyed by name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TemplateRegistry {
+    pub templates: BTreeMap<String, TemplateEntry>,
+}
+
+impl TemplateRegistry {
+    /// Load the registry, or an empty one if there is none
+    pub(crate) async fn load() -> Result<Self> {
+        Self::load_from(&Self::registry_path()?).await
+    }
+
+    /// Save the registry
+    pub(crate) async fn save(&self) -> Result<()> {
+        self.save_to(&Self::registry_path()?).await
+    }
+
+    pub(crate) async fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read template registry: {}", path.display()))?;
+
+        serde_json::from_str(&content).with_context(|| "Failed to parse template registry")
+    }
+
+    pub(crate) async fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await.with_context(|| {
+                format!("Failed to create config directory: {}", parent.display())
+            })?;
+        }
+
+        let content = serde_json::to_string_pretty(self)
+            .with_context(|| "Failed to serialize template registry")?;
+
+        fs::write(path, content)
+            .await
+            .with_context(|| format!("Failed to write template registry: {}", path.display()))
+    }
+
+    /// Get the registry file path
+    pub(crate) fn registry_path() -> Result<PathBuf> {
+        Ok(Self::config_dir()?.join("templates.json"))
+    }
+
+    /// Directory git templates are cloned into
+    pub(crate) fn checkout_dir(name: &str) -> Result<PathBuf> {
+        Ok(Self::config_dir()?.join("templates").join(name))
+    }
+
+    fn config_dir() -> Result<PathBuf> {
+        let config_dir =
+            dirs::config_dir().ok_or_else(|| anyhow!("Could not determine config directory"))?;
+
+        Ok(config_dir.join("icarus"))
+    }
+
+    /// Records a template, replacing any previous one with the same name
+    pub fn insert(&mut self, name: &str, entry: TemplateEntry) -> Option<TemplateEntry> {
+        self.templates.insert(name.to_string(), entry)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<TemplateEntry> {
+        self.templates.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TemplateEntry> {
+        self.templates.get(name)
+    }
+
+    /// Directory holding the files of template `name`
+    pub fn template_dir(&self, name: &str) -> Option<PathBuf> {
+        self.get(name).map(|entry| entry.path.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_registry_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("templates.json");
+
+        let mut registry = TemplateRegistry::load_from(&path).await.unwrap();
+        assert!(registry.templates.is_empty());
+
+        registry.insert(
+            "team-starter",
+            TemplateEntry {
+                source: "gh:acme/icarus-starter".to_string(),
+                description: Some("Acme's canister layout".to_string()),
+                path: PathBuf::from("/tmp/team-starter"),
+            },
+        );
+        registry.save_to(&path).await.unwrap();
+
+        let mut loaded = TemplateRegistry::load_from(&path).await.unwrap();
+        assert_eq!(
+            loaded.template_dir("team-starter"),
+            Some(PathBuf::from("/tmp/team-starter"))
+        );
+        assert!(loaded.remove("team-starter").is_some());
+        assert!(loaded.get("team-starter").is_none());
+    }
+}
@@ -0,0 +1,128 @@
+//! Tool metadata snapshots for bridge cold starts
+//!
+//! A client lists tools as soon as it starts the bridge and gives up if the
+//! canister is slow to answer. `icarus mcp add` saves the canister's tool list
+//! to a snapshot file next to the other Icarus config, one per canister and
+//! network, and the bridge answers the first `tools/list` from it while it
+//! fetches the live list in the background.
+//!
+//! ```json
+//! {
+//!   "canister_id": "rdmx6-jaaaa-aaaaa-aaadq-cai",
+//!   "network": "ic",
+//!   "taken_at": "2026-10-16T09:30:00Z",
+//!   "tools": [{ "name": "get_weather", "inputSchema": { "type": "object" } }]
+//! }
+//! ```
+
+#![allow(dead_code)]
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// A canister's tool list as it was listed at `taken_at`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolSnapshot {
+    /// Canister the tools belong to
+    pub canister_id: String,
+    /// Network the canister is deployed on
+    pub network: String,
+    /// When the list was fetched
+    pub taken_at: DateTime<Utc>,
+    /// Tools as the canister lists them, before any prefix is stripped
+    pub tools: Vec<serde_json::Value>,
+}
+
+impl ToolSnapshot {
+    pub(crate) fn new(canister_id: &str, network: &str, tools: Vec<serde_json::Value>) -> Self {
+        Self {
+            canister_id: canister_id.to_string(),
+            network: network.to_string(),
+            taken_at: Utc::now(),
+            tools,
+        }
+    }
+
+    /// Loads the snapshot at `path`, or `None` if there is none
+    pub(crate) async fn load_from(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read tool snapshot: {}", path.display()))?;
+
+        serde_json::from_str(&content)
+            .map(Some)
+            .with_context(|| "Failed to parse tool snapshot")
+    }
+
+    pub(crate) async fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await.with_context(|| {
+                format!("Failed to create config directory: {}", parent.display())
+            })?;
+        }
+
+        let content = serde_json::to_string_pretty(self)
+            .with_context(|| "Failed to serialize tool snapshot")?;
+
+        fs::write(path, content)
+            .await
+            .with_context(|| format!("Failed to write tool snapshot: {}", path.display()))
+    }
+
+    /// Get the snapshot file path of a canister on a network
+    pub(crate) fn snapshot_path(canister_id: &str, network: &str) -> Result<PathBuf> {
+        let config_dir =
+            dirs::config_dir().ok_or_else(|| anyhow!("Could not determine config directory"))?;
+
+        // Custom networks are URLs; keep only characters safe in a file name
+        let network: String = network
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+
+        Ok(config_dir
+            .join("icarus")
+            .join("tool-snapshots")
+            .join(format!("{canister_id}.{network}.json")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const CANISTER: &str = "rdmx6-jaaaa-aaaaa-aaadq-cai";
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tool-snapshots").join("snapshot.json");
+        assert!(ToolSnapshot::load_from(&path).await.unwrap().is_none());
+
+        let snapshot = ToolSnapshot::new(
+            CANISTER,
+            "ic",
+            vec![serde_json::json!({ "name": "get_weather" })],
+        );
+        snapshot.save_to(&path).await.unwrap();
+
+        let loaded = ToolSnapshot::load_from(&path).await.unwrap().unwrap();
+        assert_eq!(loaded, snapshot);
+    }
+
+    #[test]
+    fn test_snapshot_path_is_per_canister_and_network() {
+        let ic = ToolSnapshot::snapshot_path(CANISTER, "ic").unwrap();
+        let custom = ToolSnapshot::snapshot_path(CANISTER, "http://10.0.0.2:8080").unwrap();
+        assert!(ic.ends_with(format!("tool-snapshots/{CANISTER}.ic.json")));
+        assert!(custom.ends_with(format!("{CANISTER}.http___10_0_0_2_8080.json")));
+    }
+}
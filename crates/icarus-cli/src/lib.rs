@@ -17,6 +17,7 @@ pub mod utils;
 pub mod types;
 
 // Re-export commonly used types for convenience
+pub use config::icarus_toml::IcarusToml;
 pub use config::mcp::{McpConfig, McpConfigMetadata, McpConfigStats, McpServerConfig};
 
 // Re-export domain types
@@ -27,7 +28,9 @@ pub use utils::{
     client_detector::{
         detect_installed_clients, get_all_client_configs, get_chatgpt_desktop_config_path,
         get_claude_code_config_path, get_claude_desktop_config_path, get_continue_config_path,
+        get_cursor_config_path, get_windsurf_config_path, get_zed_config_path,
     },
+    dfx_config::{BuildCommand, CanisterIds, DfxCanister, DfxJson, DfxNetwork},
     project::{
         create_project_structure, find_project_root, is_icarus_project, load_project_config,
         validate_project_structure, ProjectConfig,
@@ -10,7 +10,10 @@ mod templates;
 mod types;
 mod utils;
 
-use commands::{BuildArgs, DeployArgs, McpArgs, NewArgs};
+use commands::{
+    AddArgs, BuildArgs, CandidArgs, ConfigArgs, DeployArgs, DoctorArgs, ExportArgs, GenerateArgs,
+    McpArgs, MonitorArgs, NewArgs, ServeArgs, TemplateArgs,
+};
 
 /// Icarus CLI - MCP canister framework for Internet Computer
 #[derive(Parser)]
@@ -42,15 +45,47 @@ pub enum Commands {
     /// Create a new MCP canister project
     New(NewArgs),
 
+    /// Manage user-defined project templates
+    #[command(subcommand)]
+    Template(TemplateArgs),
+
+    /// Add code to the current project
+    #[command(subcommand)]
+    Add(AddArgs),
+
     /// Build the current project
     Build(BuildArgs),
 
+    /// Regenerate the Candid file from the compiled canister
+    Candid(CandidArgs),
+
     /// Deploy the canister to Internet Computer
     Deploy(DeployArgs),
 
+    /// Project configuration (icarus.toml) commands
+    #[command(subcommand)]
+    Config(ConfigArgs),
+
     /// MCP server management commands
     #[command(subcommand)]
     Mcp(McpArgs),
+
+    /// Serve the project's tools over stdio MCP without a canister
+    Serve(ServeArgs),
+
+    /// Show tool usage, error rates and latencies of a canister
+    Monitor(MonitorArgs),
+
+    /// Diagnose why an AI client cannot see or call a canister's tools
+    Doctor(DoctorArgs),
+
+    /// Generate client code from a deployed canister's tools
+    #[command(subcommand)]
+    Generate(GenerateArgs),
+
+    /// Export a deployed canister's tools for other agent frameworks
+    #[command(subcommand)]
+    Export(ExportArgs),
 }
 
 #[tokio::main]
@@ -60,17 +95,26 @@ async fn main() -> Result<()> {
     // Initialize logging
     init_logging(&cli)?;
 
-    // Display banner if not in quiet mode
-    if !cli.quiet {
+    // Display banner if not in quiet mode; `serve` owns stdout for MCP messages
+    if !cli.quiet && !matches!(cli.command, Commands::Serve(_)) {
         display_banner();
     }
 
     // Execute the command
     match cli.command {
         Commands::New(ref args) => commands::new::execute(args.clone(), &cli).await,
+        Commands::Template(ref args) => commands::template::execute(args.clone(), &cli).await,
+        Commands::Add(ref args) => commands::add::execute(args.clone(), &cli).await,
         Commands::Build(ref args) => commands::build::execute(args.clone(), &cli).await,
+        Commands::Candid(ref args) => commands::candid::execute(args.clone(), &cli).await,
         Commands::Deploy(ref args) => commands::deploy::execute(args.clone(), &cli).await,
+        Commands::Config(ref args) => commands::config::execute(args.clone(), &cli).await,
         Commands::Mcp(ref mcp_args) => commands::mcp::execute(mcp_args.clone(), &cli).await,
+        Commands::Serve(ref args) => commands::serve::execute(args.clone(), &cli).await,
+        Commands::Monitor(ref args) => commands::monitor::execute(args.clone(), &cli).await,
+        Commands::Doctor(ref args) => commands::doctor::execute(args.clone(), &cli).await,
+        Commands::Generate(ref args) => commands::generate::execute(args.clone(), &cli).await,
+        Commands::Export(ref args) => commands::export::execute(args.clone(), &cli).await,
     }
 }
 
@@ -0,0 +1,426 @@
+//! "agent-memory" template: long-term memory for AI agents with semantic
+//! search.
+//!
+//! The generated canister stores memories together with an embedding vector,
+//! either supplied by the client or computed through an HTTP outcall to an
+//! OpenAI-compatible embeddings API, and answers `recall_semantic` with the
+//! top-k memories by cosine similarity.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use tokio::fs;
+
+use super::basic::GITIGNORE;
+use crate::utils::dfx_config::DfxJson;
+
+/// Name `icarus new --template` selects this template by
+pub const NAME: &str = "agent-memory";
+
+/// Template content for Cargo.toml
+const CARGO_TOML: &str = r#"[package]
+name = "{{PROJECT_NAME}}"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+crate-type = ["cdylib"]
+
+[dependencies]
+icarus = { version = "0.9", features = ["macros"] }
+ic-cdk = "0.16"
+ic-stable-structures = "0.7"
+serde = { version = "1.0", features = ["derive"] }
+serde_json = "1.0"
+candid = "0.10"
+
+[dev-dependencies]
+candid = "0.10"
+"#;
+
+/// Template content for icarus.toml
+const ICARUS_TOML: &str = r#"[service]
+name = "{{PROJECT_NAME}}"
+description = "Long-term memory with semantic search"
+
+[features]
+# Admin-only configuration tools
+auth = true
+# The embeddings API key is stored as the secret "embeddings"
+secrets = true
+"#;
+
+/// Template content for src/lib.rs
+const LIB_RS: &str = r#"//! Long-term memory for AI agents with semantic search.
+//!
+//! Every memory is stored with an embedding vector. Vectors are normalized
+//! when stored, so cosine similarity is a dot product, and `recall_semantic`
+//! scans all of them for the top k. A flat scan is exact and stays within the
+//! instruction limit for tens of thousands of memories at typical dimensions.
+
+use std::cell::RefCell;
+
+use candid::CandidType;
+use ic_stable_structures::{StableBTreeMap, StableCell};
+use icarus::http::{self, HttpMethod, HttpRequest};
+use icarus::memory::{self, StableMemory, FIRST_USER_MEMORY_ID};
+use icarus::prelude::*;
+use icarus::{secrets, IcarusStorable};
+use serde::{Deserialize, Serialize};
+
+/// Secret holding the embeddings API key, set with the `set_secret` tool
+const API_KEY_SECRET: &str = "embeddings";
+
+/// Most memories `recall_semantic` returns
+const MAX_K: u32 = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize, CandidType, IcarusStorable)]
+struct StoredMemory {
+    text: String,
+    tags: Vec<String>,
+    /// Unit-length embedding
+    embedding: Vec<f32>,
+    created_at: u64,
+}
+
+/// Where embeddings come from when the client does not supply them
+#[derive(Debug, Clone, Default, Serialize, Deserialize, CandidType, IcarusStorable)]
+struct EmbeddingConfig {
+    /// OpenAI-compatible embeddings endpoint; empty until configured
+    url: String,
+    model: String,
+    /// Fixed once the first memory is stored
+    dimensions: u32,
+}
+
+thread_local! {
+    static MEMORIES: RefCell<StableBTreeMap<u64, StoredMemory, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(FIRST_USER_MEMORY_ID))
+    );
+    static CONFIG: RefCell<StableCell<EmbeddingConfig, StableMemory>> = RefCell::new(
+        StableCell::init(memory::get_memory(FIRST_USER_MEMORY_ID + 1), EmbeddingConfig::default())
+    );
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, CandidType)]
+struct Recall {
+    id: u64,
+    text: String,
+    tags: Vec<String>,
+    /// Cosine similarity to the query, from -1 to 1
+    score: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, CandidType)]
+struct MemoryStats {
+    memories: u64,
+    dimensions: u32,
+    embedding_model: Option<String>,
+    api_key_set: bool,
+}
+
+/// Points the canister at an embeddings API, e.g.
+/// `https://api.openai.com/v1/embeddings` with `text-embedding-3-small`.
+#[tool(auth = "admin")]
+fn configure_embeddings(url: String, model: String, dimensions: u32) -> Result<(), String> {
+    if !url.starts_with("https://") {
+        return Err("The embeddings URL must use https".to_string());
+    }
+    let stored = config().dimensions;
+    if stored != 0 && stored != dimensions && memory_count() > 0 {
+        return Err(format!(
+            "Stored memories have {stored} dimensions; forget them before changing it"
+        ));
+    }
+    CONFIG.with(|config| {
+        config.borrow_mut().set(EmbeddingConfig { url, model, dimensions });
+    });
+    Ok(())
+}
+
+/// Stores a memory and returns its id.
+///
+/// Pass `embedding` to store a vector computed by the client; otherwise the
+/// configured embeddings API computes one.
+#[tool]
+async fn remember(
+    text: String,
+    tags: Option<Vec<String>>,
+    embedding: Option<Vec<f32>>,
+) -> Result<u64, String> {
+    if text.trim().is_empty() {
+        return Err("Nothing to remember".to_string());
+    }
+    let embedding = match embedding {
+        Some(embedding) => embedding,
+        None => embed(&text).await?,
+    };
+    let embedding = prepare(embedding)?;
+
+    let id = MEMORIES.with(|memories| {
+        let mut memories = memories.borrow_mut();
+        let id = memories.last_key_value().map_or(0, |(id, _)| id + 1);
+        memories.insert(
+            id,
+            StoredMemory {
+                text,
+                tags: tags.unwrap_or_default(),
+                embedding,
+                created_at: icarus::time::now_nanos(),
+            },
+        );
+        id
+    });
+    Ok(id)
+}
+
+/// Returns the `k` memories most similar in meaning to `query`.
+#[tool]
+async fn recall_semantic(
+    query: String,
+    k: u32,
+    query_embedding: Option<Vec<f32>>,
+) -> Result<Vec<Recall>, String> {
+    if memory_count() == 0 {
+        return Ok(Vec::new());
+    }
+    let query = match query_embedding {
+        Some(embedding) => embedding,
+        None => embed(&query).await?,
+    };
+    let query = prepare(query)?;
+    let k = k.clamp(1, MAX_K) as usize;
+
+    let mut scored: Vec<(f32, u64)> = MEMORIES.with(|memories| {
+        memories
+            .borrow()
+            .iter()
+            .map(|entry| (dot(&query, &entry.value().embedding), *entry.key()))
+            .collect()
+    });
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.truncate(k);
+
+    Ok(MEMORIES.with(|memories| {
+        let memories = memories.borrow();
+        scored
+            .into_iter()
+            .filter_map(|(score, id)| {
+                memories.get(&id).map(|memory| Recall {
+                    id,
+                    text: memory.text,
+                    tags: memory.tags,
+                    score,
+                })
+            })
+            .collect()
+    }))
+}
+
+/// Deletes a memory.
+#[tool]
+fn forget(id: u64) -> Result<(), String> {
+    MEMORIES
+        .with(|memories| memories.borrow_mut().remove(&id))
+        .map(|_| ())
+        .ok_or_else(|| format!("No memory with id {id}"))
+}
+
+/// Reports how many memories are stored and how embeddings are computed.
+#[tool]
+fn stats() -> MemoryStats {
+    let config = config();
+    MemoryStats {
+        memories: memory_count(),
+        dimensions: config.dimensions,
+        embedding_model: (!config.url.is_empty()).then_some(config.model),
+        api_key_set: secrets::secret_info(API_KEY_SECRET).is_some(),
+    }
+}
+
+fn config() -> EmbeddingConfig {
+    CONFIG.with(|config| config.borrow().get().clone())
+}
+
+fn memory_count() -> u64 {
+    MEMORIES.with(|memories| memories.borrow().len())
+}
+
+/// Checks the vector against the configured dimensions and normalizes it.
+///
+/// The first vector stored fixes the dimensions if none are configured.
+fn prepare(mut vector: Vec<f32>) -> Result<Vec<f32>, String> {
+    let mut config = config();
+    if config.dimensions == 0 {
+        config.dimensions = vector.len() as u32;
+        CONFIG.with(|cell| cell.borrow_mut().set(config.clone()));
+    }
+    if vector.len() != config.dimensions as usize {
+        return Err(format!(
+            "Expected {} dimensions, got {}",
+            config.dimensions,
+            vector.len()
+        ));
+    }
+
+    let norm = dot(&vector, &vector).sqrt();
+    if norm == 0.0 || !norm.is_finite() {
+        return Err("The embedding must be a non-zero, finite vector".to_string());
+    }
+    vector.iter_mut().for_each(|x| *x /= norm);
+    Ok(vector)
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+}
+
+/// Computes an embedding with the configured API.
+async fn embed(text: &str) -> Result<Vec<f32>, String> {
+    let config = config();
+    if config.url.is_empty() {
+        return Err(
+            "No embeddings API configured: call configure_embeddings or pass an embedding"
+                .to_string(),
+        );
+    }
+
+    let body = serde_json::json!({ "model": config.model, "input": text });
+    let mut request = HttpRequest::new(HttpMethod::Post, &config.url)
+        .header("Content-Type", "application/json")
+        .body(body.to_string());
+    if secrets::secret_info(API_KEY_SECRET).is_some() {
+        let authorization = secrets::expand(&format!("Bearer {{{{secret:{API_KEY_SECRET}}}}}"))
+            .map_err(|e| e.to_string())?;
+        request = request.header("Authorization", authorization);
+    }
+
+    let response = http::request(request).await.map_err(|e| e.to_string())?;
+    let parsed: EmbeddingResponse = response.json().map_err(|e| e.to_string())?;
+    parsed
+        .data
+        .into_iter()
+        .next()
+        .map(|data| data.embedding)
+        .ok_or_else(|| "The embeddings API returned no embedding".to_string())
+}
+
+mcp! {}
+
+// Export candid interface
+ic_cdk::export_candid!();
+"#;
+
+/// Template content for README.md
+const README_MD: &str = r#"# {{PROJECT_NAME}}
+
+Long-term memory for AI agents, built with the Icarus SDK. Memories are stored
+with embedding vectors in stable memory and recalled by meaning.
+
+## Tools
+
+- **remember**(text, tags?, embedding?): stores a memory and returns its id
+- **recall_semantic**(query, k, query_embedding?): the k most similar memories
+- **forget**(id): deletes a memory
+- **stats**(): memory count, dimensions and embedding provider
+- **configure_embeddings**(url, model, dimensions): admin only
+
+## Embeddings
+
+Clients that compute embeddings themselves pass them as `embedding` and
+`query_embedding`. Otherwise configure an OpenAI-compatible API and store its
+key as the `embeddings` secret:
+
+```bash
+icarus mcp call configure_embeddings \
+  '{"url": "https://api.openai.com/v1/embeddings", "model": "text-embedding-3-small", "dimensions": 1536}'
+icarus mcp call set_secret '{"name": "embeddings", "value": "sk-..."}'
+```
+
+All memories must have the same number of dimensions; the first one stored
+fixes it if `configure_embeddings` has not.
+
+## Building and Deploying
+
+```bash
+icarus build
+icarus deploy --network local
+```
+
+Service name and enabled features are set in `icarus.toml`.
+"#;
+
+/// Generate an agent-memory project.
+pub async fn generate_project(project_name: &str, project_path: &Path) -> Result<()> {
+    let src_dir = project_path.join("src");
+    fs::create_dir_all(&src_dir)
+        .await
+        .context("Failed to create src directory")?;
+
+    let files = [
+        ("Cargo.toml", CARGO_TOML),
+        ("icarus.toml", ICARUS_TOML),
+        ("src/lib.rs", LIB_RS),
+        ("README.md", README_MD),
+        (".gitignore", GITIGNORE),
+    ];
+    for (name, content) in files {
+        fs::write(
+            project_path.join(name),
+            content.replace("{{PROJECT_NAME}}", project_name),
+        )
+        .await
+        .with_context(|| format!("Failed to write {name}"))?;
+    }
+
+    DfxJson::for_rust_canister(project_name)
+        .save(project_path)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::icarus_toml::IcarusToml;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_generate_agent_memory_project() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().join("recall");
+        fs::create_dir_all(&project_path).await.unwrap();
+
+        generate_project("recall", &project_path).await.unwrap();
+
+        let lib_rs = fs::read_to_string(project_path.join("src/lib.rs"))
+            .await
+            .unwrap();
+        for tool in ["fn remember", "fn recall_semantic", "fn forget", "fn stats"] {
+            assert!(lib_rs.contains(tool), "missing {tool}");
+        }
+        // The secret placeholder must survive project-name substitution
+        assert!(lib_rs.contains("{{{{secret:{API_KEY_SECRET}}}}}"));
+
+        let config = IcarusToml::load(&project_path).await.unwrap().unwrap();
+        assert_eq!(config.service.name.as_deref(), Some("recall"));
+        assert_eq!(config.features.secrets, Some(true));
+        assert!(config.validate().is_empty());
+
+        assert!(DfxJson::load(&project_path)
+            .await
+            .unwrap()
+            .canister("recall")
+            .is_some());
+    }
+}
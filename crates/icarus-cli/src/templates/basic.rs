@@ -6,6 +6,8 @@ use anyhow::{Context, Result};
 use std::path::Path;
 use tokio::fs;
 
+use crate::utils::dfx_config::DfxJson;
+
 /// Template content for Cargo.toml
 const CARGO_TOML: &str = r#"[package]
 name = "{{PROJECT_NAME}}"
@@ -63,28 +65,6 @@ struct SystemInfo {
 ic_cdk::export_candid!();
 "#;
 
-/// Template content for dfx.json
-const DFX_JSON: &str = r#"{
-  "version": 1,
-  "canisters": {
-    "{{PROJECT_NAME}}": {
-      "type": "rust",
-      "candid": "{{PROJECT_NAME}}.did",
-      "package": "{{PROJECT_NAME}}",
-      "build": [
-        "cargo build --target wasm32-unknown-unknown --release --package {{PROJECT_NAME}}"
-      ],
-      "wasm": "target/wasm32-unknown-unknown/release/{{PROJECT_NAME}}.wasm"
-    }
-  },
-  "defaults": {
-    "build": {
-      "packtool": ""
-    }
-  }
-}
-"#;
-
 /// Template content for README.md
 const README_MD: &str = r#"# {{PROJECT_NAME}}
 
@@ -172,7 +152,7 @@ This project was created with Icarus SDK.
 "#;
 
 /// Template content for .gitignore
-const GITIGNORE: &str = r#"# Rust
+pub(super) const GITIGNORE: &str = r#"# Rust
 /target
 Cargo.lock
 **/*.rs.bk
@@ -211,7 +191,6 @@ pub async fn generate_project(project_name: &str, project_path: &Path) -> Result
 
     // Replace {{PROJECT_NAME}} placeholder in all templates
     let cargo_toml = CARGO_TOML.replace("{{PROJECT_NAME}}", project_name);
-    let dfx_json = DFX_JSON.replace("{{PROJECT_NAME}}", project_name);
     let readme_md = README_MD.replace("{{PROJECT_NAME}}", project_name);
 
     // Write Cargo.toml
@@ -225,9 +204,9 @@ pub async fn generate_project(project_name: &str, project_path: &Path) -> Result
         .context("Failed to write src/lib.rs")?;
 
     // Write dfx.json
-    fs::write(project_path.join("dfx.json"), dfx_json)
-        .await
-        .context("Failed to write dfx.json")?;
+    DfxJson::for_rust_canister(project_name)
+        .save(project_path)
+        .await?;
 
     // Write README.md
     fs::write(project_path.join("README.md"), readme_md)
@@ -269,5 +248,11 @@ mod tests {
             .await
             .unwrap();
         assert!(cargo_content.contains(&format!("name = \"{}\"", project_name)));
+
+        let dfx = DfxJson::load(&project_path).await.unwrap();
+        assert_eq!(
+            dfx.canister(project_name).unwrap().package.as_deref(),
+            Some(project_name)
+        );
     }
 }
@@ -0,0 +1,323 @@
+//! User-defined project templates.
+//!
+//! A template is a directory tree: a git repository (`gh:org/repo`, any git
+//! URL), a local directory, or a template registered with `icarus template
+//! add`. Every file is copied into the new project with `{{VARIABLE}}`
+//! placeholders replaced, in file names as well as contents. Placeholders
+//! that name no known variable are left as they are, so templates can contain
+//! other `{{...}}` syntax.
+//!
+//! | Variable            | Value                                   |
+//! |---------------------|-----------------------------------------|
+//! | `{{PROJECT_NAME}}`  | name given to `icarus new`              |
+//! | `{{CRATE_NAME}}`    | project name with `-` replaced by `_`   |
+//! | `{{CANISTER_NAME}}` | dfx canister name (the project name)    |
+//! | `{{AUTHOR}}`        | git `user.name`, or `$USER`             |
+
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+use crate::config::templates::TemplateRegistry;
+use crate::utils::git;
+
+/// Templates shipped with the CLI; the first is the default
+pub const BUILTINS: [&str; 2] = ["basic", super::agent_memory::NAME];
+
+/// Where a template comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateSource {
+    /// A template shipped with the CLI
+    Builtin(&'static str),
+    /// A git repository, optionally at a branch or tag
+    Git {
+        url: String,
+        reference: Option<String>,
+    },
+    /// A directory on this machine
+    Local(PathBuf),
+    /// A template added with `icarus template add`
+    Registered(String),
+}
+
+impl TemplateSource {
+    /// Parses a `--template` value
+    ///
+    /// `gh:org/repo` is shorthand for the GitHub repository, and a `#ref`
+    /// suffix on any git source selects a branch or tag. Values that are
+    /// neither git sources nor existing paths are registry names.
+    pub fn parse(value: &str) -> Result<Self> {
+        if let Some(name) = BUILTINS.iter().find(|name| **name == value) {
+            return Ok(Self::Builtin(name));
+        }
+
+        let (location, reference) = match value.split_once('#') {
+            Some((location, reference)) if !reference.is_empty() => {
+                (location, Some(reference.to_string()))
+            }
+            _ => (value, None),
+        };
+
+        if let Some(repo) = location.strip_prefix("gh:") {
+            let valid = repo.split('/').count() == 2 && repo.split('/').all(|p| !p.is_empty());
+            if !valid {
+                return Err(anyhow!(
+                    "Invalid GitHub template '{}': expected gh:<owner>/<repo>",
+                    value
+                ));
+            }
+            return Ok(Self::Git {
+                url: format!("https://github.com/{}.git", repo.trim_end_matches(".git")),
+                reference,
+            });
+        }
+
+        let is_git_url = ["https://", "http://", "git@", "ssh://", "git://"]
+            .iter()
+            .any(|scheme| location.starts_with(scheme));
+        if is_git_url {
+            return Ok(Self::Git {
+                url: location.to_string(),
+                reference,
+            });
+        }
+
+        let path = Path::new(value);
+        if path.is_dir() || value.starts_with('.') || value.contains(std::path::MAIN_SEPARATOR) {
+            return Ok(Self::Local(path.to_path_buf()));
+        }
+
+        Ok(Self::Registered(value.to_string()))
+    }
+}
+
+/// Values substituted into template files
+#[derive(Debug, Clone, Default)]
+pub struct TemplateVariables(BTreeMap<&'static str, String>);
+
+impl TemplateVariables {
+    /// Variables for a project called `project_name`
+    pub async fn for_project(project_name: &str) -> Self {
+        let author = match git::get_user_name().await {
+            Some(name) => name,
+            None => std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_default(),
+        };
+        Self::new(project_name, &author)
+    }
+
+    pub fn new(project_name: &str, author: &str) -> Self {
+        let mut vars = BTreeMap::new();
+        vars.insert("PROJECT_NAME", project_name.to_string());
+        vars.insert("CRATE_NAME", project_name.replace('-', "_"));
+        vars.insert("CANISTER_NAME", project_name.to_string());
+        vars.insert("AUTHOR", author.to_string());
+        Self(vars)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    /// Replaces `{{NAME}}` (spaces inside the braces allowed) with the value
+    /// of each known variable
+    pub fn render(&self, content: &str) -> String {
+        let mut out = String::with_capacity(content.len());
+        let mut rest = content;
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let value = after
+                .find("}}")
+                .and_then(|end| self.get(after[..end].trim()).map(|value| (end, value)));
+            match value {
+                Some((end, value)) => {
+                    out.push_str(value);
+                    rest = &after[end + 2..];
+                }
+                None => {
+                    out.push_str("{{");
+                    rest = after;
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Generates a project at `project_path` from a non-builtin template
+pub async fn generate_project(
+    source: &TemplateSource,
+    vars: &TemplateVariables,
+    project_path: &Path,
+) -> Result<()> {
+    match source {
+        TemplateSource::Builtin(name) => Err(anyhow!("'{}' is a builtin template", name)),
+        TemplateSource::Local(dir) => render_dir(dir, project_path, vars).await,
+        TemplateSource::Git { url, reference } => {
+            let checkout = tempfile::TempDir::new().context("Failed to create a temp directory")?;
+            let dir = checkout.path().join("template");
+            git::clone_repository(url, &dir, reference.as_deref()).await?;
+            render_dir(&dir, project_path, vars).await
+        }
+        TemplateSource::Registered(name) => {
+            let registry = TemplateRegistry::load().await?;
+            let dir = registry.template_dir(name).ok_or_else(|| {
+                anyhow!(
+                    "Unknown template '{}'. Run 'icarus template list' to see registered templates",
+                    name
+                )
+            })?;
+            render_dir(&dir, project_path, vars).await
+        }
+    }
+}
+
+/// Copies `template_dir` into `project_path`, rendering names and contents
+///
+/// The `.git` directory is skipped, and files that are not UTF-8 are copied
+/// unchanged.
+pub async fn render_dir(
+    template_dir: &Path,
+    project_path: &Path,
+    vars: &TemplateVariables,
+) -> Result<()> {
+    if !template_dir.is_dir() {
+        return Err(anyhow!(
+            "Template directory not found: {}",
+            template_dir.display()
+        ));
+    }
+
+    let mut pending = vec![(template_dir.to_path_buf(), project_path.to_path_buf())];
+    while let Some((from, to)) = pending.pop() {
+        fs::create_dir_all(&to)
+            .await
+            .with_context(|| format!("Failed to create {}", to.display()))?;
+
+        let mut entries = fs::read_dir(&from)
+            .await
+            .with_context(|| format!("Failed to read {}", from.display()))?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name == ".git" {
+                continue;
+            }
+            let target = to.join(vars.render(&name));
+
+            if entry.file_type().await?.is_dir() {
+                pending.push((entry.path(), target));
+                continue;
+            }
+
+            let bytes = fs::read(entry.path())
+                .await
+                .with_context(|| format!("Failed to read {}", entry.path().display()))?;
+            let bytes = match String::from_utf8(bytes) {
+                Ok(text) => vars.render(&text).into_bytes(),
+                Err(e) => e.into_bytes(),
+            };
+            fs::write(&target, bytes)
+                .await
+                .with_context(|| format!("Failed to write {}", target.display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_template_sources() {
+        assert_eq!(
+            TemplateSource::parse("basic").unwrap(),
+            TemplateSource::Builtin("basic")
+        );
+        assert_eq!(
+            TemplateSource::parse("agent-memory").unwrap(),
+            TemplateSource::Builtin("agent-memory")
+        );
+        assert_eq!(
+            TemplateSource::parse("gh:acme/icarus-starter#v2").unwrap(),
+            TemplateSource::Git {
+                url: "https://github.com/acme/icarus-starter.git".to_string(),
+                reference: Some("v2".to_string()),
+            }
+        );
+        assert_eq!(
+            TemplateSource::parse("git@github.com:acme/starter.git").unwrap(),
+            TemplateSource::Git {
+                url: "git@github.com:acme/starter.git".to_string(),
+                reference: None,
+            }
+        );
+        assert_eq!(
+            TemplateSource::parse("./starter").unwrap(),
+            TemplateSource::Local(PathBuf::from("./starter"))
+        );
+        assert_eq!(
+            TemplateSource::parse("team-starter").unwrap(),
+            TemplateSource::Registered("team-starter".to_string())
+        );
+        assert!(TemplateSource::parse("gh:acme").is_err());
+    }
+
+    #[test]
+    fn test_render_substitutes_known_variables() {
+        let vars = TemplateVariables::new("weather-bot", "Ada");
+        assert_eq!(
+            vars.render("name = \"{{PROJECT_NAME}}\" # {{ CRATE_NAME }} by {{AUTHOR}}"),
+            "name = \"weather-bot\" # weather_bot by Ada"
+        );
+        assert_eq!(
+            vars.render("{{UNKNOWN}} {{ and {{"),
+            "{{UNKNOWN}} {{ and {{"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_render_dir() {
+        let template = TempDir::new().unwrap();
+        fs::create_dir_all(template.path().join("src/{{CRATE_NAME}}"))
+            .await
+            .unwrap();
+        fs::create_dir_all(template.path().join(".git"))
+            .await
+            .unwrap();
+        fs::write(template.path().join(".git/HEAD"), "ref")
+            .await
+            .unwrap();
+        fs::write(
+            template.path().join("src/{{CRATE_NAME}}/mod.rs"),
+            "// {{CANISTER_NAME}}",
+        )
+        .await
+        .unwrap();
+        fs::write(template.path().join("logo.bin"), [0xff, 0xfe, b'{'])
+            .await
+            .unwrap();
+
+        let project = TempDir::new().unwrap();
+        let vars = TemplateVariables::new("weather-bot", "Ada");
+        render_dir(template.path(), project.path(), &vars)
+            .await
+            .unwrap();
+
+        let module = fs::read_to_string(project.path().join("src/weather_bot/mod.rs"))
+            .await
+            .unwrap();
+        assert_eq!(module, "// weather-bot");
+        assert_eq!(
+            fs::read(project.path().join("logo.bin")).await.unwrap(),
+            [0xff, 0xfe, b'{']
+        );
+        assert!(!project.path().join(".git").exists());
+    }
+}
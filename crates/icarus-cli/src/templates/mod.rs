@@ -1,5 +1,10 @@
 //! Project templates for Icarus MCP canister scaffolding.
 //!
-//! This module provides a simple "Hello World" template for new projects.
+//! This module provides a simple "Hello World" template and an agent memory
+//! template with semantic search for new projects, user-defined templates from git repositories or local directories, and
+//! the TypeScript client package emitted by `icarus generate ts-client`.
 
+pub mod agent_memory;
 pub mod basic;
+pub mod custom;
+pub mod ts_client;
@@ -0,0 +1,491 @@
+//! TypeScript client package template.
+//!
+//! Renders an npm package that calls a canister's tools through
+//! `mcp_call_tool` using `@dfinity/agent`, with one typed method per tool.
+//! Parameter types are derived from each tool's JSON input schema.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::path::Path;
+use tokio::fs;
+
+use icarus_core::Tool;
+
+/// Template content for package.json
+const PACKAGE_JSON: &str = r#"{
+  "name": "{{PACKAGE_NAME}}",
+  "version": "0.1.0",
+  "description": "TypeScript client for the MCP tools of canister {{CANISTER_ID}}",
+  "type": "module",
+  "main": "dist/index.js",
+  "types": "dist/index.d.ts",
+  "files": ["dist"],
+  "scripts": {
+    "build": "tsc",
+    "prepare": "tsc"
+  },
+  "dependencies": {
+    "@dfinity/agent": "^2.1.0",
+    "@dfinity/candid": "^2.1.0"
+  },
+  "devDependencies": {
+    "typescript": "^5.4.0"
+  }
+}
+"#;
+
+/// Template content for tsconfig.json
+const TSCONFIG_JSON: &str = r#"{
+  "compilerOptions": {
+    "target": "ES2020",
+    "module": "ES2020",
+    "moduleResolution": "bundler",
+    "declaration": true,
+    "outDir": "dist",
+    "rootDir": "src",
+    "strict": true,
+    "skipLibCheck": true
+  },
+  "include": ["src"]
+}
+"#;
+
+/// Template content for the fixed part of src/index.ts
+const INDEX_HEADER: &str = r#"// Generated by `icarus generate ts-client`. Do not edit by hand;
+// regenerate after changing the canister's tools.
+
+import { Actor, HttpAgent, type ActorSubclass } from "@dfinity/agent";
+import type { IDL } from "@dfinity/candid";
+
+/** Canister the client was generated from. */
+export const CANISTER_ID = "{{CANISTER_ID}}";
+
+/** Replica host of the network the canister was deployed on. */
+export const DEFAULT_HOST = "{{HOST}}";
+
+/** A content item of a tool result. */
+export type Content =
+  | { type: "text"; text: string }
+  | { type: "image"; data: string; mimeType: string }
+  | { type: "resource"; resource: Record<string, unknown> };
+
+/** The result of a tool call. */
+export interface CallToolResult {
+  content: Content[];
+  structuredContent?: unknown;
+  isError?: boolean;
+}
+
+/** Thrown when the canister rejects a tool call. */
+export class ToolError extends Error {
+  constructor(message: string, readonly code?: number) {
+    super(message);
+    this.name = "ToolError";
+  }
+}
+
+/** Options for {@link IcarusClient.create}. */
+export interface ClientOptions {
+  /** Canister to call, defaults to {@link CANISTER_ID}. */
+  canisterId?: string;
+  /** Agent to use, e.g. one with an authenticated identity. */
+  agent?: HttpAgent;
+  /** Replica host, defaults to {@link DEFAULT_HOST}. Ignored with `agent`. */
+  host?: string;
+}
+
+interface McpService {
+  mcp_call_tool: (request: string) => Promise<string>;
+}
+
+const idlFactory: IDL.InterfaceFactory = ({ IDL }) =>
+  IDL.Service({
+    mcp_call_tool: IDL.Func([IDL.Text], [IDL.Text], []),
+  });
+
+/** Joins the text content of a tool result. */
+export function resultText(result: CallToolResult): string {
+  return result.content
+    .filter((item): item is { type: "text"; text: string } => item.type === "text")
+    .map((item) => item.text)
+    .join("\n");
+}
+"#;
+
+/// Template content for the generic part of the client class
+const CLIENT_HEADER: &str = r#"
+/** Calls the canister's MCP tools. */
+export class IcarusClient {
+  private nextId = 0;
+
+  private constructor(private readonly actor: ActorSubclass<McpService>) {}
+
+  /** Creates a client; fetches the root key when talking to a local replica. */
+  static async create(options: ClientOptions = {}): Promise<IcarusClient> {
+    const host = options.host ?? DEFAULT_HOST;
+    const agent =
+      options.agent ??
+      (await HttpAgent.create({
+        host,
+        shouldFetchRootKey: host.startsWith("http://"),
+      }));
+    const actor = Actor.createActor<McpService>(idlFactory, {
+      agent,
+      canisterId: options.canisterId ?? CANISTER_ID,
+    });
+    return new IcarusClient(actor);
+  }
+
+  /** Calls a tool by name. */
+  async callTool(name: string, args: object = {}): Promise<CallToolResult> {
+    const request = JSON.stringify({
+      jsonrpc: "2.0",
+      id: String(++this.nextId),
+      method: "tools/call",
+      params: { name, arguments: args },
+    });
+    const response = JSON.parse(await this.actor.mcp_call_tool(request));
+    if (response.error) {
+      throw new ToolError(response.error.message, response.error.code);
+    }
+    return response.result as CallToolResult;
+  }
+"#;
+
+/// Returns the replica host for a dfx network name or URL.
+#[must_use]
+pub fn network_host(network: &str) -> String {
+    match network {
+        "local" => "http://127.0.0.1:4943".to_string(),
+        "ic" | "mainnet" => "https://icp-api.io".to_string(),
+        url => url.trim_end_matches('/').to_string(),
+    }
+}
+
+/// Generate a TypeScript client package for `tools` in `output_path`.
+pub async fn generate_package(
+    package_name: &str,
+    canister_id: &str,
+    network: &str,
+    tools: &[Tool],
+    output_path: &Path,
+) -> Result<()> {
+    let src_dir = output_path.join("src");
+    fs::create_dir_all(&src_dir)
+        .await
+        .context("Failed to create src directory")?;
+
+    let package_json = PACKAGE_JSON
+        .replace("{{PACKAGE_NAME}}", package_name)
+        .replace("{{CANISTER_ID}}", canister_id);
+
+    fs::write(output_path.join("package.json"), package_json)
+        .await
+        .context("Failed to write package.json")?;
+
+    fs::write(output_path.join("tsconfig.json"), TSCONFIG_JSON)
+        .await
+        .context("Failed to write tsconfig.json")?;
+
+    fs::write(
+        src_dir.join("index.ts"),
+        render_index(tools, canister_id, &network_host(network)),
+    )
+    .await
+    .context("Failed to write src/index.ts")?;
+
+    Ok(())
+}
+
+/// Renders src/index.ts: shared types, one argument interface per tool and
+/// the client class with one method per tool.
+#[must_use]
+pub fn render_index(tools: &[Tool], canister_id: &str, host: &str) -> String {
+    let mut out = INDEX_HEADER
+        .replace("{{CANISTER_ID}}", canister_id)
+        .replace("{{HOST}}", host);
+
+    for tool in tools {
+        out.push('\n');
+        out.push_str(&format!(
+            "/** Arguments of `{}`. */\nexport type {} = {};\n",
+            tool.name,
+            args_type_name(&tool.name),
+            object_type(&tool.input_schema, 0)
+        ));
+    }
+
+    out.push_str(CLIENT_HEADER);
+    for tool in tools {
+        out.push('\n');
+        if let Some(description) = tool.description.as_deref() {
+            out.push_str(&format!("  /** {} */\n", doc_text(description)));
+        }
+        out.push_str(&format!(
+            "  {}(args: {}): Promise<CallToolResult> {{\n    return this.callTool({}, args);\n  }}\n",
+            method_name(&tool.name),
+            args_type_name(&tool.name),
+            string_literal(&tool.name)
+        ));
+    }
+    out.push_str("}\n");
+
+    out
+}
+
+/// Maps a JSON schema to a TypeScript type.
+#[must_use]
+pub fn schema_type(schema: &Value, depth: usize) -> String {
+    let Some(schema) = schema.as_object() else {
+        return "unknown".to_string();
+    };
+
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return union(values.iter().map(|value| value.to_string()));
+    }
+    if let Some(value) = schema.get("const") {
+        return value.to_string();
+    }
+    for key in ["anyOf", "oneOf"] {
+        if let Some(variants) = schema.get(key).and_then(Value::as_array) {
+            return union(variants.iter().map(|variant| schema_type(variant, depth)));
+        }
+    }
+
+    match schema.get("type") {
+        Some(Value::String(kind)) => primitive_type(kind, schema, depth),
+        // e.g. `"type": ["string", "null"]` for an `Option<String>` parameter
+        Some(Value::Array(kinds)) => union(
+            kinds
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|kind| primitive_type(kind, schema, depth)),
+        ),
+        _ => "unknown".to_string(),
+    }
+}
+
+fn primitive_type(kind: &str, schema: &serde_json::Map<String, Value>, depth: usize) -> String {
+    match kind {
+        "string" => "string".to_string(),
+        "number" | "integer" => "number".to_string(),
+        "boolean" => "boolean".to_string(),
+        "null" => "null".to_string(),
+        "array" => match schema.get("items") {
+            Some(items) => format!("Array<{}>", schema_type(items, depth)),
+            None => "unknown[]".to_string(),
+        },
+        "object" => object_type(schema, depth),
+        _ => "unknown".to_string(),
+    }
+}
+
+/// Renders an object schema as an inline type literal.
+fn object_type(schema: &serde_json::Map<String, Value>, depth: usize) -> String {
+    let Some(properties) = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .filter(|properties| !properties.is_empty())
+    else {
+        return "Record<string, unknown>".to_string();
+    };
+
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| required.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let indent = "  ".repeat(depth + 1);
+    let mut out = String::from("{\n");
+    for (name, property) in properties {
+        if let Some(description) = property.get("description").and_then(Value::as_str) {
+            out.push_str(&format!("{indent}/** {} */\n", doc_text(description)));
+        }
+        let optional = if required.contains(&name.as_str()) {
+            ""
+        } else {
+            "?"
+        };
+        out.push_str(&format!(
+            "{indent}{}{optional}: {};\n",
+            property_name(name),
+            schema_type(property, depth + 1)
+        ));
+    }
+    out.push_str(&"  ".repeat(depth));
+    out.push('}');
+    out
+}
+
+fn union(types: impl Iterator<Item = String>) -> String {
+    let mut types: Vec<String> = types.collect();
+    types.dedup();
+    if types.is_empty() {
+        "unknown".to_string()
+    } else {
+        types.join(" | ")
+    }
+}
+
+/// Converts a tool name such as `get_user` to a method name such as `getUser`.
+#[must_use]
+pub fn method_name(tool_name: &str) -> String {
+    let mut name = String::with_capacity(tool_name.len());
+    let mut upper_next = false;
+    for c in tool_name.chars() {
+        if c == '_' || c == '-' {
+            upper_next = !name.is_empty();
+        } else if upper_next {
+            name.extend(c.to_uppercase());
+            upper_next = false;
+        } else {
+            name.push(c);
+        }
+    }
+    name
+}
+
+/// Converts a tool name such as `get_user` to a type name such as `GetUserArgs`.
+#[must_use]
+pub fn args_type_name(tool_name: &str) -> String {
+    let method = method_name(tool_name);
+    let mut chars = method.chars();
+    match chars.next() {
+        Some(first) => format!("{}{}Args", first.to_uppercase(), chars.as_str()),
+        None => "Args".to_string(),
+    }
+}
+
+/// Quotes a property name unless it is a valid identifier.
+fn property_name(name: &str) -> String {
+    let is_identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+
+    if is_identifier {
+        name.to_string()
+    } else {
+        string_literal(name)
+    }
+}
+
+fn string_literal(value: &str) -> String {
+    Value::String(value.to_string()).to_string()
+}
+
+/// Keeps a description on one line and from closing the doc comment.
+fn doc_text(description: &str) -> String {
+    description
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("*/", "*\\/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    fn tool(name: &str, schema: Value) -> Tool {
+        let Value::Object(schema) = schema else {
+            panic!("schema must be an object");
+        };
+        Tool::new(name.to_string(), "Adds two numbers", Arc::new(schema))
+    }
+
+    #[test]
+    fn test_schema_types() {
+        assert_eq!(schema_type(&json!({ "type": "integer" }), 0), "number");
+        assert_eq!(
+            schema_type(&json!({ "type": ["string", "null"] }), 0),
+            "string | null"
+        );
+        assert_eq!(
+            schema_type(
+                &json!({ "type": "array", "items": { "type": "boolean" } }),
+                0
+            ),
+            "Array<boolean>"
+        );
+        assert_eq!(
+            schema_type(&json!({ "enum": ["asc", "desc"] }), 0),
+            "\"asc\" | \"desc\""
+        );
+        assert_eq!(
+            schema_type(&json!({ "type": "object" }), 0),
+            "Record<string, unknown>"
+        );
+        assert_eq!(schema_type(&json!({}), 0), "unknown");
+    }
+
+    #[test]
+    fn test_object_type_marks_optional_properties() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "a": { "type": "number" },
+                "max-results": { "type": "integer" }
+            },
+            "required": ["a"]
+        });
+        assert_eq!(
+            schema_type(&schema, 0),
+            "{\n  a: number;\n  \"max-results\"?: number;\n}"
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(method_name("add_numbers"), "addNumbers");
+        assert_eq!(method_name("_private"), "private");
+        assert_eq!(args_type_name("add_numbers"), "AddNumbersArgs");
+        assert_eq!(args_type_name("echo"), "EchoArgs");
+    }
+
+    #[test]
+    fn test_render_index() {
+        let tools = [tool(
+            "add_numbers",
+            json!({
+                "type": "object",
+                "properties": { "a": { "type": "number" }, "b": { "type": "number" } },
+                "required": ["a", "b"]
+            }),
+        )];
+        let index = render_index(
+            &tools,
+            "rrkah-fqaaa-aaaaa-aaaaq-cai",
+            "http://127.0.0.1:4943",
+        );
+
+        assert!(index.contains("export const CANISTER_ID = \"rrkah-fqaaa-aaaaa-aaaaq-cai\";"));
+        assert!(index.contains("export type AddNumbersArgs = {\n  a: number;\n  b: number;\n};"));
+        assert!(index.contains("  /** Adds two numbers */\n  addNumbers(args: AddNumbersArgs)"));
+        assert!(index.contains("return this.callTool(\"add_numbers\", args);"));
+        assert!(index.ends_with("}\n"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_package() {
+        let temp_dir = TempDir::new().unwrap();
+        let tools = [tool("echo", json!({ "type": "object" }))];
+
+        generate_package("echo-client", "aaaaa-aa", "ic", &tools, temp_dir.path())
+            .await
+            .unwrap();
+
+        let package_json = std::fs::read_to_string(temp_dir.path().join("package.json")).unwrap();
+        assert!(package_json.contains("\"name\": \"echo-client\""));
+        let index = std::fs::read_to_string(temp_dir.path().join("src/index.ts")).unwrap();
+        assert!(index.contains("https://icp-api.io"));
+        assert!(index.contains("export type EchoArgs = Record<string, unknown>;"));
+    }
+}
@@ -0,0 +1,210 @@
+//! Reuse of IC agents across bridge calls.
+//!
+//! Building an agent means loading an identity and, on a local replica,
+//! fetching the root key, so the bridge keeps one per network and canister.
+//! The pool holds at most [`AgentPoolConfig::max_connections`] entries,
+//! dropping the least recently used one to make room, and drops entries left
+//! unused for longer than [`AgentPoolConfig::idle_timeout`]. An entry unused
+//! for [`HEALTH_CHECK_AFTER`] is handed out marked for a health check, which
+//! the caller makes before trusting it.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use ic_agent::AgentError;
+
+/// Idle time after which a pooled agent is checked before it is reused.
+pub(crate) const HEALTH_CHECK_AFTER: Duration = Duration::from_secs(30);
+
+/// Size and lifetime limits of an [`AgentPool`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct AgentPoolConfig {
+    /// Most agents kept at once
+    pub(crate) max_connections: usize,
+    /// How long an unused agent is kept
+    pub(crate) idle_timeout: Duration,
+}
+
+impl Default for AgentPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 8,
+            idle_timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+/// A pooled value handed out by [`AgentPool::checkout`].
+#[derive(Debug, Clone)]
+pub(crate) struct Checkout<T> {
+    pub(crate) value: T,
+    /// Whether the value sat unused long enough to need a health check
+    pub(crate) needs_health_check: bool,
+}
+
+#[derive(Debug)]
+struct Entry<T> {
+    value: T,
+    last_used: Instant,
+}
+
+/// Agents (with whatever the caller keeps alongside them) by pool key.
+#[derive(Debug)]
+pub(crate) struct AgentPool<T> {
+    config: AgentPoolConfig,
+    entries: HashMap<String, Entry<T>>,
+}
+
+impl<T: Clone> AgentPool<T> {
+    pub(crate) fn new(config: AgentPoolConfig) -> Self {
+        Self {
+            config,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Returns the value pooled under `key` and marks it used, or `None` if
+    /// there is none or it sat idle past the timeout.
+    pub(crate) fn checkout(&mut self, key: &str, now: Instant) -> Option<Checkout<T>> {
+        let entry = self.entries.get_mut(key)?;
+        let idle = now.saturating_duration_since(entry.last_used);
+        if idle > self.config.idle_timeout {
+            self.entries.remove(key);
+            return None;
+        }
+        entry.last_used = now;
+        Some(Checkout {
+            value: entry.value.clone(),
+            needs_health_check: idle >= HEALTH_CHECK_AFTER,
+        })
+    }
+
+    /// Pools `value` under `key`, evicting idle entries and then the least
+    /// recently used one if the pool is full.
+    pub(crate) fn insert(&mut self, key: String, value: T, now: Instant) {
+        let idle_timeout = self.config.idle_timeout;
+        self.entries
+            .retain(|_, entry| now.saturating_duration_since(entry.last_used) <= idle_timeout);
+
+        while self.entries.len() >= self.config.max_connections.max(1)
+            && !self.entries.contains_key(&key)
+        {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+
+        self.entries.insert(
+            key,
+            Entry {
+                value,
+                last_used: now,
+            },
+        );
+    }
+
+    /// Drops the value pooled under `key`, so the next call builds a new one.
+    pub(crate) fn remove(&mut self, key: &str) -> Option<T> {
+        self.entries.remove(key).map(|entry| entry.value)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// Pool key of the agent for a canister on a network.
+pub(crate) fn pool_key(network: &str, canister_id: &str) -> String {
+    format!("{network}/{canister_id}")
+}
+
+/// Whether an agent that failed with `error` should be replaced.
+///
+/// A reject means the canister answered and the agent works; anything else
+/// (transport, certificate or replica errors) may be the agent's state.
+pub(crate) fn is_fatal(error: &AgentError) -> bool {
+    !matches!(
+        error,
+        AgentError::CertifiedReject { .. } | AgentError::UncertifiedReject { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(max_connections: usize) -> AgentPool<u32> {
+        AgentPool::new(AgentPoolConfig {
+            max_connections,
+            idle_timeout: Duration::from_secs(60),
+        })
+    }
+
+    #[test]
+    fn test_agents_are_reused_per_canister() {
+        let start = Instant::now();
+        let mut pool = pool(4);
+        pool.insert(pool_key("ic", "aaaaa-aa"), 1, start);
+        pool.insert(pool_key("local", "aaaaa-aa"), 2, start);
+
+        let checkout = pool.checkout("ic/aaaaa-aa", start).unwrap();
+        assert_eq!(checkout.value, 1);
+        assert!(!checkout.needs_health_check);
+        assert_eq!(pool.checkout("local/aaaaa-aa", start).unwrap().value, 2);
+        assert!(pool.checkout("ic/bbbbb-bb", start).is_none());
+
+        assert_eq!(pool.remove("ic/aaaaa-aa"), Some(1));
+        assert!(pool.checkout("ic/aaaaa-aa", start).is_none());
+    }
+
+    #[test]
+    fn test_idle_agents_are_checked_then_dropped() {
+        let start = Instant::now();
+        let mut pool = pool(4);
+        pool.insert("a".to_string(), 1, start);
+
+        let checkout = pool.checkout("a", start + HEALTH_CHECK_AFTER).unwrap();
+        assert!(checkout.needs_health_check);
+        assert!(
+            !pool
+                .checkout("a", start + HEALTH_CHECK_AFTER + Duration::from_secs(1))
+                .unwrap()
+                .needs_health_check
+        );
+
+        assert!(pool
+            .checkout("a", start + Duration::from_secs(200))
+            .is_none());
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn test_full_pool_evicts_least_recently_used() {
+        let start = Instant::now();
+        let mut pool = pool(2);
+        pool.insert("a".to_string(), 1, start);
+        pool.insert("b".to_string(), 2, start + Duration::from_secs(1));
+        pool.checkout("a", start + Duration::from_secs(2));
+
+        pool.insert("c".to_string(), 3, start + Duration::from_secs(3));
+        assert_eq!(pool.len(), 2);
+        assert!(pool.checkout("b", start + Duration::from_secs(3)).is_none());
+        assert!(pool.checkout("a", start + Duration::from_secs(3)).is_some());
+
+        // Replacing an entry never evicts another
+        pool.insert("c".to_string(), 4, start + Duration::from_secs(4));
+        assert_eq!(pool.len(), 2);
+        assert_eq!(
+            pool.checkout("c", start + Duration::from_secs(4))
+                .unwrap()
+                .value,
+            4
+        );
+    }
+}
@@ -5,12 +5,19 @@
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::net::TcpListener;
-use tokio::sync::RwLock;
-use tracing::{error, info, warn};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{debug, error, info, warn};
 
-use crate::config::mcp::McpConfig;
+use crate::config::mcp::{McpConfig, McpServerConfig};
+use crate::config::profiles::IdentityProfiles;
+use crate::utils::response_cache::{self, CacheKey, ResponseCache};
+use crate::utils::rmcp_bridge::BridgeConfig;
+use crate::utils::rpc_log::RpcLogger;
+use crate::utils::session_recorder::SessionRecorder;
 
 /// MCP Bridge Server trait
 #[async_trait]
@@ -26,6 +33,16 @@ pub(crate) struct SimpleBridgeServer {
     port: u16,
     config: Arc<RwLock<McpConfig>>,
     running: Arc<RwLock<bool>>,
+    recorder: Option<SessionRecorder>,
+    rpc_log: Option<RpcLogger>,
+    /// Identity for every server, overriding the profiles
+    identity: Option<String>,
+    profiles: Arc<IdentityProfiles>,
+    /// Replies to read-only tool calls, keyed by canister, tool and arguments
+    cache: Arc<Mutex<ResponseCache<String>>>,
+    /// Read-only tools of each canister with the cache TTL each declares,
+    /// fetched on its first cacheable call
+    read_only_tools: Arc<RwLock<HashMap<String, HashMap<String, Option<Duration>>>>>,
 }
 
 impl SimpleBridgeServer {
@@ -35,9 +52,58 @@ impl SimpleBridgeServer {
             port,
             config: Arc::new(RwLock::new(config)),
             running: Arc::new(RwLock::new(false)),
+            recorder: None,
+            rpc_log: None,
+            identity: None,
+            profiles: Arc::new(IdentityProfiles::default()),
+            cache: Arc::new(Mutex::new(ResponseCache::new(0, Duration::ZERO))),
+            read_only_tools: Arc::new(RwLock::new(HashMap::new())),
         })
     }
 
+    /// Caches replies to read-only tools as `config.cache_size` and
+    /// `config.cache_ttl` say.
+    pub(crate) fn with_cache(mut self, config: &BridgeConfig) -> Self {
+        self.cache = Arc::new(Mutex::new(ResponseCache::new(
+            config.cache_size,
+            config.cache_ttl,
+        )));
+        self
+    }
+
+    /// Records every request/response pair with `recorder`.
+    pub(crate) fn with_recorder(mut self, recorder: SessionRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Logs every exchange, redacted, with `rpc_log`.
+    pub(crate) fn with_rpc_log(mut self, rpc_log: RpcLogger) -> Self {
+        self.rpc_log = Some(rpc_log);
+        self
+    }
+
+    /// Selects the dfx identity per server: `identity` for all of them if
+    /// given, otherwise the one in each canister's profile.
+    pub(crate) fn with_identity(
+        mut self,
+        identity: Option<String>,
+        profiles: IdentityProfiles,
+    ) -> Self {
+        self.identity = identity;
+        self.profiles = Arc::new(profiles);
+        self
+    }
+
+    /// The dfx identity used for `server`, or `None` for the current one.
+    fn identity_for(&self, server: &McpServerConfig) -> Option<String> {
+        self.profiles.resolve(
+            self.identity.as_deref(),
+            server.canister_id.as_str(),
+            server.network.as_str(),
+        )
+    }
+
     async fn handle_connection(&self, stream: tokio::net::TcpStream) -> Result<()> {
         use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 
@@ -65,10 +131,17 @@ impl SimpleBridgeServer {
             info!("Received from {}: {}", peer_addr, trimmed_line);
 
             // Parse and handle MCP request
+            let started = std::time::Instant::now();
             let response = self.handle_mcp_request(trimmed_line).await;
 
             match response {
                 Ok(resp) => {
+                    if let Some(recorder) = &self.recorder {
+                        recorder.record_text(trimmed_line, &resp);
+                    }
+                    if let Some(rpc_log) = &self.rpc_log {
+                        rpc_log.log_text(trimmed_line, &resp, started.elapsed());
+                    }
                     writer.write_all(resp.as_bytes()).await?;
                     writer.write_all(b"\n").await?;
                     writer.flush().await?;
@@ -76,6 +149,12 @@ impl SimpleBridgeServer {
                 Err(e) => {
                     error!("Error handling MCP request: {}", e);
                     let error_response = format!(r#"{{"error": "{}"}}"#, e);
+                    if let Some(recorder) = &self.recorder {
+                        recorder.record_text(trimmed_line, &error_response);
+                    }
+                    if let Some(rpc_log) = &self.rpc_log {
+                        rpc_log.log_text(trimmed_line, &error_response, started.elapsed());
+                    }
                     writer.write_all(error_response.as_bytes()).await?;
                     writer.write_all(b"\n").await?;
                     writer.flush().await?;
@@ -116,7 +195,8 @@ impl SimpleBridgeServer {
                     "name": server.name,
                     "canister_id": server.canister_id,
                     "network": server.network,
-                    "url": server.url
+                    "url": server.url,
+                    "identity": self.identity_for(server)
                 }));
             }
         }
@@ -150,34 +230,79 @@ impl SimpleBridgeServer {
             .find(|s| s.name == tool_name && s.enabled)
             .ok_or_else(|| anyhow!("Tool not found: {}", tool_name))?;
 
-        // Make HTTP request to the canister
-        let client = reqwest::Client::new();
-        let response = client
-            .post(&format!("{}/call", server.url))
-            .json(&serde_json::json!({
-                "method": "mcp_call_tool",
-                "args": args
-            }))
-            .send()
-            .await
-            .map_err(|e| anyhow!("HTTP request failed: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(anyhow!("Canister returned error: {}", response.status()));
+        let cache_key = self.cache_key(server, args).await;
+        if let Some((key, _)) = &cache_key {
+            let bypass = args
+                .pointer("/params/_meta")
+                .and_then(serde_json::Value::as_object)
+                .is_some_and(response_cache::bypass_requested);
+            if !bypass {
+                let mut cache = self.cache.lock().await;
+                if let Some(cached) = cache.get(key, Instant::now()) {
+                    let (hits, misses) = cache.hit_counts();
+                    debug!("Cached result ({} hits, {} misses)", hits, misses);
+                    return Ok(cached);
+                }
+            }
         }
 
-        let canister_result = response
-            .text()
-            .await
-            .map_err(|e| anyhow!("Failed to read response: {}", e))?;
+        let canister_result = call_canister(server, "mcp_call_tool", args).await?;
+        let reply = call_tool_response(&canister_result);
+        let response = serde_json::to_string(&reply)?;
 
-        let result = serde_json::json!({
-            "result": {
-                "content": canister_result
-            }
-        });
+        let mut cache = self.cache.lock().await;
+        match cache_key {
+            Some((key, ttl)) if reply["result"]["isError"] != true => match ttl {
+                Some(ttl) => cache.insert_for(key, response.clone(), ttl, Instant::now()),
+                None => cache.insert(key, response.clone(), Instant::now()),
+            },
+            Some(_) => {}
+            // Any other tool may change what the read-only ones return
+            None => cache.clear(),
+        }
+        Ok(response)
+    }
 
-        Ok(serde_json::to_string(&result)?)
+    /// The cache key of a `tools/call` request forwarded to `server` and the
+    /// TTL the tool declares, or `None` if the cache is off or the called
+    /// tool is not read-only.
+    async fn cache_key(
+        &self,
+        server: &McpServerConfig,
+        request: &serde_json::Value,
+    ) -> Option<(CacheKey, Option<Duration>)> {
+        if !self.cache.lock().await.is_enabled() {
+            return None;
+        }
+        let tool = request.pointer("/params/name")?.as_str()?;
+        let canister_id = server.canister_id.as_str();
+
+        let known = self.read_only_tools.read().await.contains_key(canister_id);
+        if !known {
+            let tools =
+                match call_canister(server, "mcp_list_tools", &serde_json::Value::Null).await {
+                    Ok(list) => read_only_tool_names(&list),
+                    Err(e) => {
+                        warn!("Cannot list tools of {}: {}", canister_id, e);
+                        return None;
+                    }
+                };
+            self.read_only_tools
+                .write()
+                .await
+                .insert(canister_id.to_string(), tools);
+        }
+
+        let ttl = *self
+            .read_only_tools
+            .read()
+            .await
+            .get(canister_id)?
+            .get(tool)?;
+        let arguments = request
+            .pointer("/params/arguments")
+            .and_then(serde_json::Value::as_object);
+        Some((CacheKey::new(canister_id, tool, arguments), ttl))
     }
 
     async fn handle_get_server_info(&self) -> Result<String> {
@@ -196,6 +321,67 @@ impl SimpleBridgeServer {
     }
 }
 
+/// Calls `method` on the canister behind `server` and returns its reply.
+async fn call_canister(
+    server: &McpServerConfig,
+    method: &str,
+    args: &serde_json::Value,
+) -> Result<String> {
+    let response = reqwest::Client::new()
+        .post(&format!("{}/call", server.url))
+        .json(&serde_json::json!({
+            "method": method,
+            "args": args
+        }))
+        .send()
+        .await
+        .map_err(|e| anyhow!("HTTP request failed: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Canister returned error: {}", response.status()));
+    }
+
+    response
+        .text()
+        .await
+        .map_err(|e| anyhow!("Failed to read response: {}", e))
+}
+
+/// Names of the tools an `mcp_list_tools` reply marks `readOnlyHint: true`.
+fn read_only_tool_names(list: &str) -> HashMap<String, Option<Duration>> {
+    let list: serde_json::Value = serde_json::from_str(list).unwrap_or_default();
+    list["tools"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|tool| tool["annotations"]["readOnlyHint"] == true)
+        .filter_map(|tool| {
+            let ttl = tool["inputSchema"]
+                .as_object()
+                .and_then(response_cache::declared_ttl);
+            tool["name"].as_str().map(|name| (name.to_string(), ttl))
+        })
+        .collect()
+}
+
+/// Wraps a canister reply for the client.
+///
+/// A `CallToolResult` is forwarded unchanged so image and resource content
+/// reach the client intact; any other reply is passed along as-is.
+fn call_tool_response(canister_result: &str) -> serde_json::Value {
+    let parsed: Option<serde_json::Value> = serde_json::from_str(canister_result).ok();
+    match parsed {
+        Some(response) if response["result"]["content"].is_array() => {
+            serde_json::json!({ "result": response["result"] })
+        }
+        _ => serde_json::json!({
+            "result": {
+                "content": canister_result
+            }
+        }),
+    }
+}
+
 #[async_trait]
 impl McpBridgeServer for SimpleBridgeServer {
     async fn run(&mut self) -> Result<()> {
@@ -237,6 +423,12 @@ impl McpBridgeServer for SimpleBridgeServer {
                         port: self.port,
                         config: config.clone(),
                         running: running.clone(),
+                        recorder: self.recorder.clone(),
+                        rpc_log: self.rpc_log.clone(),
+                        identity: self.identity.clone(),
+                        profiles: self.profiles.clone(),
+                        cache: self.cache.clone(),
+                        read_only_tools: self.read_only_tools.clone(),
                     };
 
                     // Handle connection in a separate task
@@ -417,6 +609,58 @@ mod tests {
         config
     }
 
+    #[test]
+    fn test_read_only_tool_names() {
+        let list = serde_json::json!({
+            "tools": [
+                { "name": "search", "annotations": { "readOnlyHint": true } },
+                {
+                    "name": "quote",
+                    "inputSchema": { "type": "object", response_cache::CACHE_TTL_SCHEMA_KEY: 10 },
+                    "annotations": { "readOnlyHint": true }
+                },
+                {
+                    "name": "catalog",
+                    "inputSchema": { "type": "object", response_cache::CACHE_TTL_SCHEMA_KEY: 900 },
+                    "annotations": { "readOnlyHint": true }
+                },
+                { "name": "delete", "annotations": { "readOnlyHint": false } },
+                { "name": "create" }
+            ]
+        })
+        .to_string();
+        assert_eq!(
+            read_only_tool_names(&list),
+            HashMap::from([
+                ("search".to_string(), None),
+                ("quote".to_string(), Some(Duration::from_secs(10))),
+                ("catalog".to_string(), Some(Duration::from_secs(900))),
+            ])
+        );
+        assert!(read_only_tool_names("not json").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cache_follows_bridge_config() {
+        let server = SimpleBridgeServer::new("127.0.0.1", 0, create_test_config()).unwrap();
+        assert!(!server.cache.lock().await.is_enabled());
+
+        let config = BridgeConfig {
+            cache_size: 16,
+            ..BridgeConfig::default()
+        };
+        let server = server.with_cache(&config);
+        assert!(server.cache.lock().await.is_enabled());
+
+        // Calls that name no tool are never cached
+        let mcp_config = server.config.read().await.clone();
+        let request = serde_json::json!({ "params": {} });
+        assert!(server
+            .cache_key(&mcp_config.servers[0], &request)
+            .await
+            .is_none());
+    }
+
     #[tokio::test]
     async fn test_bridge_server_creation() {
         let config = create_test_config();
@@ -470,4 +714,35 @@ mod tests {
         let result = server.handle_mcp_request(request).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_list_tools_reports_identity() {
+        let mut profiles = IdentityProfiles::default();
+        profiles.set("rdmx6-jaaaa-aaaaa-aaadq-cai", "local", "alice");
+
+        let server = SimpleBridgeServer::new("127.0.0.1", 0, create_test_config())
+            .unwrap()
+            .with_identity(None, profiles.clone());
+        let response: serde_json::Value =
+            serde_json::from_str(&server.handle_list_tools().await.unwrap()).unwrap();
+        assert_eq!(response["result"]["tools"][0]["identity"], "alice");
+
+        let server = SimpleBridgeServer::new("127.0.0.1", 0, create_test_config())
+            .unwrap()
+            .with_identity(Some("bob".to_string()), profiles);
+        let response: serde_json::Value =
+            serde_json::from_str(&server.handle_list_tools().await.unwrap()).unwrap();
+        assert_eq!(response["result"]["tools"][0]["identity"], "bob");
+    }
+
+    #[test]
+    fn test_call_tool_response_passes_content_through() {
+        let reply = r#"{"jsonrpc":"2.0","id":"1","result":{"content":[{"type":"image","data":"AQID","mimeType":"image/png"}],"isError":false}}"#;
+        let response = call_tool_response(reply);
+        assert_eq!(response["result"]["content"][0]["type"], "image");
+        assert_eq!(response["result"]["content"][0]["data"], "AQID");
+
+        let response = call_tool_response("plain text");
+        assert_eq!(response["result"]["content"], "plain text");
+    }
 }
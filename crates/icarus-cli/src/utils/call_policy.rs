@@ -0,0 +1,229 @@
+//! Retries and a circuit breaker for the bridge's canister calls.
+//!
+//! Transient failures, where the replica or the network did not answer, are
+//! retried with exponential backoff when the call is idempotent (queries and
+//! `tools/list`); tool calls are never retried, since the canister may have
+//! run them. After [`CircuitBreaker`]'s threshold of consecutive transient
+//! failures the breaker opens and calls fail at once with
+//! [`CanisterUnreachable`] until the cooldown ends; the next call then goes
+//! through, closing the breaker if it succeeds and reopening it if not.
+//!
+//! A canister that answers with an error, such as a reject or a missing
+//! method, is reachable and does not count as a failure.
+
+use std::time::{Duration, Instant};
+
+/// How idempotent calls are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RetryPolicy {
+    /// Retries after the first attempt
+    pub(crate) max_retries: u32,
+    /// Wait before the first retry, doubled for each one after it
+    pub(crate) initial_backoff: Duration,
+    /// Longest wait between retries
+    pub(crate) max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `retry`, counting from 0.
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+}
+
+/// A call refused because the breaker is open.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "Canister unreachable after {failures} consecutive failed calls; \
+     the bridge will try again in {}s",
+    retry_in.as_secs().max(1)
+)]
+pub(crate) struct CanisterUnreachable {
+    pub(crate) failures: u32,
+    pub(crate) retry_in: Duration,
+}
+
+/// Counts of what the retry layer did, for logs and diagnostics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallStats {
+    /// Transient failures seen
+    pub failures: u64,
+    /// Retries made
+    pub retries: u64,
+    /// Times the breaker opened
+    pub trips: u64,
+    /// Calls refused while the breaker was open
+    pub rejected: u64,
+}
+
+/// Opens after consecutive transient failures and refuses calls while open.
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    stats: CallStats,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cooldown,
+            consecutive_failures: 0,
+            open_until: None,
+            stats: CallStats::default(),
+        }
+    }
+
+    /// Allows a call unless the breaker is open.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CanisterUnreachable`] while the cooldown lasts.
+    pub(crate) fn check(&mut self, now: Instant) -> Result<(), CanisterUnreachable> {
+        match self.open_until {
+            Some(until) if now < until => {
+                self.stats.rejected += 1;
+                Err(CanisterUnreachable {
+                    failures: self.consecutive_failures,
+                    retry_in: until - now,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Records a call the canister answered, closing the breaker.
+    pub(crate) fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.open_until = None;
+    }
+
+    /// Records a transient failure, returning the error to report if it
+    /// opened the breaker.
+    pub(crate) fn record_failure(&mut self, now: Instant) -> Option<CanisterUnreachable> {
+        self.stats.failures += 1;
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if self.consecutive_failures < self.threshold {
+            return None;
+        }
+        self.open_until = Some(now + self.cooldown);
+        self.stats.trips += 1;
+        Some(CanisterUnreachable {
+            failures: self.consecutive_failures,
+            retry_in: self.cooldown,
+        })
+    }
+
+    pub(crate) fn record_retry(&mut self) {
+        self.stats.retries += 1;
+    }
+
+    pub(crate) fn stats(&self) -> CallStats {
+        self.stats
+    }
+}
+
+/// Returns whether a call failed without the canister answering, so that
+/// trying again may succeed.
+pub(crate) fn is_transient(error: &anyhow::Error) -> bool {
+    const TRANSIENT: [&str; 12] = [
+        "connection refused",
+        "connection reset",
+        "timed out",
+        "timeout",
+        "transport",
+        "429",
+        "502",
+        "503",
+        "504",
+        "too many requests",
+        "service unavailable",
+        "replica is overloaded",
+    ];
+    let message = error.to_string().to_lowercase();
+    TRANSIENT.iter().any(|pattern| message.contains(pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_limit() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(0), Duration::from_millis(200));
+        assert_eq!(policy.backoff(1), Duration::from_millis(400));
+        assert_eq!(policy.backoff(3), Duration::from_millis(1600));
+        assert_eq!(policy.backoff(10), Duration::from_secs(5));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_breaker_trips_and_recovers() {
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new(3, Duration::from_secs(30));
+
+        assert!(breaker.record_failure(start).is_none());
+        assert!(breaker.record_failure(start).is_none());
+        breaker.record_success();
+        assert!(breaker.record_failure(start).is_none());
+        assert!(breaker.record_failure(start).is_none());
+        assert!(breaker.check(start).is_ok());
+        assert_eq!(breaker.record_failure(start).unwrap().failures, 3);
+
+        let err = breaker.check(start + Duration::from_secs(10)).unwrap_err();
+        assert_eq!(err.failures, 3);
+        assert_eq!(err.retry_in, Duration::from_secs(20));
+        assert!(err.to_string().contains("Canister unreachable"));
+
+        // After the cooldown one failure reopens the breaker
+        let later = start + Duration::from_secs(30);
+        assert!(breaker.check(later).is_ok());
+        assert!(breaker.record_failure(later).is_some());
+        assert!(breaker.check(later).is_err());
+
+        breaker.record_success();
+        assert!(breaker.check(later).is_ok());
+        assert_eq!(
+            breaker.stats(),
+            CallStats {
+                failures: 6,
+                retries: 0,
+                trips: 2,
+                rejected: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(&anyhow!(
+            "dfx call failed: error sending request: Connection refused (os error 111)"
+        )));
+        assert!(is_transient(&anyhow!(
+            "dfx call failed: The replica returned an HTTP Error: Http Error: status 503 Service Unavailable"
+        )));
+        assert!(!is_transient(&anyhow!(
+            "dfx call failed: Canister abc has no query method 'mcp_tools_sequence'"
+        )));
+        assert!(!is_transient(&anyhow!(
+            "dfx call failed: The replica returned a rejection error: reject code CanisterError"
+        )));
+    }
+}
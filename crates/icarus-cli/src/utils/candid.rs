@@ -0,0 +1,210 @@
+//! Candid interface extraction and drift detection
+//!
+//! `ic_cdk::export_candid!()` embeds the canister's interface in the WASM
+//! module, and `candid-extractor` reads it back out. The `.did` file dfx.json
+//! points at is generated from it rather than edited by hand, and comparing
+//! the two shows when a committed file no longer matches the code.
+
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::process::Command;
+
+use super::project;
+
+/// Lines of unchanged context shown around each change in a diff
+const DIFF_CONTEXT: usize = 2;
+
+/// How the `.did` file compares with the compiled interface
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum CandidStatus {
+    UpToDate,
+    /// The file was rewritten; `diff` shows what changed
+    Updated {
+        diff: String,
+    },
+    /// The file differs and was left alone (check mode)
+    OutOfDate {
+        diff: String,
+    },
+}
+
+/// Result of [`refresh`] for one `.did` file
+#[derive(Debug, Clone)]
+pub(crate) struct CandidRefresh {
+    pub(crate) did_path: PathBuf,
+    pub(crate) status: CandidStatus,
+}
+
+pub(crate) fn is_extractor_available() -> bool {
+    which::which("candid-extractor").is_ok()
+}
+
+/// Path of the WASM module cargo builds for `package` with `profile`
+pub(crate) fn wasm_path(project_root: &Path, package: &str, profile: &str) -> PathBuf {
+    project_root
+        .join("target")
+        .join("wasm32-unknown-unknown")
+        .join(profile)
+        .join(format!("{}.wasm", package.replace('-', "_")))
+}
+
+/// Extracts the Candid interface embedded in the module at `wasm_path`
+pub(crate) async fn extract(wasm_path: &Path) -> Result<String> {
+    let output = Command::new("candid-extractor")
+        .arg(wasm_path)
+        .output()
+        .await
+        .context("Failed to execute candid-extractor")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("candid-extractor failed: {}", stderr));
+    }
+
+    String::from_utf8(output.stdout).context("candid-extractor printed invalid UTF-8")
+}
+
+/// Compares `package`'s `.did` file with the interface in its built module,
+/// rewriting the file unless `check` is set
+pub(crate) async fn refresh(
+    project_root: &Path,
+    package: &str,
+    profile: &str,
+    check: bool,
+) -> Result<CandidRefresh> {
+    let extracted = extract(&wasm_path(project_root, package, profile)).await?;
+    let did_path = project::candid_file(project_root, package).await;
+    let committed = match fs::read_to_string(&did_path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", did_path.display()));
+        }
+    };
+
+    let status = match diff(&committed, &extracted) {
+        None => CandidStatus::UpToDate,
+        Some(diff) if check => CandidStatus::OutOfDate { diff },
+        Some(diff) => {
+            fs::write(&did_path, &extracted)
+                .await
+                .with_context(|| format!("Failed to write {}", did_path.display()))?;
+            CandidStatus::Updated { diff }
+        }
+    };
+
+    Ok(CandidRefresh { did_path, status })
+}
+
+/// Line diff from `old` to `new`, or `None` if they differ only in trailing
+/// whitespace
+///
+/// Removed lines start with `-`, added lines with `+` and context lines with
+/// a space; `...` separates changes that are far apart.
+pub(crate) fn diff(old: &str, new: &str) -> Option<String> {
+    let old: Vec<&str> = old.lines().map(str::trim_end).collect();
+    let new: Vec<&str> = new.lines().map(str::trim_end).collect();
+    let trim =
+        |lines: &[&str]| lines.len() - lines.iter().rev().take_while(|l| l.is_empty()).count();
+    let (old, new) = (&old[..trim(&old)], &new[..trim(&new)]);
+    if old == new {
+        return None;
+    }
+
+    // Longest common subsequence table, filled from the end
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines: Vec<(char, &str)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(('-', old[i]));
+            i += 1;
+        } else {
+            lines.push(('+', new[j]));
+            j += 1;
+        }
+    }
+
+    let near_change = |index: usize| {
+        let start = index.saturating_sub(DIFF_CONTEXT);
+        let end = (index + DIFF_CONTEXT + 1).min(lines.len());
+        lines[start..end].iter().any(|(tag, _)| *tag != ' ')
+    };
+
+    let mut out = String::new();
+    let mut skipped = false;
+    for (index, (tag, line)) in lines.iter().enumerate() {
+        if near_change(index) {
+            if skipped && !out.is_empty() {
+                out.push_str("...\n");
+            }
+            skipped = false;
+            out.push(*tag);
+            out.push_str(line);
+            out.push('\n');
+        } else {
+            skipped = true;
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVICE: &str = "service : {\n  echo : (text) -> (text) query;\n  list : () -> (vec text) query;\n  get_info : () -> (text) query;\n  version : () -> (text) query;\n  health : () -> (text) query;\n  ping : () -> () query;\n  stats : () -> (nat64) query;\n}\n";
+
+    #[test]
+    fn test_diff_ignores_trailing_whitespace() {
+        assert_eq!(diff(SERVICE, &format!("{SERVICE}\n\n")), None);
+        assert_eq!(diff(SERVICE, &SERVICE.replace('\n', " \r\n")), None);
+    }
+
+    #[test]
+    fn test_diff_shows_changes_with_context() {
+        let updated = SERVICE
+            .replace("  echo : (text) -> (text) query;\n", "")
+            .replace("stats : () -> (nat64)", "stats : () -> (nat)");
+
+        let expected = [
+            " service : {",
+            "-  echo : (text) -> (text) query;",
+            "   list : () -> (vec text) query;",
+            "   get_info : () -> (text) query;",
+            "...",
+            "   health : () -> (text) query;",
+            "   ping : () -> () query;",
+            "-  stats : () -> (nat64) query;",
+            "+  stats : () -> (nat) query;",
+            " }",
+            "",
+        ];
+        assert_eq!(diff(SERVICE, &updated).unwrap(), expected.join("\n"));
+
+        assert_eq!(diff("", "service : {}\n").unwrap(), "+service : {}\n");
+    }
+
+    #[test]
+    fn test_wasm_path() {
+        assert_eq!(
+            wasm_path(Path::new("/p"), "weather-bot", "release"),
+            Path::new("/p/target/wasm32-unknown-unknown/release/weather_bot.wasm")
+        );
+    }
+}
@@ -185,6 +185,48 @@ pub fn get_continue_config_path() -> Result<PathBuf> {
     Ok(path)
 }
 
+/// Get Cursor's global MCP configuration path (`~/.cursor/mcp.json` on every OS)
+pub fn get_cursor_config_path() -> Result<PathBuf> {
+    let home_dir = dirs::home_dir().ok_or_else(|| anyhow!("Could not determine home directory"))?;
+    Ok(home_dir.join(".cursor").join("mcp.json"))
+}
+
+/// Get Windsurf's MCP configuration path (`~/.codeium/windsurf/mcp_config.json` on every OS)
+pub fn get_windsurf_config_path() -> Result<PathBuf> {
+    let home_dir = dirs::home_dir().ok_or_else(|| anyhow!("Could not determine home directory"))?;
+    Ok(home_dir
+        .join(".codeium")
+        .join("windsurf")
+        .join("mcp_config.json"))
+}
+
+/// Get Zed's settings path, where context servers are configured
+pub fn get_zed_config_path() -> Result<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let path = dirs::config_dir()
+        .ok_or_else(|| anyhow!("Could not determine config directory"))?
+        .join("Zed")
+        .join("settings.json");
+
+    // Zed uses ~/.config on macOS too, not ~/Library/Application Support
+    #[cfg(not(target_os = "windows"))]
+    let path = dirs::home_dir()
+        .ok_or_else(|| anyhow!("Could not determine home directory"))?
+        .join(".config")
+        .join("zed")
+        .join("settings.json");
+
+    Ok(path)
+}
+
+/// Checks whether a client's configuration directory exists, which the
+/// editors create on first launch
+fn config_dir_exists(path: Result<PathBuf>) -> bool {
+    path.ok()
+        .and_then(|path| path.parent().map(|dir| dir.exists()))
+        .unwrap_or(false)
+}
+
 /// Detect installed AI clients
 pub fn detect_installed_clients() -> Vec<String> {
     let mut clients = Vec::new();
@@ -208,6 +250,19 @@ pub fn detect_installed_clients() -> Vec<String> {
         clients.push("continue".to_string());
     }
 
+    // Editors with their own MCP settings
+    if config_dir_exists(get_cursor_config_path()) {
+        clients.push("cursor".to_string());
+    }
+
+    if config_dir_exists(get_windsurf_config_path()) {
+        clients.push("windsurf".to_string());
+    }
+
+    if config_dir_exists(get_zed_config_path()) {
+        clients.push("zed".to_string());
+    }
+
     clients
 }
 
@@ -248,6 +303,9 @@ pub fn get_all_client_configs() -> Vec<(String, Result<PathBuf>)> {
             get_chatgpt_desktop_config_path(),
         ),
         ("continue".to_string(), get_continue_config_path()),
+        ("cursor".to_string(), get_cursor_config_path()),
+        ("windsurf".to_string(), get_windsurf_config_path()),
+        ("zed".to_string(), get_zed_config_path()),
     ]
 }
 
@@ -264,6 +322,15 @@ mod tests {
             assert!(get_chatgpt_desktop_config_path().is_ok());
             assert!(get_continue_config_path().is_ok());
         }
+        if dirs::home_dir().is_some() {
+            assert!(get_cursor_config_path()
+                .unwrap()
+                .ends_with(".cursor/mcp.json"));
+            assert!(get_windsurf_config_path()
+                .unwrap()
+                .ends_with("windsurf/mcp_config.json"));
+            assert!(get_zed_config_path().unwrap().ends_with("settings.json"));
+        }
     }
 
     #[test]
@@ -277,7 +344,7 @@ mod tests {
     #[test]
     fn test_all_client_configs() {
         let configs = get_all_client_configs();
-        assert_eq!(configs.len(), 4);
+        assert_eq!(configs.len(), 7);
 
         // Check that all expected clients are included
         let client_names: Vec<&str> = configs.iter().map(|(name, _)| name.as_str()).collect();
@@ -285,6 +352,9 @@ mod tests {
         assert!(client_names.contains(&"claude-code"));
         assert!(client_names.contains(&"chatgpt-desktop"));
         assert!(client_names.contains(&"continue"));
+        assert!(client_names.contains(&"cursor"));
+        assert!(client_names.contains(&"windsurf"));
+        assert!(client_names.contains(&"zed"));
     }
 
     #[test]
@@ -0,0 +1,632 @@
+//! Internet Identity login for users without dfx.
+//!
+//! [`login`] generates a session key, opens the browser on a page served from
+//! localhost that asks Internet Identity to delegate to that key, and receives
+//! the delegation chain back on a one-time callback URL. The chain and session
+//! key are kept in [`DelegationStore`], encrypted with ChaCha20-Poly1305 under a
+//! key file that only the user can read, and [`fresh_delegation`] logs in
+//! again once the stored chain is close to expiring.
+//!
+//! The key file sits next to the ciphertext, so the encryption is obfuscation
+//! only: it keeps the session key out of plain-text greps and stray copies of
+//! the delegation file, but anyone who can read both files can use the
+//! delegation. The protection is the files' owner-only permissions.
+
+#![allow(dead_code)]
+
+use anyhow::{anyhow, Context, Result};
+use candid::Principal;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use ic_agent::identity::{BasicIdentity, DelegatedIdentity, Delegation, SignedDelegation};
+use ic_agent::Identity;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info};
+
+/// Internet Identity on mainnet
+pub(crate) const II_URL: &str = "https://identity.ic0.app";
+
+/// Lifetime requested for new delegations
+pub(crate) const DEFAULT_TTL: Duration = Duration::from_secs(8 * 60 * 60);
+
+/// How long before expiry a delegation is replaced
+pub(crate) const REFRESH_MARGIN: Duration = Duration::from_secs(10 * 60);
+
+/// How long to wait for the user to finish logging in
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+const NONCE_LEN: usize = 12;
+
+/// A delegation chain from Internet Identity to a local session key.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct StoredDelegation {
+    /// Ed25519 secret key the chain delegates to
+    session_key: Vec<u8>,
+    /// DER public key of the user's Internet Identity principal
+    user_public_key: Vec<u8>,
+    delegations: Vec<SignedDelegation>,
+    /// Identity provider the chain was obtained from
+    identity_provider: String,
+}
+
+impl StoredDelegation {
+    /// When the chain stops being valid, in nanoseconds since the epoch.
+    pub(crate) fn expiration(&self) -> u64 {
+        self.delegations
+            .iter()
+            .map(|signed| signed.delegation.expiration)
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Whether the chain expires within `margin` of `now_ns`.
+    pub(crate) fn expires_within(&self, now_ns: u64, margin: Duration) -> bool {
+        let margin_ns = u64::try_from(margin.as_nanos()).unwrap_or(u64::MAX);
+        self.expiration() <= now_ns.saturating_add(margin_ns)
+    }
+
+    /// Whether the chain should be replaced before making more calls.
+    pub(crate) fn needs_refresh(&self) -> bool {
+        self.expires_within(now_ns(), REFRESH_MARGIN)
+    }
+
+    /// The user's principal, which canisters see as the caller.
+    pub(crate) fn principal(&self) -> Result<Principal> {
+        Ok(Principal::self_authenticating(&self.user_public_key))
+    }
+
+    pub(crate) fn identity_provider(&self) -> &str {
+        &self.identity_provider
+    }
+
+    /// The identity that signs calls as the user's principal.
+    pub(crate) fn identity(&self) -> Result<DelegatedIdentity> {
+        let session = session_identity(&self.session_key)?;
+        DelegatedIdentity::new(
+            self.user_public_key.clone(),
+            Box::new(session),
+            self.delegations.clone(),
+        )
+        .map_err(|e| anyhow!("Invalid delegation chain: {}", e))
+    }
+}
+
+/// Encrypted on-disk storage for a [`StoredDelegation`].
+pub(crate) struct DelegationStore {
+    dir: PathBuf,
+}
+
+impl DelegationStore {
+    pub(crate) fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The store in the Icarus config directory.
+    pub(crate) fn default_location() -> Result<Self> {
+        let config_dir =
+            dirs::config_dir().ok_or_else(|| anyhow!("Could not determine config directory"))?;
+        Ok(Self::new(config_dir.join("icarus")))
+    }
+
+    fn delegation_path(&self) -> PathBuf {
+        self.dir.join("delegation.enc")
+    }
+
+    fn key_path(&self) -> PathBuf {
+        self.dir.join("delegation.key")
+    }
+
+    /// Loads the stored delegation, if there is one.
+    pub(crate) async fn load(&self) -> Result<Option<StoredDelegation>> {
+        let path = self.delegation_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let data = tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let plaintext = decrypt(&self.key().await?, &data)?;
+        let delegation = serde_json::from_slice(&plaintext)
+            .with_context(|| "Failed to parse stored delegation")?;
+        Ok(Some(delegation))
+    }
+
+    /// Encrypts and saves `delegation`, replacing any previous one.
+    pub(crate) async fn save(&self, delegation: &StoredDelegation) -> Result<()> {
+        let plaintext = serde_json::to_vec(delegation)?;
+        let data = encrypt(&self.key().await?, &plaintext)?;
+        write_private(&self.delegation_path(), &data).await
+    }
+
+    /// Deletes the stored delegation and its key.
+    pub(crate) async fn clear(&self) -> Result<()> {
+        for path in [self.delegation_path(), self.key_path()] {
+            if path.exists() {
+                tokio::fs::remove_file(&path)
+                    .await
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the encryption key, creating it on first use.
+    async fn key(&self) -> Result<Key> {
+        let path = self.key_path();
+        if path.exists() {
+            let bytes = tokio::fs::read(&path)
+                .await
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            if bytes.len() != 32 {
+                return Err(anyhow!("Corrupt delegation key: {}", path.display()));
+            }
+            return Ok(*Key::from_slice(&bytes));
+        }
+
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        write_private(&path, &key).await?;
+        Ok(key)
+    }
+}
+
+/// Writes a file only the current user can read.
+///
+/// The data goes to a fresh temporary file created with mode `0600`, which is
+/// then renamed over `path`, so the contents are never readable by others,
+/// not even between creating and restricting the file.
+async fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    // A temporary file left by an interrupted write may have any mode
+    match tokio::fs::remove_file(&temp_path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(e).with_context(|| format!("Failed to remove {}", temp_path.display()));
+        }
+        _ => {}
+    }
+
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options
+        .open(&temp_path)
+        .await
+        .with_context(|| format!("Failed to create {}", temp_path.display()))?;
+    file.write_all(data)
+        .await
+        .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+    file.sync_all().await?;
+    drop(file);
+
+    tokio::fs::rename(&temp_path, path)
+        .await
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn encrypt(key: &Key, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(key)
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| anyhow!("Failed to encrypt delegation"))?;
+
+    let mut data = nonce.to_vec();
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+fn decrypt(key: &Key, data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < NONCE_LEN {
+        return Err(anyhow!("Stored delegation is truncated"));
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Failed to decrypt stored delegation; log in again"))
+}
+
+fn session_identity(secret: &[u8]) -> Result<BasicIdentity> {
+    let secret: &[u8; 32] = secret
+        .try_into()
+        .map_err(|_| anyhow!("Invalid session key in stored delegation"))?;
+    Ok(BasicIdentity::from_raw_key(secret))
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| u64::try_from(d.as_nanos()).unwrap_or(u64::MAX))
+        .unwrap_or(0)
+}
+
+/// Returns the stored delegation, logging in again through the browser when
+/// there is none or it is about to expire.
+pub(crate) async fn fresh_delegation(
+    store: &DelegationStore,
+    identity_provider: &str,
+    ttl: Duration,
+) -> Result<StoredDelegation> {
+    match store.load().await {
+        Ok(Some(delegation)) if !delegation.needs_refresh() => {
+            return Ok(delegation);
+        }
+        Ok(Some(_)) => info!("Internet Identity delegation expires soon, logging in again"),
+        Ok(None) => info!("No Internet Identity delegation stored, logging in"),
+        Err(e) => info!("Ignoring unreadable delegation ({}), logging in", e),
+    }
+
+    let delegation = login(identity_provider, ttl).await?;
+    store.save(&delegation).await?;
+    Ok(delegation)
+}
+
+/// Logs in with Internet Identity in the browser and returns the delegation
+/// chain to a new session key.
+pub(crate) async fn login(identity_provider: &str, ttl: Duration) -> Result<StoredDelegation> {
+    let session_key: [u8; 32] = rand::random();
+    let session_public_key = session_identity(&session_key)?
+        .public_key()
+        .ok_or_else(|| anyhow!("Session identity has no public key"))?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    let token = nanoid::nanoid!(24);
+    let page = login_page(identity_provider, &session_public_key, ttl, &token);
+
+    let url = format!("http://127.0.0.1:{}/", port);
+    println!("Opening {} to log in with Internet Identity", url);
+    if let Err(e) = open_browser(&url) {
+        println!(
+            "Could not open a browser ({}); open the URL above manually",
+            e
+        );
+    }
+
+    let callback = tokio::time::timeout(LOGIN_TIMEOUT, serve_login(&listener, &page, &token))
+        .await
+        .map_err(|_| anyhow!("Timed out waiting for Internet Identity login"))??;
+
+    let delegation = callback.into_delegation(session_key.to_vec(), identity_provider)?;
+    // Fails early on a chain that does not lead to the session key
+    delegation.identity()?;
+    Ok(delegation)
+}
+
+/// Body the login page posts to the callback URL.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LoginCallback {
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    user_public_key: String,
+    #[serde(default)]
+    delegations: Vec<CallbackDelegation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CallbackDelegation {
+    pubkey: String,
+    expiration: String,
+    signature: String,
+}
+
+impl LoginCallback {
+    fn into_delegation(
+        self,
+        session_key: Vec<u8>,
+        identity_provider: &str,
+    ) -> Result<StoredDelegation> {
+        if let Some(error) = self.error {
+            return Err(anyhow!("Internet Identity login failed: {}", error));
+        }
+        if self.delegations.is_empty() {
+            return Err(anyhow!("Internet Identity returned no delegations"));
+        }
+
+        let delegations = self
+            .delegations
+            .into_iter()
+            .map(|d| {
+                Ok(SignedDelegation {
+                    delegation: Delegation {
+                        pubkey: from_hex(&d.pubkey)?,
+                        expiration: d
+                            .expiration
+                            .parse()
+                            .map_err(|_| anyhow!("Invalid delegation expiration"))?,
+                        targets: None,
+                    },
+                    signature: from_hex(&d.signature)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(StoredDelegation {
+            session_key,
+            user_public_key: from_hex(&self.user_public_key)?,
+            delegations,
+            identity_provider: identity_provider.to_string(),
+        })
+    }
+}
+
+/// Serves the login page until the callback arrives.
+async fn serve_login(listener: &TcpListener, page: &str, token: &str) -> Result<LoginCallback> {
+    let callback_path = format!("/callback/{}", token);
+
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let (method, path, body) = match read_request(&mut stream).await {
+            Ok(request) => request,
+            Err(e) => {
+                debug!("Ignoring malformed login request: {}", e);
+                continue;
+            }
+        };
+
+        match (method.as_str(), path.as_str()) {
+            ("GET", "/") => respond(&mut stream, "200 OK", "text/html", page).await?,
+            ("POST", path) if path == callback_path => {
+                let callback: Result<LoginCallback> =
+                    serde_json::from_slice(&body).with_context(|| "Invalid login callback");
+                let message = match &callback {
+                    Ok(LoginCallback { error: None, .. }) => {
+                        "Logged in. You can close this window."
+                    }
+                    _ => "Login failed. See the terminal for details.",
+                };
+                respond(&mut stream, "200 OK", "text/plain", message).await?;
+                return callback;
+            }
+            _ => respond(&mut stream, "404 Not Found", "text/plain", "Not Found").await?,
+        }
+    }
+}
+
+/// Reads the method, path and body of an HTTP/1.1 request.
+async fn read_request(stream: &mut TcpStream) -> Result<(String, String, Vec<u8>)> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().ok_or_else(|| anyhow!("Missing method"))?;
+    let path = parts.next().ok_or_else(|| anyhow!("Missing path"))?;
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).await?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse()?;
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+    Ok((method.to_string(), path.to_string(), body))
+}
+
+async fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+fn open_browser(url: &str) -> std::io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    command.arg(url).spawn().map(|_| ())
+}
+
+/// The page that runs the Internet Identity authorize protocol and posts the
+/// result back to the CLI.
+fn login_page(
+    identity_provider: &str,
+    session_public_key: &[u8],
+    ttl: Duration,
+    token: &str,
+) -> String {
+    LOGIN_PAGE
+        .replace("{{PROVIDER}}", identity_provider)
+        .replace("{{SESSION_KEY}}", &to_hex(session_public_key))
+        .replace("{{TTL_NS}}", &ttl.as_nanos().to_string())
+        .replace("{{TOKEN}}", token)
+}
+
+const LOGIN_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"><title>Icarus login</title></head>
+<body style="font-family: sans-serif; text-align: center; margin-top: 4em">
+<h2>Connect Icarus to your canisters</h2>
+<button id="login" style="font-size: 1.2em">Log in with Internet Identity</button>
+<p id="status"></p>
+<script>
+const PROVIDER = "{{PROVIDER}}";
+const hex = (bytes) => Array.from(new Uint8Array(bytes), (b) => b.toString(16).padStart(2, "0")).join("");
+const unhex = (s) => new Uint8Array(s.match(/../g).map((b) => parseInt(b, 16)));
+const status = (text) => { document.getElementById("status").textContent = text; };
+const finish = (body) => fetch("/callback/{{TOKEN}}", { method: "POST", body: JSON.stringify(body) })
+  .then((response) => response.text()).then(status);
+
+document.getElementById("login").onclick = () => {
+  const idp = window.open(PROVIDER + "/#authorize", "idpWindow");
+  window.addEventListener("message", (event) => {
+    if (event.origin !== new URL(PROVIDER).origin) return;
+    const message = event.data;
+    if (message.kind === "authorize-ready") {
+      idp.postMessage({
+        kind: "authorize-client",
+        sessionPublicKey: unhex("{{SESSION_KEY}}"),
+        maxTimeToLive: BigInt("{{TTL_NS}}"),
+      }, PROVIDER);
+    } else if (message.kind === "authorize-client-success") {
+      idp.close();
+      finish({
+        userPublicKey: hex(message.userPublicKey),
+        delegations: message.delegations.map((d) => ({
+          pubkey: hex(d.delegation.pubkey),
+          expiration: d.delegation.expiration.toString(),
+          signature: hex(d.signature),
+        })),
+      });
+    } else if (message.kind === "authorize-client-failure") {
+      idp.close();
+      finish({ error: message.text });
+    }
+  });
+  status("Waiting for Internet Identity...");
+};
+</script>
+</body>
+</html>
+"#;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return Err(anyhow!("Invalid hex: odd length"));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| anyhow!("Invalid hex: {}", s)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn sample_delegation(expiration: u64) -> StoredDelegation {
+        StoredDelegation {
+            session_key: vec![7; 32],
+            user_public_key: vec![1, 2, 3],
+            delegations: vec![SignedDelegation {
+                delegation: Delegation {
+                    pubkey: vec![4, 5, 6],
+                    expiration,
+                    targets: None,
+                },
+                signature: vec![8, 9],
+            }],
+            identity_provider: II_URL.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_expires_within() {
+        let delegation = sample_delegation(1_000_000_000_000);
+        assert!(!delegation.expires_within(0, Duration::from_secs(60)));
+        assert!(delegation.expires_within(1_000_000_000_000, Duration::ZERO));
+        assert!(delegation.expires_within(999_000_000_000, Duration::from_secs(60)));
+    }
+
+    #[tokio::test]
+    async fn test_store_encrypts_delegation() {
+        let dir = TempDir::new().unwrap();
+        let store = DelegationStore::new(dir.path());
+        assert!(store.load().await.unwrap().is_none());
+
+        store.save(&sample_delegation(42)).await.unwrap();
+        let on_disk = std::fs::read(dir.path().join("delegation.enc")).unwrap();
+        assert!(!String::from_utf8_lossy(&on_disk).contains(II_URL));
+
+        let loaded = store.load().await.unwrap().unwrap();
+        assert_eq!(loaded.expiration(), 42);
+        assert_eq!(loaded.identity_provider(), II_URL);
+
+        // Without its key the delegation cannot be read
+        std::fs::remove_file(dir.path().join("delegation.key")).unwrap();
+        assert!(store.load().await.is_err());
+
+        store.clear().await.unwrap();
+        assert!(store.load().await.unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_store_files_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        let store = DelegationStore::new(dir.path());
+        store.save(&sample_delegation(1)).await.unwrap();
+        store.save(&sample_delegation(2)).await.unwrap();
+
+        for name in ["delegation.enc", "delegation.key"] {
+            let mode = std::fs::metadata(dir.path().join(name))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600, "{name}");
+        }
+        assert!(!dir.path().join("delegation.enc.tmp").exists());
+        assert_eq!(store.load().await.unwrap().unwrap().expiration(), 2);
+    }
+
+    #[test]
+    fn test_login_callback() {
+        let callback: LoginCallback = serde_json::from_str(
+            r#"{"userPublicKey":"0102","delegations":[{"pubkey":"0a0b","expiration":"1700000000000000000","signature":"ff"}]}"#,
+        )
+        .unwrap();
+        let delegation = callback.into_delegation(vec![0; 32], II_URL).unwrap();
+        assert_eq!(delegation.user_public_key, [1, 2]);
+        assert_eq!(delegation.expiration(), 1_700_000_000_000_000_000);
+        assert_eq!(delegation.delegations[0].signature, [0xff]);
+
+        let failed: LoginCallback = serde_json::from_str(r#"{"error":"UserInterrupt"}"#).unwrap();
+        assert!(failed.into_delegation(vec![0; 32], II_URL).is_err());
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        assert_eq!(to_hex(&[0, 15, 255]), "000fff");
+        assert_eq!(from_hex("000fff").unwrap(), [0, 15, 255]);
+        assert!(from_hex("abc").is_err());
+        assert!(from_hex("zz").is_err());
+    }
+}
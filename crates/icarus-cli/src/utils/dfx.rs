@@ -179,6 +179,97 @@ pub(crate) async fn get_canister_logs(
     Ok(logs.to_string())
 }
 
+/// Call a canister query method and return the raw Candid reply bytes
+pub(crate) async fn query_canister_raw(
+    canister_id: &str,
+    method: &str,
+    args: &str,
+    network: &str,
+) -> Result<Vec<u8>> {
+    let output = Command::new("dfx")
+        .args([
+            "canister",
+            "call",
+            canister_id,
+            method,
+            args,
+            "--query",
+            "--network",
+            network,
+            "--output",
+            "raw",
+        ])
+        .output()
+        .await
+        .with_context(|| format!("Failed to call {}", method))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("dfx canister call {} failed: {}", method, stderr));
+    }
+
+    decode_hex(String::from_utf8_lossy(&output.stdout).trim())
+}
+
+/// Call a canister method with Candid-encoded arguments and return the raw
+/// Candid reply bytes
+pub(crate) async fn call_canister_raw(
+    canister_id: &str,
+    method: &str,
+    args: &[u8],
+    network: &str,
+    query: bool,
+) -> Result<Vec<u8>> {
+    let mut command = Command::new("dfx");
+    command.args([
+        "canister",
+        "call",
+        canister_id,
+        method,
+        &encode_hex(args),
+        "--type",
+        "raw",
+        "--network",
+        network,
+        "--output",
+        "raw",
+    ]);
+    if query {
+        command.arg("--query");
+    }
+
+    let output = command
+        .output()
+        .await
+        .with_context(|| format!("Failed to call {}", method))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("dfx canister call {} failed: {}", method, stderr));
+    }
+
+    decode_hex(String::from_utf8_lossy(&output.stdout).trim())
+}
+
+/// Encode bytes as the hex string `dfx canister call --type raw` expects
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode the hex string printed by `dfx canister call --output raw`
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return Err(anyhow!("Invalid hex reply: {}", hex));
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16).map_err(|e| anyhow!("Invalid hex reply: {}", e))
+        })
+        .collect()
+}
+
 /// Create a new dfx identity
 pub(crate) async fn create_identity(name: &str) -> Result<()> {
     let output = Command::new("dfx")
@@ -302,6 +393,22 @@ pub(crate) async fn install_dfx() -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_decode_hex() {
+        assert_eq!(decode_hex("4449444c").unwrap(), b"DIDL".to_vec());
+        assert!(decode_hex("abc").is_err());
+        assert!(decode_hex("zz").is_err());
+    }
+
+    #[test]
+    fn test_encode_hex_round_trip() {
+        assert_eq!(encode_hex(b"DIDL"), "4449444c");
+        assert_eq!(
+            decode_hex(&encode_hex(&[0, 15, 255])).unwrap(),
+            vec![0, 15, 255]
+        );
+    }
     use tempfile::TempDir;
 
     #[tokio::test]
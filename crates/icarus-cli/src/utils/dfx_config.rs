@@ -0,0 +1,384 @@
+//! Typed access to a project's dfx.json and canister_ids.json
+//!
+//! The CLI scaffolds dfx.json in `icarus new` and reads back the IDs dfx
+//! assigned after `icarus deploy`. Both files are also edited by hand and by
+//! dfx itself, so fields this module does not model are kept as they are and
+//! written back unchanged.
+//!
+//! ```no_run
+//! # async fn example(project_root: &std::path::Path) -> anyhow::Result<()> {
+//! use icarus_cli::{CanisterIds, DfxCanister, DfxJson};
+//!
+//! let mut dfx = DfxJson::load(project_root).await?;
+//! dfx.add_canister("weather", DfxCanister::rust("weather"));
+//! dfx.set_build_command("weather", vec!["icarus build".to_string()])?;
+//! dfx.save(project_root).await?;
+//!
+//! let ids = CanisterIds::load(project_root, "ic").await?;
+//! println!("{:?}", ids.get("weather", "ic"));
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Fields a type does not model, kept so that saving does not drop them
+type Extra = serde_json::Map<String, serde_json::Value>;
+
+/// dfx.json of a project
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DfxJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    #[serde(default)]
+    pub canisters: BTreeMap<String, DfxCanister>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub networks: BTreeMap<String, DfxNetwork>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// A canister entry in dfx.json
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DfxCanister {
+    #[serde(rename = "type")]
+    pub canister_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<BuildCommand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasm: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// A canister's `build` entry, which dfx accepts as one command or a list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BuildCommand {
+    Single(String),
+    Steps(Vec<String>),
+}
+
+/// A network entry in dfx.json
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DfxNetwork {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub network_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub providers: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+impl DfxCanister {
+    /// A Rust canister built from `package` the way `icarus new` sets it up
+    pub fn rust(package: &str) -> Self {
+        Self {
+            canister_type: "rust".to_string(),
+            package: Some(package.to_string()),
+            candid: Some(format!("{package}.did")),
+            build: Some(BuildCommand::Steps(vec![format!(
+                "cargo build --target wasm32-unknown-unknown --release --package {package}"
+            )])),
+            wasm: Some(format!(
+                "target/wasm32-unknown-unknown/release/{}.wasm",
+                package.replace('-', "_")
+            )),
+            extra: Extra::new(),
+        }
+    }
+}
+
+impl BuildCommand {
+    /// The commands in the order dfx runs them
+    pub fn steps(&self) -> Vec<&str> {
+        match self {
+            Self::Single(command) => vec![command.as_str()],
+            Self::Steps(commands) => commands.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+impl DfxJson {
+    /// dfx.json for a project with a single Rust canister named `package`
+    pub fn for_rust_canister(package: &str) -> Self {
+        let mut dfx = Self {
+            version: Some(1),
+            ..Self::default()
+        };
+        dfx.add_canister(package, DfxCanister::rust(package));
+        dfx.extra.insert(
+            "defaults".to_string(),
+            serde_json::json!({ "build": { "packtool": "" } }),
+        );
+        dfx
+    }
+
+    /// Path of dfx.json in `project_root`
+    pub fn path(project_root: &Path) -> PathBuf {
+        project_root.join("dfx.json")
+    }
+
+    /// Loads dfx.json from `project_root`
+    pub async fn load(project_root: &Path) -> Result<Self> {
+        let path = Self::path(project_root);
+        let content = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read dfx.json: {}", path.display()))?;
+
+        serde_json::from_str(&content).with_context(|| "Failed to parse dfx.json")
+    }
+
+    /// Writes dfx.json to `project_root`
+    pub async fn save(&self, project_root: &Path) -> Result<()> {
+        let path = Self::path(project_root);
+        let mut content =
+            serde_json::to_string_pretty(self).with_context(|| "Failed to serialize dfx.json")?;
+        content.push('\n');
+
+        fs::write(&path, content)
+            .await
+            .with_context(|| format!("Failed to write dfx.json: {}", path.display()))
+    }
+
+    pub fn canister(&self, name: &str) -> Option<&DfxCanister> {
+        self.canisters.get(name)
+    }
+
+    /// Adds `canister` under `name`, returning the entry it replaced
+    pub fn add_canister(&mut self, name: &str, canister: DfxCanister) -> Option<DfxCanister> {
+        self.canisters.insert(name.to_string(), canister)
+    }
+
+    /// Replaces the build commands of canister `name`
+    pub fn set_build_command(&mut self, name: &str, steps: Vec<String>) -> Result<()> {
+        let canister = self
+            .canisters
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("Canister '{}' is not defined in dfx.json", name))?;
+        canister.build = Some(BuildCommand::Steps(steps));
+        Ok(())
+    }
+
+    /// Whether canister IDs on `network` are kept in the project's
+    /// canister_ids.json rather than under `.dfx`
+    ///
+    /// dfx treats `ic` and networks declared with `"type": "persistent"` this
+    /// way; everything else, such as the local replica, is ephemeral.
+    pub fn is_persistent(&self, network: &str) -> bool {
+        network == "ic"
+            || self
+                .networks
+                .get(network)
+                .and_then(|n| n.network_type.as_deref())
+                == Some("persistent")
+    }
+}
+
+/// Canister IDs by canister name and then network, as in canister_ids.json
+///
+/// ```json
+/// { "weather": { "ic": "rdmx6-jaaaa-aaaaa-aaadq-cai" } }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CanisterIds(BTreeMap<String, BTreeMap<String, String>>);
+
+impl CanisterIds {
+    /// Path of the file dfx keeps `network`'s canister IDs in
+    ///
+    /// IDs on persistent networks live in the project's canister_ids.json;
+    /// IDs on other networks live in `.dfx/<network>/canister_ids.json`. A
+    /// project without dfx.json is treated as having no persistent networks
+    /// besides `ic`.
+    pub async fn path(project_root: &Path, network: &str) -> PathBuf {
+        let persistent = match DfxJson::load(project_root).await {
+            Ok(dfx) => dfx.is_persistent(network),
+            Err(_) => network == "ic",
+        };
+
+        if persistent {
+            project_root.join("canister_ids.json")
+        } else {
+            project_root
+                .join(".dfx")
+                .join(network)
+                .join("canister_ids.json")
+        }
+    }
+
+    /// Loads the IDs tracked for `network`, or none if there are no IDs yet
+    pub async fn load(project_root: &Path, network: &str) -> Result<Self> {
+        Self::load_from(&Self::path(project_root, network).await).await
+    }
+
+    pub async fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read canister_ids.json: {}", path.display()))?;
+
+        serde_json::from_str(&content).with_context(|| "Failed to parse canister_ids.json")
+    }
+
+    /// Writes the IDs to the file dfx keeps `network`'s IDs in
+    pub async fn save(&self, project_root: &Path, network: &str) -> Result<()> {
+        self.save_to(&Self::path(project_root, network).await).await
+    }
+
+    pub async fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        let mut content = serde_json::to_string_pretty(self)
+            .with_context(|| "Failed to serialize canister_ids.json")?;
+        content.push('\n');
+
+        fs::write(path, content)
+            .await
+            .with_context(|| format!("Failed to write canister_ids.json: {}", path.display()))
+    }
+
+    /// ID of canister `name` on `network`
+    pub fn get(&self, name: &str, network: &str) -> Option<&str> {
+        self.0.get(name)?.get(network).map(String::as_str)
+    }
+
+    /// Records the ID of canister `name` on `network`
+    pub fn set(&mut self, name: &str, network: &str, id: &str) {
+        self.0
+            .entry(name.to_string())
+            .or_default()
+            .insert(network.to_string(), id.to_string());
+    }
+
+    /// Forgets the ID of canister `name` on `network`, returning it
+    pub fn remove(&mut self, name: &str, network: &str) -> Option<String> {
+        let networks = self.0.get_mut(name)?;
+        let id = networks.remove(network);
+        if networks.is_empty() {
+            self.0.remove(name);
+        }
+        id
+    }
+
+    /// `(canister, id)` pairs of every canister with an ID on `network`
+    pub fn on_network<'a>(&'a self, network: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.0.iter().filter_map(move |(name, networks)| {
+            networks.get(network).map(|id| (name.as_str(), id.as_str()))
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_dfx_json_keeps_unknown_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(
+            DfxJson::path(root),
+            r#"{
+              "version": 1,
+              "canisters": {
+                "weather": { "type": "rust", "package": "weather", "build": "make", "gzip": true }
+              },
+              "networks": { "staging": { "type": "persistent", "providers": ["https://icp0.io"] } },
+              "output_env_file": ".env"
+            }"#,
+        )
+        .await
+        .unwrap();
+
+        let mut dfx = DfxJson::load(root).await.unwrap();
+        assert_eq!(
+            dfx.canister("weather")
+                .unwrap()
+                .build
+                .as_ref()
+                .unwrap()
+                .steps(),
+            vec!["make"]
+        );
+        assert!(dfx.is_persistent("staging"));
+        assert!(dfx.is_persistent("ic"));
+        assert!(!dfx.is_persistent("local"));
+
+        dfx.set_build_command("weather", vec!["icarus build".to_string()])
+            .unwrap();
+        assert!(dfx.set_build_command("missing", Vec::new()).is_err());
+        dfx.add_canister("news", DfxCanister::rust("news-feed"));
+        dfx.save(root).await.unwrap();
+
+        let saved = DfxJson::load(root).await.unwrap();
+        assert_eq!(saved, dfx);
+        assert_eq!(saved.extra["output_env_file"], ".env");
+        assert_eq!(saved.canisters["weather"].extra["gzip"], true);
+        assert_eq!(
+            saved.canisters["news"].wasm.as_deref(),
+            Some("target/wasm32-unknown-unknown/release/news_feed.wasm")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_canister_ids_are_tracked_per_network() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        let mut ids = CanisterIds::load(root, "ic").await.unwrap();
+        assert!(ids.is_empty());
+        ids.set("weather", "ic", "rdmx6-jaaaa-aaaaa-aaadq-cai");
+        ids.set("news", "ic", "rrkah-fqaaa-aaaaa-aaaaq-cai");
+        ids.save(root, "ic").await.unwrap();
+        assert!(root.join("canister_ids.json").exists());
+
+        let mut local = CanisterIds::default();
+        local.set("weather", "local", "bkyz2-fmaaa-aaaaa-qaaaq-cai");
+        local.save(root, "local").await.unwrap();
+        assert!(root.join(".dfx/local/canister_ids.json").exists());
+
+        let mut loaded = CanisterIds::load(root, "ic").await.unwrap();
+        assert_eq!(loaded, ids);
+        assert_eq!(
+            loaded.get("weather", "ic"),
+            Some("rdmx6-jaaaa-aaaaa-aaadq-cai")
+        );
+        assert_eq!(loaded.get("weather", "local"), None);
+        assert_eq!(
+            loaded
+                .on_network("ic")
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            vec!["news", "weather"]
+        );
+
+        assert_eq!(
+            loaded.remove("news", "ic").as_deref(),
+            Some("rrkah-fqaaa-aaaaa-aaaaq-cai")
+        );
+        assert_eq!(loaded.on_network("ic").count(), 1);
+    }
+}
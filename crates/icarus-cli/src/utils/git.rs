@@ -253,6 +253,45 @@ pub(crate) async fn get_remote_url(project_path: &Path) -> Result<Option<String>
     Ok(Some(url))
 }
 
+/// Shallow-clone `url` into `dest`, checking out `reference` if given
+pub(crate) async fn clone_repository(
+    url: &str,
+    dest: &Path,
+    reference: Option<&str>,
+) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.args(["clone", "--depth", "1"]);
+    if let Some(reference) = reference {
+        cmd.args(["--branch", reference]);
+    }
+
+    let output = cmd
+        .arg(url)
+        .arg(dest)
+        .output()
+        .await
+        .context("Failed to execute git clone")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!("Git clone of {} failed: {}", url, stderr));
+    }
+
+    Ok(())
+}
+
+/// The `user.name` git is configured with, if any
+pub(crate) async fn get_user_name() -> Option<String> {
+    let output = Command::new("git")
+        .args(["config", "user.name"])
+        .output()
+        .await
+        .ok()?;
+
+    let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !name.is_empty()).then_some(name)
+}
+
 /// Create and checkout a new branch
 pub(crate) async fn create_and_checkout_branch(
     project_path: &Path,
@@ -0,0 +1,74 @@
+//! JSON parsing for canister responses.
+//!
+//! Tool results carrying images or large documents make responses of several
+//! megabytes, and the bridge parses every one of them. With the `simd-json`
+//! feature they are parsed in place by `simd-json`, which picks the widest
+//! instruction set the CPU supports at runtime. Without the feature, or on
+//! targets other than x86_64 and aarch64, `serde_json` parses them; both paths
+//! return `serde_json` values and errors, so callers do not tell them apart.
+//!
+//! Run `cargo bench -p icarus-cli --bench json_parsing --features simd-json`
+//! to compare the two.
+
+use serde::de::DeserializeOwned;
+
+/// Whether responses are parsed with `simd-json` in this build.
+pub const SIMD_ENABLED: bool = cfg!(all(
+    feature = "simd-json",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+));
+
+/// Parses a JSON document, consuming the text so it can be parsed in place.
+pub fn from_string<T: DeserializeOwned>(text: String) -> serde_json::Result<T> {
+    from_vec(text.into_bytes())
+}
+
+/// Parses a JSON document from bytes such as a process's stdout.
+#[cfg(all(
+    feature = "simd-json",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub fn from_vec<T: DeserializeOwned>(mut bytes: Vec<u8>) -> serde_json::Result<T> {
+    simd_json::serde::from_slice(&mut bytes).map_err(serde::de::Error::custom)
+}
+
+/// Parses a JSON document from bytes such as a process's stdout.
+#[cfg(not(all(
+    feature = "simd-json",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+)))]
+pub fn from_vec<T: DeserializeOwned>(bytes: Vec<u8>) -> serde_json::Result<T> {
+    serde_json::from_slice(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_parses_like_serde_json() {
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": "1",
+            "result": {
+                "content": [{ "type": "text", "text": "caf\u{e9} \"quoted\"\n".repeat(1000) }],
+                "isError": false,
+                "count": 18_446_744_073_709_551_615_u64,
+                "ratio": -0.5
+            }
+        });
+        let text = response.to_string();
+
+        let parsed: Value = from_string(text.clone()).unwrap();
+        assert_eq!(parsed, response);
+        assert_eq!(from_vec::<Value>(text.into_bytes()).unwrap(), response);
+    }
+
+    #[test]
+    fn test_reports_invalid_json() {
+        assert!(from_string::<Value>("{\"result\": ".to_string()).is_err());
+        assert!(from_string::<Value>(String::new()).is_err());
+        assert!(from_vec::<Value>(b"[1, 2,]".to_vec()).is_err());
+    }
+}
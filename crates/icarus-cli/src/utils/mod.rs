@@ -1,9 +1,23 @@
+pub(crate) mod agent_pool;
 pub(crate) mod bridge;
+pub(crate) mod call_policy;
+pub(crate) mod candid;
 pub(crate) mod cargo;
 #[doc(hidden)]
 pub mod client_detector;
+pub(crate) mod delegation;
 pub(crate) mod dfx;
+pub mod dfx_config;
 pub(crate) mod git;
 #[doc(hidden)]
+pub mod json;
+pub(crate) mod pocket_ic;
+#[doc(hidden)]
 pub mod project;
+pub(crate) mod response_cache;
 pub(crate) mod rmcp_bridge;
+pub(crate) mod rpc_log;
+pub(crate) mod session_recorder;
+pub(crate) mod wasi;
+pub(crate) mod wasm_size;
+pub(crate) mod watchdog;
@@ -0,0 +1,85 @@
+//! Ephemeral PocketIC instances for `icarus deploy --network pocket-ic`
+//!
+//! PocketIC runs a replica in a single process, so a project can be deployed
+//! and bridged without dfx or a running local replica. The server binary is
+//! taken from `POCKET_IC_BIN` or downloaded on first use. Canisters are
+//! installed first and the instance is then made live, which starts an HTTP
+//! gateway that agents and `dfx --network <endpoint>` can talk to. The
+//! instance, and everything installed on it, is gone once it is shut down.
+
+use anyhow::{anyhow, Context, Result};
+use candid::Principal;
+use pocket_ic::nonblocking::PocketIc;
+use pocket_ic::PocketIcBuilder;
+use std::path::Path;
+use url::Url;
+
+/// Name of the PocketIC deployment target on the command line
+pub(crate) const NETWORK: &str = "pocket-ic";
+
+/// Cycles each canister starts with unless `--with-cycles` says otherwise
+pub(crate) const DEFAULT_CYCLES: u128 = 2_000_000_000_000;
+
+/// A running PocketIC instance with one application subnet
+pub(crate) struct PocketIcDeployment {
+    pic: PocketIc,
+    canisters: Vec<(String, Principal)>,
+}
+
+impl PocketIcDeployment {
+    /// Starts a fresh instance
+    pub(crate) async fn start() -> Result<Self> {
+        let pic = PocketIcBuilder::new()
+            .with_application_subnet()
+            .build_async()
+            .await;
+
+        Ok(Self {
+            pic,
+            canisters: Vec::new(),
+        })
+    }
+
+    /// Creates canister `name` and installs the module at `wasm_path` on it
+    /// with empty init arguments
+    pub(crate) async fn install(
+        &mut self,
+        name: &str,
+        wasm_path: &Path,
+        cycles: u128,
+    ) -> Result<Principal> {
+        let wasm = tokio::fs::read(wasm_path)
+            .await
+            .with_context(|| format!("Failed to read WASM module: {}", wasm_path.display()))?;
+        let init_arg = candid::encode_args(()).context("Failed to encode init arguments")?;
+
+        let canister_id = self.pic.create_canister().await;
+        self.pic.add_cycles(canister_id, cycles).await;
+        self.pic
+            .install_canister(canister_id, wasm, init_arg, None)
+            .await;
+
+        self.canisters.push((name.to_string(), canister_id));
+        Ok(canister_id)
+    }
+
+    /// Makes the instance live and returns the endpoint it serves on
+    ///
+    /// `port` picks the gateway port; by default a free one is chosen.
+    pub(crate) async fn serve(&mut self, port: Option<u16>) -> Result<Url> {
+        if self.canisters.is_empty() {
+            return Err(anyhow!("No canisters were installed on PocketIC"));
+        }
+        Ok(self.pic.make_live(port).await)
+    }
+
+    /// `(name, canister ID)` of each installed canister, in install order
+    pub(crate) fn canisters(&self) -> &[(String, Principal)] {
+        &self.canisters
+    }
+
+    /// Stops the instance and deletes its state
+    pub(crate) async fn shutdown(self) {
+        self.pic.drop().await;
+    }
+}
@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use tokio::fs;
 
+use super::dfx_config::DfxJson;
+
 /// Project configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectConfig {
@@ -202,6 +204,7 @@ pub(crate) struct CanisterConfig {
     pub canister_type: String,
     pub package: Option<String>,
     pub main: Option<String>,
+    pub candid: Option<String>,
     pub dependencies: Option<Vec<String>>,
 }
 
@@ -236,6 +239,24 @@ async fn load_dfx_config(project_root: &Path) -> Result<DfxConfig> {
     Ok(dfx_config)
 }
 
+/// Path of the Candid file dfx.json declares for `package`, or `<package>.did`
+pub(crate) async fn candid_file(project_root: &Path, package: &str) -> PathBuf {
+    let declared = DfxJson::load(project_root)
+        .await
+        .ok()
+        .and_then(|dfx_config| {
+            dfx_config
+                .canisters
+                .into_iter()
+                .find(|(name, canister)| {
+                    canister.package.as_deref().unwrap_or(name.as_str()) == package
+                })
+                .and_then(|(_, canister)| canister.candid)
+        });
+
+    project_root.join(declared.unwrap_or_else(|| format!("{}.did", package)))
+}
+
 #[allow(dead_code)]
 async fn load_canister_ids(
     project_root: &Path,
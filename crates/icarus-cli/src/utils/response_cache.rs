@@ -0,0 +1,244 @@
+//! Cached results of read-only tool calls.
+//!
+//! A conversation often repeats the same lookup, and every call to the
+//! canister costs latency and cycles. The bridge keeps the results of tools
+//! the canister lists with `readOnlyHint: true`, keyed by canister, tool and
+//! a hash of the arguments. A result is kept for the TTL the tool declares
+//! under [`CACHE_TTL_SCHEMA_KEY`] in its input schema
+//! (`#[tool(cache_ttl_secs = ..)]`), or else for the bridge's `--cache-ttl`.
+//! The cache holds a bounded number of results and drops the least recently
+//! used one when full.
+//!
+//! A client skips the cache for one call by setting [`BYPASS_META_KEY`] in the
+//! request's `_meta`; the fresh result replaces the cached one.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+pub(crate) use icarus_core::tool::CACHE_TTL_SCHEMA_KEY;
+
+/// `_meta` key that makes a tool call skip the cache.
+pub(crate) const BYPASS_META_KEY: &str = "icarus/noCache";
+
+/// What a cached result is stored under.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct CacheKey {
+    canister_id: String,
+    tool: String,
+    args_hash: u64,
+}
+
+impl CacheKey {
+    pub(crate) fn new(
+        canister_id: &str,
+        tool: &str,
+        arguments: Option<&serde_json::Map<String, serde_json::Value>>,
+    ) -> Self {
+        // Object keys serialize in sorted order, so equal arguments hash alike
+        let mut hasher = DefaultHasher::new();
+        serde_json::to_string(&arguments)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        Self {
+            canister_id: canister_id.to_string(),
+            tool: tool.to_string(),
+            args_hash: hasher.finish(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Cached<V> {
+    value: V,
+    stored_at: Instant,
+    last_used: Instant,
+    ttl: Duration,
+}
+
+/// Bounded cache of values that expire after a per-value time.
+#[derive(Debug)]
+pub(crate) struct ResponseCache<V> {
+    capacity: usize,
+    ttl: Duration,
+    entries: HashMap<CacheKey, Cached<V>>,
+    hits: u64,
+    misses: u64,
+}
+
+impl<V: Clone> ResponseCache<V> {
+    /// Creates a cache of `capacity` values kept for `ttl` unless inserted
+    /// with their own; a capacity of 0 caches nothing.
+    pub(crate) fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Returns the value stored under `key` unless it has expired.
+    pub(crate) fn get(&mut self, key: &CacheKey, now: Instant) -> Option<V> {
+        let fresh = self
+            .entries
+            .get(key)
+            .is_some_and(|cached| now.saturating_duration_since(cached.stored_at) < cached.ttl);
+        if !fresh {
+            self.entries.remove(key);
+            self.misses += 1;
+            return None;
+        }
+        self.hits += 1;
+        self.entries.get_mut(key).map(|cached| {
+            cached.last_used = now;
+            cached.value.clone()
+        })
+    }
+
+    /// Stores `value` for the cache's TTL, dropping the least recently used
+    /// value if full.
+    pub(crate) fn insert(&mut self, key: CacheKey, value: V, now: Instant) {
+        self.insert_for(key, value, self.ttl, now);
+    }
+
+    /// Stores `value` for `ttl`, dropping the least recently used value if
+    /// full. A zero `ttl` stores nothing.
+    pub(crate) fn insert_for(&mut self, key: CacheKey, value: V, ttl: Duration, now: Instant) {
+        if !self.is_enabled() || ttl.is_zero() {
+            return;
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(
+            key,
+            Cached {
+                value,
+                stored_at: now,
+                last_used: now,
+                ttl,
+            },
+        );
+    }
+
+    /// Drops every cached value, e.g. after a call that may change them.
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Returns the number of hits and misses so far.
+    pub(crate) fn hit_counts(&self) -> (u64, u64) {
+        (self.hits, self.misses)
+    }
+}
+
+/// The TTL a tool declares in its input schema, if any.
+pub(crate) fn declared_ttl(
+    input_schema: &serde_json::Map<String, serde_json::Value>,
+) -> Option<Duration> {
+    input_schema
+        .get(CACHE_TTL_SCHEMA_KEY)
+        .and_then(serde_json::Value::as_u64)
+        .map(Duration::from_secs)
+}
+
+/// Returns whether the client asked to skip the cache for this call.
+pub(crate) fn bypass_requested(meta: &serde_json::Map<String, serde_json::Value>) -> bool {
+    meta.get(BYPASS_META_KEY)
+        .and_then(serde_json::Value::as_bool)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn args(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_keys_depend_on_canister_tool_and_arguments() {
+        let a = args(json!({ "city": "Zurich", "units": "metric" }));
+        let b = args(json!({ "units": "metric", "city": "Zurich" }));
+        assert_eq!(
+            CacheKey::new("aaaaa-aa", "forecast", Some(&a)),
+            CacheKey::new("aaaaa-aa", "forecast", Some(&b))
+        );
+        assert_ne!(
+            CacheKey::new("aaaaa-aa", "forecast", Some(&a)),
+            CacheKey::new("aaaaa-aa", "forecast", None)
+        );
+        assert_ne!(
+            CacheKey::new("aaaaa-aa", "forecast", None),
+            CacheKey::new("bbbbb-bb", "forecast", None)
+        );
+    }
+
+    #[test]
+    fn test_values_expire_and_least_recently_used_is_dropped() {
+        let start = Instant::now();
+        let key = |tool: &str| CacheKey::new("aaaaa-aa", tool, None);
+        let mut cache = ResponseCache::new(2, Duration::from_secs(60));
+
+        let at = |secs| start + Duration::from_secs(secs);
+        cache.insert(key("a"), 1, at(0));
+        cache.insert(key("b"), 2, at(1));
+        assert_eq!(cache.get(&key("a"), at(2)), Some(1));
+        cache.insert(key("c"), 3, at(3));
+        assert_eq!(cache.get(&key("b"), at(3)), None);
+        assert_eq!(cache.get(&key("a"), at(3)), Some(1));
+
+        assert_eq!(cache.get(&key("c"), at(63)), None);
+        assert_eq!(cache.hit_counts(), (2, 2));
+
+        cache.clear();
+        assert_eq!(cache.get(&key("a"), start), None);
+
+        let mut disabled = ResponseCache::new(0, Duration::from_secs(60));
+        disabled.insert(key("a"), 1, start);
+        assert_eq!(disabled.get(&key("a"), start), None);
+    }
+
+    #[test]
+    fn test_values_keep_their_own_ttl() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let key = |tool: &str| CacheKey::new("aaaaa-aa", tool, None);
+        let mut cache = ResponseCache::new(4, Duration::ZERO);
+
+        cache.insert(key("default"), 0, at(0));
+        cache.insert_for(key("prices"), 1, Duration::from_secs(5), at(0));
+        cache.insert_for(key("catalog"), 2, Duration::from_secs(600), at(0));
+        assert_eq!(cache.get(&key("default"), at(0)), None);
+        assert_eq!(cache.get(&key("prices"), at(4)), Some(1));
+        assert_eq!(cache.get(&key("prices"), at(5)), None);
+        assert_eq!(cache.get(&key("catalog"), at(599)), Some(2));
+
+        let schema = args(json!({ "type": "object", CACHE_TTL_SCHEMA_KEY: 5 }));
+        assert_eq!(declared_ttl(&schema), Some(Duration::from_secs(5)));
+        assert_eq!(declared_ttl(&args(json!({ "type": "object" }))), None);
+    }
+
+    #[test]
+    fn test_bypass_meta() {
+        assert!(bypass_requested(&args(json!({ BYPASS_META_KEY: true }))));
+        assert!(!bypass_requested(&args(json!({ BYPASS_META_KEY: false }))));
+        assert!(!bypass_requested(&args(json!({}))));
+    }
+}
@@ -2,26 +2,152 @@
 //!
 //! This bridge implements `rmcp::ServerHandler` to provide proper MCP protocol
 //! support. It forwards tool calls from Claude Desktop to IC canisters using dfx.
+//!
+//! Tool calls carry a W3C trace context: the bridge continues the client's
+//! `_meta.traceparent` (or starts a new trace) and forwards a child span to the
+//! canister in the same field, where it is attached to canister log entries.
+//!
+//! With [`BridgeConfig::tool_watch_interval`] set, the bridge polls the
+//! canister's `mcp_tools_sequence` query and sends
+//! `notifications/tools/list_changed` when it changes, so a redeploy during
+//! development shows new tools without restarting the client. Canisters built
+//! before the sequence existed are polled for `mcp_tools_fingerprint` instead.
+//!
+//! A canister built with `mcp!{ prefix = "memory_" }` lists its tools as
+//! `memory_*`. Setting [`BridgeConfig::strip_tool_prefix`] to the same prefix
+//! shows the client the flat names and adds the prefix back on calls.
+//!
+//! A canister JSON-RPC error whose `data` has a stable error code, such as
+//! `ICARUS-TOOL-404`, reaches the client as a JSON-RPC error with the same
+//! code and `data`, so clients can branch on `error.data.code` (see
+//! [`error_codes`]). Errors from canisters built without codes still arrive
+//! as failed tool results.
+//!
+//! While the canister is in maintenance mode its tool calls fail with a
+//! "service unavailable" error. The bridge notices from that error, from
+//! `mcp_server_info` at startup and on every tool watch poll, and puts the
+//! maintenance message in the instructions of its own server info.
+//!
+//! A tool that returns a sampling request answers with the request in
+//! `structuredContent.sampling`. The bridge sends it to the client as
+//! `sampling/createMessage` and passes the completion, or the client's
+//! refusal, to the canister's `mcp_sampling_result`, whose result the client
+//! receives as the tool's result.
+//!
+//! Calls use [`BridgeConfig::identity`] when set, so canisters owned by
+//! different dfx identities can be bridged without `dfx identity use`.
+//!
+//! Users without dfx set [`BridgeConfig::auth`] to
+//! [`BridgeAuth::InternetIdentity`]. The bridge then signs its calls with an
+//! Internet Identity delegation from `icarus mcp login`, logging in again in
+//! the browser when the delegation is about to expire. Agents are reused
+//! between calls through an [`AgentPool`] sized by
+//! [`BridgeConfig::max_connections`] and [`BridgeConfig::idle_timeout`].
+//!
+//! Canister responses are parsed by [`json`](crate::utils::json), with
+//! `simd-json` when the `simd-json` feature is enabled.
+//!
+//! Queries and tool listings that fail without an answer from the replica
+//! are retried with backoff. After [`BridgeConfig::failure_threshold`] such
+//! failures in a row the bridge stops calling the canister for
+//! [`BridgeConfig::breaker_cooldown`] and fails tool calls with a "canister
+//! unreachable" error instead (see [`call_policy`](crate::utils::call_policy)).
+//!
+//! With [`BridgeConfig::cache_size`] set, results of tools the canister lists
+//! as read-only are cached for the TTL each tool declares, or else for
+//! [`BridgeConfig::cache_ttl`] (see
+//! [`response_cache`](crate::utils::response_cache)). A call to any other tool
+//! clears the cache, since it may change what the read-only tools return.
+//!
+//! With [`BridgeConfig::watchdog_interval`] set, the bridge pings the
+//! canister and the client on that interval. Repeated failed canister pings
+//! make it reconnect its agent, and repeated failed client pings make it exit
+//! so it can be restarted (see [`watchdog`](crate::utils::watchdog)).
+//!
+//! Arguments to tools whose input schema carries the
+//! [`encrypted_args::SCHEMA_KEY`] marker (`#[tool(encrypted)]`) are encrypted
+//! under the canister's vetKD key from `mcp_args_public_key` and sent as an
+//! envelope only the canister can open, so they never appear in clear text in
+//! recordings or replica logs.
+//!
+//! With [`BridgeConfig::tool_snapshot`] set, the first `tools/list` is
+//! answered from the snapshot `icarus mcp add` saved, so a client does not
+//! time out while the canister is slow to answer. The live list is fetched in
+//! the background and the client is notified if it differs.
+//!
+//! A `tools/list` request whose `_meta.locale` names a locale, such as
+//! `es-MX`, is answered from the canister's `mcp_list_tools_in` query, with
+//! the titles and descriptions of `#[tool(i18n = "...")]` tools translated.
+//! Canisters built without it answer with the untranslated list.
+//!
+//! With [`BridgeConfig::read_replicas`] set, calls of read-only tools go to
+//! the replicas in turn (see `icarus_core::replication`). A replica that
+//! fails, or refuses a tool it only lets its primary run, is skipped and the
+//! configured canister answers instead.
+//!
+//! Tools whose input schema carries the [`composite::SCHEMA_KEY`] marker
+//! (`#[tool(composite_query)]`) are called as a query of the canister's
+//! [`composite::COMPOSITE_METHOD`], so their calls to other canisters need no
+//! consensus round. Canisters built without it are called through
+//! `mcp_call_tool`.
+//!
+//! Next to the canister's tools the bridge lists [`HEALTH_TOOL`], which
+//! answers with the canister's `mcp_health` report so the model can check
+//! whether the server is ready.
 
 use anyhow::{anyhow, Result};
+use base64::Engine as _;
+use candid::{IDLArgs, IDLValue, Principal};
+use ic_agent::Agent;
+use ic_vetkeys::{DerivedPublicKey, IbeCiphertext, IbeIdentity, IbeSeed};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{debug, error, info};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{debug, error, info, warn};
 
 // Import RMCP types from icarus-core
+use icarus_core::composite;
+use icarus_core::encrypted_args::{self, ARGS_IDENTITY, ENVELOPE_KEY};
+use icarus_core::error_codes;
+use icarus_core::health::HealthStatus;
+use icarus_core::i18n::LOCALE_META_KEY;
+use icarus_core::maintenance::SERVICE_UNAVAILABLE_CODE;
+use icarus_core::replication::READ_ONLY_REPLICA_CODE;
+use icarus_core::trace::{TraceContext, TRACEPARENT};
 use icarus_core::{CallToolResult, Content, Tool};
 
 // Import types directly from rmcp crate for protocol handling
 use rmcp::model::{
-    CallToolRequestParam, Implementation, ListToolsResult, PaginatedRequestParam, ProtocolVersion,
-    ServerCapabilities, ServerInfo, ToolsCapability,
+    CallToolRequestParam, CreateMessageRequestParam, Implementation, ListToolsResult,
+    PaginatedRequestParam, PingRequest, ProtocolVersion, ServerCapabilities, ServerInfo,
+    ServerRequest, ToolsCapability,
 };
-use rmcp::service::{RequestContext, RoleServer};
+use rmcp::service::{NotificationContext, Peer, RequestContext, RoleServer};
 use rmcp::ErrorData;
 use rmcp::ServerHandler;
 
 use crate::config::mcp::McpConfig;
+use crate::config::tool_snapshot::ToolSnapshot;
+use crate::utils::agent_pool::{self, AgentPool, AgentPoolConfig};
+use crate::utils::call_policy::{
+    self, CallStats, CanisterUnreachable, CircuitBreaker, RetryPolicy,
+};
+use crate::utils::delegation::{self, DelegationStore, StoredDelegation};
+use crate::utils::json;
+use crate::utils::response_cache::{self, CacheKey, ResponseCache};
+use crate::utils::session_recorder::SessionRecorder;
+use crate::utils::watchdog::{Watchdog, WEDGED_EXIT_CODE};
+
+/// Name of the tool that reports the canister's health.
+///
+/// MCP's `server/health` spelling is not a valid tool name for clients that
+/// only accept letters, digits, `_` and `-`.
+pub const HEALTH_TOOL: &str = "server_health";
 
 /// Bridge configuration for connecting to an IC canister.
 #[allow(dead_code)]
@@ -35,6 +161,48 @@ pub struct BridgeConfig {
     pub server_name: String,
     /// Server version
     pub server_version: String,
+    /// How often to check the canister for tool changes, or `None` to not watch
+    pub tool_watch_interval: Option<Duration>,
+    /// Tool name prefix of the canister to hide from the client
+    pub strip_tool_prefix: Option<String>,
+    /// dfx identity to call the canister with, or `None` for the current one
+    pub identity: Option<String>,
+    /// How canister calls are authenticated
+    pub auth: BridgeAuth,
+    /// Most Internet Identity agents kept for reuse, one per network and canister
+    pub max_connections: usize,
+    /// How long an unused agent is kept before it is built again
+    pub idle_timeout: Duration,
+    /// Retries of queries and tool listings that fail without an answer
+    pub max_retries: u32,
+    /// Consecutive failed calls after which calls fail at once
+    pub failure_threshold: u32,
+    /// How long calls fail at once before the canister is tried again
+    pub breaker_cooldown: Duration,
+    /// Results of read-only tools kept for repeated calls, 0 to not cache
+    pub cache_size: usize,
+    /// How long a cached result is returned before the tool is called again,
+    /// for tools that declare no TTL of their own
+    pub cache_ttl: Duration,
+    /// Tool snapshot to answer the first `tools/list` from, kept up to date
+    /// with every list fetched from the canister
+    pub tool_snapshot: Option<PathBuf>,
+    /// How often to ping the canister and the client, or `None` to not watch
+    pub watchdog_interval: Option<Duration>,
+    /// Consecutive failed pings after which the bridge recovers
+    pub watchdog_threshold: u32,
+    /// Replicas of the canister that take read-only tool calls in turn
+    pub read_replicas: Vec<String>,
+}
+
+/// How the bridge authenticates its canister calls.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum BridgeAuth {
+    /// Shell out to dfx with its identity
+    #[default]
+    Dfx,
+    /// Sign calls with a delegation from this Internet Identity provider
+    InternetIdentity { identity_provider: String },
 }
 
 impl Default for BridgeConfig {
@@ -44,6 +212,21 @@ impl Default for BridgeConfig {
             network: "local".to_string(),
             server_name: "Icarus Bridge".to_string(),
             server_version: env!("CARGO_PKG_VERSION").to_string(),
+            tool_watch_interval: None,
+            strip_tool_prefix: None,
+            identity: None,
+            auth: BridgeAuth::Dfx,
+            max_connections: AgentPoolConfig::default().max_connections,
+            idle_timeout: AgentPoolConfig::default().idle_timeout,
+            max_retries: RetryPolicy::default().max_retries,
+            failure_threshold: 5,
+            breaker_cooldown: Duration::from_secs(30),
+            cache_size: 0,
+            cache_ttl: Duration::from_secs(60),
+            tool_snapshot: None,
+            watchdog_interval: Some(Duration::from_secs(30)),
+            watchdog_threshold: 3,
+            read_replicas: Vec::new(),
         }
     }
 }
@@ -54,40 +237,173 @@ impl Default for BridgeConfig {
 /// It uses dfx to communicate with IC canisters, forwarding tool calls and
 /// returning results in RMCP-compliant format.
 #[allow(dead_code)]
+#[derive(Clone)]
 pub struct IcarusBridge {
     config: Arc<RwLock<BridgeConfig>>,
     mcp_config: Arc<RwLock<McpConfig>>,
+    recorder: Option<SessionRecorder>,
+    /// Maintenance message of the canister, last time it was checked
+    maintenance: Arc<RwLock<Option<String>>>,
+    /// Agents for Internet Identity auth and the delegations they sign with
+    agents: Arc<Mutex<AgentPool<(Agent, StoredDelegation)>>>,
+    /// Circuit breaker shared by all canister calls
+    breaker: Arc<Mutex<CircuitBreaker>>,
+    /// Results of read-only tool calls
+    cache: Arc<Mutex<ResponseCache<CallToolResult>>>,
+    /// Tools the canister lists as read-only, by the names the client sees
+    read_only_tools: Arc<RwLock<HashSet<String>>>,
+    /// Cache TTLs the tools declare, by the names the client sees
+    cache_ttls: Arc<RwLock<HashMap<String, Duration>>>,
+    /// Tools that take encrypted arguments, by the names the client sees
+    encrypted_tools: Arc<RwLock<HashSet<String>>>,
+    /// Tools that run as composite queries, by the names the client sees
+    composite_tools: Arc<RwLock<HashSet<String>>>,
+    /// The canister's public key for encrypted arguments, once fetched
+    args_public_key: Arc<RwLock<Option<Vec<u8>>>>,
+    /// Whether the first `tools/list` has been answered
+    cold_started: Arc<AtomicBool>,
+    /// Turn counter for picking a read replica
+    next_replica: Arc<AtomicUsize>,
 }
 
 #[allow(dead_code)]
 impl IcarusBridge {
     /// Creates a new Icarus bridge with the given configuration.
     pub fn new(config: BridgeConfig, mcp_config: McpConfig) -> Self {
+        let pool = AgentPool::new(AgentPoolConfig {
+            max_connections: config.max_connections,
+            idle_timeout: config.idle_timeout,
+        });
+        let breaker = CircuitBreaker::new(config.failure_threshold, config.breaker_cooldown);
+        let cache = ResponseCache::new(config.cache_size, config.cache_ttl);
         Self {
             config: Arc::new(RwLock::new(config)),
             mcp_config: Arc::new(RwLock::new(mcp_config)),
+            recorder: None,
+            maintenance: Arc::new(RwLock::new(None)),
+            agents: Arc::new(Mutex::new(pool)),
+            breaker: Arc::new(Mutex::new(breaker)),
+            cache: Arc::new(Mutex::new(cache)),
+            read_only_tools: Arc::new(RwLock::new(HashSet::new())),
+            cache_ttls: Arc::new(RwLock::new(HashMap::new())),
+            encrypted_tools: Arc::new(RwLock::new(HashSet::new())),
+            composite_tools: Arc::new(RwLock::new(HashSet::new())),
+            args_public_key: Arc::new(RwLock::new(None)),
+            cold_started: Arc::new(AtomicBool::new(false)),
+            next_replica: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Records every tool call sent to the canister with `recorder`.
+    pub fn with_recorder(mut self, recorder: SessionRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Returns how often calls were retried and the breaker opened.
+    pub async fn call_stats(&self) -> CallStats {
+        self.breaker.lock().await.stats()
+    }
+
+    /// Runs a canister call through the circuit breaker, retrying transient
+    /// failures with backoff if the call is `idempotent`.
+    async fn guarded<T, F, Fut>(&self, idempotent: bool, mut call: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.breaker.lock().await.check(Instant::now())?;
+
+        let retry = RetryPolicy {
+            max_retries: self.config.read().await.max_retries,
+            ..RetryPolicy::default()
+        };
+        let mut retries = 0;
+        loop {
+            let error = match call().await {
+                Ok(value) => {
+                    self.breaker.lock().await.record_success();
+                    return Ok(value);
+                }
+                Err(e) if !call_policy::is_transient(&e) => {
+                    // The canister answered, so it is reachable
+                    self.breaker.lock().await.record_success();
+                    return Err(e);
+                }
+                Err(e) => e,
+            };
+
+            let mut breaker = self.breaker.lock().await;
+            if let Some(unreachable) = breaker.record_failure(Instant::now()) {
+                let stats = breaker.stats();
+                warn!(
+                    "{} ({} trips, {} retries so far): {}",
+                    unreachable, stats.trips, stats.retries, error
+                );
+                return Err(unreachable.into());
+            }
+            if !idempotent || retries >= retry.max_retries {
+                return Err(error);
+            }
+            breaker.record_retry();
+            drop(breaker);
+
+            let backoff = retry.backoff(retries);
+            debug!("Retrying canister call in {:?}: {}", backoff, error);
+            tokio::time::sleep(backoff).await;
+            retries += 1;
         }
     }
 
-    /// Calls a canister method using dfx.
+    /// Calls a canister method using dfx, or as the logged-in Internet
+    /// Identity.
     async fn dfx_call(&self, method: &str, args: &str) -> Result<String> {
+        let canister_id = self.config.read().await.canister_id.clone();
+        self.dfx_call_on(&canister_id, method, args).await
+    }
+
+    /// Calls a method of `canister_id`, which may be a replica of the
+    /// configured canister, like [`Self::dfx_call`].
+    async fn dfx_call_on(&self, canister_id: &str, method: &str, args: &str) -> Result<String> {
+        self.dfx_request_on(canister_id, method, args, false).await
+    }
+
+    /// Sends `args` to a method of `canister_id` as a query, or as an update
+    /// like [`Self::dfx_call_on`].
+    async fn dfx_request_on(
+        &self,
+        canister_id: &str,
+        method: &str,
+        args: &str,
+        query: bool,
+    ) -> Result<String> {
+        if self.uses_delegation().await {
+            let reply = self
+                .agent_call_on(canister_id, method, candid::Encode!(&args)?, query)
+                .await?;
+            return candid::Decode!(&reply, String)
+                .map_err(|e| anyhow!("Failed to decode {} response: {}", method, e));
+        }
+
         let config = self.config.read().await;
 
         debug!(
             "Calling canister {} method {} with args: {}",
-            config.canister_id, method, args
+            canister_id, method, args
         );
 
         // Build dfx command
         let output = Command::new("dfx")
             .arg("canister")
             .arg("call")
-            .arg(&config.canister_id)
+            .arg(canister_id)
             .arg(method)
+            .args(query.then_some("--query"))
             .arg("--network")
             .arg(&config.network)
             .arg("--output")
             .arg("json")
+            .args(identity_args(&config))
             .arg(format!(
                 "(record {{ request = \"{}\" }})",
                 args.replace('"', "\\\"")
@@ -101,42 +417,487 @@ impl IcarusBridge {
             return Err(anyhow!("dfx call failed: {}", stderr));
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
+        // Responses can be megabytes; keep the buffer rather than copying it
+        let stdout = String::from_utf8(output.stdout)
+            .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned());
         debug!("dfx response: {}", stdout);
 
-        Ok(stdout.to_string())
+        Ok(stdout)
+    }
+
+    /// Queries the canister's tool list version: the change sequence, or the
+    /// fingerprint for canisters without `mcp_tools_sequence`.
+    async fn tools_version(&self) -> Result<String> {
+        match self.query_json("mcp_tools_sequence").await {
+            Ok(sequence) => Ok(format!("sequence:{}", sequence)),
+            Err(e) if is_missing_method(&e) => self
+                .query_json("mcp_tools_fingerprint")
+                .await
+                .map(|fingerprint| format!("fingerprint:{}", fingerprint)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Calls an argument-less query and returns its JSON-encoded result.
+    async fn query_json(&self, method: &str) -> Result<serde_json::Value> {
+        self.guarded(true, || self.query_json_once(method)).await
+    }
+
+    async fn query_json_once(&self, method: &str) -> Result<serde_json::Value> {
+        self.call_json_once(method, true).await
+    }
+
+    /// Calls an argument-less method, as a query if `query`, and returns its
+    /// JSON-encoded result.
+    async fn call_json_once(&self, method: &str, query: bool) -> Result<serde_json::Value> {
+        if self.uses_delegation().await {
+            let reply = self.agent_call(method, candid::Encode!()?, query).await?;
+            return reply_to_json(&reply);
+        }
+
+        let config = self.config.read().await;
+
+        let output = Command::new("dfx")
+            .arg("canister")
+            .arg("call")
+            .arg(&config.canister_id)
+            .arg(method)
+            .args(query.then_some("--query"))
+            .arg("--network")
+            .arg(&config.network)
+            .arg("--output")
+            .arg("json")
+            .args(identity_args(&config))
+            .output()
+            .map_err(|e| anyhow!("Failed to execute dfx: {}", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("dfx call failed: {}", stderr));
+        }
+
+        json::from_vec(output.stdout)
+            .map_err(|e| anyhow!("Failed to parse {} response: {}", method, e))
+    }
+
+    async fn uses_delegation(&self) -> bool {
+        matches!(
+            self.config.read().await.auth,
+            BridgeAuth::InternetIdentity { .. }
+        )
+    }
+
+    /// Calls a canister method with the Internet Identity agent.
+    async fn agent_call(&self, method: &str, arg: Vec<u8>, query: bool) -> Result<Vec<u8>> {
+        let canister_id = self.config.read().await.canister_id.clone();
+        self.agent_call_on(&canister_id, method, arg, query).await
+    }
+
+    /// Calls a method of `canister_id` with the configured canister's
+    /// Internet Identity agent.
+    async fn agent_call_on(
+        &self,
+        canister_id: &str,
+        method: &str,
+        arg: Vec<u8>,
+        query: bool,
+    ) -> Result<Vec<u8>> {
+        let (agent, key) = self.agent().await?;
+        let canister_id =
+            Principal::from_text(canister_id).map_err(|e| anyhow!("Invalid canister ID: {}", e))?;
+
+        debug!(
+            "Calling canister {} method {} as Internet Identity",
+            canister_id, method
+        );
+        let reply = if query {
+            agent.query(&canister_id, method).with_arg(arg).call().await
+        } else {
+            agent
+                .update(&canister_id, method)
+                .with_arg(arg)
+                .call_and_wait()
+                .await
+        };
+        match reply {
+            Ok(reply) => Ok(reply),
+            Err(e) => {
+                if agent_pool::is_fatal(&e) {
+                    // The next call builds a new agent instead of reusing this one
+                    debug!("Dropping agent for {} after error: {}", key, e);
+                    self.agents.lock().await.remove(&key);
+                }
+                Err(anyhow!("dfx call failed: {}", e))
+            }
+        }
+    }
+
+    /// The agent signing with the stored delegation for the configured
+    /// canister, and its pool key. A pooled agent is replaced by a new one
+    /// when its delegation is about to expire or it fails a health check.
+    async fn agent(&self) -> Result<(Agent, String)> {
+        let (network, identity_provider, key) = {
+            let config = self.config.read().await;
+            match &config.auth {
+                BridgeAuth::InternetIdentity { identity_provider } => (
+                    config.network.clone(),
+                    identity_provider.clone(),
+                    agent_pool::pool_key(&config.network, &config.canister_id),
+                ),
+                BridgeAuth::Dfx => return Err(anyhow!("Bridge is not using Internet Identity")),
+            }
+        };
+
+        let mut agents = self.agents.lock().await;
+        if let Some(checkout) = agents.checkout(&key, Instant::now()) {
+            let (agent, delegation) = checkout.value;
+            let healthy = !checkout.needs_health_check || agent.status().await.is_ok();
+            if healthy && !delegation.needs_refresh() {
+                return Ok((agent, key));
+            }
+            agents.remove(&key);
+        }
+
+        let store = DelegationStore::default_location()?;
+        let delegation =
+            delegation::fresh_delegation(&store, &identity_provider, delegation::DEFAULT_TTL)
+                .await?;
+
+        let agent = Agent::builder()
+            .with_url(network_url(&network))
+            .with_identity(delegation.identity()?)
+            .build()
+            .map_err(|e| anyhow!("Failed to create agent: {}", e))?;
+        if network == "local" {
+            agent
+                .fetch_root_key()
+                .await
+                .map_err(|e| anyhow!("Failed to fetch local root key: {}", e))?;
+        }
+
+        agents.insert(key.clone(), (agent.clone(), delegation), Instant::now());
+        debug!("Built agent for {} ({} pooled)", key, agents.len());
+        Ok((agent, key))
+    }
+
+    /// Reads the maintenance state from the canister's server info.
+    async fn refresh_maintenance(&self) {
+        match self.query_json("mcp_server_info").await {
+            Ok(info) => {
+                let message = maintenance_message(&info);
+                if let Some(message) = &message {
+                    info!("Canister is in maintenance: {}", message);
+                }
+                *self.maintenance.write().await = message;
+            }
+            Err(e) => debug!("Failed to read canister server info: {}", e),
+        }
+    }
+
+    /// Pings the canister and the client, reconnecting to a canister that
+    /// stopped answering and exiting when the client did.
+    async fn watchdog(self, peer: Peer<RoleServer>, interval: Duration) {
+        let threshold = self.config.read().await.watchdog_threshold;
+        let mut canister = Watchdog::new(threshold);
+        let mut client = Watchdog::new(threshold);
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick completes immediately
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+
+            // Bypasses the breaker, which refuses calls while it is open
+            let reachable =
+                match tokio::time::timeout(interval, self.query_json_once("mcp_server_info")).await
+                {
+                    Ok(Ok(_)) => true,
+                    // The canister answered, if only with an error
+                    Ok(Err(e)) => !call_policy::is_transient(&e),
+                    Err(_) => false,
+                };
+            if canister.record(reachable) {
+                warn!("Canister did not answer {} pings, reconnecting", threshold);
+                self.reconnect().await;
+            }
+
+            let ping = peer.send_request(ServerRequest::PingRequest(PingRequest::default()));
+            let answered = matches!(tokio::time::timeout(interval, ping).await, Ok(Ok(_)));
+            if client.record(answered) {
+                error!(
+                    "Client did not answer {} pings, exiting so the bridge can be restarted",
+                    threshold
+                );
+                std::process::exit(WEDGED_EXIT_CODE);
+            }
+        }
+    }
+
+    /// Drops the pooled agent and closes the breaker, so the next call
+    /// connects afresh.
+    async fn reconnect(&self) {
+        let key = {
+            let config = self.config.read().await;
+            agent_pool::pool_key(&config.network, &config.canister_id)
+        };
+        self.agents.lock().await.remove(&key);
+        self.breaker.lock().await.record_success();
+    }
+
+    /// Notifies the client whenever the canister's tool list changes.
+    async fn watch_tools(self, peer: Peer<RoleServer>, interval: Duration) {
+        let mut last = self.tools_version().await.ok();
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick completes immediately
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            self.refresh_maintenance().await;
+
+            // Polls fail while the canister is being redeployed; keep the last
+            // known version and try again on the next tick
+            let current = match self.tools_version().await {
+                Ok(current) => current,
+                Err(e) => {
+                    debug!("Failed to check canister tools: {}", e);
+                    continue;
+                }
+            };
+
+            if tool_list_changed(last.as_deref(), &current) {
+                info!("Canister tools changed, notifying client");
+                self.cache.lock().await.clear();
+                if let Err(e) = peer.notify_tool_list_changed().await {
+                    debug!("Stopping tool watch, client is gone: {}", e);
+                    return;
+                }
+            }
+            last = Some(current);
+        }
     }
 
     /// Lists tools from the canister.
-    async fn list_canister_tools(&self) -> Result<Vec<Tool>> {
-        let response = self.dfx_call("mcp_list_tools", "{}").await?;
+    pub(crate) async fn list_canister_tools(&self) -> Result<Vec<Tool>> {
+        self.list_canister_tools_in(None).await
+    }
+
+    /// Lists tools from the canister, translated for `locale` if given.
+    /// Canisters without `mcp_list_tools_in` answer untranslated.
+    async fn list_canister_tools_in(&self, locale: Option<&str>) -> Result<Vec<Tool>> {
+        let response = match locale {
+            Some(locale) => match self
+                .guarded(true, || self.dfx_call("mcp_list_tools_in", locale))
+                .await
+            {
+                Err(e) if is_missing_method(&e) => {
+                    debug!("Canister cannot translate tools: {}", e);
+                    self.guarded(true, || self.dfx_call("mcp_list_tools", "{}"))
+                        .await?
+                }
+                response => response?,
+            },
+            None => {
+                self.guarded(true, || self.dfx_call("mcp_list_tools", "{}"))
+                    .await?
+            }
+        };
 
         // Parse the JSON-RPC response
-        let response_json: serde_json::Value = serde_json::from_str(&response)
+        let mut response_json: serde_json::Value = json::from_string(response)
             .map_err(|e| anyhow!("Failed to parse list_tools response: {}", e))?;
 
         // Extract tools from result
-        let tools = response_json
-            .get("result")
-            .and_then(|r| r.get("tools"))
-            .and_then(|t| t.as_array())
-            .ok_or_else(|| anyhow!("Invalid list_tools response format"))?;
+        let listing = match response_json["result"]["tools"].take() {
+            serde_json::Value::Array(tools) => tools,
+            _ => return Err(anyhow!("Invalid list_tools response format")),
+        };
+
+        self.save_snapshot(&listing).await;
+        Ok(self.tools_from_listing(&listing).await)
+    }
 
+    /// Converts the canister's tool listing to the tools the client sees.
+    async fn tools_from_listing(&self, listing: &[serde_json::Value]) -> Vec<Tool> {
         // Convert to Tool objects
-        let tools: Vec<Tool> = tools
+        let tools: Vec<Tool> = listing
             .iter()
             .filter_map(|tool_json| serde_json::from_value(tool_json.clone()).ok())
             .collect();
 
-        Ok(tools)
+        let tools = match &self.config.read().await.strip_tool_prefix {
+            Some(prefix) => strip_tool_prefix(tools, prefix),
+            None => tools,
+        };
+
+        *self.read_only_tools.write().await = tools
+            .iter()
+            .filter(|tool| {
+                tool.annotations
+                    .as_ref()
+                    .and_then(|annotations| annotations.read_only_hint)
+                    == Some(true)
+            })
+            .map(|tool| tool.name.to_string())
+            .collect();
+        *self.cache_ttls.write().await = tools
+            .iter()
+            .filter_map(|tool| {
+                response_cache::declared_ttl(&tool.input_schema)
+                    .map(|ttl| (tool.name.to_string(), ttl))
+            })
+            .collect();
+        *self.encrypted_tools.write().await = marked_tool_names(&tools, encrypted_args::SCHEMA_KEY);
+        *self.composite_tools.write().await = marked_tool_names(&tools, composite::SCHEMA_KEY);
+        tools
+    }
+
+    /// The snapshot's tools on the first `tools/list`, or `None` after it or
+    /// without a snapshot.
+    async fn cold_start_tools(&self) -> Option<Vec<Tool>> {
+        let path = self.config.read().await.tool_snapshot.clone()?;
+        if self.cold_started.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+
+        match ToolSnapshot::load_from(&path).await {
+            Ok(Some(snapshot)) => {
+                info!("Listing tools from the snapshot of {}", snapshot.taken_at);
+                Some(self.tools_from_listing(&snapshot.tools).await)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                debug!("Ignoring tool snapshot: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Replaces the tool snapshot, if the bridge keeps one, with `listing`.
+    async fn save_snapshot(&self, listing: &[serde_json::Value]) {
+        let config = self.config.read().await;
+        let Some(path) = &config.tool_snapshot else {
+            return;
+        };
+        let snapshot = ToolSnapshot::new(&config.canister_id, &config.network, listing.to_vec());
+        if let Err(e) = snapshot.save_to(path).await {
+            debug!("Failed to save tool snapshot: {}", e);
+        }
+    }
+
+    /// Fetches the live tool list after the client was answered from the
+    /// snapshot, and tells the client if the tools changed since.
+    async fn refresh_after_cold_start(self, served: Vec<Tool>, peer: Peer<RoleServer>) {
+        match self.list_canister_tools().await {
+            Ok(tools) if !same_tools(&served, &tools) => {
+                info!("Canister tools differ from the snapshot, notifying client");
+                if let Err(e) = peer.notify_tool_list_changed().await {
+                    debug!("Failed to notify client of tool changes: {}", e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => debug!("Failed to refresh tools after cold start: {}", e),
+        }
+    }
+
+    /// The cache key of a call, or `None` if the tool's results are not
+    /// cached.
+    async fn cache_key(
+        &self,
+        tool_name: &str,
+        arguments: Option<&serde_json::Map<String, serde_json::Value>>,
+    ) -> Option<CacheKey> {
+        if !self.cache.lock().await.is_enabled()
+            || !self.read_only_tools.read().await.contains(tool_name)
+        {
+            return None;
+        }
+        let canister_id = self.config.read().await.canister_id.clone();
+        Some(CacheKey::new(&canister_id, tool_name, arguments))
+    }
+
+    /// How long results of `tool_name` are cached: its declared TTL, or
+    /// [`BridgeConfig::cache_ttl`].
+    async fn cache_ttl(&self, tool_name: &str) -> Duration {
+        match self.cache_ttls.read().await.get(tool_name) {
+            Some(ttl) => *ttl,
+            None => self.config.read().await.cache_ttl,
+        }
     }
 
-    /// Calls a tool on the canister.
+    /// The canister's public key for encrypted arguments, fetched on first
+    /// use.
+    async fn args_public_key(&self) -> Result<Vec<u8>> {
+        if let Some(key) = self.args_public_key.read().await.clone() {
+            return Ok(key);
+        }
+
+        // Fetching the key calls the management canister, so this is an update
+        let response = match self.call_json_once("mcp_args_public_key", false).await {
+            Ok(serde_json::Value::String(json)) => json,
+            Ok(other) => return Err(anyhow!("Invalid mcp_args_public_key response: {}", other)),
+            Err(e) if is_missing_method(&e) => {
+                return Err(anyhow!(
+                    "The canister has tools with encrypted arguments but no \
+                     mcp_args_public_key; build it with mcp!{{ encryption = true }}"
+                ))
+            }
+            Err(e) => return Err(e),
+        };
+        let key = parse_args_public_key(&response)?;
+        *self.args_public_key.write().await = Some(key.clone());
+        Ok(key)
+    }
+
+    /// Replaces `arguments` with an envelope only the canister can open.
+    async fn seal_arguments(
+        &self,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<serde_json::Map<String, serde_json::Value>> {
+        let public_key = DerivedPublicKey::deserialize(&self.args_public_key().await?)
+            .map_err(|e| anyhow!("Invalid canister public key: {:?}", e))?;
+        let seed = IbeSeed::from_bytes(&rand::random::<[u8; 32]>())
+            .map_err(|e| anyhow!("Invalid encryption seed: {:?}", e))?;
+        let plaintext = serde_json::to_vec(&arguments)?;
+        let ciphertext = IbeCiphertext::encrypt(
+            &public_key,
+            &IbeIdentity::from_bytes(ARGS_IDENTITY),
+            &plaintext,
+            &seed,
+        );
+
+        let mut envelope = serde_json::Map::new();
+        envelope.insert(
+            ENVELOPE_KEY.to_string(),
+            base64::engine::general_purpose::STANDARD
+                .encode(ciphertext.serialize())
+                .into(),
+        );
+        Ok(envelope)
+    }
+
+    /// Calls a tool on the canister as a child span of `trace`.
     async fn call_canister_tool(
         &self,
         tool_name: &str,
         arguments: Option<serde_json::Map<String, serde_json::Value>>,
+        trace: &TraceContext,
     ) -> Result<CallToolResult> {
+        // Read-only tools can be answered by a replica
+        let read_only = self.read_only_tools.read().await.contains(tool_name);
+        let composite = self.composite_tools.read().await.contains(tool_name);
+        let arguments = arguments.unwrap_or_default();
+        let arguments = if self.encrypted_tools.read().await.contains(tool_name) {
+            self.seal_arguments(arguments).await?
+        } else {
+            arguments
+        };
+
+        let tool_name = match &self.config.read().await.strip_tool_prefix {
+            Some(prefix) => format!("{prefix}{tool_name}"),
+            None => tool_name.to_string(),
+        };
+
         // Build JSON-RPC request
         let request = serde_json::json!({
             "jsonrpc": "2.0",
@@ -144,18 +905,43 @@ impl IcarusBridge {
             "method": "tools/call",
             "params": {
                 "name": tool_name,
-                "arguments": arguments.unwrap_or_default()
+                "arguments": arguments,
+                "_meta": {
+                    TRACEPARENT: trace.child().to_string()
+                }
             }
         });
 
         let request_str = serde_json::to_string(&request)
             .map_err(|e| anyhow!("Failed to serialize request: {}", e))?;
 
-        let response = self.dfx_call("mcp_call_tool", &request_str).await?;
+        let answered = if composite {
+            self.call_composite(&tool_name, &request_str).await
+        } else {
+            None
+        };
+        let answered = match answered {
+            Some(response_json) => Some(response_json),
+            None if read_only => self.call_replica(&tool_name, &request_str).await,
+            None => None,
+        };
+        let response_json = match answered {
+            Some(response_json) => response_json,
+            None => {
+                // The tool may have run, so a failed call is not repeated
+                let response = self
+                    .guarded(false, || self.dfx_call("mcp_call_tool", &request_str))
+                    .await?;
 
-        // Parse the JSON-RPC response
-        let response_json: serde_json::Value = serde_json::from_str(&response)
-            .map_err(|e| anyhow!("Failed to parse call_tool response: {}", e))?;
+                // Parse the JSON-RPC response
+                json::from_string(response)
+                    .map_err(|e| anyhow!("Failed to parse call_tool response: {}", e))?
+            }
+        };
+
+        if let Some(recorder) = &self.recorder {
+            recorder.record(&request, &response_json);
+        }
 
         // Check for JSON-RPC error
         if let Some(error) = response_json.get("error") {
@@ -163,6 +949,15 @@ impl IcarusBridge {
                 .get("message")
                 .and_then(|m| m.as_str())
                 .unwrap_or("Unknown error");
+            let in_maintenance = error.get("code").and_then(serde_json::Value::as_i64)
+                == Some(i64::from(SERVICE_UNAVAILABLE_CODE));
+            if in_maintenance {
+                *self.maintenance.write().await = Some(error_msg.to_string());
+            }
+            // Errors with a stable code reach the client as JSON-RPC errors
+            if let Some(error) = CanisterError::from_response(error) {
+                return Err(error.into());
+            }
             return Ok(CallToolResult {
                 content: vec![Content::text(error_msg)],
                 structured_content: None,
@@ -171,6 +966,9 @@ impl IcarusBridge {
             });
         }
 
+        // The call went through, so maintenance is over
+        *self.maintenance.write().await = None;
+
         // Extract CallToolResult from result field
         let result = response_json
             .get("result")
@@ -181,17 +979,176 @@ impl IcarusBridge {
 
         Ok(call_tool_result)
     }
+
+    /// Sends a `tools/call` request for a composite query tool to the
+    /// canister's composite query endpoint. Returns `None` for it to be sent
+    /// as usual when the call fails, such as on canisters built without the
+    /// endpoint, which are not tried again.
+    async fn call_composite(&self, tool_name: &str, request: &str) -> Option<serde_json::Value> {
+        let canister_id = self.config.read().await.canister_id.clone();
+        let response = self
+            .dfx_request_on(&canister_id, composite::COMPOSITE_METHOD, request, true)
+            .await
+            .and_then(|response| {
+                json::from_string(response)
+                    .map_err(|e| anyhow!("Failed to parse call_tool response: {}", e))
+            });
+        match response {
+            Ok(response) => Some(response),
+            Err(e) => {
+                if is_missing_method(&e) {
+                    self.composite_tools.write().await.clear();
+                }
+                debug!(
+                    "Composite query of {} failed, calling mcp_call_tool: {}",
+                    tool_name, e
+                );
+                None
+            }
+        }
+    }
+
+    /// Sends a `tools/call` request to the next read replica. Returns `None`
+    /// for the configured canister to answer instead: without replicas, or
+    /// when the replica fails or refuses the tool.
+    async fn call_replica(&self, tool_name: &str, request: &str) -> Option<serde_json::Value> {
+        let replica = next_replica(&self.config.read().await.read_replicas, &self.next_replica)?;
+
+        let response = self
+            .dfx_call_on(&replica, "mcp_call_tool", request)
+            .await
+            .and_then(|response| {
+                json::from_string(response)
+                    .map_err(|e| anyhow!("Failed to parse call_tool response: {}", e))
+            });
+        match response {
+            Ok(response) if !refused_by_replica(&response) => Some(response),
+            Ok(_) => {
+                debug!(
+                    "Replica {} refused {}, calling the primary",
+                    replica, tool_name
+                );
+                None
+            }
+            Err(e) => {
+                debug!("Replica {} failed, calling the primary: {}", replica, e);
+                None
+            }
+        }
+    }
+
+    /// Answers [`HEALTH_TOOL`] with the canister's `mcp_health` report.
+    async fn health_result(&self) -> CallToolResult {
+        let health = match self.query_json("mcp_health").await {
+            Ok(serde_json::Value::String(json)) => serde_json::from_str::<HealthStatus>(&json)
+                .map_err(|e| anyhow!("Invalid mcp_health response: {}", e)),
+            Ok(other) => Err(anyhow!("Invalid mcp_health response: {}", other)),
+            Err(e) if is_missing_method(&e) => Err(anyhow!(
+                "The canister has no mcp_health query; rebuild it with a current icarus"
+            )),
+            Err(e) => Err(e),
+        };
+
+        match health {
+            Ok(health) => CallToolResult {
+                content: vec![Content::text(health.to_string())],
+                structured_content: serde_json::to_value(&health).ok(),
+                is_error: Some(false),
+                meta: None,
+            },
+            Err(e) => CallToolResult {
+                content: vec![Content::text(format!("Health check failed: {}", e))],
+                structured_content: None,
+                is_error: Some(true),
+                meta: None,
+            },
+        }
+    }
+
+    /// Asks the client for the completion a tool requested and returns the
+    /// tool's final result from the canister.
+    async fn complete_sampling(
+        &self,
+        sampling: &serde_json::Value,
+        peer: &Peer<RoleServer>,
+    ) -> Result<CallToolResult> {
+        let sampling_id = sampling
+            .get("id")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or_else(|| anyhow!("Sampling request without an id"))?;
+
+        // The canister fails the tool call if the client cannot sample
+        let outcome = match serde_json::from_value::<CreateMessageRequestParam>(
+            sampling.get("params").cloned().unwrap_or_default(),
+        ) {
+            Ok(params) => match peer.create_message(params).await {
+                Ok(result) => serde_json::json!({ "result": result }),
+                Err(e) => serde_json::json!({ "error": { "message": e.to_string() } }),
+            },
+            Err(e) => serde_json::json!({
+                "error": { "message": format!("Invalid sampling request: {e}") }
+            }),
+        };
+        let mut params = outcome;
+        params["samplingId"] = sampling_id.into();
+
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": "1",
+            "params": params,
+        });
+        let request_str = serde_json::to_string(&request)
+            .map_err(|e| anyhow!("Failed to serialize request: {}", e))?;
+        let response = self
+            .guarded(false, || self.dfx_call("mcp_sampling_result", &request_str))
+            .await?;
+        let response_json: serde_json::Value = json::from_string(response)
+            .map_err(|e| anyhow!("Failed to parse sampling response: {}", e))?;
+
+        if let Some(error) = response_json.get("error") {
+            let error_msg = error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("Unknown error");
+            return Ok(CallToolResult {
+                content: vec![Content::text(error_msg)],
+                structured_content: None,
+                is_error: Some(true),
+                meta: None,
+            });
+        }
+        let result = response_json
+            .get("result")
+            .ok_or_else(|| anyhow!("Missing result field in response"))?;
+        serde_json::from_value(result.clone())
+            .map_err(|e| anyhow!("Failed to parse CallToolResult: {}", e))
+    }
 }
 
 impl ServerHandler for IcarusBridge {
     fn get_info(&self) -> ServerInfo {
         // This is synchronous, so we can't use async lock
         // We'll return a default server info
+        let watching = self.config.try_read().is_ok_and(|config| {
+            config.tool_watch_interval.is_some() || config.tool_snapshot.is_some()
+        });
+        let maintenance = self
+            .maintenance
+            .try_read()
+            .ok()
+            .and_then(|message| message.clone());
+        let instructions = match maintenance {
+            Some(message) => format!(
+                "The canister is in maintenance and tool calls will fail until it ends: {message}"
+            ),
+            None => "Bridge server for connecting Claude Desktop to Internet Computer canisters via MCP protocol.".to_string(),
+        };
+
         ServerInfo {
             protocol_version: ProtocolVersion::default(),
             capabilities: ServerCapabilities {
                 tools: Some(ToolsCapability {
-                    list_changed: None,
+                    list_changed: watching.then_some(true),
                 }),
                 prompts: None,
                 resources: None,
@@ -206,20 +1163,49 @@ impl ServerHandler for IcarusBridge {
                 icons: None,
                 website_url: None,
             },
-            instructions: Some("Bridge server for connecting Claude Desktop to Internet Computer canisters via MCP protocol.".to_string()),
+            instructions: Some(instructions),
         }
     }
 
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        self.refresh_maintenance().await;
+
+        if let Some(interval) = self.config.read().await.watchdog_interval {
+            info!("Pinging canister and client every {:?}", interval);
+            tokio::spawn(self.clone().watchdog(context.peer.clone(), interval));
+        }
+
+        let Some(interval) = self.config.read().await.tool_watch_interval else {
+            return;
+        };
+
+        info!("Watching canister tools every {:?}", interval);
+        tokio::spawn(self.clone().watch_tools(context.peer, interval));
+    }
+
     async fn list_tools(
         &self,
         _request: Option<PaginatedRequestParam>,
-        _context: RequestContext<RoleServer>,
+        context: RequestContext<RoleServer>,
     ) -> Result<ListToolsResult, ErrorData> {
+        // Answer a cold start at once; the canister may be slow to wake up
+        if let Some(tools) = self.cold_start_tools().await {
+            tokio::spawn(
+                self.clone()
+                    .refresh_after_cold_start(tools.clone(), context.peer),
+            );
+            return Ok(ListToolsResult {
+                tools: with_health_tool(tools),
+                next_cursor: None,
+            });
+        }
+
+        let locale = client_locale(&context.meta);
         info!("Listing tools from canister");
 
-        match self.list_canister_tools().await {
+        match self.list_canister_tools_in(locale).await {
             Ok(tools) => Ok(ListToolsResult {
-                tools,
+                tools: with_health_tool(tools),
                 next_cursor: None,
             }),
             Err(e) => {
@@ -235,23 +1221,283 @@ impl ServerHandler for IcarusBridge {
     async fn call_tool(
         &self,
         request: CallToolRequestParam,
-        _context: RequestContext<RoleServer>,
+        context: RequestContext<RoleServer>,
     ) -> Result<CallToolResult, ErrorData> {
-        info!("Calling tool: {}", request.name);
+        if request.name == HEALTH_TOOL {
+            return Ok(self.health_result().await);
+        }
 
-        match self
-            .call_canister_tool(&request.name, request.arguments)
-            .await
-        {
+        let trace = client_trace_context(&context.meta);
+        let trace_id = trace.trace_id_hex();
+        info!(trace_id = %trace_id, "Calling tool: {}", request.name);
+
+        let cache_key = self
+            .cache_key(&request.name, request.arguments.as_ref())
+            .await;
+        if let Some(key) = &cache_key {
+            if !response_cache::bypass_requested(&context.meta) {
+                let mut cache = self.cache.lock().await;
+                if let Some(cached) = cache.get(key, Instant::now()) {
+                    let (hits, misses) = cache.hit_counts();
+                    debug!(trace_id = %trace_id, "Cached result ({} hits, {} misses)", hits, misses);
+                    return Ok(cached);
+                }
+            }
+        }
+
+        let mut result = self
+            .call_canister_tool(&request.name, request.arguments, &trace)
+            .await;
+        if let Some(sampling) = result.as_ref().ok().and_then(sampling_request).cloned() {
+            info!(trace_id = %trace_id, "Tool requested sampling from the client");
+            result = self.complete_sampling(&sampling, &context.peer).await;
+        } else if let Ok(result) = &result {
+            let ttl = self.cache_ttl(&request.name).await;
+            let mut cache = self.cache.lock().await;
+            match cache_key {
+                Some(key) if result.is_error != Some(true) => {
+                    cache.insert_for(key, result.clone(), ttl, Instant::now());
+                }
+                Some(_) => {}
+                // Any other tool may change what the read-only ones return
+                None => cache.clear(),
+            }
+        }
+
+        match result {
             Ok(result) => Ok(result),
-            Err(e) => {
-                error!("Failed to call tool: {}", e);
+            Err(e) if e.is::<CanisterUnreachable>() => {
+                error!(trace_id = %trace_id, "Not calling tool: {}", e);
                 Err(ErrorData::internal_error(
-                    format!("Failed to call tool: {}", e),
-                    None,
+                    e.to_string(),
+                    Some(serde_json::json!({ "reason": "canister_unreachable" })),
                 ))
             }
+            Err(e) => match e.downcast_ref::<CanisterError>() {
+                Some(error) => {
+                    info!(trace_id = %trace_id, "Tool call failed: {}", error);
+                    Err(error.to_error_data())
+                }
+                None => {
+                    error!(trace_id = %trace_id, "Failed to call tool: {}", e);
+                    Err(ErrorData::internal_error(
+                        format!("Failed to call tool: {}", e),
+                        None,
+                    ))
+                }
+            },
+        }
+    }
+}
+
+/// A JSON-RPC error of the canister whose `data` has a stable error code.
+#[derive(Debug, thiserror::Error)]
+#[error("{message} ({code})")]
+struct CanisterError {
+    rpc_code: i32,
+    code: String,
+    message: String,
+    data: serde_json::Value,
+}
+
+impl CanisterError {
+    /// Reads the `error` of a canister response, if it carries a code.
+    fn from_response(error: &serde_json::Value) -> Option<Self> {
+        let code = error.get("data")?.get(error_codes::CODE_KEY)?.as_str()?;
+        Some(Self {
+            rpc_code: error
+                .get("code")
+                .and_then(serde_json::Value::as_i64)
+                .and_then(|code| i32::try_from(code).ok())
+                .unwrap_or(-32603),
+            code: code.to_string(),
+            message: error
+                .get("message")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or("Unknown error")
+                .to_string(),
+            data: error["data"].clone(),
+        })
+    }
+
+    /// The error for the client, with the canister's code and data.
+    fn to_error_data(&self) -> ErrorData {
+        ErrorData::new(
+            rmcp::model::ErrorCode(self.rpc_code),
+            self.message.clone(),
+            Some(self.data.clone()),
+        )
+    }
+}
+
+/// Adds [`HEALTH_TOOL`] to the canister's tools, replacing a canister tool
+/// of the same name.
+fn with_health_tool(mut tools: Vec<Tool>) -> Vec<Tool> {
+    tools.retain(|tool| tool.name != HEALTH_TOOL);
+    let mut schema = serde_json::Map::new();
+    schema.insert("type".to_string(), serde_json::json!("object"));
+    schema.insert("properties".to_string(), serde_json::json!({}));
+    tools.push(Tool::new(
+        HEALTH_TOOL,
+        "Reports the server's version, uptime, tool count, memory, cycles and whether it is ready for tool calls",
+        Arc::new(schema),
+    ));
+    tools
+}
+
+/// Returns the sampling request a tool answered with, if any.
+fn sampling_request(result: &CallToolResult) -> Option<&serde_json::Value> {
+    result.structured_content.as_ref()?.get("sampling")
+}
+
+/// Returns whether two tool lists describe the same tools.
+fn same_tools(a: &[Tool], b: &[Tool]) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+/// Returns whether the tool list version differs from the last one seen.
+///
+/// Without a previous version (the first poll failed) any version counts as
+/// a change, since the client may have listed an older module.
+fn tool_list_changed(last: Option<&str>, current: &str) -> bool {
+    last != Some(current)
+}
+
+/// Returns the maintenance message from a `mcp_server_info` response, which
+/// dfx returns as a JSON string holding the JSON object.
+fn maintenance_message(server_info: &serde_json::Value) -> Option<String> {
+    let parsed;
+    let info = match server_info.as_str() {
+        Some(text) => {
+            parsed = serde_json::from_str::<serde_json::Value>(text).ok()?;
+            &parsed
         }
+        None => server_info,
+    };
+    let maintenance = info.get("maintenance")?;
+    if !maintenance.get("enabled")?.as_bool()? {
+        return None;
+    }
+    maintenance
+        .get("message")
+        .and_then(|message| message.as_str())
+        .map(ToString::to_string)
+}
+
+/// Removes `prefix` from the tool names that have it.
+fn strip_tool_prefix(tools: Vec<Tool>, prefix: &str) -> Vec<Tool> {
+    tools
+        .into_iter()
+        .map(|mut tool| {
+            if let Some(name) = tool.name.strip_prefix(prefix) {
+                tool.name = name.to_string().into();
+            }
+            tool
+        })
+        .collect()
+}
+
+/// Returns whether a dfx call failed because the canister lacks the method.
+fn is_missing_method(error: &anyhow::Error) -> bool {
+    let message = error.to_string();
+    message.contains("has no query method") || message.contains("has no update method")
+}
+
+/// The client's locale hint, `_meta.locale`, if it names one.
+fn client_locale(meta: &serde_json::Map<String, serde_json::Value>) -> Option<&str> {
+    meta.get(LOCALE_META_KEY)
+        .and_then(serde_json::Value::as_str)
+        .map(str::trim)
+        .filter(|locale| !locale.is_empty())
+}
+
+/// The read replica whose turn it is, or `None` without replicas.
+fn next_replica(replicas: &[String], turn: &AtomicUsize) -> Option<String> {
+    if replicas.is_empty() {
+        return None;
+    }
+    let turn = turn.fetch_add(1, Ordering::Relaxed);
+    Some(replicas[turn % replicas.len()].clone())
+}
+
+/// Returns whether a replica answered that only its primary runs the tool.
+fn refused_by_replica(response: &serde_json::Value) -> bool {
+    response["error"]["code"].as_i64() == Some(i64::from(READ_ONLY_REPLICA_CODE))
+}
+
+/// Continues the client's trace from `_meta.traceparent`, or starts a new one.
+fn client_trace_context(meta: &serde_json::Map<String, serde_json::Value>) -> TraceContext {
+    match meta.get(TRACEPARENT).and_then(|value| value.as_str()) {
+        Some(traceparent) => traceparent.parse().unwrap_or_else(|e| {
+            debug!("Ignoring invalid traceparent from client: {}", e);
+            TraceContext::generate()
+        }),
+        None => TraceContext::generate(),
+    }
+}
+
+/// The replica URL for a network name, or the network itself if it is a URL.
+fn network_url(network: &str) -> &str {
+    match network {
+        "local" => "http://127.0.0.1:4943",
+        "ic" => "https://icp-api.io",
+        url => url,
+    }
+}
+
+/// Converts a Candid reply to JSON the way `dfx --output json` does for the
+/// text and `nat64` results of the queries the bridge makes.
+fn reply_to_json(reply: &[u8]) -> Result<serde_json::Value> {
+    let args = IDLArgs::from_bytes(reply).map_err(|e| anyhow!("Failed to decode reply: {}", e))?;
+    Ok(match args.args.first() {
+        Some(IDLValue::Text(text)) => serde_json::Value::String(text.clone()),
+        Some(IDLValue::Nat64(n)) => serde_json::Value::String(n.to_string()),
+        Some(value) => serde_json::Value::String(value.to_string()),
+        None => serde_json::Value::Null,
+    })
+}
+
+/// Names of the tools whose input schema sets the marker `key` to `true`.
+fn marked_tool_names(tools: &[Tool], key: &str) -> HashSet<String> {
+    tools
+        .iter()
+        .filter(|tool| tool.input_schema.get(key) == Some(&serde_json::Value::Bool(true)))
+        .map(|tool| tool.name.to_string())
+        .collect()
+}
+
+/// Decodes the hex public key from an `mcp_args_public_key` response.
+fn parse_args_public_key(response: &str) -> Result<Vec<u8>> {
+    let response: serde_json::Value = serde_json::from_str(response)
+        .map_err(|e| anyhow!("Invalid mcp_args_public_key response: {}", e))?;
+    if let Some(error) = response.get("error").and_then(serde_json::Value::as_str) {
+        return Err(anyhow!(
+            "Failed to fetch the canister's public key: {}",
+            error
+        ));
+    }
+    let hex = response
+        .get("public_key")
+        .and_then(serde_json::Value::as_str)
+        .ok_or_else(|| anyhow!("mcp_args_public_key returned no public key"))?;
+    if hex.len() % 2 != 0 {
+        return Err(anyhow!("Public key has an odd number of hex digits"));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| anyhow!("Public key is not hex"))
+        })
+        .collect()
+}
+
+/// The `--identity` option for dfx calls, empty for the current identity.
+fn identity_args(config: &BridgeConfig) -> Vec<&str> {
+    match &config.identity {
+        Some(identity) => vec!["--identity", identity],
+        None => Vec::new(),
     }
 }
 
@@ -274,6 +1520,315 @@ mod tests {
         // Just ensure field exists
     }
 
+    #[test]
+    fn test_client_trace_context() {
+        let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let mut meta = serde_json::Map::new();
+        meta.insert(TRACEPARENT.to_string(), traceparent.into());
+        assert_eq!(client_trace_context(&meta).to_string(), traceparent);
+
+        meta.insert(TRACEPARENT.to_string(), "garbage".into());
+        assert_ne!(client_trace_context(&meta).to_string(), "garbage");
+        assert!(client_trace_context(&serde_json::Map::new()).is_sampled());
+    }
+
+    #[test]
+    fn test_canister_error_keeps_the_code() {
+        let error = serde_json::json!({
+            "code": -32601,
+            "message": "Tool not found: missing",
+            "data": { "code": "ICARUS-TOOL-404" }
+        });
+        let canister_error = CanisterError::from_response(&error).unwrap();
+        assert_eq!(canister_error.code, "ICARUS-TOOL-404");
+
+        let data = canister_error.to_error_data();
+        assert_eq!(data.code.0, -32601);
+        assert_eq!(data.message, "Tool not found: missing");
+        assert_eq!(data.data.unwrap()["code"], "ICARUS-TOOL-404");
+
+        // Canisters from before the catalog send no code
+        assert!(CanisterError::from_response(&serde_json::json!({
+            "code": -32601,
+            "message": "Tool not found: missing"
+        }))
+        .is_none());
+    }
+
+    #[test]
+    fn test_client_locale() {
+        let mut meta = serde_json::Map::new();
+        assert_eq!(client_locale(&meta), None);
+
+        meta.insert(LOCALE_META_KEY.to_string(), " es-MX ".into());
+        assert_eq!(client_locale(&meta), Some("es-MX"));
+
+        meta.insert(LOCALE_META_KEY.to_string(), "".into());
+        assert_eq!(client_locale(&meta), None);
+        meta.insert(LOCALE_META_KEY.to_string(), 7.into());
+        assert_eq!(client_locale(&meta), None);
+    }
+
+    #[test]
+    fn test_read_replicas_take_turns() {
+        let turn = AtomicUsize::new(0);
+        assert_eq!(next_replica(&[], &turn), None);
+
+        let replicas = ["aaaaa-aa".to_string(), "bbbbb-bb".to_string()];
+        let picked: Vec<_> = (0..3)
+            .filter_map(|_| next_replica(&replicas, &turn))
+            .collect();
+        assert_eq!(picked, ["aaaaa-aa", "bbbbb-bb", "aaaaa-aa"]);
+
+        let refused = serde_json::json!({
+            "error": { "code": READ_ONLY_REPLICA_CODE, "message": "Read replica" }
+        });
+        assert!(refused_by_replica(&refused));
+        assert!(!refused_by_replica(&serde_json::json!({ "result": {} })));
+    }
+
+    #[test]
+    fn test_tool_list_changed() {
+        assert!(!tool_list_changed(Some("00ab"), "00ab"));
+        assert!(tool_list_changed(Some("00ab"), "00cd"));
+        assert!(tool_list_changed(None, "00ab"));
+        assert!(tool_list_changed(Some("sequence:\"3\""), "sequence:\"4\""));
+    }
+
+    #[test]
+    fn test_strip_tool_prefix() {
+        let schema = Arc::new(serde_json::Map::new());
+        let tools = vec![
+            Tool::new("memory_search", "Searches", schema.clone()),
+            Tool::new("status", "Not prefixed", schema),
+        ];
+
+        let names: Vec<_> = strip_tool_prefix(tools, "memory_")
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+        assert_eq!(names, ["search", "status"]);
+    }
+
+    #[test]
+    fn test_with_health_tool() {
+        let schema = Arc::new(serde_json::Map::new());
+        let tools = vec![
+            Tool::new("search", "Searches", schema.clone()),
+            Tool::new(HEALTH_TOOL, "The canister's own", schema),
+        ];
+
+        let tools = with_health_tool(tools);
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[1].name, HEALTH_TOOL);
+        assert_eq!(tools[1].input_schema["type"], "object");
+    }
+
+    #[test]
+    fn test_maintenance_message() {
+        let info = serde_json::json!({
+            "name": "notes",
+            "maintenance": { "enabled": true, "message": "Migrating", "since": 1 }
+        });
+        assert_eq!(maintenance_message(&info).as_deref(), Some("Migrating"));
+
+        // As returned by dfx
+        let quoted = serde_json::Value::String(info.to_string());
+        assert_eq!(maintenance_message(&quoted).as_deref(), Some("Migrating"));
+
+        let off = serde_json::json!({ "maintenance": { "enabled": false, "message": "" } });
+        assert!(maintenance_message(&off).is_none());
+        assert!(maintenance_message(&serde_json::json!({ "name": "old" })).is_none());
+    }
+
+    #[test]
+    fn test_sampling_request() {
+        let mut result = CallToolResult {
+            content: vec![Content::text("Waiting for sampling/createMessage")],
+            structured_content: Some(serde_json::json!({
+                "sampling": { "id": 3, "method": "sampling/createMessage", "params": {} }
+            })),
+            is_error: Some(false),
+            meta: None,
+        };
+        assert_eq!(sampling_request(&result).unwrap()["id"], 3);
+
+        result.structured_content = Some(serde_json::json!({ "timeout_ms": 10 }));
+        assert!(sampling_request(&result).is_none());
+        result.structured_content = None;
+        assert!(sampling_request(&result).is_none());
+    }
+
+    #[test]
+    fn test_reply_to_json() {
+        let reply = candid::Encode!(&"{\"name\":\"notes\"}".to_string()).unwrap();
+        assert_eq!(
+            reply_to_json(&reply).unwrap(),
+            serde_json::Value::String("{\"name\":\"notes\"}".to_string())
+        );
+
+        let reply = candid::Encode!(&7u64).unwrap();
+        assert_eq!(reply_to_json(&reply).unwrap(), "7");
+
+        assert_eq!(network_url("ic"), "https://icp-api.io");
+        assert_eq!(network_url("http://10.0.0.2:8080"), "http://10.0.0.2:8080");
+    }
+
+    #[test]
+    fn test_identity_args() {
+        let mut config = BridgeConfig::default();
+        assert!(identity_args(&config).is_empty());
+
+        config.identity = Some("alice".to_string());
+        assert_eq!(identity_args(&config), ["--identity", "alice"]);
+    }
+
+    #[test]
+    fn test_is_missing_method() {
+        assert!(is_missing_method(&anyhow!(
+            "dfx call failed: Canister abc has no query method 'mcp_tools_sequence'"
+        )));
+        assert!(!is_missing_method(&anyhow!(
+            "dfx call failed: connection refused"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_list_changed_advertised_when_watching() {
+        let config = BridgeConfig {
+            tool_watch_interval: Some(Duration::from_secs(2)),
+            ..BridgeConfig::default()
+        };
+        let bridge = IcarusBridge::new(config, McpConfig::default());
+
+        let tools = bridge.get_info().capabilities.tools.unwrap();
+        assert_eq!(tools.list_changed, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_first_listing_comes_from_snapshot() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("snapshot.json");
+        ToolSnapshot::new(
+            "rdmx6-jaaaa-aaaaa-aaadq-cai",
+            "ic",
+            vec![
+                serde_json::json!({
+                    "name": "memory_recall",
+                    "description": "Recalls a memory",
+                    "inputSchema": { "type": "object" },
+                    "annotations": { "readOnlyHint": true }
+                }),
+                serde_json::json!({ "name": "not a tool" }),
+            ],
+        )
+        .save_to(&path)
+        .await
+        .unwrap();
+
+        let config = BridgeConfig {
+            strip_tool_prefix: Some("memory_".to_string()),
+            tool_snapshot: Some(path),
+            ..BridgeConfig::default()
+        };
+        let bridge = IcarusBridge::new(config, McpConfig::default());
+        assert_eq!(
+            bridge.get_info().capabilities.tools.unwrap().list_changed,
+            Some(true)
+        );
+
+        let tools = bridge.cold_start_tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "recall");
+        assert!(bridge.read_only_tools.read().await.contains("recall"));
+        assert!(bridge.encrypted_tools.read().await.is_empty());
+        assert!(bridge.cold_start_tools().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tools_keep_their_own_cache_ttl() {
+        let config = BridgeConfig {
+            cache_size: 8,
+            cache_ttl: Duration::from_secs(45),
+            ..BridgeConfig::default()
+        };
+        let bridge = IcarusBridge::new(config, McpConfig::default());
+        let read_only = serde_json::json!({ "readOnlyHint": true });
+        bridge
+            .tools_from_listing(&[
+                serde_json::json!({
+                    "name": "get_price",
+                    "inputSchema": { "type": "object", response_cache::CACHE_TTL_SCHEMA_KEY: 5 },
+                    "annotations": read_only
+                }),
+                serde_json::json!({
+                    "name": "get_catalog",
+                    "inputSchema": { "type": "object", response_cache::CACHE_TTL_SCHEMA_KEY: 3600 },
+                    "annotations": read_only
+                }),
+                serde_json::json!({
+                    "name": "get_stock",
+                    "inputSchema": { "type": "object" },
+                    "annotations": read_only
+                }),
+            ])
+            .await;
+
+        assert_eq!(bridge.cache_ttl("get_price").await, Duration::from_secs(5));
+        assert_eq!(
+            bridge.cache_ttl("get_catalog").await,
+            Duration::from_secs(3600)
+        );
+        assert_eq!(bridge.cache_ttl("get_stock").await, Duration::from_secs(45));
+    }
+
+    #[test]
+    fn test_encrypted_tools_are_found_by_marker() {
+        let tools: Vec<Tool> = serde_json::from_value(serde_json::json!([
+            {
+                "name": "store_key",
+                "inputSchema": { "type": "object", encrypted_args::SCHEMA_KEY: true }
+            },
+            { "name": "recall", "inputSchema": { "type": "object" } }
+        ]))
+        .unwrap();
+
+        let encrypted = marked_tool_names(&tools, encrypted_args::SCHEMA_KEY);
+        assert_eq!(encrypted.len(), 1);
+        assert!(encrypted.contains("store_key"));
+    }
+
+    #[test]
+    fn test_composite_tools_are_found_by_marker() {
+        let tools: Vec<Tool> = serde_json::from_value(serde_json::json!([
+            {
+                "name": "total_balance",
+                "inputSchema": { "type": "object", composite::SCHEMA_KEY: true }
+            },
+            {
+                "name": "store_key",
+                "inputSchema": { "type": "object", encrypted_args::SCHEMA_KEY: true }
+            }
+        ]))
+        .unwrap();
+
+        let composite = marked_tool_names(&tools, composite::SCHEMA_KEY);
+        assert_eq!(composite.len(), 1);
+        assert!(composite.contains("total_balance"));
+    }
+
+    #[test]
+    fn test_parse_args_public_key() {
+        assert_eq!(
+            parse_args_public_key(r#"{"public_key":"00ff1a"}"#).unwrap(),
+            vec![0x00, 0xff, 0x1a]
+        );
+        assert!(parse_args_public_key(r#"{"public_key":"0fz"}"#).is_err());
+        assert!(parse_args_public_key(r#"{"public_key":"zz"}"#).is_err());
+        let error = parse_args_public_key(r#"{"error":"vetKD unavailable"}"#).unwrap_err();
+        assert!(error.to_string().contains("vetKD unavailable"));
+    }
+
     #[tokio::test]
     async fn test_get_info() {
         let config = BridgeConfig::default();
@@ -0,0 +1,486 @@
+//! JSON-RPC traffic logging for debugging bridges.
+//!
+//! `icarus mcp start --log-rpc <file>` appends every exchange passing
+//! through the bridge to a JSON Lines file:
+//!
+//! ```json
+//! {"logged_at": "2026-10-16T09:30:00Z", "duration_ms": 12, "request": {...}, "response": {...}}
+//! ```
+//!
+//! Unlike a [`SessionRecorder`](crate::utils::session_recorder::SessionRecorder)
+//! recording, the log is meant to be shared, so before writing:
+//!
+//! - fields matching a [`JsonPath`] rule are replaced with [`REDACTED`]. The
+//!   [`DEFAULT_REDACTIONS`] always apply; `--log-rpc-redact` and
+//!   `redact` in the `[bridge]` table of icarus.toml add more
+//! - the `value` argument of calls to the [`SECRET_TOOLS`] is replaced with
+//!   [`REDACTED`]
+//! - only a `--log-rpc-sample` fraction of successful exchanges is kept;
+//!   errors are always logged
+//! - requests and responses serializing to more than `--log-rpc-max-bytes`
+//!   are replaced with a truncated preview
+//!
+//! Rules use a subset of JSONPath: `$` followed by `.name`, `['name']`,
+//! `[0]`, `.*` or `[*]` steps, and `..name` or `..*` to match at any depth,
+//! e.g. `$.params.arguments.card_number` or `$..password`.
+
+#![allow(dead_code)]
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::warn;
+
+/// Replacement of redacted values.
+pub(crate) const REDACTED: &str = "[REDACTED]";
+
+/// Rules applied to every log, for the field names secrets usually go by.
+pub(crate) const DEFAULT_REDACTIONS: &[&str] = &[
+    "$..password",
+    "$..secret",
+    "$..token",
+    "$..api_key",
+    "$..apiKey",
+    "$..authorization",
+    "$..private_key",
+    "$..delegation",
+];
+
+/// Tools generated by `mcp!{ secrets = true }` whose `value` argument is a
+/// secret.
+pub(crate) const SECRET_TOOLS: &[&str] = &["set_secret", "rotate_secret"];
+
+/// Replaces the `value` argument of `tools/call` requests to the
+/// [`SECRET_TOOLS`], including prefixed ones, with [`REDACTED`].
+pub(crate) fn redact_secret_arguments(message: &mut Value) {
+    if let Value::Array(batch) = message {
+        batch.iter_mut().for_each(redact_secret_arguments);
+        return;
+    }
+    if message.get("method").and_then(Value::as_str) != Some("tools/call") {
+        return;
+    }
+    let Some(params) = message.get_mut("params") else {
+        return;
+    };
+    let is_secret_tool = params
+        .get("name")
+        .and_then(Value::as_str)
+        .is_some_and(|name| SECRET_TOOLS.iter().any(|tool| name.ends_with(tool)));
+    if !is_secret_tool {
+        return;
+    }
+    if let Some(value) = params
+        .get_mut("arguments")
+        .and_then(|arguments| arguments.get_mut("value"))
+    {
+        *value = Value::String(REDACTED.to_string());
+    }
+}
+
+/// Which members a path step selects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Selector {
+    Name(String),
+    Index(usize),
+    Wildcard,
+}
+
+/// One step of a path: a child, or any descendant with `recursive`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Step {
+    selector: Selector,
+    recursive: bool,
+}
+
+/// A parsed JSONPath redaction rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct JsonPath {
+    source: String,
+    steps: Vec<Step>,
+}
+
+impl JsonPath {
+    pub(crate) fn parse(path: &str) -> Result<Self> {
+        let invalid = |reason: &str| anyhow!("Invalid JSONPath '{}': {}", path, reason);
+        let mut rest = path
+            .trim()
+            .strip_prefix('$')
+            .ok_or_else(|| invalid("must start with '$'"))?;
+
+        let mut steps = Vec::new();
+        while !rest.is_empty() {
+            let recursive = rest.starts_with("..");
+            if recursive {
+                rest = &rest[2..];
+            } else if let Some(after) = rest.strip_prefix('.') {
+                rest = after;
+            } else if !rest.starts_with('[') {
+                return Err(invalid("expected '.' or '['"));
+            }
+
+            let selector = if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(|| invalid("unclosed '['"))?;
+                let inner = after[..end].trim();
+                rest = &after[end + 1..];
+                if inner == "*" {
+                    Selector::Wildcard
+                } else if let Some(name) = inner
+                    .strip_prefix('\'')
+                    .and_then(|name| name.strip_suffix('\''))
+                    .or_else(|| inner.strip_prefix('"').and_then(|n| n.strip_suffix('"')))
+                {
+                    Selector::Name(name.to_string())
+                } else {
+                    Selector::Index(
+                        inner
+                            .parse()
+                            .map_err(|_| invalid("expected an index, '*' or a quoted name"))?,
+                    )
+                }
+            } else {
+                let end = rest.find(['.', '[']).unwrap_or(rest.len());
+                let name = &rest[..end];
+                rest = &rest[end..];
+                match name {
+                    "" => return Err(invalid("empty name")),
+                    "*" => Selector::Wildcard,
+                    _ => Selector::Name(name.to_string()),
+                }
+            };
+            steps.push(Step {
+                selector,
+                recursive,
+            });
+        }
+
+        if steps.is_empty() {
+            return Err(invalid("the whole message cannot be redacted"));
+        }
+        Ok(Self {
+            source: path.trim().to_string(),
+            steps,
+        })
+    }
+
+    /// Replaces every value the path matches in `value` with [`REDACTED`].
+    pub(crate) fn redact(&self, value: &mut Value) {
+        redact_steps(value, &self.steps);
+    }
+}
+
+impl std::fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn redact_steps(value: &mut Value, steps: &[Step]) {
+    let Some((step, rest)) = steps.split_first() else {
+        *value = Value::String(REDACTED.to_string());
+        return;
+    };
+
+    match value {
+        Value::Object(map) => {
+            for (name, child) in map.iter_mut() {
+                let matches = match &step.selector {
+                    Selector::Name(selected) => selected == name,
+                    Selector::Wildcard => true,
+                    Selector::Index(_) => false,
+                };
+                if matches {
+                    redact_steps(child, rest);
+                } else if step.recursive {
+                    redact_steps(child, steps);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (index, child) in items.iter_mut().enumerate() {
+                let matches = match &step.selector {
+                    Selector::Index(selected) => *selected == index,
+                    Selector::Wildcard => true,
+                    Selector::Name(_) => false,
+                };
+                if matches {
+                    redact_steps(child, rest);
+                } else if step.recursive {
+                    redact_steps(child, steps);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// What to log and how.
+#[derive(Debug, Clone)]
+pub(crate) struct RpcLogConfig {
+    /// Rules applied on top of [`DEFAULT_REDACTIONS`]
+    pub redactions: Vec<JsonPath>,
+    /// Fraction of successful exchanges to log, from 0 to 1
+    pub sample_rate: f64,
+    /// Largest serialized request or response logged in full
+    pub max_bytes: usize,
+}
+
+impl Default for RpcLogConfig {
+    fn default() -> Self {
+        Self {
+            redactions: Vec::new(),
+            sample_rate: 1.0,
+            max_bytes: 64 * 1024,
+        }
+    }
+}
+
+impl RpcLogConfig {
+    /// Builds a configuration from the rules, rate and size given to
+    /// `icarus mcp start`.
+    pub(crate) fn from_args(redact: &[String], sample_rate: f64, max_bytes: usize) -> Result<Self> {
+        if !(0.0..=1.0).contains(&sample_rate) {
+            return Err(anyhow!(
+                "Sample rate must be between 0 and 1, got {}",
+                sample_rate
+            ));
+        }
+
+        Ok(Self {
+            redactions: redact
+                .iter()
+                .map(|rule| JsonPath::parse(rule))
+                .collect::<Result<_>>()?,
+            sample_rate,
+            max_bytes,
+        })
+    }
+}
+
+/// Appends redacted, sampled and truncated exchanges to a JSON Lines file.
+#[derive(Clone)]
+pub(crate) struct RpcLogger {
+    path: PathBuf,
+    file: Arc<Mutex<File>>,
+    redactions: Arc<Vec<JsonPath>>,
+    sample_rate: f64,
+    max_bytes: usize,
+}
+
+impl RpcLogger {
+    /// Opens the log file for appending, creating it if needed.
+    pub(crate) fn open(path: &Path, config: RpcLogConfig) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open RPC log {}", path.display()))?;
+
+        let mut redactions = DEFAULT_REDACTIONS
+            .iter()
+            .map(|rule| JsonPath::parse(rule))
+            .collect::<Result<Vec<_>>>()?;
+        redactions.extend(config.redactions);
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Arc::new(Mutex::new(file)),
+            redactions: Arc::new(redactions),
+            sample_rate: config.sample_rate,
+            max_bytes: config.max_bytes,
+        })
+    }
+
+    /// Path of the log file.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Logs one exchange, if sampled. Failures are logged rather than
+    /// returned so that logging never breaks the session.
+    pub(crate) fn log_text(&self, request: &str, response: &str, duration: Duration) {
+        let request = parse_or_string(request);
+        let response = parse_or_string(response);
+        let failed = response.get("error").is_some();
+        if !failed && !self.sampled() {
+            return;
+        }
+
+        let entry = serde_json::json!({
+            "logged_at": chrono::Utc::now().to_rfc3339(),
+            "duration_ms": u64::try_from(duration.as_millis()).unwrap_or(u64::MAX),
+            "request": self.prepare(request),
+            "response": self.prepare(response),
+        });
+
+        let result = self
+            .file
+            .lock()
+            .map_err(|_| anyhow!("RPC log lock poisoned"))
+            .and_then(|mut file| {
+                writeln!(file, "{}", entry)?;
+                file.flush()?;
+                Ok(())
+            });
+
+        if let Err(e) = result {
+            warn!("Failed to write RPC log {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn sampled(&self) -> bool {
+        self.sample_rate >= 1.0 || rand::random::<f64>() < self.sample_rate
+    }
+
+    /// Redacts a message, then truncates it if it is still too large.
+    fn prepare(&self, mut message: Value) -> Value {
+        redact_secret_arguments(&mut message);
+        for rule in self.redactions.iter() {
+            rule.redact(&mut message);
+        }
+        truncate(message, self.max_bytes)
+    }
+}
+
+/// Replaces a message serializing to more than `max_bytes` with a preview.
+fn truncate(message: Value, max_bytes: usize) -> Value {
+    let text = message.to_string();
+    if text.len() <= max_bytes {
+        return message;
+    }
+
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    serde_json::json!({
+        "truncated": true,
+        "bytes": text.len(),
+        "preview": &text[..end],
+    })
+}
+
+fn parse_or_string(text: &str) -> Value {
+    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn redacted(rule: &str, mut value: Value) -> Value {
+        JsonPath::parse(rule).unwrap().redact(&mut value);
+        value
+    }
+
+    #[test]
+    fn test_jsonpath_rules() {
+        let call = json!({
+            "params": {
+                "arguments": { "card": "4111", "items": [{ "pin": 1 }, { "pin": 2 }] },
+                "name": "pay"
+            }
+        });
+
+        let value = redacted("$.params.arguments.card", call.clone());
+        assert_eq!(value["params"]["arguments"]["card"], REDACTED);
+        assert_eq!(value["params"]["name"], "pay");
+
+        let value = redacted("$..pin", call.clone());
+        assert_eq!(value["params"]["arguments"]["items"][1]["pin"], REDACTED);
+
+        let value = redacted("$['params'].arguments.items[0].*", call.clone());
+        assert_eq!(value["params"]["arguments"]["items"][0]["pin"], REDACTED);
+        assert_eq!(value["params"]["arguments"]["items"][1]["pin"], 2);
+
+        let value = redacted("$.params.arguments.items[*]", call);
+        assert_eq!(
+            value["params"]["arguments"]["items"],
+            json!([REDACTED, REDACTED])
+        );
+
+        assert!(JsonPath::parse("params.card").is_err());
+        assert!(JsonPath::parse("$").is_err());
+        assert!(JsonPath::parse("$.items[x]").is_err());
+        assert!(JsonPath::parse("$.items[0").is_err());
+    }
+
+    #[test]
+    fn test_logs_redacted_and_truncated_exchanges() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("logs/rpc.jsonl");
+        let config =
+            RpcLogConfig::from_args(&["$.params.arguments.card".to_string()], 1.0, 100).unwrap();
+        let logger = RpcLogger::open(&path, config).unwrap();
+
+        logger.log_text(
+            r#"{"method":"tools/call","params":{"arguments":{"card":"4111","password":"x"}}}"#,
+            &format!(r#"{{"result":"{}"}}"#, "a".repeat(200)),
+            Duration::from_millis(5),
+        );
+
+        let contents = std::fs::read_to_string(logger.path()).unwrap();
+        let entry: Value = serde_json::from_str(contents.trim()).unwrap();
+        assert_eq!(entry["duration_ms"], 5);
+        assert_eq!(entry["request"]["params"]["arguments"]["card"], REDACTED);
+        assert_eq!(
+            entry["request"]["params"]["arguments"]["password"],
+            REDACTED
+        );
+        assert_eq!(entry["response"]["truncated"], true);
+        assert_eq!(entry["response"]["preview"].as_str().unwrap().len(), 100);
+    }
+
+    #[test]
+    fn test_secret_values_are_redacted() {
+        let mut call = json!({
+            "method": "tools/call",
+            "params": { "name": "billing_rotate_secret", "arguments": { "name": "stripe", "value": "sk_live" } }
+        });
+        redact_secret_arguments(&mut call);
+        assert_eq!(call["params"]["arguments"]["value"], REDACTED);
+        assert_eq!(call["params"]["arguments"]["name"], "stripe");
+
+        let mut other = json!({
+            "method": "tools/call",
+            "params": { "name": "set_note", "arguments": { "value": "groceries" } }
+        });
+        redact_secret_arguments(&mut other);
+        assert_eq!(other["params"]["arguments"]["value"], "groceries");
+    }
+
+    #[test]
+    fn test_sampling_keeps_errors() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("rpc.jsonl");
+        let config = RpcLogConfig::from_args(&[], 0.0, 1024).unwrap();
+        let logger = RpcLogger::open(&path, config).unwrap();
+
+        logger.log_text(
+            r#"{"method":"ping"}"#,
+            r#"{"result":"pong"}"#,
+            Duration::ZERO,
+        );
+        logger.log_text(
+            r#"{"method":"x"}"#,
+            r#"{"error":"Unknown"}"#,
+            Duration::ZERO,
+        );
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 1);
+        assert!(contents.contains("Unknown"));
+
+        assert!(RpcLogConfig::from_args(&[], 1.5, 1024).is_err());
+    }
+}
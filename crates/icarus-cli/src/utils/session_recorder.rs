@@ -0,0 +1,130 @@
+//! JSON Lines recording of the JSON-RPC traffic passing through a bridge.
+//!
+//! Each line holds one exchange:
+//!
+//! ```json
+//! {"request": {...}, "response": {...}, "recorded_at": "2025-10-01T12:00:00Z"}
+//! ```
+//!
+//! Recordings of `tools/call` requests can be replayed against a canister
+//! with `icarus_test::harness::replay` to turn real sessions into regression
+//! tests. Secret values passed to `set_secret` and `rotate_secret` are
+//! recorded as `[REDACTED]`, so replaying those calls stores a placeholder.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+use crate::utils::rpc_log::redact_secret_arguments;
+
+/// Appends request/response pairs to a JSON Lines file.
+#[derive(Clone)]
+pub(crate) struct SessionRecorder {
+    path: PathBuf,
+    file: Arc<Mutex<File>>,
+}
+
+impl SessionRecorder {
+    /// Creates (or truncates) the recording file.
+    pub(crate) fn create(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create recording {}", path.display()))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    /// Path of the recording file.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records one exchange. Failures are logged rather than returned so that
+    /// a full disk never breaks the session being recorded.
+    pub(crate) fn record(&self, request: &Value, response: &Value) {
+        let mut request = request.clone();
+        redact_secret_arguments(&mut request);
+        let entry = serde_json::json!({
+            "request": request,
+            "response": response,
+            "recorded_at": chrono::Utc::now().to_rfc3339(),
+        });
+
+        let result = self
+            .file
+            .lock()
+            .map_err(|_| anyhow::anyhow!("recording lock poisoned"))
+            .and_then(|mut file| {
+                writeln!(file, "{}", entry)?;
+                file.flush()?;
+                Ok(())
+            });
+
+        if let Err(e) = result {
+            warn!(
+                "Failed to record exchange to {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+
+    /// Records an exchange whose text may or may not be JSON.
+    pub(crate) fn record_text(&self, request: &str, response: &str) {
+        self.record(&parse_or_string(request), &parse_or_string(response));
+    }
+}
+
+fn parse_or_string(text: &str) -> Value {
+    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_records_one_line_per_exchange() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("sessions/session.jsonl");
+        let recorder = SessionRecorder::create(&path).unwrap();
+
+        recorder.record(
+            &serde_json::json!({ "method": "tools/call" }),
+            &serde_json::json!({ "result": {} }),
+        );
+        recorder.record_text(r#"{"method":"ping"}"#, "not json");
+        recorder.record(
+            &serde_json::json!({
+                "method": "tools/call",
+                "params": { "name": "set_secret", "arguments": { "name": "k", "value": "hunter2" } }
+            }),
+            &serde_json::json!({ "result": {} }),
+        );
+
+        let contents = std::fs::read_to_string(recorder.path()).unwrap();
+        let lines: Vec<Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["request"]["method"], "tools/call");
+        assert_eq!(lines[1]["request"]["method"], "ping");
+        assert_eq!(lines[1]["response"], "not json");
+        assert_eq!(
+            lines[2]["request"]["params"]["arguments"]["value"],
+            "[REDACTED]"
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! Detecting dependencies that need WASI
+//!
+//! Some crates only build for `wasm32-unknown-unknown` with the right
+//! features, and otherwise fail with link errors that do not name the
+//! culprit. Such a project is built for `wasm32-wasip1` instead and the
+//! module's WASI imports are rewritten by `wasi2ic` into calls to the
+//! `ic-wasi-polyfill`, giving a module the IC accepts.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+use super::candid;
+
+/// Target of builds that need WASI
+pub(crate) const WASI_TARGET: &str = "wasm32-wasip1";
+
+/// tokio features that reach for the operating system
+const TOKIO_OS_FEATURES: &[&str] = &[
+    "fs",
+    "io-std",
+    "net",
+    "process",
+    "rt-multi-thread",
+    "signal",
+];
+
+/// A dependency that needs WASI, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WasiNeed {
+    pub(crate) package: String,
+    pub(crate) version: String,
+    pub(crate) reason: String,
+}
+
+/// Lists the project's dependencies that need WASI, from `cargo metadata`
+pub(crate) async fn scan(project_root: &Path) -> Result<Vec<WasiNeed>> {
+    let output = Command::new("cargo")
+        .args([
+            "metadata",
+            "--format-version",
+            "1",
+            "--filter-platform",
+            "wasm32-unknown-unknown",
+        ])
+        .current_dir(project_root)
+        .output()
+        .await
+        .context("Failed to execute cargo metadata")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("cargo metadata failed: {}", stderr));
+    }
+
+    let metadata: Value =
+        serde_json::from_slice(&output.stdout).context("cargo metadata printed invalid JSON")?;
+    Ok(needs(&metadata))
+}
+
+/// Finds the packages in the resolved graph of `metadata` that need WASI
+pub(crate) fn needs(metadata: &Value) -> Vec<WasiNeed> {
+    let packages = metadata["packages"]
+        .as_array()
+        .map_or(&[][..], Vec::as_slice);
+    let nodes = metadata["resolve"]["nodes"]
+        .as_array()
+        .map_or(&[][..], Vec::as_slice);
+
+    let mut needs: Vec<WasiNeed> = nodes
+        .iter()
+        .filter_map(|node| {
+            let package = packages
+                .iter()
+                .find(|package| package["id"] == node["id"])?;
+            let name = package["name"].as_str()?;
+            let version = package["version"].as_str()?;
+            let features: Vec<&str> = node["features"]
+                .as_array()
+                .map(|features| features.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+
+            let reason = reason(name, version, &features)?;
+            Some(WasiNeed {
+                package: name.to_string(),
+                version: version.to_string(),
+                reason,
+            })
+        })
+        .collect();
+    needs.sort_by(|a, b| (&a.package, &a.version).cmp(&(&b.package, &b.version)));
+    needs
+}
+
+/// Why `name` with `features` needs WASI, if it does
+fn reason(name: &str, version: &str, features: &[&str]) -> Option<String> {
+    match name {
+        "tokio" => {
+            let os: Vec<&str> = features
+                .iter()
+                .copied()
+                .filter(|feature| TOKIO_OS_FEATURES.contains(feature))
+                .collect();
+            (!os.is_empty()).then(|| format!("uses OS features: {}", os.join(", ")))
+        }
+        "reqwest" => Some("opens sockets; use HTTPS outcalls from a canister".to_string()),
+        "getrandom" if version.starts_with("0.2.") => (!features
+            .iter()
+            .any(|feature| matches!(*feature, "custom" | "js")))
+        .then(|| "has no randomness source without the `custom` feature".to_string()),
+        "getrandom" => (!features.contains(&"wasm_js"))
+            .then(|| "has no randomness source without the `wasm_js` feature".to_string()),
+        _ => None,
+    }
+}
+
+/// Path of the module cargo builds for `package` with `profile` for WASI
+pub(crate) fn wasm_path(project_root: &Path, package: &str, profile: &str) -> PathBuf {
+    project_root
+        .join("target")
+        .join(WASI_TARGET)
+        .join(profile)
+        .join(format!("{}.wasm", package.replace('-', "_")))
+}
+
+pub(crate) fn is_wasi2ic_available() -> bool {
+    which::which("wasi2ic").is_ok()
+}
+
+/// Rewrites the WASI build of `package` with `wasi2ic`, writing it where
+/// dfx and candid-extractor expect the module
+pub(crate) async fn transform(
+    project_root: &Path,
+    package: &str,
+    profile: &str,
+) -> Result<PathBuf> {
+    if !is_wasi2ic_available() {
+        return Err(anyhow!(
+            "wasi2ic not found; install it with `cargo install wasi2ic`"
+        ));
+    }
+
+    let input = wasm_path(project_root, package, profile);
+    let output_path = candid::wasm_path(project_root, package, profile);
+    if let Some(parent) = output_path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    let output = Command::new("wasi2ic")
+        .arg(&input)
+        .arg(&output_path)
+        .output()
+        .await
+        .context("Failed to execute wasi2ic")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("wasi2ic failed: {}", stderr));
+    }
+
+    Ok(output_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn metadata(packages: &[(&str, &str, &[&str])]) -> Value {
+        json!({
+            "packages": packages
+                .iter()
+                .map(|(name, version, _)| json!({
+                    "id": format!("{name} {version}"),
+                    "name": name,
+                    "version": version,
+                }))
+                .collect::<Vec<_>>(),
+            "resolve": {
+                "nodes": packages
+                    .iter()
+                    .map(|(name, version, features)| json!({
+                        "id": format!("{name} {version}"),
+                        "features": features,
+                    }))
+                    .collect::<Vec<_>>(),
+            },
+        })
+    }
+
+    #[test]
+    fn test_canister_friendly_graph_needs_nothing() {
+        let metadata = metadata(&[
+            ("tokio", "1.40.0", &["sync", "macros", "rt", "time"]),
+            ("getrandom", "0.2.15", &["custom"]),
+            ("getrandom", "0.3.3", &["wasm_js"]),
+            ("serde", "1.0.210", &["derive", "std"]),
+        ]);
+        assert!(needs(&metadata).is_empty());
+    }
+
+    #[test]
+    fn test_reports_why_wasi_is_needed() {
+        let metadata = metadata(&[
+            ("tokio", "1.40.0", &["rt-multi-thread", "net", "sync"]),
+            ("reqwest", "0.12.8", &["json"]),
+            ("getrandom", "0.2.15", &["std"]),
+        ]);
+        let needs = needs(&metadata);
+
+        assert_eq!(
+            needs
+                .iter()
+                .map(|need| need.package.as_str())
+                .collect::<Vec<_>>(),
+            ["getrandom", "reqwest", "tokio"]
+        );
+        assert!(needs[0].reason.contains("`custom` feature"));
+        assert_eq!(needs[2].reason, "uses OS features: rt-multi-thread, net");
+    }
+
+    #[test]
+    fn test_wasm_path() {
+        assert_eq!(
+            wasm_path(Path::new("/p"), "weather-bot", "release"),
+            Path::new("/p/target/wasm32-wasip1/release/weather_bot.wasm")
+        );
+    }
+}
@@ -0,0 +1,366 @@
+//! Size budgets for canister WASM modules
+//!
+//! Canisters are uploaded gzipped, so the budget applies to the gzipped
+//! module. When a module is over budget, [`analyze`] attributes the code
+//! section to functions by their entry in the `name` custom section and sums
+//! them per crate, the way twiggy's `top` does, so the report points at what
+//! to trim. Modules built without names (e.g. stripped by `ic-wasm shrink`)
+//! are reported by function index.
+
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::HashMap;
+use std::io::Write;
+
+const WASM_MAGIC: &[u8] = b"\0asm";
+
+/// Code size attributed to functions and the crates they come from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SizeReport {
+    /// Bytes in the code section
+    pub(crate) code_size: u64,
+    /// `(function, body size)`, largest first
+    pub(crate) functions: Vec<(String, u64)>,
+    /// `(crate, summed body size)`, largest first
+    pub(crate) crates: Vec<(String, u64)>,
+}
+
+/// Parses a size such as `2MB`, `512KiB` or `1048576`
+///
+/// Units are binary: `K`/`KB`/`KiB` are 1024 bytes and `M`/`MB`/`MiB` are
+/// 1024 KiB, matching how the IC states its module size limits.
+pub(crate) fn parse_size(size: &str) -> Result<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1024,
+        "m" | "mb" | "mib" => 1024 * 1024,
+        _ => bail!("Invalid size unit in '{}'. Use B, KB or MB", size),
+    };
+    let number: f64 = number
+        .parse()
+        .with_context(|| format!("Invalid size: '{}'", size))?;
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let bytes = (number * multiplier as f64).round() as u64;
+    Ok(bytes)
+}
+
+/// Formats `bytes` in the units [`parse_size`] accepts
+pub(crate) fn format_size(bytes: u64) -> String {
+    const KIB: u64 = 1024;
+    const MIB: u64 = 1024 * 1024;
+    match bytes {
+        b if b >= MIB => format!("{:.2} MB", b as f64 / MIB as f64),
+        b if b >= KIB => format!("{:.1} KB", b as f64 / KIB as f64),
+        b => format!("{b} B"),
+    }
+}
+
+/// Size of `wasm` after gzip at the best compression level
+pub(crate) fn gzipped_size(wasm: &[u8]) -> Result<u64> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(wasm)?;
+    Ok(encoder.finish()?.len() as u64)
+}
+
+/// Attributes the code section of `wasm` to functions and crates
+pub(crate) fn analyze(wasm: &[u8]) -> Result<SizeReport> {
+    if !wasm.starts_with(WASM_MAGIC) || wasm.len() < 8 {
+        bail!("Not a WASM module");
+    }
+
+    let mut reader = Reader::new(&wasm[8..]);
+    let mut imported_functions = 0u32;
+    let mut bodies: Vec<u64> = Vec::new();
+    let mut code_size = 0u64;
+    let mut names: HashMap<u32, String> = HashMap::new();
+
+    while !reader.is_empty() {
+        let id = reader.byte()?;
+        let len = reader.u32()? as usize;
+        let mut section = Reader::new(reader.take(len)?);
+        match id {
+            0 => {
+                if section.name()? == "name" {
+                    names = function_names(&mut section)?;
+                }
+            }
+            2 => imported_functions = count_function_imports(&mut section)?,
+            10 => {
+                code_size = len as u64;
+                for _ in 0..section.u32()? {
+                    let body = section.u32()?;
+                    section.take(body as usize)?;
+                    bodies.push(u64::from(body));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut functions: Vec<(String, u64)> = bodies
+        .into_iter()
+        .enumerate()
+        .map(|(i, size)| {
+            let index = imported_functions + u32::try_from(i).unwrap_or(u32::MAX);
+            let name = names.get(&index).map_or_else(
+                || format!("function[{index}]"),
+                |name| format!("{:#}", rustc_demangle::demangle(name)),
+            );
+            (name, size)
+        })
+        .collect();
+    functions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut by_crate: HashMap<String, u64> = HashMap::new();
+    for (name, size) in &functions {
+        *by_crate.entry(crate_of(name)).or_default() += size;
+    }
+    let mut crates: Vec<(String, u64)> = by_crate.into_iter().collect();
+    crates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    Ok(SizeReport {
+        code_size,
+        functions,
+        crates,
+    })
+}
+
+/// Crate a demangled function path belongs to
+///
+/// For trait impls such as `<serde_json::Value as Clone>::clone` this is the
+/// crate of the implementing type.
+fn crate_of(function: &str) -> String {
+    let path = function.trim_start_matches(['<', '&', '*']);
+    let path = path.strip_prefix("mut ").unwrap_or(path);
+    match path.find("::") {
+        Some(end) if end > 0 => path[..end].to_string(),
+        _ => "[unknown]".to_string(),
+    }
+}
+
+/// Number of functions among the entries of an import section
+fn count_function_imports(section: &mut Reader<'_>) -> Result<u32> {
+    let mut functions = 0;
+    for _ in 0..section.u32()? {
+        section.name()?;
+        section.name()?;
+        match section.byte()? {
+            0x00 => {
+                section.u32()?;
+                functions += 1;
+            }
+            0x01 => {
+                section.byte()?;
+                section.limits()?;
+            }
+            0x02 => section.limits()?,
+            0x03 => {
+                section.byte()?;
+                section.byte()?;
+            }
+            0x04 => {
+                section.byte()?;
+                section.u32()?;
+            }
+            kind => bail!("Unknown import kind {:#04x}", kind),
+        }
+    }
+    Ok(functions)
+}
+
+/// Function names from the body of a `name` custom section
+fn function_names(section: &mut Reader<'_>) -> Result<HashMap<u32, String>> {
+    let mut names = HashMap::new();
+    while !section.is_empty() {
+        let id = section.byte()?;
+        let len = section.u32()? as usize;
+        let mut subsection = Reader::new(section.take(len)?);
+        if id == 1 {
+            for _ in 0..subsection.u32()? {
+                let index = subsection.u32()?;
+                names.insert(index, subsection.name()?.to_string());
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// Cursor over the bytes of a module
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.bytes.len() {
+            bail!("Unexpected end of WASM module");
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Unsigned LEB128
+    fn u64(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(anyhow!("Malformed integer in WASM module"))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        u32::try_from(self.u64()?).map_err(|_| anyhow!("Malformed integer in WASM module"))
+    }
+
+    fn name(&mut self) -> Result<&'a str> {
+        let len = self.u32()? as usize;
+        std::str::from_utf8(self.take(len)?).context("Invalid name in WASM module")
+    }
+
+    fn limits(&mut self) -> Result<()> {
+        let flags = self.byte()?;
+        self.u64()?;
+        if flags & 0x01 != 0 {
+            self.u64()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leb(mut value: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        loop {
+            let byte = u8::try_from(value & 0x7f).unwrap();
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                return out;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    fn name(s: &str) -> Vec<u8> {
+        [leb(s.len()), s.as_bytes().to_vec()].concat()
+    }
+
+    fn section(id: u8, content: &[u8]) -> Vec<u8> {
+        [vec![id], leb(content.len()), content.to_vec()].concat()
+    }
+
+    /// One imported function followed by three defined ones
+    fn module() -> Vec<u8> {
+        let types = section(1, &[1, 0x60, 0, 0]);
+        let imports = section(
+            2,
+            &[vec![1], name("ic0"), name("trap"), vec![0, 0]].concat(),
+        );
+        let functions = section(3, &[3, 0, 0, 0]);
+        let code = section(
+            10,
+            &[
+                vec![3],
+                vec![2, 0, 0x0b],
+                vec![5, 0, 1, 1, 1, 0x0b],
+                vec![4, 0, 1, 1, 0x0b],
+            ]
+            .concat(),
+        );
+        let entries = [
+            vec![3],
+            leb(1),
+            name("_ZN5serde2de7Visitor4test17h0123456789abcdefE"),
+            leb(2),
+            name("icarus_core::tool::call"),
+            leb(3),
+            name("<serde_json::value::Value as core::clone::Clone>::clone"),
+        ]
+        .concat();
+        let names = section(
+            0,
+            &[name("name"), vec![1], leb(entries.len()), entries].concat(),
+        );
+
+        [
+            b"\0asm\x01\0\0\0".to_vec(),
+            types,
+            imports,
+            functions,
+            code,
+            names,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("2MB").unwrap(), 2 * 1024 * 1024);
+        assert_eq!(parse_size("1.5 MiB").unwrap(), 1_572_864);
+        assert_eq!(parse_size("512k").unwrap(), 512 * 1024);
+        assert_eq!(parse_size("1000").unwrap(), 1000);
+        assert!(parse_size("2GB").is_err());
+        assert!(parse_size("MB").is_err());
+
+        assert_eq!(format_size(2 * 1024 * 1024), "2.00 MB");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(12), "12 B");
+    }
+
+    #[test]
+    fn test_analyze_attributes_code_to_functions_and_crates() {
+        let report = analyze(&module()).unwrap();
+
+        assert_eq!(report.code_size, 15);
+        assert_eq!(
+            report.functions,
+            vec![
+                ("icarus_core::tool::call".to_string(), 5),
+                (
+                    "<serde_json::value::Value as core::clone::Clone>::clone".to_string(),
+                    4
+                ),
+                ("serde::de::Visitor::test".to_string(), 2),
+            ]
+        );
+        assert_eq!(
+            report.crates,
+            vec![
+                ("icarus_core".to_string(), 5),
+                ("serde_json".to_string(), 4),
+                ("serde".to_string(), 2),
+            ]
+        );
+
+        assert!(analyze(b"not wasm").is_err());
+        assert!(gzipped_size(&module()).unwrap() > 0);
+    }
+}
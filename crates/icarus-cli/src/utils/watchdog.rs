@@ -0,0 +1,134 @@
+//! Detection and recovery of bridges that stop responding.
+//!
+//! A bridge can wedge without exiting: its agent holds on to a dead
+//! connection, or the client stopped reading its stdio, and the client just
+//! hangs. The bridge pings the canister and the client every
+//! [`BridgeConfig::watchdog_interval`](crate::utils::rmcp_bridge::BridgeConfig::watchdog_interval),
+//! counting consecutive failed pings with a [`Watchdog`] each:
+//!
+//! - after too many failed canister pings it drops its pooled agent and
+//!   closes the circuit breaker, so the next call connects afresh
+//! - after too many failed client pings it exits with [`WEDGED_EXIT_CODE`],
+//!   so whatever launched it can start a new one
+//!
+//! `icarus mcp start --daemon --supervise` runs the bridge under a
+//! supervisor that restarts it when it exits with an error or stops
+//! accepting connections, waiting longer after each restart. A
+//! [`RestartPolicy`] gives up when the bridge keeps failing.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Exit code of a bridge whose client stopped answering pings (`EX_TEMPFAIL`).
+pub(crate) const WEDGED_EXIT_CODE: i32 = 75;
+
+/// Counts consecutive failed pings and says when to recover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Watchdog {
+    threshold: u32,
+    failures: u32,
+}
+
+impl Watchdog {
+    pub(crate) fn new(threshold: u32) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            failures: 0,
+        }
+    }
+
+    /// Records a ping, returning whether the failures reached the threshold.
+    /// The count starts over after each recovery.
+    pub(crate) fn record(&mut self, ok: bool) -> bool {
+        if ok {
+            self.failures = 0;
+            return false;
+        }
+        self.failures += 1;
+        if self.failures < self.threshold {
+            return false;
+        }
+        self.failures = 0;
+        true
+    }
+}
+
+/// How the supervisor restarts a failed bridge.
+#[allow(dead_code)] // Only the CLI binary supervises bridges
+#[derive(Debug, Clone)]
+pub(crate) struct RestartPolicy {
+    /// Restarts allowed within `window` before giving up
+    max_restarts: u32,
+    window: Duration,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    restarts: VecDeque<Instant>,
+}
+
+#[allow(dead_code)]
+impl RestartPolicy {
+    pub(crate) fn new(max_restarts: u32, window: Duration) -> Self {
+        Self {
+            max_restarts,
+            window,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            restarts: VecDeque::new(),
+        }
+    }
+
+    /// Records a failure at `now`, returning how long to wait before the
+    /// restart, or `None` after too many restarts within the window.
+    pub(crate) fn next_restart(&mut self, now: Instant) -> Option<Duration> {
+        while self
+            .restarts
+            .front()
+            .is_some_and(|restart| now.duration_since(*restart) > self.window)
+        {
+            self.restarts.pop_front();
+        }
+        let recent = u32::try_from(self.restarts.len()).unwrap_or(u32::MAX);
+        if recent >= self.max_restarts {
+            return None;
+        }
+
+        self.restarts.push_back(now);
+        Some(
+            self.initial_backoff
+                .saturating_mul(2u32.saturating_pow(recent))
+                .min(self.max_backoff),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchdog_recovers_after_consecutive_failures() {
+        let mut watchdog = Watchdog::new(3);
+        assert!(!watchdog.record(false));
+        assert!(!watchdog.record(false));
+        // A success starts the count over
+        assert!(!watchdog.record(true));
+        assert!(!watchdog.record(false));
+        assert!(!watchdog.record(false));
+        assert!(watchdog.record(false));
+        assert!(!watchdog.record(false));
+    }
+
+    #[test]
+    fn test_restart_policy_backs_off_and_gives_up() {
+        let start = Instant::now();
+        let mut policy = RestartPolicy::new(3, Duration::from_secs(600));
+        assert_eq!(policy.next_restart(start), Some(Duration::from_secs(1)));
+        assert_eq!(policy.next_restart(start), Some(Duration::from_secs(2)));
+        assert_eq!(policy.next_restart(start), Some(Duration::from_secs(4)));
+        assert_eq!(policy.next_restart(start), None);
+
+        // Restarts older than the window no longer count
+        let later = start + Duration::from_secs(601);
+        assert_eq!(policy.next_restart(later), Some(Duration::from_secs(1)));
+    }
+}
@@ -447,6 +447,9 @@ fn test_mcp_client_detection() {
         "claude-code",
         "chatgpt-desktop",
         "continue",
+        "cursor",
+        "windsurf",
+        "zed",
     ];
 
     for client in clients {
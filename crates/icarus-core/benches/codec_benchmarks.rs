@@ -0,0 +1,63 @@
+//! Benchmarks comparing the stored value codecs.
+//!
+//! Run with `cargo bench -p icarus-core --features cbor,bincode`. Besides
+//! timing, the encoded sizes are printed once so the space trade-off is
+//! visible next to the speed.
+
+use candid::CandidType;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use icarus_core::codec::Codec;
+use serde::{Deserialize, Serialize};
+
+#[derive(CandidType, Serialize, Deserialize)]
+struct Record {
+    id: u64,
+    title: String,
+    content: String,
+    tags: Vec<String>,
+    scores: Vec<f64>,
+    created_at: u64,
+    archived: bool,
+}
+
+fn create_record() -> Record {
+    Record {
+        id: 42,
+        title: "Quarterly planning notes".to_string(),
+        content: "Lorem ipsum dolor sit amet. ".repeat(40),
+        tags: (0..8).map(|i| format!("tag-{i}")).collect(),
+        scores: (0..32).map(f64::from).collect(),
+        created_at: 1_700_000_000_000_000_000,
+        archived: false,
+    }
+}
+
+const CODECS: [Codec; 3] = [Codec::Candid, Codec::Cbor, Codec::Bincode];
+
+fn bench_codecs(c: &mut Criterion) {
+    let record = create_record();
+    for codec in CODECS {
+        let size = codec.encode(&record).expect("encodes").len();
+        println!("{codec}: {size} bytes");
+    }
+
+    let mut group = c.benchmark_group("codec_encode");
+    for codec in CODECS {
+        group.bench_with_input(BenchmarkId::from_parameter(codec), &codec, |b, codec| {
+            b.iter(|| codec.encode(black_box(&record)).expect("encodes"));
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("codec_decode");
+    for codec in CODECS {
+        let bytes = codec.encode(&record).expect("encodes");
+        group.bench_with_input(BenchmarkId::from_parameter(codec), &bytes, |b, bytes| {
+            b.iter(|| codec.decode::<Record>(black_box(bytes)).expect("decodes"));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_codecs);
+criterion_main!(benches);
@@ -0,0 +1,319 @@
+//! Per-resource access grants.
+//!
+//! The whitelist in [`auth`](crate::auth) decides who may call a tool; this
+//! module decides what a caller may do with one particular record. Each
+//! resource id maps principals to a set of [`Permission`]s, stored in stable
+//! memory so grants survive upgrades.
+//!
+//! Tools check grants with [`require_permission`], which looks at the
+//! current [`caller`](crate::context::caller). Admins pass every check, so a
+//! record never becomes unreachable when its grants are lost or revoked.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use candid::Principal;
+//! use icarus_core::acl::{self, Permission};
+//!
+//! let owner = Principal::from_slice(&[1]);
+//! let reader = Principal::from_slice(&[2]);
+//!
+//! acl::grant("record:7", owner, &Permission::ALL).unwrap();
+//! acl::grant("record:7", reader, &[Permission::Read]).unwrap();
+//!
+//! assert!(acl::has_permission("record:7", &reader, Permission::Read));
+//! assert!(!acl::has_permission("record:7", &reader, Permission::Write));
+//! assert_eq!(acl::grants("record:7").len(), 2);
+//!
+//! acl::revoke("record:7", &reader);
+//! assert!(!acl::has_permission("record:7", &reader, Permission::Read));
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Deserialize, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+
+use crate::memory::{self, StableMemory, ACL_GRANTS_MEMORY_ID};
+use crate::{auth, context, IcarusError, Result};
+
+/// Longest accepted resource id in bytes.
+pub const MAX_RESOURCE_ID_LENGTH: usize = 256;
+
+/// Longest principal in bytes.
+const MAX_PRINCIPAL_LENGTH: usize = 29;
+
+/// Something a principal may do with a resource.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, CandidType, Deserialize, Serialize,
+)]
+pub enum Permission {
+    /// View the resource
+    Read,
+    /// Change or delete the resource
+    Write,
+    /// Grant and revoke other principals' permissions
+    Share,
+}
+
+impl Permission {
+    /// Every permission, as given to the creator of a resource.
+    pub const ALL: [Permission; 3] = [Permission::Read, Permission::Write, Permission::Share];
+
+    fn bit(self) -> u8 {
+        match self {
+            Self::Read => 1,
+            Self::Write => 2,
+            Self::Share => 4,
+        }
+    }
+
+    fn from_bits(bits: u8) -> Vec<Permission> {
+        Self::ALL
+            .into_iter()
+            .filter(|permission| bits & permission.bit() != 0)
+            .collect()
+    }
+}
+
+/// The permissions of one principal on a resource.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct Grant {
+    /// Principal holding the permissions.
+    pub principal: Principal,
+    /// Granted permissions, in `Read`, `Write`, `Share` order.
+    pub permissions: Vec<Permission>,
+}
+
+/// Resource id, a zero byte, then the principal's bytes.
+///
+/// Resource ids cannot contain zero bytes, so all grants of a resource sort
+/// together between `id\0` and `id\x01`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct GrantKey(Vec<u8>);
+
+impl GrantKey {
+    fn new(resource_id: &str, principal: &Principal) -> Self {
+        let mut bytes = Self::start(resource_id).0;
+        bytes.extend_from_slice(principal.as_slice());
+        Self(bytes)
+    }
+
+    /// Lowest key of `resource_id`.
+    fn start(resource_id: &str) -> Self {
+        let mut bytes = resource_id.as_bytes().to_vec();
+        bytes.push(0);
+        Self(bytes)
+    }
+
+    /// First key past those of `resource_id`.
+    fn end(resource_id: &str) -> Self {
+        let mut bytes = resource_id.as_bytes().to_vec();
+        bytes.push(1);
+        Self(bytes)
+    }
+
+    fn principal(&self) -> Principal {
+        let separator = self.0.iter().position(|b| *b == 0).unwrap_or_default();
+        Principal::from_slice(&self.0[separator + 1..])
+    }
+}
+
+impl Storable for GrantKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Self(bytes.into_owned())
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    const BOUND: Bound = Bound::Bounded {
+        max_size: (MAX_RESOURCE_ID_LENGTH + 1 + MAX_PRINCIPAL_LENGTH) as u32,
+        is_fixed_size: false,
+    };
+}
+
+thread_local! {
+    /// Permission bits by resource and principal.
+    static GRANTS: RefCell<StableBTreeMap<GrantKey, u8, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(ACL_GRANTS_MEMORY_ID))
+    );
+}
+
+fn check_resource_id(resource_id: &str) -> Result<()> {
+    if resource_id.is_empty()
+        || resource_id.len() > MAX_RESOURCE_ID_LENGTH
+        || resource_id.contains('\0')
+    {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Resource ids must be 1 to {MAX_RESOURCE_ID_LENGTH} bytes without NUL characters"
+        )));
+    }
+    Ok(())
+}
+
+/// Sets the permissions of `principal` on `resource_id`, replacing any it
+/// had. An empty `permissions` revokes them.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if `resource_id` is empty,
+/// longer than [`MAX_RESOURCE_ID_LENGTH`] or contains a NUL character.
+pub fn grant(resource_id: &str, principal: Principal, permissions: &[Permission]) -> Result<()> {
+    check_resource_id(resource_id)?;
+    let bits = permissions.iter().fold(0, |bits, p| bits | p.bit());
+    let key = GrantKey::new(resource_id, &principal);
+    GRANTS.with(|grants| {
+        let mut grants = grants.borrow_mut();
+        if bits == 0 {
+            grants.remove(&key);
+        } else {
+            grants.insert(key, bits);
+        }
+    });
+    Ok(())
+}
+
+/// Removes all permissions of `principal` on `resource_id` and returns
+/// whether it had any.
+pub fn revoke(resource_id: &str, principal: &Principal) -> bool {
+    GRANTS.with(|grants| {
+        grants
+            .borrow_mut()
+            .remove(&GrantKey::new(resource_id, principal))
+            .is_some()
+    })
+}
+
+/// Removes every grant on `resource_id`, e.g. when the resource is deleted,
+/// and returns how many were removed.
+pub fn revoke_all(resource_id: &str) -> usize {
+    GRANTS.with(|grants| {
+        let mut grants = grants.borrow_mut();
+        let keys: Vec<GrantKey> = grants
+            .range(GrantKey::start(resource_id)..GrantKey::end(resource_id))
+            .map(|entry| entry.key().clone())
+            .collect();
+        for key in &keys {
+            grants.remove(key);
+        }
+        keys.len()
+    })
+}
+
+/// Returns the permissions of `principal` on `resource_id`.
+#[must_use]
+pub fn permissions(resource_id: &str, principal: &Principal) -> Vec<Permission> {
+    GRANTS
+        .with(|grants| grants.borrow().get(&GrantKey::new(resource_id, principal)))
+        .map_or_else(Vec::new, Permission::from_bits)
+}
+
+/// Returns whether `principal` was granted `permission` on `resource_id`.
+///
+/// Admins are not special here; see [`require_permission`].
+#[must_use]
+pub fn has_permission(resource_id: &str, principal: &Principal, permission: Permission) -> bool {
+    GRANTS
+        .with(|grants| grants.borrow().get(&GrantKey::new(resource_id, principal)))
+        .is_some_and(|bits| bits & permission.bit() != 0)
+}
+
+/// Returns every grant on `resource_id`, ordered by principal.
+#[must_use]
+pub fn grants(resource_id: &str) -> Vec<Grant> {
+    GRANTS.with(|grants| {
+        grants
+            .borrow()
+            .range(GrantKey::start(resource_id)..GrantKey::end(resource_id))
+            .map(|entry| Grant {
+                principal: entry.key().principal(),
+                permissions: Permission::from_bits(entry.value()),
+            })
+            .collect()
+    })
+}
+
+/// Checks that the current caller holds `permission` on `resource_id`.
+///
+/// Admins always pass.
+///
+/// # Errors
+///
+/// Returns `IcarusError::AccessDenied` if the caller is not an admin and was
+/// not granted `permission`.
+pub fn require_permission(resource_id: &str, permission: Permission) -> Result<()> {
+    let caller = context::caller();
+    if auth::has_admin_access(&caller) || has_permission(resource_id, &caller, permission) {
+        return Ok(());
+    }
+    Err(IcarusError::AccessDenied(format!(
+        "{} lacks {permission:?} permission on {resource_id}",
+        caller.to_text()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grants_are_scoped_to_their_resource() {
+        let alice = Principal::from_slice(&[21]);
+        let bob = Principal::from_slice(&[22, 0, 5]);
+
+        grant("doc", alice, &[Permission::Read, Permission::Write]).unwrap();
+        grant("doc", bob, &[Permission::Read]).unwrap();
+        grant("doc2", alice, &Permission::ALL).unwrap();
+        grant("do", bob, &[Permission::Share]).unwrap();
+
+        let doc = grants("doc");
+        assert_eq!(doc.len(), 2);
+        assert!(doc.contains(&Grant {
+            principal: bob,
+            permissions: vec![Permission::Read],
+        }));
+        assert_eq!(
+            permissions("doc", &alice),
+            [Permission::Read, Permission::Write]
+        );
+        assert!(!has_permission("doc", &alice, Permission::Share));
+
+        // Granting nothing revokes
+        grant("doc", alice, &[]).unwrap();
+        assert!(permissions("doc", &alice).is_empty());
+
+        assert_eq!(revoke_all("doc"), 1);
+        assert!(grants("doc").is_empty());
+        assert_eq!(grants("doc2").len(), 1);
+        assert_eq!(grants("do").len(), 1);
+
+        assert!(grant("", alice, &[Permission::Read]).is_err());
+        assert!(grant("a\0b", alice, &[Permission::Read]).is_err());
+    }
+
+    #[test]
+    fn test_require_permission_uses_caller() {
+        let carol = Principal::from_slice(&[23]);
+        context::set_caller_override(Some(carol));
+
+        assert!(require_permission("report", Permission::Read).is_err());
+        grant("report", carol, &[Permission::Read]).unwrap();
+        assert!(require_permission("report", Permission::Read).is_ok());
+        assert!(require_permission("report", Permission::Write).is_err());
+
+        auth::add_admin(carol);
+        assert!(require_permission("report", Permission::Write).is_ok());
+        auth::remove_admin(&carol);
+
+        context::set_caller_override(None);
+    }
+}
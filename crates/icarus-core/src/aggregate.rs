@@ -0,0 +1,377 @@
+//! Group-by counts and time series over stored records.
+//!
+//! An [`Aggregator`] is configured with extractor closures: which group(s) a
+//! record belongs to, when it happened, and which number to aggregate. Running
+//! it over any iterator of records, such as the values of a `StableBTreeMap`,
+//! yields an [`AggregateReport`] with one row per group and, if a
+//! [`TimeBucket`] is set, one row per time bucket for dashboard charts.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::aggregate::{Aggregator, TimeBucket};
+//!
+//! struct Order {
+//!     region: &'static str,
+//!     total: f64,
+//!     placed_at: u64,
+//! }
+//!
+//! const DAY: u64 = 86_400_000_000_000;
+//! let orders = [
+//!     Order { region: "eu", total: 20.0, placed_at: 0 },
+//!     Order { region: "us", total: 5.0, placed_at: DAY + 1 },
+//!     Order { region: "eu", total: 10.0, placed_at: DAY + 2 },
+//! ];
+//!
+//! let report = Aggregator::new()
+//!     .group_by(|order: &Order| vec![order.region.to_string()])
+//!     .sum(|order| order.total)
+//!     .time_series(|order| order.placed_at, TimeBucket::Day)
+//!     .run(&orders);
+//!
+//! assert_eq!(report.groups[0].key, "eu");
+//! assert_eq!(report.groups[0].value, 30.0);
+//! assert_eq!(report.series[1].count, 2);
+//! ```
+
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+use crate::IcarusError;
+
+const NANOS_PER_HOUR: u64 = 60 * 60 * 1_000_000_000;
+
+/// How the values of a group are combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    /// Number of records.
+    #[default]
+    Count,
+    /// Sum of the extracted values.
+    Sum,
+    /// Mean of the extracted values.
+    Average,
+    /// Smallest extracted value.
+    Min,
+    /// Largest extracted value.
+    Max,
+}
+
+impl FromStr for Metric {
+    type Err = IcarusError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "count" => Ok(Self::Count),
+            "sum" => Ok(Self::Sum),
+            "average" | "avg" => Ok(Self::Average),
+            "min" => Ok(Self::Min),
+            "max" => Ok(Self::Max),
+            _ => Err(IcarusError::ConfigurationError(format!(
+                "Unknown metric '{s}', expected count, sum, average, min or max"
+            ))),
+        }
+    }
+}
+
+/// Width of the buckets of a time series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeBucket {
+    /// One hour.
+    Hour,
+    /// One day (UTC).
+    Day,
+    /// Seven days, starting on Thursdays since the Unix epoch was one.
+    Week,
+}
+
+impl TimeBucket {
+    /// Returns the bucket width in nanoseconds.
+    #[must_use]
+    pub const fn nanos(self) -> u64 {
+        match self {
+            Self::Hour => NANOS_PER_HOUR,
+            Self::Day => 24 * NANOS_PER_HOUR,
+            Self::Week => 7 * 24 * NANOS_PER_HOUR,
+        }
+    }
+
+    /// Returns the start of the bucket containing `timestamp`.
+    #[must_use]
+    pub const fn start_of(self, timestamp: u64) -> u64 {
+        timestamp - timestamp % self.nanos()
+    }
+}
+
+impl FromStr for TimeBucket {
+    type Err = IcarusError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hour" => Ok(Self::Hour),
+            "day" => Ok(Self::Day),
+            "week" => Ok(Self::Week),
+            _ => Err(IcarusError::ConfigurationError(format!(
+                "Unknown time bucket '{s}', expected hour, day or week"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for TimeBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+            Self::Week => "week",
+        })
+    }
+}
+
+/// Aggregate of one group.
+#[derive(Debug, Clone, PartialEq, CandidType, Deserialize, Serialize)]
+pub struct GroupStat {
+    /// Group key.
+    pub key: String,
+    /// Number of records in the group.
+    pub count: u64,
+    /// The configured metric over the group.
+    pub value: f64,
+}
+
+/// Aggregate of one time bucket.
+#[derive(Debug, Clone, PartialEq, CandidType, Deserialize, Serialize)]
+pub struct BucketStat {
+    /// Bucket start in nanoseconds since the epoch.
+    pub bucket_start: u64,
+    /// Number of records in the bucket.
+    pub count: u64,
+    /// The configured metric over the bucket.
+    pub value: f64,
+}
+
+/// Result of [`Aggregator::run`].
+#[derive(Debug, Clone, Default, PartialEq, CandidType, Deserialize, Serialize)]
+pub struct AggregateReport {
+    /// Metric applied to each group and bucket.
+    pub metric: Metric,
+    /// Number of records aggregated.
+    pub total: u64,
+    /// One entry per group, sorted by key. Empty without a group extractor.
+    pub groups: Vec<GroupStat>,
+    /// One entry per non-empty bucket, oldest first. Empty without a time series.
+    pub series: Vec<BucketStat>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Accumulator {
+    count: u64,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl Accumulator {
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+
+    fn value(&self, metric: Metric) -> f64 {
+        match metric {
+            Metric::Count => self.count as f64,
+            Metric::Sum => self.sum,
+            Metric::Average if self.count > 0 => self.sum / self.count as f64,
+            Metric::Average => 0.0,
+            Metric::Min => self.min.unwrap_or_default(),
+            Metric::Max => self.max.unwrap_or_default(),
+        }
+    }
+}
+
+type Extractor<V, T> = Box<dyn Fn(&V) -> T>;
+
+/// Configurable group-by and time-series aggregation over records of type `V`.
+pub struct Aggregator<V> {
+    metric: Metric,
+    group_by: Option<Extractor<V, Vec<String>>>,
+    value: Option<Extractor<V, f64>>,
+    time_series: Option<(Extractor<V, u64>, TimeBucket)>,
+}
+
+impl<V> Default for Aggregator<V> {
+    fn default() -> Self {
+        Self {
+            metric: Metric::Count,
+            group_by: None,
+            value: None,
+            time_series: None,
+        }
+    }
+}
+
+impl<V> Aggregator<V> {
+    /// Creates an aggregator that counts records.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Groups records by the keys `extract` returns. A record with several
+    /// keys, such as tags, counts towards each; one with none is skipped.
+    #[must_use]
+    pub fn group_by(mut self, extract: impl Fn(&V) -> Vec<String> + 'static) -> Self {
+        self.group_by = Some(Box::new(extract));
+        self
+    }
+
+    /// Applies `metric` to the numbers `extract` returns.
+    #[must_use]
+    pub fn metric(mut self, metric: Metric, extract: impl Fn(&V) -> f64 + 'static) -> Self {
+        self.metric = metric;
+        self.value = Some(Box::new(extract));
+        self
+    }
+
+    /// Sums the numbers `extract` returns.
+    #[must_use]
+    pub fn sum(self, extract: impl Fn(&V) -> f64 + 'static) -> Self {
+        self.metric(Metric::Sum, extract)
+    }
+
+    /// Averages the numbers `extract` returns.
+    #[must_use]
+    pub fn average(self, extract: impl Fn(&V) -> f64 + 'static) -> Self {
+        self.metric(Metric::Average, extract)
+    }
+
+    /// Also buckets records by the timestamp `extract` returns, in
+    /// nanoseconds since the epoch.
+    #[must_use]
+    pub fn time_series(
+        mut self,
+        extract: impl Fn(&V) -> u64 + 'static,
+        bucket: TimeBucket,
+    ) -> Self {
+        self.time_series = Some((Box::new(extract), bucket));
+        self
+    }
+
+    /// Aggregates `records`, given by reference or by value.
+    pub fn run<I>(&self, records: I) -> AggregateReport
+    where
+        I: IntoIterator,
+        I::Item: Borrow<V>,
+    {
+        let mut total = Accumulator::default();
+        let mut groups: BTreeMap<String, Accumulator> = BTreeMap::new();
+        let mut series: BTreeMap<u64, Accumulator> = BTreeMap::new();
+
+        for record in records {
+            let record = record.borrow();
+            let value = self.value.as_ref().map_or(1.0, |extract| extract(record));
+            total.add(value);
+
+            if let Some(group_by) = &self.group_by {
+                for key in group_by(record) {
+                    groups.entry(key).or_default().add(value);
+                }
+            }
+            if let Some((timestamp, bucket)) = &self.time_series {
+                let start = bucket.start_of(timestamp(record));
+                series.entry(start).or_default().add(value);
+            }
+        }
+
+        AggregateReport {
+            metric: self.metric,
+            total: total.count,
+            groups: groups
+                .into_iter()
+                .map(|(key, acc)| GroupStat {
+                    key,
+                    count: acc.count,
+                    value: acc.value(self.metric),
+                })
+                .collect(),
+            series: series
+                .into_iter()
+                .map(|(bucket_start, acc)| BucketStat {
+                    bucket_start,
+                    count: acc.count,
+                    value: acc.value(self.metric),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)] // Sums of small integers are exact
+mod tests {
+    use super::*;
+
+    struct Event {
+        tags: Vec<&'static str>,
+        duration: f64,
+        at: u64,
+    }
+
+    #[test]
+    fn test_multi_key_groups_and_hourly_series() {
+        let events = vec![
+            Event {
+                tags: vec!["a", "b"],
+                duration: 2.0,
+                at: 10,
+            },
+            Event {
+                tags: vec!["b"],
+                duration: 4.0,
+                at: NANOS_PER_HOUR + 5,
+            },
+            Event {
+                tags: vec![],
+                duration: 9.0,
+                at: NANOS_PER_HOUR + 6,
+            },
+        ];
+
+        let report = Aggregator::new()
+            .group_by(|event: &Event| event.tags.iter().map(ToString::to_string).collect())
+            .metric(Metric::Max, |event| event.duration)
+            .time_series(|event| event.at, TimeBucket::Hour)
+            .run(&events);
+
+        assert_eq!(report.total, 3);
+        let groups: Vec<_> = report
+            .groups
+            .iter()
+            .map(|g| (g.key.as_str(), g.count, g.value))
+            .collect();
+        assert_eq!(groups, [("a", 1, 2.0), ("b", 2, 4.0)]);
+        assert_eq!(report.series[1].bucket_start, NANOS_PER_HOUR);
+        assert_eq!(report.series[1].value, 9.0);
+    }
+
+    #[test]
+    fn test_parse_metric_and_bucket() {
+        assert_eq!("avg".parse::<Metric>().unwrap(), Metric::Average);
+        assert_eq!("week".parse::<TimeBucket>().unwrap(), TimeBucket::Week);
+        assert!("median".parse::<Metric>().is_err());
+        assert_eq!(
+            TimeBucket::Day.start_of(TimeBucket::Day.nanos() + 7),
+            TimeBucket::Day.nanos()
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! API keys for calling tools through the HTTP gateway.
+//!
+//! Browsers calling `POST /tools/<name>` have no IC identity, so each
+//! request carries an API key instead, either as `Authorization: Bearer
+//! <key>` or as `X-API-Key: <key>`. A key acts as the principal it was
+//! issued for: tools see that principal as their caller, and `#[tool(auth)]`
+//! checks its role.
+//!
+//! Only the SHA-256 hash of a key is stored; the key itself is returned once,
+//! by [`create_api_key`]. Keys are listed and revoked by their id, the first
+//! hex digits of the hash.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! use candid::Principal;
+//! use icarus_core::api_keys;
+//!
+//! let owner = Principal::from_slice(&[1]);
+//! let app = Principal::from_slice(&[2]);
+//! let created = api_keys::create_api_key("web app", app, owner).await.unwrap();
+//!
+//! assert_eq!(api_keys::authenticate(&created.key).unwrap(), app);
+//!
+//! api_keys::revoke_api_key(&created.info.id).unwrap();
+//! assert!(api_keys::authenticate(&created.key).is_err());
+//! # });
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::gateway::GatewayRequest;
+use crate::memory::{self, StableMemory, API_KEYS_MEMORY_ID};
+use crate::{IcarusError, Result};
+
+/// Prefix of every key, so leaked keys are easy to recognize.
+pub const KEY_PREFIX: &str = "icarus_";
+
+/// Random bytes in a key, shown as twice as many hex digits.
+const KEY_BYTES: usize = 24;
+
+/// Hex digits of the hash used as a key's id.
+const ID_LEN: usize = 12;
+
+/// A stored API key, without the key itself.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct ApiKey {
+    /// Id for listing and revoking the key.
+    pub id: String,
+    /// What the key is for.
+    pub label: String,
+    /// Principal that tools called with the key see as their caller.
+    pub principal: Principal,
+    /// Principal that created it.
+    pub created_by: Principal,
+    /// Creation time in nanoseconds since the epoch.
+    pub created_at: u64,
+    /// Time of the last tool call made with it, if any.
+    pub last_used_at: Option<u64>,
+}
+
+/// A newly created key, the only time the key is available.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct CreatedApiKey {
+    /// The key to send with requests.
+    pub key: String,
+    /// What is stored about it.
+    pub info: ApiKey,
+}
+
+impl Storable for ApiKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap_or_default())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap_or_else(|e| unreachable!("corrupt API key: {e}"))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).unwrap_or_default()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+thread_local! {
+    /// Keys by the hex SHA-256 hash of the key.
+    static KEYS: RefCell<StableBTreeMap<String, ApiKey, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(API_KEYS_MEMORY_ID))
+    );
+}
+
+fn hash(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Creates a key with which HTTP requests act as `principal`.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if `principal` is anonymous, and
+/// `IcarusError::ExternalServiceError` if no entropy could be obtained.
+pub async fn create_api_key(
+    label: &str,
+    principal: Principal,
+    created_by: Principal,
+) -> Result<CreatedApiKey> {
+    if crate::auth::is_anonymous(&principal) {
+        return Err(IcarusError::ConfigurationError(
+            "API keys cannot act as the anonymous principal".to_string(),
+        ));
+    }
+
+    let random = crate::rand::entropy().await?;
+    let secret: String = random[..KEY_BYTES]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    let key = format!("{KEY_PREFIX}{secret}");
+    let hash = hash(&key);
+
+    let info = ApiKey {
+        id: hash[..ID_LEN].to_string(),
+        label: label.to_string(),
+        principal,
+        created_by,
+        created_at: crate::time::now_nanos(),
+        last_used_at: None,
+    };
+    KEYS.with(|keys| keys.borrow_mut().insert(hash, info.clone()));
+    Ok(CreatedApiKey { key, info })
+}
+
+/// Returns the principal `key` acts as, and records that it was used.
+///
+/// # Errors
+///
+/// Returns `IcarusError::AccessDenied` if the key is unknown or revoked.
+pub fn authenticate(key: &str) -> Result<Principal> {
+    let hash = hash(key.trim());
+    KEYS.with(|keys| {
+        let mut keys = keys.borrow_mut();
+        let mut info = keys
+            .get(&hash)
+            .ok_or_else(|| IcarusError::AccessDenied("Invalid API key".to_string()))?;
+        info.last_used_at = Some(crate::time::now_nanos());
+        let principal = info.principal;
+        keys.insert(hash, info);
+        Ok(principal)
+    })
+}
+
+/// Revokes the key with `id`. Requests using it fail from then on.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if there is no such key.
+pub fn revoke_api_key(id: &str) -> Result<ApiKey> {
+    KEYS.with(|keys| {
+        let mut keys = keys.borrow_mut();
+        let hash = keys
+            .iter()
+            .find(|entry| entry.value().id == id)
+            .map(|entry| entry.key().clone())
+            .ok_or_else(|| IcarusError::ConfigurationError(format!("Unknown API key: {id}")))?;
+        keys.remove(&hash)
+            .ok_or_else(|| IcarusError::ConfigurationError(format!("Unknown API key: {id}")))
+    })
+}
+
+/// Returns all keys, newest first.
+#[must_use]
+pub fn list_api_keys() -> Vec<ApiKey> {
+    let mut keys: Vec<ApiKey> =
+        KEYS.with(|keys| keys.borrow().iter().map(|entry| entry.value()).collect());
+    keys.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    keys
+}
+
+/// Returns the key a request carries in `Authorization: Bearer` or
+/// `X-API-Key`.
+#[must_use]
+pub fn from_request(request: &GatewayRequest) -> Option<&str> {
+    let bearer = request.header("Authorization").and_then(|value| {
+        let (scheme, token) = value.trim().split_once(' ')?;
+        scheme
+            .eq_ignore_ascii_case("Bearer")
+            .then_some(token.trim())
+    });
+    bearer
+        .or_else(|| request.header("X-API-Key").map(str::trim))
+        .filter(|key| !key.is_empty())
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_keys_authenticate_until_revoked() {
+        let owner = Principal::from_slice(&[71]);
+        let app = Principal::from_slice(&[72]);
+        let created = create_api_key("dashboard", app, owner).await.unwrap();
+        assert!(created.key.starts_with(KEY_PREFIX));
+        assert_eq!(created.key.len(), KEY_PREFIX.len() + KEY_BYTES * 2);
+        assert!(create_api_key("anon", Principal::anonymous(), owner)
+            .await
+            .is_err());
+
+        assert_eq!(authenticate(&created.key).unwrap(), app);
+        assert!(authenticate("icarus_guess").is_err());
+        let listed = list_api_keys()
+            .into_iter()
+            .find(|key| key.id == created.info.id)
+            .unwrap();
+        assert!(listed.last_used_at.is_some());
+
+        revoke_api_key(&created.info.id).unwrap();
+        assert!(authenticate(&created.key).is_err());
+        assert!(revoke_api_key(&created.info.id).is_err());
+    }
+
+    #[test]
+    fn test_key_is_read_from_either_header() {
+        let bearer = GatewayRequest::get("/tools").with_header("authorization", "Bearer abc");
+        assert_eq!(from_request(&bearer), Some("abc"));
+        let header = GatewayRequest::get("/tools").with_header("X-API-Key", " def ");
+        assert_eq!(from_request(&header), Some("def"));
+        let basic = GatewayRequest::get("/tools").with_header("Authorization", "Basic abc");
+        assert_eq!(from_request(&basic), None);
+        assert_eq!(from_request(&GatewayRequest::get("/tools")), None);
+    }
+}
@@ -0,0 +1,791 @@
+//! Compaction of stable logs into archived segments.
+//!
+//! A [`StorageLog`] registered with [`register_log`] is archived once it
+//! holds more than its policy's `max_entries`: its oldest entries are cut into
+//! segments of up to `segment_size`, each segment's entries are encoded and
+//! compressed, and the segment is handed to the [`ArchiveTarget`]. Only after
+//! the target accepted a segment are its entries removed from the log, so a
+//! failed call loses nothing and is retried on the next run. This continues
+//! until `keep_entries` remain.
+//!
+//! The target is either another canister, called with the segment at its
+//! `archive_segment` method, or this canister's blob store, where each segment
+//! becomes a blob named `archive/<log>/<first>-<last>` that can be downloaded
+//! from `/blobs/<id>`. Blob archives move history out of the log's memory but
+//! not out of the canister.
+//!
+//! An archive canister is any canister built with `mcp!{ archive_store = true }`,
+//! which keeps received segments in stable memory through [`store_segment`]
+//! and lists them for later retrieval. It only accepts segments from canisters
+//! added with [`add_source`]. A segment whose reply was lost is sent again;
+//! the archive recognises the copy and returns the id of the first one.
+//!
+//! Logs and the target are kept on the heap, so register them in both `init`
+//! and `post_upgrade`, where [`start_archiving`] also belongs.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::archive::{self, ArchivePolicy, ArchiveTarget};
+//! use icarus_core::collections::StorageLog;
+//! use icarus_core::memory::FIRST_USER_MEMORY_ID;
+//!
+//! thread_local! {
+//!     static ACTIVITY: StorageLog<String> = StorageLog::init(FIRST_USER_MEMORY_ID);
+//! }
+//!
+//! archive::set_target(ArchiveTarget::Blobs);
+//! archive::register_log("activity", &ACTIVITY, ArchivePolicy::new(4).with_segment_size(2));
+//! ACTIVITY.with(|log| {
+//!     for n in 0..5 {
+//!         log.append(format!("event {n}"));
+//!     }
+//! });
+//!
+//! // The archive timer writes and completes every due segment
+//! while let Some(segment) = archive::next_segment("activity") {
+//!     let outcome = archive::write_blob(&segment).map(|id| format!("blob {id}"));
+//!     archive::complete(&segment, outcome.map_err(|e| e.to_string()));
+//! }
+//!
+//! assert_eq!(ACTIVITY.with(|log| log.len()), 2);
+//! assert_eq!(archive::archive_status().logs[0].archived_entries, 3);
+//! ```
+
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::thread::LocalKey;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+
+use crate::collections::StorageLog;
+use crate::memory::{self, StableMemory, ARCHIVE_SEGMENTS_MEMORY_ID, ARCHIVE_SOURCES_MEMORY_ID};
+use crate::{compression, IcarusError, Result};
+
+/// Method of an archive canister that receives segments.
+pub const ARCHIVE_SEGMENT_METHOD: &str = "archive_segment";
+
+/// Default number of entries per segment.
+pub const DEFAULT_SEGMENT_SIZE: u64 = 1_000;
+
+/// Largest encoded segment, kept below the inter-canister message limit.
+pub const MAX_SEGMENT_BYTES: usize = 1_800_000;
+
+/// Where archived segments are sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub enum ArchiveTarget {
+    /// Another canister's `archive_segment` method.
+    Canister(Principal),
+    /// This canister's blob store.
+    Blobs,
+}
+
+/// When a log is archived and how much of it stays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchivePolicy {
+    max_entries: u64,
+    keep_entries: u64,
+    segment_size: u64,
+}
+
+impl ArchivePolicy {
+    /// Archives a log holding more than `max_entries` down to half of that.
+    #[must_use]
+    pub const fn new(max_entries: u64) -> Self {
+        Self {
+            max_entries,
+            keep_entries: max_entries / 2,
+            segment_size: DEFAULT_SEGMENT_SIZE,
+        }
+    }
+
+    /// Keeps the newest `keep_entries` entries, at most `max_entries`.
+    #[must_use]
+    pub const fn with_keep_entries(mut self, keep_entries: u64) -> Self {
+        self.keep_entries = if keep_entries < self.max_entries {
+            keep_entries
+        } else {
+            self.max_entries
+        };
+        self
+    }
+
+    /// Puts up to `segment_size` entries, at least one, in each segment.
+    #[must_use]
+    pub const fn with_segment_size(mut self, segment_size: u64) -> Self {
+        self.segment_size = if segment_size == 0 { 1 } else { segment_size };
+        self
+    }
+
+    /// Returns the size above which the log is archived.
+    #[must_use]
+    #[inline]
+    pub const fn max_entries(&self) -> u64 {
+        self.max_entries
+    }
+
+    /// Returns the number of entries left after archiving.
+    #[must_use]
+    #[inline]
+    pub const fn keep_entries(&self) -> u64 {
+        self.keep_entries
+    }
+
+    /// Returns the largest number of entries per segment.
+    #[must_use]
+    #[inline]
+    pub const fn segment_size(&self) -> u64 {
+        self.segment_size
+    }
+}
+
+/// A compressed run of consecutive log entries.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct ArchiveSegment {
+    /// Name the log was registered under.
+    pub log: String,
+    /// Sequence number of the first entry.
+    pub first_seq: u64,
+    /// Sequence number of the last entry.
+    pub last_seq: u64,
+    /// Number of entries.
+    pub count: u64,
+    /// Creation time in nanoseconds since the epoch.
+    pub created_at: u64,
+    /// Entries as compressed Candid `vec record { nat64; blob }`.
+    pub data: Vec<u8>,
+}
+
+impl ArchiveSegment {
+    /// Returns the sequence numbers and stored bytes of the entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not a valid segment encoding.
+    pub fn raw_entries(&self) -> Result<Vec<(u64, Vec<u8>)>> {
+        let bytes = compression::decompress(&self.data)?;
+        Decode!(&bytes, Vec<(u64, Vec<u8>)>).map_err(|e| IcarusError::CandidError(e.to_string()))
+    }
+
+    /// Returns the entries decoded as the log's element type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not a valid segment encoding.
+    pub fn entries<T: Storable>(&self) -> Result<Vec<(u64, T)>> {
+        Ok(self
+            .raw_entries()?
+            .into_iter()
+            .map(|(seq, bytes)| (seq, T::from_bytes(Cow::Owned(bytes))))
+            .collect())
+    }
+
+    /// Reads a segment downloaded from an archive blob.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::CandidError` if the bytes are not a segment.
+    pub fn from_blob(bytes: &[u8]) -> Result<Self> {
+        Decode!(bytes, Self).map_err(|e| IcarusError::CandidError(e.to_string()))
+    }
+}
+
+/// Archiving results of one log.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct LogArchiveStatus {
+    /// Name the log was registered under.
+    pub name: String,
+    /// Size above which the log is archived.
+    pub max_entries: u64,
+    /// Entries left after archiving.
+    pub keep_entries: u64,
+    /// Entries currently in the log.
+    pub retained: u64,
+    /// Segments archived since the canister started.
+    pub archived_segments: u64,
+    /// Entries archived since the canister started.
+    pub archived_entries: u64,
+    /// Sequence number of the newest archived entry.
+    pub last_archived_seq: Option<u64>,
+    /// Where the newest segment went, e.g. `blob 3` or `segment 12`.
+    pub last_location: Option<String>,
+    /// Error of the last failed segment, cleared by the next success.
+    pub last_error: Option<String>,
+}
+
+/// Archiving status returned by [`archive_status`].
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct ArchiveStatus {
+    /// Where segments are sent.
+    pub target: ArchiveTarget,
+    /// Time of the last run in nanoseconds since the epoch.
+    pub last_run: Option<u64>,
+    /// Number of runs since the canister started.
+    pub runs: u64,
+    /// One entry per registered log, in registration order.
+    pub logs: Vec<LogArchiveStatus>,
+}
+
+/// A segment kept by an archive canister.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct StoredSegment {
+    /// Id assigned by the archive.
+    pub id: u64,
+    /// Canister the segment came from.
+    pub source: Principal,
+    /// Arrival time in nanoseconds since the epoch.
+    pub received_at: u64,
+    /// The segment as sent.
+    pub segment: ArchiveSegment,
+}
+
+/// A stored segment without its data.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct SegmentInfo {
+    /// Id assigned by the archive.
+    pub id: u64,
+    /// Canister the segment came from.
+    pub source: Principal,
+    /// Name of the archived log.
+    pub log: String,
+    /// Sequence number of the first entry.
+    pub first_seq: u64,
+    /// Sequence number of the last entry.
+    pub last_seq: u64,
+    /// Number of entries.
+    pub count: u64,
+    /// Size of the compressed data in bytes.
+    pub size: u64,
+    /// Arrival time in nanoseconds since the epoch.
+    pub received_at: u64,
+}
+
+impl Storable for StoredSegment {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap_or_default())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap_or_else(|e| unreachable!("corrupt archive segment: {e}"))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).unwrap_or_default()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Type-erased access to a registered log.
+trait ArchiveSource {
+    fn len(&self) -> u64;
+    fn oldest(&self, limit: u64) -> Vec<(u64, Vec<u8>)>;
+    fn truncate_before(&self, seq: u64);
+}
+
+impl<T: Storable + 'static> ArchiveSource for &'static LocalKey<StorageLog<T>> {
+    fn len(&self) -> u64 {
+        self.with(StorageLog::len)
+    }
+
+    fn oldest(&self, limit: u64) -> Vec<(u64, Vec<u8>)> {
+        self.with(|log| {
+            log.oldest(limit)
+                .into_iter()
+                .map(|(seq, entry)| (seq, entry.into_bytes()))
+                .collect()
+        })
+    }
+
+    fn truncate_before(&self, seq: u64) {
+        self.with(|log| log.truncate_before(seq));
+    }
+}
+
+struct Entry {
+    name: &'static str,
+    policy: ArchivePolicy,
+    source: Box<dyn ArchiveSource>,
+    archived_segments: u64,
+    archived_entries: u64,
+    last_archived_seq: Option<u64>,
+    last_location: Option<String>,
+    last_error: Option<String>,
+    /// Over `max_entries` and not yet back to `keep_entries`
+    draining: bool,
+}
+
+struct State {
+    target: ArchiveTarget,
+    entries: Vec<Entry>,
+    last_run: Option<u64>,
+    runs: u64,
+}
+
+thread_local! {
+    static STATE: RefCell<State> = const {
+        RefCell::new(State {
+            target: ArchiveTarget::Blobs,
+            entries: Vec::new(),
+            last_run: None,
+            runs: 0,
+        })
+    };
+
+    static ARCHIVING: Cell<bool> = const { Cell::new(false) };
+
+    static SEGMENTS: RefCell<StableBTreeMap<u64, StoredSegment, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(ARCHIVE_SEGMENTS_MEMORY_ID))
+    );
+
+    static SOURCES: RefCell<StableBTreeMap<Principal, u64, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(ARCHIVE_SOURCES_MEMORY_ID))
+    );
+}
+
+/// Sets where segments are sent. The default is [`ArchiveTarget::Blobs`].
+pub fn set_target(target: ArchiveTarget) {
+    STATE.with(|state| state.borrow_mut().target = target);
+}
+
+/// Returns where segments are sent.
+#[must_use]
+pub fn target() -> ArchiveTarget {
+    STATE.with(|state| state.borrow().target)
+}
+
+/// Archives `log` under `policy`, replacing any log registered as `name`.
+pub fn register_log<T: Storable + 'static>(
+    name: &'static str,
+    log: &'static LocalKey<StorageLog<T>>,
+    policy: ArchivePolicy,
+) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if let Some(entry) = state.entries.iter_mut().find(|entry| entry.name == name) {
+            entry.policy = policy;
+            entry.source = Box::new(log);
+        } else {
+            state.entries.push(Entry {
+                name,
+                policy,
+                source: Box::new(log),
+                archived_segments: 0,
+                archived_entries: 0,
+                last_archived_seq: None,
+                last_location: None,
+                last_error: None,
+                draining: false,
+            });
+        }
+    });
+}
+
+/// Stops archiving a log, returning whether it was registered.
+pub fn unregister_log(name: &str) -> bool {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let before = state.entries.len();
+        state.entries.retain(|entry| entry.name != name);
+        state.entries.len() != before
+    })
+}
+
+/// Builds the next segment of log `name`, or returns `None` if the log is
+/// not registered or has nothing to archive.
+///
+/// A log starts being archived when it grows over `max_entries` and is
+/// archived segment by segment until `keep_entries` remain.
+///
+/// The entries stay in the log until [`complete`] reports the segment stored.
+/// Segments are cut short to stay below [`MAX_SEGMENT_BYTES`].
+#[must_use]
+pub fn next_segment(name: &str) -> Option<ArchiveSegment> {
+    let entries = STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let entry = state.entries.iter_mut().find(|entry| entry.name == name)?;
+        let len = entry.source.len();
+        let policy = entry.policy;
+        // Once over max_entries, the log is archived down to keep_entries
+        if len > policy.max_entries {
+            entry.draining = true;
+        } else if len <= policy.keep_entries {
+            entry.draining = false;
+        }
+        if !entry.draining {
+            return None;
+        }
+        let count = (len - policy.keep_entries).min(policy.segment_size);
+        Some(entry.source.oldest(count))
+    })?;
+
+    let mut size = 0;
+    let entries: Vec<(u64, Vec<u8>)> = entries
+        .into_iter()
+        .enumerate()
+        .take_while(|(index, (_, bytes))| {
+            size += bytes.len() + 16;
+            *index == 0 || size <= MAX_SEGMENT_BYTES
+        })
+        .map(|(_, entry)| entry)
+        .collect();
+    let (first_seq, last_seq) = (entries.first()?.0, entries.last()?.0);
+    let encoded = Encode!(&entries).ok()?;
+
+    Some(ArchiveSegment {
+        log: name.to_string(),
+        first_seq,
+        last_seq,
+        count: entries.len() as u64,
+        created_at: crate::time::now_nanos(),
+        data: compression::compress("archive.segment", encoded, compression::DEFAULT_THRESHOLD),
+    })
+}
+
+/// Records the outcome of sending `segment`, given as where it was stored or
+/// why it was not. Stored entries are removed from their log.
+pub fn complete(segment: &ArchiveSegment, outcome: std::result::Result<String, String>) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let Some(entry) = state.entries.iter_mut().find(|e| e.name == segment.log) else {
+            return;
+        };
+        match outcome {
+            Ok(location) => {
+                entry.source.truncate_before(segment.last_seq + 1);
+                entry.archived_segments += 1;
+                entry.archived_entries += segment.count;
+                entry.last_archived_seq = Some(segment.last_seq);
+                entry.last_location = Some(location);
+                entry.last_error = None;
+            }
+            Err(error) => {
+                crate::logging::warn(format_args!(
+                    "archiving {} entries {}-{} failed: {error}",
+                    segment.log, segment.first_seq, segment.last_seq
+                ));
+                entry.last_error = Some(error);
+            }
+        }
+    });
+}
+
+/// Stores `segment` in this canister's blob store and returns the blob id.
+///
+/// # Errors
+///
+/// Returns an error if the blob store rejects the upload.
+pub fn write_blob(segment: &ArchiveSegment) -> Result<u64> {
+    let bytes = Encode!(segment).map_err(|e| IcarusError::CandidError(e.to_string()))?;
+    let name = format!(
+        "archive/{}/{}-{}",
+        segment.log, segment.first_seq, segment.last_seq
+    );
+    let id = crate::blobs::upload_begin(&name, bytes.len() as u64, "application/candid")?;
+    for (index, chunk) in bytes.chunks(crate::blobs::MAX_CHUNK_SIZE).enumerate() {
+        let index = u32::try_from(index).map_err(|e| IcarusError::InternalError(e.to_string()))?;
+        crate::blobs::upload_chunk(id, index, chunk)?;
+    }
+    crate::blobs::upload_commit(id)?;
+    Ok(id)
+}
+
+/// Sends `segment` to the archive canister and returns its id there.
+async fn send_segment(canister: Principal, segment: &ArchiveSegment) -> Result<u64> {
+    let external = |message: String| IcarusError::ExternalServiceError {
+        service: canister.to_string(),
+        message,
+    };
+
+    ic_cdk::call::Call::bounded_wait(canister, ARCHIVE_SEGMENT_METHOD)
+        .with_arg(segment)
+        .await
+        .map_err(|e| external(e.to_string()))?
+        .candid::<std::result::Result<u64, String>>()
+        .map_err(|e| IcarusError::CandidError(e.to_string()))?
+        .map_err(external)
+}
+
+/// Archives every registered log that is over its `max_entries` and returns
+/// the updated status.
+///
+/// A log whose segment fails is left for the next run while the other logs
+/// carry on. Returns the status without archiving anything while an earlier
+/// run is still in flight, so a segment is never sent twice at once.
+pub async fn archive_now() -> ArchiveStatus {
+    if ARCHIVING.with(|archiving| archiving.replace(true)) {
+        return archive_status();
+    }
+    let names: Vec<&'static str> = STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.last_run = Some(crate::time::now_nanos());
+        state.runs += 1;
+        state.entries.iter().map(|entry| entry.name).collect()
+    });
+
+    for name in names {
+        while let Some(segment) = next_segment(name) {
+            let outcome = match target() {
+                ArchiveTarget::Blobs => write_blob(&segment).map(|id| format!("blob {id}")),
+                ArchiveTarget::Canister(canister) => send_segment(canister, &segment)
+                    .await
+                    .map(|id| format!("segment {id} on {canister}")),
+            };
+            let failed = outcome.is_err();
+            complete(&segment, outcome.map_err(|e| e.to_string()));
+            if failed {
+                break;
+            }
+        }
+    }
+    ARCHIVING.with(|archiving| archiving.set(false));
+    archive_status()
+}
+
+/// Returns the archiving target and the results for each log.
+#[must_use]
+pub fn archive_status() -> ArchiveStatus {
+    STATE.with(|state| {
+        let state = state.borrow();
+        ArchiveStatus {
+            target: state.target,
+            last_run: state.last_run,
+            runs: state.runs,
+            logs: state
+                .entries
+                .iter()
+                .map(|entry| LogArchiveStatus {
+                    name: entry.name.to_string(),
+                    max_entries: entry.policy.max_entries,
+                    keep_entries: entry.policy.keep_entries,
+                    retained: entry.source.len(),
+                    archived_segments: entry.archived_segments,
+                    archived_entries: entry.archived_entries,
+                    last_archived_seq: entry.last_archived_seq,
+                    last_location: entry.last_location.clone(),
+                    last_error: entry.last_error.clone(),
+                })
+                .collect(),
+        }
+    })
+}
+
+/// Starts a timer that calls [`archive_now`] every `interval`.
+///
+/// Timers do not survive upgrades, so call this from both `init` and
+/// `post_upgrade`.
+#[cfg(feature = "ic-canister")]
+pub fn start_archiving(interval: std::time::Duration) -> ic_cdk_timers::TimerId {
+    ic_cdk_timers::set_timer_interval(interval, || {
+        ic_cdk::futures::spawn(async {
+            archive_now().await;
+        });
+    })
+}
+
+/// Lets canister `source` store segments in this archive.
+pub fn add_source(source: Principal) {
+    SOURCES.with(|sources| {
+        sources
+            .borrow_mut()
+            .insert(source, crate::time::now_nanos());
+    });
+}
+
+/// Stops accepting segments from `source`, returning whether it was allowed.
+pub fn remove_source(source: Principal) -> bool {
+    SOURCES.with(|sources| sources.borrow_mut().remove(&source).is_some())
+}
+
+/// Returns true if `source` may store segments in this archive.
+#[must_use]
+pub fn is_source(source: &Principal) -> bool {
+    SOURCES.with(|sources| sources.borrow().contains_key(source))
+}
+
+/// Returns the canisters that may store segments in this archive.
+#[must_use]
+pub fn list_sources() -> Vec<Principal> {
+    SOURCES.with(|sources| sources.borrow().iter().map(|entry| *entry.key()).collect())
+}
+
+/// Keeps a segment sent by `source` and returns its id.
+///
+/// A segment already stored for the same source, log and first entry is not
+/// stored again; its id is returned instead.
+///
+/// # Errors
+///
+/// Returns `IcarusError::AccessDenied` if `source` was not added with
+/// [`add_source`], or an error if the segment's data cannot be decoded.
+pub fn store_segment(source: Principal, segment: ArchiveSegment) -> Result<u64> {
+    if !is_source(&source) {
+        return Err(IcarusError::AccessDenied(format!(
+            "{source} may not store archive segments"
+        )));
+    }
+    segment.raw_entries()?;
+
+    SEGMENTS.with(|segments| {
+        let mut segments = segments.borrow_mut();
+        let existing = segments.iter().map(|entry| entry.value()).find(|stored| {
+            stored.source == source
+                && stored.segment.log == segment.log
+                && stored.segment.first_seq == segment.first_seq
+        });
+        if let Some(stored) = existing {
+            return Ok(stored.id);
+        }
+
+        let id = segments.last_key_value().map_or(0, |(id, _)| id + 1);
+        segments.insert(
+            id,
+            StoredSegment {
+                id,
+                source,
+                received_at: crate::time::now_nanos(),
+                segment,
+            },
+        );
+        Ok(id)
+    })
+}
+
+/// Lists stored segments, optionally only those of one log, oldest first.
+#[must_use]
+pub fn list_segments(log: Option<&str>) -> Vec<SegmentInfo> {
+    SEGMENTS.with(|segments| {
+        segments
+            .borrow()
+            .iter()
+            .map(|entry| entry.value())
+            .filter(|stored| log.map_or(true, |log| stored.segment.log == log))
+            .map(|stored| SegmentInfo {
+                id: stored.id,
+                source: stored.source,
+                log: stored.segment.log,
+                first_seq: stored.segment.first_seq,
+                last_seq: stored.segment.last_seq,
+                count: stored.segment.count,
+                size: stored.segment.data.len() as u64,
+                received_at: stored.received_at,
+            })
+            .collect()
+    })
+}
+
+/// Returns a stored segment with its data.
+#[must_use]
+pub fn get_segment(id: u64) -> Option<StoredSegment> {
+    SEGMENTS.with(|segments| segments.borrow().get(&id))
+}
+
+/// Deletes a stored segment, returning whether it existed.
+pub fn delete_segment(id: u64) -> bool {
+    SEGMENTS.with(|segments| segments.borrow_mut().remove(&id).is_some())
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+    use crate::memory::FIRST_USER_MEMORY_ID;
+
+    thread_local! {
+        static NOTES: StorageLog<String> = StorageLog::init(FIRST_USER_MEMORY_ID);
+    }
+
+    #[test]
+    fn test_policy_defaults() {
+        let policy = ArchivePolicy::new(100);
+        assert_eq!(policy.keep_entries(), 50);
+        assert_eq!(policy.segment_size(), DEFAULT_SEGMENT_SIZE);
+        assert_eq!(policy.with_keep_entries(500).keep_entries(), 100);
+        assert_eq!(policy.with_segment_size(0).segment_size(), 1);
+    }
+
+    #[test]
+    fn test_segments_leave_the_log_only_once_stored() {
+        register_log(
+            "notes",
+            &NOTES,
+            ArchivePolicy::new(5)
+                .with_keep_entries(1)
+                .with_segment_size(3),
+        );
+        NOTES.with(|log| {
+            for n in 0..6 {
+                log.append(format!("note {n}"));
+            }
+        });
+
+        let segment = next_segment("notes").unwrap();
+        assert_eq!(
+            (segment.first_seq, segment.last_seq, segment.count),
+            (0, 2, 3)
+        );
+        assert_eq!(
+            segment.entries::<String>().unwrap()[2],
+            (2, "note 2".to_string())
+        );
+
+        complete(&segment, Err("archive canister is stopped".to_string()));
+        assert_eq!(NOTES.with(StorageLog::len), 6);
+        assert_eq!(next_segment("notes").unwrap().first_seq, 0);
+
+        complete(&segment, Ok("segment 0".to_string()));
+        let segment = next_segment("notes").unwrap();
+        assert_eq!((segment.first_seq, segment.last_seq), (3, 4));
+        complete(&segment, Ok("segment 1".to_string()));
+        assert!(next_segment("notes").is_none());
+
+        let status = archive_status();
+        let notes = status.logs.iter().find(|log| log.name == "notes").unwrap();
+        assert_eq!(notes.retained, 1);
+        assert_eq!(notes.archived_entries, 5);
+        assert_eq!(notes.last_archived_seq, Some(4));
+        assert_eq!(notes.last_error, None);
+        assert!(next_segment("unknown").is_none());
+    }
+
+    #[test]
+    fn test_archive_accepts_known_sources_once() {
+        let source = Principal::from_slice(&[7]);
+        let segment = ArchiveSegment {
+            log: "audit".to_string(),
+            first_seq: 10,
+            last_seq: 10,
+            count: 1,
+            created_at: 0,
+            data: compression::compress(
+                "archive.segment",
+                Encode!(&vec![(10_u64, b"entry".to_vec())]).unwrap(),
+                compression::DEFAULT_THRESHOLD,
+            ),
+        };
+
+        assert!(matches!(
+            store_segment(source, segment.clone()),
+            Err(IcarusError::AccessDenied(_))
+        ));
+
+        add_source(source);
+        let id = store_segment(source, segment.clone()).unwrap();
+        assert_eq!(store_segment(source, segment.clone()).unwrap(), id);
+        assert_eq!(list_segments(Some("audit")).len(), 1);
+        assert!(list_segments(Some("other")).is_empty());
+        assert_eq!(
+            get_segment(id).unwrap().segment.raw_entries().unwrap(),
+            vec![(10, b"entry".to_vec())]
+        );
+
+        let corrupt = ArchiveSegment {
+            data: vec![0, 1, 2],
+            ..segment
+        };
+        assert!(store_segment(source, corrupt).is_err());
+        assert!(remove_source(source));
+        assert!(!is_source(&source));
+    }
+}
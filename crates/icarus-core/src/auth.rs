@@ -3,21 +3,24 @@
 //! This module provides a whitelist-based RBAC (Role-Based Access Control) system
 //! with three tiers: public (no auth), user, and admin. All data is stored in
 //! stable memory to survive canister upgrades.
-
-use candid::Principal;
-use ic_stable_structures::{
-    memory_manager::{MemoryId, MemoryManager, VirtualMemory},
-    storable::Bound,
-    DefaultMemoryImpl, StableBTreeMap, Storable,
-};
+//!
+//! The owner can also open a guest tier with [`set_guest_policy`]: tools with
+//! `auth = "guest"` then accept callers that are not on the whitelist,
+//! including the anonymous principal that unauthenticated MCP bridges call
+//! as. With guest access off, which is the default, they require user access.
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
 use std::borrow::Cow;
 use std::cell::RefCell;
 
-/// Type alias for virtual memory
-type Memory = VirtualMemory<DefaultMemoryImpl>;
+use crate::memory::{
+    self, StableMemory, AUTH_ADMINS_MEMORY_ID, AUTH_GUEST_POLICY_MEMORY_ID, AUTH_USERS_MEMORY_ID,
+};
 
 /// Type alias for principal set stored in stable memory
-type PrincipalSet = RefCell<StableBTreeMap<Principal, Unit, Memory>>;
+type PrincipalSet = RefCell<StableBTreeMap<Principal, Unit, StableMemory>>;
 
 /// Empty value type for set-like behavior in `BTreeMap`
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -42,23 +45,50 @@ impl Storable for Unit {
     };
 }
 
+const GUEST_POLICY_KEY: u8 = 0;
+
+/// Whether callers that are not on the whitelist get guest access.
+#[derive(Debug, Clone, Default, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct GuestPolicy {
+    /// Whether `auth = "guest"` tools accept any caller.
+    pub enabled: bool,
+    /// When the policy last changed, in nanoseconds since the epoch.
+    pub since: u64,
+    /// Principal that last changed the policy.
+    pub changed_by: Option<Principal>,
+}
+
+impl Storable for GuestPolicy {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap_or_default())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap_or_else(|e| unreachable!("corrupt guest policy: {e}"))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).unwrap_or_default()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
 // Stable storage for admin and user principals
 thread_local! {
-    static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
-        RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
-
     /// Set of admin principals (Memory ID 0)
     static ADMINS: PrincipalSet = RefCell::new(
-        StableBTreeMap::init(
-            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(0)))
-        )
+        StableBTreeMap::init(memory::get_memory(AUTH_ADMINS_MEMORY_ID))
     );
 
     /// Set of user principals (Memory ID 1)
     static USERS: PrincipalSet = RefCell::new(
-        StableBTreeMap::init(
-            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(1)))
-        )
+        StableBTreeMap::init(memory::get_memory(AUTH_USERS_MEMORY_ID))
+    );
+
+    /// Guest access switch
+    static GUEST_POLICY: RefCell<StableBTreeMap<u8, GuestPolicy, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(AUTH_GUEST_POLICY_MEMORY_ID))
     );
 }
 
@@ -141,6 +171,56 @@ pub fn get_all_users() -> Vec<Principal> {
     })
 }
 
+/// Exports the admin whitelist for snapshots
+pub(crate) fn export_admins() -> crate::Result<Vec<u8>> {
+    encode_principals(&get_all_admins())
+}
+
+/// Exports the user whitelist for snapshots
+pub(crate) fn export_users() -> crate::Result<Vec<u8>> {
+    encode_principals(&get_all_users())
+}
+
+/// Replaces the admin whitelist from a snapshot
+pub(crate) fn restore_admins(bytes: &[u8]) -> crate::Result<()> {
+    let principals = decode_principals(bytes)?;
+    ADMINS.with(|admins| replace_all(&mut admins.borrow_mut(), principals));
+    Ok(())
+}
+
+/// Replaces the user whitelist from a snapshot
+pub(crate) fn restore_users(bytes: &[u8]) -> crate::Result<()> {
+    let principals = decode_principals(bytes)?;
+    USERS.with(|users| replace_all(&mut users.borrow_mut(), principals));
+    Ok(())
+}
+
+/// Checks that a snapshot of a whitelist decodes
+pub(crate) fn validate_principals(bytes: &[u8]) -> crate::Result<()> {
+    decode_principals(bytes).map(drop)
+}
+
+fn encode_principals(principals: &[Principal]) -> crate::Result<Vec<u8>> {
+    candid::encode_one(principals).map_err(|e| crate::IcarusError::CandidError(e.to_string()))
+}
+
+fn decode_principals(bytes: &[u8]) -> crate::Result<Vec<Principal>> {
+    candid::decode_one(bytes).map_err(|e| crate::IcarusError::CandidError(e.to_string()))
+}
+
+fn replace_all(
+    set: &mut StableBTreeMap<Principal, Unit, StableMemory>,
+    principals: Vec<Principal>,
+) {
+    let existing: Vec<Principal> = set.iter().map(|entry| *entry.key()).collect();
+    for principal in &existing {
+        set.remove(principal);
+    }
+    for principal in principals {
+        set.insert(principal, Unit);
+    }
+}
+
 /// Check if a principal has user-level access (user OR admin)
 #[inline]
 #[must_use]
@@ -155,6 +235,34 @@ pub fn has_admin_access(principal: &Principal) -> bool {
     is_admin(principal)
 }
 
+/// Turns guest access on or off
+pub fn set_guest_policy(enabled: bool, changed_by: Principal) {
+    let policy = GuestPolicy {
+        enabled,
+        since: crate::time::now_nanos(),
+        changed_by: Some(changed_by),
+    };
+    GUEST_POLICY.with(|state| state.borrow_mut().insert(GUEST_POLICY_KEY, policy));
+}
+
+/// Replaces the guest policy, keeping when and by whom it was last changed
+pub(crate) fn restore_guest_policy(policy: GuestPolicy) {
+    GUEST_POLICY.with(|state| state.borrow_mut().insert(GUEST_POLICY_KEY, policy));
+}
+
+/// Get the guest policy, disabled if it was never set
+#[must_use]
+pub fn guest_policy() -> GuestPolicy {
+    GUEST_POLICY.with(|state| state.borrow().get(&GUEST_POLICY_KEY).unwrap_or_default())
+}
+
+/// Check if a principal may call guest tools (anyone while guest access is
+/// on, otherwise users and admins)
+#[must_use]
+pub fn has_guest_access(principal: &Principal) -> bool {
+    guest_policy().enabled || has_user_access(principal)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -235,6 +343,26 @@ mod tests {
         assert!(!has_admin_access(&user)); // Users don't have admin access
     }
 
+    #[test]
+    fn test_guest_policy() {
+        let owner = test_principal(15);
+        let anon = Principal::anonymous();
+        let user = test_principal(16);
+        add_user(user);
+
+        assert!(!guest_policy().enabled);
+        assert!(!has_guest_access(&anon));
+        assert!(has_guest_access(&user)); // Users always have guest access
+
+        set_guest_policy(true, owner);
+        assert!(has_guest_access(&anon));
+        assert!(!has_user_access(&anon)); // Guests don't get user access
+        assert_eq!(guest_policy().changed_by, Some(owner));
+
+        set_guest_policy(false, owner);
+        assert!(!has_guest_access(&anon));
+    }
+
     #[test]
     fn test_get_all_admins() {
         let admin1 = test_principal(11);
@@ -0,0 +1,463 @@
+//! Export and import of the auth state for moving to another canister.
+//!
+//! [`export_auth_state`] packs the admin and user whitelists, the guest
+//! policy, invites and the invite audit log into a blob signed with
+//! HMAC-SHA256 under a migration key the operator picks. The key is staged
+//! on both canisters beforehand with [`set_migration_key`], so it does not
+//! travel with the blob or in the arguments of an export or import, though
+//! it does travel in plain text in the arguments of `set_migration_key`
+//! itself. [`import_auth_state`] refuses blobs whose signature does not
+//! match, so a blob edited in transit or made up by someone without the key
+//! is never applied.
+//!
+//! The canister stores the SHA-256 digest of the key in stable memory and
+//! signs with that digest, so the digest is as good as the key: anyone who
+//! can read the canister's memory can sign exports. Stage a fresh key for
+//! each migration and clear it with `set_migration_key(None)` on both
+//! canisters once the import is done.
+//!
+//! Imports replace the existing state: admins, users and invites missing
+//! from the export are dropped, so a principal revoked on the source before
+//! the export does not keep access on the target. The admin running the
+//! import stays an admin so they are not locked out. Every export has an id,
+//! and each canister remembers the ids it exported and imported: importing
+//! the same blob twice, or back into the canister it came from, is rejected.
+//!
+//! `mcp! { auth = true }` generates the admin-only `set_migration_key`,
+//! `export_auth_state`, `import_auth_state` and `list_auth_transfers`
+//! endpoints.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use candid::Principal;
+//! use icarus_core::{auth, auth_state};
+//!
+//! let owner = Principal::from_slice(&[1]);
+//! auth::add_admin(owner);
+//! auth::add_user(Principal::from_slice(&[2]));
+//!
+//! auth_state::set_migration_key(Some("correct horse battery staple")).unwrap();
+//! let blob = auth_state::export_auth_state(owner).unwrap();
+//!
+//! // The source canister will not take its own export back
+//! assert!(auth_state::import_auth_state(&blob, owner).is_err());
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use hmac::{Hmac, Mac};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::auth::{self, GuestPolicy};
+use crate::invites::{self, Invite, InviteAuditEntry};
+use crate::memory::{self, StableMemory, AUTH_MIGRATION_KEY_MEMORY_ID, AUTH_TRANSFERS_MEMORY_ID};
+use crate::{IcarusError, Result};
+
+/// Version of the exported format; other versions are rejected.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Shortest migration key accepted by [`set_migration_key`].
+pub const MIN_KEY_LEN: usize = 16;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The auth state inside an export.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct AuthState {
+    /// Format of the export, [`FORMAT_VERSION`] when written.
+    pub version: u32,
+    /// Identifier of the export.
+    pub export_id: String,
+    /// Export time in nanoseconds since the epoch.
+    pub exported_at: u64,
+    /// Principal that exported it.
+    pub exported_by: Principal,
+    /// Admin whitelist.
+    pub admins: Vec<Principal>,
+    /// User whitelist.
+    pub users: Vec<Principal>,
+    /// Guest access policy.
+    pub guest_policy: GuestPolicy,
+    /// All invites, including used up and revoked ones.
+    pub invites: Vec<Invite>,
+    /// Invite audit log, oldest first.
+    pub invite_audit: Vec<InviteAuditEntry>,
+}
+
+/// The exported blob: the encoded [`AuthState`] and its signature.
+#[derive(CandidType, Deserialize)]
+struct SignedAuthState {
+    payload: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+/// Whether a canister produced or applied an export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub enum TransferDirection {
+    /// Exported from this canister
+    Exported,
+    /// Imported into this canister
+    Imported,
+}
+
+/// One export or import, as remembered by the canister.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct AuthTransfer {
+    /// Identifier of the export.
+    pub export_id: String,
+    /// Whether it was exported or imported here.
+    pub direction: TransferDirection,
+    /// Time in nanoseconds since the epoch.
+    pub at: u64,
+    /// Principal that exported or imported it.
+    pub by: Principal,
+    /// Admins in the export.
+    pub admins: u32,
+    /// Users in the export.
+    pub users: u32,
+    /// Invites in the export, which for imports replaced the existing ones.
+    pub invites: u32,
+}
+
+impl Storable for AuthTransfer {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap_or_default())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap_or_else(|e| unreachable!("corrupt auth transfer: {e}"))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).unwrap_or_default()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+thread_local! {
+    /// Exports and imports by export id.
+    static TRANSFERS: RefCell<StableBTreeMap<String, AuthTransfer, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(AUTH_TRANSFERS_MEMORY_ID))
+    );
+
+    /// SHA-256 digest of the staged migration key, which is the HMAC key, at key 0.
+    static MIGRATION_KEY: RefCell<StableBTreeMap<u8, Vec<u8>, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(AUTH_MIGRATION_KEY_MEMORY_ID))
+    );
+}
+
+fn mac(key: &[u8], payload: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(payload);
+    mac
+}
+
+/// Returns the HMAC key: the SHA-256 digest of the staged migration key.
+fn signing_key() -> Result<Vec<u8>> {
+    MIGRATION_KEY
+        .with(|cell| cell.borrow().get(&0))
+        .ok_or_else(|| {
+            IcarusError::ConfigurationError(
+                "No migration key is set; call set_migration_key first".to_string(),
+            )
+        })
+}
+
+/// Stages the migration key that signs exports and checks imports, or
+/// clears it with `None`.
+///
+/// The SHA-256 digest of the key is stored and used as the HMAC key, so it
+/// is as secret as the key itself; clear it once the migration is done.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if `key` is shorter than
+/// [`MIN_KEY_LEN`].
+pub fn set_migration_key(key: Option<&str>) -> Result<()> {
+    let Some(key) = key else {
+        MIGRATION_KEY.with(|cell| cell.borrow_mut().remove(&0));
+        return Ok(());
+    };
+    if key.len() < MIN_KEY_LEN {
+        return Err(IcarusError::ConfigurationError(format!(
+            "The migration key must be at least {MIN_KEY_LEN} bytes"
+        )));
+    }
+    let hash = Sha256::digest(key.as_bytes()).to_vec();
+    MIGRATION_KEY.with(|cell| cell.borrow_mut().insert(0, hash));
+    Ok(())
+}
+
+fn count(len: usize) -> u32 {
+    u32::try_from(len).unwrap_or(u32::MAX)
+}
+
+fn encode<T: CandidType>(value: &T) -> Result<Vec<u8>> {
+    candid::encode_one(value).map_err(|e| IcarusError::CandidError(e.to_string()))
+}
+
+/// Exports the auth state as a blob signed with the staged migration key.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if no migration key is set, and
+/// `IcarusError::CandidError` if the state cannot be encoded.
+pub fn export_auth_state(exported_by: Principal) -> Result<Vec<u8>> {
+    let key = signing_key()?;
+
+    let now = crate::time::now_nanos();
+    let (invites, invite_audit) = invites::export_all();
+    let mut state = AuthState {
+        version: FORMAT_VERSION,
+        export_id: String::new(),
+        exported_at: now,
+        exported_by,
+        admins: auth::get_all_admins(),
+        users: auth::get_all_users(),
+        guest_policy: auth::guest_policy(),
+        invites,
+        invite_audit,
+    };
+    // Two exports of the same state still get different ids
+    let mut hasher = Sha256::new();
+    hasher.update(encode(&state)?);
+    hasher.update(now.to_le_bytes());
+    state.export_id = hasher.finalize()[..16]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+
+    let payload = encode(&state)?;
+    let signature = mac(&key, &payload).finalize().into_bytes().to_vec();
+    let blob = encode(&SignedAuthState { payload, signature })?;
+
+    let transfer = AuthTransfer {
+        export_id: state.export_id.clone(),
+        direction: TransferDirection::Exported,
+        at: now,
+        by: exported_by,
+        admins: count(state.admins.len()),
+        users: count(state.users.len()),
+        invites: count(state.invites.len()),
+    };
+    TRANSFERS.with(|transfers| transfers.borrow_mut().insert(state.export_id, transfer));
+    Ok(blob)
+}
+
+/// Checks the signature of `blob` against the staged migration key and
+/// returns the state inside without applying it.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if no migration key is set or
+/// the format version is unknown, `IcarusError::AccessDenied` if the
+/// signature does not match, and `IcarusError::CandidError` if the blob is
+/// malformed.
+pub fn verify_auth_state(blob: &[u8]) -> Result<AuthState> {
+    let key = signing_key()?;
+
+    let signed: SignedAuthState =
+        candid::decode_one(blob).map_err(|e| IcarusError::CandidError(e.to_string()))?;
+    // `verify_slice` compares in constant time
+    if mac(&key, &signed.payload)
+        .verify_slice(&signed.signature)
+        .is_err()
+    {
+        return Err(IcarusError::AccessDenied(
+            "Auth state signature does not match the migration key".to_string(),
+        ));
+    }
+
+    let state: AuthState =
+        candid::decode_one(&signed.payload).map_err(|e| IcarusError::CandidError(e.to_string()))?;
+    if state.version != FORMAT_VERSION {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Unsupported auth state version {} (expected {FORMAT_VERSION})",
+            state.version
+        )));
+    }
+    Ok(state)
+}
+
+/// Replaces this canister's auth state with a blob from
+/// [`export_auth_state`].
+///
+/// Afterwards the admins, users, guest policy and invites are those of the
+/// export; principals and invites missing from it are removed. `imported_by`
+/// stays an admin even when the export does not list it. The invite audit
+/// log is appended rather than replaced.
+///
+/// # Errors
+///
+/// Returns the errors of [`verify_auth_state`], and
+/// `IcarusError::ConfigurationError` if this export was already imported
+/// here or was exported from this canister.
+pub fn import_auth_state(blob: &[u8], imported_by: Principal) -> Result<AuthTransfer> {
+    let state = verify_auth_state(blob)?;
+
+    if let Some(previous) = TRANSFERS.with(|transfers| transfers.borrow().get(&state.export_id)) {
+        return Err(IcarusError::ConfigurationError(match previous.direction {
+            TransferDirection::Exported => format!(
+                "Auth state {} was exported from this canister",
+                state.export_id
+            ),
+            TransferDirection::Imported => {
+                format!("Auth state {} was already imported", state.export_id)
+            }
+        }));
+    }
+
+    let keeps_admin =
+        |principal: &Principal| *principal == imported_by || state.admins.contains(principal);
+    for admin in auth::get_all_admins() {
+        if !keeps_admin(&admin) {
+            auth::remove_admin(&admin);
+        }
+    }
+    for user in auth::get_all_users() {
+        if keeps_admin(&user) || !state.users.contains(&user) {
+            auth::remove_user(&user);
+        }
+    }
+    for admin in state.admins.iter().chain([&imported_by]) {
+        auth::add_admin(*admin);
+    }
+    for user in &state.users {
+        if !keeps_admin(user) {
+            auth::add_user(*user);
+        }
+    }
+    auth::restore_guest_policy(state.guest_policy);
+    let restored = invites::import_all(state.invites, state.invite_audit);
+
+    let transfer = AuthTransfer {
+        export_id: state.export_id.clone(),
+        direction: TransferDirection::Imported,
+        at: crate::time::now_nanos(),
+        by: imported_by,
+        admins: count(state.admins.len()),
+        users: count(state.users.len()),
+        invites: restored,
+    };
+    TRANSFERS.with(|transfers| {
+        transfers
+            .borrow_mut()
+            .insert(state.export_id, transfer.clone())
+    });
+    Ok(transfer)
+}
+
+/// Returns the exports and imports of this canister, newest first.
+#[must_use]
+pub fn list_transfers() -> Vec<AuthTransfer> {
+    let mut transfers: Vec<AuthTransfer> = TRANSFERS.with(|transfers| {
+        transfers
+            .borrow()
+            .iter()
+            .map(|entry| entry.value())
+            .collect()
+    });
+    transfers.sort_by(|a, b| b.at.cmp(&a.at));
+    transfers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "0123456789abcdef";
+
+    /// Forgets an export so the test can import it as another canister would
+    fn forget(export_id: &str) {
+        TRANSFERS.with(|transfers| transfers.borrow_mut().remove(&export_id.to_string()));
+    }
+
+    #[test]
+    fn test_import_replaces_state_once() {
+        let owner = Principal::from_slice(&[41]);
+        let user = Principal::from_slice(&[42]);
+        let revoked = Principal::from_slice(&[45]);
+        auth::add_admin(owner);
+        auth::add_user(user);
+
+        assert!(set_migration_key(Some("short")).is_err());
+        set_migration_key(None).unwrap();
+        assert!(export_auth_state(owner).is_err());
+        set_migration_key(Some(KEY)).unwrap();
+        let blob = export_auth_state(owner).unwrap();
+        let state = verify_auth_state(&blob).unwrap();
+        assert!(state.admins.contains(&owner));
+        assert!(state.users.contains(&user));
+        assert!(import_auth_state(&blob, owner).is_err());
+
+        // A different canister with its own admin and a stale user
+        forget(&state.export_id);
+        let installer = Principal::from_slice(&[46]);
+        auth::remove_admin(&owner);
+        auth::remove_user(&user);
+        auth::add_admin(installer);
+        auth::add_user(revoked);
+        set_migration_key(Some("fedcba9876543210")).unwrap();
+        assert!(matches!(
+            import_auth_state(&blob, installer),
+            Err(IcarusError::AccessDenied(_))
+        ));
+        assert!(!auth::is_admin(&owner));
+
+        set_migration_key(Some(KEY)).unwrap();
+        let transfer = import_auth_state(&blob, installer).unwrap();
+        assert_eq!(transfer.direction, TransferDirection::Imported);
+        assert!(auth::is_admin(&owner));
+        assert!(auth::is_admin(&installer));
+        assert!(auth::is_user(&user));
+        assert!(!auth::is_user(&revoked));
+        assert!(import_auth_state(&blob, installer).is_err());
+        assert_eq!(list_transfers()[0].export_id, state.export_id);
+    }
+
+    #[test]
+    fn test_only_correctly_signed_exports_are_accepted() {
+        let owner = Principal::from_slice(&[43]);
+        set_migration_key(Some(KEY)).unwrap();
+        let blob = export_auth_state(owner).unwrap();
+        assert!(verify_auth_state(&blob).is_ok());
+
+        let mut signed: SignedAuthState = candid::decode_one(&blob).unwrap();
+        let mut state: AuthState = candid::decode_one(&signed.payload).unwrap();
+        state.admins.push(Principal::from_slice(&[44]));
+        signed.payload = encode(&state).unwrap();
+
+        // Tampered payload under the original signature
+        let tampered = encode(&signed).unwrap();
+        assert!(matches!(
+            verify_auth_state(&tampered),
+            Err(IcarusError::AccessDenied(_))
+        ));
+
+        // Signed with another key
+        signed.signature = mac(b"not the migration key", &signed.payload)
+            .finalize()
+            .into_bytes()
+            .to_vec();
+        let forged = encode(&signed).unwrap();
+        assert!(matches!(
+            verify_auth_state(&forged),
+            Err(IcarusError::AccessDenied(_))
+        ));
+
+        // Signed with the migration key
+        signed.signature = mac(&Sha256::digest(KEY.as_bytes()), &signed.payload)
+            .finalize()
+            .into_bytes()
+            .to_vec();
+        let resigned = encode(&signed).unwrap();
+        let accepted = verify_auth_state(&resigned).unwrap();
+        assert!(accepted.admins.contains(&Principal::from_slice(&[44])));
+    }
+}
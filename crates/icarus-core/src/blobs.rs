@@ -0,0 +1,443 @@
+//! Stable storage for binary blobs with chunked uploads.
+//!
+//! Canister messages are limited to about 2MB, so files are uploaded in
+//! pieces: [`upload_begin`] reserves an id for a blob of a declared size,
+//! [`upload_chunk`] stores chunks by index (in any order, retries overwrite),
+//! and [`upload_commit`] checks that every byte arrived and records the blob's
+//! SHA-256. Chunks go straight to stable memory, so neither uploads in progress
+//! nor committed blobs are lost on upgrade.
+//!
+//! `mcp!{ blobs = true }` exposes the upload functions and readers as canister
+//! endpoints and serves committed blobs from `http_request` at
+//! `/blobs/<id>` through [`serve`].
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::blobs;
+//!
+//! let data = b"%PDF-1.7 ...";
+//! let id = blobs::upload_begin("invoice.pdf", data.len() as u64, "application/pdf").unwrap();
+//! for (index, chunk) in data.chunks(4).enumerate() {
+//!     blobs::upload_chunk(id, index as u32, chunk).unwrap();
+//! }
+//!
+//! let info = blobs::upload_commit(id).unwrap();
+//! assert_eq!(info.size, data.len() as u64);
+//! assert_eq!(blobs::read_blob(id).unwrap(), data);
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::gateway::{GatewayRequest, GatewayResponse};
+use crate::memory::{self, StableMemory, BLOB_CHUNKS_MEMORY_ID, BLOB_INDEX_MEMORY_ID};
+use crate::{IcarusError, Result};
+
+/// Largest accepted chunk, leaving room for the rest of the message.
+pub const MAX_CHUNK_SIZE: usize = 1_900_000;
+
+/// Largest blob [`serve`] returns in a single HTTP response.
+pub const MAX_HTTP_BODY_SIZE: u64 = 2_000_000;
+
+/// Path prefix of blobs served over HTTP.
+pub const HTTP_PATH_PREFIX: &str = "/blobs/";
+
+/// Metadata of a stored or uploading blob.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct BlobInfo {
+    /// Blob id.
+    pub id: u64,
+    /// File name given at upload.
+    pub name: String,
+    /// MIME type, e.g. `image/png`.
+    pub mime_type: String,
+    /// Size in bytes.
+    pub size: u64,
+    /// Hex SHA-256 of the contents, set once the upload is committed.
+    pub sha256: Option<String>,
+    /// Upload start time in nanoseconds since the epoch.
+    pub created_at: u64,
+}
+
+impl BlobInfo {
+    /// Returns true once the upload has been committed.
+    #[must_use]
+    #[inline]
+    pub fn is_committed(&self) -> bool {
+        self.sha256.is_some()
+    }
+}
+
+impl Storable for BlobInfo {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap_or_default())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap_or_else(|e| unreachable!("corrupt blob index: {e}"))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).unwrap_or_default()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Key of one chunk: big-endian so a blob's chunks are adjacent and in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct ChunkKey {
+    blob_id: u64,
+    index: u32,
+}
+
+impl Storable for ChunkKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(self.into_bytes())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        let mut blob_id = [0u8; 8];
+        let mut index = [0u8; 4];
+        blob_id.copy_from_slice(&bytes[..8]);
+        index.copy_from_slice(&bytes[8..12]);
+        Self {
+            blob_id: u64::from_be_bytes(blob_id),
+            index: u32::from_be_bytes(index),
+        }
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(12);
+        bytes.extend_from_slice(&self.blob_id.to_be_bytes());
+        bytes.extend_from_slice(&self.index.to_be_bytes());
+        bytes
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 12,
+        is_fixed_size: true,
+    };
+}
+
+thread_local! {
+    static INDEX: RefCell<StableBTreeMap<u64, BlobInfo, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(BLOB_INDEX_MEMORY_ID))
+    );
+
+    static CHUNKS: RefCell<StableBTreeMap<ChunkKey, Vec<u8>, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(BLOB_CHUNKS_MEMORY_ID))
+    );
+}
+
+/// Starts an upload and returns the new blob's id.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if `name` or `mime_type` is empty.
+pub fn upload_begin(name: &str, size: u64, mime_type: &str) -> Result<u64> {
+    if name.trim().is_empty() {
+        return Err(IcarusError::ConfigurationError(
+            "Blob name cannot be empty".to_string(),
+        ));
+    }
+    if mime_type.trim().is_empty() {
+        return Err(IcarusError::ConfigurationError(
+            "Blob MIME type cannot be empty".to_string(),
+        ));
+    }
+
+    INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        let id = index.last_key_value().map_or(1, |(id, _)| id + 1);
+        index.insert(
+            id,
+            BlobInfo {
+                id,
+                name: name.to_string(),
+                mime_type: mime_type.to_string(),
+                size,
+                sha256: None,
+                created_at: crate::time::now_nanos(),
+            },
+        );
+        Ok(id)
+    })
+}
+
+/// Stores chunk `index` of an upload, replacing an earlier copy.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if the upload does not exist or is
+/// already committed, if the chunk is larger than [`MAX_CHUNK_SIZE`], or if
+/// the upload would exceed its declared size.
+pub fn upload_chunk(id: u64, index: u32, bytes: &[u8]) -> Result<()> {
+    let info = pending_upload(id)?;
+    if bytes.len() > MAX_CHUNK_SIZE {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Chunk of {} bytes exceeds the {MAX_CHUNK_SIZE} byte limit",
+            bytes.len()
+        )));
+    }
+
+    let key = ChunkKey { blob_id: id, index };
+    CHUNKS.with(|chunks| {
+        let mut chunks = chunks.borrow_mut();
+        let replaced = chunks.get(&key).map_or(0, |chunk| chunk.len() as u64);
+        let received = received_bytes(&chunks, id) - replaced + bytes.len() as u64;
+        if received > info.size {
+            return Err(IcarusError::ConfigurationError(format!(
+                "Upload {id} would exceed its declared size of {} bytes",
+                info.size
+            )));
+        }
+
+        chunks.insert(key, bytes.to_vec());
+        Ok(())
+    })
+}
+
+/// Completes an upload once every chunk has arrived.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if the upload does not exist, is
+/// already committed, has a gap in its chunk indices, or is missing bytes.
+pub fn upload_commit(id: u64) -> Result<BlobInfo> {
+    let mut info = pending_upload(id)?;
+
+    let (received, sha256) = CHUNKS.with(|chunks| {
+        let chunks = chunks.borrow();
+        let mut hasher = Sha256::new();
+        let mut received = 0u64;
+        for (expected, entry) in chunks.range(chunk_range(id)).enumerate() {
+            if u64::from(entry.key().index) != expected as u64 {
+                return Err(IcarusError::ConfigurationError(format!(
+                    "Upload {id} is missing chunk {expected}"
+                )));
+            }
+            let chunk = entry.value();
+            hasher.update(&chunk);
+            received += chunk.len() as u64;
+        }
+        Ok((received, to_hex(&hasher.finalize())))
+    })?;
+
+    if received != info.size {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Upload {id} has {received} of {} bytes",
+            info.size
+        )));
+    }
+
+    info.sha256 = Some(sha256);
+    INDEX.with(|index| index.borrow_mut().insert(id, info.clone()));
+    Ok(info)
+}
+
+/// Deletes a blob or abandons an upload, returning whether it existed.
+pub fn delete_blob(id: u64) -> bool {
+    CHUNKS.with(|chunks| {
+        let mut chunks = chunks.borrow_mut();
+        let keys: Vec<ChunkKey> = chunks
+            .range(chunk_range(id))
+            .map(|entry| *entry.key())
+            .collect();
+        for key in keys {
+            chunks.remove(&key);
+        }
+    });
+    INDEX.with(|index| index.borrow_mut().remove(&id).is_some())
+}
+
+/// Returns the metadata of a blob or upload.
+#[must_use]
+pub fn blob_info(id: u64) -> Option<BlobInfo> {
+    INDEX.with(|index| index.borrow().get(&id))
+}
+
+/// Lists committed blobs in id order.
+#[must_use]
+pub fn list_blobs() -> Vec<BlobInfo> {
+    INDEX.with(|index| {
+        index
+            .borrow()
+            .iter()
+            .map(|entry| entry.value())
+            .filter(BlobInfo::is_committed)
+            .collect()
+    })
+}
+
+/// Returns one chunk of a committed blob, for clients downloading in pieces.
+#[must_use]
+pub fn read_chunk(id: u64, index: u32) -> Option<Vec<u8>> {
+    blob_info(id).filter(BlobInfo::is_committed)?;
+    CHUNKS.with(|chunks| chunks.borrow().get(&ChunkKey { blob_id: id, index }))
+}
+
+/// Returns the contents of a committed blob.
+#[must_use]
+pub fn read_blob(id: u64) -> Option<Vec<u8>> {
+    let info = blob_info(id).filter(BlobInfo::is_committed)?;
+    CHUNKS.with(|chunks| {
+        let mut data = Vec::with_capacity(usize::try_from(info.size).unwrap_or_default());
+        for entry in chunks.borrow().range(chunk_range(id)) {
+            data.extend_from_slice(&entry.value());
+        }
+        Some(data)
+    })
+}
+
+/// Handles `GET /blobs/<id>`, returning `None` for paths outside [`HTTP_PATH_PREFIX`].
+///
+/// Blobs larger than [`MAX_HTTP_BODY_SIZE`] get `413` and must be read with
+/// [`read_chunk`] instead.
+#[must_use]
+pub fn serve(request: &GatewayRequest) -> Option<GatewayResponse> {
+    let id = request.path().strip_prefix(HTTP_PATH_PREFIX)?;
+    if !request.method.eq_ignore_ascii_case("GET") {
+        return Some(GatewayResponse::text(
+            405,
+            "text/plain; charset=utf-8",
+            "Method not allowed",
+        ));
+    }
+
+    let Some(info) = id
+        .parse::<u64>()
+        .ok()
+        .and_then(blob_info)
+        .filter(BlobInfo::is_committed)
+    else {
+        return Some(GatewayResponse::not_found());
+    };
+    if info.size > MAX_HTTP_BODY_SIZE {
+        return Some(GatewayResponse::text(
+            413,
+            "text/plain; charset=utf-8",
+            "Blob too large for a single response; download it with blob_chunk",
+        ));
+    }
+
+    let body = read_blob(info.id).unwrap_or_default();
+    Some(GatewayResponse {
+        status_code: 200,
+        headers: vec![
+            ("Content-Type".to_string(), info.mime_type),
+            ("Content-Length".to_string(), body.len().to_string()),
+            (
+                "ETag".to_string(),
+                format!("\"{}\"", info.sha256.unwrap_or_default()),
+            ),
+        ],
+        body,
+        upgrade: None,
+    })
+}
+
+fn pending_upload(id: u64) -> Result<BlobInfo> {
+    match blob_info(id) {
+        Some(info) if !info.is_committed() => Ok(info),
+        Some(_) => Err(IcarusError::ConfigurationError(format!(
+            "Upload {id} is already committed"
+        ))),
+        None => Err(IcarusError::ConfigurationError(format!(
+            "No upload with id {id}"
+        ))),
+    }
+}
+
+fn chunk_range(id: u64) -> std::ops::RangeInclusive<ChunkKey> {
+    ChunkKey {
+        blob_id: id,
+        index: 0,
+    }..=ChunkKey {
+        blob_id: id,
+        index: u32::MAX,
+    }
+}
+
+fn received_bytes(chunks: &StableBTreeMap<ChunkKey, Vec<u8>, StableMemory>, id: u64) -> u64 {
+    chunks
+        .range(chunk_range(id))
+        .map(|entry| entry.value().len() as u64)
+        .sum()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upload(name: &str, data: &[u8], chunk_size: usize) -> BlobInfo {
+        let id = upload_begin(name, data.len() as u64, "text/plain").unwrap();
+        // Out of order, to show chunks are reassembled by index
+        let chunks: Vec<&[u8]> = data.chunks(chunk_size).collect();
+        for (index, chunk) in chunks.iter().enumerate().rev() {
+            upload_chunk(id, u32::try_from(index).unwrap(), chunk).unwrap();
+        }
+        upload_commit(id).unwrap()
+    }
+
+    #[test]
+    fn test_chunked_upload_round_trip() {
+        let info = upload("hello.txt", b"hello world", 3);
+        assert_eq!(info.size, 11);
+        assert_eq!(
+            info.sha256.as_deref(),
+            Some("b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9")
+        );
+        assert_eq!(read_blob(info.id).unwrap(), b"hello world");
+        assert_eq!(read_chunk(info.id, 1).unwrap(), b"lo ");
+        assert!(list_blobs().iter().any(|blob| blob.id == info.id));
+    }
+
+    #[test]
+    fn test_commit_rejects_incomplete_uploads() {
+        let id = upload_begin("gap.bin", 6, "application/octet-stream").unwrap();
+        upload_chunk(id, 0, b"ab").unwrap();
+        upload_chunk(id, 2, b"ef").unwrap();
+        assert!(upload_commit(id).is_err());
+        assert!(read_blob(id).is_none());
+
+        upload_chunk(id, 1, b"cd").unwrap();
+        assert!(upload_chunk(id, 3, b"g").is_err());
+        assert!(upload_commit(id).is_ok());
+        assert!(upload_chunk(id, 0, b"ab").is_err());
+
+        assert!(delete_blob(id));
+        assert!(blob_info(id).is_none());
+    }
+
+    #[test]
+    fn test_serve_blob_over_http() {
+        let info = upload("page.html", b"<h1>hi</h1>", 4);
+
+        let response = serve(&GatewayRequest::get(format!("/blobs/{}", info.id))).unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body, b"<h1>hi</h1>");
+        assert!(response
+            .headers
+            .contains(&("Content-Type".to_string(), "text/plain".to_string())));
+
+        let missing = serve(&GatewayRequest::get("/blobs/999")).unwrap();
+        assert_eq!(missing.status_code, 404);
+        assert!(serve(&GatewayRequest::get("/metrics")).is_none());
+    }
+}
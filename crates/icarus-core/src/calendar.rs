@@ -0,0 +1,471 @@
+//! Calendar schedules in a fixed time zone.
+//!
+//! A [`CalendarSchedule`] describes a wall-clock time that repeats daily,
+//! weekly or monthly in a [`Zone`], and [`CalendarSchedule::next_after`]
+//! finds the next occurrence with the civil calendar: months have their real
+//! length and leap years are accounted for. A monthly day past the end of a
+//! month, e.g. the 31st in April, falls on the month's last day.
+//!
+//! Zones are fixed offsets from UTC. They can be given as an offset
+//! (`+05:30`), as `UTC`, or as the IANA name of a zone that does not observe
+//! daylight saving time, such as `Asia/Tokyo`. Zones with daylight saving
+//! time are rejected rather than being run an hour off for half the year.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::calendar::{CalendarSchedule, Recurrence, Zone};
+//!
+//! // Every day at 09:00 in Tokyo (00:00 UTC)
+//! let zone: Zone = "Asia/Tokyo".parse().unwrap();
+//! let daily = CalendarSchedule::new(Recurrence::Daily { hour: 9, minute: 0 }, zone).unwrap();
+//!
+//! // 2024-01-01T00:00:00Z is 09:00 in Tokyo, so the next run is a day later
+//! let new_year = 1_704_067_200_000_000_000;
+//! assert_eq!(daily.next_after(new_year), new_year + 24 * 60 * 60 * 1_000_000_000);
+//!
+//! assert!("Europe/Berlin".parse::<Zone>().is_err());
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use candid::{CandidType, Deserialize};
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::Serialize;
+
+use crate::{IcarusError, Result};
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// Largest offset from UTC in use, in minutes (UTC+14).
+const MAX_OFFSET_MINUTES: i32 = 14 * 60;
+
+/// IANA zones without daylight saving time, with their offsets in minutes.
+const FIXED_ZONES: &[(&str, i32)] = &[
+    ("Africa/Accra", 0),
+    ("Africa/Johannesburg", 120),
+    ("Africa/Lagos", 60),
+    ("Africa/Nairobi", 180),
+    ("America/Argentina/Buenos_Aires", -180),
+    ("America/Bogota", -300),
+    ("America/Lima", -300),
+    ("America/Panama", -300),
+    ("America/Phoenix", -420),
+    ("America/Sao_Paulo", -180),
+    ("Asia/Bangkok", 420),
+    ("Asia/Calcutta", 330),
+    ("Asia/Dhaka", 360),
+    ("Asia/Dubai", 240),
+    ("Asia/Ho_Chi_Minh", 420),
+    ("Asia/Hong_Kong", 480),
+    ("Asia/Jakarta", 420),
+    ("Asia/Karachi", 300),
+    ("Asia/Kathmandu", 345),
+    ("Asia/Kolkata", 330),
+    ("Asia/Manila", 480),
+    ("Asia/Riyadh", 180),
+    ("Asia/Seoul", 540),
+    ("Asia/Shanghai", 480),
+    ("Asia/Singapore", 480),
+    ("Asia/Taipei", 480),
+    ("Asia/Tashkent", 300),
+    ("Asia/Tokyo", 540),
+    ("Australia/Brisbane", 600),
+    ("Australia/Darwin", 570),
+    ("Australia/Perth", 480),
+    ("Europe/Istanbul", 180),
+    ("Europe/Moscow", 180),
+    ("Pacific/Honolulu", -600),
+];
+
+/// A fixed offset from UTC, with the name it was given by.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct Zone {
+    name: String,
+    offset_minutes: i32,
+}
+
+impl Zone {
+    /// Returns UTC.
+    #[must_use]
+    pub fn utc() -> Self {
+        Self {
+            name: "UTC".to_string(),
+            offset_minutes: 0,
+        }
+    }
+
+    /// Returns the zone `offset_minutes` east of UTC.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::ConfigurationError` for offsets beyond ±14 hours.
+    pub fn fixed(offset_minutes: i32) -> Result<Self> {
+        if offset_minutes.abs() > MAX_OFFSET_MINUTES {
+            return Err(IcarusError::ConfigurationError(format!(
+                "UTC offset of {offset_minutes} minutes is out of range"
+            )));
+        }
+        let sign = if offset_minutes < 0 { '-' } else { '+' };
+        let minutes = offset_minutes.abs();
+        Ok(Self {
+            name: format!("{sign}{:02}:{:02}", minutes / 60, minutes % 60),
+            offset_minutes,
+        })
+    }
+
+    /// Returns the zone name, e.g. `Asia/Tokyo` or `+05:30`.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the offset east of UTC in minutes.
+    #[must_use]
+    pub const fn offset_minutes(&self) -> i32 {
+        self.offset_minutes
+    }
+
+    fn offset_nanos(&self) -> i64 {
+        i64::from(self.offset_minutes) * 60 * NANOS_PER_SECOND
+    }
+}
+
+impl Default for Zone {
+    fn default() -> Self {
+        Self::utc()
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl FromStr for Zone {
+    type Err = IcarusError;
+
+    /// Parses `UTC`, an offset such as `+05:30`, `Etc/GMT-9`, or the IANA
+    /// name of a zone without daylight saving time.
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if matches!(s, "UTC" | "Etc/UTC" | "GMT" | "Etc/GMT" | "Z") {
+            return Ok(Self::utc());
+        }
+        if let Some(offset) = parse_offset(s) {
+            return Self::fixed(offset);
+        }
+        // POSIX-style names: Etc/GMT-9 is nine hours east of UTC
+        if let Some(hours) = s
+            .strip_prefix("Etc/GMT")
+            .and_then(|hours| hours.parse::<i32>().ok())
+        {
+            let mut zone = Self::fixed(-hours * 60)?;
+            zone.name = s.to_string();
+            return Ok(zone);
+        }
+        FIXED_ZONES
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|&(name, offset_minutes)| Self {
+                name: name.to_string(),
+                offset_minutes,
+            })
+            .ok_or_else(|| {
+                IcarusError::ConfigurationError(format!(
+                    "Unknown zone '{s}', or it observes daylight saving time; \
+                     use a fixed offset such as +01:00"
+                ))
+            })
+    }
+}
+
+/// Parses `+HH:MM`, `-HH:MM`, `+HHMM` or `+HH` into minutes.
+fn parse_offset(s: &str) -> Option<i32> {
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (
+            digits[..2].parse::<i32>().ok()?,
+            digits[2..].parse::<i32>().ok()?,
+        ),
+        _ => return None,
+    };
+    (minutes < 60).then_some(sign * (hours * 60 + minutes))
+}
+
+/// When a calendar schedule repeats, in local time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub enum Recurrence {
+    /// Every day at `hour:minute`.
+    Daily {
+        /// Hour, 0-23
+        hour: u8,
+        /// Minute, 0-59
+        minute: u8,
+    },
+    /// Every week on `weekday` at `hour:minute`.
+    Weekly {
+        /// Days from Monday, 0-6
+        weekday: u8,
+        /// Hour, 0-23
+        hour: u8,
+        /// Minute, 0-59
+        minute: u8,
+    },
+    /// Every month on `day` at `hour:minute`, or on the last day of shorter
+    /// months.
+    Monthly {
+        /// Day of the month, 1-31
+        day: u8,
+        /// Hour, 0-23
+        hour: u8,
+        /// Minute, 0-59
+        minute: u8,
+    },
+}
+
+impl Recurrence {
+    fn time(self) -> (u8, u8) {
+        match self {
+            Self::Daily { hour, minute }
+            | Self::Weekly { hour, minute, .. }
+            | Self::Monthly { hour, minute, .. } => (hour, minute),
+        }
+    }
+}
+
+/// A recurrence in a zone.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct CalendarSchedule {
+    /// When the schedule repeats, in `zone`'s local time.
+    pub recurrence: Recurrence,
+    /// Zone of the wall-clock times.
+    pub zone: Zone,
+}
+
+impl CalendarSchedule {
+    /// Creates a schedule after checking the recurrence's fields.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::ConfigurationError` for an hour, minute, weekday
+    /// or day of the month out of range.
+    pub fn new(recurrence: Recurrence, zone: Zone) -> Result<Self> {
+        let (hour, minute) = recurrence.time();
+        let problem = if hour > 23 {
+            Some(format!("hour {hour} is not 0-23"))
+        } else if minute > 59 {
+            Some(format!("minute {minute} is not 0-59"))
+        } else {
+            match recurrence {
+                Recurrence::Weekly { weekday, .. } if weekday > 6 => {
+                    Some(format!("weekday {weekday} is not 0 (Monday) to 6 (Sunday)"))
+                }
+                Recurrence::Monthly { day, .. } if !(1..=31).contains(&day) => {
+                    Some(format!("day {day} is not 1-31"))
+                }
+                _ => None,
+            }
+        };
+        match problem {
+            Some(problem) => Err(IcarusError::ConfigurationError(format!(
+                "Invalid schedule: {problem}"
+            ))),
+            None => Ok(Self { recurrence, zone }),
+        }
+    }
+
+    /// Returns the first occurrence strictly after `after_nanos`, in
+    /// nanoseconds since the epoch.
+    #[must_use]
+    pub fn next_after(&self, after_nanos: u64) -> u64 {
+        let offset = self.zone.offset_nanos();
+        let local_after = to_local(
+            i64::try_from(after_nanos)
+                .unwrap_or(i64::MAX)
+                .saturating_add(offset),
+        );
+        let (hour, minute) = self.recurrence.time();
+        let time = NaiveTime::from_hms_opt(u32::from(hour), u32::from(minute), 0)
+            .unwrap_or(NaiveTime::MIN);
+        let today = local_after.date();
+
+        let next = match self.recurrence {
+            Recurrence::Daily { .. } => {
+                let candidate = today.and_time(time);
+                if candidate > local_after {
+                    candidate
+                } else {
+                    add_days(today, 1).and_time(time)
+                }
+            }
+            Recurrence::Weekly { weekday, .. } => {
+                let ahead = (i64::from(weekday)
+                    - i64::from(today.weekday().num_days_from_monday()))
+                .rem_euclid(7);
+                let candidate = add_days(today, ahead.unsigned_abs()).and_time(time);
+                if candidate > local_after {
+                    candidate
+                } else {
+                    add_days(today, ahead.unsigned_abs() + 7).and_time(time)
+                }
+            }
+            Recurrence::Monthly { day, .. } => {
+                let candidate = day_in_month(today, day).and_time(time);
+                if candidate > local_after {
+                    candidate
+                } else {
+                    let next_month = first_of_month(today)
+                        .checked_add_months(Months::new(1))
+                        .unwrap_or(NaiveDate::MAX);
+                    day_in_month(next_month, day).and_time(time)
+                }
+            }
+        };
+
+        let utc = next
+            .and_utc()
+            .timestamp_nanos_opt()
+            .unwrap_or(i64::MAX)
+            .saturating_sub(offset);
+        u64::try_from(utc).unwrap_or(0)
+    }
+}
+
+fn to_local(nanos: i64) -> NaiveDateTime {
+    DateTime::from_timestamp(
+        nanos.div_euclid(NANOS_PER_SECOND),
+        u32::try_from(nanos.rem_euclid(NANOS_PER_SECOND)).unwrap_or(0),
+    )
+    .unwrap_or_default()
+    .naive_utc()
+}
+
+fn add_days(date: NaiveDate, days: u64) -> NaiveDate {
+    date.checked_add_days(Days::new(days))
+        .unwrap_or(NaiveDate::MAX)
+}
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// Returns `day` of the month containing `date`, or the month's last day.
+fn day_in_month(date: NaiveDate, day: u8) -> NaiveDate {
+    (1..=u32::from(day))
+        .rev()
+        .find_map(|day| date.with_day(day))
+        .unwrap_or(date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Nanoseconds of a UTC date and time.
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> u64 {
+        let nanos = NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+            .and_utc()
+            .timestamp_nanos_opt()
+            .unwrap();
+        u64::try_from(nanos).unwrap()
+    }
+
+    #[test]
+    fn test_monthly_uses_real_month_lengths() {
+        let schedule = CalendarSchedule::new(
+            Recurrence::Monthly {
+                day: 31,
+                hour: 12,
+                minute: 0,
+            },
+            Zone::utc(),
+        )
+        .unwrap();
+
+        // Leap year February ends on the 29th, April on the 30th
+        assert_eq!(
+            schedule.next_after(at(2024, 1, 31, 12, 0)),
+            at(2024, 2, 29, 12, 0)
+        );
+        assert_eq!(
+            schedule.next_after(at(2023, 2, 1, 0, 0)),
+            at(2023, 2, 28, 12, 0)
+        );
+        assert_eq!(
+            schedule.next_after(at(2024, 4, 1, 0, 0)),
+            at(2024, 4, 30, 12, 0)
+        );
+        assert_eq!(
+            schedule.next_after(at(2024, 12, 31, 13, 0)),
+            at(2025, 1, 31, 12, 0)
+        );
+    }
+
+    #[test]
+    fn test_daily_and_weekly_in_local_time() {
+        let kolkata: Zone = "Asia/Kolkata".parse().unwrap();
+        let daily =
+            CalendarSchedule::new(Recurrence::Daily { hour: 9, minute: 0 }, kolkata).unwrap();
+        // 09:00 in Kolkata is 03:30 UTC
+        assert_eq!(
+            daily.next_after(at(2024, 3, 1, 1, 0)),
+            at(2024, 3, 1, 3, 30)
+        );
+        assert_eq!(
+            daily.next_after(at(2024, 3, 1, 3, 30)),
+            at(2024, 3, 2, 3, 30)
+        );
+
+        // Mondays at 08:00, seven hours behind UTC; 2024-03-04 is a Monday
+        let weekly = CalendarSchedule::new(
+            Recurrence::Weekly {
+                weekday: 0,
+                hour: 8,
+                minute: 0,
+            },
+            "-07:00".parse().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            weekly.next_after(at(2024, 3, 1, 0, 0)),
+            at(2024, 3, 4, 15, 0)
+        );
+        assert_eq!(
+            weekly.next_after(at(2024, 3, 4, 15, 0)),
+            at(2024, 3, 11, 15, 0)
+        );
+    }
+
+    #[test]
+    fn test_zone_parsing() {
+        assert_eq!("+05:45".parse::<Zone>().unwrap().offset_minutes(), 345);
+        assert_eq!("-0330".parse::<Zone>().unwrap().name(), "-03:30");
+        assert_eq!("Etc/GMT+5".parse::<Zone>().unwrap().offset_minutes(), -300);
+        assert_eq!("UTC".parse::<Zone>().unwrap(), Zone::utc());
+        assert!("America/New_York".parse::<Zone>().is_err());
+        assert!("+15:00".parse::<Zone>().is_err());
+        assert!(CalendarSchedule::new(
+            Recurrence::Daily {
+                hour: 24,
+                minute: 0
+            },
+            Zone::utc()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! Inter-canister tool invocation client.
+//!
+//! [`RemoteToolClient`] lets one Icarus canister call tools exposed by another
+//! Icarus canister through its generated `mcp_call_tool` endpoint. Requests are
+//! encoded as JSON-RPC `tools/call` messages and results are decoded back into
+//! [`CallToolResult`] or any `DeserializeOwned` type.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use icarus_core::client::RemoteToolClient;
+//! use icarus_core::CanisterId;
+//!
+//! #[tool("Add numbers on the calculator canister")]
+//! async fn remote_add(a: f64, b: f64) -> Result<f64, String> {
+//!     let target = CanisterId::from_text("rrkah-fqaaa-aaaaa-aaaaq-cai").map_err(|e| e.to_string())?;
+//!     RemoteToolClient::new(target)
+//!         .with_timeout_secs(30)
+//!         .call_typed("add", &serde_json::json!({ "a": a, "b": b }))
+//!         .await
+//!         .map_err(|e| e.to_string())
+//! }
+//! ```
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{CallToolResult, CanisterId, IcarusError, JsonRpcError, Result};
+
+/// Name of the canister method generated by `mcp!{}` for tool execution.
+pub const MCP_CALL_TOOL_METHOD: &str = "mcp_call_tool";
+
+/// Default timeout for bounded-wait inter-canister calls, in seconds.
+pub const DEFAULT_CALL_TIMEOUT_SECS: u32 = 60;
+
+/// Client for invoking tools on a remote Icarus canister.
+///
+/// The client is cheap to construct and holds no state beyond its target and
+/// timeout, so it can be created inside each `#[tool]` function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemoteToolClient {
+    canister_id: CanisterId,
+    timeout_secs: Option<u32>,
+}
+
+impl RemoteToolClient {
+    /// Creates a client targeting the given canister with the default timeout.
+    #[must_use]
+    pub const fn new(canister_id: CanisterId) -> Self {
+        Self {
+            canister_id,
+            timeout_secs: Some(DEFAULT_CALL_TIMEOUT_SECS),
+        }
+    }
+
+    /// Sets the bounded-wait timeout for calls made by this client.
+    #[must_use]
+    pub const fn with_timeout_secs(mut self, timeout_secs: u32) -> Self {
+        self.timeout_secs = Some(timeout_secs);
+        self
+    }
+
+    /// Uses unbounded-wait calls, which never time out but guarantee a response.
+    #[must_use]
+    pub const fn without_timeout(mut self) -> Self {
+        self.timeout_secs = None;
+        self
+    }
+
+    /// Returns the target canister.
+    #[must_use]
+    #[inline]
+    pub const fn canister_id(&self) -> CanisterId {
+        self.canister_id
+    }
+
+    /// Returns the configured timeout, if any.
+    #[must_use]
+    #[inline]
+    pub const fn timeout_secs(&self) -> Option<u32> {
+        self.timeout_secs
+    }
+
+    /// Calls a remote tool and returns the raw MCP result.
+    ///
+    /// # Errors
+    ///
+    /// - `IcarusError::Timeout` if a bounded-wait call exceeds the timeout
+    /// - `IcarusError::ExternalServiceError` if the call is rejected
+    /// - `IcarusError::CandidError` if the reply cannot be decoded
+    /// - `IcarusError::JsonRpcError` if the remote canister returns an error response
+    pub async fn call<A: Serialize + ?Sized>(
+        &self,
+        tool_name: &str,
+        arguments: &A,
+    ) -> Result<CallToolResult> {
+        let request = build_call_request(tool_name, arguments)?;
+        let response = self.invoke(&request).await?;
+        parse_call_response(&response)
+    }
+
+    /// Calls a remote tool and deserializes its text output into `T`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`call`](Self::call), plus
+    /// `IcarusError::ToolExecutionFailed` if the remote tool reported an error
+    /// and `IcarusError::JsonError` if the output does not deserialize into `T`.
+    pub async fn call_typed<A, T>(&self, tool_name: &str, arguments: &A) -> Result<T>
+    where
+        A: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        let result = self.call(tool_name, arguments).await?;
+        decode_result(tool_name, &result)
+    }
+
+    async fn invoke(&self, request: &str) -> Result<String> {
+        use ic_cdk::call::{Call, CallFailed, RejectCode};
+
+        let principal = *self.canister_id.as_principal();
+        let call = match self.timeout_secs {
+            Some(secs) => Call::bounded_wait(principal, MCP_CALL_TOOL_METHOD).change_timeout(secs),
+            None => Call::unbounded_wait(principal, MCP_CALL_TOOL_METHOD),
+        };
+
+        let response = call.with_arg(request).await.map_err(|err| match &err {
+            CallFailed::CallRejected(rejected)
+                if matches!(rejected.reject_code(), Ok(RejectCode::SysUnknown)) =>
+            {
+                IcarusError::Timeout {
+                    operation: format!("{}::{MCP_CALL_TOOL_METHOD}", self.canister_id),
+                    timeout_ms: u64::from(self.timeout_secs.unwrap_or_default()) * 1000,
+                }
+            }
+            _ => IcarusError::ExternalServiceError {
+                service: self.canister_id.to_string(),
+                message: err.to_string(),
+            },
+        })?;
+
+        response
+            .candid::<String>()
+            .map_err(|e| IcarusError::CandidError(e.to_string()))
+    }
+}
+
+/// Builds the JSON-RPC `tools/call` request accepted by `mcp_call_tool`.
+///
+/// # Errors
+///
+/// Returns `IcarusError::JsonError` if the arguments cannot be serialized.
+pub fn build_call_request<A: Serialize + ?Sized>(tool_name: &str, arguments: &A) -> Result<String> {
+    let arguments = serde_json::to_value(arguments)
+        .map_err(|e| IcarusError::JsonError(format!("Failed to serialize arguments: {e}")))?;
+
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": "1",
+        "method": "tools/call",
+        "params": {
+            "name": tool_name,
+            "arguments": arguments,
+        }
+    });
+
+    Ok(request.to_string())
+}
+
+/// Parses a JSON-RPC response string returned by `mcp_call_tool`.
+///
+/// # Errors
+///
+/// Returns `IcarusError::JsonRpcError` for error responses and
+/// `IcarusError::JsonError` for malformed payloads.
+pub fn parse_call_response(response: &str) -> Result<CallToolResult> {
+    let json: serde_json::Value = serde_json::from_str(response)
+        .map_err(|e| IcarusError::JsonError(format!("Invalid JSON-RPC response: {e}")))?;
+
+    if let Some(error) = json.get("error") {
+        let code = error
+            .get("code")
+            .and_then(serde_json::Value::as_i64)
+            .and_then(|c| i32::try_from(c).ok())
+            .unwrap_or(-32603);
+        let message = error
+            .get("message")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or("Unknown error");
+        return Err(JsonRpcError::new(code, message).into());
+    }
+
+    let result = json
+        .get("result")
+        .cloned()
+        .ok_or_else(|| IcarusError::JsonError("Missing result field in response".to_string()))?;
+
+    serde_json::from_value(result)
+        .map_err(|e| IcarusError::JsonError(format!("Invalid CallToolResult: {e}")))
+}
+
+/// Decodes the text content of a [`CallToolResult`] into `T`.
+///
+/// Text that is not valid JSON is decoded as a JSON string, so tools returning
+/// plain strings work with `T = String`.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ToolExecutionFailed` when the result is flagged as an
+/// error, and `IcarusError::JsonError` when decoding fails.
+pub fn decode_result<T: DeserializeOwned>(tool_name: &str, result: &CallToolResult) -> Result<T> {
+    let text: String = result
+        .content
+        .iter()
+        .filter_map(|content| content.as_text().map(|t| t.text.as_str()))
+        .collect();
+
+    if result.is_error == Some(true) {
+        let tool_id = crate::ToolId::new(tool_name)?;
+        return Err(IcarusError::tool_execution_failed(
+            tool_id,
+            IcarusError::ExternalServiceError {
+                service: tool_name.to_string(),
+                message: text,
+            },
+        ));
+    }
+
+    serde_json::from_str(&text)
+        .or_else(|_| serde_json::from_value(serde_json::Value::String(text)))
+        .map_err(|e| IcarusError::JsonError(format!("Failed to decode tool output: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Content;
+
+    fn text_result(text: &str, is_error: bool) -> CallToolResult {
+        CallToolResult {
+            content: vec![Content::text(text)],
+            structured_content: None,
+            is_error: Some(is_error),
+            meta: None,
+        }
+    }
+
+    #[test]
+    fn test_build_call_request() {
+        let request = build_call_request("add", &serde_json::json!({"a": 1, "b": 2})).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&request).unwrap();
+
+        assert_eq!(json["method"], "tools/call");
+        assert_eq!(json["params"]["name"], "add");
+        assert_eq!(json["params"]["arguments"]["b"], 2);
+    }
+
+    #[test]
+    fn test_parse_success_response() {
+        let response = r#"{"jsonrpc":"2.0","id":"1","result":{"content":[{"type":"text","text":"3"}],"isError":false}}"#;
+        let result = parse_call_response(response).unwrap();
+        let value: f64 = decode_result("add", &result).unwrap();
+        assert!((value - 3.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_parse_error_response() {
+        let response = r#"{"jsonrpc":"2.0","id":"1","error":{"code":-32601,"message":"Tool not found: nope"}}"#;
+        let err = parse_call_response(response).unwrap_err();
+        assert!(matches!(err, IcarusError::JsonRpcError(ref e) if e.code == -32601));
+    }
+
+    #[test]
+    fn test_decode_plain_string() {
+        let value: String = decode_result("greet", &text_result("Hello, world!", false)).unwrap();
+        assert_eq!(value, "Hello, world!");
+    }
+
+    #[test]
+    fn test_decode_tool_error() {
+        let err = decode_result::<String>("greet", &text_result("boom", true)).unwrap_err();
+        assert!(matches!(err, IcarusError::ToolExecutionFailed { .. }));
+    }
+
+    #[test]
+    fn test_client_configuration() {
+        let target = CanisterId::from_text("rrkah-fqaaa-aaaaa-aaaaq-cai").unwrap();
+        let client = RemoteToolClient::new(target);
+        assert_eq!(client.timeout_secs(), Some(DEFAULT_CALL_TIMEOUT_SECS));
+        assert_eq!(client.with_timeout_secs(5).timeout_secs(), Some(5));
+        assert_eq!(client.without_timeout().timeout_secs(), None);
+        assert_eq!(client.canister_id(), target);
+    }
+}
@@ -0,0 +1,317 @@
+//! Serialization codecs for stored values.
+//!
+//! `#[derive(IcarusStorable)]` encodes values with Candid by default.
+//! Candid is self-describing, which makes large structs bulky and slow to
+//! encode; `#[icarus_storable(codec = "cbor")]` and `codec = "bincode"` switch
+//! to the codecs in this module (`cbor` and `bincode` features).
+//!
+//! Stored bytes are not tagged with their codec, so changing the codec of an
+//! existing map requires rewriting it with [`migrate`] before the new type
+//! reads it, typically in `post_upgrade`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::codec::{self, Codec};
+//!
+//! #[derive(candid::CandidType, serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+//! struct Note {
+//!     title: String,
+//! }
+//!
+//! let note = Note { title: "draft".to_string() };
+//! let bytes = Codec::Candid.encode(&note).unwrap();
+//! assert_eq!(Codec::Candid.decode::<Note>(&bytes).unwrap(), note);
+//! assert_eq!("cbor".parse::<Codec>().unwrap(), Codec::Cbor);
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use candid::CandidType;
+use ic_stable_structures::{Memory, StableBTreeMap, Storable};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{IcarusError, Result};
+
+/// A serialization format for stored values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// Candid, the default.
+    Candid,
+    /// CBOR via `ciborium` (`cbor` feature).
+    Cbor,
+    /// Bincode (`bincode` feature).
+    Bincode,
+}
+
+impl Codec {
+    /// Encodes `value` with this codec.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::CandidError` or `IcarusError::SerializationError`
+    /// if encoding fails, or the latter if the codec's feature is disabled.
+    pub fn encode<T: CandidType + Serialize>(self, value: &T) -> Result<Vec<u8>> {
+        match self {
+            Self::Candid => {
+                candid::encode_one(value).map_err(|e| IcarusError::CandidError(e.to_string()))
+            }
+            Self::Cbor => cbor::encode(value),
+            Self::Bincode => bincode::encode(value),
+        }
+    }
+
+    /// Decodes a value encoded with this codec.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::CandidError` or `IcarusError::SerializationError`
+    /// if decoding fails, or the latter if the codec's feature is disabled.
+    pub fn decode<T: CandidType + DeserializeOwned>(self, bytes: &[u8]) -> Result<T> {
+        match self {
+            Self::Candid => {
+                candid::decode_one(bytes).map_err(|e| IcarusError::CandidError(e.to_string()))
+            }
+            Self::Cbor => cbor::decode(bytes),
+            Self::Bincode => bincode::decode(bytes),
+        }
+    }
+}
+
+impl FromStr for Codec {
+    type Err = IcarusError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "candid" => Ok(Self::Candid),
+            "cbor" => Ok(Self::Cbor),
+            "bincode" => Ok(Self::Bincode),
+            _ => Err(IcarusError::ConfigurationError(format!(
+                "Unknown codec '{s}', expected candid, cbor or bincode"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Candid => "candid",
+            Self::Cbor => "cbor",
+            Self::Bincode => "bincode",
+        })
+    }
+}
+
+/// CBOR encoding, used by `#[icarus_storable(codec = "cbor")]`.
+pub mod cbor {
+    use super::{DeserializeOwned, IcarusError, Result, Serialize};
+
+    /// Encodes `value` as CBOR.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::SerializationError` if encoding fails or the
+    /// `cbor` feature is disabled.
+    pub fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+        #[cfg(feature = "cbor")]
+        {
+            let mut bytes = Vec::new();
+            ciborium::into_writer(value, &mut bytes)
+                .map_err(|e| IcarusError::SerializationError(format!("cbor: {e}")))?;
+            Ok(bytes)
+        }
+        #[cfg(not(feature = "cbor"))]
+        {
+            let _ = value;
+            Err(disabled())
+        }
+    }
+
+    /// Decodes a CBOR value.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::SerializationError` if decoding fails or the
+    /// `cbor` feature is disabled.
+    pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+        #[cfg(feature = "cbor")]
+        {
+            ciborium::from_reader(bytes)
+                .map_err(|e| IcarusError::SerializationError(format!("cbor: {e}")))
+        }
+        #[cfg(not(feature = "cbor"))]
+        {
+            let _ = bytes;
+            Err(disabled())
+        }
+    }
+
+    #[cfg(not(feature = "cbor"))]
+    fn disabled() -> IcarusError {
+        IcarusError::SerializationError("cbor: enable the `cbor` feature".to_string())
+    }
+}
+
+/// Bincode encoding, used by `#[icarus_storable(codec = "bincode")]`.
+pub mod bincode {
+    use super::{DeserializeOwned, IcarusError, Result, Serialize};
+
+    /// Encodes `value` with bincode.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::SerializationError` if encoding fails or the
+    /// `bincode` feature is disabled.
+    pub fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+        #[cfg(feature = "bincode")]
+        {
+            ::bincode::serialize(value)
+                .map_err(|e| IcarusError::SerializationError(format!("bincode: {e}")))
+        }
+        #[cfg(not(feature = "bincode"))]
+        {
+            let _ = value;
+            Err(disabled())
+        }
+    }
+
+    /// Decodes a bincode value.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::SerializationError` if decoding fails or the
+    /// `bincode` feature is disabled.
+    pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+        #[cfg(feature = "bincode")]
+        {
+            ::bincode::deserialize(bytes)
+                .map_err(|e| IcarusError::SerializationError(format!("bincode: {e}")))
+        }
+        #[cfg(not(feature = "bincode"))]
+        {
+            let _ = bytes;
+            Err(disabled())
+        }
+    }
+
+    #[cfg(not(feature = "bincode"))]
+    fn disabled() -> IcarusError {
+        IcarusError::SerializationError("bincode: enable the `bincode` feature".to_string())
+    }
+}
+
+/// Re-encodes every value of a map from one codec to another and returns how
+/// many values were rewritten.
+///
+/// Open the map's memory with raw `Vec<u8>` values, which share the layout of
+/// any unbounded `IcarusStorable` type, before opening it with the new type.
+///
+/// # Errors
+///
+/// Returns the first decoding or encoding error. Values rewritten before the
+/// error keep the new encoding.
+///
+/// # Examples
+///
+/// ```rust
+/// use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap};
+/// use icarus_core::codec::{self, Codec};
+///
+/// let mut raw: StableBTreeMap<u64, Vec<u8>, _> = StableBTreeMap::init(DefaultMemoryImpl::default());
+/// raw.insert(1, Codec::Candid.encode(&"note".to_string()).unwrap());
+///
+/// // Nothing to convert when the codec stays the same
+/// assert_eq!(codec::migrate::<_, String, _>(&mut raw, Codec::Candid, Codec::Candid).unwrap(), 0);
+/// ```
+pub fn migrate<K, T, M>(
+    map: &mut StableBTreeMap<K, Vec<u8>, M>,
+    from: Codec,
+    to: Codec,
+) -> Result<u64>
+where
+    K: Storable + Ord + Clone,
+    T: CandidType + Serialize + DeserializeOwned,
+    M: Memory,
+{
+    if from == to {
+        return Ok(0);
+    }
+
+    let keys: Vec<K> = map.iter().map(|entry| entry.key().clone()).collect();
+    let mut migrated = 0;
+    for key in keys {
+        let Some(bytes) = map.get(&key) else {
+            continue;
+        };
+        let value: T = from.decode(&bytes)?;
+        map.insert(key, to.encode(&value)?);
+        migrated += 1;
+    }
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, CandidType, Serialize, Deserialize)]
+    struct Record {
+        id: u64,
+        tags: Vec<String>,
+    }
+
+    fn record(id: u64) -> Record {
+        Record {
+            id,
+            tags: vec!["a".to_string(), "b".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_candid_round_trip_and_parse() {
+        let bytes = Codec::Candid.encode(&record(1)).unwrap();
+        assert_eq!(Codec::Candid.decode::<Record>(&bytes).unwrap(), record(1));
+        assert!("json".parse::<Codec>().is_err());
+        assert_eq!(Codec::Bincode.to_string(), "bincode");
+    }
+
+    #[cfg(all(feature = "cbor", feature = "bincode"))]
+    #[test]
+    fn test_migrate_between_codecs() {
+        use ic_stable_structures::DefaultMemoryImpl;
+
+        let mut map: StableBTreeMap<u64, Vec<u8>, _> =
+            StableBTreeMap::init(DefaultMemoryImpl::default());
+        for id in 0..3 {
+            map.insert(id, Codec::Candid.encode(&record(id)).unwrap());
+        }
+
+        assert_eq!(
+            migrate::<_, Record, _>(&mut map, Codec::Candid, Codec::Cbor).unwrap(),
+            3
+        );
+        let stored = map.get(&2).unwrap();
+        assert_eq!(cbor::decode::<Record>(&stored).unwrap(), record(2));
+        assert!(Codec::Candid.decode::<Record>(&stored).is_err());
+
+        assert_eq!(
+            migrate::<_, Record, _>(&mut map, Codec::Cbor, Codec::Bincode).unwrap(),
+            3
+        );
+        assert_eq!(
+            bincode::decode::<Record>(&map.get(&0).unwrap()).unwrap(),
+            record(0)
+        );
+    }
+
+    #[cfg(not(feature = "cbor"))]
+    #[test]
+    fn test_disabled_codec_reports_feature() {
+        let err = Codec::Cbor.encode(&record(1)).unwrap_err();
+        assert!(err.to_string().contains("`cbor` feature"));
+    }
+}
@@ -0,0 +1,525 @@
+//! Stable collections: an append-only [`StorageLog`], an indexed
+//! [`StorageVec`] and a priority queue, [`StableMinHeap`].
+//!
+//! Keeping a `Vec<T>` in a cell rewrites the whole vector on every push and
+//! loses it on upgrade unless it is serialized by hand. These wrappers store
+//! each element separately in stable memory, so appends and removals touch one
+//! entry and the data survives upgrades.
+//!
+//! Both are keyed by a `u64` position in a `StableBTreeMap` rather than built
+//! on the library's `Log` and `Vec`, which cannot drop their oldest entries.
+//! That is what lets a log keep bounded retention with
+//! [`StorageLog::with_max_entries`].
+//!
+//! [`StableMinHeap`] keeps keys ordered by a priority such as a due time, so
+//! the smallest is found without scanning, and a key's priority can be
+//! changed or removed without knowing where it sits.
+//!
+//! All use interior mutability, so they can live directly in a `thread_local!`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::collections::{StorageLog, StorageVec};
+//! use icarus_core::memory::FIRST_USER_MEMORY_ID;
+//!
+//! thread_local! {
+//!     static ACTIVITY: StorageLog<String> =
+//!         StorageLog::init(FIRST_USER_MEMORY_ID).with_max_entries(2);
+//!     static QUEUE: StorageVec<u64> = StorageVec::init(FIRST_USER_MEMORY_ID + 1);
+//! }
+//!
+//! ACTIVITY.with(|log| {
+//!     log.append("created".to_string());
+//!     log.append("renamed".to_string());
+//!     log.append("deleted".to_string());
+//!
+//!     // Only the newest two entries are retained
+//!     let entries: Vec<String> = log.iter().map(|(_, entry)| entry).collect();
+//!     assert_eq!(entries, ["renamed", "deleted"]);
+//! });
+//!
+//! QUEUE.with(|queue| {
+//!     queue.push(7);
+//!     queue.push(9);
+//!     assert_eq!(queue.get(1), Some(9));
+//!     assert_eq!(queue.pop(), Some(9));
+//! });
+//! ```
+//!
+//! ```rust
+//! use icarus_core::collections::StableMinHeap;
+//! use icarus_core::memory::FIRST_USER_MEMORY_ID;
+//!
+//! let deadlines = StableMinHeap::<String, u64>::init(FIRST_USER_MEMORY_ID);
+//! deadlines.push("report".to_string(), 300);
+//! deadlines.push("backup".to_string(), 100);
+//! deadlines.push("report".to_string(), 50);
+//!
+//! assert_eq!(deadlines.peek(), Some(("report".to_string(), 50)));
+//! assert_eq!(deadlines.pop_if_at_most(&40), None);
+//! assert_eq!(deadlines.pop(), Some(("report".to_string(), 50)));
+//! assert_eq!(deadlines.len(), 1);
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+
+use crate::memory::{self, StableMemory};
+
+/// An append-only stable log with optional bounded retention.
+///
+/// Each entry gets a sequence number that keeps increasing even as old
+/// entries are evicted, so clients can page through the log with
+/// [`StorageLog::since`].
+pub struct StorageLog<T: Storable> {
+    entries: RefCell<StableBTreeMap<u64, T, StableMemory>>,
+    max_entries: Option<u64>,
+}
+
+impl<T: Storable> StorageLog<T> {
+    /// Opens the log stored in virtual memory `memory_id`.
+    #[must_use]
+    pub fn init(memory_id: u8) -> Self {
+        Self {
+            entries: RefCell::new(StableBTreeMap::init(memory::get_memory(memory_id))),
+            max_entries: None,
+        }
+    }
+
+    /// Keeps only the newest `max_entries` entries, evicting older ones on append.
+    #[must_use]
+    pub fn with_max_entries(mut self, max_entries: u64) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Appends an entry and returns its sequence number.
+    pub fn append(&self, entry: T) -> u64 {
+        let mut entries = self.entries.borrow_mut();
+        let seq = entries.last_key_value().map_or(0, |(seq, _)| seq + 1);
+        entries.insert(seq, entry);
+
+        if let Some(max_entries) = self.max_entries {
+            let oldest_kept = (seq + 1).saturating_sub(max_entries);
+            evict_before(&mut entries, oldest_kept);
+        }
+        seq
+    }
+
+    /// Returns the entry with sequence number `seq`, if still retained.
+    #[must_use]
+    pub fn get(&self, seq: u64) -> Option<T> {
+        self.entries.borrow().get(&seq)
+    }
+
+    /// Returns the number of retained entries.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.entries.borrow().len()
+    }
+
+    /// Returns true if no entries are retained.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+
+    /// Returns the retained entries, oldest first, with their sequence numbers.
+    #[must_use]
+    pub fn iter(&self) -> std::vec::IntoIter<(u64, T)> {
+        self.since(0)
+    }
+
+    /// Returns the entries from sequence number `seq` onwards, oldest first.
+    #[must_use]
+    pub fn since(&self, seq: u64) -> std::vec::IntoIter<(u64, T)> {
+        let entries: Vec<_> = self
+            .entries
+            .borrow()
+            .range(seq..)
+            .map(|entry| (*entry.key(), entry.value()))
+            .collect();
+        entries.into_iter()
+    }
+
+    /// Returns up to `limit` of the oldest entries, oldest first.
+    #[must_use]
+    pub fn oldest(&self, limit: u64) -> Vec<(u64, T)> {
+        self.entries
+            .borrow()
+            .iter()
+            .take(usize::try_from(limit).unwrap_or(usize::MAX))
+            .map(|entry| (*entry.key(), entry.value()))
+            .collect()
+    }
+
+    /// Returns up to `limit` of the newest entries, newest first.
+    #[must_use]
+    pub fn latest(&self, limit: u64) -> Vec<(u64, T)> {
+        let entries = self.entries.borrow();
+        // Sequence numbers are contiguous since eviction only trims the front
+        let Some((last, _)) = entries.last_key_value() else {
+            return Vec::new();
+        };
+        let mut latest: Vec<_> = entries
+            .range((last + 1).saturating_sub(limit)..)
+            .map(|entry| (*entry.key(), entry.value()))
+            .collect();
+        latest.reverse();
+        latest
+    }
+
+    /// Removes all entries older than sequence number `seq`.
+    pub fn truncate_before(&self, seq: u64) {
+        evict_before(&mut self.entries.borrow_mut(), seq);
+    }
+
+    /// Removes all entries. Sequence numbers restart from zero.
+    pub fn clear(&self) {
+        let mut entries = self.entries.borrow_mut();
+        let all: Vec<u64> = entries.iter().map(|entry| *entry.key()).collect();
+        for key in all {
+            entries.remove(&key);
+        }
+    }
+}
+
+fn evict_before<T: Storable>(entries: &mut StableBTreeMap<u64, T, StableMemory>, seq: u64) {
+    let stale: Vec<u64> = entries.range(..seq).map(|entry| *entry.key()).collect();
+    for key in stale {
+        entries.remove(&key);
+    }
+}
+
+/// A growable stable vector addressed by index.
+pub struct StorageVec<T: Storable> {
+    elements: RefCell<StableBTreeMap<u64, T, StableMemory>>,
+}
+
+impl<T: Storable> StorageVec<T> {
+    /// Opens the vector stored in virtual memory `memory_id`.
+    #[must_use]
+    pub fn init(memory_id: u8) -> Self {
+        Self {
+            elements: RefCell::new(StableBTreeMap::init(memory::get_memory(memory_id))),
+        }
+    }
+
+    /// Appends an element.
+    pub fn push(&self, element: T) {
+        let mut elements = self.elements.borrow_mut();
+        let len = elements.len();
+        elements.insert(len, element);
+    }
+
+    /// Removes and returns the last element.
+    pub fn pop(&self) -> Option<T> {
+        let mut elements = self.elements.borrow_mut();
+        let last = elements.len().checked_sub(1)?;
+        elements.remove(&last)
+    }
+
+    /// Returns the element at `index`.
+    #[must_use]
+    pub fn get(&self, index: u64) -> Option<T> {
+        self.elements.borrow().get(&index)
+    }
+
+    /// Replaces the element at `index`, returning the previous one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&self, index: u64, element: T) -> T {
+        let mut elements = self.elements.borrow_mut();
+        let len = elements.len();
+        assert!(index < len, "index {index} out of bounds for length {len}");
+        elements
+            .insert(index, element)
+            .unwrap_or_else(|| unreachable!("indices below len are occupied"))
+    }
+
+    /// Returns the number of elements.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.elements.borrow().len()
+    }
+
+    /// Returns true if the vector has no elements.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.elements.borrow().is_empty()
+    }
+
+    /// Returns the elements in index order.
+    #[must_use]
+    pub fn iter(&self) -> std::vec::IntoIter<T> {
+        let elements: Vec<_> = self
+            .elements
+            .borrow()
+            .iter()
+            .map(|entry| entry.value())
+            .collect();
+        elements.into_iter()
+    }
+
+    /// Shortens the vector to `len` elements, dropping the rest.
+    pub fn truncate(&self, len: u64) {
+        let mut elements = self.elements.borrow_mut();
+        let tail: Vec<u64> = elements.range(len..).map(|entry| *entry.key()).collect();
+        for key in tail {
+            elements.remove(&key);
+        }
+    }
+
+    /// Removes all elements.
+    pub fn clear(&self) {
+        self.truncate(0);
+    }
+}
+
+/// A stable priority queue of unique keys, smallest priority first.
+///
+/// Each key is stored twice in one `StableBTreeMap`: once ordered by
+/// `(priority, key)` to find the minimum, and once by key to find its
+/// current priority. Every operation is `O(log n)`.
+pub struct StableMinHeap<K, P>
+where
+    K: Storable + Ord + Clone,
+    P: Storable + Ord + Clone,
+{
+    entries: RefCell<StableBTreeMap<HeapKey<K, P>, P, StableMemory>>,
+}
+
+/// Key of a [`StableMinHeap`] entry. `Order` entries sort before all `Index`
+/// entries, so the first entry of the map is the minimum.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum HeapKey<K, P> {
+    Order(P, K),
+    Index(K),
+}
+
+const ORDER_TAG: u8 = 0;
+const INDEX_TAG: u8 = 1;
+
+impl<K: Storable + Clone, P: Storable + Clone> Storable for HeapKey<K, P> {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(self.clone().into_bytes())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        match bytes.first() {
+            Some(&ORDER_TAG) => {
+                let (len, rest) = bytes[1..].split_at(4);
+                let len = u32::from_le_bytes(len.try_into().unwrap_or_default()) as usize;
+                let (priority, key) = rest.split_at(len);
+                Self::Order(
+                    P::from_bytes(Cow::Borrowed(priority)),
+                    K::from_bytes(Cow::Borrowed(key)),
+                )
+            }
+            Some(&INDEX_TAG) => Self::Index(K::from_bytes(Cow::Borrowed(&bytes[1..]))),
+            _ => unreachable!("corrupt heap key"),
+        }
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Self::Order(priority, key) => {
+                let priority = priority.into_bytes();
+                let key = key.into_bytes();
+                let len = u32::try_from(priority.len())
+                    .unwrap_or_else(|_| unreachable!("priority larger than 4 GiB"));
+                let mut bytes = Vec::with_capacity(5 + priority.len() + key.len());
+                bytes.push(ORDER_TAG);
+                bytes.extend_from_slice(&len.to_le_bytes());
+                bytes.extend(priority);
+                bytes.extend(key);
+                bytes
+            }
+            Self::Index(key) => {
+                let mut bytes = vec![INDEX_TAG];
+                bytes.extend(key.into_bytes());
+                bytes
+            }
+        }
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl<K, P> StableMinHeap<K, P>
+where
+    K: Storable + Ord + Clone,
+    P: Storable + Ord + Clone,
+{
+    /// Opens the heap stored in virtual memory `memory_id`.
+    #[must_use]
+    pub fn init(memory_id: u8) -> Self {
+        Self {
+            entries: RefCell::new(StableBTreeMap::init(memory::get_memory(memory_id))),
+        }
+    }
+
+    /// Inserts `key` with `priority`, or moves it to `priority` if present.
+    /// Returns its previous priority.
+    pub fn push(&self, key: K, priority: P) -> Option<P> {
+        let mut entries = self.entries.borrow_mut();
+        let previous = entries.insert(HeapKey::Index(key.clone()), priority.clone());
+        if let Some(previous) = &previous {
+            entries.remove(&HeapKey::Order(previous.clone(), key.clone()));
+        }
+        entries.insert(HeapKey::Order(priority.clone(), key), priority);
+        previous
+    }
+
+    /// Returns the key with the smallest priority, ties broken by key.
+    #[must_use]
+    pub fn peek(&self) -> Option<(K, P)> {
+        match self.entries.borrow().first_key_value() {
+            Some((HeapKey::Order(priority, key), _)) => Some((key, priority)),
+            _ => None,
+        }
+    }
+
+    /// Removes and returns the key with the smallest priority.
+    pub fn pop(&self) -> Option<(K, P)> {
+        let (key, priority) = self.peek()?;
+        self.remove(&key);
+        Some((key, priority))
+    }
+
+    /// Removes and returns the smallest key if its priority is at most `max`,
+    /// e.g. the next task due by now.
+    pub fn pop_if_at_most(&self, max: &P) -> Option<(K, P)> {
+        let (key, priority) = self.peek()?;
+        if priority > *max {
+            return None;
+        }
+        self.remove(&key);
+        Some((key, priority))
+    }
+
+    /// Removes `key`, returning its priority.
+    pub fn remove(&self, key: &K) -> Option<P> {
+        let mut entries = self.entries.borrow_mut();
+        let priority = entries.remove(&HeapKey::Index(key.clone()))?;
+        entries.remove(&HeapKey::Order(priority.clone(), key.clone()));
+        Some(priority)
+    }
+
+    /// Returns the priority of `key`.
+    #[must_use]
+    pub fn priority(&self, key: &K) -> Option<P> {
+        self.entries.borrow().get(&HeapKey::Index(key.clone()))
+    }
+
+    /// Returns the number of keys.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.entries.borrow().len() / 2
+    }
+
+    /// Returns true if the heap has no keys.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+
+    /// Returns the keys with their priorities, smallest first.
+    #[must_use]
+    pub fn iter(&self) -> std::vec::IntoIter<(K, P)> {
+        let entries: Vec<_> = self
+            .entries
+            .borrow()
+            .iter()
+            .map_while(|entry| match entry.key() {
+                HeapKey::Order(priority, key) => Some((key.clone(), priority.clone())),
+                HeapKey::Index(_) => None,
+            })
+            .collect();
+        entries.into_iter()
+    }
+
+    /// Removes all keys.
+    pub fn clear(&self) {
+        let mut entries = self.entries.borrow_mut();
+        let all: Vec<_> = entries.iter().map(|entry| entry.key().clone()).collect();
+        for key in all {
+            entries.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::FIRST_USER_MEMORY_ID;
+
+    #[test]
+    fn test_log_retention_keeps_sequence_numbers() {
+        let log = StorageLog::<u64>::init(FIRST_USER_MEMORY_ID + 40).with_max_entries(3);
+        for value in 0..5 {
+            assert_eq!(log.append(value * 10), value);
+        }
+
+        assert_eq!(log.len(), 3);
+        assert!(log.get(1).is_none());
+        assert_eq!(log.since(3).collect::<Vec<_>>(), [(3, 30), (4, 40)]);
+        assert_eq!(log.latest(1), [(4, 40)]);
+
+        log.truncate_before(4);
+        assert_eq!(log.iter().collect::<Vec<_>>(), [(4, 40)]);
+        assert_eq!(log.append(50), 5);
+    }
+
+    #[test]
+    fn test_vec_push_pop_truncate() {
+        let vec = StorageVec::<String>::init(FIRST_USER_MEMORY_ID + 41);
+        for word in ["a", "b", "c", "d"] {
+            vec.push(word.to_string());
+        }
+
+        assert_eq!(vec.set(1, "B".to_string()), "b");
+        assert_eq!(vec.pop().as_deref(), Some("d"));
+        vec.truncate(2);
+        assert_eq!(vec.iter().collect::<Vec<_>>(), ["a", "B"]);
+
+        vec.push("e".to_string());
+        assert_eq!(vec.get(2).as_deref(), Some("e"));
+
+        vec.clear();
+        assert!(vec.is_empty());
+        assert!(vec.pop().is_none());
+    }
+
+    #[test]
+    fn test_min_heap_orders_and_reprioritizes() {
+        let heap = StableMinHeap::<String, u64>::init(FIRST_USER_MEMORY_ID + 42);
+        for (key, priority) in [("c", 30), ("a", 10), ("b", 20), ("d", 10)] {
+            assert!(heap.push(key.to_string(), priority).is_none());
+        }
+        assert_eq!(heap.len(), 4);
+
+        // Moving a key drops its old position
+        assert_eq!(heap.push("c".to_string(), 5), Some(30));
+        assert_eq!(heap.priority(&"c".to_string()), Some(5));
+        assert_eq!(heap.remove(&"b".to_string()), Some(20));
+        assert!(heap.remove(&"b".to_string()).is_none());
+
+        let order: Vec<_> = heap.iter().map(|(key, _)| key).collect();
+        assert_eq!(order, ["c", "a", "d"]);
+
+        assert_eq!(heap.pop(), Some(("c".to_string(), 5)));
+        assert!(heap.pop_if_at_most(&9).is_none());
+        assert_eq!(heap.pop_if_at_most(&10), Some(("a".to_string(), 10)));
+        assert_eq!(heap.len(), 1);
+
+        heap.clear();
+        assert!(heap.is_empty());
+        assert!(heap.peek().is_none());
+    }
+}
@@ -0,0 +1,240 @@
+//! Composite query tools and the inter-canister calls they make.
+//!
+//! `#[tool(composite_query)]` marks a read-only tool that fans out to other
+//! canisters. `mcp!{}` canisters run such tools from
+//! [`COMPOSITE_METHOD`], a composite query, besides `mcp_call_tool`, and the
+//! bridge calls it as a query for tools whose input schema carries
+//! [`SCHEMA_KEY`]. Canisters built before the endpoint existed are called
+//! through `mcp_call_tool` instead.
+//!
+//! A composite query answers in one round without consensus, but everything
+//! it changes is discarded when it returns, and it can only call queries of
+//! canisters on the same subnet. The endpoint therefore refuses tools not
+//! marked composite and tools given a price at runtime, and skips charging
+//! and the usage, health and tool list bookkeeping; the macro refuses paid,
+//! encrypted and job tools.
+//!
+//! Tools make their calls with [`call`]. Within a composite query it is a
+//! query call to the target, so the fan-out stays on the query path; when the
+//! same tool runs as an update, it is a bounded-wait call like the rest of
+//! Icarus. [`is_active`] tells which path the current call is on. Off-chain
+//! the calls go to a handler installed with [`set_call_handler`].
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use icarus_core::composite;
+//!
+//! #[tool("Sum the balances of an account across ledgers", composite_query)]
+//! async fn total_balance(account: Principal, ledgers: Vec<Principal>) -> Result<u64, String> {
+//!     let mut total = 0;
+//!     for ledger in ledgers {
+//!         let balance: u64 = composite::call(ledger, "balance_of", &account)
+//!             .await
+//!             .map_err(|e| e.to_string())?;
+//!         total += balance;
+//!     }
+//!     Ok(total)
+//! }
+//! ```
+
+use std::cell::{Cell, RefCell};
+
+use candid::utils::ArgumentEncoder;
+use candid::{CandidType, Principal};
+use rustc_hash::FxHashSet;
+use serde::de::DeserializeOwned;
+
+use crate::{IcarusError, Result};
+
+/// Composite query method `mcp!{}` serves composite query tools from.
+pub const COMPOSITE_METHOD: &str = "mcp_call_tool_composite";
+
+/// Input schema key set to `true` on composite query tools.
+pub const SCHEMA_KEY: &str = "x-icarus-composite-query";
+
+/// An inter-canister call made off-chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutgoingCall {
+    /// Canister called.
+    pub canister: Principal,
+    /// Method called.
+    pub method: String,
+    /// Candid-encoded argument.
+    pub arg: Vec<u8>,
+    /// Whether the call was made within a composite query.
+    pub in_composite_query: bool,
+}
+
+/// Handler that answers inter-canister calls off-chain with the
+/// Candid-encoded reply.
+#[cfg(not(feature = "ic-canister"))]
+pub type CallHandler = Box<dyn Fn(&OutgoingCall) -> Result<Vec<u8>>>;
+
+thread_local! {
+    static COMPOSITE_TOOLS: RefCell<FxHashSet<String>> = RefCell::new(FxHashSet::default());
+
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+}
+
+#[cfg(not(feature = "ic-canister"))]
+thread_local! {
+    static CALL_HANDLER: RefCell<Option<CallHandler>> = const { RefCell::new(None) };
+}
+
+/// Marks a tool as runnable as a composite query.
+pub fn mark_composite(tool: &str) {
+    COMPOSITE_TOOLS.with(|tools| {
+        tools.borrow_mut().insert(tool.to_string());
+    });
+}
+
+/// Returns whether a tool is runnable as a composite query.
+#[must_use]
+pub fn is_composite(tool: &str) -> bool {
+    COMPOSITE_TOOLS.with(|tools| tools.borrow().contains(tool))
+}
+
+/// Records whether the current tool call runs as a composite query. Set by
+/// the [`COMPOSITE_METHOD`] endpoint around the tool.
+pub fn set_active(active: bool) {
+    ACTIVE.with(|cell| cell.set(active));
+}
+
+/// Returns whether the current tool call runs as a composite query.
+#[must_use]
+pub fn is_active() -> bool {
+    ACTIVE.with(Cell::get)
+}
+
+/// Installs the handler for off-chain inter-canister calls on the current
+/// thread, or removes it with `None`.
+#[cfg(not(feature = "ic-canister"))]
+pub fn set_call_handler(handler: Option<CallHandler>) {
+    CALL_HANDLER.with(|cell| *cell.borrow_mut() = handler);
+}
+
+/// Calls `method` of `canister` with one argument and decodes the reply.
+///
+/// Within a composite query `method` must be a query or composite query of a
+/// canister on the same subnet.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ExternalServiceError` if the call fails or,
+/// off-chain, if no handler is installed, and `IcarusError::CandidError` if
+/// the argument or reply does not match the types.
+pub async fn call<A, R>(canister: Principal, method: &str, arg: &A) -> Result<R>
+where
+    A: CandidType,
+    R: CandidType + DeserializeOwned,
+{
+    call_with_args(canister, method, (arg,)).await
+}
+
+/// Like [`call`], for methods taking several arguments or none.
+///
+/// # Errors
+///
+/// See [`call`].
+#[cfg(not(feature = "ic-canister"))]
+#[allow(clippy::unused_async)]
+pub async fn call_with_args<A, R>(canister: Principal, method: &str, args: A) -> Result<R>
+where
+    A: ArgumentEncoder,
+    R: CandidType + DeserializeOwned,
+{
+    let arg = candid::encode_args(args).map_err(|e| IcarusError::CandidError(e.to_string()))?;
+    let request = OutgoingCall {
+        canister,
+        method: method.to_string(),
+        arg,
+        in_composite_query: is_active(),
+    };
+    let reply = CALL_HANDLER.with(|cell| match cell.borrow().as_ref() {
+        Some(handler) => handler(&request),
+        None => Err(call_error(
+            canister,
+            method,
+            "inter-canister calls are unavailable outside a canister; install a mock handler",
+        )),
+    })?;
+    candid::decode_one(&reply).map_err(|e| IcarusError::CandidError(e.to_string()))
+}
+
+/// Like [`call`], for methods taking several arguments or none.
+///
+/// # Errors
+///
+/// See [`call`].
+#[cfg(feature = "ic-canister")]
+pub async fn call_with_args<A, R>(canister: Principal, method: &str, args: A) -> Result<R>
+where
+    A: ArgumentEncoder,
+    R: CandidType + DeserializeOwned,
+{
+    use ic_cdk::call::Call;
+
+    // Within a composite query the system makes this a query call to the
+    // target; it waits for the reply rather than risk a best-effort timeout
+    let call = if is_active() {
+        Call::unbounded_wait(canister, method)
+    } else {
+        Call::bounded_wait(canister, method)
+    };
+    call.with_args(&args)
+        .await
+        .map_err(|e| call_error(canister, method, e.to_string()))?
+        .candid::<R>()
+        .map_err(|e| IcarusError::CandidError(e.to_string()))
+}
+
+fn call_error(canister: Principal, method: &str, message: impl Into<String>) -> IcarusError {
+    IcarusError::ExternalServiceError {
+        service: format!("{canister}.{method}"),
+        message: message.into(),
+    }
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[tokio::test]
+    async fn test_calls_report_the_query_path() {
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&calls);
+        set_call_handler(Some(Box::new(move |call| {
+            recorded.borrow_mut().push(call.clone());
+            let balance: u64 = candid::decode_one(&call.arg).unwrap();
+            Ok(candid::encode_one(balance * 2).unwrap())
+        })));
+        let ledger = Principal::from_slice(&[3]);
+
+        let doubled: u64 = call(ledger, "double", &21_u64).await.unwrap();
+        assert_eq!(doubled, 42);
+        set_active(true);
+        let _: u64 = call(ledger, "double", &1_u64).await.unwrap();
+        set_active(false);
+
+        let calls = calls.borrow();
+        assert_eq!(calls[0].method, "double");
+        assert!(!calls[0].in_composite_query);
+        assert!(calls[1].in_composite_query);
+
+        assert!(matches!(
+            call::<_, String>(ledger, "double", &1_u64).await,
+            Err(IcarusError::CandidError(_))
+        ));
+        set_call_handler(None);
+        assert!(call::<_, u64>(ledger, "double", &1_u64).await.is_err());
+    }
+
+    #[test]
+    fn test_composite_tools_are_marked() {
+        assert!(!is_composite("total_balance"));
+        mark_composite("total_balance");
+        assert!(is_composite("total_balance"));
+    }
+}
@@ -0,0 +1,181 @@
+//! Transparent compression of stored values.
+//!
+//! `#[icarus_storable(compress = "lz4")]` passes every encoded value through
+//! [`compress`] on write and [`decompress`] on read. Values smaller than the
+//! threshold, or that LZ4 cannot shrink, are stored as they are, so short
+//! records pay one byte of overhead and no CPU time.
+//!
+//! Every stored value starts with a one-byte tag saying whether it is
+//! compressed. Adding or removing `compress` therefore changes the stored
+//! format, like switching codecs does; migrate existing data first.
+//!
+//! Each write is counted per type, and [`stats`] (also part of
+//! `memory::usage_report`) shows how much the compression saved. The counters
+//! live on the heap and start over after an upgrade.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::compression;
+//!
+//! let text = "the same sentence again and again. ".repeat(100).into_bytes();
+//! let stored = compression::compress("Note", text.clone(), compression::DEFAULT_THRESHOLD);
+//! assert_eq!(compression::decompress(&stored).unwrap(), text);
+//!
+//! let note = compression::stats().into_iter().find(|s| s.name == "Note").unwrap();
+//! assert_eq!(note.values, 1);
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+use crate::{IcarusError, Result};
+
+/// Encoded size from which values are compressed, unless overridden with
+/// `compress_threshold`.
+pub const DEFAULT_THRESHOLD: usize = 256;
+
+const RAW: u8 = 0;
+const LZ4: u8 = 1;
+
+/// Compression results for one stored type.
+#[derive(Debug, Clone, Default, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct CompressionStats {
+    /// Type name.
+    pub name: String,
+    /// Values written.
+    pub values: u64,
+    /// Values written compressed.
+    pub compressed_values: u64,
+    /// Encoded size of the values before compression.
+    pub raw_bytes: u64,
+    /// Size of the values as stored, including the tag byte.
+    pub stored_bytes: u64,
+}
+
+impl CompressionStats {
+    /// Returns stored size as a fraction of the raw size; lower is better.
+    #[must_use]
+    pub fn ratio(&self) -> f64 {
+        if self.raw_bytes == 0 {
+            1.0
+        } else {
+            self.stored_bytes as f64 / self.raw_bytes as f64
+        }
+    }
+}
+
+thread_local! {
+    static STATS: RefCell<BTreeMap<&'static str, CompressionStats>> =
+        const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Tags `bytes` and LZ4-compresses them if they are at least `threshold`
+/// bytes long and compression makes them smaller.
+///
+/// Without the `lz4` feature values are only tagged.
+#[must_use]
+pub fn compress(name: &'static str, bytes: Vec<u8>, threshold: usize) -> Vec<u8> {
+    let raw_len = bytes.len() as u64;
+    let stored = match lz4(&bytes, threshold) {
+        Some(compressed) => compressed,
+        None => {
+            let mut stored = Vec::with_capacity(bytes.len() + 1);
+            stored.push(RAW);
+            stored.extend_from_slice(&bytes);
+            stored
+        }
+    };
+
+    STATS.with(|stats| {
+        let mut stats = stats.borrow_mut();
+        let entry = stats.entry(name).or_insert_with(|| CompressionStats {
+            name: name.to_string(),
+            ..CompressionStats::default()
+        });
+        entry.values += 1;
+        entry.compressed_values += u64::from(stored[0] == LZ4);
+        entry.raw_bytes += raw_len;
+        entry.stored_bytes += stored.len() as u64;
+    });
+    stored
+}
+
+#[cfg(feature = "lz4")]
+fn lz4(bytes: &[u8], threshold: usize) -> Option<Vec<u8>> {
+    if bytes.len() < threshold {
+        return None;
+    }
+    let mut compressed = vec![LZ4];
+    compressed.extend_from_slice(&lz4_flex::compress_prepend_size(bytes));
+    (compressed.len() <= bytes.len()).then_some(compressed)
+}
+
+#[cfg(not(feature = "lz4"))]
+fn lz4(_bytes: &[u8], _threshold: usize) -> Option<Vec<u8>> {
+    None
+}
+
+/// Returns the encoded value stored by [`compress`].
+///
+/// # Errors
+///
+/// Returns `IcarusError::SerializationError` for an unknown tag, corrupt
+/// compressed data, or compressed data read without the `lz4` feature.
+pub fn decompress(stored: &[u8]) -> Result<Cow<'_, [u8]>> {
+    match stored.split_first() {
+        Some((&RAW, bytes)) => Ok(Cow::Borrowed(bytes)),
+        #[cfg(feature = "lz4")]
+        Some((&LZ4, bytes)) => lz4_flex::decompress_size_prepended(bytes)
+            .map(Cow::Owned)
+            .map_err(|e| IcarusError::SerializationError(format!("lz4: {e}"))),
+        #[cfg(not(feature = "lz4"))]
+        Some((&LZ4, _)) => Err(IcarusError::SerializationError(
+            "lz4: enable the `lz4` feature to read compressed values".to_string(),
+        )),
+        Some((tag, _)) => Err(IcarusError::SerializationError(format!(
+            "unknown compression tag {tag}"
+        ))),
+        None => Err(IcarusError::SerializationError(
+            "empty stored value".to_string(),
+        )),
+    }
+}
+
+/// Returns compression results per type, sorted by name.
+#[must_use]
+pub fn stats() -> Vec<CompressionStats> {
+    STATS.with(|stats| stats.borrow().values().cloned().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_values_are_stored_raw() {
+        let stored = compress("Small", b"tiny".to_vec(), DEFAULT_THRESHOLD);
+        assert_eq!(stored, b"\0tiny");
+        assert_eq!(decompress(&stored).unwrap(), &b"tiny"[..]);
+        assert!(decompress(&[7, 1, 2]).is_err());
+        assert!(decompress(&[]).is_err());
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_large_values_are_compressed() {
+        let text = "stable memory is not free. ".repeat(200).into_bytes();
+        let stored = compress("Large", text.clone(), DEFAULT_THRESHOLD);
+        assert_eq!(stored[0], LZ4);
+        assert!(stored.len() < text.len() / 4);
+        assert_eq!(decompress(&stored).unwrap(), text);
+
+        let large = stats().into_iter().find(|s| s.name == "Large").unwrap();
+        assert_eq!(large.compressed_values, 1);
+        assert!(large.ratio() < 0.25);
+    }
+}
@@ -0,0 +1,133 @@
+//! Rich tool result content: images, resources and mixed arrays.
+//!
+//! A tool that returns `Vec<Content>` has its content passed to the MCP client
+//! as-is instead of being wrapped in a single text item. The legacy
+//! [`ToolResult`](crate::LegacyToolResult) only carries strings, so the array
+//! travels through the runtime serialized as JSON and tagged with
+//! [`CONTENT_METADATA`]; `mcp_call_tool` turns it back into content with
+//! [`from_tool_result`].
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::content;
+//! use icarus_core::Content;
+//!
+//! fn chart() -> Vec<Content> {
+//!     vec![
+//!         Content::text("Revenue by month"),
+//!         content::image(&[0x89, b'P', b'N', b'G'], "image/png"),
+//!         content::resource("file:///reports/revenue.csv", "revenue.csv"),
+//!     ]
+//! }
+//!
+//! let result = content::to_tool_result(&chart());
+//! let content = content::from_tool_result(&result);
+//! assert_eq!(content.len(), 3);
+//! ```
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::json;
+
+use crate::{Content, LegacyToolResult};
+
+/// Metadata marking a successful result whose payload is a JSON content array.
+pub const CONTENT_METADATA: &str = r#"{"content":"mcp"}"#;
+
+/// Creates image content from raw bytes.
+#[must_use]
+pub fn image(data: &[u8], mime_type: &str) -> Content {
+    Content::image(STANDARD.encode(data), mime_type)
+}
+
+/// Creates a link to a resource the client can read by `uri`.
+#[must_use]
+pub fn resource(uri: &str, name: &str) -> Content {
+    from_wire(&json!({
+        "type": "resource_link",
+        "uri": uri,
+        "name": name,
+    }))
+}
+
+/// Creates an embedded binary resource.
+#[must_use]
+pub fn blob(uri: &str, data: &[u8], mime_type: &str) -> Content {
+    from_wire(&json!({
+        "type": "resource",
+        "resource": {
+            "uri": uri,
+            "mimeType": mime_type,
+            "blob": STANDARD.encode(data),
+        },
+    }))
+}
+
+/// Builds content from its MCP wire format, which is fixed by the spec.
+fn from_wire(value: &serde_json::Value) -> Content {
+    serde_json::from_value(value.clone())
+        .unwrap_or_else(|e| unreachable!("MCP content {value} must deserialize: {e}"))
+}
+
+/// Wraps a content array in a successful tool result.
+#[must_use]
+pub fn to_tool_result(content: &[Content]) -> LegacyToolResult<'static> {
+    // Serializing rmcp content cannot fail: it holds only strings and maps
+    let payload = serde_json::to_string(content).unwrap_or_default();
+    LegacyToolResult::success_with_metadata(payload, CONTENT_METADATA)
+}
+
+/// Returns the content of a successful tool result.
+///
+/// Results produced by [`to_tool_result`] yield their content array; any other
+/// result becomes a single text item. Non-success results yield no content.
+#[must_use]
+pub fn from_tool_result(result: &LegacyToolResult<'_>) -> Vec<Content> {
+    let LegacyToolResult::Success { result, metadata } = result else {
+        return Vec::new();
+    };
+
+    if metadata.as_deref() == Some(CONTENT_METADATA) {
+        if let Ok(content) = serde_json::from_str(result) {
+            return content;
+        }
+    }
+    vec![Content::text(result.as_ref())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mixed_content_round_trip() {
+        let content = vec![
+            Content::text("caption"),
+            image(b"GIF89a", "image/gif"),
+            resource("ic://blobs/report.pdf", "report.pdf"),
+            blob("ic://blobs/logo.png", &[1, 2, 3], "image/png"),
+        ];
+
+        let result = to_tool_result(&content);
+        assert!(result.is_success());
+
+        let wire = serde_json::to_value(from_tool_result(&result)).unwrap();
+        assert_eq!(wire[0]["type"], "text");
+        assert_eq!(wire[1]["type"], "image");
+        assert_eq!(wire[1]["data"], "R0lGODlh");
+        assert_eq!(wire[1]["mimeType"], "image/gif");
+        assert_eq!(wire[2]["type"], "resource_link");
+        assert_eq!(wire[2]["uri"], "ic://blobs/report.pdf");
+        assert_eq!(wire[3]["resource"]["blob"], "AQID");
+    }
+
+    #[test]
+    fn test_plain_results_become_text() {
+        let content = from_tool_result(&LegacyToolResult::success("[1, 2]"));
+        assert_eq!(content.len(), 1);
+        assert_eq!(serde_json::to_value(&content).unwrap()[0]["text"], "[1, 2]");
+
+        assert!(from_tool_result(&LegacyToolResult::error("boom")).is_empty());
+    }
+}
@@ -0,0 +1,237 @@
+//! The context of the tool call being executed.
+//!
+//! A `#[tool]` function may take a [`ToolContext`] (or `&ToolContext`) as its
+//! first parameter. The parameter is not part of the tool's schema; the macro
+//! fills it in with [`current_for`], which returns the context `mcp_call_tool`
+//! installed with [`set_current`] for the call, or one built from [`caller`]
+//! when the tool is called directly, e.g. from a unit test.
+//!
+//! Off-chain, [`caller`] returns the principal set with
+//! [`set_caller_override`], which is how `icarus-test`'s `MockEnvironment`
+//! lets tests call tools as a particular user without `ic_cdk::caller()`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use candid::Principal;
+//! use icarus_core::context::{self, Role};
+//!
+//! let alice = Principal::from_slice(&[7]);
+//! context::set_caller_override(Some(alice));
+//!
+//! let ctx = context::current_for("greet");
+//! assert_eq!(ctx.caller, alice);
+//! assert_eq!(ctx.role, Role::Public);
+//! assert_eq!(ctx.tool_name, "greet");
+//!
+//! context::set_caller_override(None);
+//! ```
+
+#[cfg(not(feature = "ic-canister"))]
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::fmt;
+
+use candid::{CandidType, Deserialize, Principal};
+use serde::Serialize;
+
+use crate::auth;
+
+thread_local! {
+    static CURRENT: RefCell<Option<ToolContext>> = const { RefCell::new(None) };
+}
+
+#[cfg(not(feature = "ic-canister"))]
+thread_local! {
+    static CALLER_OVERRIDE: Cell<Option<Principal>> = const { Cell::new(None) };
+}
+
+/// The access tier of a caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, CandidType, Deserialize, Serialize)]
+pub enum Role {
+    /// Whitelisted as an admin
+    Admin,
+    /// Whitelisted as a user
+    User,
+    /// Neither, while guest access is on; guest and public tools are available
+    Guest,
+    /// Neither; only public tools are available
+    Public,
+}
+
+impl Role {
+    /// Returns the role of `principal` in the auth whitelist.
+    #[must_use]
+    pub fn of(principal: &Principal) -> Self {
+        if auth::is_admin(principal) {
+            Self::Admin
+        } else if auth::is_user(principal) {
+            Self::User
+        } else if auth::guest_policy().enabled {
+            Self::Guest
+        } else {
+            Self::Public
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Admin => "admin",
+            Self::User => "user",
+            Self::Guest => "guest",
+            Self::Public => "public",
+        })
+    }
+}
+
+/// Who is calling a tool, and within which request.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct ToolContext {
+    /// Principal that made the call
+    pub caller: Principal,
+    /// The caller's access tier
+    pub role: Role,
+    /// Name of the tool being executed, without any `mcp!{}` prefix
+    pub tool_name: String,
+    /// JSON-RPC request id, empty outside `mcp_call_tool`
+    pub request_id: String,
+    /// Time in nanoseconds by which the tool should finish, if bounded.
+    ///
+    /// In a canister this is a soft deadline that nothing enforces; async
+    /// tools check [`is_past_deadline`](Self::is_past_deadline) between
+    /// awaits.
+    pub deadline: Option<u64>,
+}
+
+impl ToolContext {
+    /// Creates a context for `caller` without a request id or deadline.
+    #[must_use]
+    pub fn new(caller: Principal, tool_name: impl Into<String>) -> Self {
+        Self {
+            caller,
+            role: Role::of(&caller),
+            tool_name: tool_name.into(),
+            request_id: String::new(),
+            deadline: None,
+        }
+    }
+
+    /// Sets the JSON-RPC request id.
+    #[must_use]
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = request_id.into();
+        self
+    }
+
+    /// Sets the deadline in nanoseconds since the Unix epoch.
+    #[must_use]
+    pub fn with_deadline(mut self, deadline: u64) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Returns whether the caller is anonymous.
+    #[must_use]
+    pub fn is_anonymous(&self) -> bool {
+        auth::is_anonymous(&self.caller)
+    }
+
+    /// Returns the nanoseconds left until the deadline, or `None` if unbounded.
+    #[must_use]
+    pub fn remaining_nanos(&self) -> Option<u64> {
+        self.deadline
+            .map(|deadline| deadline.saturating_sub(crate::time::now_nanos()))
+    }
+
+    /// Returns whether the deadline has passed.
+    #[must_use]
+    pub fn is_past_deadline(&self) -> bool {
+        self.remaining_nanos() == Some(0)
+    }
+}
+
+/// Installs the context of the call being executed, or clears it with `None`.
+pub fn set_current(context: Option<ToolContext>) {
+    CURRENT.with(|current| *current.borrow_mut() = context);
+}
+
+/// Returns the context installed for the call being executed, if any.
+#[must_use]
+pub fn current() -> Option<ToolContext> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Returns the installed context, or one for [`caller`] calling `tool_name`
+/// when the tool runs outside `mcp_call_tool`.
+#[must_use]
+pub fn current_for(tool_name: &str) -> ToolContext {
+    current().unwrap_or_else(|| ToolContext::new(caller(), tool_name))
+}
+
+/// Returns the principal that made the current call.
+///
+/// While a tool runs this is the caller of the installed context, which is
+/// the API key's principal when the tool was called through the HTTP gateway.
+/// Otherwise it is the caller reported by the [`host`](crate::host), unless
+/// an override was installed off-chain with [`set_caller_override`].
+#[must_use]
+pub fn caller() -> Principal {
+    if let Some(caller) = CURRENT.with(|current| current.borrow().as_ref().map(|ctx| ctx.caller)) {
+        return caller;
+    }
+    #[cfg(not(feature = "ic-canister"))]
+    if let Some(caller) = CALLER_OVERRIDE.with(Cell::get) {
+        return caller;
+    }
+    crate::host::host().caller()
+}
+
+/// Sets the caller for the current thread, or restores the anonymous caller
+/// with `None`.
+#[cfg(not(feature = "ic-canister"))]
+pub fn set_caller_override(caller: Option<Principal>) {
+    CALLER_OVERRIDE.with(|cell| cell.set(caller));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_for_falls_back_to_caller() {
+        let admin = Principal::from_slice(&[42]);
+        auth::add_admin(admin);
+        set_caller_override(Some(admin));
+
+        let ctx = current_for("report");
+        assert_eq!(ctx.caller, admin);
+        assert_eq!(ctx.role, Role::Admin);
+        assert!(ctx.request_id.is_empty());
+        assert_eq!(ctx.remaining_nanos(), None);
+
+        set_caller_override(None);
+        auth::remove_admin(&admin);
+        assert!(current_for("report").is_anonymous());
+    }
+
+    #[test]
+    fn test_installed_context_wins() {
+        let ctx = ToolContext::new(Principal::anonymous(), "search")
+            .with_request_id("req-1")
+            .with_deadline(0);
+        set_current(Some(ctx.clone()));
+
+        let seen = current_for("other");
+        assert_eq!(seen, ctx);
+        assert!(seen.is_past_deadline());
+
+        let key_owner = Principal::from_slice(&[43]);
+        set_current(Some(ToolContext::new(key_owner, "search")));
+        assert_eq!(caller(), key_owner);
+
+        set_current(None);
+        assert_eq!(current(), None);
+    }
+}
@@ -0,0 +1,433 @@
+//! Encryption to individual principals with vetKD.
+//!
+//! The canister encrypts with identity-based encryption under its vetKD
+//! public key, using the recipient's principal as the identity. Only the
+//! vetKey for that principal decrypts the result, and the canister never sees
+//! it in the clear: [`request_decryption_key`] returns the caller's vetKey
+//! encrypted to a transport key the client generated, and the client decrypts
+//! the key and then the data locally (e.g. with `@dfinity/vetkeys`).
+//!
+//! Inside a canister (`ic-canister` feature) keys come from the management
+//! canister; the key name defaults to [`MAINNET_KEY`] and is changed with
+//! [`set_key_name`]. Off-chain the management canister calls go to a handler
+//! installed with [`set_vetkd_handler`].
+//!
+//! [`args_public_key`] and [`decrypt_args`] serve `#[tool(encrypted)]`
+//! arguments (see [`crate::encrypted_args`]) under a separate derivation
+//! context, so they never share keys with data encrypted to principals.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use icarus_core::crypto;
+//!
+//! #[tool("Store a note only you can read")]
+//! async fn save_private_note(text: String) -> Result<String, String> {
+//!     let ciphertext = crypto::encrypt_for_caller(text.as_bytes())
+//!         .await
+//!         .map_err(|e| e.to_string())?;
+//!     store(ciphertext);
+//!     Ok("saved".to_string())
+//! }
+//! ```
+
+#[cfg(not(feature = "ic-canister"))]
+use std::cell::Cell;
+use std::cell::RefCell;
+
+use candid::Principal;
+use ic_vetkeys::{
+    DerivedPublicKey, EncryptedVetKey, IbeCiphertext, IbeIdentity, IbeSeed, TransportSecretKey,
+    VetKey,
+};
+
+use crate::encrypted_args::{ARGS_CONTEXT, ARGS_IDENTITY};
+use crate::{IcarusError, Result};
+
+/// vetKD key on mainnet.
+pub const MAINNET_KEY: &str = "key_1";
+
+/// vetKD test key on mainnet, cheaper and not for production data.
+pub const TEST_KEY: &str = "test_key_1";
+
+/// vetKD key of a local dfx replica.
+pub const LOCAL_KEY: &str = "dfx_test_key";
+
+/// Domain separator for keys derived by this module.
+pub const DERIVATION_CONTEXT: &[u8] = b"icarus.crypto.v1";
+
+const SERVICE: &str = "vetKD";
+
+/// A management canister call made off-chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VetKdRequest {
+    /// `vetkd_public_key` for the canister's own key.
+    PublicKey {
+        /// Key name.
+        key_name: String,
+        /// Derivation context.
+        context: Vec<u8>,
+    },
+    /// `vetkd_derive_key`.
+    DeriveKey {
+        /// Key name.
+        key_name: String,
+        /// Derivation context.
+        context: Vec<u8>,
+        /// Identity the key is derived for.
+        input: Vec<u8>,
+        /// Client key the vetKey is encrypted to.
+        transport_public_key: Vec<u8>,
+    },
+}
+
+/// Handler that answers vetKD calls off-chain with the public key or the
+/// encrypted vetKey bytes.
+#[cfg(not(feature = "ic-canister"))]
+pub type VetKdHandler = Box<dyn Fn(&VetKdRequest) -> Result<Vec<u8>>>;
+
+thread_local! {
+    static KEY_NAME: RefCell<String> = RefCell::new(MAINNET_KEY.to_string());
+
+    /// Derived public key, fetched once per key name
+    static PUBLIC_KEY: RefCell<Option<Vec<u8>>> = const { RefCell::new(None) };
+
+    /// Derived public key for tool arguments
+    static ARGS_PUBLIC_KEY: RefCell<Option<Vec<u8>>> = const { RefCell::new(None) };
+
+    /// vetKey that decrypts tool arguments, derived on first use
+    static ARGS_KEY: RefCell<Option<VetKey>> = const { RefCell::new(None) };
+}
+
+#[cfg(not(feature = "ic-canister"))]
+thread_local! {
+    static VETKD_HANDLER: RefCell<Option<VetKdHandler>> = const { RefCell::new(None) };
+
+    static SEED_COUNTER: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Selects the vetKD key, e.g. [`LOCAL_KEY`] for a local replica.
+pub fn set_key_name(name: impl Into<String>) {
+    KEY_NAME.with(|key| *key.borrow_mut() = name.into());
+    clear_cached_keys();
+}
+
+/// Returns the selected vetKD key name.
+#[must_use]
+pub fn key_name() -> String {
+    KEY_NAME.with(|key| key.borrow().clone())
+}
+
+/// Installs the handler for off-chain vetKD calls on the current thread, or
+/// removes it with `None`.
+#[cfg(not(feature = "ic-canister"))]
+pub fn set_vetkd_handler(handler: Option<VetKdHandler>) {
+    VETKD_HANDLER.with(|cell| *cell.borrow_mut() = handler);
+    clear_cached_keys();
+}
+
+/// Returns the canister's derived public key, which clients need to verify
+/// the vetKeys they decrypt.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ExternalServiceError` if the management canister
+/// call fails or, off-chain, if no handler is installed.
+pub async fn public_key() -> Result<Vec<u8>> {
+    if let Some(key) = PUBLIC_KEY.with(|cached| cached.borrow().clone()) {
+        return Ok(key);
+    }
+    let key = call(VetKdRequest::PublicKey {
+        key_name: key_name(),
+        context: DERIVATION_CONTEXT.to_vec(),
+    })
+    .await?;
+    PUBLIC_KEY.with(|cached| *cached.borrow_mut() = Some(key.clone()));
+    Ok(key)
+}
+
+/// Returns the caller's vetKey, encrypted to `transport_public_key`.
+///
+/// # Errors
+///
+/// Returns `IcarusError::AccessDenied` for the anonymous principal and
+/// `IcarusError::ExternalServiceError` if the management canister call fails.
+pub async fn request_decryption_key(transport_public_key: Vec<u8>) -> Result<Vec<u8>> {
+    let caller = identified_caller()?;
+    call(VetKdRequest::DeriveKey {
+        key_name: key_name(),
+        context: DERIVATION_CONTEXT.to_vec(),
+        input: caller.as_slice().to_vec(),
+        transport_public_key,
+    })
+    .await
+}
+
+/// Encrypts `plaintext` so that only the caller can decrypt it.
+///
+/// # Errors
+///
+/// Returns `IcarusError::AccessDenied` for the anonymous principal, since
+/// anyone could decrypt for it; otherwise see [`encrypt_for`].
+pub async fn encrypt_for_caller(plaintext: &[u8]) -> Result<Vec<u8>> {
+    encrypt_for(identified_caller()?, plaintext).await
+}
+
+/// Encrypts `plaintext` so that only `principal` can decrypt it.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ExternalServiceError` if the public key or the
+/// randomness cannot be fetched, or the public key is malformed.
+pub async fn encrypt_for(principal: Principal, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let public_key = DerivedPublicKey::deserialize(&public_key().await?)
+        .map_err(|e| vetkd_error(format!("Invalid derived public key: {e:?}")))?;
+    let seed = IbeSeed::from_bytes(&random_seed().await?)
+        .map_err(|e| vetkd_error(format!("Invalid encryption seed: {e:?}")))?;
+    let identity = IbeIdentity::from_bytes(principal.as_slice());
+    Ok(IbeCiphertext::encrypt(&public_key, &identity, plaintext, &seed).serialize())
+}
+
+/// Returns the canister's derived public key for tool arguments, which the
+/// bridge encrypts `#[tool(encrypted)]` arguments under.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ExternalServiceError` if the management canister
+/// call fails.
+pub async fn args_public_key() -> Result<Vec<u8>> {
+    if let Some(key) = ARGS_PUBLIC_KEY.with(|cached| cached.borrow().clone()) {
+        return Ok(key);
+    }
+    let key = call(VetKdRequest::PublicKey {
+        key_name: key_name(),
+        context: ARGS_CONTEXT.to_vec(),
+    })
+    .await?;
+    ARGS_PUBLIC_KEY.with(|cached| *cached.borrow_mut() = Some(key.clone()));
+    Ok(key)
+}
+
+/// Decrypts tool arguments the bridge encrypted under [`args_public_key`].
+///
+/// The vetKey is derived once, to a transport key that never leaves the
+/// canister, and kept in heap memory until the key selection changes.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ExternalServiceError` if the vetKey cannot be
+/// derived or verified, or the ciphertext does not decrypt with it.
+pub async fn decrypt_args(ciphertext: &[u8]) -> Result<Vec<u8>> {
+    let ciphertext = IbeCiphertext::deserialize(ciphertext)
+        .map_err(|e| vetkd_error(format!("Invalid argument ciphertext: {e:?}")))?;
+    if ARGS_KEY.with(|cached| cached.borrow().is_none()) {
+        let key = derive_args_key().await?;
+        ARGS_KEY.with(|cached| *cached.borrow_mut() = Some(key));
+    }
+    ARGS_KEY.with(|cached| match cached.borrow().as_ref() {
+        Some(key) => ciphertext
+            .decrypt(key)
+            .map_err(|e| vetkd_error(format!("Failed to decrypt arguments: {e:?}"))),
+        None => Err(vetkd_error("Argument key is unavailable")),
+    })
+}
+
+async fn derive_args_key() -> Result<VetKey> {
+    let transport_key = TransportSecretKey::from_seed(random_seed().await?)
+        .map_err(|e| vetkd_error(format!("Invalid transport key seed: {e:?}")))?;
+    let public_key = DerivedPublicKey::deserialize(&args_public_key().await?)
+        .map_err(|e| vetkd_error(format!("Invalid derived public key: {e:?}")))?;
+    let encrypted_key = call(VetKdRequest::DeriveKey {
+        key_name: key_name(),
+        context: ARGS_CONTEXT.to_vec(),
+        input: ARGS_IDENTITY.to_vec(),
+        transport_public_key: transport_key.public_key(),
+    })
+    .await?;
+    EncryptedVetKey::deserialize(&encrypted_key)
+        .map_err(|e| vetkd_error(format!("Invalid encrypted vetKey: {e:?}")))?
+        .decrypt_and_verify(&transport_key, &public_key, ARGS_IDENTITY)
+        .map_err(|e| vetkd_error(format!("Failed to verify argument key: {e:?}")))
+}
+
+fn clear_cached_keys() {
+    PUBLIC_KEY.with(|cached| *cached.borrow_mut() = None);
+    ARGS_PUBLIC_KEY.with(|cached| *cached.borrow_mut() = None);
+    ARGS_KEY.with(|cached| *cached.borrow_mut() = None);
+}
+
+fn identified_caller() -> Result<Principal> {
+    let caller = crate::context::caller();
+    if caller == Principal::anonymous() {
+        return Err(IcarusError::AccessDenied(
+            "Encryption keys require an authenticated caller".to_string(),
+        ));
+    }
+    Ok(caller)
+}
+
+fn vetkd_error(message: impl Into<String>) -> IcarusError {
+    IcarusError::ExternalServiceError {
+        service: SERVICE.to_string(),
+        message: message.into(),
+    }
+}
+
+#[cfg(not(feature = "ic-canister"))]
+#[allow(clippy::unused_async)]
+async fn call(request: VetKdRequest) -> Result<Vec<u8>> {
+    VETKD_HANDLER.with(|cell| match cell.borrow().as_ref() {
+        Some(handler) => handler(&request),
+        None => Err(vetkd_error(
+            "vetKD is unavailable outside a canister; install a mock handler",
+        )),
+    })
+}
+
+#[cfg(feature = "ic-canister")]
+async fn call(request: VetKdRequest) -> Result<Vec<u8>> {
+    use ic_cdk::management_canister::{
+        vetkd_derive_key, vetkd_public_key, VetKDCurve, VetKDDeriveKeyArgs, VetKDKeyId,
+        VetKDPublicKeyArgs,
+    };
+
+    let key_id = |name: String| VetKDKeyId {
+        curve: VetKDCurve::Bls12_381_G2,
+        name,
+    };
+    match request {
+        VetKdRequest::PublicKey { key_name, context } => vetkd_public_key(&VetKDPublicKeyArgs {
+            canister_id: None,
+            context,
+            key_id: key_id(key_name),
+        })
+        .await
+        .map(|result| result.public_key)
+        .map_err(|e| vetkd_error(e.to_string())),
+        VetKdRequest::DeriveKey {
+            key_name,
+            context,
+            input,
+            transport_public_key,
+        } => vetkd_derive_key(&VetKDDeriveKeyArgs {
+            input,
+            context,
+            transport_public_key,
+            key_id: key_id(key_name),
+        })
+        .await
+        .map(|result| result.encrypted_key)
+        .map_err(|e| vetkd_error(e.to_string())),
+    }
+}
+
+#[cfg(feature = "ic-canister")]
+async fn random_seed() -> Result<Vec<u8>> {
+    ic_cdk::management_canister::raw_rand()
+        .await
+        .map_err(|e| vetkd_error(format!("raw_rand failed: {e}")))
+}
+
+/// Off-chain seeds only need to differ between calls; tests do not rely on
+/// them being unpredictable.
+#[cfg(not(feature = "ic-canister"))]
+#[allow(clippy::unused_async)]
+async fn random_seed() -> Result<Vec<u8>> {
+    use sha2::{Digest, Sha256};
+
+    let counter = SEED_COUNTER.with(|counter| {
+        counter.set(counter.get() + 1);
+        counter.get()
+    });
+    let mut hasher = Sha256::new();
+    hasher.update(crate::time::now_nanos().to_le_bytes());
+    hasher.update(counter.to_le_bytes());
+    Ok(hasher.finalize().to_vec())
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+    use crate::context::set_caller_override;
+
+    #[tokio::test]
+    async fn test_decryption_key_is_derived_for_caller() {
+        let alice = Principal::from_slice(&[7, 7]);
+        set_caller_override(Some(alice));
+        set_vetkd_handler(Some(Box::new(move |request| match request {
+            VetKdRequest::DeriveKey {
+                key_name,
+                context,
+                input,
+                transport_public_key,
+            } => {
+                assert_eq!(key_name, LOCAL_KEY);
+                assert_eq!(context, DERIVATION_CONTEXT);
+                assert_eq!(input, alice.as_slice());
+                Ok(transport_public_key.iter().rev().copied().collect())
+            }
+            VetKdRequest::PublicKey { .. } => Ok(vec![1]),
+        })));
+        set_key_name(LOCAL_KEY);
+
+        assert_eq!(
+            request_decryption_key(vec![1, 2, 3]).await.unwrap(),
+            [3, 2, 1]
+        );
+
+        set_vetkd_handler(None);
+        set_key_name(MAINNET_KEY);
+        set_caller_override(None);
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_caller_is_refused() {
+        set_caller_override(Some(Principal::anonymous()));
+        set_vetkd_handler(Some(Box::new(|_| Ok(Vec::new()))));
+
+        assert!(matches!(
+            request_decryption_key(vec![1]).await,
+            Err(IcarusError::AccessDenied(_))
+        ));
+        assert!(matches!(
+            encrypt_for_caller(b"secret").await,
+            Err(IcarusError::AccessDenied(_))
+        ));
+
+        set_vetkd_handler(None);
+        set_caller_override(None);
+    }
+
+    #[tokio::test]
+    async fn test_public_key_is_cached() {
+        let calls = std::rc::Rc::new(Cell::new(0));
+        let counted = calls.clone();
+        set_vetkd_handler(Some(Box::new(move |_| {
+            counted.set(counted.get() + 1);
+            Ok(vec![9; 96])
+        })));
+
+        assert_eq!(public_key().await.unwrap(), vec![9; 96]);
+        assert_eq!(public_key().await.unwrap(), vec![9; 96]);
+        assert_eq!(calls.get(), 1);
+
+        set_vetkd_handler(None);
+        assert!(public_key().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_args_key_uses_its_own_context() {
+        set_vetkd_handler(Some(Box::new(|request| match request {
+            VetKdRequest::PublicKey { context, .. } if context == ARGS_CONTEXT => Ok(vec![4; 96]),
+            _ => Ok(vec![5; 96]),
+        })));
+
+        assert_eq!(args_public_key().await.unwrap(), vec![4; 96]);
+        assert_eq!(public_key().await.unwrap(), vec![5; 96]);
+        // Garbage is not a ciphertext, and no key is derived for it
+        assert!(decrypt_args(b"garbage").await.is_err());
+
+        set_vetkd_handler(None);
+        assert!(args_public_key().await.is_err());
+    }
+}
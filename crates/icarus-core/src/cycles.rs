@@ -0,0 +1,209 @@
+//! Cycle balance monitoring and low-balance guardrails.
+//!
+//! A canister that runs out of cycles is frozen and eventually deleted. This
+//! module keeps a configurable reserve: once the balance drops below it,
+//! tools marked `#[tool(expensive)]` are rejected by `mcp_call_tool` so the
+//! remaining cycles are spent on cheap queries and on topping up.
+//!
+//! [`cycles_status`] is exposed by `mcp!{}` as the `get_cycles_status` query,
+//! and [`start_low_balance_alerts`] reports when the balance crosses a
+//! warning threshold.
+
+use std::cell::RefCell;
+
+use candid::{CandidType, Deserialize};
+use rustc_hash::FxHashSet;
+use serde::Serialize;
+
+use crate::{IcarusError, Result};
+
+/// Default reserve below which expensive tools are rejected (100B cycles).
+pub const DEFAULT_RESERVE_CYCLES: u128 = 100_000_000_000;
+
+/// Default threshold for low-balance warnings (1T cycles).
+pub const DEFAULT_WARNING_CYCLES: u128 = 1_000_000_000_000;
+
+#[derive(Debug)]
+struct CyclesConfig {
+    reserve: u128,
+    warning: u128,
+    expensive_tools: FxHashSet<String>,
+    #[cfg(not(feature = "ic-canister"))]
+    mock_balance: Option<u128>,
+}
+
+impl Default for CyclesConfig {
+    fn default() -> Self {
+        Self {
+            reserve: DEFAULT_RESERVE_CYCLES,
+            warning: DEFAULT_WARNING_CYCLES,
+            expensive_tools: FxHashSet::default(),
+            #[cfg(not(feature = "ic-canister"))]
+            mock_balance: None,
+        }
+    }
+}
+
+thread_local! {
+    static CONFIG: RefCell<CyclesConfig> = RefCell::new(CyclesConfig::default());
+}
+
+/// Current cycle balance and guardrail settings.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct CyclesStatus {
+    /// Current balance in cycles.
+    pub balance: u128,
+    /// Reserve below which expensive tools are rejected.
+    pub reserve: u128,
+    /// Threshold for low-balance warnings.
+    pub warning_threshold: u128,
+    /// Whether the balance is below the reserve.
+    pub below_reserve: bool,
+    /// Whether the balance is below the warning threshold.
+    pub low_balance: bool,
+    /// Tools rejected while below the reserve.
+    pub expensive_tools: Vec<String>,
+}
+
+/// Returns the canister's cycle balance.
+///
+/// The balance is the [`host`](crate::host)'s, unlimited off-chain, unless
+/// a value was set with [`set_mock_balance`].
+#[must_use]
+pub fn balance() -> u128 {
+    #[cfg(not(feature = "ic-canister"))]
+    if let Some(balance) = CONFIG.with(|config| config.borrow().mock_balance) {
+        return balance;
+    }
+    crate::host::host().cycle_balance()
+}
+
+/// Sets the balance reported off-chain, for testing guardrails.
+#[cfg(not(feature = "ic-canister"))]
+pub fn set_mock_balance(cycles: u128) {
+    CONFIG.with(|config| config.borrow_mut().mock_balance = Some(cycles));
+}
+
+/// Sets the reserve below which expensive tools are rejected.
+pub fn set_reserve(cycles: u128) {
+    CONFIG.with(|config| config.borrow_mut().reserve = cycles);
+}
+
+/// Sets the threshold for low-balance warnings.
+pub fn set_warning_threshold(cycles: u128) {
+    CONFIG.with(|config| config.borrow_mut().warning = cycles);
+}
+
+/// Marks a tool as expensive, so it is rejected while below the reserve.
+pub fn mark_expensive(tool: &str) {
+    CONFIG.with(|config| {
+        config.borrow_mut().expensive_tools.insert(tool.to_string());
+    });
+}
+
+/// Returns whether a tool is marked expensive.
+#[must_use]
+pub fn is_expensive(tool: &str) -> bool {
+    CONFIG.with(|config| config.borrow().expensive_tools.contains(tool))
+}
+
+/// Returns the current balance and guardrail settings.
+#[must_use]
+pub fn cycles_status() -> CyclesStatus {
+    let balance = balance();
+    CONFIG.with(|config| {
+        let config = config.borrow();
+        let mut expensive_tools: Vec<String> = config.expensive_tools.iter().cloned().collect();
+        expensive_tools.sort();
+        CyclesStatus {
+            balance,
+            reserve: config.reserve,
+            warning_threshold: config.warning,
+            below_reserve: balance < config.reserve,
+            low_balance: balance < config.warning,
+            expensive_tools,
+        }
+    })
+}
+
+/// Rejects expensive tools while the balance is below the reserve.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ResourceLimitExceeded` if `tool` is expensive and the
+/// balance is below the reserve.
+pub fn guard_tool(tool: &str) -> Result<()> {
+    if !is_expensive(tool) {
+        return Ok(());
+    }
+
+    let status = cycles_status();
+    if status.below_reserve {
+        return Err(IcarusError::ResourceLimitExceeded {
+            resource: "cycles".to_string(),
+            message: format!(
+                "balance {} is below the reserve of {}; '{tool}' is disabled until the canister is topped up",
+                status.balance, status.reserve
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Starts a timer that checks the balance every `interval`.
+///
+/// When the balance is below the warning threshold, a warning is printed to
+/// the canister log and `notify` (if any) is called with the status.
+#[cfg(feature = "ic-canister")]
+pub fn start_low_balance_alerts(
+    interval: std::time::Duration,
+    notify: Option<fn(&CyclesStatus)>,
+) -> ic_cdk_timers::TimerId {
+    ic_cdk_timers::set_timer_interval(interval, move || {
+        let status = cycles_status();
+        if status.low_balance {
+            crate::logging::warn(format_args!(
+                "cycle balance {} is below {} (reserve {})",
+                status.balance, status.warning_threshold, status.reserve
+            ));
+            if let Some(notify) = notify {
+                notify(&status);
+            }
+        }
+    })
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guard_rejects_expensive_tools_below_reserve() {
+        mark_expensive("render_video");
+        set_reserve(1_000);
+
+        set_mock_balance(5_000);
+        assert!(guard_tool("render_video").is_ok());
+
+        set_mock_balance(500);
+        let err = guard_tool("render_video").unwrap_err();
+        assert!(matches!(err, IcarusError::ResourceLimitExceeded { .. }));
+
+        // Cheap tools keep working.
+        assert!(guard_tool("get_status").is_ok());
+    }
+
+    #[test]
+    fn test_status_flags() {
+        set_reserve(100);
+        set_warning_threshold(1_000);
+        mark_expensive("b_tool");
+        mark_expensive("a_tool");
+
+        set_mock_balance(500);
+        let status = cycles_status();
+        assert!(status.low_balance);
+        assert!(!status.below_reserve);
+        assert_eq!(status.expensive_tools, vec!["a_tool", "b_tool"]);
+    }
+}
@@ -0,0 +1,160 @@
+//! End-to-end encrypted tool arguments.
+//!
+//! Tools declared `#[tool(encrypted)]` take their arguments encrypted by the
+//! bridge, so secrets in them never appear in bridge logs, recordings or
+//! replica request logs. The bridge encrypts the arguments with
+//! identity-based encryption under the canister's vetKD public key for
+//! [`ARGS_CONTEXT`] (served by the `mcp_args_public_key` endpoint of
+//! `mcp!{ encryption = true }`) and sends them as an envelope:
+//!
+//! ```json
+//! { "__icarus_encrypted": "<base64 ciphertext>" }
+//! ```
+//!
+//! `mcp_call_tool` passes every call through [`open`] before dispatch: an
+//! envelope is decrypted with the canister's vetKey for [`ARGS_IDENTITY`],
+//! plain arguments pass through unchanged, and plain arguments to an
+//! encrypted tool are refused. Decryption needs the `vetkd` feature.
+//!
+//! Tools marked encrypted carry [`SCHEMA_KEY`] in their input schema, which
+//! is how the bridge knows which calls to encrypt.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::encrypted_args;
+//! use serde_json::json;
+//!
+//! encrypted_args::mark_encrypted("store_api_key");
+//! assert!(encrypted_args::is_encrypted("store_api_key"));
+//!
+//! let sealed = json!({ encrypted_args::ENVELOPE_KEY: "AAEC" });
+//! assert_eq!(encrypted_args::envelope(&sealed), Some("AAEC"));
+//! assert_eq!(encrypted_args::envelope(&json!({ "key": "AAEC" })), None);
+//! ```
+
+use std::cell::RefCell;
+
+use base64::Engine as _;
+use rustc_hash::FxHashSet;
+use serde_json::Value;
+
+use crate::{IcarusError, Result};
+
+/// Argument key holding the base64 ciphertext of the real arguments.
+pub const ENVELOPE_KEY: &str = "__icarus_encrypted";
+
+/// Input schema key set to `true` on tools that take encrypted arguments.
+pub const SCHEMA_KEY: &str = "x-icarus-encrypted";
+
+/// vetKD derivation context of the argument encryption key.
+pub const ARGS_CONTEXT: &[u8] = b"icarus.args.v1";
+
+/// IBE identity arguments are encrypted to.
+pub const ARGS_IDENTITY: &[u8] = b"tool-arguments";
+
+thread_local! {
+    static ENCRYPTED_TOOLS: RefCell<FxHashSet<String>> = RefCell::new(FxHashSet::default());
+}
+
+/// Marks a tool as taking only encrypted arguments.
+pub fn mark_encrypted(tool: &str) {
+    ENCRYPTED_TOOLS.with(|tools| {
+        tools.borrow_mut().insert(tool.to_string());
+    });
+}
+
+/// Returns whether a tool takes only encrypted arguments.
+#[must_use]
+pub fn is_encrypted(tool: &str) -> bool {
+    ENCRYPTED_TOOLS.with(|tools| tools.borrow().contains(tool))
+}
+
+/// Returns the base64 ciphertext if `arguments` is an envelope.
+#[must_use]
+pub fn envelope(arguments: &Value) -> Option<&str> {
+    let object = arguments.as_object()?;
+    if object.len() != 1 {
+        return None;
+    }
+    object.get(ENVELOPE_KEY)?.as_str()
+}
+
+/// Returns the arguments of a call to `tool`, decrypted if they came in an
+/// envelope.
+///
+/// # Errors
+///
+/// Returns `IcarusError::AccessDenied` for plain arguments to an encrypted
+/// tool, `IcarusError::SerializationError` for an envelope that does not
+/// decrypt to a JSON value, `IcarusError::ConfigurationError` for an
+/// envelope without the `vetkd` feature, and vetKD errors as
+/// `IcarusError::ExternalServiceError`.
+pub async fn open(tool: &str, arguments: Value) -> Result<Value> {
+    let Some(ciphertext) = envelope(&arguments) else {
+        if is_encrypted(tool) {
+            return Err(IcarusError::AccessDenied(format!(
+                "Tool '{tool}' only accepts encrypted arguments"
+            )));
+        }
+        return Ok(arguments);
+    };
+
+    let ciphertext = base64::engine::general_purpose::STANDARD
+        .decode(ciphertext)
+        .map_err(|e| invalid_envelope(&format!("not base64: {e}")))?;
+    let plaintext = decrypt(&ciphertext).await?;
+    serde_json::from_slice(&plaintext).map_err(|e| invalid_envelope(&format!("not JSON: {e}")))
+}
+
+#[cfg(feature = "vetkd")]
+async fn decrypt(ciphertext: &[u8]) -> Result<Vec<u8>> {
+    crate::crypto::decrypt_args(ciphertext).await
+}
+
+#[cfg(not(feature = "vetkd"))]
+#[allow(clippy::unused_async)]
+async fn decrypt(_ciphertext: &[u8]) -> Result<Vec<u8>> {
+    Err(IcarusError::ConfigurationError(
+        "Encrypted arguments need the vetkd feature".to_string(),
+    ))
+}
+
+fn invalid_envelope(reason: &str) -> IcarusError {
+    IcarusError::SerializationError(format!("Invalid encrypted arguments: {reason}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_plain_arguments_pass_through() {
+        let arguments = json!({ "city": "Zurich" });
+        assert_eq!(open("weather", arguments.clone()).await.unwrap(), arguments);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_tool_refuses_plain_arguments() {
+        mark_encrypted("store_secret");
+        assert!(matches!(
+            open("store_secret", json!({ "secret": "hunter2" })).await,
+            Err(IcarusError::AccessDenied(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_envelope_is_rejected() {
+        let result = open("weather", json!({ ENVELOPE_KEY: "not base64!" })).await;
+        assert!(matches!(result, Err(IcarusError::SerializationError(_))));
+    }
+
+    #[test]
+    fn test_envelope_needs_exactly_one_key() {
+        assert_eq!(envelope(&json!({ ENVELOPE_KEY: "AA==" })), Some("AA=="));
+        assert_eq!(envelope(&json!({ ENVELOPE_KEY: "AA==", "x": 1 })), None);
+        assert_eq!(envelope(&json!({ ENVELOPE_KEY: 1 })), None);
+        assert_eq!(envelope(&json!("AA==")), None);
+    }
+}
@@ -10,6 +10,8 @@ use candid::{CandidType, Deserialize};
 use serde::Serialize;
 use thiserror::Error;
 
+use crate::error_codes::{CodedError, IcarusCode};
+
 /// Main error type for the Icarus CDK.
 ///
 /// This enum covers all possible errors that can occur during MCP server
@@ -72,6 +74,10 @@ pub enum IcarusError {
     #[error("Candid error: {0}")]
     CandidError(String),
 
+    /// CBOR or bincode serialization/deserialization error.
+    #[error("Serialization error: {0}")]
+    SerializationError(String),
+
     /// Tool parameter validation error.
     #[error("Invalid parameter '{parameter}' for tool '{tool_id}': {message}")]
     InvalidParameter {
@@ -140,10 +146,23 @@ pub enum IcarusError {
         timeout_ms: u64,
     },
 
+    /// A write was based on a stale version of a record.
+    #[error("Version conflict: expected version {expected}, current version is {actual}")]
+    Conflict {
+        /// Version the caller last read.
+        expected: u64,
+        /// Version currently stored.
+        actual: u64,
+    },
+
     /// Invalid version string provided.
     #[error("Invalid version: {0}")]
     InvalidVersion(String),
 
+    /// Invalid W3C `traceparent` header.
+    #[error("Invalid trace context: {0}")]
+    InvalidTraceContext(String),
+
     /// Context-enriched error for better debugging and observability.
     #[error("{message}")]
     WithContext {
@@ -466,6 +485,12 @@ impl IcarusError {
         }
     }
 
+    /// Creates a version conflict error.
+    #[must_use]
+    pub fn conflict(expected: u64, actual: u64) -> Self {
+        Self::Conflict { expected, actual }
+    }
+
     /// Creates an internal error.
     #[must_use]
     pub fn internal_error(message: impl Into<String>) -> Self {
@@ -548,6 +573,42 @@ impl IcarusError {
                 | Self::JsonRpcError(_)
         )
     }
+
+    /// Returns the stable code of this kind of error (see
+    /// [`error_codes`](crate::error_codes)).
+    #[must_use]
+    pub fn error_code(&self) -> IcarusCode {
+        match self {
+            Self::ToolNotFound(_) => IcarusCode::ToolNotFound,
+            Self::ToolExecutionFailed { source, .. } | Self::WithContext { source, .. } => {
+                source.error_code()
+            }
+            Self::InvalidToolId(_)
+            | Self::InvalidUserId(_)
+            | Self::InvalidSessionId(_)
+            | Self::JsonError(_)
+            | Self::InvalidParameter { .. }
+            | Self::InvalidVersion(_)
+            | Self::InvalidTraceContext(_) => IcarusCode::InvalidArguments,
+            Self::JsonRpcError(error) => IcarusCode::for_jsonrpc(error.code),
+            Self::CandidError(_) | Self::SerializationError(_) | Self::InternalError(_) => {
+                IcarusCode::Internal
+            }
+            Self::InvalidSchema { .. } | Self::ConfigurationError(_) => IcarusCode::Configuration,
+            Self::AccessDenied(_) => IcarusCode::AccessDenied,
+            Self::RateLimitExceeded { .. } => IcarusCode::RateLimited,
+            Self::ResourceLimitExceeded { .. } => IcarusCode::ResourceExhausted,
+            Self::ExternalServiceError { .. } => IcarusCode::ExternalService,
+            Self::Timeout { .. } => IcarusCode::ToolTimeout,
+            Self::Conflict { .. } => IcarusCode::StorageConflict,
+        }
+    }
+
+    /// Converts this error into a [`CodedError`] with its message.
+    #[must_use]
+    pub fn to_coded(&self) -> CodedError {
+        self.error_code().with_message(self)
+    }
 }
 
 /// Extension trait for Result types to add context chaining.
@@ -608,6 +669,17 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_error_codes() -> Result<()> {
+        let error = IcarusError::tool_not_found(ToolId::new("missing")?).with_context("lookup");
+        assert_eq!(error.error_code(), IcarusCode::ToolNotFound);
+        assert_eq!(
+            IcarusError::conflict(1, 2).to_coded().to_string(),
+            "ICARUS-STORAGE-409: Version conflict: expected version 1, current version is 2"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_json_rpc_errors() {
         let parse_error = JsonRpcError::parse_error("Invalid JSON");
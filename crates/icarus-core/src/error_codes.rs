@@ -0,0 +1,399 @@
+//! Stable error codes that clients can branch on.
+//!
+//! A tool error is free-form text, so a client can only show it. An
+//! [`ErrorCode`] such as `ICARUS-STORAGE-404` names a kind of failure that
+//! means the same thing in every canister and every release. Codes are
+//! declared with their default messages in catalogs made by
+//! [`error_catalog!`](crate::error_catalog); [`IcarusCode`] is the catalog of
+//! the CDK itself, and a canister declares its own for its domain.
+//!
+//! A tool returning `Result<T, E>` reports a code by making `E` a catalog
+//! enum or a [`CodedError`], whose text is `CODE: message`. The executor
+//! splits the code off with [`split`] and the tool's `CallToolResult` carries
+//! it as `structuredContent.code`. JSON-RPC errors of `mcp_call_tool` carry
+//! the code of their condition in `error.data.code`, which the bridge passes
+//! on to the client unchanged.
+//!
+//! A code is at least three segments of uppercase letters and digits joined
+//! by `-`, starting with a letter: the product, the area and a number, which
+//! by convention follows the closest HTTP status.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::error_catalog;
+//! use icarus_core::error_codes::{self, CodedError};
+//!
+//! error_catalog! {
+//!     /// Failures of the notes tools.
+//!     pub enum NotesError {
+//!         /// No note has the given id.
+//!         NotFound = "NOTES-NOTE-404" => "Note not found",
+//!         /// The note is being edited by someone else.
+//!         Locked = "NOTES-NOTE-423" => "Note is locked",
+//!     }
+//! }
+//!
+//! fn get_note(id: u64) -> Result<String, CodedError> {
+//!     Err(NotesError::NotFound.with_message(format!("No note {id}")))
+//! }
+//!
+//! let text = get_note(7).unwrap_err().to_string();
+//! assert_eq!(text, "NOTES-NOTE-404: No note 7");
+//! assert_eq!(error_codes::split(&text), (Some("NOTES-NOTE-404"), "No note 7"));
+//! assert_eq!(NotesError::Locked.to_string(), "NOTES-NOTE-423: Note is locked");
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::maintenance::SERVICE_UNAVAILABLE_CODE;
+use crate::payload_limits::PAYLOAD_TOO_LARGE_CODE;
+use crate::protocol::ToolResult;
+use crate::replication::READ_ONLY_REPLICA_CODE;
+
+/// Key of the code in the `data` of JSON-RPC errors and in the structured
+/// content of failed tool calls.
+pub const CODE_KEY: &str = "code";
+
+/// A stable error code, such as `ICARUS-STORAGE-404`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(transparent)]
+pub struct ErrorCode(&'static str);
+
+impl ErrorCode {
+    /// Wraps `code`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `code` is not a valid code; in a constant, such as those of
+    /// [`error_catalog!`](crate::error_catalog), that fails the build.
+    #[must_use]
+    pub const fn new(code: &'static str) -> Self {
+        assert!(is_code(code), "error codes look like AREA-KIND-404");
+        Self(code)
+    }
+
+    /// Returns the code as text.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        self.0
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+/// An error with a code and a message for people, written `CODE: message`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodedError {
+    code: ErrorCode,
+    message: String,
+}
+
+impl CodedError {
+    /// Creates an error with `code` and `message`.
+    #[must_use]
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Returns the error's code.
+    #[must_use]
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    /// Returns the error's message without the code.
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for CodedError {}
+
+/// Returns whether `text` is a valid error code.
+#[must_use]
+pub const fn is_code(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    if bytes.is_empty() || !bytes[0].is_ascii_uppercase() {
+        return false;
+    }
+
+    let mut segments = 1;
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        if byte == b'-' {
+            // Segments are never empty
+            if i + 1 == bytes.len() || bytes[i + 1] == b'-' {
+                return false;
+            }
+            segments += 1;
+        } else if !byte.is_ascii_uppercase() && !byte.is_ascii_digit() {
+            return false;
+        }
+        i += 1;
+    }
+    segments >= 3
+}
+
+/// Splits `CODE: message` into its code and message; text without a leading
+/// code is all message.
+#[must_use]
+pub fn split(text: &str) -> (Option<&str>, &str) {
+    match text.split_once(": ") {
+        Some((code, message)) if is_code(code) => (Some(code), message),
+        _ => (None, text),
+    }
+}
+
+/// Converts the error text of a tool into its result, with the code split
+/// off if the text starts with one.
+#[must_use]
+pub fn tool_error(text: String) -> ToolResult<'static> {
+    match split(&text) {
+        (Some(code), message) => ToolResult::Error {
+            code: Some(Cow::Owned(code.to_string())),
+            message: Cow::Owned(message.to_string()),
+            details: None,
+        },
+        (None, _) => ToolResult::error(text),
+    }
+}
+
+/// The `data` of a JSON-RPC error with `code`.
+#[must_use]
+pub fn jsonrpc_data(code: ErrorCode) -> serde_json::Value {
+    serde_json::json!({ CODE_KEY: code })
+}
+
+/// Declares a catalog of error codes and their default messages.
+///
+/// The catalog is an enum with one variant per code. Each variant converts
+/// to a [`CodedError`](crate::error_codes::CodedError), alone or with a
+/// message of its own, and displays as `CODE: default message`, so a tool
+/// can return it as its error directly. Invalid codes fail the build.
+///
+/// ```rust
+/// icarus_core::error_catalog! {
+///     /// Failures of the billing tools.
+///     pub enum BillingError {
+///         /// The card was declined by the processor.
+///         Declined = "BILLING-CARD-402" => "Card declined",
+///     }
+/// }
+///
+/// assert_eq!(BillingError::Declined.code().as_str(), "BILLING-CARD-402");
+/// assert_eq!(BillingError::ALL.len(), 1);
+/// ```
+#[macro_export]
+macro_rules! error_catalog {
+    (
+        $(#[$enum_attr:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$attr:meta])*
+                $variant:ident = $code:literal => $message:literal
+            ),+ $(,)?
+        }
+    ) => {
+        $(#[$enum_attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $(
+                $(#[$attr])*
+                $variant,
+            )+
+        }
+
+        impl $name {
+            /// Every code of the catalog.
+            pub const ALL: &'static [Self] = &[$(Self::$variant),+];
+
+            /// Returns the stable code.
+            #[must_use]
+            pub const fn code(self) -> $crate::error_codes::ErrorCode {
+                match self {
+                    $(Self::$variant => {
+                        const CODE: $crate::error_codes::ErrorCode =
+                            $crate::error_codes::ErrorCode::new($code);
+                        CODE
+                    })+
+                }
+            }
+
+            /// Returns the message used when none is given.
+            #[must_use]
+            pub const fn default_message(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $message,)+
+                }
+            }
+
+            /// Returns an error with this code and `message`.
+            #[must_use]
+            pub fn with_message(
+                self,
+                message: impl ::std::fmt::Display,
+            ) -> $crate::error_codes::CodedError {
+                $crate::error_codes::CodedError::new(self.code(), message.to_string())
+            }
+        }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                write!(f, "{}: {}", self.code(), self.default_message())
+            }
+        }
+
+        impl ::std::error::Error for $name {}
+
+        impl ::std::convert::From<$name> for $crate::error_codes::CodedError {
+            fn from(code: $name) -> Self {
+                $crate::error_codes::CodedError::new(code.code(), code.default_message())
+            }
+        }
+    };
+}
+
+crate::error_catalog! {
+    /// Error codes of the CDK.
+    pub enum IcarusCode {
+        /// The request is not valid JSON-RPC.
+        InvalidRequest = "ICARUS-REQUEST-400" => "Malformed request",
+        /// The request or response is over its size limit.
+        PayloadTooLarge = "ICARUS-REQUEST-413" => "Payload too large",
+        /// No tool has the name, or it is disabled.
+        ToolNotFound = "ICARUS-TOOL-404" => "Tool not found",
+        /// The tool ran past its timeout.
+        ToolTimeout = "ICARUS-TOOL-504" => "Tool timed out",
+        /// The arguments do not match the tool's schema.
+        InvalidArguments = "ICARUS-ARGS-400" => "Invalid arguments",
+        /// The caller may not use the tool.
+        AccessDenied = "ICARUS-AUTH-403" => "Access denied",
+        /// The caller made too many calls.
+        RateLimited = "ICARUS-AUTH-429" => "Rate limit exceeded",
+        /// The tool has a price the caller has not paid.
+        PaymentRequired = "ICARUS-PAYMENT-402" => "Payment required",
+        /// The canister's cycles are below the reserve for expensive tools.
+        LowCycles = "ICARUS-CYCLES-503" => "Not enough cycles",
+        /// The canister is in maintenance mode.
+        Maintenance = "ICARUS-MAINTENANCE-503" => "Service unavailable for maintenance",
+        /// The canister is a read replica and the tool changes data.
+        ReadOnlyReplica = "ICARUS-REPLICA-405" => "Read replicas only run read-only tools",
+        /// A stored record does not exist.
+        StorageNotFound = "ICARUS-STORAGE-404" => "Record not found",
+        /// A stored record changed since the caller read it.
+        StorageConflict = "ICARUS-STORAGE-409" => "Record was changed by another call",
+        /// A memory, instruction or other resource limit was reached.
+        ResourceExhausted = "ICARUS-RESOURCE-507" => "Resource limit exceeded",
+        /// An HTTP outcall or inter-canister call failed.
+        ExternalService = "ICARUS-EXTERNAL-502" => "External service failed",
+        /// The canister is not configured for the operation.
+        Configuration = "ICARUS-CONFIG-500" => "Configuration error",
+        /// Anything else.
+        Internal = "ICARUS-INTERNAL-500" => "Internal error",
+    }
+}
+
+impl IcarusCode {
+    /// Returns the code of a JSON-RPC error code of `mcp_call_tool`.
+    #[must_use]
+    pub const fn for_jsonrpc(code: i32) -> Self {
+        match code {
+            -32700 | -32600 => Self::InvalidRequest,
+            -32601 => Self::ToolNotFound,
+            -32602 => Self::InvalidArguments,
+            -32001 => Self::PaymentRequired,
+            -32002 => Self::LowCycles,
+            SERVICE_UNAVAILABLE_CODE => Self::Maintenance,
+            PAYLOAD_TOO_LARGE_CODE => Self::PayloadTooLarge,
+            READ_ONLY_REPLICA_CODE => Self::ReadOnlyReplica,
+            _ => Self::Internal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_code() {
+        assert!(is_code("ICARUS-STORAGE-404"));
+        assert!(is_code("A1-B-C"));
+        assert!(!is_code("ICARUS-404"));
+        assert!(!is_code("icarus-storage-404"));
+        assert!(!is_code("1CARUS-STORAGE-404"));
+        assert!(!is_code("ICARUS--404-X"));
+        assert!(!is_code("ICARUS-STORAGE-"));
+        assert!(!is_code("ICARUS STORAGE 404"));
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            split("ICARUS-STORAGE-404: No note 7"),
+            (Some("ICARUS-STORAGE-404"), "No note 7")
+        );
+        assert_eq!(split("Error: disk full"), (None, "Error: disk full"));
+        assert_eq!(split("no code"), (None, "no code"));
+    }
+
+    #[test]
+    fn test_tool_error_carries_the_code() {
+        let result = tool_error(
+            IcarusCode::StorageNotFound
+                .with_message("No note 7")
+                .to_string(),
+        );
+        assert!(matches!(
+            result,
+            ToolResult::Error { ref code, ref message, .. }
+                if code.as_deref() == Some("ICARUS-STORAGE-404") && message == "No note 7"
+        ));
+
+        let result = tool_error("plain failure".to_string());
+        assert!(matches!(
+            result,
+            ToolResult::Error { code: None, ref message, .. } if message == "plain failure"
+        ));
+    }
+
+    #[test]
+    fn test_catalog() {
+        let codes: std::collections::HashSet<_> =
+            IcarusCode::ALL.iter().map(|code| code.code()).collect();
+        assert_eq!(codes.len(), IcarusCode::ALL.len());
+
+        assert_eq!(IcarusCode::for_jsonrpc(-32601), IcarusCode::ToolNotFound);
+        assert_eq!(
+            IcarusCode::for_jsonrpc(SERVICE_UNAVAILABLE_CODE),
+            IcarusCode::Maintenance
+        );
+        assert_eq!(
+            jsonrpc_data(IcarusCode::Maintenance.code()),
+            serde_json::json!({ "code": "ICARUS-MAINTENANCE-503" })
+        );
+
+        let error = CodedError::from(IcarusCode::AccessDenied);
+        assert_eq!(error.code().as_str(), "ICARUS-AUTH-403");
+        assert_eq!(error.message(), "Access denied");
+    }
+}
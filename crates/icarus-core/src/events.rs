@@ -0,0 +1,551 @@
+//! Topic-based publish/subscribe between canisters.
+//!
+//! A canister subscribes to a topic by naming one of its update methods as
+//! the callback; [`publish`] then queues one delivery of the [`Event`] per
+//! subscriber. [`deliver_pending`] sends queued deliveries with inter-canister
+//! calls, and a delivery stays queued, with exponential backoff, until the
+//! callback succeeds or [`MAX_ATTEMPTS`] is reached. Delivery is therefore at
+//! least once: callbacks should ignore event ids they have already seen.
+//!
+//! Subscriptions and the delivery queue live in stable memory, so neither is
+//! lost on upgrade. `mcp!{ events = true }` exposes `subscribe`,
+//! `unsubscribe` and `list_subscriptions` endpoints, and [`start_delivery`]
+//! runs the delivery timer. A canister can also subscribe to its own topics.
+//!
+//! Only canisters can subscribe: users and the anonymous principal cannot
+//! receive callbacks, so their subscriptions are refused. Subscriptions are
+//! capped at [`DEFAULT_MAX_SUBSCRIPTIONS`] in total and
+//! [`DEFAULT_MAX_SUBSCRIPTIONS_PER_SUBSCRIBER`] per canister, which
+//! [`set_max_subscriptions`] and [`set_max_subscriptions_per_subscriber`]
+//! change, so that one subscriber cannot fill stable memory or make every
+//! [`publish`] fan out without bound.
+//!
+//! Topics starting with [`RESERVED_TOPIC_PREFIX`] carry Icarus's own traffic,
+//! such as the changes sent to read replicas. [`subscribe`] and
+//! [`unsubscribe`] refuse them, so only the owner-gated APIs that use them,
+//! like `replication::add_replica`, can register their subscribers.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use candid::Principal;
+//! use icarus_core::events;
+//!
+//! let subscriber = Principal::from_slice(&[1]);
+//! events::subscribe("orders.created", subscriber, "on_order_created").unwrap();
+//!
+//! let event_id = events::publish("orders.created", br#"{"order": 42}"#.to_vec());
+//! assert_eq!(events::pending_deliveries(), 1);
+//!
+//! // The delivery timer hands each due delivery to the subscriber, then reports back
+//! for delivery in events::take_due(10) {
+//!     assert_eq!(delivery.event.id, event_id);
+//!     events::complete(delivery.id, Ok(()));
+//! }
+//! assert_eq!(events::pending_deliveries(), 0);
+//! ```
+
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+
+use crate::memory::{self, StableMemory, EVENT_QUEUE_MEMORY_ID, EVENT_SUBSCRIPTIONS_MEMORY_ID};
+use crate::{IcarusError, Result};
+
+/// Delivery attempts before an event is dropped for a subscriber.
+pub const MAX_ATTEMPTS: u32 = 10;
+
+/// Delay before the first retry; doubled after each failure.
+pub const INITIAL_RETRY_DELAY_NANOS: u64 = 5_000_000_000;
+
+/// Longest delay between retries.
+pub const MAX_RETRY_DELAY_NANOS: u64 = 60 * 60 * 1_000_000_000;
+
+/// Largest accepted topic name in bytes.
+pub const MAX_TOPIC_LENGTH: usize = 128;
+
+/// Prefix of the topics Icarus reserves for itself.
+pub const RESERVED_TOPIC_PREFIX: &str = "icarus.";
+
+/// Default cap on subscriptions across all subscribers.
+pub const DEFAULT_MAX_SUBSCRIPTIONS: u64 = 10_000;
+
+/// Default cap on subscriptions held by one subscriber.
+pub const DEFAULT_MAX_SUBSCRIPTIONS_PER_SUBSCRIBER: u64 = 100;
+
+/// Last byte of self-authenticating principals, which users sign with.
+const SELF_AUTHENTICATING_TAG: u8 = 0x02;
+
+/// An event as passed to subscriber callbacks.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct Event {
+    /// Event id, unique per publishing canister.
+    pub id: u64,
+    /// Topic the event was published on.
+    pub topic: String,
+    /// Application-defined payload.
+    pub payload: Vec<u8>,
+    /// Publication time in nanoseconds since the epoch.
+    pub published_at: u64,
+}
+
+/// A canister method registered for a topic.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct Subscription {
+    /// Topic subscribed to.
+    pub topic: String,
+    /// Canister receiving the events.
+    pub subscriber: Principal,
+    /// Update method called with each [`Event`].
+    pub callback_method: String,
+}
+
+/// One event queued for one subscriber.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct Delivery {
+    /// Queue entry id.
+    pub id: u64,
+    /// The event to deliver.
+    pub event: Event,
+    /// Canister to call.
+    pub subscriber: Principal,
+    /// Method to call.
+    pub callback_method: String,
+    /// Failed attempts so far.
+    pub attempts: u32,
+    /// Earliest time of the next attempt in nanoseconds since the epoch.
+    pub next_attempt_at: u64,
+    /// Error of the last failed attempt.
+    pub last_error: Option<String>,
+}
+
+macro_rules! candid_storable {
+    ($type:ty) => {
+        impl Storable for $type {
+            fn to_bytes(&self) -> Cow<'_, [u8]> {
+                Cow::Owned(Encode!(self).unwrap_or_default())
+            }
+
+            fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+                Decode!(&bytes, Self)
+                    .unwrap_or_else(|e| unreachable!("corrupt event queue entry: {e}"))
+            }
+
+            fn into_bytes(self) -> Vec<u8> {
+                Encode!(&self).unwrap_or_default()
+            }
+
+            const BOUND: Bound = Bound::Unbounded;
+        }
+    };
+}
+
+candid_storable!(Subscription);
+candid_storable!(Delivery);
+
+thread_local! {
+    static SUBSCRIPTIONS: RefCell<StableBTreeMap<u64, Subscription, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(EVENT_SUBSCRIPTIONS_MEMORY_ID))
+    );
+
+    static QUEUE: RefCell<StableBTreeMap<u64, Delivery, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(EVENT_QUEUE_MEMORY_ID))
+    );
+
+    static DROPPED: RefCell<u64> = const { RefCell::new(0) };
+
+    static LAST_EVENT_ID: RefCell<u64> = const { RefCell::new(0) };
+
+    static DELIVERING: RefCell<bool> = const { RefCell::new(false) };
+
+    static MAX_SUBSCRIPTIONS: Cell<u64> = const { Cell::new(DEFAULT_MAX_SUBSCRIPTIONS) };
+
+    static MAX_PER_SUBSCRIBER: Cell<u64> =
+        const { Cell::new(DEFAULT_MAX_SUBSCRIPTIONS_PER_SUBSCRIBER) };
+}
+
+fn is_reserved(topic: &str) -> bool {
+    topic.starts_with(RESERVED_TOPIC_PREFIX)
+}
+
+/// Whether `principal` is a user or anonymous rather than a canister.
+fn is_user(principal: Principal) -> bool {
+    principal == Principal::anonymous()
+        || principal.as_slice().last() == Some(&SELF_AUTHENTICATING_TAG)
+}
+
+/// Sets the cap on subscriptions across all subscribers.
+pub fn set_max_subscriptions(max: u64) {
+    MAX_SUBSCRIPTIONS.with(|cell| cell.set(max));
+}
+
+/// Sets the cap on subscriptions held by one subscriber.
+pub fn set_max_subscriptions_per_subscriber(max: u64) {
+    MAX_PER_SUBSCRIBER.with(|cell| cell.set(max));
+}
+
+/// Registers `subscriber`'s `callback_method` for `topic`.
+///
+/// Subscribing again with the same method is a no-op.
+///
+/// # Errors
+///
+/// - `IcarusError::AccessDenied` if the topic starts with
+///   [`RESERVED_TOPIC_PREFIX`] or `subscriber` is not a canister
+/// - `IcarusError::ConfigurationError` if the topic or method name is empty
+///   or the topic is longer than [`MAX_TOPIC_LENGTH`]
+/// - `IcarusError::ResourceLimitExceeded` if the subscription would exceed
+///   the total or per-subscriber cap
+pub fn subscribe(topic: &str, subscriber: Principal, callback_method: &str) -> Result<()> {
+    if is_reserved(topic) {
+        return Err(IcarusError::AccessDenied(format!(
+            "Topics starting with '{RESERVED_TOPIC_PREFIX}' are reserved"
+        )));
+    }
+    subscribe_reserved(topic, subscriber, callback_method)
+}
+
+/// Registers a subscription without refusing reserved topics, for the
+/// owner-gated APIs built on them.
+pub(crate) fn subscribe_reserved(
+    topic: &str,
+    subscriber: Principal,
+    callback_method: &str,
+) -> Result<()> {
+    if topic.is_empty() || topic.len() > MAX_TOPIC_LENGTH {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Topic must be 1 to {MAX_TOPIC_LENGTH} bytes"
+        )));
+    }
+    if callback_method.is_empty() {
+        return Err(IcarusError::ConfigurationError(
+            "Callback method cannot be empty".to_string(),
+        ));
+    }
+    if is_user(subscriber) {
+        return Err(IcarusError::AccessDenied(format!(
+            "Only canisters can subscribe to events, not {subscriber}"
+        )));
+    }
+
+    let subscription = Subscription {
+        topic: topic.to_string(),
+        subscriber,
+        callback_method: callback_method.to_string(),
+    };
+    SUBSCRIPTIONS.with(|subscriptions| {
+        let mut subscriptions = subscriptions.borrow_mut();
+        let mut held = 0;
+        for entry in subscriptions.iter() {
+            let existing = entry.value();
+            if existing == subscription {
+                return Ok(());
+            }
+            if existing.subscriber == subscriber {
+                held += 1;
+            }
+        }
+
+        let max_per_subscriber = MAX_PER_SUBSCRIBER.with(Cell::get);
+        if held >= max_per_subscriber {
+            return Err(IcarusError::ResourceLimitExceeded {
+                resource: "event subscriptions".to_string(),
+                message: format!("{subscriber} already holds {max_per_subscriber}"),
+            });
+        }
+        let max = MAX_SUBSCRIPTIONS.with(Cell::get);
+        if subscriptions.len() >= max {
+            return Err(IcarusError::ResourceLimitExceeded {
+                resource: "event subscriptions".to_string(),
+                message: format!("the canister already holds {max}"),
+            });
+        }
+
+        let id = subscriptions.last_key_value().map_or(0, |(id, _)| id + 1);
+        subscriptions.insert(id, subscription);
+        Ok(())
+    })
+}
+
+/// Removes a subscription, returning whether it existed. Subscriptions to
+/// reserved topics are left alone.
+///
+/// Deliveries already queued for it are still attempted.
+pub fn unsubscribe(topic: &str, subscriber: Principal, callback_method: &str) -> bool {
+    !is_reserved(topic) && unsubscribe_reserved(topic, subscriber, callback_method)
+}
+
+/// Removes a subscription, including one to a reserved topic.
+pub(crate) fn unsubscribe_reserved(
+    topic: &str,
+    subscriber: Principal,
+    callback_method: &str,
+) -> bool {
+    SUBSCRIPTIONS.with(|subscriptions| {
+        let mut subscriptions = subscriptions.borrow_mut();
+        let found = subscriptions.iter().find_map(|entry| {
+            let s = entry.value();
+            (s.topic == topic && s.subscriber == subscriber && s.callback_method == callback_method)
+                .then_some(*entry.key())
+        });
+        found.is_some_and(|id| subscriptions.remove(&id).is_some())
+    })
+}
+
+/// Lists all subscriptions, optionally only those for `topic`.
+#[must_use]
+pub fn list_subscriptions(topic: Option<&str>) -> Vec<Subscription> {
+    SUBSCRIPTIONS.with(|subscriptions| {
+        subscriptions
+            .borrow()
+            .iter()
+            .map(|entry| entry.value())
+            .filter(|subscription| topic.map_or(true, |topic| subscription.topic == topic))
+            .collect()
+    })
+}
+
+/// Publishes an event, queueing a delivery for every subscriber of `topic`,
+/// and returns the event id.
+pub fn publish(topic: &str, payload: Vec<u8>) -> u64 {
+    let now = crate::time::now_nanos();
+    let subscribers = list_subscriptions(Some(topic));
+
+    QUEUE.with(|queue| {
+        let mut queue = queue.borrow_mut();
+        let mut next_id = queue.last_key_value().map_or(0, |(id, _)| id + 1);
+        // Ids are publication times, bumped when several events share a timestamp
+        let id = LAST_EVENT_ID.with(|last| {
+            let mut last = last.borrow_mut();
+            *last = now.max(*last + 1);
+            *last
+        });
+        let event = Event {
+            id,
+            topic: topic.to_string(),
+            payload,
+            published_at: now,
+        };
+        for subscription in subscribers {
+            queue.insert(
+                next_id,
+                Delivery {
+                    id: next_id,
+                    event: event.clone(),
+                    subscriber: subscription.subscriber,
+                    callback_method: subscription.callback_method,
+                    attempts: 0,
+                    next_attempt_at: now,
+                    last_error: None,
+                },
+            );
+            next_id += 1;
+        }
+        event.id
+    })
+}
+
+/// Returns the number of queued deliveries.
+#[must_use]
+pub fn pending_deliveries() -> u64 {
+    QUEUE.with(|queue| queue.borrow().len())
+}
+
+/// Returns the queued deliveries, oldest first.
+#[must_use]
+pub fn list_pending(limit: usize) -> Vec<Delivery> {
+    QUEUE.with(|queue| {
+        queue
+            .borrow()
+            .iter()
+            .map(|entry| entry.value())
+            .take(limit)
+            .collect()
+    })
+}
+
+/// Returns the number of deliveries dropped after [`MAX_ATTEMPTS`] failures.
+#[must_use]
+pub fn dropped_deliveries() -> u64 {
+    DROPPED.with(|dropped| *dropped.borrow())
+}
+
+/// Returns up to `limit` deliveries whose next attempt is due.
+///
+/// Each must be reported back with [`complete`]; until then it stays queued.
+#[must_use]
+pub fn take_due(limit: usize) -> Vec<Delivery> {
+    let now = crate::time::now_nanos();
+    QUEUE.with(|queue| {
+        queue
+            .borrow()
+            .iter()
+            .map(|entry| entry.value())
+            .filter(|delivery| delivery.next_attempt_at <= now)
+            .take(limit)
+            .collect()
+    })
+}
+
+/// Records the outcome of a delivery attempt.
+///
+/// Successful deliveries leave the queue. Failed ones are retried with
+/// exponential backoff, and dropped after [`MAX_ATTEMPTS`] attempts.
+pub fn complete(delivery_id: u64, outcome: std::result::Result<(), String>) {
+    QUEUE.with(|queue| {
+        let mut queue = queue.borrow_mut();
+        let Some(mut delivery) = queue.remove(&delivery_id) else {
+            return;
+        };
+        let Err(error) = outcome else {
+            return;
+        };
+
+        delivery.attempts += 1;
+        if delivery.attempts >= MAX_ATTEMPTS {
+            crate::logging::warn(format_args!(
+                "dropping event {} for {}::{} after {} attempts: {error}",
+                delivery.event.id, delivery.subscriber, delivery.callback_method, delivery.attempts
+            ));
+            DROPPED.with(|dropped| *dropped.borrow_mut() += 1);
+            return;
+        }
+
+        let delay = INITIAL_RETRY_DELAY_NANOS
+            .saturating_mul(1 << (delivery.attempts - 1).min(20))
+            .min(MAX_RETRY_DELAY_NANOS);
+        delivery.next_attempt_at = crate::time::now_nanos().saturating_add(delay);
+        delivery.last_error = Some(error);
+        queue.insert(delivery_id, delivery);
+    });
+}
+
+/// Sends up to `limit` due deliveries and returns how many succeeded.
+///
+/// Calls are made one after another so a slow subscriber delays, but cannot
+/// reorder, the rest of the batch. Returns 0 without sending anything while
+/// an earlier batch is still in flight, so a delivery is never sent twice at
+/// once.
+pub async fn deliver_pending(limit: usize) -> usize {
+    if DELIVERING.with(|delivering| delivering.replace(true)) {
+        return 0;
+    }
+    let mut delivered = 0;
+    for delivery in take_due(limit) {
+        let outcome =
+            ic_cdk::call::Call::bounded_wait(delivery.subscriber, &delivery.callback_method)
+                .with_arg(&delivery.event)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string());
+        if outcome.is_ok() {
+            delivered += 1;
+        }
+        complete(delivery.id, outcome);
+    }
+    DELIVERING.with(|delivering| delivering.replace(false));
+    delivered
+}
+
+/// Starts a timer that calls [`deliver_pending`] every `interval`.
+///
+/// Timers do not survive upgrades, so call this from both `init` and
+/// `post_upgrade`.
+#[cfg(feature = "ic-canister")]
+pub fn start_delivery(interval: std::time::Duration, batch_size: usize) -> ic_cdk_timers::TimerId {
+    ic_cdk_timers::set_timer_interval(interval, move || {
+        ic_cdk::futures::spawn(async move {
+            deliver_pending(batch_size).await;
+        });
+    })
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+
+    fn canister(id: u8) -> Principal {
+        Principal::from_slice(&[0, 0, 0, 0, 0, 0, 0, id, 1, 1])
+    }
+
+    #[test]
+    fn test_publish_fans_out_to_subscribers() {
+        subscribe("jobs.done", canister(1), "on_job").unwrap();
+        subscribe("jobs.done", canister(2), "on_job").unwrap();
+        subscribe("jobs.done", canister(2), "on_job").unwrap();
+        subscribe("jobs.failed", canister(1), "on_failure").unwrap();
+        assert_eq!(list_subscriptions(Some("jobs.done")).len(), 2);
+
+        publish("jobs.done", b"42".to_vec());
+        let due = take_due(10);
+        assert_eq!(due.len(), 2);
+        assert_ne!(publish("jobs.done", Vec::new()), due[0].event.id);
+        assert!(due.iter().all(|d| d.event.payload == b"42"));
+
+        assert!(unsubscribe("jobs.done", canister(2), "on_job"));
+        assert!(!unsubscribe("jobs.done", canister(2), "on_job"));
+        assert!(subscribe("", canister(1), "on_job").is_err());
+    }
+
+    #[test]
+    fn test_failed_deliveries_back_off_and_drop() {
+        crate::time::set_time_override(Some(1_000));
+        subscribe("ticks", canister(3), "on_tick").unwrap();
+        publish("ticks", Vec::new());
+
+        let delivery = take_due(1).remove(0);
+        complete(delivery.id, Err("canister stopped".to_string()));
+        assert!(take_due(1).is_empty(), "retry waits for the backoff");
+
+        let queued = list_pending(1).remove(0);
+        assert_eq!(queued.attempts, 1);
+        assert_eq!(queued.next_attempt_at, 1_000 + INITIAL_RETRY_DELAY_NANOS);
+        assert_eq!(queued.last_error.as_deref(), Some("canister stopped"));
+
+        for _ in 1..MAX_ATTEMPTS {
+            complete(delivery.id, Err("canister stopped".to_string()));
+        }
+        crate::time::set_time_override(None);
+        assert_eq!(pending_deliveries(), 0);
+        assert_eq!(dropped_deliveries(), 1);
+    }
+
+    #[test]
+    fn test_only_canisters_can_subscribe() {
+        let user = Principal::self_authenticating([7u8; 32]);
+        for subscriber in [user, Principal::anonymous()] {
+            assert!(matches!(
+                subscribe("orders", subscriber, "on_order"),
+                Err(IcarusError::AccessDenied(_))
+            ));
+        }
+        assert!(list_subscriptions(Some("orders")).is_empty());
+        subscribe("orders", canister(4), "on_order").unwrap();
+    }
+
+    #[test]
+    fn test_subscriptions_are_capped() {
+        set_max_subscriptions_per_subscriber(2);
+        subscribe("a", canister(5), "on_event").unwrap();
+        subscribe("b", canister(5), "on_event").unwrap();
+        subscribe("b", canister(5), "on_event").unwrap();
+        assert!(matches!(
+            subscribe("c", canister(5), "on_event"),
+            Err(IcarusError::ResourceLimitExceeded { .. })
+        ));
+
+        set_max_subscriptions(3);
+        subscribe("a", canister(6), "on_event").unwrap();
+        assert!(matches!(
+            subscribe("b", canister(6), "on_event"),
+            Err(IcarusError::ResourceLimitExceeded { .. })
+        ));
+        assert_eq!(list_subscriptions(None).len(), 3);
+
+        set_max_subscriptions(DEFAULT_MAX_SUBSCRIPTIONS);
+        set_max_subscriptions_per_subscriber(DEFAULT_MAX_SUBSCRIPTIONS_PER_SUBSCRIBER);
+    }
+}
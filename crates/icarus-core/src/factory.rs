@@ -0,0 +1,672 @@
+//! Spawning isolated per-user instances of a child canister.
+//!
+//! A factory canister holds the WASM of a child canister as a blob named
+//! [`CHILD_WASM_BLOB`], stored with [`store_child_wasm`] (for example from
+//! `include_bytes!` in `init` and `post_upgrade`) or uploaded through the blob
+//! tools. [`spawn_instance`] creates a canister through the management
+//! canister with [`CREATE_CANISTER_FEE`] plus the cycles set with
+//! [`set_initial_cycles`], installs the child WASM with the owner as its
+//! `init` argument, which is the admin `mcp!{}` canisters are initialized
+//! with, and tracks the instance in stable memory. The factory stays the
+//! child's controller, so it can top it up with [`top_up_instance`] and
+//! upgrade it later; the owner administers it through auth.
+//!
+//! Modules too large for one call are uploaded to the child's chunk store and
+//! installed with `install_chunked_code`. If installing fails, the instance
+//! is still tracked with `installed: false` and [`install_instance`] retries.
+//! Spawning and topping up are refused when they would take the balance below
+//! the reserve set in [`crate::cycles`].
+//!
+//! Off-chain the management canister calls go to a handler installed with
+//! [`set_management_handler`]. `mcp!{ factory = true }` adds the owner-only
+//! `spawn_instance`, `install_instance`, `top_up_instance`, `list_instances`
+//! and `get_factory_status` tools.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use icarus_core::factory;
+//!
+//! const CHILD_WASM: &[u8] = include_bytes!("../../child/child.wasm.gz");
+//!
+//! #[ic_cdk::post_upgrade]
+//! fn post_upgrade() {
+//!     factory::store_child_wasm(CHILD_WASM).expect("child WASM");
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::blobs::{self, BlobInfo};
+use crate::memory::{self, StableMemory, FACTORY_INSTANCES_MEMORY_ID};
+use crate::{IcarusError, Result};
+
+/// Name of the blob holding the child WASM; the newest one is installed.
+pub const CHILD_WASM_BLOB: &str = "child.wasm";
+
+/// Fee the management canister charges for creating a canister on a
+/// 13-node subnet.
+pub const CREATE_CANISTER_FEE: u128 = 500_000_000_000;
+
+/// Cycles a new instance starts with by default, on top of the creation fee.
+pub const DEFAULT_INITIAL_CYCLES: u128 = 1_000_000_000_000;
+
+/// Largest module installed with a single `install_code` call.
+pub const MAX_DIRECT_INSTALL_SIZE: usize = 1_900_000;
+
+/// Size of the chunks larger modules are uploaded in.
+pub const WASM_CHUNK_SIZE: usize = 1_048_576;
+
+const SERVICE: &str = "management canister";
+
+/// A management canister call made off-chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManagementRequest {
+    /// `create_canister` with the factory as controller.
+    CreateCanister {
+        /// Cycles attached, including the creation fee.
+        cycles: u128,
+    },
+    /// `install_code` in install mode.
+    InstallCode {
+        /// Canister to install.
+        canister_id: Principal,
+        /// WASM module.
+        wasm_module: Vec<u8>,
+        /// Candid-encoded `init` argument.
+        arg: Vec<u8>,
+    },
+    /// `upload_chunk` to a canister's own chunk store.
+    UploadChunk {
+        /// Canister whose chunk store receives the chunk.
+        canister_id: Principal,
+        /// Chunk of the WASM module.
+        chunk: Vec<u8>,
+    },
+    /// `install_chunked_code` in install mode from the canister's chunk store.
+    InstallChunkedCode {
+        /// Canister to install.
+        canister_id: Principal,
+        /// Hashes of the uploaded chunks, in order.
+        chunk_hashes: Vec<Vec<u8>>,
+        /// SHA-256 of the whole module.
+        wasm_module_hash: Vec<u8>,
+        /// Candid-encoded `init` argument.
+        arg: Vec<u8>,
+    },
+    /// `clear_chunk_store`.
+    ClearChunkStore {
+        /// Canister whose chunk store is cleared.
+        canister_id: Principal,
+    },
+    /// `deposit_cycles`.
+    DepositCycles {
+        /// Canister receiving the cycles.
+        canister_id: Principal,
+        /// Cycles attached.
+        cycles: u128,
+    },
+}
+
+/// Answer to a [`ManagementRequest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManagementReply {
+    /// Id of the created canister.
+    Created(Principal),
+    /// Hash of an uploaded chunk.
+    ChunkHash(Vec<u8>),
+    /// The call succeeded without a result.
+    Done,
+}
+
+/// Handler that answers management canister calls off-chain.
+#[cfg(not(feature = "ic-canister"))]
+pub type ManagementHandler = Box<dyn Fn(&ManagementRequest) -> Result<ManagementReply>>;
+
+/// A canister spawned by the factory.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct Instance {
+    /// The instance's canister id.
+    pub canister_id: Principal,
+    /// Principal the instance was initialized with as admin.
+    pub owner: Principal,
+    /// When the instance was created, in nanoseconds since the epoch.
+    pub created_at: u64,
+    /// Principal that spawned it.
+    pub created_by: Principal,
+    /// Hex-encoded SHA-256 of the child WASM it was created for.
+    pub wasm_sha256: String,
+    /// Whether the child WASM was installed.
+    pub installed: bool,
+    /// Cycles given to the instance, beyond the creation fee.
+    pub cycles_deposited: u128,
+}
+
+/// The child WASM and the instances spawned so far.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct FactoryStatus {
+    /// The blob installed in new instances, if one is stored.
+    pub child_wasm: Option<BlobInfo>,
+    /// Cycles a new instance starts with.
+    pub initial_cycles: u128,
+    /// Instances tracked.
+    pub instances: u64,
+    /// Instances whose install failed.
+    pub uninstalled: u64,
+}
+
+impl Storable for Instance {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap_or_default())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap_or_else(|e| unreachable!("corrupt factory instance: {e}"))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).unwrap_or_default()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+thread_local! {
+    static INSTANCES: RefCell<StableBTreeMap<Principal, Instance, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(FACTORY_INSTANCES_MEMORY_ID))
+    );
+
+    static INITIAL_CYCLES: RefCell<u128> = const { RefCell::new(DEFAULT_INITIAL_CYCLES) };
+}
+
+#[cfg(not(feature = "ic-canister"))]
+thread_local! {
+    static MANAGEMENT_HANDLER: RefCell<Option<ManagementHandler>> = const { RefCell::new(None) };
+}
+
+/// Installs the handler for off-chain management canister calls on the
+/// current thread, or removes it with `None`.
+#[cfg(not(feature = "ic-canister"))]
+pub fn set_management_handler(handler: Option<ManagementHandler>) {
+    MANAGEMENT_HANDLER.with(|cell| *cell.borrow_mut() = handler);
+}
+
+/// Sets the cycles new instances start with, on top of the creation fee.
+pub fn set_initial_cycles(cycles: u128) {
+    INITIAL_CYCLES.with(|initial| *initial.borrow_mut() = cycles);
+}
+
+/// Returns the cycles new instances start with.
+#[must_use]
+pub fn initial_cycles() -> u128 {
+    INITIAL_CYCLES.with(|initial| *initial.borrow())
+}
+
+/// Stores `wasm` as the child WASM blob, replacing earlier ones. Storing the
+/// same module again keeps the existing blob.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if `wasm` is empty.
+pub fn store_child_wasm(wasm: &[u8]) -> Result<BlobInfo> {
+    if wasm.is_empty() {
+        return Err(IcarusError::ConfigurationError(
+            "The child WASM cannot be empty".to_string(),
+        ));
+    }
+    let sha256 = to_hex(&Sha256::digest(wasm));
+    if let Some(info) = child_wasm().filter(|info| info.sha256.as_deref() == Some(&sha256)) {
+        return Ok(info);
+    }
+
+    let id = blobs::upload_begin(CHILD_WASM_BLOB, wasm.len() as u64, "application/wasm")?;
+    for (index, chunk) in (0u32..).zip(wasm.chunks(blobs::MAX_CHUNK_SIZE)) {
+        blobs::upload_chunk(id, index, chunk)?;
+    }
+    let info = blobs::upload_commit(id)?;
+    for old in blobs::list_blobs() {
+        if old.name == CHILD_WASM_BLOB && old.id != id {
+            blobs::delete_blob(old.id);
+        }
+    }
+    Ok(info)
+}
+
+/// Returns the newest committed blob named [`CHILD_WASM_BLOB`].
+#[must_use]
+pub fn child_wasm() -> Option<BlobInfo> {
+    blobs::list_blobs()
+        .into_iter()
+        .rev()
+        .find(|info| info.name == CHILD_WASM_BLOB)
+}
+
+/// Creates a canister, installs the child WASM in it with `owner` as admin
+/// and tracks it.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if no child WASM is stored,
+/// `IcarusError::ResourceLimitExceeded` if the cycles would take the balance
+/// below the reserve and `IcarusError::ExternalServiceError` if a management
+/// canister call fails. When only the install fails the instance is tracked
+/// and [`install_instance`] retries it.
+pub async fn spawn_instance(owner: Principal, caller: Principal) -> Result<Instance> {
+    let (info, _) = load_child_wasm()?;
+    let cycles = initial_cycles();
+    ensure_affordable(
+        CREATE_CANISTER_FEE.saturating_add(cycles),
+        "spawning an instance",
+    )?;
+
+    let reply = call(ManagementRequest::CreateCanister {
+        cycles: CREATE_CANISTER_FEE.saturating_add(cycles),
+    })
+    .await?;
+    let ManagementReply::Created(canister_id) = reply else {
+        return Err(management_error("expected a canister id"));
+    };
+
+    let instance = Instance {
+        canister_id,
+        owner,
+        created_at: crate::time::now_nanos(),
+        created_by: caller,
+        wasm_sha256: info.sha256.unwrap_or_default(),
+        installed: false,
+        cycles_deposited: cycles,
+    };
+    INSTANCES.with(|instances| instances.borrow_mut().insert(canister_id, instance));
+    install_instance(canister_id).await
+}
+
+/// Installs the child WASM in a tracked instance whose install failed.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if the canister is not an
+/// uninstalled instance or no child WASM is stored, and
+/// `IcarusError::ExternalServiceError` if a management canister call fails.
+pub async fn install_instance(canister_id: Principal) -> Result<Instance> {
+    let mut instance = instance(canister_id)
+        .filter(|instance| !instance.installed)
+        .ok_or_else(|| {
+            IcarusError::ConfigurationError(format!(
+                "{canister_id} is not an instance waiting to be installed"
+            ))
+        })?;
+    let (info, wasm) = load_child_wasm()?;
+    let arg = Encode!(&instance.owner).map_err(|e| IcarusError::CandidError(e.to_string()))?;
+
+    install(canister_id, wasm, arg).await.map_err(|e| {
+        management_error(format!(
+            "instance {canister_id} was created but installing it failed, retry with install_instance: {e}"
+        ))
+    })?;
+
+    instance.installed = true;
+    instance.wasm_sha256 = info.sha256.unwrap_or_default();
+    INSTANCES.with(|instances| instances.borrow_mut().insert(canister_id, instance.clone()));
+    Ok(instance)
+}
+
+/// Deposits `cycles` from the factory's balance into a tracked instance.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` for an unknown instance,
+/// `IcarusError::ResourceLimitExceeded` if the cycles would take the balance
+/// below the reserve and `IcarusError::ExternalServiceError` if the deposit
+/// fails.
+pub async fn top_up_instance(canister_id: Principal, cycles: u128) -> Result<Instance> {
+    if instance(canister_id).is_none() {
+        return Err(IcarusError::ConfigurationError(format!(
+            "{canister_id} is not an instance of this factory"
+        )));
+    }
+    ensure_affordable(cycles, "topping up an instance")?;
+
+    call(ManagementRequest::DepositCycles {
+        canister_id,
+        cycles,
+    })
+    .await?;
+
+    INSTANCES.with(|instances| {
+        let mut instances = instances.borrow_mut();
+        let mut instance = instances
+            .get(&canister_id)
+            .ok_or_else(|| management_error(format!("instance {canister_id} disappeared")))?;
+        instance.cycles_deposited = instance.cycles_deposited.saturating_add(cycles);
+        instances.insert(canister_id, instance.clone());
+        Ok(instance)
+    })
+}
+
+/// Returns a tracked instance.
+#[must_use]
+pub fn instance(canister_id: Principal) -> Option<Instance> {
+    INSTANCES.with(|instances| instances.borrow().get(&canister_id))
+}
+
+/// Lists tracked instances, optionally only those of one owner.
+#[must_use]
+pub fn list_instances(owner: Option<Principal>) -> Vec<Instance> {
+    INSTANCES.with(|instances| {
+        instances
+            .borrow()
+            .iter()
+            .map(|entry| entry.value())
+            .filter(|instance| owner.map_or(true, |owner| instance.owner == owner))
+            .collect()
+    })
+}
+
+/// Returns the child WASM and instance counts.
+#[must_use]
+pub fn factory_status() -> FactoryStatus {
+    let instances = list_instances(None);
+    FactoryStatus {
+        child_wasm: child_wasm(),
+        initial_cycles: initial_cycles(),
+        instances: instances.len() as u64,
+        uninstalled: instances.iter().filter(|i| !i.installed).count() as u64,
+    }
+}
+
+fn load_child_wasm() -> Result<(BlobInfo, Vec<u8>)> {
+    let missing = || {
+        IcarusError::ConfigurationError(format!(
+            "No child WASM; store one with store_child_wasm or upload a blob named '{CHILD_WASM_BLOB}'"
+        ))
+    };
+    let info = child_wasm().ok_or_else(missing)?;
+    let wasm = blobs::read_blob(info.id).ok_or_else(missing)?;
+    Ok((info, wasm))
+}
+
+async fn install(canister_id: Principal, wasm: Vec<u8>, arg: Vec<u8>) -> Result<()> {
+    if wasm.len() <= MAX_DIRECT_INSTALL_SIZE {
+        call(ManagementRequest::InstallCode {
+            canister_id,
+            wasm_module: wasm,
+            arg,
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let mut chunk_hashes = Vec::new();
+    for chunk in wasm.chunks(WASM_CHUNK_SIZE) {
+        let reply = call(ManagementRequest::UploadChunk {
+            canister_id,
+            chunk: chunk.to_vec(),
+        })
+        .await?;
+        let ManagementReply::ChunkHash(hash) = reply else {
+            return Err(management_error("expected a chunk hash"));
+        };
+        chunk_hashes.push(hash);
+    }
+    call(ManagementRequest::InstallChunkedCode {
+        canister_id,
+        chunk_hashes,
+        wasm_module_hash: Sha256::digest(&wasm).to_vec(),
+        arg,
+    })
+    .await?;
+    call(ManagementRequest::ClearChunkStore { canister_id }).await?;
+    Ok(())
+}
+
+fn ensure_affordable(cycles: u128, action: &str) -> Result<()> {
+    let status = crate::cycles::cycles_status();
+    if status.balance.saturating_sub(cycles) < status.reserve {
+        return Err(IcarusError::ResourceLimitExceeded {
+            resource: "cycles".to_string(),
+            message: format!(
+                "{action} costs {cycles} cycles and the balance {} is too close to the reserve of {}",
+                status.balance, status.reserve
+            ),
+        });
+    }
+    Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn management_error(message: impl Into<String>) -> IcarusError {
+    IcarusError::ExternalServiceError {
+        service: SERVICE.to_string(),
+        message: message.into(),
+    }
+}
+
+#[cfg(not(feature = "ic-canister"))]
+#[allow(clippy::unused_async)]
+async fn call(request: ManagementRequest) -> Result<ManagementReply> {
+    MANAGEMENT_HANDLER.with(|cell| match cell.borrow().as_ref() {
+        Some(handler) => handler(&request),
+        None => Err(management_error(
+            "the management canister is unavailable outside a canister; install a mock handler",
+        )),
+    })
+}
+
+#[cfg(feature = "ic-canister")]
+async fn call(request: ManagementRequest) -> Result<ManagementReply> {
+    use ic_cdk::management_canister::{
+        clear_chunk_store, create_canister_with_extra_cycles, deposit_cycles, install_chunked_code,
+        install_code, upload_chunk, CanisterInstallMode, ChunkHash, ClearChunkStoreArgs,
+        CreateCanisterArgs, DepositCyclesArgs, InstallChunkedCodeArgs, InstallCodeArgs,
+        UploadChunkArgs,
+    };
+
+    let done = |result: std::result::Result<(), _>| {
+        result
+            .map(|()| ManagementReply::Done)
+            .map_err(|e: ic_cdk::call::Error| management_error(e.to_string()))
+    };
+    match request {
+        ManagementRequest::CreateCanister { cycles } => {
+            create_canister_with_extra_cycles(&CreateCanisterArgs { settings: None }, cycles)
+                .await
+                .map(|result| ManagementReply::Created(result.canister_id))
+                .map_err(|e| management_error(e.to_string()))
+        }
+        ManagementRequest::InstallCode {
+            canister_id,
+            wasm_module,
+            arg,
+        } => done(
+            install_code(&InstallCodeArgs {
+                mode: CanisterInstallMode::Install,
+                canister_id,
+                wasm_module,
+                arg,
+            })
+            .await,
+        ),
+        ManagementRequest::UploadChunk { canister_id, chunk } => {
+            upload_chunk(&UploadChunkArgs { canister_id, chunk })
+                .await
+                .map(|result| ManagementReply::ChunkHash(result.hash))
+                .map_err(|e| management_error(e.to_string()))
+        }
+        ManagementRequest::InstallChunkedCode {
+            canister_id,
+            chunk_hashes,
+            wasm_module_hash,
+            arg,
+        } => done(
+            install_chunked_code(&InstallChunkedCodeArgs {
+                mode: CanisterInstallMode::Install,
+                target_canister: canister_id,
+                store_canister: None,
+                chunk_hashes_list: chunk_hashes
+                    .into_iter()
+                    .map(|hash| ChunkHash { hash })
+                    .collect(),
+                wasm_module_hash,
+                arg,
+            })
+            .await,
+        ),
+        ManagementRequest::ClearChunkStore { canister_id } => {
+            done(clear_chunk_store(&ClearChunkStoreArgs { canister_id }).await)
+        }
+        ManagementRequest::DepositCycles {
+            canister_id,
+            cycles,
+        } => done(deposit_cycles(&DepositCyclesArgs { canister_id }, cycles).await),
+    }
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    /// Records requests, creating canisters with ids 1, 2, ...; installs fail
+    /// while `fail_installs` is set.
+    fn fake_management(
+        requests: Rc<RefCell<Vec<ManagementRequest>>>,
+        fail_installs: Rc<RefCell<bool>>,
+    ) -> ManagementHandler {
+        Box::new(move |request| {
+            requests.borrow_mut().push(request.clone());
+            match request {
+                ManagementRequest::CreateCanister { .. } => {
+                    let created = requests
+                        .borrow()
+                        .iter()
+                        .filter(|r| matches!(r, ManagementRequest::CreateCanister { .. }))
+                        .count();
+                    Ok(ManagementReply::Created(Principal::from_slice(&[
+                        u8::try_from(created).unwrap(),
+                    ])))
+                }
+                ManagementRequest::InstallCode { .. }
+                | ManagementRequest::InstallChunkedCode { .. }
+                    if *fail_installs.borrow() =>
+                {
+                    Err(management_error("out of cycles"))
+                }
+                ManagementRequest::UploadChunk { chunk, .. } => {
+                    Ok(ManagementReply::ChunkHash(Sha256::digest(chunk).to_vec()))
+                }
+                _ => Ok(ManagementReply::Done),
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_spawn_installs_child_with_owner() {
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        set_management_handler(Some(fake_management(
+            Rc::clone(&requests),
+            Rc::new(RefCell::new(false)),
+        )));
+        let owner = Principal::from_slice(&[9]);
+        assert!(matches!(
+            spawn_instance(owner, owner).await,
+            Err(IcarusError::ConfigurationError(_))
+        ));
+
+        let info = store_child_wasm(b"\0asm child").unwrap();
+        assert_eq!(store_child_wasm(b"\0asm child").unwrap().id, info.id);
+        let instance = spawn_instance(owner, owner).await.unwrap();
+        assert!(instance.installed);
+        assert_eq!(instance.owner, owner);
+        assert_eq!(instance.cycles_deposited, DEFAULT_INITIAL_CYCLES);
+        assert_eq!(
+            requests.borrow()[1],
+            ManagementRequest::InstallCode {
+                canister_id: instance.canister_id,
+                wasm_module: b"\0asm child".to_vec(),
+                arg: Encode!(&owner).unwrap(),
+            }
+        );
+
+        let topped = top_up_instance(instance.canister_id, 5).await.unwrap();
+        assert_eq!(topped.cycles_deposited, DEFAULT_INITIAL_CYCLES + 5);
+        assert!(top_up_instance(owner, 5).await.is_err());
+        assert_eq!(list_instances(Some(owner)), vec![topped]);
+        assert!(list_instances(Some(Principal::anonymous())).is_empty());
+
+        crate::cycles::set_reserve(1_000);
+        crate::cycles::set_mock_balance(CREATE_CANISTER_FEE);
+        assert!(matches!(
+            spawn_instance(owner, owner).await,
+            Err(IcarusError::ResourceLimitExceeded { .. })
+        ));
+        crate::cycles::set_mock_balance(u128::MAX);
+        set_management_handler(None);
+    }
+
+    #[tokio::test]
+    async fn test_failed_install_is_tracked_and_retried() {
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        let fail_installs = Rc::new(RefCell::new(true));
+        set_management_handler(Some(fake_management(
+            Rc::clone(&requests),
+            Rc::clone(&fail_installs),
+        )));
+        store_child_wasm(b"\0asm").unwrap();
+        let owner = Principal::from_slice(&[7]);
+
+        assert!(spawn_instance(owner, owner).await.is_err());
+        let pending = list_instances(Some(owner)).remove(0);
+        assert!(!pending.installed);
+        assert_eq!(factory_status().uninstalled, 1);
+
+        *fail_installs.borrow_mut() = false;
+        assert!(
+            install_instance(pending.canister_id)
+                .await
+                .unwrap()
+                .installed
+        );
+        assert!(install_instance(pending.canister_id).await.is_err());
+        set_management_handler(None);
+    }
+
+    #[tokio::test]
+    async fn test_large_modules_are_installed_in_chunks() {
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        set_management_handler(Some(fake_management(
+            Rc::clone(&requests),
+            Rc::new(RefCell::new(false)),
+        )));
+        let wasm = vec![1u8; MAX_DIRECT_INSTALL_SIZE + 1];
+        store_child_wasm(&wasm).unwrap();
+        assert_eq!(blobs::list_blobs().len(), 1);
+
+        let owner = Principal::from_slice(&[5]);
+        spawn_instance(owner, owner).await.unwrap();
+        let requests = requests.borrow();
+        let uploads = requests
+            .iter()
+            .filter(|r| matches!(r, ManagementRequest::UploadChunk { .. }))
+            .count();
+        assert_eq!(uploads, 2);
+        assert!(matches!(
+            &requests[3],
+            ManagementRequest::InstallChunkedCode { chunk_hashes, wasm_module_hash, .. }
+                if chunk_hashes.len() == 2 && *wasm_module_hash == Sha256::digest(&wasm).to_vec()
+        ));
+        assert!(matches!(
+            requests[4],
+            ManagementRequest::ClearChunkStore { .. }
+        ));
+        set_management_handler(None);
+    }
+}
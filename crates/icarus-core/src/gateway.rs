@@ -0,0 +1,153 @@
+//! Types for the canister's `http_request` endpoint.
+//!
+//! Browsers and scrapers such as Prometheus reach a canister through the HTTP
+//! gateway, which calls the canister's `http_request` query with a
+//! [`GatewayRequest`] and returns the [`GatewayResponse`] as a plain HTTP
+//! response. These are the Candid types of that interface; outgoing requests
+//! made by the canister use [`crate::http`] instead.
+//!
+//! Queries cannot change state, so a route that must, such as a tool call,
+//! answers the query with [`GatewayResponse::upgrade`]. The gateway then
+//! repeats the request as an `http_request_update` call.
+
+use candid::{CandidType, Deserialize};
+
+/// An HTTP request forwarded by the gateway.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct GatewayRequest {
+    /// Request method, e.g. `GET`.
+    pub method: String,
+    /// Request target including the query string, e.g. `/metrics?x=1`.
+    pub url: String,
+    /// Request headers as `(name, value)` pairs.
+    pub headers: Vec<(String, String)>,
+    /// Request body.
+    pub body: Vec<u8>,
+}
+
+impl GatewayRequest {
+    /// Creates a `GET` request for `url` without headers.
+    #[must_use]
+    pub fn get(url: impl Into<String>) -> Self {
+        Self {
+            method: "GET".to_string(),
+            url: url.into(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Adds a header.
+    #[must_use]
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Returns the request path without the query string.
+    #[must_use]
+    pub fn path(&self) -> &str {
+        self.url.split(['?', '#']).next().unwrap_or_default()
+    }
+
+    /// Returns the first value of a header, compared case-insensitively.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// An HTTP response returned to the gateway.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct GatewayResponse {
+    /// HTTP status code.
+    pub status_code: u16,
+    /// Response headers as `(name, value)` pairs.
+    pub headers: Vec<(String, String)>,
+    /// Response body.
+    pub body: Vec<u8>,
+    /// Whether the gateway should repeat the request as an update call.
+    pub upgrade: Option<bool>,
+}
+
+impl GatewayResponse {
+    /// Creates a plain-text response with the given content type.
+    #[must_use]
+    pub fn text(status_code: u16, content_type: &str, body: impl Into<String>) -> Self {
+        let body = body.into().into_bytes();
+        Self {
+            status_code,
+            headers: vec![
+                ("Content-Type".to_string(), content_type.to_string()),
+                ("Content-Length".to_string(), body.len().to_string()),
+            ],
+            body,
+            upgrade: None,
+        }
+    }
+
+    /// Creates a JSON response.
+    #[must_use]
+    pub fn json(status_code: u16, value: &serde_json::Value) -> Self {
+        Self::text(status_code, "application/json", value.to_string())
+    }
+
+    /// Creates an empty response asking the gateway to repeat the request as
+    /// an `http_request_update` call.
+    #[must_use]
+    pub fn upgrade() -> Self {
+        Self {
+            status_code: 200,
+            headers: Vec::new(),
+            body: Vec::new(),
+            upgrade: Some(true),
+        }
+    }
+
+    /// Adds a header.
+    #[must_use]
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Creates a `404 Not Found` response.
+    #[must_use]
+    pub fn not_found() -> Self {
+        Self::text(404, "text/plain; charset=utf-8", "Not found")
+    }
+
+    /// Creates a `403 Forbidden` response.
+    #[must_use]
+    pub fn forbidden() -> Self {
+        Self::text(403, "text/plain; charset=utf-8", "Forbidden")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_and_headers() {
+        let request =
+            GatewayRequest::get("/metrics?format=text").with_header("X-Real-IP", "10.0.0.1");
+        assert_eq!(request.path(), "/metrics");
+        assert_eq!(request.header("x-real-ip"), Some("10.0.0.1"));
+        assert_eq!(request.header("host"), None);
+    }
+
+    #[test]
+    fn test_text_response_sets_length() {
+        let response = GatewayResponse::text(200, "text/plain", "ok");
+        assert_eq!(response.status_code, 200);
+        assert!(response
+            .headers
+            .contains(&("Content-Length".to_string(), "2".to_string())));
+        assert_eq!(response.upgrade, None);
+        assert_eq!(GatewayResponse::upgrade().upgrade, Some(true));
+    }
+}
@@ -0,0 +1,421 @@
+//! A directed graph of typed nodes and labeled edges in stable memory.
+//!
+//! [`StableGraph`] keeps nodes and both directions of every edge as entries
+//! of one `StableBTreeMap`, so adding an edge writes two small entries and
+//! [`StableGraph::neighbors`] is a range scan rather than a search. Removing
+//! a node removes the edges that touch it, so no dangling edges remain.
+//!
+//! Node ids are assigned on insert and never reused, even after removal, so a
+//! client holding an old id cannot end up pointing at a different node.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::graph::{Direction, StableGraph};
+//! use icarus_core::memory::FIRST_USER_MEMORY_ID;
+//!
+//! let graph = StableGraph::<String, String>::init(FIRST_USER_MEMORY_ID);
+//! let ada = graph.add_node("Ada".to_string());
+//! let charles = graph.add_node("Charles".to_string());
+//! let engine = graph.add_node("Analytical Engine".to_string());
+//!
+//! graph.add_edge(ada, charles, "worked_with".to_string()).unwrap();
+//! graph.add_edge(charles, engine, "designed".to_string()).unwrap();
+//!
+//! assert_eq!(
+//!     graph.neighbors(ada, Direction::Outgoing),
+//!     [(charles, "worked_with".to_string())]
+//! );
+//! // Breadth-first from Ada: each reachable node with its distance
+//! assert_eq!(
+//!     graph.bfs(ada, Direction::Outgoing, 10),
+//!     [(ada, 0), (charles, 1), (engine, 2)]
+//! );
+//!
+//! graph.remove_node(charles);
+//! assert!(graph.neighbors(ada, Direction::Outgoing).is_empty());
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, VecDeque};
+use std::marker::PhantomData;
+
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+
+use crate::memory::{self, StableMemory};
+use crate::{IcarusError, Result};
+
+/// Which edges of a node to follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub enum Direction {
+    /// Edges from the node
+    Outgoing,
+    /// Edges to the node
+    Incoming,
+    /// Both, regardless of direction
+    Both,
+}
+
+/// Keys of the graph's single stable map.
+///
+/// Edges are stored twice, under their source and their target, so both
+/// directions are one range each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum GraphKey {
+    /// Next node id to assign
+    NextId,
+    Node(u64),
+    /// Edge label, keyed by source then target
+    Out(u64, u64),
+    /// Marker for the same edge, keyed by target then source
+    In(u64, u64),
+}
+
+const NEXT_ID_TAG: u8 = 0;
+const NODE_TAG: u8 = 1;
+const OUT_TAG: u8 = 2;
+const IN_TAG: u8 = 3;
+const KEY_SIZE: u32 = 17;
+
+impl Storable for GraphKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(self.into_bytes())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        let a = u64::from_be_bytes(bytes[1..9].try_into().unwrap_or_default());
+        let b = u64::from_be_bytes(bytes[9..17].try_into().unwrap_or_default());
+        match bytes[0] {
+            NEXT_ID_TAG => Self::NextId,
+            NODE_TAG => Self::Node(a),
+            OUT_TAG => Self::Out(a, b),
+            IN_TAG => Self::In(a, b),
+            _ => unreachable!("corrupt graph key"),
+        }
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        // Big-endian, so byte order matches the derived `Ord`
+        let (tag, a, b) = match self {
+            Self::NextId => (NEXT_ID_TAG, 0, 0),
+            Self::Node(id) => (NODE_TAG, id, 0),
+            Self::Out(from, to) => (OUT_TAG, from, to),
+            Self::In(to, from) => (IN_TAG, to, from),
+        };
+        let mut bytes = Vec::with_capacity(KEY_SIZE as usize);
+        bytes.push(tag);
+        bytes.extend_from_slice(&a.to_be_bytes());
+        bytes.extend_from_slice(&b.to_be_bytes());
+        bytes
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: KEY_SIZE,
+        is_fixed_size: true,
+    };
+}
+
+/// A directed graph with nodes of type `N` and edge labels of type `E`.
+///
+/// There is at most one edge from one node to another; adding it again
+/// replaces its label.
+pub struct StableGraph<N: Storable, E: Storable> {
+    entries: RefCell<StableBTreeMap<GraphKey, Vec<u8>, StableMemory>>,
+    _types: PhantomData<(N, E)>,
+}
+
+impl<N: Storable, E: Storable> StableGraph<N, E> {
+    /// Opens the graph stored in virtual memory `memory_id`.
+    #[must_use]
+    pub fn init(memory_id: u8) -> Self {
+        Self {
+            entries: RefCell::new(StableBTreeMap::init(memory::get_memory(memory_id))),
+            _types: PhantomData,
+        }
+    }
+
+    /// Adds a node and returns its id.
+    pub fn add_node(&self, node: N) -> u64 {
+        let mut entries = self.entries.borrow_mut();
+        let id = entries
+            .get(&GraphKey::NextId)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map_or(0, u64::from_le_bytes);
+        entries.insert(GraphKey::NextId, (id + 1).to_le_bytes().to_vec());
+        entries.insert(GraphKey::Node(id), node.into_bytes());
+        id
+    }
+
+    /// Returns the node with id `id`.
+    #[must_use]
+    pub fn node(&self, id: u64) -> Option<N> {
+        self.entries
+            .borrow()
+            .get(&GraphKey::Node(id))
+            .map(|bytes| N::from_bytes(Cow::Owned(bytes)))
+    }
+
+    /// Replaces the node with id `id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::ConfigurationError` if there is no such node.
+    pub fn update_node(&self, id: u64, node: N) -> Result<N> {
+        let mut entries = self.entries.borrow_mut();
+        if !entries.contains_key(&GraphKey::Node(id)) {
+            return Err(no_node(id));
+        }
+        let previous = entries
+            .insert(GraphKey::Node(id), node.into_bytes())
+            .unwrap_or_default();
+        Ok(N::from_bytes(Cow::Owned(previous)))
+    }
+
+    /// Removes a node and every edge that touches it.
+    pub fn remove_node(&self, id: u64) -> Option<N> {
+        let mut entries = self.entries.borrow_mut();
+        let node = entries.remove(&GraphKey::Node(id))?;
+
+        let outgoing: Vec<u64> = entries
+            .range(GraphKey::Out(id, 0)..=GraphKey::Out(id, u64::MAX))
+            .map(|entry| other_end(*entry.key()))
+            .collect();
+        for to in outgoing {
+            entries.remove(&GraphKey::Out(id, to));
+            entries.remove(&GraphKey::In(to, id));
+        }
+        let incoming: Vec<u64> = entries
+            .range(GraphKey::In(id, 0)..=GraphKey::In(id, u64::MAX))
+            .map(|entry| other_end(*entry.key()))
+            .collect();
+        for from in incoming {
+            entries.remove(&GraphKey::Out(from, id));
+            entries.remove(&GraphKey::In(id, from));
+        }
+
+        Some(N::from_bytes(Cow::Owned(node)))
+    }
+
+    /// Adds an edge from `from` to `to`, returning the label it replaced.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::ConfigurationError` if either node does not exist.
+    pub fn add_edge(&self, from: u64, to: u64, label: E) -> Result<Option<E>> {
+        let mut entries = self.entries.borrow_mut();
+        for id in [from, to] {
+            if !entries.contains_key(&GraphKey::Node(id)) {
+                return Err(no_node(id));
+            }
+        }
+        entries.insert(GraphKey::In(to, from), Vec::new());
+        Ok(entries
+            .insert(GraphKey::Out(from, to), label.into_bytes())
+            .map(|bytes| E::from_bytes(Cow::Owned(bytes))))
+    }
+
+    /// Returns the label of the edge from `from` to `to`.
+    #[must_use]
+    pub fn edge(&self, from: u64, to: u64) -> Option<E> {
+        self.entries
+            .borrow()
+            .get(&GraphKey::Out(from, to))
+            .map(|bytes| E::from_bytes(Cow::Owned(bytes)))
+    }
+
+    /// Removes the edge from `from` to `to`, returning its label.
+    pub fn remove_edge(&self, from: u64, to: u64) -> Option<E> {
+        let mut entries = self.entries.borrow_mut();
+        entries.remove(&GraphKey::In(to, from));
+        entries
+            .remove(&GraphKey::Out(from, to))
+            .map(|bytes| E::from_bytes(Cow::Owned(bytes)))
+    }
+
+    /// Returns the nodes adjacent to `id` with the labels of the connecting
+    /// edges, ordered by node id.
+    ///
+    /// With [`Direction::Both`], a node connected in both directions appears
+    /// twice, once per edge.
+    #[must_use]
+    pub fn neighbors(&self, id: u64, direction: Direction) -> Vec<(u64, E)> {
+        let entries = self.entries.borrow();
+        let mut neighbors = Vec::new();
+
+        if matches!(direction, Direction::Outgoing | Direction::Both) {
+            neighbors.extend(
+                entries
+                    .range(GraphKey::Out(id, 0)..=GraphKey::Out(id, u64::MAX))
+                    .map(|entry| {
+                        let label = E::from_bytes(Cow::Owned(entry.value()));
+                        (other_end(*entry.key()), label)
+                    }),
+            );
+        }
+        if matches!(direction, Direction::Incoming | Direction::Both) {
+            let sources: Vec<u64> = entries
+                .range(GraphKey::In(id, 0)..=GraphKey::In(id, u64::MAX))
+                .map(|entry| other_end(*entry.key()))
+                .collect();
+            neighbors.extend(sources.into_iter().filter_map(|from| {
+                entries
+                    .get(&GraphKey::Out(from, id))
+                    .map(|bytes| (from, E::from_bytes(Cow::Owned(bytes))))
+            }));
+        }
+
+        neighbors.sort_by_key(|(node, _)| *node);
+        neighbors
+    }
+
+    /// Walks the graph breadth-first from `start` and returns up to `limit`
+    /// nodes with their distance from it, `start` first at distance 0.
+    ///
+    /// Returns nothing if `start` does not exist.
+    #[must_use]
+    pub fn bfs(&self, start: u64, direction: Direction, limit: usize) -> Vec<(u64, u32)> {
+        if limit == 0 || !self.contains_node(start) {
+            return Vec::new();
+        }
+
+        let mut visited = BTreeSet::from([start]);
+        let mut queue = VecDeque::from([(start, 0u32)]);
+        let mut order = Vec::new();
+        while let Some((id, depth)) = queue.pop_front() {
+            order.push((id, depth));
+            if order.len() == limit {
+                break;
+            }
+            for (next, _) in self.neighbors(id, direction) {
+                if visited.insert(next) {
+                    queue.push_back((next, depth + 1));
+                }
+            }
+        }
+        order
+    }
+
+    /// Returns true if there is a node with id `id`.
+    #[must_use]
+    pub fn contains_node(&self, id: u64) -> bool {
+        self.entries.borrow().contains_key(&GraphKey::Node(id))
+    }
+
+    /// Returns the number of nodes.
+    #[must_use]
+    pub fn node_count(&self) -> u64 {
+        self.entries
+            .borrow()
+            .range(GraphKey::Node(0)..=GraphKey::Node(u64::MAX))
+            .count() as u64
+    }
+
+    /// Returns the number of edges.
+    #[must_use]
+    pub fn edge_count(&self) -> u64 {
+        self.entries
+            .borrow()
+            .range(GraphKey::Out(0, 0)..=GraphKey::Out(u64::MAX, u64::MAX))
+            .count() as u64
+    }
+
+    /// Returns all nodes with their ids, ordered by id.
+    #[must_use]
+    pub fn nodes(&self) -> Vec<(u64, N)> {
+        self.entries
+            .borrow()
+            .range(GraphKey::Node(0)..=GraphKey::Node(u64::MAX))
+            .filter_map(|entry| match *entry.key() {
+                GraphKey::Node(id) => Some((id, N::from_bytes(Cow::Owned(entry.value())))),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// The node at the far end of an `Out` or `In` key.
+fn other_end(key: GraphKey) -> u64 {
+    match key {
+        GraphKey::Out(_, other) | GraphKey::In(_, other) => other,
+        GraphKey::NextId | GraphKey::Node(_) => unreachable!("not an edge key"),
+    }
+}
+
+fn no_node(id: u64) -> IcarusError {
+    IcarusError::ConfigurationError(format!("No node with id {id}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::FIRST_USER_MEMORY_ID;
+
+    #[test]
+    fn test_key_order_matches_byte_order() {
+        let keys = [
+            GraphKey::NextId,
+            GraphKey::Node(7),
+            GraphKey::Out(1, 300),
+            GraphKey::Out(2, 0),
+            GraphKey::In(0, 5),
+        ];
+        for pair in keys.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(pair[0].into_bytes() < pair[1].into_bytes());
+            assert_eq!(GraphKey::from_bytes(pair[1].to_bytes()), pair[1]);
+        }
+    }
+
+    #[test]
+    fn test_edges_traversal_and_removal() {
+        let graph = StableGraph::<String, String>::init(FIRST_USER_MEMORY_ID + 44);
+        let ids: Vec<u64> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|name| graph.add_node((*name).to_string()))
+            .collect();
+        let [a, b, c, d] = ids[..] else {
+            unreachable!()
+        };
+
+        graph.add_edge(a, b, "knows".to_string()).unwrap();
+        graph.add_edge(b, c, "knows".to_string()).unwrap();
+        graph.add_edge(d, a, "follows".to_string()).unwrap();
+        assert!(graph.add_edge(a, 99, "knows".to_string()).is_err());
+        assert_eq!(
+            graph.add_edge(a, b, "trusts".to_string()).unwrap(),
+            Some("knows".to_string())
+        );
+        assert_eq!(graph.edge_count(), 3);
+
+        assert_eq!(
+            graph.neighbors(a, Direction::Both),
+            [(b, "trusts".to_string()), (d, "follows".to_string())]
+        );
+        assert_eq!(
+            graph.neighbors(a, Direction::Incoming),
+            [(d, "follows".to_string())]
+        );
+        assert_eq!(
+            graph.bfs(d, Direction::Outgoing, 3),
+            [(d, 0), (a, 1), (b, 2)]
+        );
+        assert_eq!(
+            graph.bfs(c, Direction::Incoming, 10),
+            [(c, 0), (b, 1), (a, 2), (d, 3)]
+        );
+
+        assert_eq!(graph.remove_node(a), Some("a".to_string()));
+        assert_eq!(graph.edge_count(), 1);
+        assert!(graph.neighbors(d, Direction::Outgoing).is_empty());
+        assert!(graph.neighbors(b, Direction::Incoming).is_empty());
+
+        // Ids are not reused after removal
+        assert_eq!(graph.add_node("e".to_string()), 4);
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.remove_edge(b, c), Some("knows".to_string()));
+        assert_eq!(graph.edge_count(), 0);
+    }
+}
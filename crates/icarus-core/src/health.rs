@@ -0,0 +1,182 @@
+//! Liveness and readiness for monitoring systems.
+//!
+//! `mcp!{}` generates an `mcp_health` query returning [`check`] as JSON: the
+//! server version, uptime, enabled tool count, stable memory usage and cycle
+//! balance. Answering at all shows the canister is live; `ready` tells a
+//! load balancer or uptime monitor whether tool calls can succeed right now,
+//! with the reasons listed in `issues` when they cannot.
+//!
+//! The bridge serves the same report to MCP clients as the `server_health`
+//! tool, and `icarus monitor --health` prints it.
+//!
+//! Uptime is counted from the first update call after an install or
+//! upgrade, since queries cannot persist state: [`record_start`] runs on
+//! every `mcp_call_tool`. Canisters with their own `#[init]` and
+//! `#[post_upgrade]` can call it there to count from the start.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::health;
+//!
+//! health::record_start();
+//! let status = health::check("1.2.0", 3);
+//!
+//! assert!(status.ready);
+//! assert_eq!(status.tool_count, 3);
+//! assert!(status.uptime_secs.is_some());
+//! ```
+
+use std::cell::Cell;
+use std::fmt;
+
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+use crate::{cycles, maintenance, memory};
+
+/// Stable memory usage at which a canister is reported as not ready.
+pub const MEMORY_NOT_READY_PERCENT: f64 = 95.0;
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+thread_local! {
+    static STARTED_AT: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// A health report.
+#[derive(Debug, Clone, PartialEq, CandidType, Deserialize, Serialize)]
+pub struct HealthStatus {
+    /// Whether tool calls can succeed right now.
+    pub ready: bool,
+    /// Why the canister is not ready, empty when it is.
+    pub issues: Vec<String>,
+    /// Server version from `mcp!{}`.
+    pub version: String,
+    /// Seconds since the first recorded start, if any.
+    pub uptime_secs: Option<u64>,
+    /// Number of enabled tools.
+    pub tool_count: u64,
+    /// Bytes of stable memory in use.
+    pub memory_bytes: u64,
+    /// Stable memory usage as a percentage of capacity.
+    pub memory_usage_percent: f64,
+    /// Current cycle balance.
+    pub cycles: u128,
+    /// Whether the balance is below the warning threshold.
+    pub low_cycles: bool,
+    /// Time of the report in nanoseconds since the epoch.
+    pub checked_at: u64,
+}
+
+impl fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.ready { "ready" } else { "not ready" };
+        write!(f, "{state}, version {}", self.version)?;
+        if let Some(secs) = self.uptime_secs {
+            write!(
+                f,
+                ", up {}d {}h {}m",
+                secs / 86_400,
+                secs % 86_400 / 3_600,
+                secs % 3_600 / 60
+            )?;
+        }
+        write!(
+            f,
+            ", {} tools, {} bytes of stable memory ({:.1}%), {} cycles",
+            self.tool_count, self.memory_bytes, self.memory_usage_percent, self.cycles
+        )?;
+        for issue in &self.issues {
+            write!(f, "; {issue}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Records the time the canister started, if not recorded yet.
+pub fn record_start() {
+    STARTED_AT.with(|started| {
+        if started.get().is_none() {
+            started.set(Some(crate::time::now_nanos()));
+        }
+    });
+}
+
+/// Returns the recorded start time in nanoseconds since the epoch.
+#[must_use]
+pub fn started_at() -> Option<u64> {
+    STARTED_AT.with(Cell::get)
+}
+
+/// Builds the health report of a server at `version` with `tool_count`
+/// enabled tools.
+#[must_use]
+pub fn check(version: &str, tool_count: usize) -> HealthStatus {
+    let now = crate::time::now_nanos();
+    let memory = memory::usage_report();
+    let cycles = cycles::cycles_status();
+    let maintenance = maintenance::status();
+
+    let mut issues = Vec::new();
+    if maintenance.enabled {
+        issues.push(format!("Maintenance mode: {}", maintenance.message));
+    }
+    if cycles.below_reserve {
+        issues.push(format!(
+            "Cycle balance {} is below the reserve of {}",
+            cycles.balance, cycles.reserve
+        ));
+    }
+    let memory_usage_percent = memory.usage_percent();
+    if memory_usage_percent >= MEMORY_NOT_READY_PERCENT {
+        issues.push(format!("Stable memory is {memory_usage_percent:.1}% full"));
+    }
+
+    HealthStatus {
+        ready: issues.is_empty(),
+        issues,
+        version: version.to_string(),
+        uptime_secs: started_at().map(|started| now.saturating_sub(started) / NANOS_PER_SEC),
+        tool_count: tool_count as u64,
+        memory_bytes: memory.total_bytes,
+        memory_usage_percent,
+        cycles: cycles.balance,
+        low_cycles: cycles.low_balance,
+        checked_at: now,
+    }
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+    use candid::Principal;
+
+    #[test]
+    fn test_check_reports_readiness() {
+        crate::time::set_time_override(Some(10 * NANOS_PER_SEC));
+        record_start();
+        crate::time::set_time_override(Some(70 * NANOS_PER_SEC));
+        // A second start does not reset the uptime
+        record_start();
+
+        let status = check("2.0.0", 4);
+        assert!(status.ready);
+        assert_eq!(status.uptime_secs, Some(60));
+        assert_eq!(status.version, "2.0.0");
+        assert!(status
+            .to_string()
+            .starts_with("ready, version 2.0.0, up 0d 0h 1m, 4 tools"));
+
+        maintenance::set(true, "Migrating", Principal::anonymous());
+        cycles::set_mock_balance(1);
+        let status = check("2.0.0", 4);
+        assert!(!status.ready);
+        assert_eq!(status.issues.len(), 2);
+        assert!(status.issues[0].contains("Migrating"));
+
+        maintenance::set(false, "", Principal::anonymous());
+        cycles::set_mock_balance(u128::MAX);
+        crate::time::set_time_override(None);
+    }
+}
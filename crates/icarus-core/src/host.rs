@@ -0,0 +1,165 @@
+//! The environment tools run in.
+//!
+//! The few things a tool learns from outside the canister, namely who called
+//! it, the time, the cycle balance and where log lines go, are read through
+//! the [`Host`] trait, so the same `#[tool]` functions run unchanged inside a
+//! canister and in a native process.
+//!
+//! With the `ic-canister` feature [`host`] is always [`CanisterHost`], which
+//! asks `ic_cdk`. Off-chain it is [`NativeHost`] unless another host was
+//! installed with [`set_host`]; `icarus-runtime`'s native MCP server installs
+//! one that calls as the local owner. The per-thread overrides of
+//! [`context`](crate::context), [`time`](crate::time) and
+//! [`cycles`](crate::cycles) used by tests still take precedence.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use candid::Principal;
+//! use icarus_core::host::{Host, NativeHost};
+//!
+//! let owner = Principal::from_slice(&[1, 2, 3]);
+//! let host = NativeHost::new().with_caller(owner);
+//! assert_eq!(host.caller(), owner);
+//! assert_eq!(host.cycle_balance(), u128::MAX);
+//! ```
+
+use candid::Principal;
+
+/// What tools read from the environment they run in.
+pub trait Host: Send + Sync {
+    /// Principal that made the current call.
+    fn caller(&self) -> Principal;
+
+    /// Current time in nanoseconds since the Unix epoch.
+    fn time(&self) -> u64;
+
+    /// Cycles available to pay for calls.
+    fn cycle_balance(&self) -> u128;
+
+    /// Writes a line to the log.
+    fn print(&self, line: &str);
+}
+
+/// The Internet Computer, through `ic_cdk`.
+#[cfg(feature = "ic-canister")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CanisterHost;
+
+#[cfg(feature = "ic-canister")]
+impl Host for CanisterHost {
+    fn caller(&self) -> Principal {
+        ic_cdk::api::msg_caller()
+    }
+
+    fn time(&self) -> u64 {
+        ic_cdk::api::time()
+    }
+
+    fn cycle_balance(&self) -> u128 {
+        ic_cdk::api::canister_cycle_balance()
+    }
+
+    fn print(&self, line: &str) {
+        ic_cdk::println!("{line}");
+    }
+}
+
+/// A native process: the system clock, unlimited cycles and stderr, since
+/// stdout may be carrying MCP messages.
+#[cfg(not(feature = "ic-canister"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NativeHost {
+    caller: Principal,
+}
+
+#[cfg(not(feature = "ic-canister"))]
+impl Default for NativeHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(feature = "ic-canister"))]
+impl NativeHost {
+    /// Creates a host whose calls come from the anonymous principal.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            caller: Principal::anonymous(),
+        }
+    }
+
+    /// Makes every call come from `caller`.
+    #[must_use]
+    pub const fn with_caller(mut self, caller: Principal) -> Self {
+        self.caller = caller;
+        self
+    }
+}
+
+#[cfg(not(feature = "ic-canister"))]
+impl Host for NativeHost {
+    fn caller(&self) -> Principal {
+        self.caller
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn time(&self) -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("System time before Unix epoch")
+            .as_nanos() as u64
+    }
+
+    fn cycle_balance(&self) -> u128 {
+        u128::MAX
+    }
+
+    fn print(&self, line: &str) {
+        eprintln!("{line}");
+    }
+}
+
+#[cfg(not(feature = "ic-canister"))]
+static HOST: std::sync::OnceLock<&'static dyn Host> = std::sync::OnceLock::new();
+
+/// Returns the host tools run in.
+#[must_use]
+pub fn host() -> &'static dyn Host {
+    #[cfg(feature = "ic-canister")]
+    {
+        &CanisterHost
+    }
+    #[cfg(not(feature = "ic-canister"))]
+    {
+        static DEFAULT: NativeHost = NativeHost::new();
+        HOST.get().copied().unwrap_or(&DEFAULT)
+    }
+}
+
+/// Installs the host of this process. The host can be set once, before
+/// the first call is served.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if a host was already installed.
+#[cfg(not(feature = "ic-canister"))]
+pub fn set_host(host: &'static dyn Host) -> crate::Result<()> {
+    HOST.set(host).map_err(|_| {
+        crate::IcarusError::ConfigurationError("A host is already installed".to_string())
+    })
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_host_is_native() {
+        let host = host();
+        assert_eq!(host.caller(), Principal::anonymous());
+        assert_eq!(host.cycle_balance(), u128::MAX);
+        assert!(host.time() > 1_600_000_000_000_000_000);
+    }
+}
@@ -0,0 +1,317 @@
+//! HTTP outcalls with a test seam.
+//!
+//! Inside a canister (`ic-canister` feature) requests go through the management
+//! canister's `http_request`. Tests route them to a thread-local handler
+//! installed with [`set_outcall_handler`] instead, which is how `icarus-test`'s
+//! `mock_http()` serves canned responses: the handler exists under `cfg(test)`,
+//! with the `test-utils` feature that `icarus-test` turns on, and in builds
+//! without `ic-canister`, and takes precedence over the management canister
+//! while installed. Off-chain there is no network path, so a request without
+//! a handler fails.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use icarus_core::http;
+//!
+//! #[tool("Get the current BTC price")]
+//! async fn btc_price() -> Result<String, String> {
+//!     let response = http::get("https://api.example.com/btc").await.map_err(|e| e.to_string())?;
+//!     response.text().map_err(|e| e.to_string())
+//! }
+//! ```
+
+#[cfg(any(test, feature = "test-utils", not(feature = "ic-canister")))]
+use std::cell::RefCell;
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+
+use crate::{IcarusError, Result};
+
+/// Default cap on response size, matching the IC limit of 2MB.
+pub const DEFAULT_MAX_RESPONSE_BYTES: u64 = 2_000_000;
+
+/// HTTP methods supported by IC outcalls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HttpMethod {
+    /// `GET`
+    Get,
+    /// `POST`
+    Post,
+    /// `HEAD`
+    Head,
+}
+
+impl HttpMethod {
+    /// Returns the upper-case method name.
+    #[must_use]
+    #[inline]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Get => "GET",
+            Self::Post => "POST",
+            Self::Head => "HEAD",
+        }
+    }
+}
+
+impl fmt::Display for HttpMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An outgoing HTTP request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    /// Request method.
+    pub method: HttpMethod,
+    /// Absolute URL.
+    pub url: String,
+    /// Request headers as name/value pairs.
+    pub headers: Vec<(String, String)>,
+    /// Optional request body.
+    pub body: Option<Vec<u8>>,
+    /// Maximum response size in bytes.
+    pub max_response_bytes: u64,
+}
+
+impl HttpRequest {
+    /// Creates a request with no headers or body.
+    #[must_use]
+    pub fn new(method: HttpMethod, url: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            headers: Vec::new(),
+            body: None,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+        }
+    }
+
+    /// Adds a header.
+    #[must_use]
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the request body.
+    #[must_use]
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Sets the maximum response size.
+    #[must_use]
+    pub const fn max_response_bytes(mut self, bytes: u64) -> Self {
+        self.max_response_bytes = bytes;
+        self
+    }
+}
+
+/// A received HTTP response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    /// Status code.
+    pub status: u16,
+    /// Response headers as name/value pairs.
+    pub headers: Vec<(String, String)>,
+    /// Response body.
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Creates a response with no headers.
+    #[must_use]
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// Returns `true` for 2xx status codes.
+    #[must_use]
+    #[inline]
+    pub const fn is_success(&self) -> bool {
+        self.status >= 200 && self.status < 300
+    }
+
+    /// Returns the body as UTF-8 text.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::JsonError` if the body is not valid UTF-8.
+    pub fn text(&self) -> Result<String> {
+        String::from_utf8(self.body.clone())
+            .map_err(|e| IcarusError::JsonError(format!("Invalid UTF-8 response: {e}")))
+    }
+
+    /// Deserializes the body as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::JsonError` if the body is not valid JSON for `T`.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_slice(&self.body)
+            .map_err(|e| IcarusError::JsonError(format!("Failed to parse JSON response: {e}")))
+    }
+}
+
+/// Handler that serves outcalls off-chain.
+#[cfg(any(test, feature = "test-utils", not(feature = "ic-canister")))]
+pub type OutcallHandler = Box<dyn Fn(&HttpRequest) -> Result<HttpResponse>>;
+
+#[cfg(any(test, feature = "test-utils", not(feature = "ic-canister")))]
+thread_local! {
+    static OUTCALL_HANDLER: RefCell<Option<OutcallHandler>> = const { RefCell::new(None) };
+}
+
+/// Installs the handler for off-chain outcalls on the current thread, or
+/// removes it with `None`.
+#[cfg(any(test, feature = "test-utils", not(feature = "ic-canister")))]
+pub fn set_outcall_handler(handler: Option<OutcallHandler>) {
+    OUTCALL_HANDLER.with(|cell| *cell.borrow_mut() = handler);
+}
+
+/// Sends a `GET` request.
+///
+/// # Errors
+///
+/// See [`request`].
+pub async fn get(url: &str) -> Result<HttpResponse> {
+    request(HttpRequest::new(HttpMethod::Get, url)).await
+}
+
+/// Sends a `POST` request with a JSON body.
+///
+/// # Errors
+///
+/// See [`request`]; also returns `IcarusError::JsonError` if the body cannot be
+/// serialized.
+pub async fn post_json<B: serde::Serialize + ?Sized>(url: &str, body: &B) -> Result<HttpResponse> {
+    let body = serde_json::to_vec(body)
+        .map_err(|e| IcarusError::JsonError(format!("Failed to serialize body: {e}")))?;
+    request(
+        HttpRequest::new(HttpMethod::Post, url)
+            .header("Content-Type", "application/json")
+            .body(body),
+    )
+    .await
+}
+
+/// Sends an HTTP request.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ExternalServiceError` if the outcall fails or, off-chain,
+/// if no handler is installed.
+#[allow(clippy::unused_async)]
+pub async fn request(request: HttpRequest) -> Result<HttpResponse> {
+    #[cfg(any(test, feature = "test-utils", not(feature = "ic-canister")))]
+    if let Some(response) =
+        OUTCALL_HANDLER.with(|cell| cell.borrow().as_ref().map(|handler| handler(&request)))
+    {
+        return response;
+    }
+
+    #[cfg(feature = "ic-canister")]
+    {
+        canister_request(request).await
+    }
+    #[cfg(not(feature = "ic-canister"))]
+    {
+        Err(IcarusError::ExternalServiceError {
+            service: request.url,
+            message: "HTTP outcalls need a canister; install a handler with \
+                      set_outcall_handler or icarus_test::mock::mock_http"
+                .to_string(),
+        })
+    }
+}
+
+#[cfg(feature = "ic-canister")]
+async fn canister_request(request: HttpRequest) -> Result<HttpResponse> {
+    use ic_cdk::management_canister::{self, HttpHeader, HttpRequestArgs};
+
+    let method = match request.method {
+        HttpMethod::Get => management_canister::HttpMethod::GET,
+        HttpMethod::Post => management_canister::HttpMethod::POST,
+        HttpMethod::Head => management_canister::HttpMethod::HEAD,
+    };
+    let args = HttpRequestArgs {
+        url: request.url.clone(),
+        method,
+        headers: request
+            .headers
+            .into_iter()
+            .map(|(name, value)| HttpHeader { name, value })
+            .collect(),
+        body: request.body,
+        max_response_bytes: Some(request.max_response_bytes),
+        ..Default::default()
+    };
+
+    let response = management_canister::http_request(&args)
+        .await
+        .map_err(|e| IcarusError::ExternalServiceError {
+            service: request.url.clone(),
+            message: e.to_string(),
+        })?;
+
+    let status =
+        response
+            .status
+            .to_string()
+            .parse()
+            .map_err(|_| IcarusError::ExternalServiceError {
+                service: request.url,
+                message: format!("Invalid HTTP status: {}", response.status),
+            })?;
+
+    Ok(HttpResponse {
+        status,
+        headers: response
+            .headers
+            .into_iter()
+            .map(|header| (header.name, header.value))
+            .collect(),
+        body: response.body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(feature = "ic-canister"))]
+    #[tokio::test]
+    async fn test_request_without_handler_fails() {
+        set_outcall_handler(None);
+        let err = get("https://example.com").await.unwrap_err();
+        assert!(matches!(err, IcarusError::ExternalServiceError { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_request_uses_handler() {
+        set_outcall_handler(Some(Box::new(|request| {
+            assert_eq!(request.method, HttpMethod::Post);
+            Ok(HttpResponse::new(
+                201,
+                request.body.clone().unwrap_or_default(),
+            ))
+        })));
+
+        let response = post_json("https://example.com", &serde_json::json!({"a": 1}))
+            .await
+            .unwrap();
+        assert!(response.is_success());
+        assert_eq!(response.json::<serde_json::Value>().unwrap()["a"], 1);
+
+        set_outcall_handler(None);
+    }
+}
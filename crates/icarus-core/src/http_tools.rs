@@ -0,0 +1,189 @@
+//! A JSON API for tools, served through the HTTP gateway.
+//!
+//! `mcp! { http_tools = true }` routes these requests in `http_request`, so
+//! web apps can call tools with `fetch` instead of speaking MCP:
+//!
+//! - `GET /tools` lists the enabled tools, as `mcp_list_tools` does
+//! - `POST /tools/<name>` calls a tool with the JSON body as its arguments
+//!   and answers with its `CallToolResult`. Calls need an API key from
+//!   [`crate::api_keys`] and are upgraded to `http_request_update`, where
+//!   they run like `mcp_call_tool` calls made by the key's principal.
+//! - `GET /openapi.json` describes these routes, see [`crate::openapi`]
+//! - `OPTIONS` on any of them answers CORS preflight requests
+//!
+//! Every response carries the CORS headers for the configured origin.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::gateway::GatewayRequest;
+//! use icarus_core::http_tools::{self, Route};
+//!
+//! assert_eq!(http_tools::route(&GatewayRequest::get("/tools")), Some(Route::List));
+//!
+//! let mut call = GatewayRequest::get("/tools/search?x=1");
+//! call.method = "POST".to_string();
+//! assert_eq!(http_tools::route(&call), Some(Route::Call("search")));
+//!
+//! assert_eq!(http_tools::route(&GatewayRequest::get("/metrics")), None);
+//! ```
+
+use crate::gateway::{GatewayRequest, GatewayResponse};
+
+/// Path under which tools are served.
+pub const TOOLS_PATH: &str = "/tools";
+
+/// Path of the OpenAPI document.
+pub const OPENAPI_PATH: &str = "/openapi.json";
+
+/// How long browsers may cache a preflight response, in seconds.
+const PREFLIGHT_MAX_AGE_SECS: u32 = 86_400;
+
+/// A request to the tool API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route<'a> {
+    /// `GET /tools`
+    List,
+    /// `POST /tools/<name>`, with the name as listed
+    Call(&'a str),
+    /// `GET /openapi.json`
+    OpenApi,
+    /// `OPTIONS` on a tool path
+    Preflight,
+    /// Any other method on a tool path
+    MethodNotAllowed,
+}
+
+/// Returns the tool API route of a request, or `None` for other paths.
+#[must_use]
+pub fn route(request: &GatewayRequest) -> Option<Route<'_>> {
+    let path = request.path();
+    let method = request.method.as_str();
+    if path == OPENAPI_PATH {
+        return Some(if method.eq_ignore_ascii_case("OPTIONS") {
+            Route::Preflight
+        } else if method.eq_ignore_ascii_case("GET") {
+            Route::OpenApi
+        } else {
+            Route::MethodNotAllowed
+        });
+    }
+
+    let tool = if path == TOOLS_PATH || path == "/tools/" {
+        None
+    } else {
+        Some(
+            path.strip_prefix("/tools/")
+                .filter(|name| !name.is_empty())?,
+        )
+    };
+
+    Some(if method.eq_ignore_ascii_case("OPTIONS") {
+        Route::Preflight
+    } else if method.eq_ignore_ascii_case("GET") && tool.is_none() {
+        Route::List
+    } else if let Some(name) = tool.filter(|_| method.eq_ignore_ascii_case("POST")) {
+        Route::Call(name)
+    } else {
+        Route::MethodNotAllowed
+    })
+}
+
+/// Adds the CORS headers allowing `origin` to read the response.
+#[must_use]
+pub fn with_cors(response: GatewayResponse, origin: &str) -> GatewayResponse {
+    response
+        .with_header("Access-Control-Allow-Origin", origin)
+        .with_header("Access-Control-Allow-Methods", "GET, POST, OPTIONS")
+        .with_header(
+            "Access-Control-Allow-Headers",
+            "Authorization, Content-Type, X-API-Key",
+        )
+        .with_header("Access-Control-Max-Age", PREFLIGHT_MAX_AGE_SECS.to_string())
+        .with_header("Vary", "Origin")
+}
+
+/// Creates a JSON error response, `{"error": message}`.
+#[must_use]
+pub fn error(status_code: u16, message: &str) -> GatewayResponse {
+    GatewayResponse::json(status_code, &serde_json::json!({ "error": message }))
+}
+
+/// Converts the JSON-RPC response of a tool call into an HTTP response: the
+/// result with `200`, or the error with a status matching its code.
+#[must_use]
+pub fn from_jsonrpc(response: &str) -> GatewayResponse {
+    let Ok(response) = serde_json::from_str::<serde_json::Value>(response) else {
+        return error(500, "Invalid tool response");
+    };
+    if let Some(result) = response.get("result") {
+        return GatewayResponse::json(200, result);
+    }
+
+    let error = &response["error"];
+    let status_code = match error["code"].as_i64() {
+        Some(-32700 | -32602) => 400,
+        Some(-32001) => 402,
+        Some(-32601) => 404,
+        Some(-32002 | -32003) => 503,
+        Some(-32004) => 413,
+        Some(-32005) => 405,
+        _ => 500,
+    };
+    GatewayResponse::json(status_code, &serde_json::json!({ "error": error }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, url: &str) -> GatewayRequest {
+        let mut request = GatewayRequest::get(url);
+        request.method = method.to_string();
+        request
+    }
+
+    #[test]
+    fn test_routes() {
+        assert_eq!(route(&request("GET", "/tools/")), Some(Route::List));
+        assert_eq!(
+            route(&request("post", "/tools/notes_add")),
+            Some(Route::Call("notes_add"))
+        );
+        assert_eq!(
+            route(&request("OPTIONS", "/tools/x")),
+            Some(Route::Preflight)
+        );
+        assert_eq!(
+            route(&request("POST", "/tools")),
+            Some(Route::MethodNotAllowed)
+        );
+        assert_eq!(
+            route(&request("GET", "/tools/x")),
+            Some(Route::MethodNotAllowed)
+        );
+        assert_eq!(
+            route(&request("GET", "/openapi.json")),
+            Some(Route::OpenApi)
+        );
+        assert_eq!(route(&request("GET", "/toolshed")), None);
+    }
+
+    #[test]
+    fn test_jsonrpc_errors_map_to_statuses() {
+        let ok = from_jsonrpc(r#"{"jsonrpc": "2.0", "id": "1", "result": {"content": []}}"#);
+        assert_eq!(ok.status_code, 200);
+        assert_eq!(ok.body, br#"{"content":[]}"#);
+
+        let missing = from_jsonrpc(r#"{"error": {"code": -32601, "message": "Tool not found"}}"#);
+        assert_eq!(missing.status_code, 404);
+        assert_eq!(from_jsonrpc("not json").status_code, 500);
+        let too_large = from_jsonrpc(r#"{"error": {"code": -32004, "message": "Too large"}}"#);
+        assert_eq!(too_large.status_code, 413);
+
+        let response = with_cors(error(401, "API key required"), "*");
+        assert!(response
+            .headers
+            .contains(&("Access-Control-Allow-Origin".to_string(), "*".to_string())));
+    }
+}
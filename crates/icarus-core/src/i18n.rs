@@ -0,0 +1,323 @@
+//! Translated tool titles, descriptions and messages.
+//!
+//! Bundles map message keys to text for one locale and are embedded in the
+//! canister with `icarus_runtime::i18n_bundle!`. Two formats are read:
+//!
+//! - JSON objects, whose nested keys are joined with dots, so
+//!   `{"tools": {"add": {"title": "Sumar"}}}` defines `tools.add.title`.
+//! - Fluent resources, limited to plain messages and their attributes:
+//!   `tools-add = Suma dos números` and an indented `.title = Sumar` below it
+//!   define `tools-add` and `tools-add.title`. Selectors and terms are not
+//!   supported.
+//!
+//! A tool declared with `#[tool(i18n = "tools.add")]` takes its description
+//! from `tools.add.description` and its title from `tools.add.title`. The
+//! bridge passes the MCP client's locale hint, `_meta.locale`, to the
+//! `mcp_list_tools_in` query, which lists the tools through
+//! [`localize_tool`]. A lookup for `es-MX` falls back to `es`, and a tool
+//! with no translation keeps the text written in its code.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::i18n;
+//!
+//! i18n::register_bundle("es", r#"{"errors": {"not_found": "No existe { $name }"}}"#).unwrap();
+//! i18n::register_bundle("fr", "errors-not-found = { $name } est introuvable").unwrap();
+//!
+//! assert_eq!(
+//!     i18n::translate_with("es-MX", "errors.not_found", &[("name", "nota")]).as_deref(),
+//!     Some("No existe nota")
+//! );
+//! assert_eq!(
+//!     i18n::translate_with("fr", "errors-not-found", &[("name", "note")]).as_deref(),
+//!     Some("note est introuvable")
+//! );
+//! assert_eq!(i18n::translate("de", "errors.not_found"), None);
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use crate::{IcarusError, Result, Tool, ToolAnnotations};
+
+/// Key of the client's locale hint in MCP `_meta` objects.
+pub const LOCALE_META_KEY: &str = "locale";
+
+thread_local! {
+    /// Messages by locale, then by key.
+    static BUNDLES: RefCell<BTreeMap<String, BTreeMap<String, String>>> =
+        const { RefCell::new(BTreeMap::new()) };
+
+    /// Translation keys by tool name.
+    static TOOL_KEYS: RefCell<BTreeMap<String, String>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Adds the messages of a JSON or Fluent bundle to `locale`, replacing
+/// messages with the same keys.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if the bundle cannot be parsed;
+/// nothing is added then.
+pub fn register_bundle(locale: &str, source: &str) -> Result<()> {
+    let messages = if source.trim_start().starts_with('{') {
+        parse_json(source)?
+    } else {
+        parse_fluent(source)?
+    };
+
+    BUNDLES.with(|bundles| {
+        bundles
+            .borrow_mut()
+            .entry(normalize(locale))
+            .or_default()
+            .extend(messages);
+    });
+    Ok(())
+}
+
+/// Returns the locales that have a bundle.
+#[must_use]
+pub fn locales() -> Vec<String> {
+    BUNDLES.with(|bundles| bundles.borrow().keys().cloned().collect())
+}
+
+/// Records that `tool` is translated under `key`.
+pub fn set_tool_key(tool: &str, key: &str) {
+    TOOL_KEYS.with(|keys| {
+        keys.borrow_mut().insert(tool.to_string(), key.to_string());
+    });
+}
+
+/// Returns the translation key of `tool`, if it has one.
+#[must_use]
+pub fn tool_key(tool: &str) -> Option<String> {
+    TOOL_KEYS.with(|keys| keys.borrow().get(tool).cloned())
+}
+
+/// Returns the message `key` in `locale`, or in its language without the
+/// region.
+#[must_use]
+pub fn translate(locale: &str, key: &str) -> Option<String> {
+    let locale = normalize(locale);
+    BUNDLES.with(|bundles| {
+        let bundles = bundles.borrow();
+        let lookup = |locale: &str| bundles.get(locale)?.get(key).cloned();
+        lookup(&locale).or_else(|| {
+            let (language, _) = locale.split_once('-')?;
+            lookup(language)
+        })
+    })
+}
+
+/// Returns the message `key` in `locale` with the `{ $name }` placeables
+/// replaced by `args`.
+#[must_use]
+pub fn translate_with(locale: &str, key: &str, args: &[(&str, &str)]) -> Option<String> {
+    let mut message = translate(locale, key)?;
+    for (name, value) in args {
+        message = message.replace(&format!("{{ ${name} }}"), value);
+        message = message.replace(&format!("{{${name}}}"), value);
+    }
+    Some(message)
+}
+
+/// Returns `tool` with its description and title in `locale`, where the
+/// bundles translate them.
+#[must_use]
+pub fn localize_tool(mut tool: Tool, locale: &str) -> Tool {
+    let Some(key) = tool_key(&tool.name) else {
+        return tool;
+    };
+
+    if let Some(description) = translate(locale, &format!("{key}.description")) {
+        tool.description = Some(Cow::Owned(description));
+    }
+    if let Some(title) = translate(locale, &format!("{key}.title")) {
+        tool.annotations
+            .get_or_insert(ToolAnnotations {
+                title: None,
+                read_only_hint: None,
+                destructive_hint: None,
+                idempotent_hint: None,
+                open_world_hint: None,
+            })
+            .title = Some(title);
+    }
+    tool
+}
+
+/// Lowercases a locale and writes it with hyphens, so `en_US` is `en-us`.
+fn normalize(locale: &str) -> String {
+    locale.trim().replace('_', "-").to_ascii_lowercase()
+}
+
+/// Flattens a JSON bundle into dotted keys.
+fn parse_json(source: &str) -> Result<BTreeMap<String, String>> {
+    fn flatten(
+        prefix: &str,
+        value: &serde_json::Value,
+        messages: &mut BTreeMap<String, String>,
+    ) -> Result<()> {
+        match value {
+            serde_json::Value::Object(entries) => {
+                for (key, value) in entries {
+                    let key = if prefix.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{prefix}.{key}")
+                    };
+                    flatten(&key, value, messages)?;
+                }
+                Ok(())
+            }
+            serde_json::Value::String(text) => {
+                messages.insert(prefix.to_string(), text.clone());
+                Ok(())
+            }
+            other => Err(IcarusError::ConfigurationError(format!(
+                "i18n message '{prefix}' must be a string, not {other}"
+            ))),
+        }
+    }
+
+    let value: serde_json::Value = serde_json::from_str(source)
+        .map_err(|e| IcarusError::ConfigurationError(format!("invalid i18n JSON bundle: {e}")))?;
+    let mut messages = BTreeMap::new();
+    flatten("", &value, &mut messages)?;
+    Ok(messages)
+}
+
+/// Reads the messages and attributes of a Fluent resource.
+fn parse_fluent(source: &str) -> Result<BTreeMap<String, String>> {
+    let mut messages = BTreeMap::new();
+    let mut message: Option<String> = None;
+    let mut current: Option<String> = None;
+
+    for (number, line) in source.lines().enumerate() {
+        let indented = line.starts_with([' ', '\t']);
+        let trimmed = line.trim();
+        if trimmed.is_empty() || (!indented && line.starts_with('#')) {
+            continue;
+        }
+
+        let invalid = |reason: &str| {
+            IcarusError::ConfigurationError(format!(
+                "invalid i18n Fluent bundle, line {}: {reason}",
+                number + 1
+            ))
+        };
+
+        if !indented {
+            let (id, value) = trimmed
+                .split_once('=')
+                .ok_or_else(|| invalid("expected `id = value`"))?;
+            let id = id.trim();
+            if id.is_empty() || id.starts_with('-') {
+                return Err(invalid("expected a message identifier"));
+            }
+            messages.insert(id.to_string(), value.trim().to_string());
+            message = Some(id.to_string());
+            current = Some(id.to_string());
+        } else if let Some(attribute) = trimmed.strip_prefix('.') {
+            let id = message
+                .as_ref()
+                .ok_or_else(|| invalid("attribute outside a message"))?;
+            let (name, value) = attribute
+                .split_once('=')
+                .ok_or_else(|| invalid("expected `.attribute = value`"))?;
+            let key = format!("{id}.{}", name.trim());
+            messages.insert(key.clone(), value.trim().to_string());
+            current = Some(key);
+        } else {
+            // Continuation of the previous value
+            let key = current
+                .as_ref()
+                .ok_or_else(|| invalid("indented text outside a message"))?;
+            let value = messages.entry(key.clone()).or_default();
+            if !value.is_empty() {
+                value.push('\n');
+            }
+            value.push_str(trimmed);
+        }
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_fluent() {
+        let messages = parse_fluent(
+            "# Tools\n\
+             tools-add = Suma dos números\n\
+             \x20   .title = Sumar\n\
+             \n\
+             long =\n\
+             \x20   first line\n\
+             \x20   second line\n",
+        )
+        .unwrap();
+
+        assert_eq!(messages["tools-add"], "Suma dos números");
+        assert_eq!(messages["tools-add.title"], "Sumar");
+        assert_eq!(messages["long"], "first line\nsecond line");
+        assert!(matches!(
+            parse_fluent("no equals sign"),
+            Err(IcarusError::ConfigurationError(_))
+        ));
+        assert!(parse_fluent("    .title = Orphan").is_err());
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let messages =
+            parse_json(r#"{"tools": {"add": {"title": "Add", "description": "Adds"}}}"#).unwrap();
+        assert_eq!(messages["tools.add.title"], "Add");
+        assert_eq!(messages["tools.add.description"], "Adds");
+        assert!(parse_json(r#"{"count": 3}"#).is_err());
+    }
+
+    #[test]
+    fn test_locale_fallback() {
+        register_bundle("pt", r#"{"greeting": "Olá"}"#).unwrap();
+        register_bundle("pt_BR", r#"{"farewell": "Tchau"}"#).unwrap();
+
+        assert_eq!(translate("pt-BR", "farewell").as_deref(), Some("Tchau"));
+        assert_eq!(translate("PT-br", "greeting").as_deref(), Some("Olá"));
+        assert_eq!(translate("pt", "farewell"), None);
+        assert!(locales().contains(&"pt-br".to_string()));
+    }
+
+    #[test]
+    fn test_localize_tool() {
+        register_bundle(
+            "it",
+            r#"{"tools": {"i18n_add": {"title": "Somma", "description": "Somma due numeri"}}}"#,
+        )
+        .unwrap();
+        set_tool_key("i18n_add", "tools.i18n_add");
+
+        let tool = Tool::new(
+            "i18n_add",
+            "Adds two numbers",
+            Arc::new(serde_json::Map::new()),
+        );
+        let localized = localize_tool(tool.clone(), "it-IT");
+        assert_eq!(localized.description.as_deref(), Some("Somma due numeri"));
+        assert_eq!(
+            localized.annotations.and_then(|a| a.title).as_deref(),
+            Some("Somma")
+        );
+
+        let untouched = localize_tool(tool, "ja");
+        assert_eq!(untouched.description.as_deref(), Some("Adds two numbers"));
+        assert!(untouched.annotations.is_none());
+    }
+}
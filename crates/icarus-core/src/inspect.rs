@@ -0,0 +1,134 @@
+//! Cheap rejection of ingress update calls before they run.
+//!
+//! An update call pays for executing its method even when the method's first
+//! step is to refuse the caller. The canister's `inspect_message` hook runs
+//! before that, on a single replica and without charging the canister for
+//! the execution, so `mcp! { inspect = true }` generates one that drops:
+//!
+//! - calls from principals that are neither whitelisted nor hold a custom
+//!   role, when auth is on and guest access is off
+//! - `mcp_call_tool` calls to tools the owner disabled
+//!
+//! Controllers and the methods or tools on the allowlist are always
+//! accepted. With auth on, `redeem_invite` is on the allowlist so that new
+//! principals can join, and with `http_tools` so is `http_request_update`,
+//! which the HTTP gateway sends as the anonymous principal.
+//!
+//! `inspect_message` is not a security boundary: it is skipped for
+//! inter-canister calls and a malicious replica may ignore it. The checks
+//! inside the methods still apply.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use candid::Principal;
+//! use icarus_core::{inspect, tool_switches};
+//!
+//! let stranger = Principal::from_slice(&[7]);
+//! assert!(inspect::inspect("add_user", &stranger, None, true, &[]).is_err());
+//! assert!(inspect::inspect("redeem_invite", &stranger, None, true, &["redeem_invite"]).is_ok());
+//!
+//! tool_switches::set_enabled("send_email", false, Principal::anonymous());
+//! let request = r#"{"params": {"name": "send_email"}}"#;
+//! let tool = inspect::requested_tool(request, "");
+//! assert!(inspect::inspect("mcp_call_tool", &stranger, tool.as_deref(), false, &[]).is_err());
+//! ```
+
+use candid::Principal;
+
+use crate::{auth, roles, tool_switches, IcarusError, Result};
+
+/// Update method through which clients call tools.
+pub const CALL_TOOL_METHOD: &str = "mcp_call_tool";
+
+/// Returns the registered name of the tool a `mcp_call_tool` request calls,
+/// with `prefix` stripped when present.
+#[must_use]
+pub fn requested_tool(request: &str, prefix: &str) -> Option<String> {
+    let request: serde_json::Value = serde_json::from_str(request).ok()?;
+    let name = request.get("params")?.get("name")?.as_str()?;
+    Some(name.strip_prefix(prefix).unwrap_or(name).to_string())
+}
+
+/// Checks if a principal is known to the canister: whitelisted, holding a
+/// custom role, or any principal while guest access is on.
+#[must_use]
+pub fn is_known(principal: &Principal) -> bool {
+    auth::has_guest_access(principal) || roles::assigned_role(principal).is_some()
+}
+
+/// Decides whether an ingress call to `method`, running `tool` for
+/// `mcp_call_tool`, should be accepted. Names in `allow` may be methods or
+/// tools.
+///
+/// # Errors
+///
+/// Returns `IcarusError::AccessDenied` when the call should be dropped.
+pub fn inspect(
+    method: &str,
+    caller: &Principal,
+    tool: Option<&str>,
+    auth: bool,
+    allow: &[&str],
+) -> Result<()> {
+    if allow.contains(&method) || tool.is_some_and(|tool| allow.contains(&tool)) {
+        return Ok(());
+    }
+    if let Some(tool) = tool {
+        if !tool_switches::is_enabled(tool) {
+            return Err(IcarusError::AccessDenied(format!("Tool disabled: {tool}")));
+        }
+    }
+    if auth && !is_known(caller) {
+        return Err(IcarusError::AccessDenied(format!(
+            "Unknown caller {caller} for {method}"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect_rejects_strangers_and_disabled_tools() {
+        let user = Principal::from_slice(&[61]);
+        let stranger = Principal::from_slice(&[62]);
+        auth::add_user(user);
+
+        assert!(inspect("mcp_call_tool", &user, Some("notes"), true, &[]).is_ok());
+        assert!(inspect("mcp_call_tool", &stranger, Some("notes"), true, &[]).is_err());
+        // Without auth, anyone may call
+        assert!(inspect("mcp_call_tool", &stranger, Some("notes"), false, &[]).is_ok());
+        assert!(inspect(
+            "mcp_call_tool",
+            &stranger,
+            Some("redeem_invite"),
+            true,
+            &["redeem_invite"]
+        )
+        .is_ok());
+
+        tool_switches::set_enabled("notes", false, user);
+        assert!(inspect("mcp_call_tool", &user, Some("notes"), true, &[]).is_err());
+
+        auth::set_guest_policy(true, user);
+        assert!(inspect("subscribe", &stranger, None, true, &[]).is_ok());
+    }
+
+    #[test]
+    fn test_requested_tool_strips_prefix() {
+        let request = r#"{"jsonrpc": "2.0", "params": {"name": "memory_search"}}"#;
+        assert_eq!(
+            requested_tool(request, "memory_").as_deref(),
+            Some("search")
+        );
+        assert_eq!(
+            requested_tool(request, "").as_deref(),
+            Some("memory_search")
+        );
+        assert_eq!(requested_tool("not json", ""), None);
+        assert_eq!(requested_tool(r#"{"params": {}}"#, ""), None);
+    }
+}
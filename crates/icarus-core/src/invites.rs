@@ -0,0 +1,457 @@
+//! Invite codes that let new principals join the auth whitelist themselves.
+//!
+//! An admin creates an invite for a role with a use limit and an optional
+//! expiry, and hands the code out. Whoever redeems it is added to the
+//! whitelist with that role, until the invite runs out of uses, expires or is
+//! revoked. Creating, redeeming and revoking invites is recorded in an audit
+//! log.
+//!
+//! `mcp! { auth = true }` generates the `create_invite`, `redeem_invite`,
+//! `revoke_invite`, `list_invites` and `get_invite_audit_log` tools.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! use candid::Principal;
+//! use icarus_core::{auth, invites::{self, InviteRole}};
+//!
+//! let owner = Principal::from_slice(&[1]);
+//! let invite = invites::create_invite(InviteRole::User, 1, None, owner).await.unwrap();
+//!
+//! let newcomer = Principal::from_slice(&[2]);
+//! invites::redeem_invite(&invite.code, newcomer).unwrap();
+//! assert!(auth::is_user(&newcomer));
+//!
+//! // One use only
+//! assert!(invites::redeem_invite(&invite.code, Principal::from_slice(&[3])).is_err());
+//! # });
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt;
+use std::str::FromStr;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+
+use crate::memory::{self, StableMemory, INVITES_MEMORY_ID, INVITE_AUDIT_MEMORY_ID};
+use crate::{auth, IcarusError, Result};
+
+/// Audit entries kept; older ones are dropped.
+pub const MAX_AUDIT_ENTRIES: u64 = 1_000;
+
+/// Random bytes in a code, shown as twice as many hex digits.
+const CODE_BYTES: usize = 12;
+
+/// The role an invite grants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub enum InviteRole {
+    /// Added with [`auth::add_admin`]
+    Admin,
+    /// Added with [`auth::add_user`]
+    User,
+}
+
+impl FromStr for InviteRole {
+    type Err = IcarusError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "admin" => Ok(Self::Admin),
+            "user" => Ok(Self::User),
+            _ => Err(IcarusError::ConfigurationError(format!(
+                "Invalid role: {s}. Must be 'admin' or 'user'"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for InviteRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Admin => "admin",
+            Self::User => "user",
+        })
+    }
+}
+
+/// A stored invite.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct Invite {
+    /// The code to redeem.
+    pub code: String,
+    /// Role given to whoever redeems it.
+    pub role: InviteRole,
+    /// How many principals may redeem it.
+    pub max_uses: u32,
+    /// How many have.
+    pub uses: u32,
+    /// Time after which it cannot be redeemed, in nanoseconds since the epoch.
+    pub expires_at: Option<u64>,
+    /// Principal that created it.
+    pub created_by: Principal,
+    /// Creation time in nanoseconds since the epoch.
+    pub created_at: u64,
+    /// Whether an admin revoked it.
+    pub revoked: bool,
+}
+
+impl Invite {
+    /// Returns whether the invite can still be redeemed at `now`.
+    #[must_use]
+    pub fn is_usable(&self, now: u64) -> bool {
+        !self.revoked && self.uses < self.max_uses && self.expires_at.map_or(true, |at| now < at)
+    }
+}
+
+/// What happened to an invite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub enum InviteAction {
+    /// Created by `principal`
+    Created,
+    /// Redeemed by `principal`, who joined with the invite's role
+    Redeemed,
+    /// Revoked by `principal`
+    Revoked,
+}
+
+/// One audit log entry.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct InviteAuditEntry {
+    /// Code of the invite.
+    pub code: String,
+    /// What happened.
+    pub action: InviteAction,
+    /// Role of the invite.
+    pub role: InviteRole,
+    /// Principal that acted.
+    pub principal: Principal,
+    /// Time in nanoseconds since the epoch.
+    pub at: u64,
+}
+
+impl Storable for Invite {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap_or_default())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap_or_else(|e| unreachable!("corrupt invite: {e}"))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).unwrap_or_default()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for InviteAuditEntry {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap_or_default())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap_or_else(|e| unreachable!("corrupt invite audit entry: {e}"))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).unwrap_or_default()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+thread_local! {
+    /// Invites by code.
+    static INVITES: RefCell<StableBTreeMap<String, Invite, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(INVITES_MEMORY_ID))
+    );
+
+    static AUDIT: RefCell<StableBTreeMap<u64, InviteAuditEntry, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(INVITE_AUDIT_MEMORY_ID))
+    );
+}
+
+fn record(invite: &Invite, action: InviteAction, principal: Principal, at: u64) {
+    append_audit(InviteAuditEntry {
+        code: invite.code.clone(),
+        action,
+        role: invite.role,
+        principal,
+        at,
+    });
+}
+
+fn append_audit(entry: InviteAuditEntry) {
+    AUDIT.with(|audit| {
+        let mut audit = audit.borrow_mut();
+        let next = audit.last_key_value().map_or(0, |(id, _)| id + 1);
+        audit.insert(next, entry);
+        while audit.len() > MAX_AUDIT_ENTRIES {
+            let Some((oldest, _)) = audit.first_key_value() else {
+                break;
+            };
+            audit.remove(&oldest);
+        }
+    });
+}
+
+/// Creates an invite for `role` that `max_uses` principals may redeem until
+/// `expires_at`, if given.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if `max_uses` is zero or
+/// `expires_at` has passed, and `IcarusError::ExternalServiceError` if no
+/// entropy could be obtained for the code.
+pub async fn create_invite(
+    role: InviteRole,
+    max_uses: u32,
+    expires_at: Option<u64>,
+    created_by: Principal,
+) -> Result<Invite> {
+    let now = crate::time::now_nanos();
+    if max_uses == 0 {
+        return Err(IcarusError::ConfigurationError(
+            "An invite must allow at least one use".to_string(),
+        ));
+    }
+    if expires_at.is_some_and(|at| at <= now) {
+        return Err(IcarusError::ConfigurationError(
+            "An invite cannot expire in the past".to_string(),
+        ));
+    }
+
+    let random = crate::rand::entropy().await?;
+    let code: String = random[..CODE_BYTES]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+
+    let invite = Invite {
+        code,
+        role,
+        max_uses,
+        uses: 0,
+        expires_at,
+        created_by,
+        created_at: now,
+        revoked: false,
+    };
+    INVITES.with(|invites| {
+        invites
+            .borrow_mut()
+            .insert(invite.code.clone(), invite.clone())
+    });
+    record(&invite, InviteAction::Created, created_by, now);
+    Ok(invite)
+}
+
+/// Adds `principal` to the whitelist with the role of the invite `code`.
+///
+/// # Errors
+///
+/// Returns `IcarusError::AccessDenied` if `principal` is anonymous or the
+/// invite does not exist, is used up, expired or revoked, and
+/// `IcarusError::ConfigurationError` if `principal` already has the role or
+/// a higher one.
+pub fn redeem_invite(code: &str, principal: Principal) -> Result<InviteRole> {
+    if auth::is_anonymous(&principal) {
+        return Err(IcarusError::AccessDenied(
+            "Sign in before redeeming an invite".to_string(),
+        ));
+    }
+
+    let now = crate::time::now_nanos();
+    let mut invite = INVITES
+        .with(|invites| invites.borrow().get(&code.to_string()))
+        .filter(|invite| invite.is_usable(now))
+        // One message for every failure, so codes cannot be probed
+        .ok_or_else(|| IcarusError::AccessDenied("Invalid or expired invite".to_string()))?;
+
+    let has_role = match invite.role {
+        InviteRole::Admin => auth::is_admin(&principal),
+        InviteRole::User => auth::has_user_access(&principal),
+    };
+    if has_role {
+        return Err(IcarusError::ConfigurationError(format!(
+            "{principal} already has {} access",
+            invite.role
+        )));
+    }
+
+    match invite.role {
+        InviteRole::Admin => {
+            auth::remove_user(&principal);
+            auth::add_admin(principal);
+        }
+        InviteRole::User => auth::add_user(principal),
+    }
+    invite.uses += 1;
+    INVITES.with(|invites| {
+        invites
+            .borrow_mut()
+            .insert(invite.code.clone(), invite.clone())
+    });
+    record(&invite, InviteAction::Redeemed, principal, now);
+    Ok(invite.role)
+}
+
+/// Revokes the invite `code` so it cannot be redeemed again. Principals that
+/// already redeemed it keep their role.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if there is no such invite or it
+/// was already revoked.
+pub fn revoke_invite(code: &str, revoked_by: Principal) -> Result<Invite> {
+    let mut invite = INVITES
+        .with(|invites| invites.borrow().get(&code.to_string()))
+        .ok_or_else(|| IcarusError::ConfigurationError(format!("Unknown invite: {code}")))?;
+    if invite.revoked {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Invite {code} is already revoked"
+        )));
+    }
+
+    invite.revoked = true;
+    INVITES.with(|invites| {
+        invites
+            .borrow_mut()
+            .insert(invite.code.clone(), invite.clone())
+    });
+    record(
+        &invite,
+        InviteAction::Revoked,
+        revoked_by,
+        crate::time::now_nanos(),
+    );
+    Ok(invite)
+}
+
+/// Returns all invites, newest first.
+#[must_use]
+pub fn list_invites() -> Vec<Invite> {
+    let mut invites: Vec<Invite> =
+        INVITES.with(|invites| invites.borrow().iter().map(|entry| entry.value()).collect());
+    invites.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    invites
+}
+
+/// Returns up to `limit` audit entries, newest first.
+#[must_use]
+pub fn audit_log(limit: usize) -> Vec<InviteAuditEntry> {
+    AUDIT.with(|audit| {
+        let audit = audit.borrow();
+        // Ids are contiguous since trimming only removes the oldest
+        let Some((last, _)) = audit.last_key_value() else {
+            return Vec::new();
+        };
+        let limit = u64::try_from(limit).unwrap_or(u64::MAX);
+        let mut entries: Vec<_> = audit
+            .range((last + 1).saturating_sub(limit)..)
+            .map(|entry| entry.value())
+            .collect();
+        entries.reverse();
+        entries
+    })
+}
+
+/// Returns every invite and the whole audit log, oldest entry first, for
+/// [`crate::auth_state`] exports.
+pub(crate) fn export_all() -> (Vec<Invite>, Vec<InviteAuditEntry>) {
+    let invites =
+        INVITES.with(|invites| invites.borrow().iter().map(|entry| entry.value()).collect());
+    let audit = AUDIT.with(|audit| audit.borrow().iter().map(|entry| entry.value()).collect());
+    (invites, audit)
+}
+
+/// Replaces all invites with the exported ones and appends the exported
+/// audit log. Returns how many invites were restored.
+pub(crate) fn import_all(invites: Vec<Invite>, audit: Vec<InviteAuditEntry>) -> u32 {
+    let restored = u32::try_from(invites.len()).unwrap_or(u32::MAX);
+    INVITES.with(|stored| {
+        let mut stored = stored.borrow_mut();
+        stored.clear_new();
+        for invite in invites {
+            stored.insert(invite.code.clone(), invite);
+        }
+    });
+    for entry in audit {
+        append_audit(entry);
+    }
+    restored
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_invite_uses_are_limited_and_audited() {
+        let owner = Principal::from_slice(&[31]);
+        let invite = create_invite(InviteRole::User, 2, None, owner)
+            .await
+            .unwrap();
+        assert_eq!(invite.code.len(), CODE_BYTES * 2);
+
+        let first = Principal::from_slice(&[32]);
+        let second = Principal::from_slice(&[33]);
+        assert_eq!(
+            redeem_invite(&invite.code, first).unwrap(),
+            InviteRole::User
+        );
+        assert!(auth::is_user(&first));
+        // Redeeming twice would waste a use
+        assert!(redeem_invite(&invite.code, first).is_err());
+        assert!(redeem_invite(&invite.code, Principal::anonymous()).is_err());
+
+        redeem_invite(&invite.code, second).unwrap();
+        assert!(redeem_invite(&invite.code, Principal::from_slice(&[34])).is_err());
+        assert!(!list_invites()[0].is_usable(crate::time::now_nanos()));
+
+        let log = audit_log(10);
+        assert_eq!(log.len(), 3);
+        assert_eq!(log[0].action, InviteAction::Redeemed);
+        assert_eq!(log[0].principal, second);
+        assert_eq!(log[2].action, InviteAction::Created);
+    }
+
+    #[tokio::test]
+    async fn test_revoked_and_expired_invites_are_rejected() {
+        let owner = Principal::from_slice(&[35]);
+        let now = crate::time::now_nanos();
+        assert!(create_invite(InviteRole::Admin, 1, Some(now), owner)
+            .await
+            .is_err());
+        assert!(create_invite(InviteRole::Admin, 0, None, owner)
+            .await
+            .is_err());
+
+        let invite = create_invite(InviteRole::Admin, 5, None, owner)
+            .await
+            .unwrap();
+        let other = create_invite(InviteRole::Admin, 5, None, owner)
+            .await
+            .unwrap();
+        assert_ne!(invite.code, other.code);
+
+        revoke_invite(&invite.code, owner).unwrap();
+        assert!(revoke_invite(&invite.code, owner).is_err());
+        assert!(redeem_invite(&invite.code, Principal::from_slice(&[36])).is_err());
+        assert!(redeem_invite("not-a-code", Principal::from_slice(&[36])).is_err());
+
+        let expiring = Invite {
+            expires_at: Some(now + 10),
+            ..other
+        };
+        assert!(expiring.is_usable(now));
+        assert!(!expiring.is_usable(now + 10));
+        assert_eq!("admin".parse::<InviteRole>().unwrap(), InviteRole::Admin);
+        assert!("owner".parse::<InviteRole>().is_err());
+    }
+}
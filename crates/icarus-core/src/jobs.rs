@@ -0,0 +1,499 @@
+//! Long-running jobs split into chunks that each run in their own message.
+//!
+//! A call that needs more instructions than one message allows can start a
+//! job instead. The job's work is a closure returning a [`Chunk`]: each call
+//! does a bounded slice of the work and reports whether there is more. Every
+//! chunk runs in its own timer callback, so each gets a fresh instruction
+//! limit, and the caller polls [`status`] and [`result`] with the id
+//! [`start_job`] returned.
+//!
+//! Job kinds are registered with [`register_job_kind`], which maps a kind to
+//! a factory building the closure from JSON arguments; `#[tool(job)]` does
+//! this for a tool and makes the tool start the job. `mcp! { jobs = true }`
+//! adds `start_job`, `get_job_status` and `get_job_result` tools.
+//!
+//! Job records are kept in stable memory, the closures on the heap. After an
+//! upgrade, call [`resume_after_upgrade`] from `post_upgrade`: unfinished
+//! jobs start over from their arguments, so their chunks must be safe to
+//! repeat.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::jobs::{self, Chunk, JobStatus};
+//!
+//! jobs::register_job_kind("count", |args| {
+//!     let target: u64 = serde_json::from_str(args).map_err(|e| e.to_string())?;
+//!     let mut counted = 0;
+//!     Ok(Box::new(move || {
+//!         counted = (counted + 10).min(target);
+//!         if counted == target {
+//!             Chunk::done(&counted)
+//!         } else {
+//!             Chunk::progress(counted * 100 / target)
+//!         }
+//!     }))
+//! });
+//!
+//! let id = jobs::start_job("count", "25").unwrap();
+//! // On a canister, timers run the chunks; here we step through them
+//! while jobs::step(id).is_some_and(|status| !status.is_finished()) {}
+//! assert_eq!(jobs::status(id).unwrap().status, JobStatus::Completed);
+//! assert_eq!(jobs::result(id).unwrap(), "25");
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+
+use crate::memory::{self, StableMemory, JOBS_MEMORY_ID};
+use crate::{IcarusError, Result};
+
+/// Jobs kept before the oldest finished ones are dropped.
+pub const MAX_JOBS: usize = 1_000;
+
+/// The remaining work of a job; each call runs one chunk.
+pub type JobWork = Box<dyn FnMut() -> Chunk>;
+
+/// Builds a job's work from its JSON arguments.
+pub type JobFactory = fn(&str) -> std::result::Result<JobWork, String>;
+
+/// What a chunk of work reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chunk {
+    /// More work remains, optionally with a percentage done.
+    Continue(Option<u8>),
+    /// The job finished with this JSON result.
+    Done(String),
+    /// The job failed with this error.
+    Failed(String),
+}
+
+impl Chunk {
+    /// More work remains.
+    #[must_use]
+    pub const fn more() -> Self {
+        Self::Continue(None)
+    }
+
+    /// More work remains and `percent` of the job is done, capped at 100.
+    #[must_use]
+    pub fn progress(percent: u64) -> Self {
+        Self::Continue(Some(u8::try_from(percent.min(100)).unwrap_or(100)))
+    }
+
+    /// The job finished with `value`, serialized as JSON.
+    #[must_use]
+    pub fn done<T: Serialize>(value: &T) -> Self {
+        match serde_json::to_string(value) {
+            Ok(json) => Self::Done(json),
+            Err(e) => Self::Failed(format!("Failed to serialize job result: {e}")),
+        }
+    }
+}
+
+/// Where a job stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub enum JobStatus {
+    /// Started, no chunk has run yet
+    Queued,
+    /// At least one chunk has run and more remain
+    Running,
+    /// Finished with a result
+    Completed,
+    /// Finished with an error
+    Failed,
+    /// Stopped with [`cancel`]
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Returns whether no more chunks will run.
+    #[must_use]
+    pub const fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// A stored job.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct Job {
+    /// Job id.
+    pub id: u64,
+    /// Kind the job was started as.
+    pub kind: String,
+    /// Principal that started the job.
+    pub owner: Principal,
+    /// JSON arguments the work was built from.
+    pub args: String,
+    /// Current status.
+    pub status: JobStatus,
+    /// Percentage done, as last reported by a chunk.
+    pub progress: Option<u8>,
+    /// Chunks run so far.
+    pub chunks: u64,
+    /// JSON result of a completed job.
+    pub result: Option<String>,
+    /// Error of a failed job.
+    pub error: Option<String>,
+    /// When the job was started, in nanoseconds since the epoch.
+    pub created_at: u64,
+    /// When the job last changed.
+    pub updated_at: u64,
+}
+
+/// What a tool that started a job answers with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct JobTicket {
+    /// Id to pass to `get_job_status` and `get_job_result`.
+    pub job_id: u64,
+}
+
+impl Storable for Job {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap_or_default())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap_or_else(|e| unreachable!("corrupt job: {e}"))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).unwrap_or_default()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+thread_local! {
+    static JOBS: RefCell<StableBTreeMap<u64, Job, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(JOBS_MEMORY_ID))
+    );
+
+    static KINDS: RefCell<BTreeMap<String, JobFactory>> = const { RefCell::new(BTreeMap::new()) };
+
+    static WORK: RefCell<BTreeMap<u64, JobWork>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Registers the factory for jobs of `kind`.
+///
+/// Kinds live on the heap; register them again after an upgrade.
+pub fn register_job_kind(kind: impl Into<String>, factory: JobFactory) {
+    KINDS.with(|kinds| kinds.borrow_mut().insert(kind.into(), factory));
+}
+
+/// Returns whether a factory is registered for `kind`.
+#[must_use]
+pub fn is_job_kind(kind: &str) -> bool {
+    KINDS.with(|kinds| kinds.borrow().contains_key(kind))
+}
+
+/// Starts a job of `kind` with JSON `args` for the current caller and
+/// returns its id.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if `kind` is not registered or
+/// its factory rejects `args`.
+pub fn start_job(kind: &str, args: &str) -> Result<u64> {
+    let work = build_work(kind, args)?;
+    let now = crate::time::now_nanos();
+    let id = JOBS.with(|jobs| {
+        let mut jobs = jobs.borrow_mut();
+        prune(&mut jobs);
+        let id = jobs.last_key_value().map_or(1, |(id, _)| id + 1);
+        jobs.insert(
+            id,
+            Job {
+                id,
+                kind: kind.to_string(),
+                owner: crate::context::caller(),
+                args: args.to_string(),
+                status: JobStatus::Queued,
+                progress: None,
+                chunks: 0,
+                result: None,
+                error: None,
+                created_at: now,
+                updated_at: now,
+            },
+        );
+        id
+    });
+    WORK.with(|work_map| work_map.borrow_mut().insert(id, work));
+    schedule(id);
+    Ok(id)
+}
+
+/// Runs the next chunk of job `id` and returns its new status, or `None` if
+/// there is no such job. Finished jobs are left as they are.
+pub fn step(id: u64) -> Option<JobStatus> {
+    let mut job = status(id)?;
+    if job.status.is_finished() {
+        return Some(job.status);
+    }
+
+    // Take the work out so the chunk may start or inspect other jobs
+    let Some(mut work) = WORK.with(|work_map| work_map.borrow_mut().remove(&id)) else {
+        job.status = JobStatus::Failed;
+        job.error = Some("job was interrupted and its work is gone".to_string());
+        save(job.clone());
+        return Some(job.status);
+    };
+    let chunk = work();
+
+    // A chunk may have cancelled its own job
+    let mut job = status(id)?;
+    if job.status == JobStatus::Cancelled {
+        return Some(job.status);
+    }
+    job.chunks += 1;
+    match chunk {
+        Chunk::Continue(progress) => {
+            job.status = JobStatus::Running;
+            job.progress = progress.or(job.progress);
+            WORK.with(|work_map| work_map.borrow_mut().insert(id, work));
+        }
+        Chunk::Done(result) => {
+            job.status = JobStatus::Completed;
+            job.progress = Some(100);
+            job.result = Some(result);
+        }
+        Chunk::Failed(error) => {
+            crate::logging::error(format_args!("job {id} ({}) failed: {error}", job.kind));
+            job.status = JobStatus::Failed;
+            job.error = Some(error);
+        }
+    }
+    save(job.clone());
+    Some(job.status)
+}
+
+/// Returns job `id`.
+#[must_use]
+pub fn status(id: u64) -> Option<Job> {
+    JOBS.with(|jobs| jobs.borrow().get(&id))
+}
+
+/// Returns job `id` if `caller` started it or is an admin.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` for an unknown job and
+/// `IcarusError::AccessDenied` for someone else's job.
+pub fn status_for(id: u64, caller: Principal) -> Result<Job> {
+    let job =
+        status(id).ok_or_else(|| IcarusError::ConfigurationError(format!("Unknown job {id}")))?;
+    if job.owner != caller && !crate::auth::is_admin(&caller) {
+        return Err(IcarusError::AccessDenied(format!(
+            "job {id} belongs to another caller"
+        )));
+    }
+    Ok(job)
+}
+
+/// Returns the JSON result of completed job `id`.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if the job does not exist or has
+/// not completed, with the job's error if it failed.
+pub fn result(id: u64) -> Result<String> {
+    let job =
+        status(id).ok_or_else(|| IcarusError::ConfigurationError(format!("Unknown job {id}")))?;
+    match (job.status, job.result, job.error) {
+        (JobStatus::Completed, Some(result), _) => Ok(result),
+        (JobStatus::Failed, _, Some(error)) => Err(IcarusError::ConfigurationError(format!(
+            "Job {id} failed: {error}"
+        ))),
+        (status, ..) => Err(IcarusError::ConfigurationError(format!(
+            "Job {id} has no result yet ({status:?})"
+        ))),
+    }
+}
+
+/// Cancels unfinished job `id`, returning whether it was cancelled.
+pub fn cancel(id: u64) -> bool {
+    let Some(mut job) = status(id) else {
+        return false;
+    };
+    if job.status.is_finished() {
+        return false;
+    }
+    WORK.with(|work_map| work_map.borrow_mut().remove(&id));
+    job.status = JobStatus::Cancelled;
+    save(job);
+    true
+}
+
+/// Returns all stored jobs, oldest first.
+#[must_use]
+pub fn jobs() -> Vec<Job> {
+    JOBS.with(|jobs| jobs.borrow().iter().map(|entry| entry.value()).collect())
+}
+
+/// Rebuilds and reschedules the unfinished jobs after an upgrade and returns
+/// how many were restarted.
+///
+/// Each starts over from its arguments; jobs whose kind is no longer
+/// registered fail. Call it from `post_upgrade` after registering the kinds.
+pub fn resume_after_upgrade() -> usize {
+    let mut restarted = 0;
+    for mut job in jobs().into_iter().filter(|job| !job.status.is_finished()) {
+        match build_work(&job.kind, &job.args) {
+            Ok(work) => {
+                WORK.with(|work_map| work_map.borrow_mut().insert(job.id, work));
+                job.status = JobStatus::Queued;
+                job.progress = None;
+                save(job.clone());
+                schedule(job.id);
+                restarted += 1;
+            }
+            Err(e) => {
+                job.status = JobStatus::Failed;
+                job.error = Some(e.to_string());
+                save(job);
+            }
+        }
+    }
+    restarted
+}
+
+fn build_work(kind: &str, args: &str) -> Result<JobWork> {
+    let factory = KINDS
+        .with(|kinds| kinds.borrow().get(kind).copied())
+        .ok_or_else(|| IcarusError::ConfigurationError(format!("Unknown job kind '{kind}'")))?;
+    factory(args).map_err(|e| {
+        IcarusError::ConfigurationError(format!("Invalid arguments for job '{kind}': {e}"))
+    })
+}
+
+fn save(mut job: Job) {
+    job.updated_at = crate::time::now_nanos();
+    JOBS.with(|jobs| jobs.borrow_mut().insert(job.id, job));
+}
+
+/// Drops the oldest finished jobs while the store is full.
+fn prune(jobs: &mut StableBTreeMap<u64, Job, StableMemory>) {
+    while jobs.len() >= MAX_JOBS as u64 {
+        let oldest = jobs
+            .iter()
+            .find(|entry| entry.value().status.is_finished())
+            .map(|entry| *entry.key());
+        let Some(oldest) = oldest else {
+            return;
+        };
+        jobs.remove(&oldest);
+    }
+}
+
+/// Runs the job's chunks one timer callback at a time.
+#[cfg(feature = "ic-canister")]
+fn schedule(id: u64) {
+    ic_cdk_timers::set_timer(std::time::Duration::ZERO, move || {
+        if step(id).is_some_and(|status| !status.is_finished()) {
+            schedule(id);
+        }
+    });
+}
+
+/// Off-chain nothing runs chunks but [`step`].
+#[cfg(not(feature = "ic-canister"))]
+fn schedule(_id: u64) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn countdown(args: &str) -> std::result::Result<JobWork, String> {
+        let mut left: u32 = args.parse().map_err(|_| format!("not a count: {args}"))?;
+        Ok(Box::new(move || {
+            if left == 0 {
+                return Chunk::Failed("nothing to count".to_string());
+            }
+            left -= 1;
+            if left == 0 {
+                Chunk::done(&"liftoff")
+            } else {
+                Chunk::more()
+            }
+        }))
+    }
+
+    fn run(id: u64) -> JobStatus {
+        loop {
+            let status = step(id).unwrap();
+            if status.is_finished() {
+                return status;
+            }
+        }
+    }
+
+    #[test]
+    fn test_job_runs_in_chunks() {
+        register_job_kind("countdown", countdown);
+        let id = start_job("countdown", "3").unwrap();
+        assert_eq!(status(id).unwrap().status, JobStatus::Queued);
+        assert!(result(id).is_err());
+
+        assert_eq!(step(id), Some(JobStatus::Running));
+        assert_eq!(run(id), JobStatus::Completed);
+        let job = status(id).unwrap();
+        assert_eq!(job.chunks, 3);
+        assert_eq!(job.progress, Some(100));
+        assert_eq!(result(id).unwrap(), "\"liftoff\"");
+    }
+
+    #[test]
+    fn test_failures_and_cancellation() {
+        register_job_kind("countdown", countdown);
+        assert!(start_job("missing", "1").is_err());
+        assert!(start_job("countdown", "soon").is_err());
+
+        let id = start_job("countdown", "0").unwrap();
+        assert_eq!(run(id), JobStatus::Failed);
+        assert!(result(id)
+            .unwrap_err()
+            .to_string()
+            .contains("nothing to count"));
+
+        let id = start_job("countdown", "5").unwrap();
+        step(id);
+        assert!(cancel(id));
+        assert!(!cancel(id));
+        assert_eq!(step(id), Some(JobStatus::Cancelled));
+    }
+
+    #[test]
+    fn test_resume_after_upgrade_restarts_from_args() {
+        register_job_kind("countdown", countdown);
+        let id = start_job("countdown", "2").unwrap();
+        step(id);
+        // An upgrade loses the heap, including the work
+        WORK.with(|work_map| work_map.borrow_mut().clear());
+
+        assert!(resume_after_upgrade() >= 1);
+        assert_eq!(status(id).unwrap().status, JobStatus::Queued);
+        assert_eq!(run(id), JobStatus::Completed);
+        assert_eq!(status(id).unwrap().chunks, 3);
+    }
+
+    #[test]
+    fn test_only_owner_or_admin_sees_a_job() {
+        register_job_kind("countdown", countdown);
+        let owner = Principal::from_slice(&[9]);
+        crate::context::set_caller_override(Some(owner));
+        let id = start_job("countdown", "1").unwrap();
+        crate::context::set_caller_override(None);
+
+        assert!(status_for(id, owner).is_ok());
+        assert!(matches!(
+            status_for(id, Principal::anonymous()),
+            Err(IcarusError::AccessDenied(_))
+        ));
+    }
+}
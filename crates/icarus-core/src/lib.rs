@@ -35,12 +35,70 @@
 #![warn(clippy::pedantic)]
 #![deny(unsafe_code)]
 
+pub mod acl;
+pub mod aggregate;
+pub mod api_keys;
+pub mod archive;
+pub mod auth_state;
+pub mod blobs;
+pub mod calendar;
+pub mod client;
+pub mod codec;
+pub mod collections;
+pub mod composite;
+pub mod compression;
+pub mod content;
+pub mod context;
+#[cfg(feature = "vetkd")]
+pub mod crypto;
+pub mod cycles;
+pub mod encrypted_args;
 pub mod error;
+pub mod error_codes;
+pub mod events;
+pub mod factory;
+pub mod gateway;
+pub mod graph;
+pub mod health;
+pub mod host;
+pub mod http;
+pub mod http_tools;
+pub mod i18n;
+pub mod inspect;
+pub mod invites;
+pub mod jobs;
+pub mod locks;
+pub mod logging;
+pub mod maintenance;
+pub mod memory;
+pub mod metrics;
 pub mod newtypes;
+pub mod openapi;
+pub mod pagination;
+pub mod payload_limits;
+pub mod payments;
 pub mod protocol;
+pub mod rand;
+pub mod replication;
+pub mod retention;
 pub mod rmcp_types;
+pub mod roles;
+pub mod sampling;
+pub mod scheduler;
+pub mod secrets;
+pub mod signing;
+pub mod storage;
+pub mod time;
 pub mod tool;
+pub mod tool_changes;
+pub mod tool_export;
+pub mod tool_switches;
+pub mod trace;
+pub mod vector_index;
 pub mod version;
+pub mod versioned;
+#[cfg(feature = "wasi")]
+pub mod wasi;
 
 /// Authentication and authorization module with stable memory persistence
 pub mod auth;
@@ -52,6 +110,7 @@ pub mod auth;
 pub mod legacy;
 
 // Re-export commonly used types for convenience
+pub use client::RemoteToolClient;
 pub use error::IcarusError;
 pub use newtypes::{SessionId, Timestamp, ToolId, UserId};
 pub use version::{Version, VersionReq};
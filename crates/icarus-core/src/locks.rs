@@ -0,0 +1,247 @@
+//! Per-entity async locks for tools that await between reads and writes.
+//!
+//! A canister runs one message at a time, but an async tool gives up control
+//! at every `await`. If it reads a record, awaits an HTTP outcall, and then
+//! writes the record back, another call may have changed the record in
+//! between and its update is silently lost. [`with_entity_lock`] serializes
+//! such operations on the same key: a second call for the key waits at the
+//! lock until the first one finishes, while calls for other keys proceed.
+//!
+//! Waiters are served in arrival order. A lock is released when its guard
+//! drops, including when the call traps after an await and the runtime
+//! drops the pending future. Locks live on the heap and do not survive an
+//! upgrade, which never happens while calls are in flight.
+//!
+//! Only hold a lock around the read-await-write section; never wait for a
+//! lock on a key the same call already holds, which deadlocks.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use icarus_core::locks::with_entity_lock;
+//!
+//! #[tool("Refresh a record's exchange rate")]
+//! async fn refresh_rate(id: u64) -> Result<Record, String> {
+//!     with_entity_lock(format!("record:{id}"), async {
+//!         let mut record = load(id)?;
+//!         record.rate = fetch_rate(&record.currency).await?;
+//!         save(&record);
+//!         Ok(record)
+//!     })
+//!     .await
+//! }
+//! ```
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use rustc_hash::FxHashMap;
+
+thread_local! {
+    static LOCKS: RefCell<FxHashMap<String, LockState>> = RefCell::new(FxHashMap::default());
+}
+
+/// Ticket queue of one key.
+#[derive(Default)]
+struct LockState {
+    /// Ticket handed to the next caller
+    next_ticket: u64,
+    /// Ticket allowed to hold the lock
+    serving: u64,
+    /// Wakers of callers waiting for their turn
+    wakers: BTreeMap<u64, Waker>,
+    /// Tickets whose callers stopped waiting before their turn
+    abandoned: BTreeSet<u64>,
+}
+
+/// Holds the lock on a key until dropped.
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct EntityGuard {
+    key: String,
+}
+
+impl EntityGuard {
+    /// Returns the locked key.
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl fmt::Debug for EntityGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EntityGuard")
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+impl Drop for EntityGuard {
+    fn drop(&mut self) {
+        release(&self.key);
+    }
+}
+
+/// Waits for the lock on a key; returned by [`lock`].
+#[must_use = "futures do nothing unless awaited"]
+pub struct Acquire {
+    key: String,
+    ticket: u64,
+    acquired: bool,
+}
+
+impl Future for Acquire {
+    type Output = EntityGuard;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<EntityGuard> {
+        let turn = LOCKS.with(|locks| {
+            let mut locks = locks.borrow_mut();
+            let state = locks.entry(self.key.clone()).or_default();
+            if state.serving == self.ticket {
+                state.wakers.remove(&self.ticket);
+                true
+            } else {
+                state.wakers.insert(self.ticket, cx.waker().clone());
+                false
+            }
+        });
+        if !turn {
+            return Poll::Pending;
+        }
+        self.acquired = true;
+        Poll::Ready(EntityGuard {
+            key: std::mem::take(&mut self.key),
+        })
+    }
+}
+
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        if self.acquired {
+            return;
+        }
+        // A caller that gives up must not hold up the ones behind it
+        let its_turn = LOCKS.with(|locks| {
+            let mut locks = locks.borrow_mut();
+            let Some(state) = locks.get_mut(&self.key) else {
+                return false;
+            };
+            state.wakers.remove(&self.ticket);
+            if state.serving == self.ticket {
+                true
+            } else {
+                state.abandoned.insert(self.ticket);
+                false
+            }
+        });
+        if its_turn {
+            release(&self.key);
+        }
+    }
+}
+
+/// Returns a future that resolves to a guard once the lock on `key` is free.
+pub fn lock(key: impl fmt::Display) -> Acquire {
+    let key = key.to_string();
+    let ticket = LOCKS.with(|locks| {
+        let mut locks = locks.borrow_mut();
+        let state = locks.entry(key.clone()).or_default();
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        ticket
+    });
+    Acquire {
+        key,
+        ticket,
+        acquired: false,
+    }
+}
+
+/// Runs `operation` while holding the lock on `key`.
+///
+/// Other calls to `with_entity_lock` (or [`lock`]) with the same key wait
+/// until `operation` completes, across all of its await points.
+pub async fn with_entity_lock<F: Future>(key: impl fmt::Display, operation: F) -> F::Output {
+    let _guard = lock(key).await;
+    operation.await
+}
+
+/// Returns whether a call holds or waits for the lock on `key`.
+#[must_use]
+pub fn is_locked(key: impl fmt::Display) -> bool {
+    LOCKS.with(|locks| locks.borrow().contains_key(&key.to_string()))
+}
+
+/// Hands the lock on `key` to the next waiting caller, dropping the queue
+/// once nobody is waiting.
+fn release(key: &str) {
+    let waker = LOCKS.with(|locks| {
+        let mut locks = locks.borrow_mut();
+        let state = locks.get_mut(key)?;
+        state.serving += 1;
+        while state.abandoned.remove(&state.serving) {
+            state.serving += 1;
+        }
+        if state.serving == state.next_ticket {
+            locks.remove(key);
+            return None;
+        }
+        state.wakers.remove(&state.serving)
+    });
+    // Wake outside the borrow; a waker may poll the future right away
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[tokio::test]
+    async fn test_same_key_is_serialized_across_awaits() {
+        let balance = Cell::new(100);
+        // Read, await, then write back, as a tool around an HTTP outcall would
+        let withdraw = |amount| {
+            let balance = &balance;
+            with_entity_lock("account:1", async move {
+                let read = balance.get();
+                tokio::task::yield_now().await;
+                balance.set(read - amount);
+            })
+        };
+
+        tokio::join!(withdraw(30), withdraw(20), withdraw(10));
+        assert_eq!(balance.get(), 40);
+        assert!(!is_locked("account:1"));
+    }
+
+    #[tokio::test]
+    async fn test_other_keys_do_not_wait() {
+        let guard = lock("doc:1").await;
+        assert!(is_locked("doc:1"));
+        let other = lock("doc:2").await;
+        assert_eq!(other.key(), "doc:2");
+        drop(other);
+        drop(guard);
+        assert!(!is_locked("doc:1"));
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_waiter_does_not_block_the_queue() {
+        let first = lock(7).await;
+        let abandoned = lock(7);
+        let third = lock(7);
+        drop(abandoned);
+        drop(first);
+        let third = third.await;
+        assert_eq!(third.key(), "7");
+        drop(third);
+        assert!(!is_locked(7));
+    }
+}
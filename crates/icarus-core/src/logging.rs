@@ -0,0 +1,122 @@
+//! Canister logging with trace correlation.
+//!
+//! Entries are written through the [`host`](crate::host): with
+//! `ic_cdk::println!` inside a canister, where they appear in
+//! `dfx canister logs`, and to stderr off-chain. While a traced call
+//! is running (see [`crate::trace`]) each entry carries its trace and span
+//! ids, e.g.
+//!
+//! ```text
+//! [WARN] [trace=4bf92f3577b34da6a3ce929d0e0e4736 span=00f067aa0ba902b7] quota almost used
+//! ```
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::logging;
+//!
+//! logging::info("indexed 12 documents");
+//! logging::warn(format_args!("retrying {} after timeout", "fetch_prices"));
+//! ```
+
+use std::fmt;
+
+use crate::trace;
+
+/// Severity of a log entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    /// Diagnostic detail.
+    Debug,
+    /// Normal operation.
+    Info,
+    /// Something unexpected that did not fail the call.
+    Warn,
+    /// A failed operation.
+    Error,
+}
+
+impl Level {
+    /// Returns the upper-case level name.
+    #[must_use]
+    #[inline]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Formats an entry, including the current trace context if one is set.
+#[must_use]
+pub fn format_entry(level: Level, message: impl fmt::Display) -> String {
+    match trace::current() {
+        Some(context) => format!(
+            "[{level}] [trace={} span={}] {message}",
+            context.trace_id_hex(),
+            context.span_id_hex()
+        ),
+        None => format!("[{level}] {message}"),
+    }
+}
+
+/// Writes an entry to the canister log.
+pub fn log(level: Level, message: impl fmt::Display) {
+    crate::host::host().print(&format_entry(level, message));
+}
+
+/// Writes a [`Level::Debug`] entry.
+pub fn debug(message: impl fmt::Display) {
+    log(Level::Debug, message);
+}
+
+/// Writes a [`Level::Info`] entry.
+pub fn info(message: impl fmt::Display) {
+    log(Level::Info, message);
+}
+
+/// Writes a [`Level::Warn`] entry.
+pub fn warn(message: impl fmt::Display) {
+    log(Level::Warn, message);
+}
+
+/// Writes a [`Level::Error`] entry.
+pub fn error(message: impl fmt::Display) {
+    log(Level::Error, message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::TraceContext;
+
+    #[test]
+    fn test_entry_without_trace() {
+        trace::set_current(None);
+        assert_eq!(format_entry(Level::Info, "ready"), "[INFO] ready");
+    }
+
+    #[test]
+    fn test_entry_includes_trace_id() {
+        let context: TraceContext = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+            .parse()
+            .unwrap();
+        trace::set_current(Some(context));
+
+        assert_eq!(
+            format_entry(Level::Error, "boom"),
+            "[ERROR] [trace=4bf92f3577b34da6a3ce929d0e0e4736 span=00f067aa0ba902b7] boom"
+        );
+
+        trace::set_current(None);
+    }
+}
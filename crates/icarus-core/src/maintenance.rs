@@ -0,0 +1,127 @@
+//! Canister-wide maintenance mode.
+//!
+//! While maintenance mode is on, `mcp!{}` rejects tool calls with a "service
+//! unavailable" JSON-RPC error carrying the owner's message, so a data
+//! migration can run without tools changing the data underneath it. Queries
+//! such as the tool list and server info keep working, and server info
+//! reports the maintenance state for clients and the bridge.
+//!
+//! The switch is kept in stable memory, so it survives the upgrades a
+//! migration usually involves.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use candid::Principal;
+//! use icarus_core::maintenance;
+//!
+//! maintenance::set(true, "Migrating notes to v2", Principal::anonymous());
+//! assert_eq!(
+//!     maintenance::active_message().as_deref(),
+//!     Some("Migrating notes to v2")
+//! );
+//!
+//! maintenance::set(false, "", Principal::anonymous());
+//! assert!(maintenance::active_message().is_none());
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+
+use crate::memory::{self, StableMemory, MAINTENANCE_MEMORY_ID};
+
+/// JSON-RPC error code of calls rejected during maintenance.
+pub const SERVICE_UNAVAILABLE_CODE: i32 = -32003;
+
+/// Message used when maintenance is switched on without one.
+pub const DEFAULT_MESSAGE: &str = "The service is under maintenance";
+
+const STATUS_KEY: u8 = 0;
+
+/// The maintenance switch.
+#[derive(Debug, Clone, Default, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct MaintenanceStatus {
+    /// Whether tool calls are rejected.
+    pub enabled: bool,
+    /// Shown to clients while enabled.
+    pub message: String,
+    /// When the switch last changed, in nanoseconds since the epoch.
+    pub since: u64,
+    /// Principal that last changed the switch.
+    pub changed_by: Option<Principal>,
+}
+
+impl Storable for MaintenanceStatus {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap_or_default())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap_or_else(|e| unreachable!("corrupt maintenance status: {e}"))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).unwrap_or_default()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+thread_local! {
+    static STATUS: RefCell<StableBTreeMap<u8, MaintenanceStatus, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(MAINTENANCE_MEMORY_ID))
+    );
+}
+
+/// Switches maintenance mode on or off.
+///
+/// An empty `message` is replaced with [`DEFAULT_MESSAGE`].
+pub fn set(enabled: bool, message: impl Into<String>, changed_by: Principal) {
+    let mut message = message.into();
+    if enabled && message.trim().is_empty() {
+        message = DEFAULT_MESSAGE.to_string();
+    }
+    let status = MaintenanceStatus {
+        enabled,
+        message,
+        since: crate::time::now_nanos(),
+        changed_by: Some(changed_by),
+    };
+    STATUS.with(|state| state.borrow_mut().insert(STATUS_KEY, status));
+}
+
+/// Returns the current switch, off if it was never set.
+#[must_use]
+pub fn status() -> MaintenanceStatus {
+    STATUS.with(|state| state.borrow().get(&STATUS_KEY).unwrap_or_default())
+}
+
+/// Returns the message while maintenance mode is on.
+#[must_use]
+pub fn active_message() -> Option<String> {
+    let status = status();
+    status.enabled.then_some(status.message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch_keeps_message_and_author() {
+        let owner = Principal::from_slice(&[3]);
+        set(true, " ", owner);
+        let current = status();
+        assert!(current.enabled);
+        assert_eq!(current.message, DEFAULT_MESSAGE);
+        assert_eq!(current.changed_by, Some(owner));
+
+        set(false, "", owner);
+        assert!(active_message().is_none());
+        assert!(!status().enabled);
+    }
+}
@@ -0,0 +1,386 @@
+//! Shared stable memory manager and usage reporting.
+//!
+//! All Icarus subsystems allocate their stable structures from one
+//! [`MemoryManager`] so that they never overlap and so that usage can be
+//! inspected in one place. Canisters should take their own memories from
+//! [`get_memory`] as well, using ids from [`FIRST_USER_MEMORY_ID`] upward.
+//! The ids below it are all taken, so Icarus memories added since count down
+//! from the top of the range, starting at [`SCHEDULER_QUEUE_MEMORY_ID`].
+//!
+//! [`usage_report`] lists page counts and bytes per memory id; `mcp!{}`
+//! exposes it as the `mcp_memory_report` query. [`check_usage`] and, inside a
+//! canister, [`start_usage_alerts`] warn when usage crosses a threshold.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::memory::{self, FIRST_USER_MEMORY_ID};
+//!
+//! memory::register_label(FIRST_USER_MEMORY_ID, "documents");
+//! let _documents = memory::get_memory(FIRST_USER_MEMORY_ID);
+//!
+//! let report = memory::usage_report();
+//! assert!(report.usage_percent() < 1.0);
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
+use ic_stable_structures::{DefaultMemoryImpl, Memory};
+use serde::Serialize;
+
+use crate::compression::CompressionStats;
+
+/// Virtual memory handed out by the shared manager.
+pub type StableMemory = VirtualMemory<DefaultMemoryImpl>;
+
+/// Size of a WebAssembly page in bytes.
+pub const WASM_PAGE_SIZE_BYTES: u64 = 65_536;
+
+/// Default stable memory capacity used for percentages (500 GiB on the IC).
+pub const DEFAULT_CAPACITY_BYTES: u64 = 500 * 1024 * 1024 * 1024;
+
+/// Memory id of the admin whitelist.
+pub const AUTH_ADMINS_MEMORY_ID: u8 = 0;
+
+/// Memory id of the user whitelist.
+pub const AUTH_USERS_MEMORY_ID: u8 = 1;
+
+/// Memory id of per-tool payment revenue.
+pub const PAYMENTS_REVENUE_MEMORY_ID: u8 = 2;
+
+/// Memory id of per-tool and per-caller usage counters.
+pub const USAGE_METRICS_MEMORY_ID: u8 = 3;
+
+/// Memory id of the blob index.
+pub const BLOB_INDEX_MEMORY_ID: u8 = 4;
+
+/// Memory id of blob chunks.
+pub const BLOB_CHUNKS_MEMORY_ID: u8 = 5;
+
+/// Memory id of event subscriptions.
+pub const EVENT_SUBSCRIPTIONS_MEMORY_ID: u8 = 6;
+
+/// Memory id of the event delivery queue.
+pub const EVENT_QUEUE_MEMORY_ID: u8 = 7;
+
+/// Memory id of the tool list change sequence.
+pub const TOOL_CHANGES_MEMORY_ID: u8 = 8;
+
+/// Memory id of the tools disabled by the owner.
+pub const TOOL_SWITCHES_MEMORY_ID: u8 = 9;
+
+/// Memory id of the log of tool enable/disable changes.
+pub const TOOL_AUDIT_MEMORY_ID: u8 = 10;
+
+/// Memory id of the maintenance mode switch.
+pub const MAINTENANCE_MEMORY_ID: u8 = 11;
+
+/// Memory id of the polling scheduler's tasks.
+pub const SCHEDULER_TASKS_MEMORY_ID: u8 = 12;
+
+/// Memory id of long-running jobs.
+pub const JOBS_MEMORY_ID: u8 = 13;
+
+/// Memory id of named secrets.
+pub const SECRETS_MEMORY_ID: u8 = 14;
+
+/// Memory id of named threshold ECDSA derivation paths.
+pub const SIGNING_PATHS_MEMORY_ID: u8 = 15;
+
+/// First memory id not reserved by Icarus.
+pub const FIRST_USER_MEMORY_ID: u8 = 16;
+
+/// Memory id of the scheduler's index of tasks by due time.
+pub const SCHEDULER_QUEUE_MEMORY_ID: u8 = 254;
+
+/// Memory id of per-resource access grants.
+pub const ACL_GRANTS_MEMORY_ID: u8 = 253;
+
+/// Memory id of the guest access policy.
+pub const AUTH_GUEST_POLICY_MEMORY_ID: u8 = 252;
+
+/// Memory id of invite codes.
+pub const INVITES_MEMORY_ID: u8 = 251;
+
+/// Memory id of the log of invite changes.
+pub const INVITE_AUDIT_MEMORY_ID: u8 = 250;
+
+/// Memory id of auth state exports and imports.
+pub const AUTH_TRANSFERS_MEMORY_ID: u8 = 249;
+
+/// Memory id of custom role assignments.
+pub const AUTH_ROLES_MEMORY_ID: u8 = 248;
+
+/// Memory id of API keys for the HTTP tool routes.
+pub const API_KEYS_MEMORY_ID: u8 = 247;
+
+/// Memory id of log segments kept by an archive canister.
+pub const ARCHIVE_SEGMENTS_MEMORY_ID: u8 = 246;
+
+/// Memory id of the canisters allowed to store archive segments.
+pub const ARCHIVE_SOURCES_MEMORY_ID: u8 = 245;
+
+/// Memory id of the primary a read replica follows.
+pub const REPLICATION_PRIMARY_MEMORY_ID: u8 = 244;
+
+/// Memory id of the last change a read replica applied to each key.
+pub const REPLICATION_VERSIONS_MEMORY_ID: u8 = 243;
+
+/// Memory id of the instances spawned by a factory canister.
+pub const FACTORY_INSTANCES_MEMORY_ID: u8 = 242;
+
+/// Memory id of the token hash and principals allowed to scrape `/metrics`.
+pub const METRICS_TOKEN_MEMORY_ID: u8 = 241;
+
+/// Memory id of the migration key digest that signs auth state exports.
+pub const AUTH_MIGRATION_KEY_MEMORY_ID: u8 = 240;
+
+/// Memory id of the ledger that receives payments.
+pub const PAYMENTS_LEDGER_MEMORY_ID: u8 = 239;
+
+/// Memory id of the per-tool prices.
+pub const PAYMENTS_PRICES_MEMORY_ID: u8 = 238;
+
+/// Highest memory id supported by [`MemoryManager`].
+const MAX_MEMORY_ID: u8 = 254;
+
+thread_local! {
+    static STABLE_MEMORY: DefaultMemoryImpl = DefaultMemoryImpl::default();
+
+    static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
+        RefCell::new(MemoryManager::init(STABLE_MEMORY.with(Clone::clone)));
+
+    static LABELS: RefCell<BTreeMap<u8, String>> = RefCell::new(BTreeMap::from([
+        (AUTH_ADMINS_MEMORY_ID, "auth.admins".to_string()),
+        (AUTH_USERS_MEMORY_ID, "auth.users".to_string()),
+        (PAYMENTS_REVENUE_MEMORY_ID, "payments.revenue".to_string()),
+        (USAGE_METRICS_MEMORY_ID, "metrics.usage".to_string()),
+        (BLOB_INDEX_MEMORY_ID, "blobs.index".to_string()),
+        (BLOB_CHUNKS_MEMORY_ID, "blobs.chunks".to_string()),
+        (EVENT_SUBSCRIPTIONS_MEMORY_ID, "events.subscriptions".to_string()),
+        (EVENT_QUEUE_MEMORY_ID, "events.queue".to_string()),
+        (TOOL_CHANGES_MEMORY_ID, "tools.changes".to_string()),
+        (TOOL_SWITCHES_MEMORY_ID, "tools.switches".to_string()),
+        (TOOL_AUDIT_MEMORY_ID, "tools.audit".to_string()),
+        (MAINTENANCE_MEMORY_ID, "maintenance".to_string()),
+        (SCHEDULER_TASKS_MEMORY_ID, "scheduler.tasks".to_string()),
+        (JOBS_MEMORY_ID, "jobs".to_string()),
+        (SECRETS_MEMORY_ID, "secrets".to_string()),
+        (SIGNING_PATHS_MEMORY_ID, "signing.paths".to_string()),
+        (SCHEDULER_QUEUE_MEMORY_ID, "scheduler.queue".to_string()),
+        (ACL_GRANTS_MEMORY_ID, "acl.grants".to_string()),
+        (AUTH_GUEST_POLICY_MEMORY_ID, "auth.guest_policy".to_string()),
+        (INVITES_MEMORY_ID, "auth.invites".to_string()),
+        (INVITE_AUDIT_MEMORY_ID, "auth.invite_audit".to_string()),
+        (AUTH_TRANSFERS_MEMORY_ID, "auth.transfers".to_string()),
+        (AUTH_ROLES_MEMORY_ID, "auth.roles".to_string()),
+        (API_KEYS_MEMORY_ID, "auth.api_keys".to_string()),
+        (ARCHIVE_SEGMENTS_MEMORY_ID, "archive.segments".to_string()),
+        (ARCHIVE_SOURCES_MEMORY_ID, "archive.sources".to_string()),
+        (REPLICATION_PRIMARY_MEMORY_ID, "replication.primary".to_string()),
+        (REPLICATION_VERSIONS_MEMORY_ID, "replication.versions".to_string()),
+        (FACTORY_INSTANCES_MEMORY_ID, "factory.instances".to_string()),
+        (METRICS_TOKEN_MEMORY_ID, "metrics.token".to_string()),
+        (AUTH_MIGRATION_KEY_MEMORY_ID, "auth.migration_key".to_string()),
+        (PAYMENTS_LEDGER_MEMORY_ID, "payments.ledger".to_string()),
+        (PAYMENTS_PRICES_MEMORY_ID, "payments.prices".to_string()),
+    ]));
+
+    static CAPACITY_BYTES: Cell<u64> = const { Cell::new(DEFAULT_CAPACITY_BYTES) };
+}
+
+/// Returns the virtual memory for `id` from the shared manager.
+///
+/// # Panics
+///
+/// Panics if `id` is 255, which the memory manager reserves.
+#[must_use]
+pub fn get_memory(id: u8) -> StableMemory {
+    MEMORY_MANAGER.with(|manager| manager.borrow().get(MemoryId::new(id)))
+}
+
+/// Attaches a human-readable label to a memory id for reports.
+pub fn register_label(id: u8, label: impl Into<String>) {
+    LABELS.with(|labels| {
+        labels.borrow_mut().insert(id, label.into());
+    });
+}
+
+/// Returns the label attached to a memory id, if any.
+#[must_use]
+pub fn label(id: u8) -> Option<String> {
+    LABELS.with(|labels| labels.borrow().get(&id).cloned())
+}
+
+/// Overrides the capacity used to compute usage percentages.
+pub fn set_capacity_bytes(bytes: u64) {
+    CAPACITY_BYTES.with(|capacity| capacity.set(bytes.max(1)));
+}
+
+/// Usage of a single virtual memory.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct MemoryUsage {
+    /// Memory id within the shared manager.
+    pub id: u8,
+    /// Label registered with [`register_label`], if any.
+    pub label: Option<String>,
+    /// Allocated WebAssembly pages.
+    pub pages: u64,
+    /// Allocated bytes (`pages * 64 KiB`).
+    pub bytes: u64,
+}
+
+/// Snapshot of stable memory usage.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct MemoryReport {
+    /// Pages of the underlying stable memory, including manager overhead.
+    pub total_pages: u64,
+    /// Bytes of the underlying stable memory.
+    pub total_bytes: u64,
+    /// Capacity used to compute [`usage_percent`](Self::usage_percent).
+    pub capacity_bytes: u64,
+    /// Per-memory usage, for ids that are labelled or have allocated pages.
+    pub memories: Vec<MemoryUsage>,
+    /// Writes of compressed `IcarusStorable` types since the canister started.
+    pub compression: Vec<CompressionStats>,
+}
+
+impl MemoryReport {
+    /// Returns total usage as a percentage of capacity.
+    #[must_use]
+    pub fn usage_percent(&self) -> f64 {
+        self.total_bytes as f64 / self.capacity_bytes.max(1) as f64 * 100.0
+    }
+
+    /// Returns stored size as a fraction of raw size over all compressed
+    /// types, or 1.0 if nothing was compressed.
+    #[must_use]
+    pub fn compression_ratio(&self) -> f64 {
+        let (raw, stored) = self
+            .compression
+            .iter()
+            .fold((0, 0), |(raw, stored), stats| {
+                (raw + stats.raw_bytes, stored + stats.stored_bytes)
+            });
+        if raw == 0 {
+            1.0
+        } else {
+            stored as f64 / raw as f64
+        }
+    }
+
+    /// Returns usage for a memory id, if it appears in the report.
+    #[must_use]
+    pub fn memory(&self, id: u8) -> Option<&MemoryUsage> {
+        self.memories.iter().find(|usage| usage.id == id)
+    }
+}
+
+/// Builds a usage report for the shared stable memory.
+#[must_use]
+pub fn usage_report() -> MemoryReport {
+    let labels = LABELS.with(|labels| labels.borrow().clone());
+
+    let memories = MEMORY_MANAGER.with(|manager| {
+        let manager = manager.borrow();
+        (0..=MAX_MEMORY_ID)
+            .filter_map(|id| {
+                let pages = manager.get(MemoryId::new(id)).size();
+                let label = labels.get(&id).cloned();
+                (pages > 0 || label.is_some()).then(|| MemoryUsage {
+                    id,
+                    label,
+                    pages,
+                    bytes: pages * WASM_PAGE_SIZE_BYTES,
+                })
+            })
+            .collect()
+    });
+
+    let total_pages = STABLE_MEMORY.with(Memory::size);
+
+    MemoryReport {
+        total_pages,
+        total_bytes: total_pages * WASM_PAGE_SIZE_BYTES,
+        capacity_bytes: CAPACITY_BYTES.with(Cell::get),
+        memories,
+        compression: crate::compression::stats(),
+    }
+}
+
+/// Returns the current report if usage is at or above `threshold_percent`.
+#[must_use]
+pub fn check_usage(threshold_percent: f64) -> Option<MemoryReport> {
+    let report = usage_report();
+    (report.usage_percent() >= threshold_percent).then_some(report)
+}
+
+/// Starts a timer that checks usage every `interval`.
+///
+/// When usage crosses `threshold_percent`, a warning is printed to the canister
+/// log and `notify` (if any) is called with the report.
+#[cfg(feature = "ic-canister")]
+pub fn start_usage_alerts(
+    threshold_percent: f64,
+    interval: std::time::Duration,
+    notify: Option<fn(&MemoryReport)>,
+) -> ic_cdk_timers::TimerId {
+    ic_cdk_timers::set_timer_interval(interval, move || {
+        if let Some(report) = check_usage(threshold_percent) {
+            crate::logging::warn(format_args!(
+                "stable memory usage {:.2}% exceeds {threshold_percent}% ({} of {} bytes)",
+                report.usage_percent(),
+                report.total_bytes,
+                report.capacity_bytes
+            ));
+            if let Some(notify) = notify {
+                notify(&report);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_includes_labelled_memories() {
+        let report = usage_report();
+        assert_eq!(
+            report
+                .memory(AUTH_ADMINS_MEMORY_ID)
+                .and_then(|m| m.label.as_deref()),
+            Some("auth.admins")
+        );
+    }
+
+    #[test]
+    fn test_report_tracks_growth() {
+        let id = FIRST_USER_MEMORY_ID + 1;
+        register_label(id, "test.growth");
+        let memory = get_memory(id);
+        memory.grow(3);
+
+        let report = usage_report();
+        let usage = report.memory(id).unwrap();
+        assert_eq!(usage.pages, 3);
+        assert_eq!(usage.bytes, 3 * WASM_PAGE_SIZE_BYTES);
+        assert!(report.total_pages >= 3);
+    }
+
+    #[test]
+    fn test_check_usage_threshold() {
+        set_capacity_bytes(u64::MAX);
+        assert!(check_usage(50.0).is_none());
+
+        get_memory(FIRST_USER_MEMORY_ID + 2).grow(1);
+        set_capacity_bytes(WASM_PAGE_SIZE_BYTES);
+        assert!(check_usage(50.0).is_some());
+
+        set_capacity_bytes(DEFAULT_CAPACITY_BYTES);
+    }
+}
@@ -0,0 +1,593 @@
+//! Usage analytics per tool and per caller.
+//!
+//! `mcp_call_tool` records every executed tool call with [`record_call`]:
+//! the tool name, the caller principal, the latency and whether the call
+//! succeeded. Counters are aggregated into hourly buckets in stable memory so
+//! they survive upgrades, and the map is bounded two ways: buckets older than
+//! the retention period are dropped, and once [`set_max_entries`] is reached
+//! the oldest entries are evicted first.
+//!
+//! [`usage_stats`] merges the buckets inside a time window; `mcp!{}` exposes
+//! it as the `mcp_usage_stats` query, which only admins, or controllers
+//! without auth, may call, and `icarus monitor` renders it.
+//!
+//! The Prometheus `/metrics` route is open until the owner restricts it, in
+//! two ways that can be combined. Requests through the HTTP gateway carry no
+//! identity, so they need the bearer token set with [`set_scrape_token`],
+//! sent as `Authorization: Bearer <token>`; only its SHA-256 hash is kept.
+//! Scrapers that call `http_request` through an agent are identified by
+//! their principal, and are let in by [`set_scrape_principals`]. Both are
+//! kept in stable memory. Client IP addresses are not checked: the gateway
+//! does not pass on one that can be trusted.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::metrics;
+//!
+//! metrics::record_call("translate", "2vxsx-fae", 3_000_000, true);
+//! metrics::record_call("translate", "2vxsx-fae", 12_000_000, false);
+//!
+//! let stats = metrics::usage_stats(Some(3600));
+//! let translate = stats.tool("translate").unwrap();
+//! assert_eq!(translate.calls, 2);
+//! assert_eq!(translate.errors, 1);
+//! ```
+
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::memory::{self, StableMemory, METRICS_TOKEN_MEMORY_ID, USAGE_METRICS_MEMORY_ID};
+use crate::{IcarusError, Result};
+
+/// Upper bounds of the latency histogram buckets in milliseconds.
+///
+/// Histograms have one more bucket than this list, counting slower calls.
+pub const LATENCY_BUCKETS_MS: [u64; 7] = [1, 5, 10, 50, 100, 500, 1_000];
+
+/// Default number of hourly buckets kept (one week).
+pub const DEFAULT_RETENTION_HOURS: u64 = 24 * 7;
+
+/// Default cap on stored entries across all buckets.
+pub const DEFAULT_MAX_ENTRIES: u64 = 50_000;
+
+/// Shortest scrape token accepted by [`set_scrape_token`].
+pub const MIN_SCRAPE_TOKEN_LEN: usize = 16;
+
+const HISTOGRAM_LEN: usize = LATENCY_BUCKETS_MS.len() + 1;
+const SCRAPE_TOKEN_KEY: u8 = 0;
+const SCRAPE_PRINCIPALS_KEY: u8 = 1;
+const NANOS_PER_HOUR: u64 = 3_600 * 1_000_000_000;
+const NANOS_PER_MS: u64 = 1_000_000;
+
+const TOOL_KIND: char = 't';
+const CALLER_KIND: char = 'c';
+
+/// Counters for one tool or caller within one hourly bucket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct UsageRecord {
+    calls: u64,
+    errors: u64,
+    total_latency_nanos: u64,
+    histogram: [u64; HISTOGRAM_LEN],
+}
+
+impl UsageRecord {
+    const SIZE: usize = 8 * (3 + HISTOGRAM_LEN);
+
+    fn observe(&mut self, latency_nanos: u64, success: bool) {
+        self.calls += 1;
+        if !success {
+            self.errors += 1;
+        }
+        self.total_latency_nanos = self.total_latency_nanos.saturating_add(latency_nanos);
+        self.histogram[histogram_bucket(latency_nanos)] += 1;
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.calls += other.calls;
+        self.errors += other.errors;
+        self.total_latency_nanos = self
+            .total_latency_nanos
+            .saturating_add(other.total_latency_nanos);
+        for (total, count) in self.histogram.iter_mut().zip(other.histogram) {
+            *total += count;
+        }
+    }
+}
+
+impl Storable for UsageRecord {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(self.into_bytes())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        let mut words = bytes.chunks_exact(8).map(|chunk| {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(chunk);
+            u64::from_le_bytes(word)
+        });
+        let mut record = Self {
+            calls: words.next().unwrap_or_default(),
+            errors: words.next().unwrap_or_default(),
+            total_latency_nanos: words.next().unwrap_or_default(),
+            histogram: [0; HISTOGRAM_LEN],
+        };
+        for (slot, count) in record.histogram.iter_mut().zip(words) {
+            *slot = count;
+        }
+        record
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::SIZE);
+        bytes.extend_from_slice(&self.calls.to_le_bytes());
+        bytes.extend_from_slice(&self.errors.to_le_bytes());
+        bytes.extend_from_slice(&self.total_latency_nanos.to_le_bytes());
+        for count in self.histogram {
+            bytes.extend_from_slice(&count.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    const BOUND: Bound = Bound::Bounded {
+        max_size: Self::SIZE as u32,
+        is_fixed_size: true,
+    };
+}
+
+thread_local! {
+    // Keys are `{hour:016x}:{kind}:{name}` so that iteration order is
+    // chronological and old buckets can be pruned from the front.
+    static USAGE: RefCell<StableBTreeMap<String, UsageRecord, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(USAGE_METRICS_MEMORY_ID))
+    );
+
+    static RETENTION_HOURS: Cell<u64> = const { Cell::new(DEFAULT_RETENTION_HOURS) };
+
+    static MAX_ENTRIES: Cell<u64> = const { Cell::new(DEFAULT_MAX_ENTRIES) };
+
+    static SCRAPE_ACCESS: RefCell<StableBTreeMap<u8, Vec<u8>, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(METRICS_TOKEN_MEMORY_ID))
+    );
+}
+
+/// Aggregated usage of one tool or caller.
+#[derive(Debug, Clone, PartialEq, CandidType, Deserialize, Serialize)]
+pub struct UsageEntry {
+    /// Tool name or caller principal.
+    pub name: String,
+    /// Number of executed calls.
+    pub calls: u64,
+    /// Calls that failed or returned an error result.
+    pub errors: u64,
+    /// `errors / calls`, or 0 without calls.
+    pub error_rate: f64,
+    /// Mean latency in milliseconds.
+    pub avg_latency_ms: f64,
+    /// Call counts per [`LATENCY_BUCKETS_MS`] bucket, plus one overflow bucket.
+    pub latency_histogram: Vec<u64>,
+}
+
+impl UsageEntry {
+    fn new(name: String, record: &UsageRecord) -> Self {
+        let (error_rate, avg_latency_ms) = if record.calls == 0 {
+            (0.0, 0.0)
+        } else {
+            (
+                record.errors as f64 / record.calls as f64,
+                record.total_latency_nanos as f64 / record.calls as f64 / NANOS_PER_MS as f64,
+            )
+        };
+        Self {
+            name,
+            calls: record.calls,
+            errors: record.errors,
+            error_rate,
+            avg_latency_ms,
+            latency_histogram: record.histogram.to_vec(),
+        }
+    }
+}
+
+/// Usage within a time window, as returned by `mcp_usage_stats`.
+#[derive(Debug, Clone, PartialEq, CandidType, Deserialize, Serialize)]
+pub struct UsageStats {
+    /// Requested window in seconds, or `None` for the full retention period.
+    pub window_secs: Option<u64>,
+    /// Start of the first hourly bucket included, in nanoseconds since the epoch.
+    pub since_nanos: u64,
+    /// Upper bounds of the histogram buckets in milliseconds.
+    pub latency_buckets_ms: Vec<u64>,
+    /// Per-tool usage, sorted by calls (descending).
+    pub tools: Vec<UsageEntry>,
+    /// Per-caller usage, sorted by calls (descending).
+    pub callers: Vec<UsageEntry>,
+}
+
+impl UsageStats {
+    /// Returns the total number of calls in the window.
+    #[must_use]
+    pub fn total_calls(&self) -> u64 {
+        self.tools.iter().map(|entry| entry.calls).sum()
+    }
+
+    /// Returns the total number of failed calls in the window.
+    #[must_use]
+    pub fn total_errors(&self) -> u64 {
+        self.tools.iter().map(|entry| entry.errors).sum()
+    }
+
+    /// Returns usage for a tool, if it was called in the window.
+    #[must_use]
+    pub fn tool(&self, name: &str) -> Option<&UsageEntry> {
+        self.tools.iter().find(|entry| entry.name == name)
+    }
+
+    /// Returns usage for a caller, if they called any tool in the window.
+    #[must_use]
+    pub fn caller(&self, principal: &str) -> Option<&UsageEntry> {
+        self.callers.iter().find(|entry| entry.name == principal)
+    }
+}
+
+/// Sets how many hourly buckets are kept. At least one bucket is always kept.
+pub fn set_retention_hours(hours: u64) {
+    RETENTION_HOURS.with(|retention| retention.set(hours.max(1)));
+}
+
+/// Sets the maximum number of stored entries before the oldest are evicted.
+pub fn set_max_entries(entries: u64) {
+    MAX_ENTRIES.with(|max| max.set(entries.max(2)));
+}
+
+/// Records one executed tool call.
+///
+/// Each call updates two entries in the current hourly bucket: one for the
+/// tool and one for the caller.
+pub fn record_call(tool: &str, caller: &str, latency_nanos: u64, success: bool) {
+    let hour = crate::time::now_nanos() / NANOS_PER_HOUR;
+
+    USAGE.with(|usage| {
+        let mut usage = usage.borrow_mut();
+        prune(&mut usage, hour);
+
+        for key in [
+            entry_key(hour, TOOL_KIND, tool),
+            entry_key(hour, CALLER_KIND, caller),
+        ] {
+            let mut record = usage.get(&key).unwrap_or_else(|| {
+                evict_oldest(&mut usage);
+                UsageRecord::default()
+            });
+            record.observe(latency_nanos, success);
+            usage.insert(key, record);
+        }
+    });
+}
+
+/// Returns usage within the last `window_secs` seconds.
+///
+/// Counters are kept per hour, so the window is rounded out to whole hours.
+/// `None` covers the full retention period.
+#[must_use]
+pub fn usage_stats(window_secs: Option<u64>) -> UsageStats {
+    let now = crate::time::now_nanos();
+    let first_hour = match window_secs {
+        Some(secs) => now.saturating_sub(secs.saturating_mul(1_000_000_000)) / NANOS_PER_HOUR,
+        None => 0,
+    };
+
+    let mut tools: BTreeMap<String, UsageRecord> = BTreeMap::new();
+    let mut callers: BTreeMap<String, UsageRecord> = BTreeMap::new();
+    let mut earliest_hour: Option<u64> = None;
+
+    USAGE.with(|usage| {
+        for entry in usage.borrow().range(entry_prefix(first_hour)..) {
+            let Some((hour, kind, name)) = parse_key(entry.key()) else {
+                continue;
+            };
+            earliest_hour.get_or_insert(hour);
+
+            let totals = if kind == TOOL_KIND {
+                &mut tools
+            } else {
+                &mut callers
+            };
+            totals
+                .entry(name.to_string())
+                .or_default()
+                .merge(&entry.value());
+        }
+    });
+
+    UsageStats {
+        window_secs,
+        since_nanos: earliest_hour.unwrap_or(first_hour) * NANOS_PER_HOUR,
+        latency_buckets_ms: LATENCY_BUCKETS_MS.to_vec(),
+        tools: into_entries(tools),
+        callers: into_entries(callers),
+    }
+}
+
+fn into_entries(totals: BTreeMap<String, UsageRecord>) -> Vec<UsageEntry> {
+    let mut entries: Vec<UsageEntry> = totals
+        .into_iter()
+        .map(|(name, record)| UsageEntry::new(name, &record))
+        .collect();
+    entries.sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.name.cmp(&b.name)));
+    entries
+}
+
+/// Drops buckets that fell out of the retention period.
+fn prune(usage: &mut StableBTreeMap<String, UsageRecord, StableMemory>, hour: u64) {
+    let retention = RETENTION_HOURS.with(Cell::get);
+    let cutoff = hour.saturating_sub(retention - 1);
+
+    while let Some((key, _)) = usage.first_key_value() {
+        match parse_key(&key) {
+            Some((bucket, _, _)) if bucket >= cutoff => break,
+            _ => {
+                usage.remove(&key);
+            }
+        }
+    }
+}
+
+/// Makes room for a new entry by evicting the oldest ones.
+fn evict_oldest(usage: &mut StableBTreeMap<String, UsageRecord, StableMemory>) {
+    let max = MAX_ENTRIES.with(Cell::get);
+    while usage.len() >= max {
+        let Some((key, _)) = usage.first_key_value() else {
+            break;
+        };
+        usage.remove(&key);
+    }
+}
+
+/// Requires `token` to scrape `/metrics`, or removes the token with `None`.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if the token is shorter than
+/// [`MIN_SCRAPE_TOKEN_LEN`].
+pub fn set_scrape_token(token: Option<&str>) -> Result<()> {
+    let Some(token) = token else {
+        SCRAPE_ACCESS.with(|cell| cell.borrow_mut().remove(&SCRAPE_TOKEN_KEY));
+        return Ok(());
+    };
+    if token.trim().len() < MIN_SCRAPE_TOKEN_LEN {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Scrape tokens need at least {MIN_SCRAPE_TOKEN_LEN} characters"
+        )));
+    }
+    let hash = Sha256::digest(token.trim().as_bytes()).to_vec();
+    SCRAPE_ACCESS.with(|cell| cell.borrow_mut().insert(SCRAPE_TOKEN_KEY, hash));
+    Ok(())
+}
+
+/// Returns whether scraping `/metrics` requires a token.
+#[must_use]
+pub fn scrape_token_required() -> bool {
+    SCRAPE_ACCESS.with(|cell| cell.borrow().contains_key(&SCRAPE_TOKEN_KEY))
+}
+
+/// Lets `principals` scrape `/metrics` without the token; an empty list
+/// removes the allowlist.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if the list contains the
+/// anonymous principal, which every gateway request is made as.
+pub fn set_scrape_principals(principals: &[Principal]) -> Result<()> {
+    if principals.contains(&Principal::anonymous()) {
+        return Err(IcarusError::ConfigurationError(
+            "The anonymous principal cannot be allowed to scrape metrics".to_string(),
+        ));
+    }
+    if principals.is_empty() {
+        SCRAPE_ACCESS.with(|cell| cell.borrow_mut().remove(&SCRAPE_PRINCIPALS_KEY));
+        return Ok(());
+    }
+    let bytes = Encode!(&principals)?;
+    SCRAPE_ACCESS.with(|cell| cell.borrow_mut().insert(SCRAPE_PRINCIPALS_KEY, bytes));
+    Ok(())
+}
+
+/// Returns the principals allowed to scrape `/metrics` without the token.
+#[must_use]
+pub fn scrape_principals() -> Vec<Principal> {
+    SCRAPE_ACCESS
+        .with(|cell| cell.borrow().get(&SCRAPE_PRINCIPALS_KEY))
+        .and_then(|bytes| Decode!(&bytes, Vec<Principal>).ok())
+        .unwrap_or_default()
+}
+
+/// Returns whether `caller`, presenting `token`, may read the metrics:
+/// always when neither a token nor an allowlist is set, otherwise when the
+/// token matches or the caller is on the allowlist.
+#[must_use]
+pub fn scrape_allowed(token: Option<&str>, caller: &Principal) -> bool {
+    SCRAPE_ACCESS.with(|cell| {
+        let access = cell.borrow();
+        let hash = access.get(&SCRAPE_TOKEN_KEY);
+        let principals = access.get(&SCRAPE_PRINCIPALS_KEY);
+        if hash.is_none() && principals.is_none() {
+            return true;
+        }
+
+        let token_matches = hash.zip(token).is_some_and(|(hash, token)| {
+            Sha256::digest(token.trim().as_bytes()).as_slice() == hash
+        });
+        let caller_listed = principals
+            .and_then(|bytes| Decode!(&bytes, Vec<Principal>).ok())
+            .is_some_and(|principals| principals.contains(caller));
+        token_matches || caller_listed
+    })
+}
+
+fn histogram_bucket(latency_nanos: u64) -> usize {
+    let latency_ms = latency_nanos / NANOS_PER_MS;
+    LATENCY_BUCKETS_MS
+        .iter()
+        .position(|&bound| latency_ms < bound)
+        .unwrap_or(LATENCY_BUCKETS_MS.len())
+}
+
+fn entry_prefix(hour: u64) -> String {
+    format!("{hour:016x}:")
+}
+
+fn entry_key(hour: u64, kind: char, name: &str) -> String {
+    format!("{hour:016x}:{kind}:{name}")
+}
+
+fn parse_key(key: &str) -> Option<(u64, char, &str)> {
+    let mut parts = key.splitn(3, ':');
+    let hour = u64::from_str_radix(parts.next()?, 16).ok()?;
+    let kind = parts.next()?.chars().next()?;
+    Some((hour, kind, parts.next()?))
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+    use crate::time::set_time_override;
+
+    const HOUR: u64 = NANOS_PER_HOUR;
+
+    fn clear() {
+        USAGE.with(|usage| {
+            let mut usage = usage.borrow_mut();
+            while let Some((key, _)) = usage.first_key_value() {
+                usage.remove(&key);
+            }
+        });
+    }
+
+    #[test]
+    fn test_histogram_buckets() {
+        assert_eq!(histogram_bucket(0), 0);
+        assert_eq!(histogram_bucket(2 * NANOS_PER_MS), 1);
+        assert_eq!(histogram_bucket(999 * NANOS_PER_MS), 6);
+        assert_eq!(histogram_bucket(5_000 * NANOS_PER_MS), 7);
+    }
+
+    #[test]
+    fn test_stats_per_tool_and_caller() {
+        clear();
+        set_time_override(Some(1_000 * HOUR));
+
+        record_call("search", "alice", 2 * NANOS_PER_MS, true);
+        record_call("search", "bob", 4 * NANOS_PER_MS, false);
+        record_call("summarize", "alice", 200 * NANOS_PER_MS, true);
+
+        let stats = usage_stats(None);
+        assert_eq!(stats.total_calls(), 3);
+        assert_eq!(stats.total_errors(), 1);
+
+        let search = stats.tool("search").unwrap();
+        assert_eq!(search.calls, 2);
+        assert!((search.error_rate - 0.5).abs() < f64::EPSILON);
+        assert!((search.avg_latency_ms - 3.0).abs() < f64::EPSILON);
+        assert_eq!(search.latency_histogram[1], 2);
+
+        assert_eq!(stats.caller("alice").unwrap().calls, 2);
+        assert_eq!(stats.tools[0].name, "search");
+
+        set_time_override(None);
+    }
+
+    #[test]
+    fn test_window_and_retention() {
+        clear();
+        set_retention_hours(3);
+
+        set_time_override(Some(2_000 * HOUR));
+        record_call("old", "alice", 0, true);
+        set_time_override(Some(2_002 * HOUR));
+        record_call("recent", "alice", 0, true);
+
+        let stats = usage_stats(Some(3_600));
+        assert!(stats.tool("old").is_none());
+        assert!(stats.tool("recent").is_some());
+        assert!(usage_stats(None).tool("old").is_some());
+
+        // Out of retention once the clock moves past three buckets.
+        set_time_override(Some(2_003 * HOUR));
+        record_call("recent", "alice", 0, true);
+        assert!(usage_stats(None).tool("old").is_none());
+
+        set_retention_hours(DEFAULT_RETENTION_HOURS);
+        set_time_override(None);
+    }
+
+    #[test]
+    fn test_entries_are_bounded() {
+        clear();
+        set_max_entries(4);
+        set_time_override(Some(3_000 * HOUR));
+
+        for caller in ["a", "b", "c", "d", "e"] {
+            record_call("tool", caller, 0, true);
+        }
+        assert!(USAGE.with(|usage| usage.borrow().len()) <= 4);
+
+        set_max_entries(DEFAULT_MAX_ENTRIES);
+        set_time_override(None);
+    }
+
+    #[test]
+    fn test_scrape_token() {
+        let anonymous = Principal::anonymous();
+        set_scrape_token(None).unwrap();
+        assert!(scrape_allowed(None, &anonymous));
+        assert!(set_scrape_token(Some("short")).is_err());
+
+        set_scrape_token(Some("0123456789abcdef")).unwrap();
+        assert!(scrape_token_required());
+        assert!(!scrape_allowed(None, &anonymous));
+        assert!(!scrape_allowed(Some("guess"), &anonymous));
+        assert!(scrape_allowed(Some("0123456789abcdef"), &anonymous));
+
+        set_scrape_token(None).unwrap();
+        assert!(!scrape_token_required());
+    }
+
+    #[test]
+    fn test_scrape_principals() {
+        let scraper = Principal::from_slice(&[4, 2]);
+        let other = Principal::from_slice(&[4, 3]);
+        assert!(set_scrape_principals(&[Principal::anonymous()]).is_err());
+
+        set_scrape_principals(&[scraper]).unwrap();
+        assert_eq!(scrape_principals(), vec![scraper]);
+        assert!(scrape_allowed(None, &scraper));
+        assert!(!scrape_allowed(None, &other));
+
+        set_scrape_token(Some("0123456789abcdef")).unwrap();
+        assert!(scrape_allowed(None, &scraper));
+        assert!(scrape_allowed(Some("0123456789abcdef"), &other));
+        assert!(!scrape_allowed(None, &other));
+
+        set_scrape_token(None).unwrap();
+        set_scrape_principals(&[]).unwrap();
+        assert!(scrape_principals().is_empty());
+        assert!(scrape_allowed(None, &other));
+    }
+
+    #[test]
+    fn test_usage_record_round_trip() {
+        let mut record = UsageRecord::default();
+        record.observe(7 * NANOS_PER_MS, false);
+        record.observe(u64::MAX, true);
+        assert_eq!(UsageRecord::from_bytes(record.to_bytes()), record);
+    }
+}
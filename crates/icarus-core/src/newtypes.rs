@@ -374,25 +374,15 @@ impl Timestamp {
 
     /// Returns the current timestamp.
     ///
+    /// Honors the test clock installed with [`crate::time::set_time_override`].
+    ///
     /// # Panics
     ///
     /// May panic if system time is before Unix epoch (extremely unlikely).
     #[must_use]
     #[inline]
     pub fn now() -> Self {
-        #[cfg(not(feature = "ic-canister"))]
-        {
-            #[allow(clippy::cast_possible_truncation)]
-            let nanos = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .expect("System time before Unix epoch")
-                .as_nanos() as u64;
-            Self(nanos)
-        }
-        #[cfg(feature = "ic-canister")]
-        {
-            Self(time())
-        }
+        Self(crate::time::now_nanos())
     }
 
     /// Returns the timestamp as nanoseconds since Unix epoch.
@@ -0,0 +1,162 @@
+//! OpenAPI 3.1 documents for the HTTP tool API.
+//!
+//! [`document`] describes the routes of [`crate::http_tools`] for a list of
+//! tools: one `POST /tools/<name>` operation per tool, whose request body is
+//! the tool's input schema. OpenAPI 3.1 schemas are JSON Schema, so the
+//! schemas from `#[tool]` are used unchanged.
+//!
+//! `mcp! { http_tools = true }` serves the document at `/openapi.json`, and
+//! `icarus generate openapi` writes it for a deployed canister.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use icarus_core::{openapi, Tool};
+//!
+//! let tool = Tool::new("add", "Adds two numbers", Arc::new(serde_json::Map::new()));
+//! let doc = openapi::document("calculator", "1.0.0", &[tool]);
+//!
+//! assert_eq!(doc["openapi"], "3.1.0");
+//! assert_eq!(doc["paths"]["/tools/add"]["post"]["operationId"], "add");
+//! ```
+
+use serde_json::{json, Map, Value};
+
+use crate::http_tools::TOOLS_PATH;
+use crate::Tool;
+
+/// OpenAPI version of the documents.
+pub const OPENAPI_VERSION: &str = "3.1.0";
+
+/// Returns the OpenAPI document of the tool API for `tools`, listed under
+/// the names clients call them by.
+#[must_use]
+pub fn document(title: &str, version: &str, tools: &[Tool]) -> Value {
+    let mut paths = Map::new();
+    paths.insert(
+        TOOLS_PATH.to_string(),
+        json!({
+            "get": {
+                "operationId": "listTools",
+                "summary": "List the available tools",
+                "responses": {
+                    "200": {
+                        "description": "The tools with their input schemas",
+                        "content": { "application/json": { "schema": {
+                            "type": "object",
+                            "properties": { "tools": { "type": "array", "items": { "type": "object" } } }
+                        } } }
+                    }
+                }
+            }
+        }),
+    );
+    for tool in tools {
+        paths.insert(format!("{TOOLS_PATH}/{}", tool.name), operation(tool));
+    }
+
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": { "title": title, "version": version },
+        "paths": paths,
+        "components": {
+            "schemas": {
+                "CallToolResult": {
+                    "type": "object",
+                    "properties": {
+                        "content": { "type": "array", "items": { "type": "object" } },
+                        "structuredContent": {},
+                        "isError": { "type": "boolean" }
+                    },
+                    "required": ["content"]
+                },
+                "Error": {
+                    "type": "object",
+                    "properties": { "error": {} },
+                    "required": ["error"]
+                }
+            },
+            "securitySchemes": {
+                "bearer": { "type": "http", "scheme": "bearer" },
+                "apiKey": { "type": "apiKey", "in": "header", "name": "X-API-Key" }
+            }
+        }
+    })
+}
+
+/// Describes the `POST` that calls `tool`.
+fn operation(tool: &Tool) -> Value {
+    let description = tool.description.as_deref().unwrap_or_default();
+    // Tools without parameters have an empty schema
+    let schema = if tool.input_schema.is_empty() {
+        json!({ "type": "object" })
+    } else {
+        Value::Object(tool.input_schema.as_ref().clone())
+    };
+    let error = |description: &str| {
+        json!({
+            "description": description,
+            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" } } }
+        })
+    };
+
+    json!({
+        "post": {
+            "operationId": tool.name,
+            "summary": description.lines().next().unwrap_or_default(),
+            "description": description,
+            "security": [{ "bearer": [] }, { "apiKey": [] }],
+            "requestBody": {
+                "required": false,
+                "content": { "application/json": { "schema": schema } }
+            },
+            "responses": {
+                "200": {
+                    "description": "The tool's result",
+                    "content": { "application/json": { "schema": { "$ref": "#/components/schemas/CallToolResult" } } }
+                },
+                "400": error("Invalid arguments"),
+                "401": error("Missing or invalid API key"),
+                "404": error("Unknown or disabled tool"),
+                "503": error("Maintenance mode or low cycles")
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_tool_schemas_become_request_bodies() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "query": { "type": "string" } },
+            "required": ["query"]
+        });
+        let Value::Object(schema) = schema else {
+            unreachable!()
+        };
+        let search = Tool::new("search", "Searches notes\nBy keyword", Arc::new(schema));
+        let ping = Tool::new("ping", "Pings", Arc::new(Map::new()));
+
+        let doc = document("notes", "2.0.0", &[search, ping]);
+        assert_eq!(doc["info"]["version"], "2.0.0");
+
+        let post = &doc["paths"]["/tools/search"]["post"];
+        assert_eq!(post["summary"], "Searches notes");
+        assert_eq!(
+            post["requestBody"]["content"]["application/json"]["schema"]["required"][0],
+            "query"
+        );
+        assert_eq!(
+            doc["paths"]["/tools/ping"]["post"]["requestBody"]["content"]["application/json"]
+                ["schema"]["type"],
+            "object"
+        );
+        assert!(doc["paths"]["/tools"]["get"].is_object());
+    }
+}
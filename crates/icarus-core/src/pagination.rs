@@ -0,0 +1,231 @@
+//! Cursor-based pagination of tool results.
+//!
+//! Every Icarus tool that returns a list pages through it the same way, so
+//! an AI client that learned one canister's tools can page through any
+//! other's. A request carries an optional `cursor` and `limit`; a page
+//! carries the items and the `next_cursor` to pass for the following page,
+//! absent on the last one:
+//!
+//! ```json
+//! { "items": ["a", "b"], "next_cursor": "djE6Mg" }
+//! ```
+//!
+//! `#[tool(paginated)]` adds the `cursor` and `limit` parameters to a tool
+//! returning an iterator (or anything `IntoIterator`, optionally inside a
+//! `Result`) and turns its return value into a [`Paginated`] page with
+//! [`paginate`]. Filtering and sorting stay ordinary tool parameters applied
+//! before paging; a cursor is only meaningful with the same filter and sort
+//! arguments that produced it.
+//!
+//! Cursors are opaque to clients. They encode the offset of the next item,
+//! so a page of a list that changed in between may skip or repeat items.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::pagination::{paginate, PageRequest};
+//!
+//! let first = paginate(1..=5, None, Some(2)).unwrap();
+//! assert_eq!(first.items, vec![1, 2]);
+//!
+//! let request = PageRequest::new().with_cursor(first.next_cursor.unwrap()).with_limit(10);
+//! let rest = request.paginate(1..=5).unwrap();
+//! assert_eq!(rest.items, vec![3, 4, 5]);
+//! assert!(rest.next_cursor.is_none());
+//! ```
+
+use base64::Engine as _;
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+use crate::{IcarusError, Result};
+
+/// Items per page when the request sets no limit.
+pub const DEFAULT_PAGE_SIZE: u32 = 50;
+
+/// Largest page served; larger limits are lowered to it.
+pub const MAX_PAGE_SIZE: u32 = 500;
+
+const CURSOR_PREFIX: &str = "v1:";
+
+/// The paging arguments of a request.
+#[derive(Debug, Clone, Default, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct PageRequest {
+    /// `next_cursor` of the previous page, or `None` for the first page.
+    pub cursor: Option<String>,
+    /// Most items to return, [`DEFAULT_PAGE_SIZE`] if `None`.
+    pub limit: Option<u32>,
+}
+
+impl PageRequest {
+    /// Creates a request for the first page with the default limit.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Continues after the page that returned `cursor`.
+    #[must_use]
+    pub fn with_cursor(mut self, cursor: impl Into<String>) -> Self {
+        self.cursor = Some(cursor.into());
+        self
+    }
+
+    /// Sets the most items to return.
+    #[must_use]
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns the requested page of `items`.
+    ///
+    /// # Errors
+    ///
+    /// See [`paginate`].
+    pub fn paginate<I: IntoIterator>(&self, items: I) -> Result<Paginated<I::Item>> {
+        paginate(items, self.cursor.as_deref(), self.limit)
+    }
+}
+
+/// One page of results.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct Paginated<T> {
+    /// Items on this page.
+    pub items: Vec<T>,
+    /// Cursor of the next page, or `None` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Paginated<T> {
+    /// Returns whether more pages follow.
+    #[must_use]
+    pub fn has_more(&self) -> bool {
+        self.next_cursor.is_some()
+    }
+
+    /// Converts the items, keeping the cursor.
+    #[must_use]
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Paginated<U> {
+        Paginated {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+/// Returns the page of `items` after `cursor` with at most `limit` items.
+///
+/// Only the items up to the end of the page, plus one to tell whether more
+/// follow, are taken from the iterator.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` for a cursor that was not
+/// returned by this module or a limit of zero.
+pub fn paginate<I: IntoIterator>(
+    items: I,
+    cursor: Option<&str>,
+    limit: Option<u32>,
+) -> Result<Paginated<I::Item>> {
+    let offset = cursor.map(decode_cursor).transpose()?.unwrap_or(0);
+    let limit = match limit {
+        Some(0) => {
+            return Err(IcarusError::ConfigurationError(
+                "Page limit must be at least 1".to_string(),
+            ))
+        }
+        Some(limit) => limit.min(MAX_PAGE_SIZE),
+        None => DEFAULT_PAGE_SIZE,
+    } as usize;
+
+    let mut rest = items.into_iter().skip(offset);
+    let items: Vec<_> = rest.by_ref().take(limit).collect();
+    let next_cursor =
+        (items.len() == limit && rest.next().is_some()).then(|| encode_cursor(offset + limit));
+    Ok(Paginated { items, next_cursor })
+}
+
+/// Returns the cursor of the page starting at `offset`.
+#[must_use]
+pub fn encode_cursor(offset: usize) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(format!("{CURSOR_PREFIX}{offset}"))
+}
+
+/// Returns the offset a cursor points at.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if `cursor` was not made by
+/// [`encode_cursor`].
+pub fn decode_cursor(cursor: &str) -> Result<usize> {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .and_then(|text| text.strip_prefix(CURSOR_PREFIX)?.parse().ok())
+        .ok_or_else(|| IcarusError::ConfigurationError(format!("Invalid cursor '{cursor}'")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages_cover_all_items_once() {
+        let mut cursor = None;
+        let mut seen = Vec::new();
+        loop {
+            let page = paginate(0..23, cursor.as_deref(), Some(5)).unwrap();
+            seen.extend(page.items);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(seen, (0..23).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_exact_last_page_has_no_cursor() {
+        let page = paginate(0..10, None, Some(10)).unwrap();
+        assert_eq!(page.items.len(), 10);
+        assert!(!page.has_more());
+
+        let empty = paginate(Vec::<u8>::new(), None, None).unwrap();
+        assert!(empty.items.is_empty());
+        assert!(empty.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_limits() {
+        assert_eq!(
+            paginate(0..1_000, None, None).unwrap().items.len(),
+            DEFAULT_PAGE_SIZE as usize
+        );
+        assert_eq!(
+            paginate(0..1_000, None, Some(10_000)).unwrap().items.len(),
+            MAX_PAGE_SIZE as usize
+        );
+        assert!(paginate(0..10, None, Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_cursor_round_trip_and_garbage() {
+        assert_eq!(decode_cursor(&encode_cursor(42)).unwrap(), 42);
+        assert!(decode_cursor("not a cursor").is_err());
+        assert!(
+            decode_cursor(&base64::engine::general_purpose::URL_SAFE_NO_PAD.encode("v2:1"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_map_keeps_cursor() {
+        let page = paginate(["a", "b", "c"], None, Some(1))
+            .unwrap()
+            .map(str::to_uppercase);
+        assert_eq!(page.items, vec!["A".to_string()]);
+        assert_eq!(page.next_cursor, Some(encode_cursor(1)));
+    }
+}
@@ -0,0 +1,288 @@
+//! Size limits on tool call requests and responses.
+//!
+//! A runaway client can send an argument blob large enough to trap the
+//! canister while it is being parsed, and a tool can return more than fits
+//! in a reply. `mcp_call_tool` checks the raw request against
+//! [`PayloadLimits::max_request_bytes`] before parsing it, and the
+//! serialized response against [`PayloadLimits::max_response_bytes`] before
+//! returning it. Either rejection is a JSON-RPC error with code
+//! [`PAYLOAD_TOO_LARGE_CODE`] and the sizes in its `data`:
+//!
+//! ```json
+//! {
+//!   "code": -32004,
+//!   "message": "Request of 5242880 bytes exceeds the limit of 1048576 bytes",
+//!   "data": {
+//!     "payload_too_large": true, "direction": "request", "size": 5242880, "limit": 1048576,
+//!     "code": "ICARUS-REQUEST-413"
+//!   }
+//! }
+//! ```
+//!
+//! A response is checked after the tool ran, so its side effects stand.
+//!
+//! The limits default to [`DEFAULT_MAX_REQUEST_BYTES`] and
+//! [`DEFAULT_MAX_RESPONSE_BYTES`], well under the 2 MiB message limit of the
+//! Internet Computer, and are set with `mcp!{ max_request_bytes = ..,
+//! max_response_bytes = .. }` or the `[limits]` table of icarus.toml.
+//! Rejections are counted in heap memory since the last upgrade; `mcp!{}`
+//! exposes them as the `get_payload_stats` query and in `/metrics`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::payload_limits::{self, Direction, PayloadLimits};
+//!
+//! let limits = PayloadLimits::new(1_024, 4_096);
+//! assert!(limits.check_request(512).is_ok());
+//!
+//! let rejected = limits.check_request(2_048).unwrap_err();
+//! assert_eq!(rejected.direction, Direction::Request);
+//! assert_eq!(rejected.limit, 1_024);
+//!
+//! assert!(limits.check_response("export", 8_192).is_err());
+//! let stats = payload_limits::payload_stats(&limits);
+//! assert_eq!(stats.rejected_responses, 1);
+//! assert_eq!(stats.rejected_by_tool, vec![("export".to_string(), 1)]);
+//! ```
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+/// JSON-RPC error code of calls rejected for their size.
+pub const PAYLOAD_TOO_LARGE_CODE: i32 = -32004;
+
+/// Default limit on the raw `tools/call` request (1 MiB).
+pub const DEFAULT_MAX_REQUEST_BYTES: usize = 1024 * 1024;
+
+/// Default limit on the serialized `tools/call` response (1.5 MiB).
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 1536 * 1024;
+
+/// Size limits of one canister's tool calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadLimits {
+    /// Largest request accepted, in bytes.
+    pub max_request_bytes: usize,
+    /// Largest response returned, in bytes.
+    pub max_response_bytes: usize,
+}
+
+impl Default for PayloadLimits {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_REQUEST_BYTES, DEFAULT_MAX_RESPONSE_BYTES)
+    }
+}
+
+impl PayloadLimits {
+    /// Creates limits on requests and responses, in bytes.
+    #[must_use]
+    pub const fn new(max_request_bytes: usize, max_response_bytes: usize) -> Self {
+        Self {
+            max_request_bytes,
+            max_response_bytes,
+        }
+    }
+
+    /// Checks the size of a raw request, counting it if rejected.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PayloadTooLarge`] if `size` exceeds the request limit.
+    pub fn check_request(&self, size: usize) -> Result<(), PayloadTooLarge> {
+        check(Direction::Request, None, size, self.max_request_bytes)
+    }
+
+    /// Checks the size of `tool`'s serialized response, counting it if
+    /// rejected.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PayloadTooLarge`] if `size` exceeds the response limit.
+    pub fn check_response(&self, tool: &str, size: usize) -> Result<(), PayloadTooLarge> {
+        check(
+            Direction::Response,
+            Some(tool),
+            size,
+            self.max_response_bytes,
+        )
+    }
+}
+
+/// Which side of a call was too large.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// The request sent to the canister.
+    Request,
+    /// The response of the tool.
+    Response,
+}
+
+impl Direction {
+    /// Returns `"request"` or `"response"`.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Request => "request",
+            Self::Response => "response",
+        }
+    }
+}
+
+/// A request or response over its limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadTooLarge {
+    /// Which side was too large.
+    pub direction: Direction,
+    /// Size in bytes.
+    pub size: usize,
+    /// Limit in bytes.
+    pub limit: usize,
+}
+
+impl PayloadTooLarge {
+    /// Returns the `data` of the JSON-RPC error.
+    #[must_use]
+    pub fn data(&self) -> serde_json::Value {
+        serde_json::json!({
+            "payload_too_large": true,
+            "direction": self.direction.as_str(),
+            "size": self.size,
+            "limit": self.limit,
+            "code": crate::error_codes::IcarusCode::PayloadTooLarge.code(),
+        })
+    }
+}
+
+impl fmt::Display for PayloadTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = match self.direction {
+            Direction::Request => "Request",
+            Direction::Response => "Response",
+        };
+        write!(
+            f,
+            "{side} of {} bytes exceeds the limit of {} bytes",
+            self.size, self.limit
+        )
+    }
+}
+
+impl std::error::Error for PayloadTooLarge {}
+
+/// Limits and rejections since the last upgrade.
+#[derive(Debug, Clone, Default, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct PayloadStats {
+    /// Largest request accepted, in bytes.
+    pub max_request_bytes: u64,
+    /// Largest response returned, in bytes.
+    pub max_response_bytes: u64,
+    /// Requests rejected for their size.
+    pub rejected_requests: u64,
+    /// Responses withheld for their size.
+    pub rejected_responses: u64,
+    /// Largest rejected payload, in bytes.
+    pub largest_rejected_bytes: u64,
+    /// Withheld responses per tool, sorted by tool name.
+    pub rejected_by_tool: Vec<(String, u64)>,
+}
+
+#[derive(Debug, Default)]
+struct Rejections {
+    requests: u64,
+    responses: u64,
+    largest: u64,
+    by_tool: BTreeMap<String, u64>,
+}
+
+thread_local! {
+    static REJECTIONS: RefCell<Rejections> = RefCell::new(Rejections::default());
+}
+
+fn check(
+    direction: Direction,
+    tool: Option<&str>,
+    size: usize,
+    limit: usize,
+) -> Result<(), PayloadTooLarge> {
+    if size <= limit {
+        return Ok(());
+    }
+
+    REJECTIONS.with(|rejections| {
+        let mut rejections = rejections.borrow_mut();
+        match direction {
+            Direction::Request => rejections.requests += 1,
+            Direction::Response => rejections.responses += 1,
+        }
+        rejections.largest = rejections.largest.max(size as u64);
+        if let Some(tool) = tool {
+            *rejections.by_tool.entry(tool.to_string()).or_default() += 1;
+        }
+    });
+    Err(PayloadTooLarge {
+        direction,
+        size,
+        limit,
+    })
+}
+
+/// Returns `limits` and the rejections counted since the last upgrade.
+#[must_use]
+pub fn payload_stats(limits: &PayloadLimits) -> PayloadStats {
+    REJECTIONS.with(|rejections| {
+        let rejections = rejections.borrow();
+        PayloadStats {
+            max_request_bytes: limits.max_request_bytes as u64,
+            max_response_bytes: limits.max_response_bytes as u64,
+            rejected_requests: rejections.requests,
+            rejected_responses: rejections.responses,
+            largest_rejected_bytes: rejections.largest,
+            rejected_by_tool: rejections
+                .by_tool
+                .iter()
+                .map(|(tool, count)| (tool.clone(), *count))
+                .collect(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizes_at_the_limit_pass() {
+        let limits = PayloadLimits::new(10, 20);
+        assert!(limits.check_request(10).is_ok());
+        assert!(limits.check_response("echo", 20).is_ok());
+        assert_eq!(payload_stats(&limits).rejected_requests, 0);
+    }
+
+    #[test]
+    fn test_rejections_are_counted() {
+        let limits = PayloadLimits::default();
+        let rejected = limits
+            .check_request(DEFAULT_MAX_REQUEST_BYTES + 1)
+            .unwrap_err();
+        assert_eq!(
+            rejected.to_string(),
+            "Request of 1048577 bytes exceeds the limit of 1048576 bytes"
+        );
+        assert_eq!(rejected.data()["direction"], "request");
+        assert_eq!(rejected.data()["payload_too_large"], true);
+
+        limits.check_response("dump", 5_000_000).unwrap_err();
+        limits.check_response("dump", 2_000_000).unwrap_err();
+
+        let stats = payload_stats(&limits);
+        assert_eq!(stats.max_request_bytes, DEFAULT_MAX_REQUEST_BYTES as u64);
+        assert_eq!(stats.rejected_requests, 1);
+        assert_eq!(stats.rejected_responses, 2);
+        assert_eq!(stats.largest_rejected_bytes, 5_000_000);
+        assert_eq!(stats.rejected_by_tool, vec![("dump".to_string(), 2)]);
+    }
+}
@@ -0,0 +1,728 @@
+//! Per-call payment gating through ICRC-1/ICRC-2 ledgers.
+//!
+//! A tool is priced with `#[tool(paid = "0.1 ICP")]` or [`set_tool_price`].
+//! Before a priced tool runs, `mcp_call_tool` calls [`charge`], which pulls the
+//! price from the caller's account with `icrc2_transfer_from`. The caller must
+//! first approve the canister as a spender with `icrc2_approve`.
+//!
+//! Successful charges are recorded per tool in stable memory and summarized by
+//! [`revenue_report`], which `mcp!{}` exposes as `get_revenue_report`.
+//!
+//! Charges happen before the tool executes, once `mcp_call_tool` knows the
+//! tool exists. When the tool then fails, [`refund`] sends the price back to
+//! the caller less the ledger fee, and once the ledger accepts the transfer
+//! the call no longer counts as revenue.
+//!
+//! The ledger configuration and tool prices are kept in stable memory, so
+//! prices set with [`set_tool_price`] and the ledger set with
+//! [`configure_ledger`] survive upgrades.
+//!
+//! The transfer memo names the tool, or is the SHA-256 of its name when the
+//! name is longer than the [`MAX_MEMO_LEN`] bytes ICRC ledgers accept.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use icarus_core::payments::{self, LedgerConfig};
+//!
+//! #[ic_cdk::init]
+//! fn init() {
+//!     // The ICP ledger is the default; configure another ICRC-2 token here.
+//!     payments::configure_ledger(LedgerConfig::icp());
+//! }
+//!
+//! #[tool("Premium forecast", paid = "0.1 ICP")]
+//! fn forecast(city: String) -> Result<String, String> {
+//!     Ok(format!("Sunny in {city}"))
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt;
+use std::str::FromStr;
+
+use candid::{CandidType, Deserialize, Nat, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::memory::{
+    self, StableMemory, PAYMENTS_LEDGER_MEMORY_ID, PAYMENTS_PRICES_MEMORY_ID,
+    PAYMENTS_REVENUE_MEMORY_ID,
+};
+use crate::{CanisterId, IcarusError, Result};
+
+/// Canister id of the ICP ledger.
+pub const ICP_LEDGER_CANISTER_ID: &str = "ryjl3-tyaaa-aaaaa-aaaba-cai";
+
+/// Longest memo ICRC ledgers accept, in bytes.
+pub const MAX_MEMO_LEN: usize = 32;
+
+/// A price such as `0.1 ICP`, kept in decimal form until the ledger is known.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Price {
+    mantissa: u128,
+    scale: u32,
+    symbol: String,
+}
+
+impl Price {
+    /// Returns the token symbol.
+    #[must_use]
+    #[inline]
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Converts the price to ledger base units for a token with `decimals`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::ConfigurationError` if the price has more
+    /// fractional digits than the token supports or overflows.
+    pub fn to_base_units(&self, decimals: u8) -> Result<u128> {
+        let decimals = u32::from(decimals);
+        if self.scale > decimals {
+            return Err(IcarusError::ConfigurationError(format!(
+                "Price {self} has more than {decimals} decimal places"
+            )));
+        }
+        10u128
+            .checked_pow(decimals - self.scale)
+            .and_then(|factor| self.mantissa.checked_mul(factor))
+            .ok_or_else(|| IcarusError::ConfigurationError(format!("Price {self} is too large")))
+    }
+}
+
+impl FromStr for Price {
+    type Err = IcarusError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            IcarusError::ConfigurationError(format!(
+                "Invalid price '{s}': expected an amount and a symbol, e.g. \"0.1 ICP\""
+            ))
+        };
+
+        let mut parts = s.split_whitespace();
+        let (Some(amount), Some(symbol), None) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(invalid());
+        };
+
+        let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+        if (whole.is_empty() && fraction.is_empty())
+            || !whole
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let mantissa = format!("{whole}{fraction}")
+            .parse::<u128>()
+            .map_err(|_| invalid())?;
+        let scale = u32::try_from(fraction.len()).map_err(|_| invalid())?;
+
+        Ok(Self {
+            mantissa,
+            scale,
+            symbol: symbol.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.mantissa.to_string();
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return write!(f, "{digits} {}", self.symbol);
+        }
+        let padded = format!("{digits:0>width$}", width = scale + 1);
+        let (whole, fraction) = padded.split_at(padded.len() - scale);
+        write!(f, "{whole}.{fraction} {}", self.symbol)
+    }
+}
+
+/// The ledger that receives payments.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct LedgerConfig {
+    /// Ledger canister implementing ICRC-2.
+    pub ledger: CanisterId,
+    /// Token symbol that prices must use.
+    pub symbol: String,
+    /// Token decimals.
+    pub decimals: u8,
+    /// Subaccount of this canister that receives payments.
+    pub subaccount: Option<Vec<u8>>,
+}
+
+impl LedgerConfig {
+    /// Configuration for the ICP ledger.
+    ///
+    /// # Panics
+    ///
+    /// Never panics in practice; the ICP ledger id is a valid constant.
+    #[must_use]
+    pub fn icp() -> Self {
+        Self {
+            ledger: CanisterId::from_text(ICP_LEDGER_CANISTER_ID)
+                .expect("ICP ledger canister id is valid"),
+            symbol: "ICP".to_string(),
+            decimals: 8,
+            subaccount: None,
+        }
+    }
+}
+
+/// ICRC-1 account.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct Account {
+    /// Account owner.
+    pub owner: Principal,
+    /// Optional 32-byte subaccount.
+    pub subaccount: Option<Vec<u8>>,
+}
+
+/// Arguments of `icrc2_transfer_from`.
+#[derive(Debug, Clone, CandidType, Deserialize)]
+pub struct TransferFromArgs {
+    /// Subaccount of the spender (this canister).
+    pub spender_subaccount: Option<Vec<u8>>,
+    /// Account to debit.
+    pub from: Account,
+    /// Account to credit.
+    pub to: Account,
+    /// Amount in base units.
+    pub amount: Nat,
+    /// Expected fee, or `None` for the ledger default.
+    pub fee: Option<Nat>,
+    /// Optional memo.
+    pub memo: Option<Vec<u8>>,
+    /// Optional deduplication timestamp.
+    pub created_at_time: Option<u64>,
+}
+
+/// Errors returned by `icrc2_transfer_from`.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub enum TransferFromError {
+    /// The fee does not match the ledger fee.
+    BadFee {
+        /// Fee expected by the ledger.
+        expected_fee: Nat,
+    },
+    /// The burn amount is below the minimum.
+    BadBurn {
+        /// Minimum burn amount.
+        min_burn_amount: Nat,
+    },
+    /// The payer's balance is too low.
+    InsufficientFunds {
+        /// Current balance.
+        balance: Nat,
+    },
+    /// The payer has not approved enough for this canister.
+    InsufficientAllowance {
+        /// Current allowance.
+        allowance: Nat,
+    },
+    /// `created_at_time` is too far in the past.
+    TooOld,
+    /// `created_at_time` is in the future.
+    CreatedInFuture {
+        /// Current ledger time.
+        ledger_time: u64,
+    },
+    /// The transfer duplicates an earlier one.
+    Duplicate {
+        /// Block index of the original transfer.
+        duplicate_of: Nat,
+    },
+    /// The ledger cannot process transfers right now.
+    TemporarilyUnavailable,
+    /// Any other ledger error.
+    GenericError {
+        /// Ledger-specific code.
+        error_code: Nat,
+        /// Error description.
+        message: String,
+    },
+}
+
+impl fmt::Display for TransferFromError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadFee { expected_fee } => write!(f, "bad fee, expected {expected_fee}"),
+            Self::BadBurn { min_burn_amount } => {
+                write!(f, "bad burn, minimum is {min_burn_amount}")
+            }
+            Self::InsufficientFunds { balance } => {
+                write!(f, "insufficient funds (balance {balance})")
+            }
+            Self::InsufficientAllowance { allowance } => write!(
+                f,
+                "insufficient allowance ({allowance}); call icrc2_approve for this canister first"
+            ),
+            Self::TooOld => f.write_str("transaction too old"),
+            Self::CreatedInFuture { ledger_time } => {
+                write!(f, "created in the future (ledger time {ledger_time})")
+            }
+            Self::Duplicate { duplicate_of } => write!(f, "duplicate of block {duplicate_of}"),
+            Self::TemporarilyUnavailable => f.write_str("ledger temporarily unavailable"),
+            Self::GenericError {
+                error_code,
+                message,
+            } => write!(f, "ledger error {error_code}: {message}"),
+        }
+    }
+}
+
+/// Arguments of `icrc1_transfer`.
+#[derive(Debug, Clone, CandidType, Deserialize)]
+pub struct TransferArgs {
+    /// Subaccount to debit.
+    pub from_subaccount: Option<Vec<u8>>,
+    /// Account to credit.
+    pub to: Account,
+    /// Amount in base units.
+    pub amount: Nat,
+    /// Expected fee, or `None` for the ledger default.
+    pub fee: Option<Nat>,
+    /// Optional memo.
+    pub memo: Option<Vec<u8>>,
+    /// Optional deduplication timestamp.
+    pub created_at_time: Option<u64>,
+}
+
+/// A successful charge.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct PaymentReceipt {
+    /// Tool that was paid for.
+    pub tool: String,
+    /// Amount charged in base units.
+    pub amount: u128,
+    /// Ledger block index of the transfer.
+    pub block_index: Nat,
+}
+
+/// Revenue collected by one tool.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct ToolRevenue {
+    /// Tool name.
+    pub tool: String,
+    /// Paid calls.
+    pub calls: u64,
+    /// Total amount in base units.
+    pub amount: u128,
+}
+
+/// Revenue across all tools.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct RevenueReport {
+    /// Ledger the revenue was collected on.
+    pub ledger: CanisterId,
+    /// Token symbol.
+    pub symbol: String,
+    /// Token decimals.
+    pub decimals: u8,
+    /// Paid calls across all tools.
+    pub total_calls: u64,
+    /// Total amount in base units.
+    pub total_amount: u128,
+    /// Per-tool breakdown.
+    pub tools: Vec<ToolRevenue>,
+}
+
+/// Stable revenue counter for one tool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct RevenueRecord {
+    calls: u64,
+    amount: u128,
+}
+
+impl Storable for RevenueRecord {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(self.into_bytes())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        let mut calls = [0u8; 8];
+        let mut amount = [0u8; 16];
+        calls.copy_from_slice(&bytes[..8]);
+        amount.copy_from_slice(&bytes[8..24]);
+        Self {
+            calls: u64::from_le_bytes(calls),
+            amount: u128::from_le_bytes(amount),
+        }
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(24);
+        bytes.extend_from_slice(&self.calls.to_le_bytes());
+        bytes.extend_from_slice(&self.amount.to_le_bytes());
+        bytes
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 24,
+        is_fixed_size: true,
+    };
+}
+
+thread_local! {
+    // The candid-encoded ledger configuration under key 0, if configured
+    static LEDGER: RefCell<StableBTreeMap<u8, Vec<u8>, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(PAYMENTS_LEDGER_MEMORY_ID))
+    );
+
+    // Prices in their text form, e.g. `0.1 ICP`
+    static PRICES: RefCell<StableBTreeMap<String, String, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(PAYMENTS_PRICES_MEMORY_ID))
+    );
+
+    static REVENUE: RefCell<StableBTreeMap<String, RevenueRecord, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(PAYMENTS_REVENUE_MEMORY_ID))
+    );
+}
+
+/// Sets the ledger that receives payments. Defaults to [`LedgerConfig::icp`].
+///
+/// # Panics
+///
+/// Never panics in practice; a `LedgerConfig` always encodes.
+pub fn configure_ledger(config: LedgerConfig) {
+    let bytes = candid::encode_one(&config).expect("LedgerConfig encodes");
+    LEDGER.with(|ledger| ledger.borrow_mut().insert(0, bytes));
+}
+
+/// Returns the configured ledger.
+#[must_use]
+pub fn ledger_config() -> LedgerConfig {
+    LEDGER
+        .with(|ledger| ledger.borrow().get(&0))
+        .and_then(|bytes| candid::decode_one(&bytes).ok())
+        .unwrap_or_else(LedgerConfig::icp)
+}
+
+/// Sets the price of a tool, e.g. `set_tool_price("forecast", "0.1 ICP")`.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if the price cannot be parsed.
+pub fn set_tool_price(tool: &str, price: &str) -> Result<()> {
+    let price = price.parse::<Price>()?;
+    PRICES.with(|prices| {
+        prices
+            .borrow_mut()
+            .insert(tool.to_string(), price.to_string())
+    });
+    Ok(())
+}
+
+/// Removes the price of a tool, making it free.
+pub fn clear_tool_price(tool: &str) {
+    PRICES.with(|prices| prices.borrow_mut().remove(tool));
+}
+
+/// Returns the price of a tool, if it is paid.
+#[must_use]
+pub fn tool_price(tool: &str) -> Option<Price> {
+    PRICES
+        .with(|prices| prices.borrow().get(&tool.to_string()))
+        .and_then(|price| price.parse().ok())
+}
+
+/// Charges `caller` for one call of `tool`.
+///
+/// Returns `Ok(None)` without contacting the ledger when the tool is free.
+///
+/// # Errors
+///
+/// - `IcarusError::ConfigurationError` if the price does not match the ledger
+/// - `IcarusError::AccessDenied` if the caller is anonymous or the transfer is refused
+/// - `IcarusError::ExternalServiceError` if the ledger call fails
+pub async fn charge(tool: &str, caller: Principal) -> Result<Option<PaymentReceipt>> {
+    let Some(price) = tool_price(tool) else {
+        return Ok(None);
+    };
+
+    if caller == Principal::anonymous() {
+        return Err(IcarusError::AccessDenied(format!(
+            "Tool '{tool}' costs {price}; anonymous callers cannot pay"
+        )));
+    }
+
+    let config = ledger_config();
+    if !price.symbol().eq_ignore_ascii_case(&config.symbol) {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Tool '{tool}' is priced in {} but the ledger uses {}",
+            price.symbol(),
+            config.symbol
+        )));
+    }
+    let amount = price.to_base_units(config.decimals)?;
+
+    let args = TransferFromArgs {
+        spender_subaccount: None,
+        from: Account {
+            owner: caller,
+            subaccount: None,
+        },
+        to: Account {
+            owner: ic_cdk::api::canister_self(),
+            subaccount: config.subaccount.clone(),
+        },
+        amount: Nat::from(amount),
+        fee: None,
+        memo: Some(payment_memo(tool)),
+        created_at_time: None,
+    };
+
+    let block_index = transfer_from(config.ledger, &args).await?.map_err(|err| {
+        IcarusError::AccessDenied(format!("Payment of {price} for '{tool}' failed: {err}"))
+    })?;
+
+    record_revenue(tool, amount);
+
+    Ok(Some(PaymentReceipt {
+        tool: tool.to_string(),
+        amount,
+        block_index,
+    }))
+}
+
+/// Returns a charge to `caller` after the paid tool failed, less the ledger
+/// fee. Once the ledger accepts the transfer, the call is removed from the
+/// revenue report. Returns `Ok(None)` when the charge does not cover the fee,
+/// in which case nothing is sent and the charge stays in the report.
+///
+/// # Errors
+///
+/// - `IcarusError::AccessDenied` if the ledger refuses the transfer
+/// - `IcarusError::ExternalServiceError` if the ledger call fails
+pub async fn refund(receipt: &PaymentReceipt, caller: Principal) -> Result<Option<Nat>> {
+    let config = ledger_config();
+    let fee = ledger_fee(config.ledger).await?;
+    let Some(amount) = receipt.amount.checked_sub(fee).filter(|amount| *amount > 0) else {
+        return Ok(None);
+    };
+
+    let args = TransferArgs {
+        from_subaccount: config.subaccount,
+        to: Account {
+            owner: caller,
+            subaccount: None,
+        },
+        amount: Nat::from(amount),
+        fee: Some(Nat::from(fee)),
+        memo: Some(payment_memo(&receipt.tool)),
+        created_at_time: None,
+    };
+
+    // The icrc1_transfer errors are a subset of the icrc2_transfer_from ones
+    let tool = &receipt.tool;
+    let block_index = call_ledger::<_, std::result::Result<Nat, TransferFromError>>(
+        config.ledger,
+        "icrc1_transfer",
+        &args,
+    )
+    .await?
+    .map_err(|err| IcarusError::AccessDenied(format!("Refund for '{tool}' failed: {err}")))?;
+
+    reverse_revenue(tool, receipt.amount);
+    Ok(Some(block_index))
+}
+
+/// The transfer memo for a tool: its name, or the SHA-256 of names longer
+/// than [`MAX_MEMO_LEN`].
+#[must_use]
+pub fn payment_memo(tool: &str) -> Vec<u8> {
+    if tool.len() <= MAX_MEMO_LEN {
+        tool.as_bytes().to_vec()
+    } else {
+        Sha256::digest(tool.as_bytes()).to_vec()
+    }
+}
+
+async fn ledger_fee(ledger: CanisterId) -> Result<u128> {
+    let fee: Nat = call_ledger(ledger, "icrc1_fee", &()).await?;
+    u128::try_from(fee.0).map_err(|_| IcarusError::ExternalServiceError {
+        service: ledger.to_string(),
+        message: "icrc1_fee does not fit in u128".to_string(),
+    })
+}
+
+async fn transfer_from(
+    ledger: CanisterId,
+    args: &TransferFromArgs,
+) -> Result<std::result::Result<Nat, TransferFromError>> {
+    call_ledger(ledger, "icrc2_transfer_from", args).await
+}
+
+async fn call_ledger<A, R>(ledger: CanisterId, method: &str, arg: &A) -> Result<R>
+where
+    A: CandidType,
+    R: CandidType + for<'de> Deserialize<'de>,
+{
+    ic_cdk::call::Call::bounded_wait(*ledger.as_principal(), method)
+        .with_arg(arg)
+        .await
+        .map_err(|e| IcarusError::ExternalServiceError {
+            service: ledger.to_string(),
+            message: e.to_string(),
+        })?
+        .candid::<R>()
+        .map_err(|e| IcarusError::CandidError(e.to_string()))
+}
+
+/// Adds one paid call of `amount` base units to a tool's revenue.
+pub fn record_revenue(tool: &str, amount: u128) {
+    REVENUE.with(|revenue| {
+        let mut revenue = revenue.borrow_mut();
+        let mut record = revenue.get(&tool.to_string()).unwrap_or_default();
+        record.calls += 1;
+        record.amount = record.amount.saturating_add(amount);
+        revenue.insert(tool.to_string(), record);
+    });
+}
+
+/// Removes one refunded call of `amount` base units from a tool's revenue.
+pub fn reverse_revenue(tool: &str, amount: u128) {
+    REVENUE.with(|revenue| {
+        let mut revenue = revenue.borrow_mut();
+        let Some(mut record) = revenue.get(&tool.to_string()) else {
+            return;
+        };
+        record.calls = record.calls.saturating_sub(1);
+        record.amount = record.amount.saturating_sub(amount);
+        revenue.insert(tool.to_string(), record);
+    });
+}
+
+/// Summarizes revenue recorded in stable memory.
+#[must_use]
+pub fn revenue_report() -> RevenueReport {
+    let config = ledger_config();
+    let tools: Vec<ToolRevenue> = REVENUE.with(|revenue| {
+        revenue
+            .borrow()
+            .iter()
+            .map(|entry| {
+                let record = entry.value();
+                ToolRevenue {
+                    tool: entry.key().clone(),
+                    calls: record.calls,
+                    amount: record.amount,
+                }
+            })
+            .collect()
+    });
+
+    RevenueReport {
+        ledger: config.ledger,
+        symbol: config.symbol,
+        decimals: config.decimals,
+        total_calls: tools.iter().map(|t| t.calls).sum(),
+        total_amount: tools.iter().map(|t| t.amount).sum(),
+        tools,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_price() {
+        let price: Price = "0.1 ICP".parse().unwrap();
+        assert_eq!(price.symbol(), "ICP");
+        assert_eq!(price.to_base_units(8).unwrap(), 10_000_000);
+        assert_eq!(price.to_string(), "0.1 ICP");
+
+        let whole: Price = "2 ckBTC".parse().unwrap();
+        assert_eq!(whole.to_base_units(8).unwrap(), 200_000_000);
+        assert_eq!(whole.to_string(), "2 ckBTC");
+    }
+
+    #[test]
+    fn test_invalid_prices() {
+        assert!("ICP".parse::<Price>().is_err());
+        assert!("abc ICP".parse::<Price>().is_err());
+        assert!("-1 ICP".parse::<Price>().is_err());
+        assert!("1 ICP extra".parse::<Price>().is_err());
+
+        let too_precise: Price = "0.000000001 ICP".parse().unwrap();
+        assert!(too_precise.to_base_units(8).is_err());
+    }
+
+    #[test]
+    fn test_tool_prices() {
+        set_tool_price("premium", "0.5 ICP").unwrap();
+        assert_eq!(tool_price("premium").unwrap().to_string(), "0.5 ICP");
+        assert!(set_tool_price("premium", "free").is_err());
+
+        clear_tool_price("premium");
+        assert!(tool_price("premium").is_none());
+    }
+
+    #[test]
+    fn test_ledger_config_is_stored() {
+        assert_eq!(ledger_config(), LedgerConfig::icp());
+
+        let config = LedgerConfig {
+            ledger: CanisterId::from_text("mxzaz-hqaaa-aaaar-qaada-cai").unwrap(),
+            symbol: "ckBTC".to_string(),
+            decimals: 8,
+            subaccount: Some(vec![1; 32]),
+        };
+        configure_ledger(config.clone());
+        assert_eq!(ledger_config(), config);
+    }
+
+    #[test]
+    fn test_revenue_report() {
+        record_revenue("forecast", 10_000_000);
+        record_revenue("forecast", 10_000_000);
+        record_revenue("translate", 5);
+
+        let report = revenue_report();
+        assert_eq!(report.symbol, "ICP");
+        assert_eq!(report.total_calls, 3);
+        assert_eq!(report.total_amount, 20_000_005);
+
+        let forecast = report.tools.iter().find(|t| t.tool == "forecast").unwrap();
+        assert_eq!(forecast.calls, 2);
+    }
+
+    #[test]
+    fn test_refunds_reverse_revenue() {
+        record_revenue("summarize", 300);
+        record_revenue("summarize", 300);
+        reverse_revenue("summarize", 300);
+        reverse_revenue("unknown", 300);
+
+        let report = revenue_report();
+        let summarize = report.tools.iter().find(|t| t.tool == "summarize").unwrap();
+        assert_eq!((summarize.calls, summarize.amount), (1, 300));
+        assert!(report.tools.iter().all(|t| t.tool != "unknown"));
+    }
+
+    #[test]
+    fn test_payment_memo_fits_ledgers() {
+        assert_eq!(payment_memo("forecast"), b"forecast".to_vec());
+        let long = "a_tool_name_much_longer_than_thirty_two_bytes";
+        assert_eq!(payment_memo(long).len(), MAX_MEMO_LEN);
+        assert_ne!(payment_memo(long), payment_memo(&format!("{long}_v2")));
+    }
+
+    #[test]
+    fn test_revenue_record_round_trip() {
+        let record = RevenueRecord {
+            calls: 7,
+            amount: u128::MAX - 1,
+        };
+        assert_eq!(RevenueRecord::from_bytes(record.to_bytes()), record);
+    }
+}
@@ -5,6 +5,7 @@
 //! handling with proper validation and error handling.
 
 use std::borrow::Cow;
+use std::time::Duration;
 
 use candid::{CandidType, Deserialize};
 use serde::Serialize;
@@ -223,6 +224,19 @@ impl<'a> ToolCall<'a> {
         self
     }
 
+    /// Converts into a tool call that owns its arguments and metadata.
+    #[must_use]
+    pub fn into_owned(self) -> ToolCall<'static> {
+        ToolCall {
+            name: self.name,
+            arguments: Cow::Owned(self.arguments.into_owned()),
+            session_id: self.session_id,
+            metadata: self
+                .metadata
+                .map(|metadata| Cow::Owned(metadata.into_owned())),
+        }
+    }
+
     /// Extracts typed arguments from the tool call.
     ///
     /// # Errors
@@ -273,6 +287,13 @@ pub enum ToolResult<'a> {
         #[serde(skip_serializing_if = "Option::is_none", borrow)]
         status: Option<Cow<'a, str>>,
     },
+    /// Tool execution was cancelled after exceeding its timeout.
+    Timeout {
+        /// The timeout that was exceeded, in milliseconds.
+        timeout_ms: u64,
+        /// Time spent executing before cancellation, in milliseconds.
+        elapsed_ms: u64,
+    },
 }
 
 impl<'a> ToolResult<'a> {
@@ -345,6 +366,17 @@ impl<'a> ToolResult<'a> {
         }
     }
 
+    /// Creates a timeout result.
+    #[must_use]
+    #[inline]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn timeout(timeout: Duration, elapsed: Duration) -> Self {
+        Self::Timeout {
+            timeout_ms: timeout.as_millis() as u64,
+            elapsed_ms: elapsed.as_millis() as u64,
+        }
+    }
+
     /// Returns true if the result indicates success.
     #[must_use]
     #[inline]
@@ -359,6 +391,13 @@ impl<'a> ToolResult<'a> {
         matches!(self, Self::Error { .. })
     }
 
+    /// Returns true if the tool failed, either with an error or by timing out.
+    #[must_use]
+    #[inline]
+    pub fn is_failure(&self) -> bool {
+        matches!(self, Self::Error { .. } | Self::Timeout { .. })
+    }
+
     /// Returns true if the result indicates pending execution.
     #[must_use]
     #[inline]
@@ -366,6 +405,13 @@ impl<'a> ToolResult<'a> {
         matches!(self, Self::Pending { .. })
     }
 
+    /// Returns true if the tool was cancelled after exceeding its timeout.
+    #[must_use]
+    #[inline]
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, Self::Timeout { .. })
+    }
+
     /// Extracts the success value if available.
     ///
     /// # Errors
@@ -383,6 +429,9 @@ impl<'a> ToolResult<'a> {
             Self::Pending { .. } => Err(IcarusError::InternalError(
                 "Tool execution still pending".to_string(),
             )),
+            Self::Timeout { timeout_ms, .. } => Err(IcarusError::InternalError(format!(
+                "Tool execution timed out after {timeout_ms}ms"
+            ))),
         }
     }
 }
@@ -495,6 +544,20 @@ mod tests {
         assert!(!pending.is_success());
         assert!(!pending.is_error());
         assert!(pending.is_pending());
+
+        let timeout = ToolResult::timeout(Duration::from_secs(5), Duration::from_millis(5002));
+        assert!(timeout.is_timeout());
+        assert!(!timeout.is_success());
+        assert!(!timeout.is_error());
+        assert!(timeout.is_failure());
+        assert!(matches!(
+            timeout,
+            ToolResult::Timeout {
+                timeout_ms: 5000,
+                elapsed_ms: 5002
+            }
+        ));
+        assert!(timeout.into_success().is_err());
     }
 
     #[test]
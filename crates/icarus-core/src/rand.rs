@@ -0,0 +1,322 @@
+//! Random numbers seeded from the Internet Computer's randomness.
+//!
+//! A canister has no operating-system entropy, so crates that reach for
+//! `getrandom` either fail to link or return whatever a custom backend
+//! provides. This module keeps one `ChaCha20` generator per canister, seeded
+//! with 32 bytes from the management canister's `raw_rand`, and tools draw
+//! from it synchronously with [`gen_uuid`], [`gen_range`] and [`shuffle`].
+//!
+//! `raw_rand` is an async inter-canister call, so seeding cannot happen in
+//! `init`. [`start_reseeding`], called from `init` and `post_upgrade`, seeds
+//! the generator on a zero-delay timer and again every interval, which limits
+//! what a leaked generator state reveals. Draws before the first seed fail
+//! with `IcarusError::ConfigurationError`.
+//!
+//! Off-chain the generator seeds itself from the clock on first use, unless a
+//! seed was fixed with [`set_seed`]; `icarus_test::mock::mock_rand` fixes one
+//! so tests are reproducible.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::rand;
+//!
+//! rand::set_seed(Some([7; 32]));
+//!
+//! let id = rand::gen_uuid().unwrap();
+//! assert_eq!(id.len(), 36);
+//! assert_eq!(&id[14..15], "4");
+//!
+//! let roll = rand::gen_range(1..=6).unwrap();
+//! assert!((1..=6).contains(&roll));
+//!
+//! let mut deck: Vec<u8> = (0..52).collect();
+//! rand::shuffle(&mut deck).unwrap();
+//! assert_eq!(deck.len(), 52);
+//! ```
+
+use std::cell::RefCell;
+use std::fmt::Write;
+#[cfg(feature = "ic-canister")]
+use std::time::Duration;
+
+use ::rand::distr::uniform::{SampleRange, SampleUniform};
+use ::rand::seq::SliceRandom;
+use ::rand::SeedableRng;
+
+use crate::{IcarusError, Result};
+
+pub use ::rand::{Rng, RngCore};
+pub use rand_chacha::ChaCha20Rng;
+
+/// How often [`start_reseeding`] is usually asked to reseed.
+pub const DEFAULT_RESEED_INTERVAL_SECS: u64 = 60 * 60;
+
+thread_local! {
+    static RNG: RefCell<Option<ChaCha20Rng>> = const { RefCell::new(None) };
+}
+
+/// Replaces the generator's state with one derived from `seed`.
+fn install(seed: [u8; 32]) {
+    RNG.with(|rng| *rng.borrow_mut() = Some(ChaCha20Rng::from_seed(seed)));
+}
+
+/// Returns whether the generator has been seeded.
+#[must_use]
+pub fn is_seeded() -> bool {
+    RNG.with(|rng| rng.borrow().is_some())
+}
+
+/// Fixes the generator's seed for the current thread, or forgets it with
+/// `None` so the next draw seeds from the clock again.
+#[cfg(not(feature = "ic-canister"))]
+pub fn set_seed(seed: Option<[u8; 32]>) {
+    match seed {
+        Some(seed) => install(seed),
+        None => RNG.with(|rng| *rng.borrow_mut() = None),
+    }
+}
+
+/// Returns 32 bytes of fresh entropy for secrets such as API keys and
+/// invite codes: `raw_rand` on the IC, the operating system's source
+/// elsewhere. Unlike the generator off-chain, it never falls back to the
+/// clock.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ExternalServiceError` if the management canister
+/// call fails.
+#[cfg(any(
+    feature = "ic-canister",
+    all(target_arch = "wasm32", target_os = "unknown")
+))]
+pub async fn entropy() -> Result<[u8; 32]> {
+    let bytes = ic_cdk::management_canister::raw_rand().await.map_err(|e| {
+        IcarusError::ExternalServiceError {
+            service: "raw_rand".to_string(),
+            message: e.to_string(),
+        }
+    })?;
+    bytes
+        .get(..32)
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| IcarusError::ExternalServiceError {
+            service: "raw_rand".to_string(),
+            message: format!("expected 32 bytes, got {}", bytes.len()),
+        })
+}
+
+/// Returns 32 bytes of fresh entropy for secrets such as API keys and
+/// invite codes: `raw_rand` on the IC, the operating system's source
+/// elsewhere. Unlike the generator off-chain, it never falls back to the
+/// clock.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ExternalServiceError` if the operating system has
+/// no entropy to give.
+#[cfg(not(any(
+    feature = "ic-canister",
+    all(target_arch = "wasm32", target_os = "unknown")
+)))]
+#[allow(clippy::unused_async)]
+pub async fn entropy() -> Result<[u8; 32]> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| IcarusError::ExternalServiceError {
+        service: "getrandom".to_string(),
+        message: e.to_string(),
+    })?;
+    Ok(bytes)
+}
+
+/// Seeds the generator with fresh randomness from `raw_rand`.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ExternalServiceError` if the management canister
+/// call fails.
+#[cfg(feature = "ic-canister")]
+pub async fn reseed() -> Result<()> {
+    install(entropy().await?);
+    Ok(())
+}
+
+/// Seeds the generator from the clock, since off-chain draws only need to
+/// differ between runs.
+///
+/// # Errors
+///
+/// Never fails off-chain; the signature matches the canister version.
+#[cfg(not(feature = "ic-canister"))]
+#[allow(clippy::unused_async)]
+pub async fn reseed() -> Result<()> {
+    install(clock_seed());
+    Ok(())
+}
+
+#[cfg(not(feature = "ic-canister"))]
+fn clock_seed() -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    hasher.update(b"icarus_rand");
+    hasher.update(crate::time::now_nanos().to_le_bytes());
+    hasher.finalize().into()
+}
+
+/// Starts a timer that seeds the generator now and again every `interval`.
+///
+/// Timers do not survive upgrades, so call this from both `init` and
+/// `post_upgrade`. A failed reseed keeps the previous state and is logged.
+#[cfg(feature = "ic-canister")]
+pub fn start_reseeding(interval: Duration) -> ic_cdk_timers::TimerId {
+    fn spawn_reseed() {
+        ic_cdk::futures::spawn(async {
+            if let Err(e) = reseed().await {
+                crate::logging::warn(format_args!("reseeding randomness failed: {e}"));
+            }
+        });
+    }
+
+    ic_cdk_timers::set_timer(Duration::ZERO, spawn_reseed);
+    ic_cdk_timers::set_timer_interval(interval, spawn_reseed)
+}
+
+/// Runs `f` with the generator.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` inside a canister that has not
+/// been seeded yet.
+pub fn with_rng<T>(f: impl FnOnce(&mut ChaCha20Rng) -> T) -> Result<T> {
+    RNG.with(|cell| {
+        let mut rng = cell.borrow_mut();
+        #[cfg(not(feature = "ic-canister"))]
+        let rng = rng.get_or_insert_with(|| ChaCha20Rng::from_seed(clock_seed()));
+        #[cfg(feature = "ic-canister")]
+        let rng = rng.as_mut().ok_or_else(|| {
+            IcarusError::ConfigurationError(
+                "randomness is not seeded yet; call rand::start_reseeding from init and post_upgrade"
+                    .to_string(),
+            )
+        })?;
+        Ok(f(rng))
+    })
+}
+
+/// Returns a random version 4 UUID in its hyphenated form.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if the generator is not seeded.
+pub fn gen_uuid() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    with_rng(|rng| rng.fill_bytes(&mut bytes))?;
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex = bytes
+        .iter()
+        .fold(String::with_capacity(32), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        });
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+/// Returns a value drawn uniformly from `range`, such as `1..=6`.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if the generator is not seeded
+/// or `range` is empty.
+pub fn gen_range<T, R>(range: R) -> Result<T>
+where
+    T: SampleUniform,
+    R: SampleRange<T>,
+{
+    if range.is_empty() {
+        return Err(IcarusError::ConfigurationError(
+            "cannot draw from an empty range".to_string(),
+        ));
+    }
+    with_rng(|rng| rng.random_range(range))
+}
+
+/// Shuffles `items` in place.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if the generator is not seeded.
+pub fn shuffle<T>(items: &mut [T]) -> Result<()> {
+    with_rng(|rng| items.shuffle(rng))
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_draws() {
+        set_seed(Some([1; 32]));
+        let first = (gen_uuid().unwrap(), gen_range(0..1000u32).unwrap());
+        set_seed(Some([1; 32]));
+        let second = (gen_uuid().unwrap(), gen_range(0..1000u32).unwrap());
+        assert_eq!(first, second);
+
+        set_seed(Some([2; 32]));
+        assert_ne!(gen_uuid().unwrap(), first.0);
+        set_seed(None);
+    }
+
+    #[test]
+    fn test_uuid_is_version_4() {
+        set_seed(Some([3; 32]));
+        for _ in 0..20 {
+            let id = gen_uuid().unwrap();
+            let parts: Vec<&str> = id.split('-').collect();
+            assert_eq!(
+                parts.iter().map(|p| p.len()).collect::<Vec<_>>(),
+                [8, 4, 4, 4, 12]
+            );
+            assert!(parts[2].starts_with('4'));
+            assert!(matches!(&parts[3][..1], "8" | "9" | "a" | "b"));
+        }
+        set_seed(None);
+    }
+
+    #[test]
+    fn test_range_and_shuffle() {
+        set_seed(Some([4; 32]));
+        for _ in 0..100 {
+            assert!((5..10).contains(&gen_range(5..10).unwrap()));
+        }
+        assert!(matches!(
+            gen_range(3..3),
+            Err(IcarusError::ConfigurationError(_))
+        ));
+
+        let mut items: Vec<u32> = (0..50).collect();
+        shuffle(&mut items).unwrap();
+        assert_ne!(items, (0..50).collect::<Vec<_>>());
+        items.sort_unstable();
+        assert_eq!(items, (0..50).collect::<Vec<_>>());
+        set_seed(None);
+    }
+
+    #[test]
+    fn test_seeds_itself_off_chain() {
+        set_seed(None);
+        assert!(!is_seeded());
+        gen_uuid().unwrap();
+        assert!(is_seeded());
+        set_seed(None);
+    }
+}
@@ -0,0 +1,443 @@
+//! Read replicas that follow a primary canister's changes.
+//!
+//! A canister that hits query limits can spread its reads over replicas:
+//! canisters running the same code that receive every change the primary
+//! makes. The primary calls [`publish`] next to each write, and the change is
+//! queued through [`events`](crate::events) for every replica added with
+//! [`add_replica`], then delivered to its `replicate_change` method with
+//! retries by the timer of `events::start_delivery`.
+//! A replica knows its primary from [`set_primary`], accepts changes only
+//! from it, and applies each one with the function registered for the
+//! collection it belongs to. Those functions live on the heap, so register
+//! them in both `init` and `post_upgrade`.
+//!
+//! Deliveries are at least once and can overtake each other when one is
+//! retried, so a replica remembers the event id of the last change applied to
+//! each key and skips older ones. Replicas therefore converge on the
+//! primary's last write to every key. A change dropped after
+//! [`events::MAX_ATTEMPTS`](crate::events::MAX_ATTEMPTS) failed deliveries is
+//! lost for that replica; compare `events::dropped_deliveries` on the primary.
+//!
+//! A replica only runs tools listed as read-only; `mcp_call_tool` refuses the
+//! others with [`READ_ONLY_REPLICA_CODE`]. `mcp!{ replication = true }` adds
+//! the endpoints to manage replicas and receive changes, and the bridge sends
+//! read-only tool calls to the replicas in `BridgeConfig::read_replicas` in
+//! turn, falling back to the primary.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use candid::Principal;
+//! use icarus_core::replication::{self, Change};
+//! use icarus_core::{events, memory};
+//! use ic_stable_structures::StableBTreeMap;
+//!
+//! thread_local! {
+//!     static NOTES: std::cell::RefCell<StableBTreeMap<u64, String, memory::StableMemory>> =
+//!         std::cell::RefCell::new(StableBTreeMap::init(memory::get_memory(memory::FIRST_USER_MEMORY_ID)));
+//! }
+//!
+//! fn apply_note(change: &Change) {
+//!     NOTES.with(|notes| change.apply_to(&mut notes.borrow_mut()));
+//! }
+//!
+//! // On the primary, next to every write
+//! let replica = Principal::from_slice(&[3]);
+//! replication::add_replica(replica).unwrap();
+//! replication::publish("notes", Change::put(&7_u64, &"buy milk".to_string()));
+//! let delivery = events::take_due(1).remove(0);
+//!
+//! // On the replica, when the delivery arrives
+//! let primary = Principal::from_slice(&[1]);
+//! replication::set_primary(Some(primary));
+//! replication::register_collection("notes", apply_note);
+//! assert!(replication::apply(primary, &delivery.event).unwrap());
+//! assert_eq!(NOTES.with(|notes| notes.borrow().get(&7)), Some("buy milk".to_string()));
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{Memory, StableBTreeMap, Storable};
+use serde::Serialize;
+
+use crate::events::{self, Event};
+use crate::memory::{
+    self, StableMemory, REPLICATION_PRIMARY_MEMORY_ID, REPLICATION_VERSIONS_MEMORY_ID,
+};
+use crate::{IcarusError, Result, Tool};
+
+/// Topic the primary publishes changes on.
+pub const REPLICATION_TOPIC: &str = "icarus.replication";
+
+/// Method of a replica that receives changes.
+pub const REPLICATE_METHOD: &str = "replicate_change";
+
+/// JSON-RPC error code of a tool call a replica refuses to run.
+pub const READ_ONLY_REPLICA_CODE: i32 = -32005;
+
+/// Applies a replicated change to the replica's copy of a collection.
+pub type ApplyFn = fn(&Change);
+
+/// A write to one key of a collection, with keys and values as stored.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub enum Change {
+    /// The key was set to the value.
+    Put {
+        /// Encoded key.
+        key: Vec<u8>,
+        /// Encoded value.
+        value: Vec<u8>,
+    },
+    /// The key was removed.
+    Delete {
+        /// Encoded key.
+        key: Vec<u8>,
+    },
+}
+
+impl Change {
+    /// A change setting `key` to `value`.
+    #[must_use]
+    pub fn put<K: Storable, V: Storable>(key: &K, value: &V) -> Self {
+        Self::Put {
+            key: key.to_bytes().into_owned(),
+            value: value.to_bytes().into_owned(),
+        }
+    }
+
+    /// A change removing `key`.
+    #[must_use]
+    pub fn delete<K: Storable>(key: &K) -> Self {
+        Self::Delete {
+            key: key.to_bytes().into_owned(),
+        }
+    }
+
+    /// Returns the encoded key.
+    #[must_use]
+    pub fn key(&self) -> &[u8] {
+        match self {
+            Self::Put { key, .. } | Self::Delete { key } => key,
+        }
+    }
+
+    /// Makes the same write to `map`.
+    pub fn apply_to<K, V, M>(&self, map: &mut StableBTreeMap<K, V, M>)
+    where
+        K: Storable + Ord + Clone,
+        V: Storable,
+        M: Memory,
+    {
+        let key = K::from_bytes(Cow::Borrowed(self.key()));
+        match self {
+            Self::Put { value, .. } => {
+                map.insert(key, V::from_bytes(Cow::Borrowed(value)));
+            }
+            Self::Delete { .. } => {
+                map.remove(&key);
+            }
+        }
+    }
+}
+
+/// A change as published by the primary.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct ChangeEvent {
+    /// Collection the change belongs to.
+    pub collection: String,
+    /// The write.
+    pub change: Change,
+}
+
+/// Replication state returned by [`replication_status`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct ReplicationStatus {
+    /// Canisters this canister sends its changes to.
+    pub replicas: Vec<Principal>,
+    /// Canister this canister follows, if it is a replica.
+    pub primary: Option<Principal>,
+    /// Changes applied since the canister started.
+    pub applied: u64,
+    /// Changes skipped as older than the key's last change.
+    pub skipped: u64,
+    /// Event id of the newest change applied.
+    pub last_event_id: Option<u64>,
+    /// Time of the last applied change in nanoseconds since the epoch.
+    pub last_applied_at: Option<u64>,
+}
+
+#[derive(Default)]
+struct State {
+    collections: BTreeMap<&'static str, ApplyFn>,
+    applied: u64,
+    skipped: u64,
+    last_event_id: Option<u64>,
+    last_applied_at: Option<u64>,
+}
+
+thread_local! {
+    static STATE: RefCell<State> = RefCell::new(State::default());
+
+    static PRIMARY: RefCell<StableBTreeMap<u8, Principal, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(REPLICATION_PRIMARY_MEMORY_ID))
+    );
+
+    /// Event id of the last change applied to each collection and key
+    static VERSIONS: RefCell<StableBTreeMap<Vec<u8>, u64, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(REPLICATION_VERSIONS_MEMORY_ID))
+    );
+}
+
+/// Sends this canister's changes to `replica`.
+///
+/// # Errors
+///
+/// Never fails in practice; returns the error of `events::subscribe`.
+pub fn add_replica(replica: Principal) -> Result<()> {
+    // Reserved topic: only the owner-gated replica endpoints subscribe to it
+    events::subscribe_reserved(REPLICATION_TOPIC, replica, REPLICATE_METHOD)
+}
+
+/// Stops sending changes to `replica`, returning whether it was a replica.
+pub fn remove_replica(replica: Principal) -> bool {
+    events::unsubscribe_reserved(REPLICATION_TOPIC, replica, REPLICATE_METHOD)
+}
+
+/// Returns the canisters this canister sends its changes to.
+#[must_use]
+pub fn list_replicas() -> Vec<Principal> {
+    events::list_subscriptions(Some(REPLICATION_TOPIC))
+        .into_iter()
+        .map(|subscription| subscription.subscriber)
+        .collect()
+}
+
+/// Queues `change` to `collection` for every replica and returns its event id.
+pub fn publish(collection: &str, change: Change) -> u64 {
+    let event = ChangeEvent {
+        collection: collection.to_string(),
+        change,
+    };
+    events::publish(REPLICATION_TOPIC, Encode!(&event).unwrap_or_default())
+}
+
+/// Makes this canister a replica of `primary`, or a standalone canister again
+/// with `None`.
+pub fn set_primary(primary: Option<Principal>) {
+    PRIMARY.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        match primary {
+            Some(primary) => cell.insert(0, primary),
+            None => cell.remove(&0),
+        };
+    });
+}
+
+/// Returns the canister this canister follows, if it is a replica.
+#[must_use]
+pub fn primary() -> Option<Principal> {
+    PRIMARY.with(|cell| cell.borrow().get(&0))
+}
+
+/// Returns true if this canister is a replica.
+#[must_use]
+pub fn is_replica() -> bool {
+    primary().is_some()
+}
+
+/// Returns true if a replica may run `tool`, which it may if the tool is
+/// listed as read-only.
+#[must_use]
+pub fn serves(tool: &Tool) -> bool {
+    tool.annotations
+        .as_ref()
+        .and_then(|annotations| annotations.read_only_hint)
+        == Some(true)
+}
+
+/// Applies changes to `collection` with `apply`, replacing any earlier function.
+pub fn register_collection(collection: &'static str, apply: ApplyFn) {
+    STATE.with(|state| {
+        state.borrow_mut().collections.insert(collection, apply);
+    });
+}
+
+/// Applies a change event sent by `caller`. Returns false if the key already
+/// has a newer change, which is then kept.
+///
+/// # Errors
+///
+/// Returns `IcarusError::AccessDenied` if `caller` is not this replica's
+/// primary, and an error if the event is not a change or its collection has
+/// no registered function. The primary retries failed deliveries.
+pub fn apply(caller: Principal, event: &Event) -> Result<bool> {
+    if primary() != Some(caller) {
+        return Err(IcarusError::AccessDenied(format!(
+            "{caller} is not the primary of this replica"
+        )));
+    }
+    if event.topic != REPLICATION_TOPIC {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Event topic '{}' is not {REPLICATION_TOPIC}",
+            event.topic
+        )));
+    }
+    let ChangeEvent { collection, change } = Decode!(&event.payload, ChangeEvent)
+        .map_err(|e| IcarusError::CandidError(e.to_string()))?;
+    let apply = STATE
+        .with(|state| state.borrow().collections.get(collection.as_str()).copied())
+        .ok_or_else(|| {
+            IcarusError::ConfigurationError(format!(
+                "No replicated collection named '{collection}'"
+            ))
+        })?;
+
+    let version_key = [collection.as_bytes(), &[0], change.key()].concat();
+    let newer = VERSIONS.with(|versions| {
+        versions
+            .borrow()
+            .get(&version_key)
+            .is_some_and(|version| version >= event.id)
+    });
+    if newer {
+        STATE.with(|state| state.borrow_mut().skipped += 1);
+        return Ok(false);
+    }
+
+    apply(&change);
+    VERSIONS.with(|versions| versions.borrow_mut().insert(version_key, event.id));
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.applied += 1;
+        state.last_event_id = Some(state.last_event_id.map_or(event.id, |id| id.max(event.id)));
+        state.last_applied_at = Some(crate::time::now_nanos());
+    });
+    Ok(true)
+}
+
+/// Returns the replicas, the primary and how many changes were applied.
+#[must_use]
+pub fn replication_status() -> ReplicationStatus {
+    STATE.with(|state| {
+        let state = state.borrow();
+        ReplicationStatus {
+            replicas: list_replicas(),
+            primary: primary(),
+            applied: state.applied,
+            skipped: state.skipped,
+            last_event_id: state.last_event_id,
+            last_applied_at: state.last_applied_at,
+        }
+    })
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+
+    thread_local! {
+        static APPLIED: RefCell<Vec<Change>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn record(change: &Change) {
+        APPLIED.with(|applied| applied.borrow_mut().push(change.clone()));
+    }
+
+    fn change_event(id: u64, collection: &str, change: Change) -> Event {
+        Event {
+            id,
+            topic: REPLICATION_TOPIC.to_string(),
+            payload: Encode!(&ChangeEvent {
+                collection: collection.to_string(),
+                change,
+            })
+            .unwrap(),
+            published_at: id,
+        }
+    }
+
+    #[test]
+    fn test_replicas_subscribe_to_changes() {
+        let replica = Principal::from_slice(&[9]);
+        add_replica(replica).unwrap();
+        assert_eq!(list_replicas(), vec![replica]);
+
+        let id = publish("tasks", Change::delete(&"t1".to_string()));
+        let delivery = events::take_due(10)
+            .into_iter()
+            .find(|delivery| delivery.event.id == id)
+            .unwrap();
+        assert_eq!(delivery.subscriber, replica);
+        assert_eq!(delivery.callback_method, REPLICATE_METHOD);
+
+        assert!(remove_replica(replica));
+        assert!(list_replicas().is_empty());
+    }
+
+    #[test]
+    fn test_replication_topic_is_reserved() {
+        let outsider = Principal::from_slice(&[10]);
+        assert!(matches!(
+            events::subscribe(REPLICATION_TOPIC, outsider, REPLICATE_METHOD),
+            Err(IcarusError::AccessDenied(_))
+        ));
+        assert!(list_replicas().is_empty());
+
+        let replica = Principal::from_slice(&[11]);
+        add_replica(replica).unwrap();
+        assert!(!events::unsubscribe(
+            REPLICATION_TOPIC,
+            replica,
+            REPLICATE_METHOD
+        ));
+        assert_eq!(list_replicas(), vec![replica]);
+    }
+
+    #[test]
+    fn test_older_changes_do_not_overwrite_newer_ones() {
+        let primary_id = Principal::from_slice(&[1]);
+        register_collection("tags", record);
+
+        let stranger = change_event(5, "tags", Change::delete(&1_u64));
+        assert!(matches!(
+            apply(primary_id, &stranger),
+            Err(IcarusError::AccessDenied(_))
+        ));
+
+        set_primary(Some(primary_id));
+        assert!(is_replica());
+        let newer = change_event(20, "tags", Change::put(&1_u64, &"new".to_string()));
+        let older = change_event(10, "tags", Change::put(&1_u64, &"old".to_string()));
+        assert!(apply(primary_id, &newer).unwrap());
+        assert!(!apply(primary_id, &older).unwrap());
+        assert!(apply(
+            primary_id,
+            &change_event(15, "tags", Change::delete(&2_u64))
+        )
+        .unwrap());
+        assert!(apply(
+            primary_id,
+            &change_event(30, "unknown", Change::delete(&1_u64))
+        )
+        .is_err());
+
+        APPLIED.with(|applied| {
+            assert_eq!(
+                *applied.borrow(),
+                vec![
+                    Change::put(&1_u64, &"new".to_string()),
+                    Change::delete(&2_u64)
+                ]
+            );
+        });
+        let status = replication_status();
+        assert_eq!((status.applied, status.skipped), (2, 1));
+        assert_eq!(status.last_event_id, Some(20));
+
+        set_primary(None);
+        assert!(!is_replica());
+    }
+}
@@ -0,0 +1,300 @@
+//! Scheduled data retention policies.
+//!
+//! A [`RetentionPolicy`] names a piece of data and a rule for how much of it
+//! to keep: entries younger than a maximum age, or at most a number of
+//! entries. The policy's purge function does the deleting, since only the
+//! application knows how its data is stored; this module decides when to call
+//! it and keeps track of what was purged.
+//!
+//! [`start_enforcement`] runs all registered policies on a timer. The last
+//! run and purge counts are reported by [`retention_status`], which `mcp!{}`
+//! exposes as the `get_retention_status` query. Status is kept on the heap and
+//! starts over after an upgrade.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use icarus_core::retention::{self, RetentionPolicy};
+//!
+//! /// Deletes activity entries created before `cutoff` and returns how many.
+//! fn purge_activity(_cutoff_nanos: u64) -> u64 {
+//!     0
+//! }
+//!
+//! /// Trims each task's executions to the newest `max` and returns how many were removed.
+//! fn trim_executions(_max: u64) -> u64 {
+//!     0
+//! }
+//!
+//! retention::register_policy(RetentionPolicy::max_age(
+//!     "activity_log",
+//!     Duration::from_secs(90 * 24 * 60 * 60),
+//!     purge_activity,
+//! ));
+//! retention::register_policy(RetentionPolicy::max_count("executions", 1000, trim_executions));
+//!
+//! let status = retention::enforce_now();
+//! assert_eq!(status.policies.len(), 2);
+//! ```
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+/// Deletes entries created before the given time (nanoseconds since the
+/// epoch) and returns how many were deleted.
+pub type PurgeOlderThanFn = fn(u64) -> u64;
+
+/// Deletes all but the newest `n` entries and returns how many were deleted.
+pub type KeepNewestFn = fn(u64) -> u64;
+
+/// How much data a policy keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionRule {
+    /// Keep entries younger than this.
+    MaxAge(Duration),
+    /// Keep at most this many entries.
+    MaxCount(u64),
+}
+
+/// A named retention rule and the function that enforces it.
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    name: &'static str,
+    rule: RetentionRule,
+    /// Receives the cutoff time or the count to keep, depending on `rule`
+    purge: fn(u64) -> u64,
+}
+
+impl RetentionPolicy {
+    /// Keeps entries younger than `max_age`.
+    #[must_use]
+    pub const fn max_age(name: &'static str, max_age: Duration, purge: PurgeOlderThanFn) -> Self {
+        Self {
+            name,
+            rule: RetentionRule::MaxAge(max_age),
+            purge,
+        }
+    }
+
+    /// Keeps at most `max_count` entries.
+    #[must_use]
+    pub const fn max_count(name: &'static str, max_count: u64, purge: KeepNewestFn) -> Self {
+        Self {
+            name,
+            rule: RetentionRule::MaxCount(max_count),
+            purge,
+        }
+    }
+
+    /// Returns the policy name.
+    #[must_use]
+    #[inline]
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the policy rule.
+    #[must_use]
+    #[inline]
+    pub const fn rule(&self) -> RetentionRule {
+        self.rule
+    }
+
+    fn enforce(&self, now_nanos: u64) -> u64 {
+        let argument = match self.rule {
+            RetentionRule::MaxAge(max_age) => {
+                let max_age = u64::try_from(max_age.as_nanos()).unwrap_or(u64::MAX);
+                now_nanos.saturating_sub(max_age)
+            }
+            RetentionRule::MaxCount(max_count) => max_count,
+        };
+        (self.purge)(argument)
+    }
+}
+
+/// Last results of one policy.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct PolicyStatus {
+    /// Policy name.
+    pub name: String,
+    /// Human-readable rule, e.g. `max_age 7776000s`.
+    pub rule: String,
+    /// Entries purged by the last run.
+    pub last_purged: u64,
+    /// Entries purged since the canister started.
+    pub total_purged: u64,
+}
+
+/// Retention enforcement status returned by [`retention_status`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct RetentionStatus {
+    /// Time of the last run in nanoseconds since the epoch.
+    pub last_run: Option<u64>,
+    /// Number of runs since the canister started.
+    pub runs: u64,
+    /// One entry per registered policy, in registration order.
+    pub policies: Vec<PolicyStatus>,
+}
+
+struct Entry {
+    policy: RetentionPolicy,
+    last_purged: u64,
+    total_purged: u64,
+}
+
+#[derive(Default)]
+struct State {
+    entries: Vec<Entry>,
+    last_run: Option<u64>,
+    runs: u64,
+}
+
+thread_local! {
+    static STATE: RefCell<State> = RefCell::new(State::default());
+}
+
+/// Registers a policy, replacing any existing policy with the same name.
+pub fn register_policy(policy: RetentionPolicy) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if let Some(entry) = state
+            .entries
+            .iter_mut()
+            .find(|entry| entry.policy.name == policy.name)
+        {
+            entry.policy = policy;
+        } else {
+            state.entries.push(Entry {
+                policy,
+                last_purged: 0,
+                total_purged: 0,
+            });
+        }
+    });
+}
+
+/// Removes a policy, returning whether it was registered.
+pub fn unregister_policy(name: &str) -> bool {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let before = state.entries.len();
+        state.entries.retain(|entry| entry.policy.name != name);
+        state.entries.len() != before
+    })
+}
+
+/// Enforces every registered policy once and returns the updated status.
+pub fn enforce_now() -> RetentionStatus {
+    let now = crate::time::now_nanos();
+    // Purge functions run without the state borrowed, so they may register policies
+    let policies: Vec<RetentionPolicy> =
+        STATE.with(|state| state.borrow().entries.iter().map(|e| e.policy).collect());
+    let purged: Vec<(&'static str, u64)> = policies
+        .iter()
+        .map(|policy| (policy.name, policy.enforce(now)))
+        .collect();
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        for (name, count) in purged {
+            if let Some(entry) = state.entries.iter_mut().find(|e| e.policy.name == name) {
+                entry.last_purged = count;
+                entry.total_purged += count;
+            }
+        }
+        state.last_run = Some(now);
+        state.runs += 1;
+    });
+    retention_status()
+}
+
+/// Returns when policies last ran and how much each purged.
+#[must_use]
+pub fn retention_status() -> RetentionStatus {
+    STATE.with(|state| {
+        let state = state.borrow();
+        RetentionStatus {
+            last_run: state.last_run,
+            runs: state.runs,
+            policies: state
+                .entries
+                .iter()
+                .map(|entry| PolicyStatus {
+                    name: entry.policy.name.to_string(),
+                    rule: match entry.policy.rule {
+                        RetentionRule::MaxAge(age) => format!("max_age {}s", age.as_secs()),
+                        RetentionRule::MaxCount(count) => format!("max_count {count}"),
+                    },
+                    last_purged: entry.last_purged,
+                    total_purged: entry.total_purged,
+                })
+                .collect(),
+        }
+    })
+}
+
+/// Starts a timer that enforces all registered policies every `interval`.
+///
+/// Timers do not survive upgrades, so call this from both `init` and
+/// `post_upgrade`.
+#[cfg(feature = "ic-canister")]
+pub fn start_enforcement(interval: Duration) -> ic_cdk_timers::TimerId {
+    ic_cdk_timers::set_timer_interval(interval, || {
+        enforce_now();
+    })
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    thread_local! {
+        static CUTOFF: Cell<u64> = const { Cell::new(0) };
+    }
+
+    fn purge_three(cutoff: u64) -> u64 {
+        CUTOFF.with(|c| c.set(cutoff));
+        3
+    }
+
+    fn keep_newest(max: u64) -> u64 {
+        1000 - max
+    }
+
+    #[test]
+    fn test_enforce_tracks_purge_counts() {
+        crate::time::set_time_override(Some(10_000_000_000));
+        register_policy(RetentionPolicy::max_age(
+            "activity",
+            Duration::from_secs(4),
+            purge_three,
+        ));
+        register_policy(RetentionPolicy::max_count("executions", 990, keep_newest));
+
+        enforce_now();
+        let status = enforce_now();
+        crate::time::set_time_override(None);
+
+        assert_eq!(CUTOFF.with(Cell::get), 6_000_000_000);
+        assert_eq!(status.runs, 2);
+        assert_eq!(status.last_run, Some(10_000_000_000));
+        assert_eq!(status.policies[0].rule, "max_age 4s");
+        assert_eq!(
+            (
+                status.policies[0].last_purged,
+                status.policies[0].total_purged
+            ),
+            (3, 6)
+        );
+        assert_eq!(status.policies[1].last_purged, 10);
+
+        assert!(unregister_policy("activity"));
+        assert_eq!(retention_status().policies.len(), 1);
+    }
+}
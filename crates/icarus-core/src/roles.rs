@@ -0,0 +1,251 @@
+//! Custom roles ordered in a hierarchy.
+//!
+//! The built-in whitelist knows two roles, admin and user. A canister that
+//! needs more declares its own at the crate root, highest first:
+//!
+//! ```rust,ignore
+//! roles!(Owner > Admin > Editor > Viewer);
+//!
+//! #[tool(auth = "editor")]
+//! fn publish(id: u64) -> Result<(), String> { .. }
+//! ```
+//!
+//! A tool restricted to a declared role accepts callers holding that role or
+//! any role above it. Role names are the snake-cased identifiers, and an
+//! `auth` naming a role that was not declared fails to compile.
+//!
+//! Principals get a declared role with [`assign_role`]. The names `admin` and
+//! `user` are the whitelist roles: assigning them adds the principal to the
+//! whitelist, and whitelisted principals hold them wherever they appear in
+//! the hierarchy. The built-in `auth = "admin"` and `auth = "user"` levels
+//! keep checking the whitelist only.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use candid::Principal;
+//! use icarus_core::roles;
+//!
+//! const HIERARCHY: &[&str] = &["owner", "admin", "editor", "viewer"];
+//!
+//! let owner = Principal::from_slice(&[1]);
+//! let editor = Principal::from_slice(&[2]);
+//! roles::assign_role(editor, "editor", HIERARCHY, owner).unwrap();
+//!
+//! assert!(roles::has_role_or_higher(&editor, "viewer", HIERARCHY));
+//! assert!(roles::has_role_or_higher(&editor, "editor", HIERARCHY));
+//! assert!(!roles::has_role_or_higher(&editor, "admin", HIERARCHY));
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+
+use crate::auth;
+use crate::memory::{self, StableMemory, AUTH_ROLES_MEMORY_ID};
+use crate::{IcarusError, Result};
+
+/// Whitelist role of [`auth::add_admin`].
+pub const ADMIN: &str = "admin";
+
+/// Whitelist role of [`auth::add_user`].
+pub const USER: &str = "user";
+
+/// A declared role held by a principal.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct RoleAssignment {
+    /// Principal holding the role.
+    pub principal: Principal,
+    /// Name of the role.
+    pub role: String,
+    /// Principal that assigned it.
+    pub assigned_by: Principal,
+    /// Assignment time in nanoseconds since the epoch.
+    pub assigned_at: u64,
+}
+
+impl Storable for RoleAssignment {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap_or_default())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap_or_else(|e| unreachable!("corrupt role assignment: {e}"))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).unwrap_or_default()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+thread_local! {
+    /// Declared roles other than the whitelist ones, by principal.
+    static ASSIGNMENTS: RefCell<StableBTreeMap<Principal, RoleAssignment, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(AUTH_ROLES_MEMORY_ID))
+    );
+}
+
+/// Returns the position of `role` in `hierarchy`, 0 being the highest.
+#[must_use]
+pub fn rank(hierarchy: &[&str], role: &str) -> Option<usize> {
+    hierarchy.iter().position(|declared| *declared == role)
+}
+
+/// Gives `principal` the declared `role`, replacing any declared role it
+/// held. `admin` and `user` add it to the whitelist instead.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if `role` is not in
+/// `hierarchy`.
+pub fn assign_role(
+    principal: Principal,
+    role: &str,
+    hierarchy: &[&str],
+    assigned_by: Principal,
+) -> Result<()> {
+    if rank(hierarchy, role).is_none() {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Unknown role: {role}. Declared roles are {}",
+            hierarchy.join(", ")
+        )));
+    }
+
+    match role {
+        ADMIN => {
+            auth::remove_user(&principal);
+            auth::add_admin(principal);
+        }
+        USER => auth::add_user(principal),
+        _ => {
+            let assignment = RoleAssignment {
+                principal,
+                role: role.to_string(),
+                assigned_by,
+                assigned_at: crate::time::now_nanos(),
+            };
+            ASSIGNMENTS.with(|assignments| assignments.borrow_mut().insert(principal, assignment));
+        }
+    }
+    Ok(())
+}
+
+/// Takes away the declared role of `principal`, leaving its whitelist
+/// entry alone. Returns the removed assignment.
+pub fn unassign_role(principal: &Principal) -> Option<RoleAssignment> {
+    ASSIGNMENTS.with(|assignments| assignments.borrow_mut().remove(principal))
+}
+
+/// Returns the declared role assigned to `principal`, if any.
+#[must_use]
+pub fn assigned_role(principal: &Principal) -> Option<RoleAssignment> {
+    ASSIGNMENTS.with(|assignments| assignments.borrow().get(principal))
+}
+
+/// Returns all declared role assignments.
+#[must_use]
+pub fn list_assignments() -> Vec<RoleAssignment> {
+    ASSIGNMENTS.with(|assignments| {
+        assignments
+            .borrow()
+            .iter()
+            .map(|entry| entry.value())
+            .collect()
+    })
+}
+
+/// Returns the highest role `principal` holds in `hierarchy`, counting its
+/// assigned role and its whitelist roles.
+#[must_use]
+pub fn effective_role<'a>(principal: &Principal, hierarchy: &[&'a str]) -> Option<&'a str> {
+    let assigned = assigned_role(principal).map(|assignment| assignment.role);
+    let held = [
+        assigned.as_deref(),
+        auth::is_admin(principal).then_some(ADMIN),
+        auth::has_user_access(principal).then_some(USER),
+    ];
+    held.into_iter()
+        .flatten()
+        .filter_map(|role| rank(hierarchy, role))
+        .min()
+        .map(|index| hierarchy[index])
+}
+
+/// Checks if `principal` holds `role` or a role above it in `hierarchy`.
+#[must_use]
+pub fn has_role_or_higher(principal: &Principal, role: &str, hierarchy: &[&str]) -> bool {
+    let Some(required) = rank(hierarchy, role) else {
+        return false;
+    };
+    effective_role(principal, hierarchy)
+        .and_then(|held| rank(hierarchy, held))
+        .is_some_and(|held| held <= required)
+}
+
+/// Fails unless `principal` holds `role` or a role above it in `hierarchy`.
+///
+/// # Errors
+///
+/// Returns `IcarusError::AccessDenied` naming the required role.
+pub fn require_role_or_higher(principal: &Principal, role: &str, hierarchy: &[&str]) -> Result<()> {
+    if has_role_or_higher(principal, role, hierarchy) {
+        Ok(())
+    } else {
+        Err(IcarusError::AccessDenied(format!(
+            "Authentication required: {role} role or higher needed"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HIERARCHY: &[&str] = &["owner", "admin", "editor", "viewer", "user"];
+
+    #[test]
+    fn test_roles_follow_the_hierarchy() {
+        let owner = Principal::from_slice(&[51]);
+        let viewer = Principal::from_slice(&[52]);
+        let nobody = Principal::from_slice(&[53]);
+        assign_role(owner, "owner", HIERARCHY, owner).unwrap();
+        assign_role(viewer, "viewer", HIERARCHY, owner).unwrap();
+        assert!(assign_role(nobody, "superuser", HIERARCHY, owner).is_err());
+
+        assert!(has_role_or_higher(&owner, "admin", HIERARCHY));
+        assert!(has_role_or_higher(&viewer, "viewer", HIERARCHY));
+        assert!(!has_role_or_higher(&viewer, "editor", HIERARCHY));
+        assert!(require_role_or_higher(&nobody, "user", HIERARCHY).is_err());
+        // Undeclared roles are never held
+        assert!(!has_role_or_higher(&owner, "superuser", HIERARCHY));
+
+        assign_role(viewer, "editor", HIERARCHY, owner).unwrap();
+        assert_eq!(effective_role(&viewer, HIERARCHY), Some("editor"));
+        assert_eq!(list_assignments().len(), 2);
+
+        unassign_role(&viewer);
+        assert_eq!(effective_role(&viewer, HIERARCHY), None);
+    }
+
+    #[test]
+    fn test_whitelist_roles_count() {
+        let admin = Principal::from_slice(&[54]);
+        let user = Principal::from_slice(&[55]);
+        assign_role(admin, ADMIN, HIERARCHY, admin).unwrap();
+        auth::add_user(user);
+
+        assert!(auth::is_admin(&admin));
+        assert!(assigned_role(&admin).is_none());
+        assert!(has_role_or_higher(&admin, "editor", HIERARCHY));
+        assert_eq!(effective_role(&user, HIERARCHY), Some(USER));
+        assert!(!has_role_or_higher(&user, "viewer", HIERARCHY));
+
+        // Without "user" in the hierarchy, whitelisted users hold no role
+        assert_eq!(effective_role(&user, &["owner", "editor"]), None);
+    }
+}
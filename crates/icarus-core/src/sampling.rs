@@ -0,0 +1,461 @@
+//! MCP sampling: tools that ask the client's LLM for a completion.
+//!
+//! A canister cannot reach the client's model, so sampling takes two calls.
+//! A tool returns a [`SamplingRequest`] naming a continuation and carrying
+//! whatever state it needs afterwards. `mcp_call_tool` records the request
+//! with [`begin`] and returns its `sampling/createMessage` parameters in the
+//! result's `structuredContent.sampling`; the bridge forwards them to the
+//! client and passes the client's answer to the canister's
+//! `mcp_sampling_result` endpoint, which calls [`resume`]. The continuation
+//! registered under the request's name then receives the completion and the
+//! state, and its return value becomes the tool's final result.
+//!
+//! Pending requests live on the heap and are lost on upgrade; only the
+//! principal that made the original call can resume one. At most
+//! [`MAX_PENDING`] requests are kept, dropping the oldest first.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use candid::Principal;
+//! use icarus_core::sampling::{self, SamplingMessage, SamplingRequest, SamplingResponse};
+//!
+//! fn store_summary(response: SamplingResponse, doc_id: String) -> Result<String, String> {
+//!     Ok(format!("{doc_id}: {}", response.text))
+//! }
+//!
+//! sampling::register_continuation("store_summary", store_summary);
+//!
+//! // Returned by a `#[tool]` function
+//! let request = SamplingRequest::new("store_summary", 200)
+//!     .with_system_prompt("Summarize in one sentence.")
+//!     .with_message(SamplingMessage::user("The canister has 12 documents..."))
+//!     .with_state("doc-7");
+//!
+//! let caller = Principal::anonymous();
+//! let wire = sampling::begin(request, caller);
+//! assert_eq!(wire["method"], "sampling/createMessage");
+//! assert_eq!(wire["params"]["maxTokens"], 200);
+//!
+//! // The client's `sampling/createMessage` result, forwarded by the bridge
+//! let answer = serde_json::json!({
+//!     "role": "assistant",
+//!     "content": { "type": "text", "text": "Twelve documents." },
+//!     "model": "client-model",
+//! });
+//! let id = wire["id"].as_u64().unwrap();
+//! let result = sampling::resume(id, caller, &answer).unwrap();
+//! assert_eq!(result.into_success().unwrap(), "doc-7: Twelve documents.");
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+use candid::Principal;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{IcarusError, LegacyToolResult, Result};
+
+/// Metadata marking a successful result whose payload is a [`SamplingRequest`].
+pub const SAMPLING_METADATA: &str = r#"{"content":"sampling"}"#;
+
+/// JSON-RPC method the bridge sends to the client.
+pub const CREATE_MESSAGE_METHOD: &str = "sampling/createMessage";
+
+/// Pending requests kept by [`begin`].
+pub const MAX_PENDING: usize = 1_000;
+
+/// Code run with the client's completion; the `String` is the request's state.
+pub type ContinuationFn = fn(SamplingResponse, String) -> std::result::Result<String, String>;
+
+thread_local! {
+    static CONTINUATIONS: RefCell<BTreeMap<String, ContinuationFn>> = const { RefCell::new(BTreeMap::new()) };
+    static PENDING: RefCell<BTreeMap<u64, Pending>> = const { RefCell::new(BTreeMap::new()) };
+    static NEXT_ID: Cell<u64> = const { Cell::new(1) };
+}
+
+/// A request waiting for the client's completion.
+struct Pending {
+    caller: Principal,
+    continuation: String,
+    state: String,
+}
+
+/// Who wrote a sampling message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SamplingRole {
+    /// The user side of the conversation
+    User,
+    /// The model
+    Assistant,
+}
+
+/// A text message of the conversation sent to the client's model.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SamplingMessage {
+    /// Author of the message.
+    pub role: SamplingRole,
+    /// Message text.
+    pub text: String,
+}
+
+impl SamplingMessage {
+    /// Creates a user message.
+    #[must_use]
+    pub fn user(text: impl Into<String>) -> Self {
+        Self {
+            role: SamplingRole::User,
+            text: text.into(),
+        }
+    }
+
+    /// Creates an assistant message, e.g. for few-shot examples.
+    #[must_use]
+    pub fn assistant(text: impl Into<String>) -> Self {
+        Self {
+            role: SamplingRole::Assistant,
+            text: text.into(),
+        }
+    }
+}
+
+/// A completion a tool asks the client for, returned as the tool's result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SamplingRequest {
+    /// Conversation to complete.
+    pub messages: Vec<SamplingMessage>,
+    /// System prompt, if any.
+    pub system_prompt: Option<String>,
+    /// Maximum tokens the client should generate.
+    pub max_tokens: u32,
+    /// Sequences that end the completion.
+    pub stop_sequences: Vec<String>,
+    /// Name of the continuation that receives the completion.
+    pub continuation: String,
+    /// Opaque state passed to the continuation; it never leaves the canister.
+    pub state: String,
+}
+
+impl SamplingRequest {
+    /// Creates a request without messages for the continuation `continuation`.
+    #[must_use]
+    pub fn new(continuation: impl Into<String>, max_tokens: u32) -> Self {
+        Self {
+            messages: Vec::new(),
+            system_prompt: None,
+            max_tokens,
+            stop_sequences: Vec::new(),
+            continuation: continuation.into(),
+            state: String::new(),
+        }
+    }
+
+    /// Appends a message.
+    #[must_use]
+    pub fn with_message(mut self, message: SamplingMessage) -> Self {
+        self.messages.push(message);
+        self
+    }
+
+    /// Sets the system prompt.
+    #[must_use]
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Adds a stop sequence.
+    #[must_use]
+    pub fn with_stop_sequence(mut self, sequence: impl Into<String>) -> Self {
+        self.stop_sequences.push(sequence.into());
+        self
+    }
+
+    /// Sets the state passed to the continuation.
+    #[must_use]
+    pub fn with_state(mut self, state: impl Into<String>) -> Self {
+        self.state = state.into();
+        self
+    }
+
+    /// Returns the `sampling/createMessage` parameters in MCP wire format.
+    #[must_use]
+    pub fn create_message_params(&self) -> Value {
+        let messages: Vec<Value> = self
+            .messages
+            .iter()
+            .map(|message| {
+                json!({
+                    "role": message.role,
+                    "content": { "type": "text", "text": message.text },
+                })
+            })
+            .collect();
+        let mut params = json!({
+            "messages": messages,
+            "maxTokens": self.max_tokens,
+        });
+        if let Some(prompt) = &self.system_prompt {
+            params["systemPrompt"] = json!(prompt);
+        }
+        if !self.stop_sequences.is_empty() {
+            params["stopSequences"] = json!(self.stop_sequences);
+        }
+        params
+    }
+}
+
+/// The client's completion, from its `sampling/createMessage` result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SamplingResponse {
+    /// Author of the completion, normally the assistant.
+    pub role: SamplingRole,
+    /// Completion text.
+    pub text: String,
+    /// Model the client used.
+    pub model: String,
+    /// Why generation stopped, e.g. `endTurn` or `maxTokens`.
+    pub stop_reason: Option<String>,
+}
+
+impl SamplingResponse {
+    /// Parses a `sampling/createMessage` result.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::JsonError` if the result has no role or text
+    /// content, e.g. because the client answered with an image.
+    pub fn from_create_message_result(result: &Value) -> Result<Self> {
+        let role = serde_json::from_value(result["role"].clone())
+            .map_err(|e| IcarusError::JsonError(format!("Invalid sampling role: {e}")))?;
+        let content = &result["content"];
+        if content["type"] != "text" {
+            return Err(IcarusError::JsonError(format!(
+                "Sampling result must be text content, got {}",
+                content["type"]
+            )));
+        }
+        let text = content["text"]
+            .as_str()
+            .ok_or_else(|| IcarusError::JsonError("Sampling result has no text".to_string()))?;
+
+        Ok(Self {
+            role,
+            text: text.to_string(),
+            model: result["model"].as_str().unwrap_or_default().to_string(),
+            stop_reason: result["stopReason"].as_str().map(str::to_string),
+        })
+    }
+}
+
+/// Registers the continuation named `name`.
+///
+/// Continuations live on the heap; register them again after an upgrade.
+pub fn register_continuation(name: &'static str, continuation: ContinuationFn) {
+    CONTINUATIONS.with(|continuations| {
+        continuations
+            .borrow_mut()
+            .insert(name.to_string(), continuation)
+    });
+}
+
+/// Wraps a sampling request in a successful tool result.
+#[must_use]
+pub fn to_tool_result(request: &SamplingRequest) -> LegacyToolResult<'static> {
+    // Serializing a request cannot fail: it holds only strings and numbers
+    let payload = serde_json::to_string(request).unwrap_or_default();
+    LegacyToolResult::success_with_metadata(payload, SAMPLING_METADATA)
+}
+
+/// Returns the sampling request of a result produced by [`to_tool_result`].
+#[must_use]
+pub fn from_tool_result(result: &LegacyToolResult<'_>) -> Option<SamplingRequest> {
+    let LegacyToolResult::Success { result, metadata } = result else {
+        return None;
+    };
+    if metadata.as_deref() != Some(SAMPLING_METADATA) {
+        return None;
+    }
+    serde_json::from_str(result).ok()
+}
+
+/// Records `request` as pending for `caller` and returns what the bridge
+/// sends to the client: the pending id, the method and its parameters.
+pub fn begin(request: SamplingRequest, caller: Principal) -> Value {
+    let id = NEXT_ID.with(|next| {
+        let id = next.get();
+        next.set(id + 1);
+        id
+    });
+    let params = request.create_message_params();
+
+    PENDING.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        while pending.len() >= MAX_PENDING {
+            pending.pop_first();
+        }
+        pending.insert(
+            id,
+            Pending {
+                caller,
+                continuation: request.continuation,
+                state: request.state,
+            },
+        );
+    });
+
+    json!({
+        "id": id,
+        "method": CREATE_MESSAGE_METHOD,
+        "params": params,
+    })
+}
+
+/// Feeds the client's `sampling/createMessage` result for pending request
+/// `id` to its continuation and returns the tool's final result.
+///
+/// A continuation's `Err` becomes an error result, as for tools.
+///
+/// # Errors
+///
+/// Returns `IcarusError::AccessDenied` if `caller` did not start the
+/// request, `IcarusError::ConfigurationError` if it is not pending or its
+/// continuation is not registered, and `IcarusError::JsonError` if the
+/// result is malformed. Except for the first, the request is dropped.
+pub fn resume(id: u64, caller: Principal, result: &Value) -> Result<LegacyToolResult<'static>> {
+    let pending = take_pending(id, caller)?;
+    let continuation = CONTINUATIONS
+        .with(|continuations| continuations.borrow().get(&pending.continuation).copied())
+        .ok_or_else(|| {
+            IcarusError::ConfigurationError(format!(
+                "no continuation registered for sampling '{}'",
+                pending.continuation
+            ))
+        })?;
+    let response = SamplingResponse::from_create_message_result(result)?;
+
+    Ok(match continuation(response, pending.state) {
+        Ok(text) => LegacyToolResult::success(text),
+        Err(message) => LegacyToolResult::error(message),
+    })
+}
+
+/// Drops pending request `id` because the client refused or failed to
+/// sample, returning the tool's final error result.
+///
+/// # Errors
+///
+/// Fails like [`resume`] if the request is not pending for `caller`.
+pub fn decline(id: u64, caller: Principal, reason: &str) -> Result<LegacyToolResult<'static>> {
+    let pending = take_pending(id, caller)?;
+    Ok(LegacyToolResult::error(format!(
+        "Sampling for '{}' was declined: {reason}",
+        pending.continuation
+    )))
+}
+
+/// Returns the number of requests waiting for a completion.
+#[must_use]
+pub fn pending_count() -> usize {
+    PENDING.with(|pending| pending.borrow().len())
+}
+
+/// Removes pending request `id` if `caller` started it.
+fn take_pending(id: u64, caller: Principal) -> Result<Pending> {
+    PENDING.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        let Some(request) = pending.remove(&id) else {
+            return Err(IcarusError::ConfigurationError(format!(
+                "no pending sampling request {id}"
+            )));
+        };
+        if request.caller != caller {
+            pending.insert(id, request);
+            return Err(IcarusError::AccessDenied(format!(
+                "sampling request {id} belongs to another caller"
+            )));
+        }
+        Ok(request)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo(response: SamplingResponse, state: String) -> std::result::Result<String, String> {
+        if response.text.is_empty() {
+            return Err(format!("{state}: empty completion"));
+        }
+        Ok(format!("{state}={}", response.text))
+    }
+
+    fn answer(text: &str) -> Value {
+        json!({
+            "role": "assistant",
+            "content": { "type": "text", "text": text },
+            "model": "test",
+            "stopReason": "endTurn",
+        })
+    }
+
+    #[test]
+    fn test_request_wire_format() {
+        let request = SamplingRequest::new("echo", 50)
+            .with_message(SamplingMessage::user("hi"))
+            .with_message(SamplingMessage::assistant("hello"))
+            .with_stop_sequence("\n\n");
+        let params = request.create_message_params();
+        assert_eq!(params["messages"][0]["role"], "user");
+        assert_eq!(params["messages"][1]["content"]["text"], "hello");
+        assert_eq!(params["stopSequences"][0], "\n\n");
+        assert!(params.get("systemPrompt").is_none());
+
+        let result = to_tool_result(&request);
+        assert_eq!(from_tool_result(&result), Some(request));
+        assert_eq!(from_tool_result(&LegacyToolResult::success("{}")), None);
+    }
+
+    #[test]
+    fn test_resume_runs_continuation_once() {
+        register_continuation("echo", echo);
+        let alice = Principal::from_slice(&[1]);
+        let wire = begin(SamplingRequest::new("echo", 10).with_state("k"), alice);
+        let id = wire["id"].as_u64().unwrap();
+
+        let stranger = Principal::from_slice(&[2]);
+        assert!(matches!(
+            resume(id, stranger, &answer("v")),
+            Err(IcarusError::AccessDenied(_))
+        ));
+
+        let result = resume(id, alice, &answer("v")).unwrap();
+        assert_eq!(result.into_success().unwrap(), "k=v");
+        assert!(resume(id, alice, &answer("v")).is_err());
+
+        let wire = begin(SamplingRequest::new("echo", 10).with_state("k"), alice);
+        let id = wire["id"].as_u64().unwrap();
+        assert!(resume(id, alice, &answer("")).unwrap().is_error());
+    }
+
+    #[test]
+    fn test_decline_and_malformed_results() {
+        let caller = Principal::anonymous();
+        let wire = begin(SamplingRequest::new("missing", 10), caller);
+        let id = wire["id"].as_u64().unwrap();
+        assert!(matches!(
+            resume(id, caller, &answer("x")),
+            Err(IcarusError::ConfigurationError(_))
+        ));
+
+        let wire = begin(SamplingRequest::new("echo", 10), caller);
+        let id = wire["id"].as_u64().unwrap();
+        assert!(decline(id, caller, "user rejected").unwrap().is_error());
+        assert_eq!(pending_count(), 0);
+
+        let image = json!({ "role": "assistant", "content": { "type": "image" } });
+        assert!(SamplingResponse::from_create_message_result(&image).is_err());
+    }
+}
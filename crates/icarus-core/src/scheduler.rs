@@ -0,0 +1,692 @@
+//! A polling scheduler that keeps its tasks in stable memory.
+//!
+//! `ic-cdk-timers` timers live on the heap and are lost on every upgrade,
+//! and a heartbeat runs (and costs cycles) every round. This scheduler uses
+//! one periodic tick timer instead: each tick takes the due tasks from a
+//! [`StableMinHeap`] ordered by `next_run`, so a tick with nothing due costs
+//! one lookup however many tasks there are. Tasks are stored in stable
+//! memory, so they survive upgrades as they are; after an upgrade only the
+//! handlers and the tick have to be set up again.
+//!
+//! A task is a name, a next run time and either a repeat interval, a
+//! [`CalendarSchedule`] such as "daily at 09:00 in +05:30", or neither for a
+//! one-off run. The
+//! code it runs is a handler registered under the same name, typically in
+//! `init` and `post_upgrade` next to [`start`]: a plain function with
+//! [`register_handler`], or an async closure taking a [`TaskContext`] with
+//! [`register_task_handler`]. A due task without a handler is recorded as a
+//! failed run naming the missing handler and moves on to its next run.
+//!
+//! Calendar tasks follow the civil calendar of their zone: "monthly on the
+//! 31st" runs on the last day of shorter months, and leap years are
+//! respected. Zones are fixed offsets; see [`crate::calendar`].
+//!
+//! Tasks run at most once per tick, so the tick resolution bounds how late a
+//! task may run. A repeating task that missed several runs, e.g. while the
+//! canister was stopped, runs once and is scheduled for its next future slot.
+//!
+//! # Budgets
+//!
+//! A task may be given an instruction budget with [`set_instruction_budget`].
+//! Each run is measured with the instruction counter and recorded as a
+//! [`TaskExecution`]; a run that used more than its budget is marked failed
+//! with the overage. Handlers doing long loops can check
+//! [`remaining_instructions`] and stop early. The tick timer started with
+//! [`start`] runs every due task in its own message, so a task that traps
+//! or hits the IC instruction limit fails alone, after the tick has already
+//! moved it to its next run. Execution records live on the heap and start
+//! over after an upgrade.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use icarus_core::calendar::{CalendarSchedule, Recurrence};
+//! use icarus_core::{scheduler, time};
+//!
+//! fn send_digest() {}
+//!
+//! scheduler::register_handler("daily_digest", send_digest);
+//! scheduler::schedule_every("daily_digest", Duration::from_secs(24 * 60 * 60));
+//!
+//! scheduler::register_task_handler("reindex", |ctx| async move {
+//!     if ctx.run > 1 {
+//!         return Err("reindexing runs only once".to_string());
+//!     }
+//!     Ok(())
+//! });
+//! scheduler::schedule_at("reindex", time::now_nanos());
+//!
+//! let nine_am = CalendarSchedule::new(
+//!     Recurrence::Daily { hour: 9, minute: 0 },
+//!     "Asia/Kolkata".parse().unwrap(),
+//! )
+//! .unwrap();
+//! scheduler::schedule_calendar("morning_report", nine_am);
+//!
+//! let task = scheduler::task("daily_digest").unwrap();
+//! assert!(task.next_run > time::now_nanos());
+//! ```
+
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::time::Duration;
+
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+
+use crate::calendar::CalendarSchedule;
+use crate::collections::StableMinHeap;
+use crate::memory::{self, StableMemory, SCHEDULER_QUEUE_MEMORY_ID, SCHEDULER_TASKS_MEMORY_ID};
+
+/// Code run when a task is due.
+pub type TaskFn = fn();
+
+/// Future returned by handlers registered with [`register_task_handler`].
+pub type TaskFuture = Pin<Box<dyn Future<Output = Result<(), String>>>>;
+
+type AsyncTaskFn = Rc<dyn Fn(TaskContext) -> TaskFuture>;
+
+/// A registered handler.
+#[derive(Clone)]
+enum Handler {
+    Sync(TaskFn),
+    Async(AsyncTaskFn),
+}
+
+/// Executions kept by [`executions`].
+pub const MAX_EXECUTIONS: usize = 200;
+
+/// A stored task.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct ScheduledTask {
+    /// Task name, also the name of its handler.
+    pub name: String,
+    /// When the task is due, in nanoseconds since the epoch.
+    pub next_run: u64,
+    /// Repeat interval in nanoseconds; one-off tasks are removed after running.
+    pub interval: Option<u64>,
+    /// Calendar recurrence, for tasks scheduled with [`schedule_calendar`].
+    pub calendar: Option<CalendarSchedule>,
+    /// When the task last ran.
+    pub last_run: Option<u64>,
+    /// Number of runs.
+    pub runs: u64,
+    /// Instructions one run may use, if limited.
+    pub instruction_budget: Option<u64>,
+}
+
+/// What an async handler knows about the run it executes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskContext {
+    /// Task name.
+    pub name: String,
+    /// When the run was due, in nanoseconds since the epoch.
+    pub scheduled_for: u64,
+    /// Number of this run, starting at 1.
+    pub run: u64,
+    /// Instructions the run may use, if limited.
+    pub instruction_budget: Option<u64>,
+    /// Instruction counter when the run started
+    start_instructions: u64,
+}
+
+impl TaskContext {
+    /// Returns the instructions this run has left, or `None` without a budget.
+    ///
+    /// The count includes instructions executed before earlier awaits.
+    #[must_use]
+    pub fn remaining_instructions(&self) -> Option<u64> {
+        let used = instruction_counter().saturating_sub(self.start_instructions);
+        self.instruction_budget
+            .map(|budget| budget.saturating_sub(used))
+    }
+}
+
+/// One run of a task.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct TaskExecution {
+    /// Task name.
+    pub task: String,
+    /// When the run started, in nanoseconds since the epoch.
+    pub started_at: u64,
+    /// Instructions the handler used.
+    pub instructions: u64,
+    /// The task's budget at the time of the run.
+    pub instruction_budget: Option<u64>,
+    /// Instructions used beyond the budget, 0 within it.
+    pub overage: u64,
+    /// Error returned by the handler, or why no handler ran.
+    pub error: Option<String>,
+    /// Whether the handler succeeded within its budget.
+    pub succeeded: bool,
+}
+
+impl Storable for ScheduledTask {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap_or_default())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap_or_else(|e| unreachable!("corrupt scheduled task: {e}"))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).unwrap_or_default()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+thread_local! {
+    static TASKS: RefCell<StableBTreeMap<String, ScheduledTask, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(SCHEDULER_TASKS_MEMORY_ID))
+    );
+
+    /// Task names by `next_run`
+    static QUEUE: StableMinHeap<String, u64> = StableMinHeap::init(SCHEDULER_QUEUE_MEMORY_ID);
+
+    static HANDLERS: RefCell<BTreeMap<String, Handler>> = const { RefCell::new(BTreeMap::new()) };
+
+    static EXECUTIONS: RefCell<VecDeque<TaskExecution>> = const { RefCell::new(VecDeque::new()) };
+
+    /// Instruction counter at the start of the running task, and its budget
+    static RUNNING: Cell<Option<(u64, u64)>> = const { Cell::new(None) };
+}
+
+#[cfg(not(feature = "ic-canister"))]
+thread_local! {
+    static INSTRUCTIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Registers the handler run for tasks named `name`, replacing any previous one.
+///
+/// Handlers live on the heap; register them again after an upgrade.
+pub fn register_handler(name: &'static str, handler: TaskFn) {
+    HANDLERS.with(|handlers| {
+        handlers
+            .borrow_mut()
+            .insert(name.to_string(), Handler::Sync(handler))
+    });
+}
+
+/// Registers an async handler for tasks named `name`, replacing any previous
+/// one.
+///
+/// The handler receives the run's [`TaskContext`]; an `Err` marks the run
+/// failed. Handlers live on the heap; register them again after an upgrade.
+pub fn register_task_handler<F, Fut>(name: impl Into<String>, handler: F)
+where
+    F: Fn(TaskContext) -> Fut + 'static,
+    Fut: Future<Output = Result<(), String>> + 'static,
+{
+    let handler: AsyncTaskFn = Rc::new(move |context| -> TaskFuture { Box::pin(handler(context)) });
+    HANDLERS.with(|handlers| {
+        handlers
+            .borrow_mut()
+            .insert(name.into(), Handler::Async(handler))
+    });
+}
+
+/// Removes the handler for `name`, returning whether one was registered.
+pub fn unregister_handler(name: &str) -> bool {
+    HANDLERS.with(|handlers| handlers.borrow_mut().remove(name).is_some())
+}
+
+/// Schedules `name` to run once at `at_nanos`, replacing any task with that
+/// name but keeping its budget.
+pub fn schedule_at(name: &str, at_nanos: u64) {
+    store(ScheduledTask {
+        name: name.to_string(),
+        next_run: at_nanos,
+        interval: None,
+        calendar: None,
+        last_run: None,
+        runs: 0,
+        instruction_budget: task(name).and_then(|task| task.instruction_budget),
+    });
+}
+
+/// Schedules `name` to run every `interval`, starting one interval from now.
+///
+/// A stored task with the same name and interval is kept as it is, so this
+/// can be called from both `init` and `post_upgrade` without pushing the
+/// next run back on every upgrade.
+pub fn schedule_every(name: &str, interval: Duration) {
+    // A zero interval would make the task due on every tick forever
+    let interval = u64::try_from(interval.as_nanos())
+        .unwrap_or(u64::MAX)
+        .max(1);
+    let existing = task(name);
+    if existing
+        .as_ref()
+        .is_some_and(|task| task.interval == Some(interval))
+    {
+        return;
+    }
+    store(ScheduledTask {
+        name: name.to_string(),
+        next_run: crate::time::now_nanos().saturating_add(interval),
+        interval: Some(interval),
+        calendar: None,
+        last_run: None,
+        runs: 0,
+        instruction_budget: existing.and_then(|task| task.instruction_budget),
+    });
+}
+
+/// Schedules `name` to run on a calendar recurrence, starting at the next
+/// matching local time.
+///
+/// As with [`schedule_every`], a stored task with the same schedule is kept
+/// as it is.
+pub fn schedule_calendar(name: &str, schedule: CalendarSchedule) {
+    let existing = task(name);
+    if existing
+        .as_ref()
+        .is_some_and(|task| task.calendar.as_ref() == Some(&schedule))
+    {
+        return;
+    }
+    store(ScheduledTask {
+        name: name.to_string(),
+        next_run: schedule.next_after(crate::time::now_nanos()),
+        interval: None,
+        calendar: Some(schedule),
+        last_run: None,
+        runs: 0,
+        instruction_budget: existing.and_then(|task| task.instruction_budget),
+    });
+}
+
+/// Limits the instructions one run of `name` may use, or lifts the limit
+/// with `None`. Returns `false` if there is no such task.
+pub fn set_instruction_budget(name: &str, budget: Option<u64>) -> bool {
+    let Some(mut task) = task(name) else {
+        return false;
+    };
+    task.instruction_budget = budget;
+    store(task);
+    true
+}
+
+/// Removes a task, returning whether it existed.
+pub fn cancel(name: &str) -> bool {
+    QUEUE.with(|queue| queue.remove(&name.to_string()));
+    TASKS.with(|tasks| tasks.borrow_mut().remove(&name.to_string()).is_some())
+}
+
+/// Returns a stored task.
+#[must_use]
+pub fn task(name: &str) -> Option<ScheduledTask> {
+    TASKS.with(|tasks| tasks.borrow().get(&name.to_string()))
+}
+
+/// Returns all stored tasks, sorted by name.
+#[must_use]
+pub fn tasks() -> Vec<ScheduledTask> {
+    TASKS.with(|tasks| tasks.borrow().iter().map(|entry| entry.value()).collect())
+}
+
+/// Runs every due task and returns how many were due.
+///
+/// Each task is rescheduled (or removed) before its handler runs, so a
+/// handler may schedule its own task again. Handlers run one after another;
+/// [`start`] runs them concurrently, each in its own message.
+pub async fn tick() -> usize {
+    let due = take_due();
+    let count = due.len();
+    for run in due {
+        execute(run).await;
+    }
+    count
+}
+
+/// Returns the most recent runs, newest first.
+#[must_use]
+pub fn executions(limit: usize) -> Vec<TaskExecution> {
+    EXECUTIONS.with(|executions| {
+        executions
+            .borrow()
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
+    })
+}
+
+/// Returns the instructions the running task has left, or `None` outside a
+/// task or for a task without a budget.
+#[must_use]
+pub fn remaining_instructions() -> Option<u64> {
+    let (start, budget) = RUNNING.with(Cell::get)?;
+    Some(budget.saturating_sub(instruction_counter().saturating_sub(start)))
+}
+
+/// A due task, already moved to its next run.
+struct DueRun {
+    task: ScheduledTask,
+    scheduled_for: u64,
+    handler: Option<Handler>,
+}
+
+/// Reschedules the due tasks and returns them with their handlers, in due
+/// order.
+fn take_due() -> Vec<DueRun> {
+    let now = crate::time::now_nanos();
+    sync_queue();
+    // Collect first: rescheduling pushes the tasks back into the queue
+    let due: Vec<ScheduledTask> = QUEUE.with(|queue| {
+        std::iter::from_fn(|| queue.pop_if_at_most(&now))
+            .filter_map(|(name, _)| task(&name))
+            .collect()
+    });
+
+    let mut runs = Vec::with_capacity(due.len());
+    for mut task in due {
+        let handler = HANDLERS.with(|handlers| handlers.borrow().get(&task.name).cloned());
+        let scheduled_for = task.next_run;
+
+        task.runs += 1;
+        task.last_run = Some(now);
+        if let Some(interval) = task.interval {
+            // Skip the slots missed since the task was due
+            let missed = (now - task.next_run) / interval;
+            task.next_run = task
+                .next_run
+                .saturating_add(interval.saturating_mul(missed + 1));
+            store(task.clone());
+        } else if let Some(calendar) = &task.calendar {
+            // The next matching time after now skips missed occurrences too
+            task.next_run = calendar.next_after(now);
+            store(task.clone());
+        } else {
+            cancel(&task.name);
+        }
+        runs.push(DueRun {
+            task,
+            scheduled_for,
+            handler,
+        });
+    }
+    runs
+}
+
+/// Runs one task's handler and records the outcome and instruction use.
+async fn execute(run: DueRun) {
+    let DueRun {
+        task,
+        scheduled_for,
+        handler,
+    } = run;
+    let started_at = crate::time::now_nanos();
+    let start = instruction_counter();
+
+    let result = match handler {
+        None => Err(format!("no handler registered for task '{}'", task.name)),
+        Some(Handler::Sync(handler)) => {
+            RUNNING.with(|running| {
+                running.set(task.instruction_budget.map(|budget| (start, budget)));
+            });
+            handler();
+            RUNNING.with(|running| running.set(None));
+            Ok(())
+        }
+        Some(Handler::Async(handler)) => {
+            handler(TaskContext {
+                name: task.name.clone(),
+                scheduled_for,
+                run: task.runs,
+                instruction_budget: task.instruction_budget,
+                start_instructions: start,
+            })
+            .await
+        }
+    };
+
+    let instructions = instruction_counter().saturating_sub(start);
+    let overage = task
+        .instruction_budget
+        .map_or(0, |budget| instructions.saturating_sub(budget));
+    if overage > 0 {
+        crate::logging::warn(format_args!(
+            "task '{}' used {instructions} instructions, {overage} over its budget",
+            task.name
+        ));
+    }
+    let error = result.err();
+    if let Some(error) = &error {
+        crate::logging::error(format_args!("task '{}' failed: {error}", task.name));
+    }
+
+    EXECUTIONS.with(|executions| {
+        let mut executions = executions.borrow_mut();
+        if executions.len() == MAX_EXECUTIONS {
+            executions.pop_front();
+        }
+        executions.push_back(TaskExecution {
+            task: task.name.clone(),
+            started_at,
+            instructions,
+            instruction_budget: task.instruction_budget,
+            overage,
+            succeeded: overage == 0 && error.is_none(),
+            error,
+        });
+    });
+}
+
+/// Instructions executed in the current call context, across awaits.
+fn instruction_counter() -> u64 {
+    #[cfg(feature = "ic-canister")]
+    {
+        ic_cdk::api::performance_counter(1)
+    }
+    #[cfg(not(feature = "ic-canister"))]
+    {
+        INSTRUCTIONS.with(Cell::get)
+    }
+}
+
+/// Starts the tick timer, checking for due tasks every `resolution`.
+///
+/// Timers do not survive upgrades, so call this from both `init` and
+/// `post_upgrade`; the tasks themselves are kept.
+#[cfg(feature = "ic-canister")]
+pub fn start(resolution: Duration) -> ic_cdk_timers::TimerId {
+    ic_cdk_timers::set_timer_interval(resolution, || {
+        // One message per task, so a runaway task cannot take the others down
+        for run in take_due() {
+            ic_cdk_timers::set_timer(Duration::ZERO, move || {
+                ic_cdk::futures::spawn(execute(run));
+            });
+        }
+    })
+}
+
+fn store(task: ScheduledTask) {
+    QUEUE.with(|queue| queue.push(task.name.clone(), task.next_run));
+    TASKS.with(|tasks| tasks.borrow_mut().insert(task.name.clone(), task));
+}
+
+/// Rebuilds the queue if it disagrees with the stored tasks, as when
+/// upgrading from a version that did not keep one.
+fn sync_queue() {
+    let stored = TASKS.with(|tasks| tasks.borrow().len());
+    if QUEUE.with(StableMinHeap::len) == stored {
+        return;
+    }
+    QUEUE.with(|queue| {
+        queue.clear();
+        for task in tasks() {
+            queue.push(task.name, task.next_run);
+        }
+    });
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+    use crate::time::set_time_override;
+
+    const SECOND: u64 = 1_000_000_000;
+
+    thread_local! {
+        static RUNS: Cell<u32> = const { Cell::new(0) };
+    }
+
+    fn count_run() {
+        RUNS.with(|runs| runs.set(runs.get() + 1));
+    }
+
+    #[tokio::test]
+    async fn test_repeating_task_skips_missed_slots() {
+        set_time_override(Some(1_000 * SECOND));
+        register_handler("sync", count_run);
+        schedule_every("sync", Duration::from_secs(10));
+        assert_eq!(task("sync").unwrap().next_run, 1_010 * SECOND);
+
+        // Registering again, as post_upgrade does, keeps the schedule
+        set_time_override(Some(1_005 * SECOND));
+        schedule_every("sync", Duration::from_secs(10));
+        assert_eq!(task("sync").unwrap().next_run, 1_010 * SECOND);
+
+        set_time_override(Some(1_035 * SECOND));
+        assert_eq!(tick().await, 1);
+        assert_eq!(RUNS.with(Cell::get), 1);
+        let stored = task("sync").unwrap();
+        assert_eq!(stored.next_run, 1_040 * SECOND);
+        assert_eq!(stored.runs, 1);
+
+        assert_eq!(tick().await, 0);
+        assert!(cancel("sync"));
+        set_time_override(None);
+    }
+
+    #[tokio::test]
+    async fn test_due_tasks_run_in_order_and_queue_is_rebuilt() {
+        set_time_override(Some(500 * SECOND));
+        schedule_at("late", 450 * SECOND);
+        schedule_at("early", 400 * SECOND);
+        schedule_at("future", 600 * SECOND);
+
+        // Tasks stored before the queue existed are picked up again
+        QUEUE.with(StableMinHeap::clear);
+
+        assert_eq!(tick().await, 2);
+        let ran: Vec<_> = executions(2).into_iter().map(|e| e.task).collect();
+        assert_eq!(ran, ["late", "early"]);
+        assert_eq!(QUEUE.with(StableMinHeap::len), 1);
+        assert_eq!(QUEUE.with(StableMinHeap::peek).unwrap().0, "future");
+
+        assert!(cancel("future"));
+        assert!(QUEUE.with(StableMinHeap::is_empty));
+        set_time_override(None);
+    }
+
+    #[tokio::test]
+    async fn test_task_without_handler_fails_clearly() {
+        set_time_override(Some(50 * SECOND));
+        schedule_at("report", 40 * SECOND);
+
+        assert_eq!(tick().await, 1);
+        assert!(task("report").is_none());
+        let execution = executions(1).pop().unwrap();
+        assert!(!execution.succeeded);
+        assert_eq!(
+            execution.error.as_deref(),
+            Some("no handler registered for task 'report'")
+        );
+        set_time_override(None);
+    }
+
+    #[tokio::test]
+    async fn test_async_handler_gets_context_and_reports_errors() {
+        set_time_override(Some(90 * SECOND));
+        register_task_handler("reindex", |ctx| async move {
+            tokio::task::yield_now().await;
+            if ctx.scheduled_for == 80 * SECOND && ctx.run == 1 {
+                Err(format!("{} is not ready", ctx.name))
+            } else {
+                Ok(())
+            }
+        });
+        schedule_at("reindex", 80 * SECOND);
+
+        assert_eq!(tick().await, 1);
+        let execution = executions(1).pop().unwrap();
+        assert_eq!(execution.error.as_deref(), Some("reindex is not ready"));
+
+        schedule_at("reindex", 85 * SECOND);
+        tick().await;
+        assert!(executions(1)[0].succeeded);
+        assert!(unregister_handler("reindex"));
+        set_time_override(None);
+    }
+
+    #[tokio::test]
+    async fn test_calendar_task_runs_on_local_schedule() {
+        use crate::calendar::Recurrence;
+
+        // 2024-01-31T00:00:00Z
+        let jan_31 = 1_706_659_200 * SECOND;
+        set_time_override(Some(jan_31));
+        register_handler("invoice", count_run);
+        let schedule = CalendarSchedule::new(
+            Recurrence::Monthly {
+                day: 31,
+                hour: 9,
+                minute: 0,
+            },
+            "+01:00".parse().unwrap(),
+        )
+        .unwrap();
+        schedule_calendar("invoice", schedule);
+        // 09:00 at +01:00 is 08:00 UTC
+        let due = jan_31 + 8 * 3_600 * SECOND;
+        assert_eq!(task("invoice").unwrap().next_run, due);
+
+        set_time_override(Some(due));
+        let before = RUNS.with(Cell::get);
+        assert_eq!(tick().await, 1);
+        assert_eq!(RUNS.with(Cell::get), before + 1);
+        // February 2024 has 29 days
+        assert_eq!(
+            task("invoice").unwrap().next_run,
+            due + 29 * 24 * 3_600 * SECOND
+        );
+        assert!(cancel("invoice"));
+        set_time_override(None);
+    }
+
+    fn burn_instructions() {
+        assert_eq!(remaining_instructions(), Some(1_000));
+        INSTRUCTIONS.with(|counter| counter.set(counter.get() + 1_500));
+        assert_eq!(remaining_instructions(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_budget_overage_is_recorded() {
+        set_time_override(Some(70 * SECOND));
+        register_handler("import", burn_instructions);
+        schedule_at("import", 60 * SECOND);
+        assert!(set_instruction_budget("import", Some(1_000)));
+        assert!(!set_instruction_budget("missing", Some(1)));
+
+        assert_eq!(tick().await, 1);
+        assert_eq!(remaining_instructions(), None);
+        let execution = executions(1).pop().unwrap();
+        assert_eq!(execution.task, "import");
+        assert_eq!(execution.instructions, 1_500);
+        assert_eq!(execution.overage, 500);
+        assert!(!execution.succeeded);
+        set_time_override(None);
+    }
+}
@@ -0,0 +1,291 @@
+//! Named secrets such as API keys, kept out of tool arguments and configs.
+//!
+//! Endpoint configs refer to a secret by name, as `{{secret:NAME}}`, and
+//! [`expand`] substitutes the value when the outgoing request is built. No
+//! function here returns a value to a caller: [`list_secrets`] reports names
+//! and versions only, and [`with_secret`] lends the value to a closure inside
+//! the canister. `mcp! { secrets = true }` adds admin-only `set_secret`,
+//! `rotate_secret`, `delete_secret` and `list_secrets` tools.
+//!
+//! Values are stored in stable memory as given. Anyone who can read the
+//! canister's memory, such as a node provider, can read them; they are
+//! protected from callers, not from the subnet.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use candid::Principal;
+//! use icarus_core::http::{HttpMethod, HttpRequest};
+//! use icarus_core::secrets;
+//!
+//! let owner = Principal::anonymous();
+//! secrets::set_secret("openai", "sk-test", owner).unwrap();
+//!
+//! let request = HttpRequest::new(HttpMethod::Post, "https://api.openai.com/v1/chat/completions")
+//!     .header("Authorization", secrets::expand("Bearer {{secret:openai}}").unwrap());
+//! assert_eq!(request.headers[0].1, "Bearer sk-test");
+//!
+//! let info = secrets::rotate_secret("openai", "sk-new", owner).unwrap();
+//! assert_eq!(info.version, 2);
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+
+use crate::memory::{self, StableMemory, SECRETS_MEMORY_ID};
+use crate::{IcarusError, Result};
+
+/// Longest accepted secret name.
+pub const MAX_NAME_LEN: usize = 64;
+
+/// Largest accepted secret value, in bytes.
+pub const MAX_VALUE_BYTES: usize = 8 * 1024;
+
+const PLACEHOLDER_START: &str = "{{secret:";
+const PLACEHOLDER_END: &str = "}}";
+
+/// What is known about a secret without revealing it.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct SecretInfo {
+    /// Name configs refer to the secret by.
+    pub name: String,
+    /// 1 when set, increased by every rotation.
+    pub version: u32,
+    /// When the secret was first set, in nanoseconds since the epoch.
+    pub created_at: u64,
+    /// When the value last changed.
+    pub updated_at: u64,
+    /// Principal that last changed the value.
+    pub updated_by: Principal,
+}
+
+#[derive(Clone, CandidType, Deserialize)]
+struct StoredSecret {
+    info: SecretInfo,
+    value: String,
+}
+
+impl Storable for StoredSecret {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap_or_default())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap_or_else(|e| unreachable!("corrupt secret: {e}"))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).unwrap_or_default()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+thread_local! {
+    static SECRETS: RefCell<StableBTreeMap<String, StoredSecret, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(SECRETS_MEMORY_ID))
+    );
+}
+
+/// Stores a new secret.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if the name or value is invalid
+/// or a secret with this name already exists; use [`rotate_secret`] to
+/// replace its value.
+pub fn set_secret(name: &str, value: &str, caller: Principal) -> Result<SecretInfo> {
+    validate(name, value)?;
+    if exists(name) {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Secret '{name}' already exists; rotate it instead"
+        )));
+    }
+
+    let now = crate::time::now_nanos();
+    let info = SecretInfo {
+        name: name.to_string(),
+        version: 1,
+        created_at: now,
+        updated_at: now,
+        updated_by: caller,
+    };
+    insert(info.clone(), value);
+    Ok(info)
+}
+
+/// Replaces the value of an existing secret and bumps its version.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if the value is invalid or there
+/// is no secret with this name.
+pub fn rotate_secret(name: &str, value: &str, caller: Principal) -> Result<SecretInfo> {
+    validate(name, value)?;
+    let mut info = secret_info(name).ok_or_else(|| not_found(name))?;
+    info.version = info.version.saturating_add(1);
+    info.updated_at = crate::time::now_nanos();
+    info.updated_by = caller;
+    insert(info.clone(), value);
+    Ok(info)
+}
+
+/// Deletes a secret.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if there is no secret with this
+/// name.
+pub fn delete_secret(name: &str) -> Result<SecretInfo> {
+    SECRETS
+        .with(|secrets| secrets.borrow_mut().remove(&name.to_string()))
+        .map(|secret| secret.info)
+        .ok_or_else(|| not_found(name))
+}
+
+/// Returns what is known about a secret, without its value.
+#[must_use]
+pub fn secret_info(name: &str) -> Option<SecretInfo> {
+    SECRETS.with(|secrets| secrets.borrow().get(&name.to_string()).map(|s| s.info))
+}
+
+/// Lists all secrets, without their values, ordered by name.
+#[must_use]
+pub fn list_secrets() -> Vec<SecretInfo> {
+    SECRETS.with(|secrets| {
+        secrets
+            .borrow()
+            .iter()
+            .map(|entry| entry.value().info)
+            .collect()
+    })
+}
+
+/// Calls `f` with the value of a secret.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if there is no secret with this
+/// name.
+pub fn with_secret<R>(name: &str, f: impl FnOnce(&str) -> R) -> Result<R> {
+    let secret = SECRETS
+        .with(|secrets| secrets.borrow().get(&name.to_string()))
+        .ok_or_else(|| not_found(name))?;
+    Ok(f(&secret.value))
+}
+
+/// Replaces every `{{secret:NAME}}` in `template` with the secret's value.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if a placeholder is not closed
+/// or names a secret that does not exist.
+pub fn expand(template: &str) -> Result<String> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(PLACEHOLDER_START) {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + PLACEHOLDER_START.len()..];
+        let end = after.find(PLACEHOLDER_END).ok_or_else(|| {
+            IcarusError::ConfigurationError("Unclosed secret placeholder".to_string())
+        })?;
+        with_secret(after[..end].trim(), |value| expanded.push_str(value))?;
+        rest = &after[end + PLACEHOLDER_END.len()..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn exists(name: &str) -> bool {
+    SECRETS.with(|secrets| secrets.borrow().contains_key(&name.to_string()))
+}
+
+fn insert(info: SecretInfo, value: &str) {
+    SECRETS.with(|secrets| {
+        secrets.borrow_mut().insert(
+            info.name.clone(),
+            StoredSecret {
+                info,
+                value: value.to_string(),
+            },
+        );
+    });
+}
+
+fn validate(name: &str, value: &str) -> Result<()> {
+    let valid_name = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !valid_name {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Invalid secret name '{name}': use up to {MAX_NAME_LEN} letters, digits, '_', '-' or '.'"
+        )));
+    }
+    if value.is_empty() || value.len() > MAX_VALUE_BYTES {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Secret values must be between 1 and {MAX_VALUE_BYTES} bytes"
+        )));
+    }
+    Ok(())
+}
+
+fn not_found(name: &str) -> IcarusError {
+    IcarusError::ConfigurationError(format!("No secret named '{name}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owner() -> Principal {
+        Principal::from_slice(&[1])
+    }
+
+    #[test]
+    fn test_secret_lifecycle() {
+        let info = set_secret("stripe", "sk_live_1", owner()).unwrap();
+        assert_eq!(info.version, 1);
+        assert!(set_secret("stripe", "sk_live_2", owner()).is_err());
+
+        let rotated = rotate_secret("stripe", "sk_live_2", owner()).unwrap();
+        assert_eq!(rotated.version, 2);
+        assert_eq!(rotated.created_at, info.created_at);
+        assert_eq!(with_secret("stripe", str::to_string).unwrap(), "sk_live_2");
+
+        assert_eq!(list_secrets(), vec![rotated]);
+        assert_eq!(delete_secret("stripe").unwrap().version, 2);
+        assert!(with_secret("stripe", |_| ()).is_err());
+        assert!(rotate_secret("stripe", "sk_live_3", owner()).is_err());
+        assert!(delete_secret("stripe").is_err());
+    }
+
+    #[test]
+    fn test_expand_placeholders() {
+        set_secret("github", "ghp_abc", owner()).unwrap();
+        set_secret("region", "eu", owner()).unwrap();
+
+        assert_eq!(
+            expand("token {{secret:github}} in {{ secret:region }}").unwrap(),
+            "token ghp_abc in {{ secret:region }}"
+        );
+        assert_eq!(expand("{{secret: region }}").unwrap(), "eu");
+        assert_eq!(expand("no secrets").unwrap(), "no secrets");
+        assert!(expand("{{secret:missing}}").is_err());
+        assert!(expand("{{secret:github").is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_secrets() {
+        assert!(set_secret("", "value", owner()).is_err());
+        assert!(set_secret("has space", "value", owner()).is_err());
+        assert!(set_secret(&"n".repeat(MAX_NAME_LEN + 1), "value", owner()).is_err());
+        assert!(set_secret("empty", "", owner()).is_err());
+        assert!(set_secret("huge", &"v".repeat(MAX_VALUE_BYTES + 1), owner()).is_err());
+    }
+}
@@ -0,0 +1,524 @@
+//! Threshold ECDSA signatures from the management canister.
+//!
+//! The canister holds no private key: the subnet signs with a secp256k1 key
+//! shared among its nodes, derived for the canister and a derivation path.
+//! Owners give paths names with [`set_derivation_path`] so tools refer to
+//! `"webhooks"` rather than raw bytes; the unnamed [`DEFAULT_PATH`] is the
+//! empty path. `mcp! { signing = true }` adds the `get_public_key`,
+//! `sign_message` and path management tools.
+//!
+//! Every signature costs cycles ([`sign_fee`]). Signing is refused when it
+//! would take the balance below the reserve set in [`crate::cycles`], and the
+//! cycles spent are counted in [`signing_stats`].
+//!
+//! Inside a canister (`ic-canister` feature) the key name defaults to
+//! [`MAINNET_KEY`] and is changed with [`set_key_name`]. Off-chain the
+//! management canister calls go to a handler installed with
+//! [`set_ecdsa_handler`]; `icarus_test::mock::mock_signing` installs one.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use icarus_core::signing;
+//!
+//! #[tool("Sign a webhook payload")]
+//! async fn sign_webhook(payload: String) -> Result<String, String> {
+//!     let signature = signing::sign_message(payload.as_bytes(), "webhooks")
+//!         .await
+//!         .map_err(|e| e.to_string())?;
+//!     Ok(signature.iter().map(|b| format!("{b:02x}")).collect())
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::memory::{self, StableMemory, SIGNING_PATHS_MEMORY_ID};
+use crate::{IcarusError, Result};
+
+/// Threshold ECDSA key on mainnet.
+pub const MAINNET_KEY: &str = "key_1";
+
+/// Threshold ECDSA test key on mainnet, cheaper and not for real assets.
+pub const TEST_KEY: &str = "test_key_1";
+
+/// Threshold ECDSA key of a local dfx replica.
+pub const LOCAL_KEY: &str = "dfx_test_key";
+
+/// Name of the empty derivation path, usable without configuring it.
+pub const DEFAULT_PATH: &str = "default";
+
+/// Fee for one signature with [`MAINNET_KEY`] on a 13-node subnet.
+pub const MAINNET_SIGN_FEE: u128 = 26_153_846_153;
+
+/// Fee for one signature with any other key.
+pub const TEST_SIGN_FEE: u128 = 10_000_000_000;
+
+/// Most segments in a derivation path, as allowed by the management canister.
+pub const MAX_PATH_SEGMENTS: usize = 255;
+
+const SERVICE: &str = "threshold ECDSA";
+
+/// A management canister call made off-chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EcdsaRequest {
+    /// `ecdsa_public_key` for the canister's own key.
+    PublicKey {
+        /// Key name.
+        key_name: String,
+        /// Derivation path.
+        derivation_path: Vec<Vec<u8>>,
+    },
+    /// `sign_with_ecdsa`.
+    Sign {
+        /// Key name.
+        key_name: String,
+        /// Derivation path.
+        derivation_path: Vec<Vec<u8>>,
+        /// SHA-256 or other 32-byte hash of the message.
+        message_hash: Vec<u8>,
+    },
+}
+
+/// Answer to an [`EcdsaRequest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EcdsaReply {
+    /// The public key and chain code.
+    PublicKey(PublicKey),
+    /// The 64-byte `r || s` signature.
+    Signature(Vec<u8>),
+}
+
+/// Handler that answers threshold ECDSA calls off-chain.
+#[cfg(not(feature = "ic-canister"))]
+pub type EcdsaHandler = Box<dyn Fn(&EcdsaRequest) -> Result<EcdsaReply>>;
+
+/// A derived public key.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct PublicKey {
+    /// SEC1-compressed secp256k1 public key (33 bytes).
+    pub public_key: Vec<u8>,
+    /// Chain code for further BIP-32 style derivation.
+    pub chain_code: Vec<u8>,
+}
+
+/// A named derivation path.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct DerivationPath {
+    /// Name tools refer to the path by.
+    pub name: String,
+    /// Path segments.
+    pub segments: Vec<Vec<u8>>,
+    /// When the path was last set, in nanoseconds since the epoch.
+    pub updated_at: u64,
+    /// Principal that last set the path.
+    pub updated_by: Principal,
+}
+
+/// Signatures made since the canister was last upgraded.
+#[derive(Debug, Clone, Default, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct SigningStats {
+    /// Key in use.
+    pub key_name: String,
+    /// Fee for one signature with that key.
+    pub sign_fee: u128,
+    /// Signatures made.
+    pub signatures: u64,
+    /// Cycles spent on them.
+    pub cycles_spent: u128,
+    /// Signatures made per path name.
+    pub signatures_by_path: BTreeMap<String, u64>,
+}
+
+impl Storable for DerivationPath {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap_or_default())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap_or_else(|e| unreachable!("corrupt derivation path: {e}"))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).unwrap_or_default()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+thread_local! {
+    static PATHS: RefCell<StableBTreeMap<String, DerivationPath, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(SIGNING_PATHS_MEMORY_ID))
+    );
+
+    static KEY_NAME: RefCell<String> = RefCell::new(MAINNET_KEY.to_string());
+
+    /// Public keys by derivation path, fetched once per key name
+    static PUBLIC_KEYS: RefCell<BTreeMap<Vec<Vec<u8>>, PublicKey>> =
+        const { RefCell::new(BTreeMap::new()) };
+
+    static STATS: RefCell<SigningStats> = RefCell::new(SigningStats::default());
+}
+
+#[cfg(not(feature = "ic-canister"))]
+thread_local! {
+    static ECDSA_HANDLER: RefCell<Option<EcdsaHandler>> = const { RefCell::new(None) };
+}
+
+/// Selects the threshold ECDSA key, e.g. [`LOCAL_KEY`] for a local replica.
+pub fn set_key_name(name: impl Into<String>) {
+    KEY_NAME.with(|key| *key.borrow_mut() = name.into());
+    PUBLIC_KEYS.with(|cached| cached.borrow_mut().clear());
+}
+
+/// Returns the selected key name.
+#[must_use]
+pub fn key_name() -> String {
+    KEY_NAME.with(|key| key.borrow().clone())
+}
+
+/// Returns the fee for one signature with the selected key.
+#[must_use]
+pub fn sign_fee() -> u128 {
+    if key_name() == MAINNET_KEY {
+        MAINNET_SIGN_FEE
+    } else {
+        TEST_SIGN_FEE
+    }
+}
+
+/// Installs the handler for off-chain threshold ECDSA calls on the current
+/// thread, or removes it with `None`.
+#[cfg(not(feature = "ic-canister"))]
+pub fn set_ecdsa_handler(handler: Option<EcdsaHandler>) {
+    ECDSA_HANDLER.with(|cell| *cell.borrow_mut() = handler);
+    PUBLIC_KEYS.with(|cached| cached.borrow_mut().clear());
+}
+
+/// Names a derivation path, replacing any path with the same name.
+///
+/// Changing a path changes the key it signs with, so anything that trusts
+/// the old public key stops verifying.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if the name is invalid or the
+/// path has too many segments.
+pub fn set_derivation_path(
+    name: &str,
+    segments: Vec<Vec<u8>>,
+    caller: Principal,
+) -> Result<DerivationPath> {
+    let valid_name = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !valid_name {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Invalid derivation path name '{name}'"
+        )));
+    }
+    if segments.len() > MAX_PATH_SEGMENTS {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Derivation paths have at most {MAX_PATH_SEGMENTS} segments"
+        )));
+    }
+
+    let path = DerivationPath {
+        name: name.to_string(),
+        segments,
+        updated_at: crate::time::now_nanos(),
+        updated_by: caller,
+    };
+    PATHS.with(|paths| paths.borrow_mut().insert(name.to_string(), path.clone()));
+    Ok(path)
+}
+
+/// Removes a named derivation path.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if there is no path with this
+/// name.
+pub fn remove_derivation_path(name: &str) -> Result<DerivationPath> {
+    PATHS
+        .with(|paths| paths.borrow_mut().remove(&name.to_string()))
+        .ok_or_else(|| unknown_path(name))
+}
+
+/// Lists the named derivation paths, ordered by name.
+#[must_use]
+pub fn list_derivation_paths() -> Vec<DerivationPath> {
+    PATHS.with(|paths| paths.borrow().iter().map(|entry| entry.value()).collect())
+}
+
+/// Returns the segments of a named path. [`DEFAULT_PATH`] is the empty path
+/// unless it has been set explicitly.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if there is no path with this
+/// name.
+pub fn resolve_path(name: &str) -> Result<Vec<Vec<u8>>> {
+    match PATHS.with(|paths| paths.borrow().get(&name.to_string())) {
+        Some(path) => Ok(path.segments),
+        None if name == DEFAULT_PATH => Ok(Vec::new()),
+        None => Err(unknown_path(name)),
+    }
+}
+
+/// Returns the public key for a named derivation path.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` for an unknown path and
+/// `IcarusError::ExternalServiceError` if the management canister call fails
+/// or, off-chain, if no handler is installed.
+pub async fn get_public_key(path: &str) -> Result<PublicKey> {
+    public_key_at(resolve_path(path)?).await
+}
+
+/// Returns the public key for raw derivation path segments, such as a path
+/// derived from the caller's principal.
+///
+/// # Errors
+///
+/// See [`get_public_key`].
+pub async fn public_key_at(derivation_path: Vec<Vec<u8>>) -> Result<PublicKey> {
+    if let Some(key) = PUBLIC_KEYS.with(|cached| cached.borrow().get(&derivation_path).cloned()) {
+        return Ok(key);
+    }
+    let reply = call(EcdsaRequest::PublicKey {
+        key_name: key_name(),
+        derivation_path: derivation_path.clone(),
+    })
+    .await?;
+    let EcdsaReply::PublicKey(key) = reply else {
+        return Err(ecdsa_error("expected a public key"));
+    };
+    PUBLIC_KEYS.with(|cached| cached.borrow_mut().insert(derivation_path, key.clone()));
+    Ok(key)
+}
+
+/// Signs the SHA-256 hash of `message` with the key of a named derivation
+/// path and returns the 64-byte `r || s` signature.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ResourceLimitExceeded` if the fee would take the
+/// balance below the cycles reserve; otherwise see [`get_public_key`].
+pub async fn sign_message(message: &[u8], path: &str) -> Result<Vec<u8>> {
+    let hash: [u8; 32] = Sha256::digest(message).into();
+    sign_hash(hash, path).await
+}
+
+/// Signs a precomputed 32-byte hash, such as an Ethereum Keccak-256
+/// transaction hash, with the key of a named derivation path.
+///
+/// # Errors
+///
+/// See [`sign_message`].
+pub async fn sign_hash(hash: [u8; 32], path: &str) -> Result<Vec<u8>> {
+    sign_hash_at(hash, resolve_path(path)?, path).await
+}
+
+/// Signs a precomputed hash with raw derivation path segments. `label` is
+/// the name the signature is counted under in [`SigningStats`].
+///
+/// # Errors
+///
+/// See [`sign_message`].
+pub async fn sign_hash_at(
+    hash: [u8; 32],
+    derivation_path: Vec<Vec<u8>>,
+    label: &str,
+) -> Result<Vec<u8>> {
+    let fee = sign_fee();
+    let status = crate::cycles::cycles_status();
+    if status.balance.saturating_sub(fee) < status.reserve {
+        return Err(IcarusError::ResourceLimitExceeded {
+            resource: "cycles".to_string(),
+            message: format!(
+                "signing costs {fee} cycles and the balance {} is too close to the reserve of {}",
+                status.balance, status.reserve
+            ),
+        });
+    }
+
+    let reply = call(EcdsaRequest::Sign {
+        key_name: key_name(),
+        derivation_path,
+        message_hash: hash.to_vec(),
+    })
+    .await?;
+    let EcdsaReply::Signature(signature) = reply else {
+        return Err(ecdsa_error("expected a signature"));
+    };
+
+    STATS.with(|stats| {
+        let mut stats = stats.borrow_mut();
+        stats.signatures += 1;
+        stats.cycles_spent += fee;
+        *stats
+            .signatures_by_path
+            .entry(label.to_string())
+            .or_default() += 1;
+    });
+    Ok(signature)
+}
+
+/// Returns the signatures made and cycles spent since the last upgrade.
+#[must_use]
+pub fn signing_stats() -> SigningStats {
+    let mut stats = STATS.with(|stats| stats.borrow().clone());
+    stats.key_name = key_name();
+    stats.sign_fee = sign_fee();
+    stats
+}
+
+fn unknown_path(name: &str) -> IcarusError {
+    IcarusError::ConfigurationError(format!("No derivation path named '{name}'"))
+}
+
+fn ecdsa_error(message: impl Into<String>) -> IcarusError {
+    IcarusError::ExternalServiceError {
+        service: SERVICE.to_string(),
+        message: message.into(),
+    }
+}
+
+#[cfg(not(feature = "ic-canister"))]
+#[allow(clippy::unused_async)]
+async fn call(request: EcdsaRequest) -> Result<EcdsaReply> {
+    ECDSA_HANDLER.with(|cell| match cell.borrow().as_ref() {
+        Some(handler) => handler(&request),
+        None => Err(ecdsa_error(
+            "threshold ECDSA is unavailable outside a canister; install a mock handler",
+        )),
+    })
+}
+
+#[cfg(feature = "ic-canister")]
+async fn call(request: EcdsaRequest) -> Result<EcdsaReply> {
+    use ic_cdk::management_canister::{
+        ecdsa_public_key, sign_with_ecdsa, EcdsaCurve, EcdsaKeyId, EcdsaPublicKeyArgs,
+        SignWithEcdsaArgs,
+    };
+
+    let key_id = |name: String| EcdsaKeyId {
+        curve: EcdsaCurve::Secp256k1,
+        name,
+    };
+    match request {
+        EcdsaRequest::PublicKey {
+            key_name,
+            derivation_path,
+        } => ecdsa_public_key(&EcdsaPublicKeyArgs {
+            canister_id: None,
+            derivation_path,
+            key_id: key_id(key_name),
+        })
+        .await
+        .map(|result| {
+            EcdsaReply::PublicKey(PublicKey {
+                public_key: result.public_key,
+                chain_code: result.chain_code,
+            })
+        })
+        .map_err(|e| ecdsa_error(e.to_string())),
+        EcdsaRequest::Sign {
+            key_name,
+            derivation_path,
+            message_hash,
+        } => sign_with_ecdsa(&SignWithEcdsaArgs {
+            message_hash,
+            derivation_path,
+            key_id: key_id(key_name),
+        })
+        .await
+        .map(|result| EcdsaReply::Signature(result.signature))
+        .map_err(|e| ecdsa_error(e.to_string())),
+    }
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+
+    fn fake_signer() -> EcdsaHandler {
+        Box::new(|request| match request {
+            EcdsaRequest::PublicKey {
+                derivation_path, ..
+            } => Ok(EcdsaReply::PublicKey(PublicKey {
+                public_key: vec![2; derivation_path.len() + 1],
+                chain_code: vec![0; 32],
+            })),
+            EcdsaRequest::Sign { message_hash, .. } => Ok(EcdsaReply::Signature(
+                [message_hash.clone(), message_hash.clone()].concat(),
+            )),
+        })
+    }
+
+    #[test]
+    fn test_named_paths() {
+        let owner = Principal::from_slice(&[1]);
+        assert_eq!(resolve_path(DEFAULT_PATH).unwrap(), Vec::<Vec<u8>>::new());
+        assert!(resolve_path("webhooks").is_err());
+
+        set_derivation_path("webhooks", vec![b"hooks".to_vec()], owner).unwrap();
+        assert_eq!(resolve_path("webhooks").unwrap(), vec![b"hooks".to_vec()]);
+        assert_eq!(list_derivation_paths().len(), 1);
+        assert!(set_derivation_path("bad name", Vec::new(), owner).is_err());
+        assert!(
+            set_derivation_path("long", vec![Vec::new(); MAX_PATH_SEGMENTS + 1], owner).is_err()
+        );
+
+        remove_derivation_path("webhooks").unwrap();
+        assert!(remove_derivation_path("webhooks").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_signing_is_counted() {
+        set_ecdsa_handler(Some(fake_signer()));
+
+        let signature = sign_message(b"payload", DEFAULT_PATH).await.unwrap();
+        let hash: [u8; 32] = Sha256::digest(b"payload").into();
+        assert_eq!(&signature[..32], hash);
+
+        let key = get_public_key(DEFAULT_PATH).await.unwrap();
+        assert_eq!(key.public_key, [2]);
+
+        let stats = signing_stats();
+        assert_eq!(stats.signatures, 1);
+        assert_eq!(stats.cycles_spent, MAINNET_SIGN_FEE);
+        assert_eq!(stats.signatures_by_path[DEFAULT_PATH], 1);
+
+        set_ecdsa_handler(None);
+        assert!(sign_message(b"payload", DEFAULT_PATH).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_signing_respects_cycles_reserve() {
+        set_ecdsa_handler(Some(fake_signer()));
+        crate::cycles::set_reserve(1_000);
+        crate::cycles::set_mock_balance(TEST_SIGN_FEE);
+        set_key_name(TEST_KEY);
+
+        assert!(matches!(
+            sign_hash([0; 32], DEFAULT_PATH).await,
+            Err(IcarusError::ResourceLimitExceeded { .. })
+        ));
+        assert_eq!(signing_stats().signatures, 0);
+
+        crate::cycles::set_mock_balance(u128::MAX);
+        set_key_name(MAINNET_KEY);
+        set_ecdsa_handler(None);
+    }
+}
@@ -0,0 +1,550 @@
+//! Chunked snapshots of canister state for backup and restore.
+//!
+//! Stable structures register a [`SnapshotSource`] that can export their
+//! contents to bytes and restore them. [`snapshot_begin`] serializes every
+//! registered source into one buffer and splits it into chunks small enough to
+//! fit in a single canister reply; [`snapshot_chunk`] returns them one by one.
+//! [`snapshot_restore_chunk`] accepts the chunks back in any order and applies
+//! the snapshot once all of them have arrived and the checksum matches.
+//!
+//! A snapshot is applied all or nothing. Every section is decoded and checked
+//! by its source's validator, set with [`SnapshotSource::with_validate`],
+//! before any is restored, and if a restore still fails, the sources restored
+//! before it are put back from a backup taken just before.
+//!
+//! The auth whitelists are registered automatically. `mcp!{ auth = true }`
+//! exposes the three functions as admin-only canister endpoints.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::storage::{self, SnapshotSource};
+//!
+//! fn export_notes() -> icarus_core::Result<Vec<u8>> {
+//!     Ok(b"notes".to_vec())
+//! }
+//!
+//! fn restore_notes(_bytes: &[u8]) -> icarus_core::Result<()> {
+//!     Ok(())
+//! }
+//!
+//! storage::register_snapshot_source(SnapshotSource::new("notes", export_notes, restore_notes));
+//!
+//! let manifest = storage::snapshot_begin(None).unwrap();
+//! for index in 0..manifest.chunk_count {
+//!     let chunk = storage::snapshot_chunk(manifest.snapshot_id, index).unwrap();
+//!     storage::snapshot_restore_chunk(manifest.clone(), index, chunk).unwrap();
+//! }
+//! ```
+
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode};
+use serde::Serialize;
+
+use crate::{IcarusError, Result};
+
+/// Default chunk size, comfortably below the 2MB reply limit.
+pub const DEFAULT_CHUNK_SIZE: u32 = 1_000_000;
+
+/// Largest accepted chunk size.
+pub const MAX_CHUNK_SIZE: u32 = 1_900_000;
+
+/// Exports a source's state as bytes.
+pub type ExportFn = fn() -> Result<Vec<u8>>;
+
+/// Restores a source's state from bytes produced by its [`ExportFn`].
+pub type RestoreFn = fn(&[u8]) -> Result<()>;
+
+/// Checks that bytes can be restored by a source without changing any state.
+pub type ValidateFn = fn(&[u8]) -> Result<()>;
+
+/// A named piece of state included in snapshots.
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotSource {
+    name: &'static str,
+    export: ExportFn,
+    restore: RestoreFn,
+    validate: Option<ValidateFn>,
+}
+
+impl SnapshotSource {
+    /// Creates a snapshot source.
+    #[must_use]
+    pub const fn new(name: &'static str, export: ExportFn, restore: RestoreFn) -> Self {
+        Self {
+            name,
+            export,
+            restore,
+            validate: None,
+        }
+    }
+
+    /// Checks this source's section with `validate` before any section of a
+    /// snapshot is restored.
+    #[must_use]
+    pub const fn with_validate(mut self, validate: ValidateFn) -> Self {
+        self.validate = Some(validate);
+        self
+    }
+
+    /// Returns the source name.
+    #[must_use]
+    #[inline]
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// Describes a snapshot prepared by [`snapshot_begin`].
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct SnapshotManifest {
+    /// Identifier of the prepared snapshot.
+    pub snapshot_id: u64,
+    /// Total size of the serialized snapshot in bytes.
+    pub total_bytes: u64,
+    /// Size of every chunk except possibly the last.
+    pub chunk_size: u32,
+    /// Number of chunks.
+    pub chunk_count: u32,
+    /// FNV-1a checksum of the serialized snapshot.
+    pub checksum: u64,
+    /// Names of the sources included, in order.
+    pub sources: Vec<String>,
+}
+
+/// Progress of an in-flight restore.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct RestoreStatus {
+    /// Snapshot being restored.
+    pub snapshot_id: u64,
+    /// Chunks received so far.
+    pub received_chunks: u32,
+    /// Chunks expected in total.
+    pub chunk_count: u32,
+    /// Whether the snapshot has been applied.
+    pub complete: bool,
+}
+
+#[derive(CandidType, Deserialize)]
+struct Section {
+    name: String,
+    data: Vec<u8>,
+}
+
+struct PreparedSnapshot {
+    manifest: SnapshotManifest,
+    bytes: Vec<u8>,
+}
+
+enum Progress {
+    Partial(u32),
+    Complete(Vec<u8>),
+}
+
+struct PendingRestore {
+    manifest: SnapshotManifest,
+    chunks: Vec<Option<Vec<u8>>>,
+}
+
+thread_local! {
+    static SOURCES: RefCell<Vec<SnapshotSource>> = RefCell::new(vec![
+        SnapshotSource::new(
+            "auth.admins",
+            crate::auth::export_admins,
+            crate::auth::restore_admins,
+        )
+        .with_validate(crate::auth::validate_principals),
+        SnapshotSource::new(
+            "auth.users",
+            crate::auth::export_users,
+            crate::auth::restore_users,
+        )
+        .with_validate(crate::auth::validate_principals),
+    ]);
+
+    static PREPARED: RefCell<Option<PreparedSnapshot>> = const { RefCell::new(None) };
+
+    static RESTORE: RefCell<Option<PendingRestore>> = const { RefCell::new(None) };
+}
+
+/// Registers a source, replacing any existing source with the same name.
+pub fn register_snapshot_source(source: SnapshotSource) {
+    SOURCES.with(|sources| {
+        let mut sources = sources.borrow_mut();
+        sources.retain(|existing| existing.name != source.name);
+        sources.push(source);
+    });
+}
+
+/// Returns the names of the registered sources.
+#[must_use]
+pub fn snapshot_sources() -> Vec<&'static str> {
+    SOURCES.with(|sources| sources.borrow().iter().map(SnapshotSource::name).collect())
+}
+
+/// Serializes all registered sources and prepares them for chunked download.
+///
+/// Any previously prepared snapshot is discarded.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` for an invalid chunk size, or the
+/// error of the first source whose export fails.
+pub fn snapshot_begin(chunk_size: Option<u32>) -> Result<SnapshotManifest> {
+    let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+    if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Snapshot chunk size must be between 1 and {MAX_CHUNK_SIZE} bytes"
+        )));
+    }
+
+    let sources = SOURCES.with(|sources| sources.borrow().clone());
+    let sections = sources
+        .iter()
+        .map(|source| {
+            Ok(Section {
+                name: source.name.to_string(),
+                data: (source.export)()?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let bytes = Encode!(&sections).map_err(|e| IcarusError::CandidError(e.to_string()))?;
+    let total_bytes = bytes.len() as u64;
+    let chunk_size_bytes = u64::from(chunk_size);
+    let chunk_count = u32::try_from((total_bytes + chunk_size_bytes - 1) / chunk_size_bytes)
+        .map_err(|_| IcarusError::internal_error("Snapshot has too many chunks"))?;
+
+    let manifest = SnapshotManifest {
+        snapshot_id: crate::time::now_nanos(),
+        total_bytes,
+        chunk_size,
+        chunk_count,
+        checksum: checksum(&bytes),
+        sources: sections.into_iter().map(|section| section.name).collect(),
+    };
+
+    PREPARED.with(|prepared| {
+        *prepared.borrow_mut() = Some(PreparedSnapshot {
+            manifest: manifest.clone(),
+            bytes,
+        });
+    });
+
+    Ok(manifest)
+}
+
+/// Returns chunk `index` of the prepared snapshot.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if no snapshot with this id is
+/// prepared or the index is out of range.
+pub fn snapshot_chunk(snapshot_id: u64, index: u32) -> Result<Vec<u8>> {
+    PREPARED.with(|prepared| {
+        let prepared = prepared.borrow();
+        let snapshot = prepared
+            .as_ref()
+            .filter(|snapshot| snapshot.manifest.snapshot_id == snapshot_id)
+            .ok_or_else(|| {
+                IcarusError::ConfigurationError(format!("No prepared snapshot {snapshot_id}"))
+            })?;
+
+        if index >= snapshot.manifest.chunk_count {
+            return Err(IcarusError::ConfigurationError(format!(
+                "Chunk {index} out of range (snapshot has {} chunks)",
+                snapshot.manifest.chunk_count
+            )));
+        }
+
+        let chunk_size = snapshot.manifest.chunk_size as usize;
+        let start = index as usize * chunk_size;
+        let end = (start + chunk_size).min(snapshot.bytes.len());
+        Ok(snapshot.bytes[start..end].to_vec())
+    })
+}
+
+/// Accepts one chunk of a snapshot and applies it once all chunks arrived.
+///
+/// Sending a chunk for a different snapshot id abandons any restore in progress.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` for out-of-range or oversized
+/// chunks and for checksum mismatches, `IcarusError::CandidError` if the
+/// snapshot cannot be decoded, or the error of a source whose validation or
+/// restore fails, in which case no state is changed.
+pub fn snapshot_restore_chunk(
+    manifest: SnapshotManifest,
+    index: u32,
+    chunk: Vec<u8>,
+) -> Result<RestoreStatus> {
+    if index >= manifest.chunk_count {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Chunk {index} out of range (snapshot has {} chunks)",
+            manifest.chunk_count
+        )));
+    }
+    if chunk.len() > manifest.chunk_size as usize {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Chunk {index} exceeds the chunk size of {} bytes",
+            manifest.chunk_size
+        )));
+    }
+
+    let progress = RESTORE.with(|restore| {
+        let mut restore = restore.borrow_mut();
+        if !matches!(restore.as_ref(), Some(pending) if pending.manifest == manifest) {
+            *restore = Some(PendingRestore {
+                chunks: vec![None; manifest.chunk_count as usize],
+                manifest: manifest.clone(),
+            });
+        }
+
+        let pending = restore.as_mut().expect("restore initialized above");
+        pending.chunks[index as usize] = Some(chunk);
+
+        let received = pending.chunks.iter().filter(|c| c.is_some()).count();
+        if received < pending.chunks.len() {
+            return Progress::Partial(u32::try_from(received).unwrap_or(u32::MAX));
+        }
+
+        let bytes = pending.chunks.drain(..).flatten().flatten().collect();
+        *restore = None;
+        Progress::Complete(bytes)
+    });
+
+    let bytes = match progress {
+        Progress::Complete(bytes) => bytes,
+        Progress::Partial(received_chunks) => {
+            return Ok(RestoreStatus {
+                snapshot_id: manifest.snapshot_id,
+                received_chunks,
+                chunk_count: manifest.chunk_count,
+                complete: false,
+            });
+        }
+    };
+
+    if bytes.len() as u64 != manifest.total_bytes || checksum(&bytes) != manifest.checksum {
+        return Err(IcarusError::ConfigurationError(
+            "Snapshot checksum mismatch; restart the restore".to_string(),
+        ));
+    }
+
+    apply(&bytes)?;
+
+    Ok(RestoreStatus {
+        snapshot_id: manifest.snapshot_id,
+        received_chunks: manifest.chunk_count,
+        chunk_count: manifest.chunk_count,
+        complete: true,
+    })
+}
+
+/// Restores every section, or none of them.
+///
+/// All sections are decoded and validated before the first is restored. If a
+/// restore fails anyway, the sources restored so far, and the failing one,
+/// get their state from before the call back.
+fn apply(bytes: &[u8]) -> Result<()> {
+    let sections =
+        Decode!(bytes, Vec<Section>).map_err(|e| IcarusError::CandidError(e.to_string()))?;
+    let sources = SOURCES.with(|sources| sources.borrow().clone());
+
+    let mut plan = Vec::with_capacity(sections.len());
+    for section in &sections {
+        let source = sources
+            .iter()
+            .find(|source| source.name == section.name)
+            .ok_or_else(|| {
+                IcarusError::ConfigurationError(format!(
+                    "Snapshot contains unknown source '{}'",
+                    section.name
+                ))
+            })?;
+        if let Some(validate) = source.validate {
+            validate(&section.data).map_err(|e| {
+                IcarusError::ConfigurationError(format!(
+                    "Snapshot section '{}' is invalid: {e}",
+                    section.name
+                ))
+            })?;
+        }
+        plan.push((source, &section.data));
+    }
+
+    let backups = plan
+        .iter()
+        .map(|(source, _)| (source.export)())
+        .collect::<Result<Vec<_>>>()?;
+
+    for (applied, (source, data)) in plan.iter().enumerate() {
+        if let Err(error) = (source.restore)(data) {
+            for ((source, _), backup) in plan.iter().zip(&backups).take(applied + 1) {
+                // Best effort: the backup was exported by this source moments ago
+                let _ = (source.restore)(backup);
+            }
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+/// 64-bit FNV-1a hash, used to detect corrupted or mixed-up chunks.
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Principal;
+
+    thread_local! {
+        static NOTES: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn export_notes() -> Result<Vec<u8>> {
+        Ok(NOTES.with(|notes| notes.borrow().clone()))
+    }
+
+    fn restore_notes(bytes: &[u8]) -> Result<()> {
+        NOTES.with(|notes| *notes.borrow_mut() = bytes.to_vec());
+        Ok(())
+    }
+
+    fn download(manifest: &SnapshotManifest) -> Vec<Vec<u8>> {
+        (0..manifest.chunk_count)
+            .map(|index| snapshot_chunk(manifest.snapshot_id, index).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip_in_small_chunks() {
+        register_snapshot_source(SnapshotSource::new("notes", export_notes, restore_notes));
+        NOTES.with(|notes| *notes.borrow_mut() = vec![7; 5_000]);
+        let admin = Principal::from_slice(&[42]);
+        crate::auth::add_admin(admin);
+
+        let manifest = snapshot_begin(Some(1_024)).unwrap();
+        assert!(manifest.chunk_count > 4);
+        assert!(manifest.sources.contains(&"notes".to_string()));
+        let chunks = download(&manifest);
+
+        NOTES.with(|notes| notes.borrow_mut().clear());
+        crate::auth::remove_admin(&admin);
+
+        // Chunks may arrive in any order.
+        let mut status = None;
+        for (index, chunk) in chunks.into_iter().enumerate().rev() {
+            let index = u32::try_from(index).unwrap();
+            status = Some(snapshot_restore_chunk(manifest.clone(), index, chunk).unwrap());
+        }
+
+        assert!(status.unwrap().complete);
+        assert_eq!(NOTES.with(|notes| notes.borrow().len()), 5_000);
+        assert!(crate::auth::is_admin(&admin));
+    }
+
+    #[test]
+    fn test_checksum_mismatch_is_rejected() {
+        let manifest = snapshot_begin(None).unwrap();
+        let mut chunks = download(&manifest);
+        chunks[0][0] ^= 0xff;
+
+        let mut result = Ok(None);
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let index = u32::try_from(index).unwrap();
+            result = snapshot_restore_chunk(manifest.clone(), index, chunk).map(Some);
+        }
+        assert!(matches!(result, Err(IcarusError::ConfigurationError(_))));
+    }
+
+    thread_local! {
+        static COUNTER: RefCell<u64> = const { RefCell::new(0) };
+    }
+
+    fn export_counter() -> Result<Vec<u8>> {
+        Ok(COUNTER.with(|counter| counter.borrow().to_le_bytes().to_vec()))
+    }
+
+    fn validate_counter(bytes: &[u8]) -> Result<()> {
+        <[u8; 8]>::try_from(bytes)
+            .map(drop)
+            .map_err(|_| IcarusError::CandidError("counter needs 8 bytes".to_string()))
+    }
+
+    fn restore_counter(bytes: &[u8]) -> Result<()> {
+        validate_counter(bytes)?;
+        let value = u64::from_le_bytes(bytes.try_into().expect("validated above"));
+        COUNTER.with(|counter| *counter.borrow_mut() = value);
+        Ok(())
+    }
+
+    fn snapshot_of(sections: Vec<Section>) -> Vec<u8> {
+        Encode!(&sections).unwrap()
+    }
+
+    #[test]
+    fn test_corrupt_later_section_changes_nothing() {
+        register_snapshot_source(SnapshotSource::new("notes", export_notes, restore_notes));
+        register_snapshot_source(
+            SnapshotSource::new("counter", export_counter, restore_counter)
+                .with_validate(validate_counter),
+        );
+        NOTES.with(|notes| *notes.borrow_mut() = b"before".to_vec());
+        COUNTER.with(|counter| *counter.borrow_mut() = 7);
+
+        let corrupt = snapshot_of(vec![
+            Section {
+                name: "notes".to_string(),
+                data: b"after".to_vec(),
+            },
+            Section {
+                name: "counter".to_string(),
+                data: vec![1, 2, 3],
+            },
+        ]);
+        assert!(apply(&corrupt).is_err());
+        assert_eq!(NOTES.with(|notes| notes.borrow().clone()), b"before");
+        assert_eq!(COUNTER.with(|counter| *counter.borrow()), 7);
+
+        // Without a validator the failed restore is rolled back instead
+        register_snapshot_source(SnapshotSource::new(
+            "counter",
+            export_counter,
+            restore_counter,
+        ));
+        assert!(apply(&corrupt).is_err());
+        assert_eq!(NOTES.with(|notes| notes.borrow().clone()), b"before");
+        assert_eq!(COUNTER.with(|counter| *counter.borrow()), 7);
+
+        let valid = snapshot_of(vec![
+            Section {
+                name: "notes".to_string(),
+                data: b"after".to_vec(),
+            },
+            Section {
+                name: "counter".to_string(),
+                data: 9_u64.to_le_bytes().to_vec(),
+            },
+        ]);
+        apply(&valid).unwrap();
+        assert_eq!(NOTES.with(|notes| notes.borrow().clone()), b"after");
+        assert_eq!(COUNTER.with(|counter| *counter.borrow()), 9);
+    }
+
+    #[test]
+    fn test_invalid_requests() {
+        assert!(snapshot_begin(Some(0)).is_err());
+        assert!(snapshot_begin(Some(MAX_CHUNK_SIZE + 1)).is_err());
+
+        let manifest = snapshot_begin(None).unwrap();
+        assert!(snapshot_chunk(manifest.snapshot_id + 1, 0).is_err());
+        assert!(snapshot_chunk(manifest.snapshot_id, manifest.chunk_count).is_err());
+    }
+}
@@ -0,0 +1,335 @@
+//! Clock access, date boundaries and RFC 3339 text.
+//!
+//! [`now_nanos`] reads the clock of the [`host`](crate::host): the
+//! canister's inside a canister (`ic-canister` feature), the system clock
+//! off-chain. Off-chain a thread-local override installed with
+//! [`set_time_override`] takes precedence, which is how `icarus-test`'s
+//! `MockEnvironment` makes time-dependent tools deterministic.
+//!
+//! [`now`] returns the same instant as a [`Timestamp`], which formats and
+//! parses RFC 3339 and finds the start of its day, week or month in a
+//! [`Zone`], so tools need no nanosecond arithmetic of their own. Durations
+//! are plain [`std::time::Duration`]s; [`days`], [`hours`] and [`minutes`]
+//! build them and [`format_duration`] prints them compactly.
+//!
+//! Tools that read the clock through [`now_nanos`] or [`now`] can be tested
+//! without sleeping.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::calendar::Zone;
+//! use icarus_core::{time, Timestamp};
+//!
+//! let at: Timestamp = "2024-03-14T15:09:26Z".parse().unwrap();
+//! let day = at.start_of_day(&Zone::utc());
+//! assert_eq!(day.to_rfc3339(), "2024-03-14T00:00:00Z");
+//! assert_eq!(at.duration_since(day), time::hours(15) + time::minutes(9) + std::time::Duration::from_secs(26));
+//! assert_eq!(time::format_duration(at.duration_since(day)), "15h 9m 26s");
+//!
+//! // Weeks start on Monday
+//! assert_eq!(at.start_of_week(&Zone::utc()).to_rfc3339(), "2024-03-11T00:00:00Z");
+//! ```
+
+#[cfg(not(feature = "ic-canister"))]
+use std::cell::Cell;
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, NaiveDateTime, SecondsFormat, Utc};
+
+use crate::calendar::Zone;
+use crate::{IcarusError, Result, Timestamp};
+
+/// Nanoseconds in a second.
+pub const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// Nanoseconds in a day.
+pub const NANOS_PER_DAY: u64 = 24 * 60 * 60 * NANOS_PER_SECOND;
+
+#[cfg(not(feature = "ic-canister"))]
+thread_local! {
+    static TIME_OVERRIDE: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Returns the current time in nanoseconds since the Unix epoch.
+///
+/// # Panics
+///
+/// May panic if system time is before Unix epoch (extremely unlikely).
+#[must_use]
+#[inline]
+pub fn now_nanos() -> u64 {
+    #[cfg(not(feature = "ic-canister"))]
+    if let Some(nanos) = TIME_OVERRIDE.with(Cell::get) {
+        return nanos;
+    }
+    crate::host::host().time()
+}
+
+/// Returns the current time.
+#[must_use]
+#[inline]
+pub fn now() -> Timestamp {
+    Timestamp::from_nanos(now_nanos())
+}
+
+/// Pins the clock for the current thread, or restores the system clock with `None`.
+#[cfg(not(feature = "ic-canister"))]
+pub fn set_time_override(nanos: Option<u64>) {
+    TIME_OVERRIDE.with(|cell| cell.set(nanos));
+}
+
+/// Returns the override installed on the current thread, if any.
+#[cfg(not(feature = "ic-canister"))]
+#[must_use]
+pub fn time_override() -> Option<u64> {
+    TIME_OVERRIDE.with(Cell::get)
+}
+
+/// Returns a duration of `n` days.
+#[must_use]
+pub const fn days(n: u64) -> Duration {
+    Duration::from_secs(n * 24 * 60 * 60)
+}
+
+/// Returns a duration of `n` hours.
+#[must_use]
+pub const fn hours(n: u64) -> Duration {
+    Duration::from_secs(n * 60 * 60)
+}
+
+/// Returns a duration of `n` minutes.
+#[must_use]
+pub const fn minutes(n: u64) -> Duration {
+    Duration::from_secs(n * 60)
+}
+
+/// Formats `duration` as days, hours, minutes and seconds, leaving out zero
+/// units, e.g. `2d 3h 5s`. Durations under a second print in milliseconds.
+#[must_use]
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs == 0 {
+        return format!("{}ms", duration.as_millis());
+    }
+
+    let units = [
+        (secs / 86_400, 'd'),
+        (secs / 3600 % 24, 'h'),
+        (secs / 60 % 60, 'm'),
+        (secs % 60, 's'),
+    ];
+    let mut text = String::new();
+    for (value, unit) in units.into_iter().filter(|(value, _)| *value > 0) {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        let _ = write!(text, "{value}{unit}");
+    }
+    text
+}
+
+impl Timestamp {
+    /// Returns the timestamp as a UTC date and time.
+    #[must_use]
+    pub fn to_datetime(self) -> DateTime<Utc> {
+        DateTime::from_timestamp_nanos(i64::try_from(self.as_nanos()).unwrap_or(i64::MAX))
+    }
+
+    /// Returns the timestamp of a date and time.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::ConfigurationError` for times before the Unix
+    /// epoch or after 2262, which nanoseconds in a `u64` cannot hold.
+    pub fn from_datetime<Tz: chrono::TimeZone>(datetime: &DateTime<Tz>) -> Result<Self> {
+        datetime
+            .timestamp_nanos_opt()
+            .and_then(|nanos| u64::try_from(nanos).ok())
+            .map(Self::from_nanos)
+            .ok_or_else(|| invalid_time(&datetime.to_rfc3339(), "is outside 1970-2262"))
+    }
+
+    /// Formats the timestamp as RFC 3339 in UTC, with as many fractional
+    /// digits as it needs, e.g. `2024-03-14T15:09:26.500Z`.
+    #[must_use]
+    pub fn to_rfc3339(self) -> String {
+        self.to_datetime()
+            .to_rfc3339_opts(SecondsFormat::AutoSi, true)
+    }
+
+    /// Parses an RFC 3339 date and time with any offset.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::ConfigurationError` if `text` is not RFC 3339 or
+    /// is outside the range of [`Timestamp`].
+    pub fn parse_rfc3339(text: &str) -> Result<Self> {
+        let datetime = DateTime::parse_from_rfc3339(text)
+            .map_err(|e| invalid_time(text, &format!("is not RFC 3339: {e}")))?;
+        Self::from_datetime(&datetime)
+    }
+
+    /// Returns the timestamp `duration` later, or `None` past 2554.
+    #[must_use]
+    pub fn checked_add(self, duration: Duration) -> Option<Self> {
+        u64::try_from(duration.as_nanos())
+            .ok()
+            .and_then(|nanos| self.as_nanos().checked_add(nanos))
+            .map(Self::from_nanos)
+    }
+
+    /// Returns the timestamp `duration` earlier, or `None` before the epoch.
+    #[must_use]
+    pub fn checked_sub(self, duration: Duration) -> Option<Self> {
+        u64::try_from(duration.as_nanos())
+            .ok()
+            .and_then(|nanos| self.as_nanos().checked_sub(nanos))
+            .map(Self::from_nanos)
+    }
+
+    /// Returns the time from `earlier` to this timestamp, or zero if
+    /// `earlier` is later.
+    #[must_use]
+    pub fn duration_since(self, earlier: Self) -> Duration {
+        Duration::from_nanos(self.as_nanos().saturating_sub(earlier.as_nanos()))
+    }
+
+    /// Returns the time since this timestamp.
+    #[must_use]
+    pub fn elapsed(self) -> Duration {
+        now().duration_since(self)
+    }
+
+    /// Returns midnight at the start of this timestamp's day in `zone`.
+    #[must_use]
+    pub fn start_of_day(self, zone: &Zone) -> Self {
+        self.boundary(zone, |local| local.date())
+    }
+
+    /// Returns midnight at the start of this timestamp's week in `zone`.
+    /// Weeks start on Monday.
+    #[must_use]
+    pub fn start_of_week(self, zone: &Zone) -> Self {
+        self.boundary(zone, |local| {
+            let date = local.date();
+            date - chrono::Days::new(u64::from(date.weekday().num_days_from_monday()))
+        })
+    }
+
+    /// Returns midnight at the start of this timestamp's month in `zone`.
+    #[must_use]
+    pub fn start_of_month(self, zone: &Zone) -> Self {
+        self.boundary(zone, |local| {
+            let date = local.date();
+            date.with_day(1).unwrap_or(date)
+        })
+    }
+
+    /// Returns whether both timestamps fall on the same day in `zone`.
+    #[must_use]
+    pub fn is_same_day(self, other: Self, zone: &Zone) -> bool {
+        self.start_of_day(zone) == other.start_of_day(zone)
+    }
+
+    /// Returns midnight of the date `start` picks from the local time in
+    /// `zone`, as a UTC timestamp.
+    fn boundary(self, zone: &Zone, start: impl FnOnce(NaiveDateTime) -> chrono::NaiveDate) -> Self {
+        let offset = i64::from(zone.offset_minutes()) * 60_000_000_000;
+        let nanos = i64::try_from(self.as_nanos()).unwrap_or(i64::MAX);
+        let local = DateTime::from_timestamp_nanos(nanos.saturating_add(offset)).naive_utc();
+        let midnight = start(local).and_hms_opt(0, 0, 0).map_or(0, |midnight| {
+            midnight.and_utc().timestamp_nanos_opt().unwrap_or(i64::MAX)
+        });
+        Self::from_nanos(u64::try_from(midnight.saturating_sub(offset)).unwrap_or(0))
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = IcarusError;
+
+    fn from_str(text: &str) -> Result<Self> {
+        Self::parse_rfc3339(text)
+    }
+}
+
+fn invalid_time(text: &str, problem: &str) -> IcarusError {
+    IcarusError::ConfigurationError(format!("time '{text}' {problem}"))
+}
+
+#[cfg(all(test, not(feature = "ic-canister")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_override_pins_clock() {
+        set_time_override(Some(42));
+        assert_eq!(now_nanos(), 42);
+        assert_eq!(time_override(), Some(42));
+
+        set_time_override(None);
+        assert!(now_nanos() > 42);
+        assert_eq!(time_override(), None);
+    }
+
+    #[test]
+    fn test_rfc3339_round_trip() {
+        let at = Timestamp::parse_rfc3339("2024-02-29T23:30:00.25+02:00").unwrap();
+        assert_eq!(at.to_rfc3339(), "2024-02-29T21:30:00.250Z");
+        assert_eq!(at.to_rfc3339().parse::<Timestamp>().unwrap(), at);
+        assert_eq!(
+            Timestamp::from_nanos(0).to_rfc3339(),
+            "1970-01-01T00:00:00Z"
+        );
+
+        assert!(Timestamp::parse_rfc3339("yesterday").is_err());
+        assert!(Timestamp::parse_rfc3339("1969-12-31T23:59:59Z").is_err());
+    }
+
+    #[test]
+    fn test_boundaries_follow_the_zone() {
+        let utc = Zone::utc();
+        let tokyo: Zone = "Asia/Tokyo".parse().unwrap();
+        // Sunday 2024-03-31 20:00 UTC is Monday 05:00 in Tokyo
+        let at: Timestamp = "2024-03-31T20:00:00Z".parse().unwrap();
+
+        assert_eq!(at.start_of_day(&utc).to_rfc3339(), "2024-03-31T00:00:00Z");
+        assert_eq!(at.start_of_day(&tokyo).to_rfc3339(), "2024-03-31T15:00:00Z");
+        assert_eq!(at.start_of_week(&utc).to_rfc3339(), "2024-03-25T00:00:00Z");
+        assert_eq!(
+            at.start_of_week(&tokyo).to_rfc3339(),
+            "2024-03-31T15:00:00Z"
+        );
+        assert_eq!(at.start_of_month(&utc).to_rfc3339(), "2024-03-01T00:00:00Z");
+        assert_eq!(
+            at.start_of_month(&tokyo).to_rfc3339(),
+            "2024-03-31T15:00:00Z"
+        );
+
+        let later = at.checked_add(hours(5)).unwrap();
+        assert!(later.is_same_day(at, &tokyo));
+        assert!(!later.is_same_day(at, &utc));
+        assert_eq!(later.checked_sub(hours(5)), Some(at));
+        assert_eq!(Timestamp::from_nanos(0).checked_sub(minutes(1)), None);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(
+            format_duration(days(2) + hours(3) + Duration::from_secs(5)),
+            "2d 3h 5s"
+        );
+        assert_eq!(format_duration(minutes(90)), "1h 30m");
+        assert_eq!(format_duration(Duration::from_millis(250)), "250ms");
+    }
+
+    #[test]
+    fn test_now_follows_the_override() {
+        set_time_override(Some(NANOS_PER_DAY));
+        assert_eq!(now().to_rfc3339(), "1970-01-02T00:00:00Z");
+        assert_eq!(now().elapsed(), Duration::ZERO);
+        set_time_override(None);
+    }
+}
@@ -15,6 +15,10 @@ use serde::Serialize;
 
 use crate::{IcarusError, ToolId};
 
+/// Input schema key holding how many seconds the bridge may cache results of
+/// a read-only tool, set by `#[tool(cache_ttl_secs = ..)]`.
+pub const CACHE_TTL_SCHEMA_KEY: &str = "x-icarus-cache-ttl";
+
 /// Annotations for tools to support RMCP compatibility and authentication levels.
 ///
 /// These annotations provide additional metadata for tools, including localization hints,
@@ -40,7 +44,7 @@ pub struct ToolAnnotations {
     /// Hint that this tool primarily performs read-only operations.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub read_only_hint: Option<bool>,
-    /// Authentication level required: "none" (public), "user", or "admin".
+    /// Authentication level required: "none" (public), "guest", "user", or "admin".
     #[serde(skip_serializing_if = "Option::is_none")]
     pub auth_level: Option<String>,
 }
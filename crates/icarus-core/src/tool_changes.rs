@@ -0,0 +1,81 @@
+//! A persistent sequence number for tool list changes.
+//!
+//! The sequence starts at 0 and increases by one each time the canister sees
+//! a tool list fingerprint that differs from the last one it recorded,
+//! typically after an upgrade added, removed or changed a tool. It is kept in
+//! stable memory so it keeps increasing across upgrades.
+//!
+//! `mcp!{}` generates an `mcp_tools_sequence` query and reports the sequence
+//! in `mcp_server_info`. The bridge polls the query and sends MCP
+//! `notifications/tools/list_changed` to connected clients when the number
+//! changes. Queries cannot persist state, so [`record`] also runs on every
+//! `mcp_call_tool`; until then a query reports the sequence the next update
+//! will store.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::tool_changes;
+//!
+//! let first = tool_changes::record(0x1234);
+//! assert_eq!(tool_changes::record(0x1234), first);
+//!
+//! // An upgrade changed the tools
+//! assert_eq!(tool_changes::record(0xabcd), first + 1);
+//! assert_eq!(tool_changes::sequence(), first + 1);
+//! ```
+
+use std::cell::RefCell;
+
+use ic_stable_structures::StableBTreeMap;
+
+use crate::memory::{self, StableMemory, TOOL_CHANGES_MEMORY_ID};
+
+const SEQUENCE_KEY: u8 = 0;
+const FINGERPRINT_KEY: u8 = 1;
+
+thread_local! {
+    static STATE: RefCell<StableBTreeMap<u8, u64, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(TOOL_CHANGES_MEMORY_ID))
+    );
+}
+
+/// Records the current tool list fingerprint and returns the sequence number.
+///
+/// The first fingerprint recorded starts the sequence at 0; each different
+/// fingerprint after that increments it.
+pub fn record(fingerprint: u64) -> u64 {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let sequence = match state.get(&FINGERPRINT_KEY) {
+            Some(last) if last == fingerprint => return state.get(&SEQUENCE_KEY).unwrap_or(0),
+            Some(_) => state.get(&SEQUENCE_KEY).unwrap_or(0) + 1,
+            None => 0,
+        };
+        state.insert(SEQUENCE_KEY, sequence);
+        state.insert(FINGERPRINT_KEY, fingerprint);
+        sequence
+    })
+}
+
+/// Returns the last recorded sequence number, 0 before any was recorded.
+#[must_use]
+pub fn sequence() -> u64 {
+    STATE.with(|state| state.borrow().get(&SEQUENCE_KEY).unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_increments_on_each_change() {
+        let start = record(1);
+        assert_eq!(record(1), start);
+        assert_eq!(record(2), start + 1);
+        assert_eq!(record(2), start + 1);
+        // Going back to an earlier tool list is still a change
+        assert_eq!(record(1), start + 2);
+        assert_eq!(sequence(), start + 2);
+    }
+}
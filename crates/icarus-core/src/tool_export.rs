@@ -0,0 +1,241 @@
+//! Tool declarations for other agent ecosystems.
+//!
+//! MCP clients read tools from `mcp_list_tools`. Other frameworks declare
+//! tools in their own JSON formats, which [`export`] produces from the same
+//! tool list:
+//!
+//! - [`ToolFormat::OpenAi`]: the `tools` array of OpenAI function calling,
+//!   one `{"type": "function", "function": {...}}` entry per tool
+//! - [`ToolFormat::Gemini`]: a Gemini `{"functionDeclarations": [...]}` tool,
+//!   with the input schemas reduced to the OpenAPI subset Gemini accepts
+//!
+//! `icarus export tools --format openai|gemini` writes these for a deployed
+//! canister.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use icarus_core::{tool_export::{self, ToolFormat}, Tool};
+//!
+//! let tool = Tool::new("add", "Adds two numbers", Arc::new(serde_json::Map::new()));
+//!
+//! let openai = tool_export::export(&[tool.clone()], ToolFormat::OpenAi);
+//! assert_eq!(openai[0]["function"]["name"], "add");
+//!
+//! let gemini = tool_export::export(&[tool], "gemini".parse().unwrap());
+//! assert_eq!(gemini["functionDeclarations"][0]["name"], "add");
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::{json, Map, Value};
+
+use crate::{IcarusError, Tool};
+
+/// Schema keywords Gemini accepts; others are dropped.
+const GEMINI_KEYWORDS: &[&str] = &[
+    "type",
+    "format",
+    "title",
+    "description",
+    "nullable",
+    "enum",
+    "properties",
+    "required",
+    "items",
+    "minItems",
+    "maxItems",
+    "minimum",
+    "maximum",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "anyOf",
+    "default",
+];
+
+/// A tool declaration format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolFormat {
+    /// OpenAI function calling
+    OpenAi,
+    /// Gemini function declarations
+    Gemini,
+}
+
+impl FromStr for ToolFormat {
+    type Err = IcarusError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "openai" => Ok(Self::OpenAi),
+            "gemini" => Ok(Self::Gemini),
+            _ => Err(IcarusError::ConfigurationError(format!(
+                "Unknown tool format: {s}. Must be 'openai' or 'gemini'"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for ToolFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::OpenAi => "openai",
+            Self::Gemini => "gemini",
+        })
+    }
+}
+
+/// Returns the declarations of `tools` in `format`.
+#[must_use]
+pub fn export(tools: &[Tool], format: ToolFormat) -> Value {
+    match format {
+        ToolFormat::OpenAi => Value::Array(tools.iter().map(openai_function).collect()),
+        ToolFormat::Gemini => json!({
+            "functionDeclarations": tools.iter().map(gemini_declaration).collect::<Vec<_>>()
+        }),
+    }
+}
+
+/// Returns a tool's input schema, with an empty object schema for tools
+/// without parameters.
+fn parameters(tool: &Tool) -> Map<String, Value> {
+    let mut schema = tool.input_schema.as_ref().clone();
+    schema.remove("$schema");
+    if schema.is_empty() {
+        schema.insert("type".to_string(), json!("object"));
+        schema.insert("properties".to_string(), json!({}));
+    }
+    schema
+}
+
+fn openai_function(tool: &Tool) -> Value {
+    json!({
+        "type": "function",
+        "function": {
+            "name": tool.name,
+            "description": tool.description.as_deref().unwrap_or_default(),
+            "parameters": parameters(tool)
+        }
+    })
+}
+
+fn gemini_declaration(tool: &Tool) -> Value {
+    let mut declaration = json!({
+        "name": tool.name,
+        "description": tool.description.as_deref().unwrap_or_default()
+    });
+    let parameters = parameters(tool);
+    // Gemini rejects object schemas without properties
+    let has_properties = parameters
+        .get("properties")
+        .and_then(Value::as_object)
+        .is_some_and(|properties| !properties.is_empty());
+    if has_properties {
+        declaration["parameters"] = gemini_schema(Value::Object(parameters));
+    }
+    declaration
+}
+
+/// Reduces a JSON Schema to the keywords Gemini accepts, turning
+/// `"type": [T, "null"]` into `"type": T, "nullable": true`.
+fn gemini_schema(schema: Value) -> Value {
+    let Value::Object(schema) = schema else {
+        return schema;
+    };
+
+    let mut reduced = Map::new();
+    for (keyword, value) in schema {
+        if !GEMINI_KEYWORDS.contains(&keyword.as_str()) {
+            continue;
+        }
+        let value = match (keyword.as_str(), value) {
+            ("type", Value::Array(types)) => {
+                if types.iter().any(|t| t == "null") {
+                    reduced.insert("nullable".to_string(), Value::Bool(true));
+                }
+                types
+                    .into_iter()
+                    .find(|t| t != "null")
+                    .unwrap_or_else(|| json!("string"))
+            }
+            ("properties", Value::Object(properties)) => Value::Object(
+                properties
+                    .into_iter()
+                    .map(|(name, schema)| (name, gemini_schema(schema)))
+                    .collect(),
+            ),
+            ("items", items) => gemini_schema(items),
+            ("anyOf", Value::Array(schemas)) => {
+                Value::Array(schemas.into_iter().map(gemini_schema).collect())
+            }
+            (_, value) => value,
+        };
+        reduced.insert(keyword, value);
+    }
+    Value::Object(reduced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn search_tool() -> Tool {
+        let Value::Object(schema) = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "Search terms" },
+                "limit": { "type": ["integer", "null"], "minimum": 1 },
+                "tags": { "type": "array", "items": { "type": "string", "examples": ["a"] } }
+            },
+            "required": ["query"],
+            "additionalProperties": false
+        }) else {
+            unreachable!()
+        };
+        Tool::new("search", "Searches notes", Arc::new(schema))
+    }
+
+    #[test]
+    fn test_openai_keeps_the_schema() {
+        let exported = export(&[search_tool()], ToolFormat::OpenAi);
+        let function = &exported[0]["function"];
+        assert_eq!(exported[0]["type"], "function");
+        assert_eq!(function["description"], "Searches notes");
+        assert_eq!(function["parameters"]["additionalProperties"], false);
+        assert!(function["parameters"].get("$schema").is_none());
+
+        let ping = Tool::new("ping", "Pings", Arc::new(Map::new()));
+        let exported = export(&[ping], ToolFormat::OpenAi);
+        assert_eq!(exported[0]["function"]["parameters"]["type"], "object");
+    }
+
+    #[test]
+    fn test_gemini_reduces_the_schema() {
+        let exported = export(&[search_tool()], ToolFormat::Gemini);
+        let parameters = &exported["functionDeclarations"][0]["parameters"];
+        assert!(parameters.get("additionalProperties").is_none());
+        assert_eq!(parameters["properties"]["limit"]["type"], "integer");
+        assert_eq!(parameters["properties"]["limit"]["nullable"], true);
+        assert!(parameters["properties"]["tags"]["items"]
+            .get("examples")
+            .is_none());
+
+        let ping = Tool::new("ping", "Pings", Arc::new(Map::new()));
+        let exported = export(&[ping], ToolFormat::Gemini);
+        assert!(exported["functionDeclarations"][0]
+            .get("parameters")
+            .is_none());
+    }
+
+    #[test]
+    fn test_format_parsing() {
+        assert_eq!("OpenAI".parse::<ToolFormat>().unwrap(), ToolFormat::OpenAi);
+        assert_eq!(ToolFormat::Gemini.to_string(), "gemini");
+        assert!("claude".parse::<ToolFormat>().is_err());
+    }
+}
@@ -0,0 +1,183 @@
+//! Owner switches that take tools out of service without a redeploy.
+//!
+//! A disabled tool is hidden from the tool list and calls to it are rejected
+//! before they reach the tool. The set of disabled tools lives in stable
+//! memory, so a switch stays in effect across upgrades, and every change is
+//! appended to an audit log recording who made it and when.
+//!
+//! `mcp!{}` generates the admin endpoints `set_tool_enabled` and
+//! `get_tool_audit_log`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use candid::Principal;
+//! use icarus_core::tool_switches;
+//!
+//! let owner = Principal::anonymous();
+//! assert!(tool_switches::set_enabled("send_email", false, owner));
+//! assert!(!tool_switches::is_enabled("send_email"));
+//!
+//! // Switching to the current state records nothing
+//! assert!(!tool_switches::set_enabled("send_email", false, owner));
+//!
+//! tool_switches::set_enabled("send_email", true, owner);
+//! let log = tool_switches::audit_log(10);
+//! assert!(log[0].enabled);
+//! assert!(!log[1].enabled);
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+
+use crate::memory::{self, StableMemory, TOOL_AUDIT_MEMORY_ID, TOOL_SWITCHES_MEMORY_ID};
+
+/// Audit entries kept; older ones are dropped.
+pub const MAX_AUDIT_ENTRIES: u64 = 1_000;
+
+/// One enable or disable change.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct ToolSwitchChange {
+    /// Tool name as registered.
+    pub tool: String,
+    /// State after the change.
+    pub enabled: bool,
+    /// Principal that made the change.
+    pub changed_by: Principal,
+    /// Change time in nanoseconds since the epoch.
+    pub changed_at: u64,
+}
+
+impl Storable for ToolSwitchChange {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap_or_default())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap_or_else(|e| unreachable!("corrupt tool audit entry: {e}"))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).unwrap_or_default()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+thread_local! {
+    /// Disabled tools and when they were disabled.
+    static DISABLED: RefCell<StableBTreeMap<String, u64, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(TOOL_SWITCHES_MEMORY_ID))
+    );
+
+    static AUDIT: RefCell<StableBTreeMap<u64, ToolSwitchChange, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(TOOL_AUDIT_MEMORY_ID))
+    );
+}
+
+/// Enables or disables `tool` and returns whether its state changed.
+///
+/// Only changes are written to the audit log.
+pub fn set_enabled(tool: &str, enabled: bool, changed_by: Principal) -> bool {
+    let changed_at = crate::time::now_nanos();
+    let changed = DISABLED.with(|disabled| {
+        let mut disabled = disabled.borrow_mut();
+        if enabled {
+            disabled.remove(&tool.to_string()).is_some()
+        } else {
+            disabled.insert(tool.to_string(), changed_at).is_none()
+        }
+    });
+
+    if changed {
+        AUDIT.with(|audit| {
+            let mut audit = audit.borrow_mut();
+            let next = audit.last_key_value().map_or(0, |(id, _)| id + 1);
+            audit.insert(
+                next,
+                ToolSwitchChange {
+                    tool: tool.to_string(),
+                    enabled,
+                    changed_by,
+                    changed_at,
+                },
+            );
+            while audit.len() > MAX_AUDIT_ENTRIES {
+                let Some((oldest, _)) = audit.first_key_value() else {
+                    break;
+                };
+                audit.remove(&oldest);
+            }
+        });
+    }
+    changed
+}
+
+/// Returns whether `tool` may be listed and called.
+#[must_use]
+pub fn is_enabled(tool: &str) -> bool {
+    DISABLED.with(|disabled| !disabled.borrow().contains_key(&tool.to_string()))
+}
+
+/// Returns the disabled tools, sorted by name.
+#[must_use]
+pub fn disabled_tools() -> Vec<String> {
+    DISABLED.with(|disabled| {
+        disabled
+            .borrow()
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect()
+    })
+}
+
+/// Returns up to `limit` audit entries, newest first.
+#[must_use]
+pub fn audit_log(limit: usize) -> Vec<ToolSwitchChange> {
+    AUDIT.with(|audit| {
+        let audit = audit.borrow();
+        // Ids are contiguous since trimming only removes the oldest
+        let Some((last, _)) = audit.last_key_value() else {
+            return Vec::new();
+        };
+        let limit = u64::try_from(limit).unwrap_or(u64::MAX);
+        let mut entries: Vec<_> = audit
+            .range((last + 1).saturating_sub(limit)..)
+            .map(|entry| entry.value())
+            .collect();
+        entries.reverse();
+        entries
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switches_are_audited() {
+        let admin = Principal::from_slice(&[7]);
+        assert!(is_enabled("flaky_tool"));
+
+        assert!(set_enabled("flaky_tool", false, admin));
+        assert!(!is_enabled("flaky_tool"));
+        assert!(disabled_tools().contains(&"flaky_tool".to_string()));
+        assert!(!set_enabled("flaky_tool", false, admin));
+
+        assert!(set_enabled("flaky_tool", true, admin));
+        assert!(is_enabled("flaky_tool"));
+
+        let log: Vec<_> = audit_log(usize::MAX)
+            .into_iter()
+            .filter(|change| change.tool == "flaky_tool")
+            .collect();
+        assert_eq!(log.len(), 2);
+        assert!(log[0].enabled);
+        assert!(!log[1].enabled);
+        assert_eq!(log[1].changed_by, admin);
+    }
+}
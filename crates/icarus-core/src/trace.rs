@@ -0,0 +1,286 @@
+//! W3C trace context propagation.
+//!
+//! The bridge accepts a `traceparent` from the MCP client (or starts a new
+//! trace) and forwards it to the canister in the request's
+//! `params._meta.traceparent`. `mcp_call_tool` installs it with
+//! [`set_current`] while the tool runs, and [`crate::logging`] prefixes every
+//! entry with the trace id, so one conversation turn can be followed from the
+//! client through the bridge into canister logs.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::trace::TraceContext;
+//!
+//! let parent: TraceContext = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+//!     .parse()
+//!     .unwrap();
+//! assert_eq!(parent.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+//!
+//! // A child span keeps the trace id and gets a new span id
+//! let child = parent.child();
+//! assert_eq!(child.trace_id(), parent.trace_id());
+//! assert_ne!(child.span_id(), parent.span_id());
+//! ```
+
+use std::cell::Cell;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{IcarusError, Result};
+
+/// Key of the trace context in MCP `_meta` objects and HTTP headers.
+pub const TRACEPARENT: &str = "traceparent";
+
+const VERSION: u8 = 0;
+const FLAG_SAMPLED: u8 = 0x01;
+
+thread_local! {
+    static CURRENT: Cell<Option<TraceContext>> = const { Cell::new(None) };
+    static ID_STATE: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A parsed `traceparent`: trace id, parent span id and trace flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    flags: u8,
+}
+
+impl TraceContext {
+    /// Creates a sampled context.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::InvalidTraceContext` if either id is zero, which
+    /// the W3C specification reserves as invalid.
+    pub fn new(trace_id: u128, span_id: u64) -> Result<Self> {
+        if trace_id == 0 || span_id == 0 {
+            return Err(IcarusError::InvalidTraceContext(
+                "trace and span ids must be non-zero".to_string(),
+            ));
+        }
+        Ok(Self {
+            trace_id,
+            span_id,
+            flags: FLAG_SAMPLED,
+        })
+    }
+
+    /// Starts a new trace with generated ids.
+    ///
+    /// Ids are unique per process but not cryptographically random.
+    #[must_use]
+    pub fn generate() -> Self {
+        let trace_id = (u128::from(next_id()) << 64) | u128::from(next_id());
+        Self {
+            trace_id,
+            span_id: next_id(),
+            flags: FLAG_SAMPLED,
+        }
+    }
+
+    /// Returns a context for a child span in the same trace.
+    #[must_use]
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: next_id(),
+            ..*self
+        }
+    }
+
+    /// Returns the 128-bit trace id.
+    #[must_use]
+    #[inline]
+    pub const fn trace_id(&self) -> u128 {
+        self.trace_id
+    }
+
+    /// Returns the 64-bit span id.
+    #[must_use]
+    #[inline]
+    pub const fn span_id(&self) -> u64 {
+        self.span_id
+    }
+
+    /// Returns whether the caller sampled this trace.
+    #[must_use]
+    #[inline]
+    pub const fn is_sampled(&self) -> bool {
+        self.flags & FLAG_SAMPLED != 0
+    }
+
+    /// Returns the trace id as 32 lowercase hex digits.
+    #[must_use]
+    pub fn trace_id_hex(&self) -> String {
+        format!("{:032x}", self.trace_id)
+    }
+
+    /// Returns the span id as 16 lowercase hex digits.
+    #[must_use]
+    pub fn span_id_hex(&self) -> String {
+        format!("{:016x}", self.span_id)
+    }
+
+    /// Reads `_meta.traceparent` from JSON-RPC request params.
+    ///
+    /// Missing or malformed values yield `None`; a bad header from a client
+    /// should not fail the call.
+    #[must_use]
+    pub fn from_meta(params: &serde_json::Value) -> Option<Self> {
+        params
+            .get("_meta")?
+            .get(TRACEPARENT)?
+            .as_str()?
+            .parse()
+            .ok()
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{VERSION:02x}-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.span_id, self.flags
+        )
+    }
+}
+
+impl FromStr for TraceContext {
+    type Err = IcarusError;
+
+    fn from_str(traceparent: &str) -> Result<Self> {
+        let invalid =
+            |reason: &str| IcarusError::InvalidTraceContext(format!("{reason} in '{traceparent}'"));
+
+        let mut parts = traceparent.trim().split('-');
+        let version = parse_hex_field(parts.next(), 2).ok_or_else(|| invalid("bad version"))?;
+        let trace_id = parse_hex_field(parts.next(), 32).ok_or_else(|| invalid("bad trace id"))?;
+        let span_id = parse_hex_field(parts.next(), 16).ok_or_else(|| invalid("bad parent id"))?;
+        let flags = parse_hex_field(parts.next(), 2).ok_or_else(|| invalid("bad flags"))?;
+
+        // Version 0xff is forbidden; version 0 has exactly four fields, while
+        // later versions may append more.
+        if version == 0xff {
+            return Err(invalid("reserved version"));
+        }
+        if version == u128::from(VERSION) && parts.next().is_some() {
+            return Err(invalid("trailing data"));
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        let mut context = Self::new(trace_id, span_id as u64)?;
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            context.flags = flags as u8;
+        }
+        Ok(context)
+    }
+}
+
+/// Parses a fixed-width lowercase hex field.
+fn parse_hex_field(field: Option<&str>, width: usize) -> Option<u128> {
+    let field = field?;
+    if field.len() != width
+        || !field
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    {
+        return None;
+    }
+    u128::from_str_radix(field, 16).ok()
+}
+
+/// Returns the trace context of the call being executed, if any.
+#[must_use]
+pub fn current() -> Option<TraceContext> {
+    CURRENT.with(Cell::get)
+}
+
+/// Installs the trace context of the call being executed, or clears it.
+///
+/// Canister calls interleave at `await` points, so set the context right
+/// before synchronous work and clear it afterwards.
+pub fn set_current(context: Option<TraceContext>) {
+    CURRENT.with(|current| current.set(context));
+}
+
+/// Returns a non-zero id from a splitmix64 sequence seeded by the clock.
+fn next_id() -> u64 {
+    ID_STATE.with(|state| {
+        let mut seed = state.get();
+        if seed == 0 {
+            seed = crate::time::now_nanos() | 1;
+        }
+        seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state.set(seed);
+
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        (z ^ (z >> 31)).max(1)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_round_trip() {
+        let context: TraceContext = SAMPLE.parse().unwrap();
+        assert!(context.is_sampled());
+        assert_eq!(context.span_id_hex(), "00f067aa0ba902b7");
+        assert_eq!(context.to_string(), SAMPLE);
+    }
+
+    #[test]
+    fn test_rejects_invalid_headers() {
+        for header in [
+            "",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "00-4bf92f3577b34da6-00f067aa0ba902b7-01",
+        ] {
+            assert!(header.parse::<TraceContext>().is_err(), "{header}");
+        }
+
+        // Future versions may carry extra fields
+        let future = "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-extra";
+        assert!(!future.parse::<TraceContext>().unwrap().is_sampled());
+    }
+
+    #[test]
+    fn test_from_meta() {
+        let params = serde_json::json!({ "name": "echo", "_meta": { "traceparent": SAMPLE } });
+        assert_eq!(
+            TraceContext::from_meta(&params).unwrap().to_string(),
+            SAMPLE
+        );
+        assert!(TraceContext::from_meta(&serde_json::json!({ "name": "echo" })).is_none());
+    }
+
+    #[test]
+    fn test_generated_ids_differ() {
+        let a = TraceContext::generate();
+        let b = TraceContext::generate();
+        assert_ne!(a.trace_id(), b.trace_id());
+        assert_ne!(a.child().span_id(), a.child().span_id());
+    }
+
+    #[test]
+    fn test_current_context() {
+        let context = TraceContext::generate();
+        set_current(Some(context));
+        assert_eq!(current(), Some(context));
+        set_current(None);
+        assert_eq!(current(), None);
+    }
+}
@@ -0,0 +1,560 @@
+//! A stable-memory index of fixed-dimension vectors with top-k search by
+//! cosine similarity.
+//!
+//! Vectors are normalized when inserted, so similarity is a dot product.
+//! Search is an exact scan of every vector until [`VectorIndex::partition`]
+//! clusters them IVF-style: vectors are assigned to the nearest of a number
+//! of centroids, and a search only scans the partitions whose centroids are
+//! nearest the query. That bounds the instructions a query costs at the price
+//! of possibly missing neighbours in partitions that were not probed; raise
+//! the probe count with [`VectorIndex::set_probes`] to trade back.
+//!
+//! Everything, including the dimension and centroids, lives in one
+//! `StableBTreeMap` in a versioned byte format, so the index survives
+//! upgrades unchanged. Reopening it with a different dimension is an error.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::memory::FIRST_USER_MEMORY_ID;
+//! use icarus_core::vector_index::VectorIndex;
+//!
+//! let index = VectorIndex::init(FIRST_USER_MEMORY_ID, 3).unwrap();
+//! index.insert(1, &[1.0, 0.0, 0.0]).unwrap();
+//! index.insert(2, &[0.0, 1.0, 0.0]).unwrap();
+//! index.insert(3, &[0.9, 0.1, 0.0]).unwrap();
+//!
+//! let hits = index.search(&[1.0, 0.0, 0.0], 2).unwrap();
+//! let ids: Vec<u64> = hits.iter().map(|hit| hit.id).collect();
+//! assert_eq!(ids, [1, 3]);
+//!
+//! index.remove(1);
+//! assert_eq!(index.search(&[1.0, 0.0, 0.0], 1).unwrap()[0].id, 3);
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Serialize;
+
+use crate::memory::{self, StableMemory};
+use crate::{IcarusError, Result};
+
+/// Largest accepted vector dimension.
+pub const MAX_DIMENSIONS: u32 = 4096;
+
+/// Partitions scanned per search unless changed with
+/// [`VectorIndex::set_probes`].
+pub const DEFAULT_PROBES: u32 = 4;
+
+/// k-means rounds [`VectorIndex::partition`] runs.
+const KMEANS_ITERATIONS: usize = 8;
+
+/// Version of the stored header; bump it when the layout changes.
+const FORMAT_VERSION: u8 = 1;
+
+/// A search result: a vector's id and its cosine similarity to the query.
+#[derive(Debug, Clone, Copy, PartialEq, CandidType, Serialize, Deserialize)]
+pub struct SearchHit {
+    /// Id the vector was inserted with
+    pub id: u64,
+    /// Cosine similarity, from -1 to 1
+    pub score: f32,
+}
+
+/// Keys of the index's single stable map.
+///
+/// `Member` keys sort by partition first, so one partition is one range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum IndexKey {
+    /// Dimension, probe count and centroids
+    Header,
+    /// A vector's values, filed under its partition
+    Member { partition: u32, id: u64 },
+    /// The partition a vector id is filed under
+    Location(u64),
+}
+
+const HEADER_TAG: u8 = 0;
+const MEMBER_TAG: u8 = 1;
+const LOCATION_TAG: u8 = 2;
+const KEY_SIZE: u32 = 13;
+
+impl Storable for IndexKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(self.into_bytes())
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        let partition = u32::from_be_bytes(bytes[1..5].try_into().unwrap_or_default());
+        let id = u64::from_be_bytes(bytes[5..13].try_into().unwrap_or_default());
+        match bytes[0] {
+            HEADER_TAG => Self::Header,
+            MEMBER_TAG => Self::Member { partition, id },
+            LOCATION_TAG => Self::Location(id),
+            _ => unreachable!("corrupt vector index key"),
+        }
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        // Big-endian, so byte order matches the derived `Ord`
+        let (tag, partition, id) = match self {
+            Self::Header => (HEADER_TAG, 0, 0),
+            Self::Member { partition, id } => (MEMBER_TAG, partition, id),
+            Self::Location(id) => (LOCATION_TAG, 0, id),
+        };
+        let mut bytes = Vec::with_capacity(KEY_SIZE as usize);
+        bytes.push(tag);
+        bytes.extend_from_slice(&partition.to_be_bytes());
+        bytes.extend_from_slice(&id.to_be_bytes());
+        bytes
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: KEY_SIZE,
+        is_fixed_size: true,
+    };
+}
+
+/// Settings stored under [`IndexKey::Header`].
+#[derive(Debug, Clone, PartialEq)]
+struct Header {
+    dimensions: u32,
+    probes: u32,
+    /// Unit-length centroids; empty while the index is flat
+    centroids: Vec<Vec<f32>>,
+}
+
+impl Header {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![FORMAT_VERSION];
+        bytes.extend_from_slice(&self.dimensions.to_le_bytes());
+        bytes.extend_from_slice(&self.probes.to_le_bytes());
+        for centroid in &self.centroids {
+            bytes.extend(encode_vector(centroid));
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.first() != Some(&FORMAT_VERSION) || bytes.len() < 9 {
+            return Err(IcarusError::SerializationError(
+                "Unsupported vector index format".to_string(),
+            ));
+        }
+        let dimensions = u32::from_le_bytes(bytes[1..5].try_into().unwrap_or_default());
+        if dimensions == 0 {
+            return Err(IcarusError::SerializationError(
+                "Corrupt vector index header".to_string(),
+            ));
+        }
+        let probes = u32::from_le_bytes(bytes[5..9].try_into().unwrap_or_default());
+        let centroids = bytes[9..]
+            .chunks_exact(dimensions as usize * 4)
+            .map(decode_vector)
+            .collect();
+        Ok(Self {
+            dimensions,
+            probes,
+            centroids,
+        })
+    }
+}
+
+/// Fixed-dimension vectors in stable memory with top-k cosine search.
+pub struct VectorIndex {
+    entries: RefCell<StableBTreeMap<IndexKey, Vec<u8>, StableMemory>>,
+    header: RefCell<Header>,
+}
+
+impl VectorIndex {
+    /// Opens the index stored in virtual memory `memory_id`, creating it
+    /// with `dimensions` if the memory is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::ConfigurationError` if `dimensions` is zero or
+    /// above [`MAX_DIMENSIONS`], or the stored index has a different
+    /// dimension, and `IcarusError::SerializationError` if the stored header
+    /// is not in a known format.
+    pub fn init(memory_id: u8, dimensions: u32) -> Result<Self> {
+        if dimensions == 0 || dimensions > MAX_DIMENSIONS {
+            return Err(IcarusError::ConfigurationError(format!(
+                "Vector dimension must be between 1 and {MAX_DIMENSIONS}, got {dimensions}"
+            )));
+        }
+
+        let mut entries: StableBTreeMap<IndexKey, Vec<u8>, StableMemory> =
+            StableBTreeMap::init(memory::get_memory(memory_id));
+        let header = match entries.get(&IndexKey::Header) {
+            Some(bytes) => {
+                let header = Header::decode(&bytes)?;
+                if header.dimensions != dimensions {
+                    return Err(IcarusError::ConfigurationError(format!(
+                        "Vector index in memory {memory_id} has {} dimensions, not {dimensions}",
+                        header.dimensions
+                    )));
+                }
+                header
+            }
+            None => {
+                let header = Header {
+                    dimensions,
+                    probes: DEFAULT_PROBES,
+                    centroids: Vec::new(),
+                };
+                entries.insert(IndexKey::Header, header.encode());
+                header
+            }
+        };
+
+        Ok(Self {
+            entries: RefCell::new(entries),
+            header: RefCell::new(header),
+        })
+    }
+
+    /// Returns the dimension of the indexed vectors.
+    #[must_use]
+    pub fn dimensions(&self) -> u32 {
+        self.header.borrow().dimensions
+    }
+
+    /// Returns the number of indexed vectors.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.entries.borrow().range(IndexKey::Location(0)..).count() as u64
+    }
+
+    /// Returns true if no vectors are indexed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries
+            .borrow()
+            .range(IndexKey::Location(0)..)
+            .next()
+            .is_none()
+    }
+
+    /// Returns the number of partitions, or zero while the index is flat.
+    #[must_use]
+    pub fn partitions(&self) -> u32 {
+        u32::try_from(self.header.borrow().centroids.len()).unwrap_or(u32::MAX)
+    }
+
+    /// Adds the vector for `id`, replacing any previous one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::ConfigurationError` if the vector has the wrong
+    /// dimension, or is zero or not finite.
+    pub fn insert(&self, id: u64, vector: &[f32]) -> Result<()> {
+        let vector = self.normalize(vector)?;
+        let partition = nearest(&self.header.borrow().centroids, &vector, 1)
+            .first()
+            .copied()
+            .unwrap_or(0);
+
+        self.remove(id);
+        let mut entries = self.entries.borrow_mut();
+        entries.insert(IndexKey::Member { partition, id }, encode_vector(&vector));
+        entries.insert(IndexKey::Location(id), partition.to_le_bytes().to_vec());
+        Ok(())
+    }
+
+    /// Removes the vector for `id`. Returns false if there was none.
+    pub fn remove(&self, id: u64) -> bool {
+        let mut entries = self.entries.borrow_mut();
+        let Some(partition) = entries.remove(&IndexKey::Location(id)) else {
+            return false;
+        };
+        let partition = u32::from_le_bytes(partition.try_into().unwrap_or_default());
+        entries.remove(&IndexKey::Member { partition, id });
+        true
+    }
+
+    /// Returns the stored, normalized vector for `id`.
+    #[must_use]
+    pub fn get(&self, id: u64) -> Option<Vec<f32>> {
+        let entries = self.entries.borrow();
+        let partition = entries.get(&IndexKey::Location(id))?;
+        let partition = u32::from_le_bytes(partition.try_into().ok()?);
+        entries
+            .get(&IndexKey::Member { partition, id })
+            .map(|bytes| decode_vector(&bytes))
+    }
+
+    /// Returns up to `k` vectors most similar to `query`, most similar first.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::ConfigurationError` if the query has the wrong
+    /// dimension, or is zero or not finite.
+    pub fn search(&self, query: &[f32], k: usize) -> Result<Vec<SearchHit>> {
+        let query = self.normalize(query)?;
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let header = self.header.borrow();
+        let partitions = if header.centroids.is_empty() {
+            vec![0]
+        } else {
+            nearest(&header.centroids, &query, header.probes as usize)
+        };
+
+        let entries = self.entries.borrow();
+        let mut hits: Vec<SearchHit> = Vec::new();
+        for partition in partitions {
+            let range = IndexKey::Member { partition, id: 0 }..=IndexKey::Member {
+                partition,
+                id: u64::MAX,
+            };
+            for entry in entries.range(range) {
+                let IndexKey::Member { id, .. } = *entry.key() else {
+                    continue;
+                };
+                let score = dot_encoded(&query, &entry.value());
+                hits.push(SearchHit { id, score });
+            }
+        }
+
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+        hits.truncate(k);
+        Ok(hits)
+    }
+
+    /// Sets how many partitions a search scans; at least one.
+    pub fn set_probes(&self, probes: u32) {
+        let mut header = self.header.borrow_mut();
+        header.probes = probes.max(1);
+        self.entries
+            .borrow_mut()
+            .insert(IndexKey::Header, header.encode());
+    }
+
+    /// Clusters the vectors into `partitions` partitions with k-means and
+    /// refiles every vector under its nearest centroid. Zero makes the index
+    /// flat again.
+    ///
+    /// This reads and rewrites the whole index in one message, so for large
+    /// indexes call it from a job or an admin tool while traffic is low.
+    /// Vectors inserted later are filed under their nearest centroid without
+    /// moving the centroids; partition again after large changes.
+    pub fn partition(&self, partitions: u32) {
+        let vectors: Vec<(u64, Vec<f32>)> = self
+            .entries
+            .borrow()
+            .range(
+                IndexKey::Member {
+                    partition: 0,
+                    id: 0,
+                }..IndexKey::Location(0),
+            )
+            .filter_map(|entry| match *entry.key() {
+                IndexKey::Member { id, .. } => Some((id, decode_vector(&entry.value()))),
+                _ => None,
+            })
+            .collect();
+
+        let centroids = kmeans(&vectors, partitions as usize);
+        let mut header = self.header.borrow_mut();
+        header.centroids = centroids;
+
+        let mut entries = self.entries.borrow_mut();
+        let stale: Vec<IndexKey> = entries
+            .range(
+                IndexKey::Member {
+                    partition: 0,
+                    id: 0,
+                }..,
+            )
+            .map(|entry| *entry.key())
+            .collect();
+        for key in stale {
+            entries.remove(&key);
+        }
+        for (id, vector) in vectors {
+            let partition = nearest(&header.centroids, &vector, 1)
+                .first()
+                .copied()
+                .unwrap_or(0);
+            entries.insert(IndexKey::Member { partition, id }, encode_vector(&vector));
+            entries.insert(IndexKey::Location(id), partition.to_le_bytes().to_vec());
+        }
+        entries.insert(IndexKey::Header, header.encode());
+    }
+
+    /// Removes all vectors. The dimension and partitions are kept.
+    pub fn clear(&self) {
+        let mut entries = self.entries.borrow_mut();
+        let all: Vec<IndexKey> = entries
+            .range(
+                IndexKey::Member {
+                    partition: 0,
+                    id: 0,
+                }..,
+            )
+            .map(|entry| *entry.key())
+            .collect();
+        for key in all {
+            entries.remove(&key);
+        }
+    }
+
+    fn normalize(&self, vector: &[f32]) -> Result<Vec<f32>> {
+        let dimensions = self.dimensions();
+        if vector.len() != dimensions as usize {
+            return Err(IcarusError::ConfigurationError(format!(
+                "Expected a vector of {dimensions} dimensions, got {}",
+                vector.len()
+            )));
+        }
+        normalized(vector).ok_or_else(|| {
+            IcarusError::ConfigurationError("Vector must be non-zero and finite".to_string())
+        })
+    }
+}
+
+/// `vector` scaled to unit length, or `None` if it is zero or not finite.
+fn normalized(vector: &[f32]) -> Option<Vec<f32>> {
+    let norm = dot(vector, vector).sqrt();
+    (norm > 0.0 && norm.is_finite()).then(|| vector.iter().map(|x| x / norm).collect())
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Dot product with a vector still in its stored encoding.
+fn dot_encoded(a: &[f32], bytes: &[u8]) -> f32 {
+    a.iter()
+        .zip(bytes.chunks_exact(4))
+        .map(|(x, y)| x * f32::from_le_bytes([y[0], y[1], y[2], y[3]]))
+        .sum()
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Indexes of the `n` centroids most similar to `vector`, most similar first.
+fn nearest(centroids: &[Vec<f32>], vector: &[f32], n: usize) -> Vec<u32> {
+    let mut scored: Vec<(f32, u32)> = centroids
+        .iter()
+        .zip(0u32..)
+        .map(|(centroid, index)| (dot(centroid, vector), index))
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.into_iter().take(n).map(|(_, index)| index).collect()
+}
+
+/// Spherical k-means over unit vectors, seeded with evenly spaced vectors so
+/// the result is deterministic.
+fn kmeans(vectors: &[(u64, Vec<f32>)], k: usize) -> Vec<Vec<f32>> {
+    let k = k.min(vectors.len());
+    if k == 0 {
+        return Vec::new();
+    }
+
+    let step = vectors.len() / k;
+    let mut centroids: Vec<Vec<f32>> = (0..k).map(|i| vectors[i * step].1.clone()).collect();
+    let dimensions = centroids[0].len();
+
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut sums = vec![vec![0.0f32; dimensions]; k];
+        for (_, vector) in vectors {
+            let cluster = nearest(&centroids, vector, 1)[0] as usize;
+            for (sum, x) in sums[cluster].iter_mut().zip(vector) {
+                *sum += x;
+            }
+        }
+        for (centroid, sum) in centroids.iter_mut().zip(sums) {
+            // An empty cluster keeps its previous centroid
+            if let Some(mean) = normalized(&sum) {
+                *centroid = mean;
+            }
+        }
+    }
+    centroids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::FIRST_USER_MEMORY_ID;
+
+    fn unit(angle: f32) -> [f32; 2] {
+        [angle.cos(), angle.sin()]
+    }
+
+    #[test]
+    fn test_key_order_matches_byte_order() {
+        let keys = [
+            IndexKey::Header,
+            IndexKey::Member {
+                partition: 0,
+                id: 9,
+            },
+            IndexKey::Member {
+                partition: 1,
+                id: 2,
+            },
+            IndexKey::Location(0),
+            IndexKey::Location(300),
+        ];
+        for pair in keys.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(pair[0].into_bytes() < pair[1].into_bytes());
+            assert_eq!(IndexKey::from_bytes(pair[1].to_bytes()), pair[1]);
+        }
+    }
+
+    #[test]
+    fn test_flat_and_partitioned_search() {
+        let index = VectorIndex::init(FIRST_USER_MEMORY_ID + 43, 2).unwrap();
+        for id in 0..40u16 {
+            index
+                .insert(u64::from(id), &unit(f32::from(id) * 0.15))
+                .unwrap();
+        }
+        assert_eq!(index.len(), 40);
+        assert!(index.insert(99, &[1.0, 0.0, 0.0]).is_err());
+        assert!(index.insert(99, &[0.0, 0.0]).is_err());
+
+        let flat = index.search(&unit(1.52), 3).unwrap();
+        let ids: Vec<u64> = flat.iter().map(|hit| hit.id).collect();
+        assert_eq!(ids, [10, 11, 9]);
+        assert!(flat[0].score > flat[1].score);
+
+        index.partition(4);
+        assert_eq!(index.partitions(), 4);
+        assert_eq!(index.len(), 40);
+        index.set_probes(1);
+        let partitioned = index.search(&unit(1.52), 3).unwrap();
+        assert_eq!(partitioned[0].id, 10);
+
+        assert!(index.remove(10));
+        assert!(!index.remove(10));
+        assert!(index.get(10).is_none());
+        assert_eq!(index.search(&unit(1.52), 1).unwrap()[0].id, 11);
+
+        // Reopening keeps vectors and partitions, and checks the dimension
+        assert!(VectorIndex::init(FIRST_USER_MEMORY_ID + 43, 3).is_err());
+        let reopened = VectorIndex::init(FIRST_USER_MEMORY_ID + 43, 2).unwrap();
+        assert_eq!(reopened.len(), 39);
+        assert_eq!(reopened.partitions(), 4);
+
+        reopened.clear();
+        assert!(reopened.is_empty());
+    }
+}
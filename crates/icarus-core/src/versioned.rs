@@ -0,0 +1,209 @@
+//! Optimistic concurrency control for stored records.
+//!
+//! Two MCP clients that read a record, edit it and write it back would
+//! otherwise overwrite each other silently. Wrapping the stored value in
+//! [`Versioned`] gives every record a version that increases on each write;
+//! `update_*` tools take the `expected_version` the client last read and
+//! fail with [`IcarusError::Conflict`] if someone else wrote in between, so
+//! the client can re-read and retry.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::cell::RefCell;
+//!
+//! use ic_stable_structures::StableBTreeMap;
+//! use icarus_core::memory::{self, StableMemory, FIRST_USER_MEMORY_ID};
+//! use icarus_core::versioned::{self, Versioned};
+//!
+//! thread_local! {
+//!     static NOTES: RefCell<StableBTreeMap<u64, Versioned<String>, StableMemory>> =
+//!         RefCell::new(StableBTreeMap::init(memory::get_memory(FIRST_USER_MEMORY_ID)));
+//! }
+//!
+//! // The shape of an `update_*` tool: returns the new version on success
+//! fn update_note(id: u64, text: String, expected_version: u64) -> Result<u64, String> {
+//!     NOTES.with(|notes| {
+//!         versioned::update(&mut notes.borrow_mut(), &id, expected_version, text)
+//!             .map_err(|e| e.to_string())
+//!     })
+//! }
+//!
+//! NOTES.with(|notes| versioned::insert(&mut notes.borrow_mut(), 1, "draft".to_string()));
+//!
+//! assert_eq!(update_note(1, "first edit".to_string(), 1), Ok(2));
+//! let stale = update_note(1, "second edit".to_string(), 1).unwrap_err();
+//! assert!(stale.contains("Version conflict"));
+//! ```
+
+use std::borrow::Cow;
+
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+use serde::Serialize;
+
+use crate::{IcarusError, Result};
+
+/// A value together with the version of its last write.
+///
+/// Versions start at 1 and increase by one on every successful update.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct Versioned<T> {
+    /// Version of the stored value.
+    pub version: u64,
+    /// The stored value.
+    pub value: T,
+}
+
+impl<T> Versioned<T> {
+    /// Wraps a newly created value at version 1.
+    #[must_use]
+    pub const fn new(value: T) -> Self {
+        Self { version: 1, value }
+    }
+
+    /// Checks that the caller's view is current.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::Conflict` if `expected_version` is not the
+    /// current version.
+    pub fn check(&self, expected_version: u64) -> Result<()> {
+        if self.version == expected_version {
+            Ok(())
+        } else {
+            Err(IcarusError::conflict(expected_version, self.version))
+        }
+    }
+
+    /// Replaces the value if `expected_version` is current and returns the
+    /// new version.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IcarusError::Conflict` if `expected_version` is stale, leaving
+    /// the value unchanged.
+    pub fn update(&mut self, expected_version: u64, value: T) -> Result<u64> {
+        self.check(expected_version)?;
+        self.value = value;
+        self.version += 1;
+        Ok(self.version)
+    }
+}
+
+impl<T: Storable> Storable for Versioned<T> {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = self.version.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&self.value.to_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        let mut version = [0u8; 8];
+        version.copy_from_slice(&bytes[..8]);
+        Self {
+            version: u64::from_le_bytes(version),
+            value: T::from_bytes(Cow::Owned(bytes[8..].to_vec())),
+        }
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.to_bytes().into_owned()
+    }
+
+    const BOUND: Bound = match T::BOUND {
+        Bound::Bounded {
+            max_size,
+            is_fixed_size,
+        } => Bound::Bounded {
+            max_size: max_size + 8,
+            is_fixed_size,
+        },
+        Bound::Unbounded => Bound::Unbounded,
+    };
+}
+
+/// Stores a new record at version 1, replacing any existing one.
+pub fn insert<K, V, M>(map: &mut StableBTreeMap<K, Versioned<V>, M>, key: K, value: V)
+where
+    K: Storable + Ord + Clone,
+    V: Storable,
+    M: Memory,
+{
+    map.insert(key, Versioned::new(value));
+}
+
+/// Writes `value` to an existing record if `expected_version` is current,
+/// returning the new version.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if the record does not exist, or
+/// `IcarusError::Conflict` if another write happened since `expected_version`
+/// was read.
+pub fn update<K, V, M>(
+    map: &mut StableBTreeMap<K, Versioned<V>, M>,
+    key: &K,
+    expected_version: u64,
+    value: V,
+) -> Result<u64>
+where
+    K: Storable + Ord + Clone,
+    V: Storable,
+    M: Memory,
+{
+    let mut record = map
+        .get(key)
+        .ok_or_else(|| IcarusError::ConfigurationError("Record not found".to_string()))?;
+    let version = record.update(expected_version, value)?;
+    map.insert(key.clone(), record);
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_concurrent_writers_conflict() {
+        let mut map: StableBTreeMap<u64, Versioned<String>, _> =
+            StableBTreeMap::init(DefaultMemoryImpl::default());
+        insert(&mut map, 7, "v1".to_string());
+
+        // Both clients read version 1
+        let alice_read = map.get(&7).unwrap().version;
+        let bob_read = map.get(&7).unwrap().version;
+
+        assert_eq!(
+            update(&mut map, &7, alice_read, "alice".to_string()).unwrap(),
+            2
+        );
+        let conflict = update(&mut map, &7, bob_read, "bob".to_string()).unwrap_err();
+        assert!(matches!(
+            conflict,
+            IcarusError::Conflict {
+                expected: 1,
+                actual: 2
+            }
+        ));
+        assert_eq!(map.get(&7).unwrap().value, "alice");
+
+        // Bob re-reads and retries against the current version
+        let bob_reread = map.get(&7).unwrap().version;
+        assert_eq!(
+            update(&mut map, &7, bob_reread, "bob".to_string()).unwrap(),
+            3
+        );
+        assert!(update(&mut map, &8, 1, "missing".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_storable_round_trip() {
+        let record = Versioned {
+            version: 42,
+            value: "note".to_string(),
+        };
+        assert_eq!(Versioned::<String>::from_bytes(record.to_bytes()), record);
+    }
+}
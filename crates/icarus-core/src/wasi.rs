@@ -0,0 +1,170 @@
+//! File system of the WASI polyfill, kept in stable memory.
+//!
+//! Canisters built for `wasm32-wasip1` have their WASI imports rewritten by
+//! `wasi2ic` into calls to `ic-wasi-polyfill`, which emulates a file system.
+//! Until the polyfill is initialized those calls fail, and where it keeps its
+//! files decides whether they survive an upgrade.
+//!
+//! [`init_persistent`] mounts the file system onto one memory of the shared
+//! [`memory`] manager, so libraries that cache to disk find their files again
+//! after an upgrade. The memory id is picked like any other canister memory,
+//! from [`FIRST_USER_MEMORY_ID`] upward, and ids labelled for another use are
+//! refused; `max_bytes` caps how far it grows, and writes past the cap fail.
+//! [`init_transient`] keeps the files on the heap instead, where an upgrade
+//! drops them. Either is called from both `init` and `post_upgrade`, before
+//! the first tool touches a file.
+//!
+//! The polyfill's `random_get` is seeded from the time of initialization, so
+//! it must not be used for secrets.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use icarus_core::{memory::FIRST_USER_MEMORY_ID, wasi};
+//!
+//! const FILES_MEMORY_ID: u8 = FIRST_USER_MEMORY_ID + 5;
+//!
+//! #[ic_cdk::init]
+//! fn init() {
+//!     wasi::init_persistent(FILES_MEMORY_ID, Some(512 * 1024 * 1024))
+//!         .expect("WASI file system");
+//! }
+//!
+//! #[ic_cdk::post_upgrade]
+//! fn post_upgrade() {
+//!     wasi::init_persistent(FILES_MEMORY_ID, Some(512 * 1024 * 1024))
+//!         .expect("WASI file system");
+//! }
+//! ```
+
+use ic_stable_structures::{Memory, VectorMemory};
+
+use crate::memory::{self, StableMemory, FIRST_USER_MEMORY_ID, WASM_PAGE_SIZE_BYTES};
+use crate::{IcarusError, Result};
+
+/// Label of the memory holding the file system.
+const LABEL: &str = "wasi.fs";
+
+/// A memory that refuses to grow past `max_pages`.
+struct CappedMemory {
+    inner: StableMemory,
+    max_pages: u64,
+}
+
+impl Memory for CappedMemory {
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+
+    fn grow(&self, pages: u64) -> i64 {
+        if self.inner.size().saturating_add(pages) > self.max_pages {
+            return -1;
+        }
+        self.inner.grow(pages)
+    }
+
+    fn read(&self, offset: u64, dst: &mut [u8]) {
+        self.inner.read(offset, dst);
+    }
+
+    fn write(&self, offset: u64, src: &[u8]) {
+        self.inner.write(offset, src);
+    }
+}
+
+/// Seed of the polyfill's `random_get`.
+fn seed() -> [u8; 32] {
+    let mut seed = [0u8; 32];
+    seed[..8].copy_from_slice(&crate::time::now_nanos().to_le_bytes());
+    seed
+}
+
+/// Checks that `memory_id` is not taken by Icarus or labelled for another
+/// use.
+fn check_memory_id(memory_id: u8) -> Result<()> {
+    if memory_id < FIRST_USER_MEMORY_ID {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Memory id {memory_id} is reserved by Icarus; mount the WASI file system \
+             at an id from {FIRST_USER_MEMORY_ID} upward"
+        )));
+    }
+    if let Some(label) = memory::label(memory_id).filter(|label| label != LABEL) {
+        return Err(IcarusError::ConfigurationError(format!(
+            "Memory id {memory_id} already holds {label}"
+        )));
+    }
+    Ok(())
+}
+
+/// Converts a byte cap to whole pages, at least one.
+fn max_pages(max_bytes: u64) -> u64 {
+    (max_bytes.saturating_add(WASM_PAGE_SIZE_BYTES - 1) / WASM_PAGE_SIZE_BYTES).max(1)
+}
+
+/// Initializes the polyfill with its file system in the stable memory
+/// `memory_id`, grown to at most `max_bytes` if given. Files written before
+/// an upgrade are there again after it.
+///
+/// # Errors
+///
+/// Returns `IcarusError::ConfigurationError` if `memory_id` is reserved by
+/// Icarus or labelled for another use.
+pub fn init_persistent(memory_id: u8, max_bytes: Option<u64>) -> Result<()> {
+    check_memory_id(memory_id)?;
+    memory::register_label(memory_id, LABEL);
+
+    let stable = memory::get_memory(memory_id);
+    match max_bytes {
+        Some(max_bytes) => ic_wasi_polyfill::init_with_memory(
+            &seed(),
+            &[],
+            CappedMemory {
+                inner: stable,
+                max_pages: max_pages(max_bytes),
+            },
+        ),
+        None => ic_wasi_polyfill::init_with_memory(&seed(), &[], stable),
+    }
+    Ok(())
+}
+
+/// Initializes the polyfill with its file system on the heap; files are
+/// lost on upgrade.
+pub fn init_transient() {
+    ic_wasi_polyfill::init_with_memory(&seed(), &[], VectorMemory::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_ids_in_use_are_refused() {
+        assert!(check_memory_id(memory::BLOB_CHUNKS_MEMORY_ID).is_err());
+        assert!(check_memory_id(memory::API_KEYS_MEMORY_ID).is_err());
+        assert!(check_memory_id(FIRST_USER_MEMORY_ID).is_ok());
+
+        memory::register_label(FIRST_USER_MEMORY_ID + 1, "documents");
+        assert!(check_memory_id(FIRST_USER_MEMORY_ID + 1).is_err());
+        memory::register_label(FIRST_USER_MEMORY_ID + 2, LABEL);
+        assert!(check_memory_id(FIRST_USER_MEMORY_ID + 2).is_ok());
+    }
+
+    #[test]
+    fn test_capped_memory_stops_growing() {
+        let memory = CappedMemory {
+            inner: memory::get_memory(FIRST_USER_MEMORY_ID + 3),
+            max_pages: max_pages(3 * WASM_PAGE_SIZE_BYTES - 1),
+        };
+        assert_eq!(memory.max_pages, 3);
+        assert_eq!(memory.grow(2), 0);
+        assert_eq!(memory.grow(2), -1);
+        assert_eq!(memory.grow(1), 2);
+        assert_eq!(memory.size(), 3);
+
+        memory.write(10, b"cached");
+        let mut read = [0u8; 6];
+        memory.read(10, &mut read);
+        assert_eq!(&read, b"cached");
+    }
+}
@@ -0,0 +1,123 @@
+//! Reads the `[service]`, `[features]` and `[limits]` tables of a project's
+//! icarus.toml.
+//!
+//! The file is looked up at compile time: `ICARUS_CONFIG` names it
+//! explicitly, otherwise `icarus.toml` next to the crate's Cargo.toml is used
+//! if it exists. Other tables (`[deploy]`, `[bridge]`) belong to the CLI and
+//! are ignored here; unknown keys in the tables read here are errors.
+
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::error::{MacroError, MacroResult};
+
+/// Name of the project configuration file.
+pub(crate) const FILE_NAME: &str = "icarus.toml";
+
+/// The parts of icarus.toml that configure `mcp!{}`.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct ConfigFile {
+    #[serde(default)]
+    pub(crate) service: ServiceTable,
+    #[serde(default)]
+    pub(crate) features: FeaturesTable,
+    #[serde(default)]
+    pub(crate) limits: LimitsTable,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ServiceTable {
+    pub(crate) name: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) version: Option<String>,
+    pub(crate) prefix: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FeaturesTable {
+    pub(crate) auth: Option<bool>,
+    pub(crate) rate_limit: Option<bool>,
+    pub(crate) metrics: Option<bool>,
+    pub(crate) blobs: Option<bool>,
+    pub(crate) events: Option<bool>,
+    pub(crate) archive_store: Option<bool>,
+    pub(crate) replication: Option<bool>,
+    pub(crate) jobs: Option<bool>,
+    pub(crate) secrets: Option<bool>,
+    pub(crate) signing: Option<bool>,
+    pub(crate) factory: Option<bool>,
+    pub(crate) encryption: Option<bool>,
+    pub(crate) inspect: Option<bool>,
+    pub(crate) http_tools: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct LimitsTable {
+    pub(crate) max_request_bytes: Option<u64>,
+    pub(crate) max_response_bytes: Option<u64>,
+}
+
+/// Path of the crate's icarus.toml, if it has one.
+///
+/// A relative `ICARUS_CONFIG` is taken relative to the crate's directory.
+fn config_path() -> Option<PathBuf> {
+    let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").ok()?);
+    if let Ok(path) = std::env::var("ICARUS_CONFIG") {
+        return Some(manifest_dir.join(path));
+    }
+    let path = manifest_dir.join(FILE_NAME);
+    path.exists().then_some(path)
+}
+
+/// Loads the crate's icarus.toml, returning it with its path.
+pub(crate) fn load() -> MacroResult<Option<(ConfigFile, PathBuf)>> {
+    let Some(path) = config_path() else {
+        return Ok(None);
+    };
+    let content = std::fs::read_to_string(&path).map_err(|e| {
+        MacroError::configuration(format!("Failed to read {}: {e}", path.display()))
+    })?;
+    let file = parse(&content)
+        .map_err(|e| MacroError::configuration(format!("{}: {e}", path.display())))?;
+    Ok(Some((file, path)))
+}
+
+/// Parses the contents of an icarus.toml.
+pub(crate) fn parse(content: &str) -> Result<ConfigFile, toml::de::Error> {
+    toml::from_str(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reads_service_and_features_only() {
+        let file = parse(
+            r#"
+            [service]
+            name = "weather"
+            prefix = "weather_"
+
+            [features]
+            auth = true
+
+            [deploy]
+            network = "ic"
+            "#,
+        )
+        .expect("valid icarus.toml");
+
+        assert_eq!(file.service.name.as_deref(), Some("weather"));
+        assert_eq!(file.service.prefix.as_deref(), Some("weather_"));
+        assert_eq!(file.features.auth, Some(true));
+        assert_eq!(file.features.metrics, None);
+
+        assert!(parse("[features]\nauth = \"yes\"\n").is_err());
+        assert!(parse("[service]\ntitle = \"weather\"\n").is_err());
+    }
+}
@@ -0,0 +1,210 @@
+//! Implementation of the `#[icarus_tools]` attribute macro.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse2, spanned::Spanned, ImplItem, ImplItemFn, ItemImpl, Meta, Type};
+
+use crate::error::{MacroError, MacroResult};
+use crate::tool::{generate_tool_items, parse_tool_args, Receiver, ToolConfig, ToolTarget};
+use crate::utils::to_snake_case;
+
+/// Implementation of the `#[icarus_tools]` attribute macro.
+///
+/// Methods marked `#[tool]` are exposed as tools and the marker is removed;
+/// the impl block is otherwise emitted unchanged.
+pub(crate) fn icarus_tools_impl(args: TokenStream, input: TokenStream) -> MacroResult<TokenStream> {
+    if !args.is_empty() {
+        return Err(MacroError::configuration_spanned(
+            "#[icarus_tools] takes no arguments",
+            args.span(),
+        ));
+    }
+
+    let mut item: ItemImpl = parse2(input)?;
+
+    if let Some((_, path, _)) = &item.trait_ {
+        return Err(MacroError::unsupported_feature_spanned(
+            "Trait implementations",
+            "#[icarus_tools] applies to inherent impl blocks",
+            path.span(),
+        ));
+    }
+    if !item.generics.params.is_empty() {
+        return Err(MacroError::unsupported_feature_spanned(
+            "Generic impl blocks",
+            "Tools are bound to a single canister-global instance",
+            item.generics.span(),
+        ));
+    }
+
+    let self_ty = (*item.self_ty).clone();
+    let Type::Path(type_path) = &self_ty else {
+        return Err(MacroError::invalid_signature_spanned(
+            "#[icarus_tools] requires a named struct or enum type",
+            self_ty.span(),
+        ));
+    };
+    let type_name = type_path
+        .path
+        .segments
+        .last()
+        .map(|segment| to_snake_case(&segment.ident.to_string()))
+        .unwrap_or_default();
+
+    let mut tools = Vec::new();
+    for impl_item in &mut item.items {
+        let ImplItem::Fn(method) = impl_item else {
+            continue;
+        };
+        let Some(index) = method.attrs.iter().position(is_tool_attribute) else {
+            continue;
+        };
+
+        let attr = method.attrs.remove(index);
+        let tool_config = match attr.meta {
+            Meta::Path(_) => ToolConfig::default(),
+            Meta::List(list) => parse_tool_args(list.tokens),
+            Meta::NameValue(name_value) => {
+                return Err(MacroError::configuration_spanned(
+                    "Expected #[tool] or #[tool(...)]",
+                    name_value.span(),
+                ))
+            }
+        };
+
+        let receiver = validate_method(method)?;
+        let base_name = format_ident!("{}_{}", type_name, method.sig.ident);
+        tools.push(generate_tool_items(
+            tool_config,
+            &method.attrs,
+            &method.sig,
+            &base_name,
+            &ToolTarget::Method {
+                self_ty: &self_ty,
+                receiver,
+            },
+        )?);
+    }
+
+    Ok(quote! {
+        #item
+
+        #(#tools)*
+    })
+}
+
+/// Matches `#[tool]` as well as paths ending in it, like `#[icarus::tool]`.
+fn is_tool_attribute(attr: &syn::Attribute) -> bool {
+    attr.path()
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "tool")
+}
+
+/// Checks that a method can be called on the shared instance and returns how
+/// it borrows it.
+fn validate_method(method: &ImplItemFn) -> MacroResult<Option<Receiver>> {
+    let sig = &method.sig;
+    if !sig.generics.params.is_empty() {
+        return Err(MacroError::unsupported_feature_spanned(
+            "Generic methods",
+            "Tool methods cannot have generic or lifetime parameters",
+            sig.generics.span(),
+        ));
+    }
+
+    let Some(receiver) = sig.receiver() else {
+        return Ok(None);
+    };
+    if receiver.reference.is_none() || receiver.colon_token.is_some() {
+        return Err(MacroError::invalid_signature_spanned(
+            "Tool methods must take &self or &mut self",
+            receiver.span(),
+        ));
+    }
+    if receiver.mutability.is_none() {
+        return Ok(Some(Receiver::Shared));
+    }
+    if sig.asyncness.is_some() {
+        return Err(MacroError::unsupported_feature_spanned(
+            "Async &mut self methods",
+            "Async tool methods must take &self, since other calls can run while one awaits; \
+             keep mutable state behind a lock or cell",
+            receiver.span(),
+        ));
+    }
+    Ok(Some(Receiver::Mutable))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_methods_become_tools() {
+        let code = icarus_tools_impl(
+            TokenStream::new(),
+            quote! {
+                impl MemoryServer {
+                    /// Stores a memory
+                    #[tool]
+                    fn remember(&mut self, text: String) -> u64 { 0 }
+
+                    /// Searches memories
+                    #[tool(name = "search-memories")]
+                    async fn search(&self, query: String) -> Vec<String> { vec![] }
+
+                    fn helper(&self) {}
+                }
+            },
+        )
+        .unwrap()
+        .to_string();
+
+        assert!(!code.contains("# [tool"));
+        assert!(code.contains("fn helper"));
+        assert!(code.contains("memory_server_remember_tool_wrapper"));
+        assert!(code.contains("state :: with_mut (| instance : & mut MemoryServer |"));
+        assert!(code.contains("state :: shared :: < MemoryServer > ()"));
+        assert!(code.contains("\"search-memories\""));
+        assert!(!code.contains("memory_server_helper"));
+    }
+
+    #[test]
+    fn test_rejects_unsupported_receivers() {
+        let async_mut = icarus_tools_impl(
+            TokenStream::new(),
+            quote! {
+                impl Server {
+                    #[tool]
+                    async fn write(&mut self, text: String) {}
+                }
+            },
+        );
+        assert!(async_mut
+            .unwrap_err()
+            .to_string()
+            .contains("Async &mut self methods"));
+
+        let by_value = icarus_tools_impl(
+            TokenStream::new(),
+            quote! {
+                impl Server {
+                    #[tool]
+                    fn consume(self) {}
+                }
+            },
+        );
+        assert!(by_value.is_err());
+
+        let trait_impl = icarus_tools_impl(
+            TokenStream::new(),
+            quote! {
+                impl Default for Server {
+                    fn default() -> Self { Server }
+                }
+            },
+        );
+        assert!(trait_impl.is_err());
+    }
+}
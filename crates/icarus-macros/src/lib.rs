@@ -6,7 +6,10 @@
 //! for building MCP servers on Internet Computer canisters:
 //!
 //! - `#[tool]` - Attribute macro for automatically generating MCP tool wrappers
+//! - `#[icarus_tools]` - Exposes `#[tool]` methods of a state struct as tools
 //! - `mcp!{}` - Declarative macro for generating canister initialization code
+//! - `roles!()` - Declares custom roles for `#[tool(auth = "...")]`
+//! - `#[derive(IcarusStorable)]` - Candid-encoded `Storable` for stable structures
 //!
 //! # Examples
 //!
@@ -27,8 +30,12 @@
 #![warn(clippy::pedantic)]
 #![deny(unsafe_code)]
 
+mod config_file;
 mod error;
+mod icarus_tools;
 mod mcp;
+mod roles;
+mod storable;
 mod tool;
 mod utils;
 
@@ -69,12 +76,187 @@ use proc_macro::TokenStream;
 /// - Error handling and conversion
 /// - MCP protocol compliance wrappers
 ///
+/// # Access
+///
+/// `auth = "user"` and `auth = "admin"` restrict a tool to whitelisted
+/// principals. `auth = "guest"` marks a read-only tool that requires user
+/// access until the owner calls `set_guest_access(true)`; from then on any
+/// caller, including an unauthenticated bridge, may call it.
+///
+/// Any other `auth` names a role declared with [`roles!`], and the tool
+/// accepts callers holding that role or a higher one. Naming a role that was
+/// not declared fails to compile.
+///
+/// # Timeouts
+///
+/// `#[tool(timeout_ms = 5000)]` overrides the `ToolExecutor` timeout for one
+/// tool. A call that runs longer is cancelled and returns `ToolResult::Timeout`.
+///
+/// In a canister, `mcp_call_tool` does not go through `ToolExecutor`, and the
+/// timeout is a soft deadline: it sets `ToolContext::deadline`, which an async
+/// tool can check with `is_past_deadline()` between awaits to stop early.
+/// Nothing cancels the tool, and a sync tool runs within one message, where
+/// time does not advance, so only the instruction limit bounds it.
+///
+/// # Result Caching
+///
+/// `#[tool(cache_ttl_secs = 300)]` tells the bridge how long it may return a
+/// cached result of a read-only tool before calling it again. Tools without
+/// it are cached for the bridge's `--cache-ttl`, and only when the bridge
+/// runs with `--cache-size` (see `icarus_core::tool::CACHE_TTL_SCHEMA_KEY`).
+///
+/// # Translations
+///
+/// `#[tool(i18n = "tools.add")]` looks up the tool's description and title
+/// under `tools.add.description` and `tools.add.title` in the bundles
+/// embedded with `icarus_runtime::i18n_bundle!`. The bridge asks for the
+/// client's locale, and a tool without a translation keeps its own
+/// description (see `icarus_core::i18n`).
+///
+/// # Encrypted Arguments
+///
+/// `#[tool(encrypted)]` marks a tool whose arguments the bridge encrypts to
+/// the canister's vetKD key, served by `mcp! { encryption = true }`. The
+/// canister decrypts them before dispatch and refuses calls with plain
+/// arguments (see `icarus_core::encrypted_args`).
+///
+/// # Error Codes
+///
+/// A tool whose error type displays as `CODE: message`, such as a
+/// `CodedError` or an enum declared with `icarus_core::error_catalog!`,
+/// fails with `structuredContent.code` set to the code, so clients can
+/// branch on it (see `icarus_core::error_codes`).
+///
+/// ```rust,ignore
+/// #[tool]
+/// fn get_note(id: u64) -> Result<Note, CodedError> {
+///     load(id).ok_or_else(|| IcarusCode::StorageNotFound.with_message(format!("No note {id}")))
+/// }
+/// ```
+///
+/// # Rich Content
+///
+/// Returning `Vec<Content>` sends the items to the client unchanged, so a tool
+/// can answer with images, resource links or a mix of text and binary content
+/// (see `icarus_core::content`). Any other return type becomes one text item.
+///
+/// # Pagination
+///
+/// `#[tool(paginated)]` adds optional `cursor` and `limit` parameters to a
+/// tool returning anything iterable, or a `Result` of it, and answers with
+/// one page, `{"items": [..], "next_cursor": ..}` (see
+/// `icarus_core::pagination`). Filter and sort parameters are the tool's own
+/// and apply before paging.
+///
+/// ```rust,ignore
+/// #[tool(paginated)]
+/// fn list_notes(tag: Option<String>) -> impl Iterator<Item = Note> {
+///     NOTES.with(|notes| notes.borrow().values().filter(|n| n.has_tag(&tag)).collect::<Vec<_>>())
+///         .into_iter()
+/// }
+/// ```
+///
+/// # Long-Running Jobs
+///
+/// `#[tool(job)]` marks a tool whose work exceeds one message's instruction
+/// limit. The function returns a closure that runs one chunk per call (see
+/// `icarus_core::jobs::Chunk`); calling the tool starts a job running it in
+/// timer-driven chunks and answers with `{"job_id": ..}`, which clients poll
+/// with the `get_job_status` and `get_job_result` tools of
+/// `mcp! { jobs = true }`.
+///
+/// ```rust,ignore
+/// #[tool(job, auth = "admin")]
+/// fn reindex(batch_size: usize) -> impl FnMut() -> Chunk {
+///     let mut next = 0;
+///     move || {
+///         next = index_records(next, batch_size);
+///         if next == record_count() { Chunk::done(&next) } else { Chunk::more() }
+///     }
+/// }
+/// ```
+///
+/// # Composite Queries
+///
+/// `#[tool(composite_query)]` marks a read-only tool that fans out to other
+/// canisters. Besides `mcp_call_tool` it runs from `mcp_call_tool_composite`,
+/// a composite query the bridge calls for it, so calls made with
+/// `icarus_core::composite::call` are query calls and the tool answers
+/// without consensus. What it changes is discarded, so it cannot be paid,
+/// encrypted or a job, and it is listed as read-only.
+///
+/// ```rust,ignore
+/// #[tool(composite_query)]
+/// async fn price_of(token: Principal) -> Result<u64, String> {
+///     composite::call(ORACLE, "price", &token).await.map_err(|e| e.to_string())
+/// }
+/// ```
+///
+/// # Sampling
+///
+/// Returning `SamplingRequest` (from `icarus_core::sampling`) asks the
+/// client's LLM for a completion. The bridge forwards the request as
+/// `sampling/createMessage` and passes the answer to `mcp_sampling_result`,
+/// which runs the continuation the request names; its result completes the
+/// tool call.
+///
+/// ```rust,ignore
+/// #[tool]
+/// fn summarize(doc_id: String) -> SamplingRequest {
+///     SamplingRequest::new("store_summary", 300)
+///         .with_message(SamplingMessage::user(load_text(&doc_id)))
+///         .with_state(doc_id)
+/// }
+/// ```
+///
+/// # Call Context
+///
+/// A tool that needs to know who is calling takes `ctx: ToolContext` or
+/// `ctx: &ToolContext` (from `icarus_core::context`) as its first parameter.
+/// It is filled in with the caller, their role, the request id and the
+/// deadline instead of being read from the arguments, and does not appear in
+/// the schema. Tests set the caller with `icarus_test::MockEnvironment`.
+///
+/// ```rust,ignore
+/// #[tool]
+/// fn whoami(ctx: &ToolContext) -> String {
+///     format!("{} ({})", ctx.caller, ctx.role)
+/// }
+/// ```
+///
+/// # Borrowed Arguments
+///
+/// Parameters may borrow from the tool's argument string, which stays alive
+/// until the tool returns: `&str`, `&[u8]` (sent as a JSON string), or
+/// `Cow<'_, str>` and `Option<Cow<'_, str>>` for strings that may contain
+/// escape sequences (a `&str` parameter rejects those, since the unescaped
+/// text is not in the input). Other slices such as `&[u64]` are rejected;
+/// take a `Vec`. This saves an allocation per string parameter, not the
+/// copy of the arguments themselves: `mcp_call_tool` parses the JSON-RPC
+/// request and hands the tool its `arguments` as a new string. Job tools
+/// must take owned arguments, as their work outlives the call.
+///
+/// ```rust,ignore
+/// #[tool]
+/// fn word_count(text: Cow<'_, str>, separator: Option<&str>) -> usize {
+///     text.split(separator.unwrap_or(" ")).count()
+/// }
+/// ```
+///
+/// # Concurrent Updates
+///
+/// Tools that modify a stored record should take an `expected_version: u64`
+/// parameter and write through `icarus_core::versioned::update`, which rejects
+/// stale writes with a version conflict instead of overwriting another
+/// client's change.
+///
 /// # Restrictions
 ///
 /// - Functions must have simple parameter types that implement `serde::Deserialize`
 /// - Return types must implement `serde::Serialize` or be convertible to `String`
 /// - Async functions are supported
 /// - Generic functions are not currently supported
+/// - Methods are supported inside an [`macro@icarus_tools`] impl block
 #[proc_macro_attribute]
 pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
     tool::tool_impl(args.into(), input.into())
@@ -82,6 +264,57 @@ pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
         .into()
 }
 
+/// Attribute macro for an impl block whose `#[tool]` methods become tools.
+///
+/// The methods run on one canister-global instance of the type, created with
+/// `Default` on first use and managed by `icarus_runtime::state`. Use
+/// `icarus_runtime::state::set` in `#[init]` to start from another value.
+/// Methods without `#[tool]` are left alone.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use icarus::prelude::*;
+///
+/// #[derive(Default)]
+/// struct MemoryServer {
+///     memories: Vec<String>,
+/// }
+///
+/// #[icarus_tools]
+/// impl MemoryServer {
+///     /// Stores a memory and returns its index
+///     #[tool]
+///     fn remember(&mut self, text: String) -> usize {
+///         self.memories.push(text);
+///         self.memories.len() - 1
+///     }
+///
+///     /// Returns the memories containing `query`
+///     #[tool(name = "search-memories")]
+///     async fn search(&self, query: String) -> Vec<String> {
+///         self.memories.iter().filter(|m| m.contains(&query)).cloned().collect()
+///     }
+/// }
+///
+/// mcp! {}
+/// ```
+///
+/// # Receivers
+///
+/// - `&self` methods share the instance, including across awaits, so the
+///   type must be `Send + Sync`
+/// - `&mut self` methods get exclusive access and fail with an error while an
+///   async `&self` call still holds the instance
+/// - Async methods must take `&self`; keep state they change behind a lock
+/// - Associated functions without a receiver are called on the type
+#[proc_macro_attribute]
+pub fn icarus_tools(args: TokenStream, input: TokenStream) -> TokenStream {
+    icarus_tools::icarus_tools_impl(args.into(), input.into())
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
 /// Declarative macro for generating MCP server initialization code.
 ///
 /// This macro generates all the necessary canister endpoints and infrastructure
@@ -118,15 +351,123 @@ pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
 /// - `name`: Service name (defaults to crate name)
 /// - `description`: Service description
 /// - `version`: Service version (defaults to crate version)
-/// - `auth`: Enable authentication (optional)
+/// - `auth`: Enable authentication, with admin-only `create_invite`,
+///   `revoke_invite`, `list_invites` and `get_invite_audit_log` tools and a
+///   `redeem_invite` tool for joining with an invite code, and admin-only
+///   `set_migration_key`, `export_auth_state` and `import_auth_state`
+///   endpoints for moving users to another canister, where the import
+///   replaces the existing users; its generated `init` and `post_upgrade`
+///   trap if the `#[tool]` definitions conflict, so such a build fails to
+///   install or upgrade, and the canister must not define either (optional)
 /// - `rate_limit`: Enable rate limiting (optional)
+/// - `metrics`: Serve Prometheus metrics at `/metrics` from `http_request`,
+///   behind the bearer token and principal allowlist the owner sets with
+///   `set_metrics_token` and `set_metrics_principals` (optional)
+/// - `blobs`: Expose chunked blob uploads and serve blobs at `/blobs/<id>` (optional)
+/// - `events`: Let other canisters subscribe to events this canister publishes (optional)
+/// - `archive_store`: Keep log segments archived by other canisters, see
+///   `icarus_core::archive` (optional)
+/// - `replication`: Stream changes to read replicas and follow a primary as
+///   one, see `icarus_core::replication` (optional)
+/// - `jobs`: Add `start_job`, `get_job_status` and `get_job_result` tools for
+///   long-running jobs (optional)
+/// - `secrets`: Add owner-only `set_secret`, `rotate_secret`, `delete_secret`
+///   and `list_secrets` tools for `icarus_core::secrets` (optional)
+/// - `signing`: Add `get_public_key` and owner-only `sign_message`,
+///   `set_signing_path`, `remove_signing_path` and `get_signing_stats` tools
+///   for `icarus_core::signing` (optional)
+/// - `factory`: Add owner-only `spawn_instance`, `install_instance`,
+///   `top_up_instance`, `list_instances` and `get_factory_status` tools that
+///   spawn per-user instances of a child canister, see `icarus_core::factory`
+///   (optional)
+/// - `encryption`: Add the `mcp_args_public_key` endpoint serving the vetKD
+///   key the bridge encrypts the arguments of `#[tool(encrypted)]` tools under;
+///   needs the `vetkd` feature (optional)
+/// - `prefix`: Namespace for tool names, e.g. `prefix = "memory_"` lists `search`
+///   as `memory_search` and routes calls to `memory_search` back to it, so
+///   several canisters can be aggregated without name collisions (optional)
+/// - `inspect`: Generate an `inspect_message` hook that drops update calls to
+///   disabled tools and, with `auth`, from principals that are not on the
+///   whitelist, before they cost cycles (optional)
+/// - `inspect_allow`: Comma-separated methods and tools the hook always
+///   accepts, e.g. `inspect_allow = "subscribe"`; `redeem_invite` is always
+///   accepted with `auth` (optional)
+/// - `http_tools`: Serve a JSON API for tools from `http_request`: `GET /tools`
+///   lists them and `POST /tools/<name>` calls one with the JSON body as
+///   arguments, authenticated with an API key; `GET /openapi.json` describes
+///   them as an OpenAPI 3.1 document (optional)
+/// - `cors_origin`: Origin allowed to read tool API responses, e.g.
+///   `cors_origin = "https://app.example.com"` (default `"*"`)
+/// - `max_request_bytes`, `max_response_bytes`: Size limits of `tools/call`
+///   requests and responses, at most 2 MiB (default 1 MiB and 1.5 MiB);
+///   larger payloads fail with a "payload too large" error (optional)
+///
+/// # icarus.toml
+///
+/// The same settings can live in an `icarus.toml` next to the crate's
+/// Cargo.toml (or the file `ICARUS_CONFIG` names), which the CLI reads too.
+/// Arguments to `mcp!{}` override the file.
+///
+/// ```toml
+/// [service]
+/// name = "weather"
+/// version = "1.2.0"
+/// prefix = "weather_"
+///
+/// [features]
+/// auth = true
+/// metrics = true
+///
+/// [limits]
+/// max_request_bytes = 262144
+/// ```
 ///
 /// # Generated Endpoints
 ///
 /// The macro generates these IC canister endpoints:
 /// - `mcp_list_tools() -> String` (query)
+/// - `mcp_list_tools_in(locale: String) -> String` (query, the same list with
+///   titles and descriptions translated for `locale`)
 /// - `mcp_call_tool(request: String) -> String` (update)
+/// - `mcp_call_tool_composite(request: String) -> String` (composite query,
+///   runs `#[tool(composite_query)]` tools)
+/// - `mcp_sampling_result(request: String) -> String` (update, completes a tool
+///   that returned a `SamplingRequest`)
 /// - `mcp_server_info() -> String` (query)
+/// - `mcp_tools_fingerprint() -> String` (query, changes whenever the tool list does)
+/// - `mcp_tools_sequence() -> u64` (query, increases whenever the tool list changes)
+/// - `mcp_health() -> String` (query, version, uptime, tool count, memory,
+///   cycles and whether the canister is ready for tool calls)
+/// - `get_payload_stats()` (query, admin-only with `auth = true`), the size
+///   limits and the calls rejected for their size
+/// - `set_tool_enabled(name, enabled) -> Result<bool, String>` (update) and
+///   `get_tool_audit_log(limit)` (query), for admins with `auth = true` and
+///   controllers otherwise; disabled tools are hidden and cannot be called
+/// - `set_maintenance(enabled, message)` (update, same access) and
+///   `get_maintenance_status()` (query); in maintenance mode tool calls fail
+///   with a "service unavailable" error while queries keep working
+/// - `set_guest_access(enabled)` (update, same access) and `get_guest_policy()`
+///   (query), which open `auth = "guest"` tools to callers not on the whitelist
+/// - `http_request(GatewayRequest) -> GatewayResponse` (query, with `metrics`,
+///   `blobs` or `http_tools`)
+/// - `set_metrics_token(token)` and `set_metrics_principals(principals)`
+///   (update, owner only), with `metrics = true`
+/// - `http_request_update(GatewayRequest) -> GatewayResponse` (update), and
+///   `create_api_key(label, principal)`, `revoke_api_key(id)` (update) and
+///   `list_api_keys()` (query) for the owner, with `http_tools = true`
+/// - `upload_begin`, `upload_chunk`, `upload_commit`, `delete_blob` (update,
+///   users and admins with auth, controllers without) and `list_blobs`,
+///   `blob_chunk` (query), with `blobs = true`
+/// - `subscribe`, `unsubscribe` (update) and `list_subscriptions`,
+///   `list_pending_events` (query), with `events = true`
+/// - `get_archive_status()` (query), for logs registered with `icarus_core::archive`
+/// - `archive_segment`, `add_archive_source`, `remove_archive_source`,
+///   `delete_archive_segment` (update) and `list_archive_sources`,
+///   `list_archive_segments`, `get_archive_segment` (query), with
+///   `archive_store = true`
+/// - `add_replica`, `remove_replica`, `set_replication_primary`,
+///   `replicate_change` (update) and `get_replication_status` (query), with
+///   `replication = true`; a replica refuses tools that are not read-only
 #[proc_macro]
 pub fn mcp(input: TokenStream) -> TokenStream {
     mcp::mcp_impl(input.into())
@@ -134,4 +475,91 @@ pub fn mcp(input: TokenStream) -> TokenStream {
         .into()
 }
 
+/// Declares custom roles, highest first, for `#[tool(auth = "...")]`.
+///
+/// Invoke it once at the crate root. Role names are the identifiers in
+/// `snake_case`, so `ContentEditor` is `auth = "content_editor"`. The names
+/// `admin` and `user` stand for the whitelist roles of `mcp! { auth = true }`.
+///
+/// Besides the declarations, the macro generates admin-only endpoints to
+/// manage role holders (see `icarus_core::roles`):
+///
+/// - `assign_role(principal, role)` and `unassign_role(principal)` (update)
+/// - `list_role_assignments()` (query)
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use icarus::prelude::*;
+///
+/// roles!(Owner > Admin > Editor > Viewer);
+///
+/// /// Publishes a draft; editors, admins and owners may call it
+/// #[tool(auth = "editor")]
+/// fn publish(id: u64) -> Result<(), String> {
+///     drafts::publish(id)
+/// }
+///
+/// mcp! { auth = true }
+/// ```
+#[proc_macro]
+pub fn roles(input: TokenStream) -> TokenStream {
+    roles::roles_impl(input.into())
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
 // Note: VERSION constant removed as proc-macro crates cannot export non-proc-macro items
+
+/// Derives `ic_stable_structures::Storable` by Candid-encoding the value.
+///
+/// The type must also derive `CandidType` and `Deserialize`. Stored values
+/// are unbounded in size, so the type can be used as a `StableBTreeMap`
+/// value but not as a key.
+///
+/// Generic types are supported: each type parameter gets
+/// `CandidType + DeserializeOwned` bounds next to any existing where-clause,
+/// and lifetime and const parameters are passed through unchanged.
+///
+/// # Codecs
+///
+/// Candid is self-describing and comparatively large. For big structs,
+/// `#[icarus_storable(codec = "cbor")]` or `codec = "bincode"` encode through
+/// `icarus_core::codec` instead (enable its `cbor` or `bincode` feature); type
+/// parameters are then bounded by `Serialize + DeserializeOwned`. Switching
+/// the codec of stored data requires rewriting it with
+/// `icarus_core::codec::migrate` first.
+///
+/// # Compression
+///
+/// `#[icarus_storable(compress = "lz4")]` compresses encoded values of at
+/// least 256 bytes (`compress_threshold = N` to change) with
+/// `icarus_core::compression`, which needs its `lz4` feature. Compression
+/// ratios per type appear in `mcp_memory_report`.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use std::borrow::Cow;
+///
+/// use candid::{CandidType, Deserialize};
+/// use icarus_macros::IcarusStorable;
+///
+/// #[derive(CandidType, Deserialize, IcarusStorable)]
+/// struct Labeled<T> {
+///     label: Cow<'static, str>,
+///     value: T,
+/// }
+///
+/// #[derive(CandidType, Deserialize, IcarusStorable)]
+/// enum Job {
+///     Pending { attempts: u32 },
+///     Done(Labeled<u64>),
+/// }
+/// ```
+#[proc_macro_derive(IcarusStorable, attributes(icarus_storable))]
+pub fn icarus_storable(input: TokenStream) -> TokenStream {
+    storable::storable_impl(input.into())
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
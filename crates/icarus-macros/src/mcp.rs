@@ -4,17 +4,48 @@ use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{parse::Parser, Expr, ExprAssign, ExprLit, ExprPath, Lit, Token};
 
+use crate::config_file::{self, ConfigFile};
 use crate::error::{MacroError, MacroResult};
+use crate::utils::canister_only;
+
+/// Default request limit, matching `icarus_core::payload_limits::DEFAULT_MAX_REQUEST_BYTES`
+const DEFAULT_MAX_REQUEST_BYTES: usize = 1024 * 1024;
+
+/// Default response limit, matching `icarus_core::payload_limits::DEFAULT_MAX_RESPONSE_BYTES`
+const DEFAULT_MAX_RESPONSE_BYTES: usize = 1536 * 1024;
+
+/// Size limit of Internet Computer ingress messages and replies
+const MAX_MESSAGE_BYTES: usize = 2 * 1024 * 1024;
 
 /// Implementation of the mcp!{} macro.
+///
+/// Settings come from the crate's icarus.toml first; arguments to the macro
+/// override them.
 pub(crate) fn mcp_impl(input: TokenStream) -> MacroResult<TokenStream> {
-    let config = if input.is_empty() {
-        McpConfig::default()
-    } else {
-        parse_mcp_config(input)?
-    };
+    let mut config = McpConfig::default();
+    let config_file = config_file::load()?;
+    if let Some((file, _)) = &config_file {
+        apply_config_file(&mut config, file)?;
+    }
+    if !input.is_empty() {
+        apply_mcp_args(input, &mut config)?;
+    }
 
-    Ok(generate_mcp_server_code(&config))
+    // A component build exports the tools through the component model
+    // instead of canister endpoints
+    let mut code = canister_only(generate_mcp_server_code(&config));
+    code.extend(quote! {
+        #[cfg(all(target_os = "wasi", target_env = "p2"))]
+        ::icarus_runtime::export_component!();
+    });
+    if let Some((_, path)) = config_file {
+        // Makes cargo rebuild the canister when icarus.toml changes
+        let path = path.to_string_lossy().into_owned();
+        code.extend(quote! {
+            const _: &[u8] = include_bytes!(#path);
+        });
+    }
+    Ok(code)
 }
 
 /// Configuration for the MCP server.
@@ -30,6 +61,40 @@ struct McpConfig {
     auth: bool,
     /// Enable rate limiting
     rate_limit: bool,
+    /// Serve Prometheus metrics from `http_request`
+    metrics: bool,
+    /// Expose chunked blob uploads and serve blobs from `http_request`
+    blobs: bool,
+    /// Let other canisters subscribe to published events
+    events: bool,
+    /// Keep log segments archived by other canisters
+    archive_store: bool,
+    /// Stream changes to read replicas, or follow a primary as one
+    replication: bool,
+    /// Add tools to start long-running jobs and poll them
+    jobs: bool,
+    /// Add owner tools to manage named secrets
+    secrets: bool,
+    /// Add threshold ECDSA signing tools
+    signing: bool,
+    /// Add owner tools to spawn and track per-user instances of a child canister
+    factory: bool,
+    /// Serve the vetKD key the bridge encrypts `#[tool(encrypted)]` arguments under
+    encryption: bool,
+    /// Prepended to every tool name in listings and stripped from calls
+    prefix: String,
+    /// Generate an `inspect_message` hook that drops unwanted update calls
+    inspect: bool,
+    /// Methods and tools the `inspect_message` hook always accepts
+    inspect_allow: Vec<String>,
+    /// Serve a JSON API for tools at `/tools` from `http_request`
+    http_tools: bool,
+    /// Origin allowed to read responses of the tool API
+    cors_origin: String,
+    /// Largest `tools/call` request accepted, in bytes
+    max_request_bytes: usize,
+    /// Largest `tools/call` response returned, in bytes
+    max_response_bytes: usize,
 }
 
 impl Default for McpConfig {
@@ -40,14 +105,95 @@ impl Default for McpConfig {
             version: "1.0.0".to_string(),
             auth: false,
             rate_limit: false,
+            metrics: false,
+            blobs: false,
+            events: false,
+            archive_store: false,
+            replication: false,
+            jobs: false,
+            secrets: false,
+            signing: false,
+            factory: false,
+            encryption: false,
+            prefix: String::new(),
+            inspect: false,
+            inspect_allow: Vec::new(),
+            http_tools: false,
+            cors_origin: "*".to_string(),
+            max_request_bytes: DEFAULT_MAX_REQUEST_BYTES,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+        }
+    }
+}
+
+/// Overrides defaults with the settings of an icarus.toml.
+fn apply_config_file(config: &mut McpConfig, file: &ConfigFile) -> MacroResult<()> {
+    let service = &file.service;
+    if let Some(name) = &service.name {
+        config.name.clone_from(name);
+    }
+    if let Some(description) = &service.description {
+        config.description.clone_from(description);
+    }
+    if let Some(version) = &service.version {
+        config.version.clone_from(version);
+    }
+    if let Some(prefix) = &service.prefix {
+        validate_prefix(prefix)?;
+        config.prefix.clone_from(prefix);
+    }
+
+    let limits = &file.limits;
+    for (bytes, limit, key) in [
+        (
+            limits.max_request_bytes,
+            &mut config.max_request_bytes,
+            "limits.max_request_bytes",
+        ),
+        (
+            limits.max_response_bytes,
+            &mut config.max_response_bytes,
+            "limits.max_response_bytes",
+        ),
+    ] {
+        if let Some(bytes) = bytes {
+            *limit = validate_payload_limit(key, bytes)?;
+        }
+    }
+
+    let features = &file.features;
+    for (enabled, flag) in [
+        (features.auth, &mut config.auth),
+        (features.rate_limit, &mut config.rate_limit),
+        (features.metrics, &mut config.metrics),
+        (features.blobs, &mut config.blobs),
+        (features.events, &mut config.events),
+        (features.archive_store, &mut config.archive_store),
+        (features.replication, &mut config.replication),
+        (features.jobs, &mut config.jobs),
+        (features.secrets, &mut config.secrets),
+        (features.signing, &mut config.signing),
+        (features.factory, &mut config.factory),
+        (features.encryption, &mut config.encryption),
+        (features.inspect, &mut config.inspect),
+        (features.http_tools, &mut config.http_tools),
+    ] {
+        if let Some(enabled) = enabled {
+            *flag = enabled;
         }
     }
+    Ok(())
 }
 
 /// Parses the mcp!{} configuration.
 fn parse_mcp_config(input: TokenStream) -> MacroResult<McpConfig> {
     let mut config = McpConfig::default();
+    apply_mcp_args(input, &mut config)?;
+    Ok(config)
+}
 
+/// Overrides `config` with the arguments given to mcp!{}.
+fn apply_mcp_args(input: TokenStream, config: &mut McpConfig) -> MacroResult<()> {
     // Try to parse as key=value assignments first
     let parser = syn::punctuated::Punctuated::<ExprAssign, Token![,]>::parse_terminated;
     if let Ok(assignments) = parser.parse2(input.clone()) {
@@ -71,6 +217,94 @@ fn parse_mcp_config(input: TokenStream) -> MacroResult<McpConfig> {
                             MacroError::configuration("rate_limit must be a boolean value")
                         })?;
                     }
+                    "metrics" => {
+                        config.metrics = value.parse::<bool>().map_err(|_| {
+                            MacroError::configuration("metrics must be a boolean value")
+                        })?;
+                    }
+                    "blobs" => {
+                        config.blobs = value.parse::<bool>().map_err(|_| {
+                            MacroError::configuration("blobs must be a boolean value")
+                        })?;
+                    }
+                    "events" => {
+                        config.events = value.parse::<bool>().map_err(|_| {
+                            MacroError::configuration("events must be a boolean value")
+                        })?;
+                    }
+                    "archive_store" => {
+                        config.archive_store = value.parse::<bool>().map_err(|_| {
+                            MacroError::configuration("archive_store must be a boolean value")
+                        })?;
+                    }
+                    "replication" => {
+                        config.replication = value.parse::<bool>().map_err(|_| {
+                            MacroError::configuration("replication must be a boolean value")
+                        })?;
+                    }
+                    "jobs" => {
+                        config.jobs = value.parse::<bool>().map_err(|_| {
+                            MacroError::configuration("jobs must be a boolean value")
+                        })?;
+                    }
+                    "secrets" => {
+                        config.secrets = value.parse::<bool>().map_err(|_| {
+                            MacroError::configuration("secrets must be a boolean value")
+                        })?;
+                    }
+                    "signing" => {
+                        config.signing = value.parse::<bool>().map_err(|_| {
+                            MacroError::configuration("signing must be a boolean value")
+                        })?;
+                    }
+                    "factory" => {
+                        config.factory = value.parse::<bool>().map_err(|_| {
+                            MacroError::configuration("factory must be a boolean value")
+                        })?;
+                    }
+                    "encryption" => {
+                        config.encryption = value.parse::<bool>().map_err(|_| {
+                            MacroError::configuration("encryption must be a boolean value")
+                        })?;
+                    }
+                    "prefix" => {
+                        validate_prefix(&value)?;
+                        config.prefix = value;
+                    }
+                    "inspect" => {
+                        config.inspect = value.parse::<bool>().map_err(|_| {
+                            MacroError::configuration("inspect must be a boolean value")
+                        })?;
+                    }
+                    "inspect_allow" => {
+                        config.inspect_allow = value
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|name| !name.is_empty())
+                            .map(str::to_string)
+                            .collect();
+                    }
+                    "http_tools" => {
+                        config.http_tools = value.parse::<bool>().map_err(|_| {
+                            MacroError::configuration("http_tools must be a boolean value")
+                        })?;
+                    }
+                    "max_request_bytes" => {
+                        config.max_request_bytes =
+                            parse_payload_limit("max_request_bytes", &value)?;
+                    }
+                    "max_response_bytes" => {
+                        config.max_response_bytes =
+                            parse_payload_limit("max_response_bytes", &value)?;
+                    }
+                    "cors_origin" => {
+                        if value.is_empty() {
+                            return Err(MacroError::configuration(
+                                "cors_origin must be an origin such as \"https://app.example.com\" or \"*\"",
+                            ));
+                        }
+                        config.cors_origin = value;
+                    }
                     _ => {
                         return Err(MacroError::configuration(format!(
                             "Unknown configuration key: {key}"
@@ -78,17 +312,16 @@ fn parse_mcp_config(input: TokenStream) -> MacroResult<McpConfig> {
                     }
                 }
             }
-            return Ok(config);
+            return Ok(());
         }
     }
 
     // Try builder pattern: .with_auth().build()
     if let Ok(expr) = syn::parse2::<Expr>(input) {
-        parse_builder_pattern(&expr, &mut config);
-        return Ok(config);
+        parse_builder_pattern(&expr, config);
     }
 
-    Ok(config)
+    Ok(())
 }
 
 /// Parses builder pattern: `.with_auth().build()`
@@ -103,12 +336,59 @@ fn parse_builder_pattern(expr: &Expr, config: &mut McpConfig) {
         match method_name.as_str() {
             "with_auth" => config.auth = true,
             "with_rate_limit" => config.rate_limit = true,
+            "with_metrics" => config.metrics = true,
+            "with_blobs" => config.blobs = true,
+            "with_events" => config.events = true,
+            "with_archive_store" => config.archive_store = true,
+            "with_replication" => config.replication = true,
+            "with_jobs" => config.jobs = true,
+            "with_secrets" => config.secrets = true,
+            "with_signing" => config.signing = true,
+            "with_factory" => config.factory = true,
+            "with_encryption" => config.encryption = true,
+            "with_inspect" => config.inspect = true,
+            "with_http_tools" => config.http_tools = true,
             "build" => {} // Terminal method, no-op
             _ => {}
         }
     }
 }
 
+/// Parses a payload size limit given to mcp!{}.
+fn parse_payload_limit(key: &str, value: &str) -> MacroResult<usize> {
+    let bytes = value
+        .replace('_', "")
+        .parse::<u64>()
+        .map_err(|_| MacroError::configuration(format!("{key} must be a number of bytes")))?;
+    validate_payload_limit(key, bytes)
+}
+
+/// Checks that a payload size limit is positive and fits in one message.
+fn validate_payload_limit(key: &str, bytes: u64) -> MacroResult<usize> {
+    match usize::try_from(bytes) {
+        Ok(bytes) if bytes > 0 && bytes <= MAX_MESSAGE_BYTES => Ok(bytes),
+        _ => Err(MacroError::configuration(format!(
+            "{key} must be between 1 and {MAX_MESSAGE_BYTES} bytes, the message size limit"
+        ))),
+    }
+}
+
+/// Checks that prefixed tool names are still valid tool IDs.
+fn validate_prefix(prefix: &str) -> MacroResult<()> {
+    let valid_start = prefix.starts_with(|c: char| c.is_ascii_alphabetic());
+    let valid_chars = prefix
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+    if valid_start && valid_chars {
+        Ok(())
+    } else {
+        Err(MacroError::configuration(format!(
+            "prefix '{prefix}' must start with an ASCII letter and contain only \
+             ASCII letters, digits, '_', '.' or '-'"
+        )))
+    }
+}
+
 /// Extracts the key from an assignment expression.
 fn extract_assignment_key(expr: &Expr) -> MacroResult<String> {
     match expr {
@@ -138,6 +418,10 @@ fn extract_assignment_value(expr: &Expr) -> MacroResult<String> {
             lit: Lit::Bool(lit_bool),
             ..
         }) => Ok(lit_bool.value.to_string()),
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(lit_int),
+            ..
+        }) => Ok(lit_int.base10_digits().to_string()),
         Expr::Path(ExprPath { path, .. }) => {
             if let Some(ident) = path.get_ident() {
                 match ident.to_string().as_str() {
@@ -154,7 +438,7 @@ fn extract_assignment_value(expr: &Expr) -> MacroResult<String> {
             }
         }
         _ => Err(MacroError::configuration(
-            "Configuration values must be string or boolean literals",
+            "Configuration values must be string, integer or boolean literals",
         )),
     }
 }
@@ -162,8 +446,20 @@ fn extract_assignment_value(expr: &Expr) -> MacroResult<String> {
 /// Generates the complete MCP server code.
 fn generate_mcp_server_code(config: &McpConfig) -> TokenStream {
     let server_info = generate_server_info(config);
-    let list_tools_endpoint = generate_list_tools_endpoint();
-    let call_tool_endpoint = generate_call_tool_endpoint();
+    let list_tools_endpoint = generate_list_tools_endpoint(&config.prefix);
+    let call_tool_endpoint = generate_call_tool_endpoint(&config.prefix);
+    let tools_fingerprint_endpoint = generate_tools_fingerprint_endpoint();
+    let memory_report_endpoint = generate_memory_report_endpoint();
+    let health_endpoint = generate_health_endpoint(config);
+    let revenue_report_endpoint = generate_revenue_report_endpoint(config.auth);
+    let cycles_status_endpoint = generate_cycles_status_endpoint();
+    let retention_status_endpoint = generate_retention_status_endpoint();
+    let archive_status_endpoint = generate_archive_status_endpoint();
+    let usage_stats_endpoint = generate_usage_stats_endpoint(config.auth);
+    let payload_limits = generate_payload_limits(config);
+    let tool_switch_functions = generate_tool_switch_functions(config.auth, &config.prefix);
+    let maintenance_functions = generate_maintenance_functions(config.auth);
+    let guest_policy_functions = generate_guest_policy_functions(config.auth);
     let candid_export = generate_candid_export();
 
     // Generate auth management functions if auth is enabled
@@ -173,6 +469,104 @@ fn generate_mcp_server_code(config: &McpConfig) -> TokenStream {
         quote! {}
     };
 
+    let invite_tools = if config.auth {
+        generate_invite_tools()
+    } else {
+        quote! {}
+    };
+
+    // Users may define their own http_request, so its routes are opt-in
+    let http_request_endpoint = if config.metrics || config.blobs || config.http_tools {
+        generate_http_request_endpoint(config)
+    } else {
+        quote! {}
+    };
+
+    let metrics_access_endpoints = if config.metrics {
+        generate_metrics_access_endpoints(config.auth)
+    } else {
+        quote! {}
+    };
+
+    let api_key_functions = if config.http_tools {
+        generate_api_key_functions(config.auth)
+    } else {
+        quote! {}
+    };
+
+    let blob_functions = if config.blobs {
+        generate_blob_functions(config.auth)
+    } else {
+        quote! {}
+    };
+
+    let event_functions = if config.events {
+        generate_event_functions(config.auth)
+    } else {
+        quote! {}
+    };
+
+    let archive_store_functions = if config.archive_store {
+        generate_archive_store_functions(config.auth)
+    } else {
+        quote! {}
+    };
+
+    let replication_functions = if config.replication {
+        generate_replication_functions(config.auth)
+    } else {
+        quote! {}
+    };
+
+    let job_tools = if config.jobs {
+        generate_job_tools()
+    } else {
+        quote! {}
+    };
+
+    let secret_tools = if config.secrets {
+        generate_secret_tools(config.auth)
+    } else {
+        quote! {}
+    };
+
+    let signing_tools = if config.signing {
+        generate_signing_tools(config.auth)
+    } else {
+        quote! {}
+    };
+
+    let factory_tools = if config.factory {
+        generate_factory_tools(config.auth)
+    } else {
+        quote! {}
+    };
+
+    let args_key_endpoint = if config.encryption {
+        generate_args_key_endpoint()
+    } else {
+        quote! {}
+    };
+
+    // Snapshot endpoints expose all canister state, so they require admin auth
+    let snapshot_functions = if config.auth {
+        generate_snapshot_functions()
+    } else {
+        quote! {}
+    };
+
+    let auth_transfer_functions = if config.auth {
+        generate_auth_transfer_functions()
+    } else {
+        quote! {}
+    };
+
+    let inspect_message_hook = if config.inspect {
+        generate_inspect_message_hook(config)
+    } else {
+        quote! {}
+    };
+
     quote! {
         // Server information
         #server_info
@@ -180,10 +574,92 @@ fn generate_mcp_server_code(config: &McpConfig) -> TokenStream {
         // MCP endpoints
         #list_tools_endpoint
         #call_tool_endpoint
+        #tools_fingerprint_endpoint
+
+        // Stable memory inspection
+        #memory_report_endpoint
+
+        // Liveness and readiness
+        #health_endpoint
+
+        // Payment revenue
+        #revenue_report_endpoint
+
+        // Cycle balance
+        #cycles_status_endpoint
+
+        // Data retention
+        #retention_status_endpoint
+
+        // Log archiving
+        #archive_status_endpoint
+
+        // Usage analytics
+        #usage_stats_endpoint
+
+        // Request and response size limits
+        #payload_limits
+
+        // Owner switches for individual tools
+        #tool_switch_functions
+
+        // Maintenance mode
+        #maintenance_functions
+
+        // Guest access for callers not on the whitelist
+        #guest_policy_functions
+
+        // Prometheus metrics, blob downloads and the tool API (if enabled)
+        #http_request_endpoint
+
+        // Scrape token and allowlist for /metrics (if enabled)
+        #metrics_access_endpoints
+
+        // API keys for the tool API (if enabled)
+        #api_key_functions
+
+        // Blob uploads (if enabled)
+        #blob_functions
+
+        // Event subscriptions (if enabled)
+        #event_functions
+
+        // Archive segments received from other canisters (if enabled)
+        #archive_store_functions
+
+        // Read replicas (if enabled)
+        #replication_functions
+
+        // Long-running job tools (if enabled)
+        #job_tools
+
+        // Secret management tools (if enabled)
+        #secret_tools
+
+        // Threshold ECDSA signing tools (if enabled)
+        #signing_tools
+
+        // Per-user instance factory tools (if enabled)
+        #factory_tools
+
+        // Key for encrypted tool arguments (if enabled)
+        #args_key_endpoint
 
         // Authentication management (if enabled)
         #auth_functions
 
+        // Invite codes for self-registration (if auth is enabled)
+        #invite_tools
+
+        // Backup and restore (if auth is enabled)
+        #snapshot_functions
+
+        // Auth state migration between canisters (if auth is enabled)
+        #auth_transfer_functions
+
+        // Drops unwanted update calls before they run (if enabled)
+        #inspect_message_hook
+
         // Candid interface export
         #candid_export
     }
@@ -194,6 +670,7 @@ fn generate_server_info(config: &McpConfig) -> TokenStream {
     let name = &config.name;
     let description = &config.description;
     let version = &config.version;
+    let prefix = &config.prefix;
 
     quote! {
         /// Returns server information
@@ -205,8 +682,11 @@ fn generate_server_info(config: &McpConfig) -> TokenStream {
                 "version": #version,
                 "protocol_version": "2024-11-05",
                 "capabilities": {
-                    "tools": {}
-                }
+                    "tools": { "listChanged": true }
+                },
+                "tools_sequence": mcp_tools_sequence(),
+                "maintenance": ::icarus_core::maintenance::status(),
+                "tool_prefix": #prefix
             });
 
             serde_json::to_string(&info).unwrap_or_else(|_| "{}".to_string())
@@ -215,7 +695,21 @@ fn generate_server_info(config: &McpConfig) -> TokenStream {
 }
 
 /// Generates the list tools endpoint.
-fn generate_list_tools_endpoint() -> TokenStream {
+///
+/// With a prefix, tools are listed under their prefixed names; the registry
+/// keeps the names from `#[tool]`.
+fn generate_list_tools_endpoint(prefix: &str) -> TokenStream {
+    let apply_prefix = if prefix.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            .map(|mut tool| {
+                tool.name = ::std::borrow::Cow::Owned(format!("{}{}", #prefix, tool.name));
+                tool
+            })
+        }
+    };
+
     quote! {
         /// Lists all available tools (native Vec for bridge)
         #[ic_cdk::query]
@@ -223,6 +717,8 @@ fn generate_list_tools_endpoint() -> TokenStream {
             ::icarus_runtime::TOOL_REGISTRY
                 .iter()
                 .map(|tool_fn| tool_fn())
+                .filter(|tool| ::icarus_core::tool_switches::is_enabled(&tool.name))
+                #apply_prefix
                 .collect()
         }
 
@@ -237,20 +733,57 @@ fn generate_list_tools_endpoint() -> TokenStream {
 
             serde_json::to_string(&tool_list).unwrap_or_else(|_| r#"{"tools": []}"#.to_string())
         }
+
+        /// Lists all available tools with titles and descriptions in
+        /// `locale` where translated (JSON string for MCP protocol)
+        #[ic_cdk::query]
+        pub fn mcp_list_tools_in(locale: String) -> String {
+            // Queries discard state, so register the bundles for this call
+            ::icarus_runtime::initialize_executors();
+
+            let tools: Vec<::icarus_core::Tool> = ::icarus_runtime::TOOL_REGISTRY
+                .iter()
+                .map(|tool_fn| tool_fn())
+                .filter(|tool| ::icarus_core::tool_switches::is_enabled(&tool.name))
+                .map(|tool| ::icarus_core::i18n::localize_tool(tool, &locale))
+                #apply_prefix
+                .collect();
+
+            let tool_list = serde_json::json!({
+                "tools": tools
+            });
+
+            serde_json::to_string(&tool_list).unwrap_or_else(|_| r#"{"tools": []}"#.to_string())
+        }
     }
 }
 
 /// Generates the call tool endpoint with helper functions for cleaner generated code.
-fn generate_call_tool_endpoint() -> TokenStream {
+fn generate_call_tool_endpoint(prefix: &str) -> TokenStream {
+    // Clients call tools by their listed, prefixed names
+    let strip_prefix = if prefix.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            let tool_name = match tool_name.strip_prefix(#prefix) {
+                Some(name) => name,
+                None => return create_jsonrpc_error(request_id, -32601, format!("Tool not found: {}", tool_name)),
+            };
+        }
+    };
+
     quote! {
-        /// Helper function to create JSON-RPC error responses
+        /// Helper function to create JSON-RPC error responses, with the
+        /// stable code of the condition in `data`
         fn create_jsonrpc_error(id: String, code: i32, message: String) -> String {
+            let error_code = ::icarus_core::error_codes::IcarusCode::for_jsonrpc(code).code();
             let error = serde_json::json!({
                 "jsonrpc": "2.0",
                 "id": id,
                 "error": {
                     "code": code,
-                    "message": message
+                    "message": message,
+                    "data": ::icarus_core::error_codes::jsonrpc_data(error_code)
                 }
             });
             serde_json::to_string(&error).unwrap_or_else(|_| "{}".to_string())
@@ -266,12 +799,124 @@ fn generate_call_tool_endpoint() -> TokenStream {
             serde_json::to_string(&response).unwrap_or_else(|_| "{}".to_string())
         }
 
+        /// Converts a tool's result into an RMCP CallToolResult, recording
+        /// sampling requests as pending for `caller`
+        fn to_call_tool_result(
+            tool_result: ::icarus_core::LegacyToolResult<'static>,
+            caller: candid::Principal,
+        ) -> ::icarus_core::CallToolResult {
+            match tool_result {
+                success @ ::icarus_core::LegacyToolResult::Success { .. } => {
+                    // Sampling requests go to the bridge, which forwards them to
+                    // the client and calls mcp_sampling_result with the answer
+                    if let Some(request) = ::icarus_core::sampling::from_tool_result(&success) {
+                        let sampling = ::icarus_core::sampling::begin(request, caller);
+                        return ::icarus_core::CallToolResult {
+                            content: vec![::icarus_core::Content::text("Waiting for sampling/createMessage")],
+                            structured_content: Some(serde_json::json!({ "sampling": sampling })),
+                            is_error: Some(false),
+                            meta: None,
+                        };
+                    }
+
+                    // Text, or the tool's own content array for `Vec<Content>` tools
+                    let content = ::icarus_core::content::from_tool_result(&success);
+                    ::icarus_core::CallToolResult {
+                        content,
+                        structured_content: None,
+                        is_error: Some(false),
+                        meta: None,
+                    }
+                }
+                ::icarus_core::LegacyToolResult::Error { message, code, .. } => {
+                    // Create CallToolResult with error content
+                    let content = vec![
+                        ::icarus_core::Content::text(message.as_ref())
+                    ];
+                    // Clients branch on the stable code, not the text
+                    let structured_content = code.map(|code| serde_json::json!({
+                        ::icarus_core::error_codes::CODE_KEY: code
+                    }));
+                    ::icarus_core::CallToolResult {
+                        content,
+                        structured_content,
+                        is_error: Some(true),
+                        meta: None,
+                    }
+                }
+                ::icarus_core::LegacyToolResult::Pending { status, .. } => {
+                    // Create CallToolResult with pending status
+                    let message = status.as_deref().unwrap_or("Tool execution pending");
+                    let content = vec![
+                        ::icarus_core::Content::text(message)
+                    ];
+                    ::icarus_core::CallToolResult {
+                        content,
+                        structured_content: None,
+                        is_error: Some(false),
+                        meta: None,
+                    }
+                }
+                ::icarus_core::LegacyToolResult::Timeout { timeout_ms, elapsed_ms } => {
+                    // Report how long the tool ran so clients can tell a hang from a slow call
+                    let content = vec![
+                        ::icarus_core::Content::text(format!("Tool timed out after {}ms", timeout_ms))
+                    ];
+                    ::icarus_core::CallToolResult {
+                        content,
+                        structured_content: Some(serde_json::json!({
+                            "timeout_ms": timeout_ms,
+                            "elapsed_ms": elapsed_ms,
+                        })),
+                        is_error: Some(true),
+                        meta: None,
+                    }
+                }
+            }
+        }
+
         /// Executes a tool with the given parameters (RMCP-compliant)
         #[ic_cdk::update]
         pub async fn mcp_call_tool(request: String) -> String {
+            call_tool_as(request, ::ic_cdk::caller()).await
+        }
+
+        /// Executes a `#[tool(composite_query)]` tool as a composite query,
+        /// so its calls to other canisters' queries stay on the query path.
+        /// Anything the tool changes is discarded
+        #[ic_cdk::query(composite = true)]
+        pub async fn mcp_call_tool_composite(request: String) -> String {
+            run_tool_call(request, ::ic_cdk::caller(), true).await
+        }
+
+        /// Executes a JSON-RPC `tools/call` request on behalf of `caller`
+        async fn call_tool_as(request: String, caller: candid::Principal) -> String {
+            run_tool_call(request, caller, false).await
+        }
+
+        /// Executes a JSON-RPC `tools/call` request on behalf of `caller`, as
+        /// a composite query if `composite`
+        async fn run_tool_call(request: String, caller: candid::Principal, composite: bool) -> String {
+            // Refuse oversized requests before parsing them
+            if let Err(e) = MCP_PAYLOAD_LIMITS.check_request(request.len()) {
+                return create_payload_too_large_error("null".to_string(), &e);
+            }
+
+            // A build with conflicting tools serves none of them
+            if let Err(e) = ::icarus_runtime::validate_tool_definitions() {
+                return create_jsonrpc_error("null".to_string(), -32603, e.to_string());
+            }
+
             // Initialize executors on first call
             ::icarus_runtime::initialize_executors();
 
+            // Persist the tool list sequence, which queries can only compute;
+            // a composite query would discard these writes
+            if !composite {
+                ::icarus_core::tool_changes::record(::icarus_runtime::ToolRegistry::fingerprint());
+                ::icarus_core::health::record_start();
+            }
+
             // Parse the raw JSON to extract tool name and arguments
             let request_json: serde_json::Value = match serde_json::from_str(&request) {
                 Ok(json) => json,
@@ -284,6 +929,25 @@ fn generate_call_tool_endpoint() -> TokenStream {
                 .unwrap_or("null")
                 .to_string();
 
+            // Reject every tool call during maintenance; queries keep working
+            let maintenance = ::icarus_core::maintenance::status();
+            if maintenance.enabled {
+                let error = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request_id,
+                    "error": {
+                        "code": ::icarus_core::maintenance::SERVICE_UNAVAILABLE_CODE,
+                        "message": maintenance.message,
+                        "data": {
+                            "maintenance": true,
+                            "since": maintenance.since,
+                            "code": ::icarus_core::error_codes::IcarusCode::Maintenance.code()
+                        }
+                    }
+                });
+                return serde_json::to_string(&error).unwrap_or_else(|_| "{}".to_string());
+            }
+
             // Extract tool name and arguments from params
             let params = match request_json.get("params") {
                 Some(p) => p,
@@ -294,11 +958,24 @@ fn generate_call_tool_endpoint() -> TokenStream {
                 Some(name) => name,
                 None => return create_jsonrpc_error(request_id, -32602, "Missing tool name in params".to_string()),
             };
+            #strip_prefix
+
+            // Continue the client's trace, or start one for this call
+            let trace_context = ::icarus_core::trace::TraceContext::from_meta(params)
+                .map(|parent| parent.child())
+                .unwrap_or_else(::icarus_core::trace::TraceContext::generate);
 
             let arguments = params.get("arguments")
                 .cloned()
                 .unwrap_or(serde_json::json!({}));
 
+            // Decrypt arguments the bridge encrypted, and refuse plain
+            // arguments to #[tool(encrypted)] tools
+            let arguments = match ::icarus_core::encrypted_args::open(tool_name, arguments).await {
+                Ok(arguments) => arguments,
+                Err(e) => return create_jsonrpc_error(request_id, -32602, e.to_string()),
+            };
+
             // Find the tool in the registry
             let tool_id = match ::icarus_core::ToolId::new(tool_name) {
                 Ok(id) => id,
@@ -311,59 +988,1154 @@ fn generate_call_tool_endpoint() -> TokenStream {
                 Err(e) => return create_jsonrpc_error(request_id, -32602, format!("Failed to serialize arguments: {}", e)),
             };
 
-            // Execute the tool using the registry
-            let tool_result = match ::icarus_runtime::ToolRegistry::execute_tool_sync(&tool_id, &arguments_str) {
-                Some(Ok(result)) => result,
-                Some(Err(e)) => return create_jsonrpc_error(request_id, -32603, format!("Tool execution error: {}", e)),
-                None => return create_jsonrpc_error(request_id, -32601, format!("Tool not found: {}", tool_name)),
-            };
+            // Tools the owner switched off are not charged or run
+            if !::icarus_core::tool_switches::is_enabled(tool_name) {
+                return create_jsonrpc_error(request_id, -32601, format!("Tool disabled: {}", tool_name));
+            }
 
-            // Convert LegacyToolResult to RMCP CallToolResult
-            let call_tool_result = match tool_result {
-                ::icarus_core::LegacyToolResult::Success { result, .. } => {
-                    // Create CallToolResult with text content
-                    let content = vec![
-                        ::icarus_core::Content::text(result.as_ref())
-                    ];
-                    ::icarus_core::CallToolResult {
-                        content,
-                        structured_content: None,
-                        is_error: Some(false),
-                        meta: None,
-                    }
+            // A composite query would silently discard what other tools change
+            if composite && !::icarus_core::composite::is_composite(tool_name) {
+                return create_jsonrpc_error(
+                    request_id,
+                    -32601,
+                    format!("{} is not a composite query tool; call it with mcp_call_tool", tool_name),
+                );
+            }
+
+            // Replicas only run read-only tools; writes go to the primary
+            if let Some(primary) = ::icarus_core::replication::primary() {
+                let serves = ::icarus_runtime::TOOL_REGISTRY
+                    .iter()
+                    .map(|tool_fn| tool_fn())
+                    .any(|tool| tool.name == tool_name && ::icarus_core::replication::serves(&tool));
+                if !serves {
+                    return create_jsonrpc_error(
+                        request_id,
+                        ::icarus_core::replication::READ_ONLY_REPLICA_CODE,
+                        format!("Read replica cannot run {}; call the primary {}", tool_name, primary),
+                    );
                 }
-                ::icarus_core::LegacyToolResult::Error { message, .. } => {
-                    // Create CallToolResult with error content
-                    let content = vec![
-                        ::icarus_core::Content::text(message.as_ref())
-                    ];
-                    ::icarus_core::CallToolResult {
-                        content,
-                        structured_content: None,
-                        is_error: Some(true),
-                        meta: None,
+            }
+
+            // Unknown tools are refused before the caller is charged
+            if !::icarus_runtime::ToolRegistry::has_tool(&tool_id) {
+                return create_jsonrpc_error(request_id, -32601, format!("Tool not found: {}", tool_name));
+            }
+
+            let started_at = ::icarus_core::time::now_nanos();
+
+            // Reject expensive tools while cycles are below the reserve
+            if let Err(e) = ::icarus_core::cycles::guard_tool(tool_name) {
+                return create_jsonrpc_error(request_id, -32002, e.to_string());
+            }
+
+            // A composite query cannot make the ledger update call, so tools
+            // priced at runtime are only sold through mcp_call_tool
+            if composite && ::icarus_core::payments::tool_price(tool_name).is_some() {
+                return create_jsonrpc_error(
+                    request_id,
+                    -32001,
+                    format!("Payment required: {} is paid; call it with mcp_call_tool", tool_name),
+                );
+            }
+
+            // Charge the caller if the tool has a price
+            let receipt = if composite {
+                None
+            } else {
+                match ::icarus_core::payments::charge(tool_name, caller).await {
+                    Ok(receipt) => receipt,
+                    Err(e) => return create_jsonrpc_error(request_id, -32001, format!("Payment required: {}", e)),
+                }
+            };
+
+            // Tools that take a `ToolContext` see this call's caller and deadline
+            let timeout = ::icarus_runtime::ToolRegistry::tool_timeout(&tool_id)
+                .unwrap_or_else(::icarus_runtime::default_timeout);
+            let deadline = started_at.saturating_add(u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX));
+            ::icarus_core::context::set_current(Some(
+                ::icarus_core::context::ToolContext::new(caller, tool_name)
+                    .with_request_id(request_id.clone())
+                    .with_deadline(deadline),
+            ));
+
+            // Execute the tool using the registry, with the trace installed
+            // for log correlation
+            ::icarus_core::trace::set_current(Some(trace_context));
+            ::icarus_core::composite::set_active(composite);
+            let execution = match ::icarus_runtime::ToolRegistry::execute_tool_sync(&tool_id, &arguments_str) {
+                Some(outcome) => Some(outcome),
+                // Async tools, such as composite queries awaiting other canisters
+                None => ::icarus_runtime::ToolRegistry::execute_tool_async(&tool_id, &arguments_str).await,
+            };
+            if let Some(Err(e)) = &execution {
+                ::icarus_core::logging::error(format_args!("Tool '{}' failed: {}", tool_name, e));
+            }
+            ::icarus_core::composite::set_active(false);
+            ::icarus_core::trace::set_current(None);
+            ::icarus_core::context::set_current(None);
+
+            // Record usage for executed tools, unless a composite query would
+            // discard it
+            if let Some(outcome) = execution.as_ref().filter(|_| !composite) {
+                let success = matches!(outcome, Ok(::icarus_core::LegacyToolResult::Success { .. } | ::icarus_core::LegacyToolResult::Pending { .. }));
+                ::icarus_core::metrics::record_call(
+                    tool_name,
+                    &caller.to_text(),
+                    ::icarus_core::time::now_nanos().saturating_sub(started_at),
+                    success,
+                );
+            }
+
+            // A paid call that failed is refunded; when the refund fails the
+            // caller is told, with the block of the charge to claim it by
+            if let Some(receipt) = &receipt {
+                let succeeded = matches!(&execution, Some(Ok(result)) if !result.is_failure());
+                if !succeeded {
+                    if let Err(e) = ::icarus_core::payments::refund(receipt, caller).await {
+                        ::icarus_core::logging::error(format_args!("Refund for '{}' failed: {}", tool_name, e));
+                        return create_jsonrpc_error(
+                            request_id,
+                            -32001,
+                            format!(
+                                "Tool '{}' failed and its refund failed: {}; the charge is ledger block {}",
+                                tool_name, e, receipt.block_index
+                            ),
+                        );
+                    }
+                }
+            }
+
+            let tool_result = match execution {
+                Some(Ok(result)) => result,
+                Some(Err(e)) => return create_jsonrpc_error(request_id, -32603, format!("Tool execution error: {}", e)),
+                None => return create_jsonrpc_error(request_id, -32601, format!("Tool not found: {}", tool_name)),
+            };
+
+            let call_tool_result = to_call_tool_result(tool_result, caller);
+
+            // Serialize the CallToolResult and return success response
+            let response = match serde_json::to_value(&call_tool_result) {
+                Ok(result_json) => create_jsonrpc_success(request_id.clone(), result_json),
+                Err(e) => return create_jsonrpc_error(request_id, -32603, format!("Failed to serialize result: {}", e)),
+            };
+
+            // The tool ran, but a reply over the limit would trap
+            match MCP_PAYLOAD_LIMITS.check_response(tool_name, response.len()) {
+                Ok(()) => response,
+                Err(e) => create_payload_too_large_error(request_id, &e),
+            }
+        }
+
+        /// Completes a tool that returned a sampling request with the client's
+        /// `sampling/createMessage` result, or its error, forwarded by the bridge
+        #[ic_cdk::update]
+        pub fn mcp_sampling_result(request: String) -> String {
+            let request_json: serde_json::Value = match serde_json::from_str(&request) {
+                Ok(json) => json,
+                Err(e) => return create_jsonrpc_error("null".to_string(), -32700, format!("Parse error: {}", e)),
+            };
+            let request_id = request_json.get("id")
+                .and_then(|id| id.as_str())
+                .unwrap_or("null")
+                .to_string();
+
+            let params = &request_json["params"];
+            let sampling_id = match params.get("samplingId").and_then(|id| id.as_u64()) {
+                Some(id) => id,
+                None => return create_jsonrpc_error(request_id, -32602, "Missing samplingId in params".to_string()),
+            };
+
+            let caller = ::ic_cdk::caller();
+            let outcome = match params.get("error") {
+                Some(error) => {
+                    let reason = error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error");
+                    ::icarus_core::sampling::decline(sampling_id, caller, reason)
+                }
+                None => ::icarus_core::sampling::resume(sampling_id, caller, &params["result"]),
+            };
+            let tool_result = match outcome {
+                Ok(result) => result,
+                Err(e) => return create_jsonrpc_error(request_id, -32602, e.to_string()),
+            };
+
+            match serde_json::to_value(&to_call_tool_result(tool_result, caller)) {
+                Ok(result_json) => create_jsonrpc_success(request_id, result_json),
+                Err(e) => create_jsonrpc_error(request_id, -32603, format!("Failed to serialize result: {}", e)),
+            }
+        }
+    }
+}
+
+/// Generates the stable memory usage report endpoint.
+fn generate_memory_report_endpoint() -> TokenStream {
+    quote! {
+        /// Returns stable memory usage per memory id (JSON string)
+        #[ic_cdk::query]
+        pub fn mcp_memory_report() -> String {
+            let report = ::icarus_core::memory::usage_report();
+            let mut json = serde_json::to_value(&report).unwrap_or_else(|_| serde_json::json!({}));
+            if let Some(object) = json.as_object_mut() {
+                object.insert(
+                    "usage_percent".to_string(),
+                    serde_json::json!(report.usage_percent()),
+                );
+                object.insert(
+                    "compression_ratio".to_string(),
+                    serde_json::json!(report.compression_ratio()),
+                );
+            }
+
+            serde_json::to_string(&json).unwrap_or_else(|_| "{}".to_string())
+        }
+    }
+}
+
+/// Generates the health endpoint polled by monitors and the bridge.
+fn generate_health_endpoint(config: &McpConfig) -> TokenStream {
+    let version = &config.version;
+
+    quote! {
+        /// Returns version, uptime, tool count, memory, cycles and readiness (JSON string)
+        #[ic_cdk::query]
+        pub fn mcp_health() -> String {
+            let status = ::icarus_core::health::check(#version, list_tools().len());
+            serde_json::to_string(&status).unwrap_or_else(|_| "{}".to_string())
+        }
+    }
+}
+
+/// Generates the payment revenue report endpoint for admins, or for controllers
+/// without auth.
+fn generate_revenue_report_endpoint(auth: bool) -> TokenStream {
+    let admin_check = generate_owner_check(auth);
+
+    quote! {
+        /// Returns revenue collected by paid tools
+        #[ic_cdk::query]
+        pub fn get_revenue_report() -> Result<::icarus_core::payments::RevenueReport, String> {
+            #admin_check
+
+            Ok(::icarus_core::payments::revenue_report())
+        }
+    }
+}
+
+/// Generates the endpoints the bridge polls to detect tool list changes.
+fn generate_tools_fingerprint_endpoint() -> TokenStream {
+    quote! {
+        /// Returns a hash of the tool definitions as 16 hex digits
+        #[ic_cdk::query]
+        pub fn mcp_tools_fingerprint() -> String {
+            format!("{:016x}", ::icarus_runtime::ToolRegistry::fingerprint())
+        }
+
+        /// Returns a number that increases whenever the tool definitions change
+        #[ic_cdk::query]
+        pub fn mcp_tools_sequence() -> u64 {
+            ::icarus_core::tool_changes::record(::icarus_runtime::ToolRegistry::fingerprint())
+        }
+    }
+}
+
+/// Generates the check that the caller owns the canister.
+///
+/// Without `auth` there are no admins, so only controllers pass. The caller
+/// comes from `icarus_core::context`, like the other checks in generated
+/// tools, so a tool called through the HTTP gateway checks the principal it
+/// runs for.
+fn generate_owner_check(auth: bool) -> TokenStream {
+    if auth {
+        quote! {
+            let caller = ::icarus_core::context::caller();
+            if !::icarus_core::auth::has_admin_access(&caller) {
+                return Err("Admin access required".to_string());
+            }
+        }
+    } else {
+        quote! {
+            let caller = ::icarus_core::context::caller();
+            if !::ic_cdk::api::is_controller(&caller) {
+                return Err("Controller access required".to_string());
+            }
+        }
+    }
+}
+
+/// Generates the endpoints that disable and re-enable tools.
+fn generate_tool_switch_functions(auth: bool, prefix: &str) -> TokenStream {
+    let owner_check = generate_owner_check(auth);
+
+    quote! {
+        /// Enables or disables a tool; returns whether its state changed
+        #[ic_cdk::update]
+        pub fn set_tool_enabled(name: String, enabled: bool) -> Result<bool, String> {
+            #owner_check
+
+            // Accept the listed name as well as the registered one
+            let name = name.strip_prefix(#prefix).unwrap_or(&name);
+            if !::icarus_runtime::TOOL_REGISTRY.iter().any(|tool_fn| tool_fn().name == name) {
+                return Err(format!("Unknown tool: {}", name));
+            }
+
+            Ok(::icarus_core::tool_switches::set_enabled(name, enabled, caller))
+        }
+
+        /// Returns the latest tool enable/disable changes, newest first
+        #[ic_cdk::query]
+        pub fn get_tool_audit_log(
+            limit: Option<u32>,
+        ) -> Result<Vec<::icarus_core::tool_switches::ToolSwitchChange>, String> {
+            #owner_check
+
+            Ok(::icarus_core::tool_switches::audit_log(limit.unwrap_or(100) as usize))
+        }
+    }
+}
+
+/// Generates the maintenance mode endpoints.
+fn generate_maintenance_functions(auth: bool) -> TokenStream {
+    let owner_check = generate_owner_check(auth);
+
+    quote! {
+        /// Switches maintenance mode, which rejects tool calls with `message`
+        #[ic_cdk::update]
+        pub fn set_maintenance(enabled: bool, message: Option<String>) -> Result<(), String> {
+            #owner_check
+
+            ::icarus_core::maintenance::set(enabled, message.unwrap_or_default(), caller);
+            Ok(())
+        }
+
+        /// Returns the maintenance mode switch
+        #[ic_cdk::query]
+        pub fn get_maintenance_status() -> ::icarus_core::maintenance::MaintenanceStatus {
+            ::icarus_core::maintenance::status()
+        }
+    }
+}
+
+/// Generates the endpoints that switch guest access for `auth = "guest"` tools.
+fn generate_guest_policy_functions(auth: bool) -> TokenStream {
+    let owner_check = generate_owner_check(auth);
+
+    quote! {
+        /// Lets callers that are not on the whitelist, including the anonymous
+        /// principal, call `auth = "guest"` tools
+        #[ic_cdk::update]
+        pub fn set_guest_access(enabled: bool) -> Result<(), String> {
+            #owner_check
+
+            ::icarus_core::auth::set_guest_policy(enabled, caller);
+            Ok(())
+        }
+
+        /// Returns whether guest access is on
+        #[ic_cdk::query]
+        pub fn get_guest_policy() -> ::icarus_core::auth::GuestPolicy {
+            ::icarus_core::auth::guest_policy()
+        }
+    }
+}
+
+/// Generates the cycle balance status endpoint.
+fn generate_cycles_status_endpoint() -> TokenStream {
+    quote! {
+        /// Returns the cycle balance, reserve and low-balance flags
+        #[ic_cdk::query]
+        pub fn get_cycles_status() -> ::icarus_core::cycles::CyclesStatus {
+            ::icarus_core::cycles::cycles_status()
+        }
+    }
+}
+
+/// Generates the data retention status endpoint.
+fn generate_retention_status_endpoint() -> TokenStream {
+    quote! {
+        /// Returns when retention policies last ran and how much each purged
+        #[ic_cdk::query]
+        pub fn get_retention_status() -> ::icarus_core::retention::RetentionStatus {
+            ::icarus_core::retention::retention_status()
+        }
+    }
+}
+
+/// Generates the log archiving status endpoint.
+fn generate_archive_status_endpoint() -> TokenStream {
+    quote! {
+        /// Returns where logs are archived and how much of each was archived
+        #[ic_cdk::query]
+        pub fn get_archive_status() -> ::icarus_core::archive::ArchiveStatus {
+            ::icarus_core::archive::archive_status()
+        }
+    }
+}
+
+/// Generates the usage analytics endpoint for admins, or for controllers
+/// without auth, since it names every caller.
+fn generate_usage_stats_endpoint(auth: bool) -> TokenStream {
+    let admin_check = generate_owner_check(auth);
+
+    quote! {
+        /// Returns call counts, error rates and latencies per tool and caller
+        /// over the last `window_secs` seconds (all retained data if omitted)
+        #[ic_cdk::query]
+        pub fn mcp_usage_stats(window_secs: Option<u64>) -> Result<::icarus_core::metrics::UsageStats, String> {
+            #admin_check
+
+            Ok(::icarus_core::metrics::usage_stats(window_secs))
+        }
+    }
+}
+
+/// Generates the size limits `mcp_call_tool` applies, the error it returns
+/// for oversized payloads and the `get_payload_stats` query, admin-only when
+/// auth is enabled.
+fn generate_payload_limits(config: &McpConfig) -> TokenStream {
+    let max_request_bytes = config.max_request_bytes;
+    let max_response_bytes = config.max_response_bytes;
+    let admin_check = if config.auth {
+        quote! {
+            let caller = ::ic_cdk::caller();
+            if !::icarus_core::auth::has_admin_access(&caller) {
+                return Err("Admin access required".to_string());
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        /// Size limits of `tools/call` requests and responses
+        const MCP_PAYLOAD_LIMITS: ::icarus_core::payload_limits::PayloadLimits =
+            ::icarus_core::payload_limits::PayloadLimits::new(#max_request_bytes, #max_response_bytes);
+
+        /// Creates the JSON-RPC error for a payload over its limit
+        fn create_payload_too_large_error(id: String, error: &::icarus_core::payload_limits::PayloadTooLarge) -> String {
+            let response = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {
+                    "code": ::icarus_core::payload_limits::PAYLOAD_TOO_LARGE_CODE,
+                    "message": error.to_string(),
+                    "data": error.data()
+                }
+            });
+            serde_json::to_string(&response).unwrap_or_else(|_| "{}".to_string())
+        }
+
+        /// Returns the payload size limits and the calls rejected for their
+        /// size since the last upgrade
+        #[ic_cdk::query]
+        pub fn get_payload_stats() -> Result<::icarus_core::payload_limits::PayloadStats, String> {
+            #admin_check
+
+            Ok(::icarus_core::payload_limits::payload_stats(&MCP_PAYLOAD_LIMITS))
+        }
+    }
+}
+
+/// Generates the HTTP gateway endpoint serving Prometheus metrics at `/metrics`
+/// and committed blobs at `/blobs/<id>`.
+fn generate_http_request_endpoint(config: &McpConfig) -> TokenStream {
+    let (tool_routes, tool_functions) = if config.http_tools {
+        (
+            generate_http_tool_routes(config),
+            generate_http_tool_functions(&config.cors_origin),
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+
+    let blob_route = if config.blobs {
+        quote! {
+            if let Some(response) = ::icarus_core::blobs::serve(&request) {
+                return response;
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let fallback = if config.metrics {
+        quote! { ::icarus_runtime::prometheus::serve(&request, &::icarus_core::context::caller()) }
+    } else {
+        quote! { ::icarus_core::gateway::GatewayResponse::not_found() }
+    };
+
+    quote! {
+        /// Serves enabled routes through the HTTP gateway
+        #[ic_cdk::query]
+        pub fn http_request(request: ::icarus_core::gateway::GatewayRequest) -> ::icarus_core::gateway::GatewayResponse {
+            #tool_routes
+
+            #blob_route
+
+            #fallback
+        }
+
+        #tool_functions
+    }
+}
+
+/// Generates the `/tools` and `/openapi.json` routes of `http_request`.
+fn generate_http_tool_routes(config: &McpConfig) -> TokenStream {
+    let name = &config.name;
+    let version = &config.version;
+    let origin = &config.cors_origin;
+
+    quote! {
+        if let Some(route) = ::icarus_core::http_tools::route(&request) {
+            let response = match route {
+                ::icarus_core::http_tools::Route::List => {
+                    ::icarus_core::gateway::GatewayResponse::json(200, &serde_json::json!({ "tools": list_tools() }))
+                }
+                ::icarus_core::http_tools::Route::OpenApi => ::icarus_core::gateway::GatewayResponse::json(
+                    200,
+                    &::icarus_core::openapi::document(#name, #version, &list_tools()),
+                ),
+                // Tool calls change state, which queries cannot
+                ::icarus_core::http_tools::Route::Call(_) => ::icarus_core::gateway::GatewayResponse::upgrade(),
+                ::icarus_core::http_tools::Route::Preflight => {
+                    ::icarus_core::gateway::GatewayResponse::text(204, "text/plain; charset=utf-8", "")
+                }
+                ::icarus_core::http_tools::Route::MethodNotAllowed => {
+                    ::icarus_core::http_tools::error(405, "Method not allowed")
+                }
+            };
+            return ::icarus_core::http_tools::with_cors(response, #origin);
+        }
+    }
+}
+
+/// Generates the `http_request_update` endpoint that runs the tool calls
+/// `http_request` upgrades.
+///
+/// Calls go through `call_tool_as` on behalf of the API key's principal, so
+/// maintenance, tool switches, payments and tool auth apply as for
+/// `mcp_call_tool`.
+fn generate_http_tool_functions(origin: &str) -> TokenStream {
+    quote! {
+        /// Runs the tool calls that `http_request` upgraded
+        #[ic_cdk::update]
+        pub async fn http_request_update(request: ::icarus_core::gateway::GatewayRequest) -> ::icarus_core::gateway::GatewayResponse {
+            let response = match ::icarus_core::http_tools::route(&request) {
+                Some(::icarus_core::http_tools::Route::Call(name)) => http_call_tool(name, &request).await,
+                _ => ::icarus_core::gateway::GatewayResponse::not_found(),
+            };
+            ::icarus_core::http_tools::with_cors(response, #origin)
+        }
+
+        /// Calls a tool with the JSON body of `request` as its arguments
+        async fn http_call_tool(name: &str, request: &::icarus_core::gateway::GatewayRequest) -> ::icarus_core::gateway::GatewayResponse {
+            let Some(key) = ::icarus_core::api_keys::from_request(request) else {
+                return ::icarus_core::http_tools::error(401, "API key required");
+            };
+            let caller = match ::icarus_core::api_keys::authenticate(key) {
+                Ok(principal) => principal,
+                Err(e) => return ::icarus_core::http_tools::error(401, &e.to_string()),
+            };
+
+            let arguments = if request.body.is_empty() {
+                serde_json::json!({})
+            } else {
+                match serde_json::from_slice::<serde_json::Value>(&request.body) {
+                    Ok(arguments) => arguments,
+                    Err(e) => return ::icarus_core::http_tools::error(400, &format!("Invalid JSON body: {}", e)),
+                }
+            };
+            let call = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": "http",
+                "method": "tools/call",
+                "params": { "name": name, "arguments": arguments }
+            });
+            ::icarus_core::http_tools::from_jsonrpc(&call_tool_as(call.to_string(), caller).await)
+        }
+    }
+}
+
+/// Generates the owner-only endpoints that restrict who may scrape `/metrics`.
+fn generate_metrics_access_endpoints(auth: bool) -> TokenStream {
+    let owner_check = generate_owner_check(auth);
+
+    quote! {
+        /// Requires `token` as a bearer token to scrape `/metrics`, or removes
+        /// the token with `None`
+        #[ic_cdk::update]
+        pub fn set_metrics_token(token: Option<String>) -> Result<(), String> {
+            #owner_check
+
+            ::icarus_core::metrics::set_scrape_token(token.as_deref()).map_err(|e| e.to_string())
+        }
+
+        /// Lets `principals` scrape `/metrics` through an agent without the
+        /// token; an empty list removes the allowlist
+        #[ic_cdk::update]
+        pub fn set_metrics_principals(principals: Vec<candid::Principal>) -> Result<(), String> {
+            #owner_check
+
+            ::icarus_core::metrics::set_scrape_principals(&principals).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Generates the owner-only endpoints that manage API keys for the tool API.
+fn generate_api_key_functions(auth: bool) -> TokenStream {
+    let owner_check = generate_owner_check(auth);
+
+    quote! {
+        /// Creates an API key acting as `principal`, or the caller; the key is
+        /// only returned here
+        #[ic_cdk::update]
+        pub async fn create_api_key(label: String, principal: Option<candid::Principal>) -> Result<::icarus_core::api_keys::CreatedApiKey, String> {
+            #owner_check
+
+            ::icarus_core::api_keys::create_api_key(&label, principal.unwrap_or(caller), caller)
+                .await
+                .map_err(|e| e.to_string())
+        }
+
+        /// Revokes an API key by its id
+        #[ic_cdk::update]
+        pub fn revoke_api_key(id: String) -> Result<::icarus_core::api_keys::ApiKey, String> {
+            #owner_check
+            let _ = caller;
+
+            ::icarus_core::api_keys::revoke_api_key(&id).map_err(|e| e.to_string())
+        }
+
+        /// Lists API keys, newest first, without the keys themselves
+        #[ic_cdk::query]
+        pub fn list_api_keys() -> Result<Vec<::icarus_core::api_keys::ApiKey>, String> {
+            #owner_check
+            let _ = caller;
+
+            Ok(::icarus_core::api_keys::list_api_keys())
+        }
+    }
+}
+
+/// Generates chunked blob upload and download endpoints.
+///
+/// With auth enabled, uploads require user access and deletion requires admin
+/// access; without it, both are for controllers. Reads stay public like the
+/// `/blobs/` HTTP route.
+fn generate_blob_functions(auth: bool) -> TokenStream {
+    let user_check = if auth {
+        quote! {
+            let caller = ::ic_cdk::caller();
+            if !::icarus_core::auth::has_user_access(&caller) {
+                return Err("User access required".to_string());
+            }
+        }
+    } else {
+        generate_owner_check(false)
+    };
+    let admin_check = generate_owner_check(auth);
+
+    quote! {
+        /// Starts a chunked upload of `size` bytes and returns the blob id
+        #[ic_cdk::update]
+        pub fn upload_begin(name: String, size: u64, mime_type: String) -> Result<u64, String> {
+            #user_check
+
+            ::icarus_core::blobs::upload_begin(&name, size, &mime_type).map_err(|e| e.to_string())
+        }
+
+        /// Stores one chunk of an upload
+        #[ic_cdk::update]
+        pub fn upload_chunk(id: u64, index: u32, bytes: Vec<u8>) -> Result<(), String> {
+            #user_check
+
+            ::icarus_core::blobs::upload_chunk(id, index, &bytes).map_err(|e| e.to_string())
+        }
+
+        /// Completes an upload once all chunks have arrived
+        #[ic_cdk::update]
+        pub fn upload_commit(id: u64) -> Result<::icarus_core::blobs::BlobInfo, String> {
+            #user_check
+
+            ::icarus_core::blobs::upload_commit(id).map_err(|e| e.to_string())
+        }
+
+        /// Deletes a blob or abandons an upload
+        #[ic_cdk::update]
+        pub fn delete_blob(id: u64) -> Result<bool, String> {
+            #admin_check
+
+            Ok(::icarus_core::blobs::delete_blob(id))
+        }
+
+        /// Lists committed blobs
+        #[ic_cdk::query]
+        pub fn list_blobs() -> Vec<::icarus_core::blobs::BlobInfo> {
+            ::icarus_core::blobs::list_blobs()
+        }
+
+        /// Returns one chunk of a committed blob
+        #[ic_cdk::query]
+        pub fn blob_chunk(id: u64, index: u32) -> Option<Vec<u8>> {
+            ::icarus_core::blobs::read_chunk(id, index)
+        }
+    }
+}
+
+/// Generates event subscription endpoints.
+///
+/// The caller subscribes itself, so a canister can only register its own
+/// callbacks. With auth enabled, subscribing requires user access and listing
+/// subscriptions or the delivery queue requires admin access.
+fn generate_event_functions(auth: bool) -> TokenStream {
+    let user_check = if auth {
+        quote! {
+            if !::icarus_core::auth::has_user_access(&caller) {
+                return Err("User access required".to_string());
+            }
+        }
+    } else {
+        quote! {}
+    };
+    let admin_check = if auth {
+        quote! {
+            let caller = ::ic_cdk::caller();
+            if !::icarus_core::auth::has_admin_access(&caller) {
+                return Err("Admin access required".to_string());
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        /// Subscribes the calling canister's `callback_method` to `topic`
+        #[ic_cdk::update]
+        pub fn subscribe(topic: String, callback_method: String) -> Result<(), String> {
+            let caller = ::ic_cdk::caller();
+            #user_check
+
+            ::icarus_core::events::subscribe(&topic, caller, &callback_method)
+                .map_err(|e| e.to_string())
+        }
+
+        /// Removes one of the calling canister's subscriptions
+        #[ic_cdk::update]
+        pub fn unsubscribe(topic: String, callback_method: String) -> bool {
+            ::icarus_core::events::unsubscribe(&topic, ::ic_cdk::caller(), &callback_method)
+        }
+
+        /// Lists subscriptions, optionally only those for one topic
+        #[ic_cdk::query]
+        pub fn list_subscriptions(
+            topic: Option<String>,
+        ) -> Result<Vec<::icarus_core::events::Subscription>, String> {
+            #admin_check
+
+            Ok(::icarus_core::events::list_subscriptions(topic.as_deref()))
+        }
+
+        /// Lists deliveries waiting to be sent or retried, oldest first
+        #[ic_cdk::query]
+        pub fn list_pending_events(
+            limit: u64,
+        ) -> Result<Vec<::icarus_core::events::Delivery>, String> {
+            #admin_check
+
+            Ok(::icarus_core::events::list_pending(
+                usize::try_from(limit).unwrap_or(usize::MAX),
+            ))
+        }
+    }
+}
+
+/// Generates the endpoints of an archive canister.
+///
+/// `archive_segment` accepts segments only from the canisters the owner
+/// added as sources; managing sources and reading segments is owner-only.
+fn generate_archive_store_functions(auth: bool) -> TokenStream {
+    let owner_check = generate_owner_check(auth);
+
+    quote! {
+        /// Stores a log segment sent by a source canister and returns its id
+        #[ic_cdk::update]
+        pub fn archive_segment(
+            segment: ::icarus_core::archive::ArchiveSegment,
+        ) -> Result<u64, String> {
+            ::icarus_core::archive::store_segment(::ic_cdk::caller(), segment)
+                .map_err(|e| e.to_string())
+        }
+
+        /// Lets a canister archive its logs here
+        #[ic_cdk::update]
+        pub fn add_archive_source(source: candid::Principal) -> Result<(), String> {
+            #owner_check
+
+            ::icarus_core::archive::add_source(source);
+            Ok(())
+        }
+
+        /// Stops accepting segments from a canister; returns whether it was a source
+        #[ic_cdk::update]
+        pub fn remove_archive_source(source: candid::Principal) -> Result<bool, String> {
+            #owner_check
+
+            Ok(::icarus_core::archive::remove_source(source))
+        }
+
+        /// Lists the canisters allowed to archive their logs here
+        #[ic_cdk::query]
+        pub fn list_archive_sources() -> Result<Vec<candid::Principal>, String> {
+            #owner_check
+
+            Ok(::icarus_core::archive::list_sources())
+        }
+
+        /// Lists stored segments, optionally only those of one log
+        #[ic_cdk::query]
+        pub fn list_archive_segments(
+            log: Option<String>,
+        ) -> Result<Vec<::icarus_core::archive::SegmentInfo>, String> {
+            #owner_check
+
+            Ok(::icarus_core::archive::list_segments(log.as_deref()))
+        }
+
+        /// Returns a stored segment with its compressed entries
+        #[ic_cdk::query]
+        pub fn get_archive_segment(
+            id: u64,
+        ) -> Result<Option<::icarus_core::archive::StoredSegment>, String> {
+            #owner_check
+
+            Ok(::icarus_core::archive::get_segment(id))
+        }
+
+        /// Deletes a stored segment; returns whether it existed
+        #[ic_cdk::update]
+        pub fn delete_archive_segment(id: u64) -> Result<bool, String> {
+            #owner_check
+
+            Ok(::icarus_core::archive::delete_segment(id))
+        }
+    }
+}
+
+/// Generates the endpoints that manage read replicas and receive changes.
+///
+/// Only the primary set by the owner may deliver changes. A change that cannot
+/// be applied traps, so the primary's delivery queue retries it.
+fn generate_replication_functions(auth: bool) -> TokenStream {
+    let owner_check = generate_owner_check(auth);
+
+    quote! {
+        /// Streams this canister's changes to a replica canister
+        #[ic_cdk::update]
+        pub fn add_replica(replica: candid::Principal) -> Result<(), String> {
+            #owner_check
+
+            ::icarus_core::replication::add_replica(replica).map_err(|e| e.to_string())
+        }
+
+        /// Stops streaming changes to a replica; returns whether it was one
+        #[ic_cdk::update]
+        pub fn remove_replica(replica: candid::Principal) -> Result<bool, String> {
+            #owner_check
+
+            Ok(::icarus_core::replication::remove_replica(replica))
+        }
+
+        /// Makes this canister a replica of `primary`, or standalone again with none
+        #[ic_cdk::update]
+        pub fn set_replication_primary(primary: Option<candid::Principal>) -> Result<(), String> {
+            #owner_check
+
+            ::icarus_core::replication::set_primary(primary);
+            Ok(())
+        }
+
+        /// Applies a change streamed by the primary
+        #[ic_cdk::update]
+        pub fn replicate_change(event: ::icarus_core::events::Event) {
+            if let Err(e) = ::icarus_core::replication::apply(::ic_cdk::caller(), &event) {
+                ::ic_cdk::trap(e.to_string());
+            }
+        }
+
+        /// Returns the replicas, the primary and how many changes were applied
+        #[ic_cdk::query]
+        pub fn get_replication_status() -> ::icarus_core::replication::ReplicationStatus {
+            ::icarus_core::replication::replication_status()
+        }
+    }
+}
+
+/// Generates the `start_job`, `get_job_status` and `get_job_result` tools.
+///
+/// They are ordinary `#[tool]` functions, listed and called like the user's.
+fn generate_job_tools() -> TokenStream {
+    let tools = [
+        (
+            quote! { "Start a long-running job of a registered kind with JSON arguments" },
+            quote! {
+                fn start_job(kind: String, args: String) -> Result<::icarus_core::jobs::JobTicket, String> {
+                    // Job tools check their own access; they are started by calling them
+                    if ::icarus_core::ToolId::new(kind.as_str())
+                        .is_ok_and(|id| ::icarus_runtime::ToolRegistry::has_tool(&id))
+                    {
+                        return Err(format!("'{kind}' is a tool; call it to start the job"));
                     }
+                    ::icarus_core::jobs::start_job(&kind, &args)
+                        .map(|job_id| ::icarus_core::jobs::JobTicket { job_id })
+                        .map_err(|e| e.to_string())
+                }
+            },
+        ),
+        (
+            quote! { "Get the status and progress of a job you started" },
+            quote! {
+                fn get_job_status(id: u64) -> Result<::icarus_core::jobs::Job, String> {
+                    ::icarus_core::jobs::status_for(id, ::icarus_core::context::caller())
+                        .map_err(|e| e.to_string())
+                }
+            },
+        ),
+        (
+            quote! { "Get the JSON result of a completed job you started" },
+            quote! {
+                fn get_job_result(id: u64) -> Result<String, String> {
+                    ::icarus_core::jobs::status_for(id, ::icarus_core::context::caller())
+                        .and_then(|_| ::icarus_core::jobs::result(id))
+                        .map_err(|e| e.to_string())
+                }
+            },
+        ),
+    ];
+
+    tools
+        .into_iter()
+        .map(|(args, function)| {
+            crate::tool::tool_impl(args, function).unwrap_or_else(|e| e.to_compile_error())
+        })
+        .collect()
+}
+
+/// Generates the owner-only `set_secret`, `rotate_secret`, `delete_secret`
+/// and `list_secrets` tools. None of them returns a secret's value, and the
+/// bridge redacts the `value` argument of the first two from its recordings
+/// and logs.
+fn generate_secret_tools(auth: bool) -> TokenStream {
+    let owner_check = generate_owner_check(auth);
+    let tools = [
+        (
+            quote! { "Store a new named secret, such as an API key, for endpoint configs to use" },
+            quote! {
+                fn set_secret(name: String, value: String) -> Result<::icarus_core::secrets::SecretInfo, String> {
+                    #owner_check
+                    ::icarus_core::secrets::set_secret(&name, &value, caller).map_err(|e| e.to_string())
+                }
+            },
+        ),
+        (
+            quote! { "Replace the value of a named secret" },
+            quote! {
+                fn rotate_secret(name: String, value: String) -> Result<::icarus_core::secrets::SecretInfo, String> {
+                    #owner_check
+                    ::icarus_core::secrets::rotate_secret(&name, &value, caller).map_err(|e| e.to_string())
+                }
+            },
+        ),
+        (
+            quote! { "Delete a named secret" },
+            quote! {
+                fn delete_secret(name: String) -> Result<::icarus_core::secrets::SecretInfo, String> {
+                    #owner_check
+                    ::icarus_core::secrets::delete_secret(&name).map_err(|e| e.to_string())
+                }
+            },
+        ),
+        (
+            quote! { "List the names and versions of stored secrets, without their values" },
+            quote! {
+                fn list_secrets() -> Result<Vec<::icarus_core::secrets::SecretInfo>, String> {
+                    #owner_check
+                    let _ = caller;
+                    Ok(::icarus_core::secrets::list_secrets())
+                }
+            },
+        ),
+    ];
+
+    tools
+        .into_iter()
+        .map(|(args, function)| {
+            crate::tool::tool_impl(args, function).unwrap_or_else(|e| e.to_compile_error())
+        })
+        .collect()
+}
+
+/// Generates the `get_public_key` tool and the owner-only `sign_message`,
+/// `set_signing_path`, `remove_signing_path` and `get_signing_stats` tools.
+///
+/// Anyone may read public keys to verify signatures, but each signature costs
+/// cycles and speaks for the canister, so only the owner may sign.
+fn generate_signing_tools(auth: bool) -> TokenStream {
+    let owner_check = generate_owner_check(auth);
+    let tools = [
+        (
+            quote! { "Get the canister's threshold ECDSA public key for a named derivation path, the default path if omitted" },
+            quote! {
+                async fn get_public_key(path: Option<String>) -> Result<::icarus_core::signing::PublicKey, String> {
+                    let path = path.unwrap_or_else(|| ::icarus_core::signing::DEFAULT_PATH.to_string());
+                    ::icarus_core::signing::get_public_key(&path).await.map_err(|e| e.to_string())
+                }
+            },
+        ),
+        (
+            quote! { "Sign the SHA-256 hash of a UTF-8 message and return the hex-encoded 64-byte signature", expensive },
+            quote! {
+                async fn sign_message(message: String, path: Option<String>) -> Result<String, String> {
+                    #owner_check
+                    let _ = caller;
+                    let path = path.unwrap_or_else(|| ::icarus_core::signing::DEFAULT_PATH.to_string());
+                    ::icarus_core::signing::sign_message(message.as_bytes(), &path)
+                        .await
+                        .map(|signature| signature.iter().map(|b| format!("{b:02x}")).collect())
+                        .map_err(|e| e.to_string())
+                }
+            },
+        ),
+        (
+            quote! { "Name a derivation path made of UTF-8 segments, replacing any path with that name" },
+            quote! {
+                fn set_signing_path(name: String, segments: Vec<String>) -> Result<::icarus_core::signing::DerivationPath, String> {
+                    #owner_check
+                    let segments = segments.into_iter().map(String::into_bytes).collect();
+                    ::icarus_core::signing::set_derivation_path(&name, segments, caller).map_err(|e| e.to_string())
+                }
+            },
+        ),
+        (
+            quote! { "Remove a named derivation path" },
+            quote! {
+                fn remove_signing_path(name: String) -> Result<::icarus_core::signing::DerivationPath, String> {
+                    #owner_check
+                    let _ = caller;
+                    ::icarus_core::signing::remove_derivation_path(&name).map_err(|e| e.to_string())
+                }
+            },
+        ),
+        (
+            quote! { "Get the signatures made and cycles spent on them since the last upgrade" },
+            quote! {
+                fn get_signing_stats() -> Result<::icarus_core::signing::SigningStats, String> {
+                    #owner_check
+                    let _ = caller;
+                    Ok(::icarus_core::signing::signing_stats())
+                }
+            },
+        ),
+    ];
+
+    tools
+        .into_iter()
+        .map(|(args, function)| {
+            crate::tool::tool_impl(args, function).unwrap_or_else(|e| e.to_compile_error())
+        })
+        .collect()
+}
+
+/// Generates the owner-only `spawn_instance`, `install_instance`,
+/// `top_up_instance`, `list_instances` and `get_factory_status` tools.
+///
+/// Spawning and topping up spend the factory's cycles, so only the owner may
+/// call them.
+fn generate_factory_tools(auth: bool) -> TokenStream {
+    let owner_check = generate_owner_check(auth);
+    let tools = [
+        (
+            quote! { "Create a canister running the child WASM with the given principal as its admin, and track it", expensive },
+            quote! {
+                async fn spawn_instance(owner_principal: String) -> Result<::icarus_core::factory::Instance, String> {
+                    #owner_check
+                    let owner = candid::Principal::from_text(owner_principal.trim()).map_err(|e| e.to_string())?;
+                    ::icarus_core::factory::spawn_instance(owner, caller).await.map_err(|e| e.to_string())
+                }
+            },
+        ),
+        (
+            quote! { "Retry installing the child WASM in an instance whose install failed" },
+            quote! {
+                async fn install_instance(canister_id: String) -> Result<::icarus_core::factory::Instance, String> {
+                    #owner_check
+                    let _ = caller;
+                    let canister_id = candid::Principal::from_text(canister_id.trim()).map_err(|e| e.to_string())?;
+                    ::icarus_core::factory::install_instance(canister_id).await.map_err(|e| e.to_string())
+                }
+            },
+        ),
+        (
+            quote! { "Deposit cycles from the factory into one of its instances", expensive },
+            quote! {
+                async fn top_up_instance(canister_id: String, cycles: u64) -> Result<::icarus_core::factory::Instance, String> {
+                    #owner_check
+                    let _ = caller;
+                    let canister_id = candid::Principal::from_text(canister_id.trim()).map_err(|e| e.to_string())?;
+                    ::icarus_core::factory::top_up_instance(canister_id, u128::from(cycles)).await.map_err(|e| e.to_string())
+                }
+            },
+        ),
+        (
+            quote! { "List the instances spawned by this factory, optionally only those of one owner" },
+            quote! {
+                fn list_instances(owner_principal: Option<String>) -> Result<Vec<::icarus_core::factory::Instance>, String> {
+                    #owner_check
+                    let _ = caller;
+                    let owner = owner_principal
+                        .map(|text| candid::Principal::from_text(text.trim()))
+                        .transpose()
+                        .map_err(|e| e.to_string())?;
+                    Ok(::icarus_core::factory::list_instances(owner))
+                }
+            },
+        ),
+        (
+            quote! { "Get the child WASM installed in new instances and how many instances exist" },
+            quote! {
+                fn get_factory_status() -> Result<::icarus_core::factory::FactoryStatus, String> {
+                    #owner_check
+                    let _ = caller;
+                    Ok(::icarus_core::factory::factory_status())
                 }
-                ::icarus_core::LegacyToolResult::Pending { status, .. } => {
-                    // Create CallToolResult with pending status
-                    let message = status.as_deref().unwrap_or("Tool execution pending");
-                    let content = vec![
-                        ::icarus_core::Content::text(message)
-                    ];
-                    ::icarus_core::CallToolResult {
-                        content,
-                        structured_content: None,
-                        is_error: Some(false),
-                        meta: None,
-                    }
+            },
+        ),
+    ];
+
+    tools
+        .into_iter()
+        .map(|(args, function)| {
+            crate::tool::tool_impl(args, function).unwrap_or_else(|e| e.to_compile_error())
+        })
+        .collect()
+}
+
+/// Generates the `mcp_args_public_key` endpoint serving the vetKD public key
+/// the bridge encrypts `#[tool(encrypted)]` arguments under.
+///
+/// Fetching the key calls the management canister, so this is an update.
+fn generate_args_key_endpoint() -> TokenStream {
+    quote! {
+        /// Returns the hex-encoded public key for encrypted tool arguments as
+        /// JSON, or the error fetching it
+        #[ic_cdk::update]
+        pub async fn mcp_args_public_key() -> String {
+            let response = match ::icarus_core::crypto::args_public_key().await {
+                Ok(key) => {
+                    let hex: String = key.iter().map(|byte| format!("{:02x}", byte)).collect();
+                    serde_json::json!({ "public_key": hex })
                 }
+                Err(e) => serde_json::json!({ "error": e.to_string() }),
             };
-
-            // Serialize the CallToolResult and return success response
-            match serde_json::to_value(&call_tool_result) {
-                Ok(result_json) => create_jsonrpc_success(request_id, result_json),
-                Err(e) => create_jsonrpc_error(request_id, -32603, format!("Failed to serialize result: {}", e)),
-            }
+            serde_json::to_string(&response).unwrap_or_else(|_| "{}".to_string())
         }
     }
 }
@@ -372,10 +2144,27 @@ fn generate_call_tool_endpoint() -> TokenStream {
 #[allow(clippy::too_many_lines)]
 fn generate_auth_management_functions() -> TokenStream {
     quote! {
+        /// Fails the install or upgrade if the tool definitions conflict
+        fn check_tool_definitions() {
+            if let Err(e) = ::icarus_runtime::validate_tool_definitions() {
+                ::ic_cdk::trap(e.to_string());
+            }
+        }
+
         /// Initializes the canister with an admin principal
         #[ic_cdk::init]
         pub fn init(admin: candid::Principal) {
+            check_tool_definitions();
             ::icarus_core::auth::add_admin(admin);
+            ::icarus_core::health::record_start();
+        }
+
+        /// Checks the tool definitions of the new build; users and admins
+        /// are kept in stable memory
+        #[ic_cdk::post_upgrade]
+        pub fn post_upgrade() {
+            check_tool_definitions();
+            ::icarus_core::health::record_start();
         }
 
         /// Adds a user with the specified role (admin only)
@@ -480,6 +2269,213 @@ fn generate_auth_management_functions() -> TokenStream {
     }
 }
 
+/// Generates the admin-only `create_invite`, `revoke_invite`, `list_invites`
+/// and `get_invite_audit_log` tools, and `redeem_invite`, which anyone signed
+/// in may call to join with the invite's role.
+fn generate_invite_tools() -> TokenStream {
+    let admin_check = generate_owner_check(true);
+    let tools = [
+        (
+            quote! { "Create an invite code that adds whoever redeems it as 'admin' or 'user', for up to max_uses principals and, if given, until expiry_secs from now" },
+            quote! {
+                async fn create_invite(role: String, max_uses: u32, expiry_secs: Option<u64>) -> Result<::icarus_core::invites::Invite, String> {
+                    #admin_check
+                    let role: ::icarus_core::invites::InviteRole = role.parse().map_err(|e: ::icarus_core::IcarusError| e.to_string())?;
+                    let expires_at = expiry_secs.map(|secs| {
+                        ::icarus_core::time::now_nanos().saturating_add(secs.saturating_mul(1_000_000_000))
+                    });
+                    ::icarus_core::invites::create_invite(role, max_uses, expires_at, caller)
+                        .await
+                        .map_err(|e| e.to_string())
+                }
+            },
+        ),
+        (
+            quote! { "Redeem an invite code to join with its role" },
+            quote! {
+                fn redeem_invite(code: String) -> Result<String, String> {
+                    ::icarus_core::invites::redeem_invite(code.trim(), ::icarus_core::context::caller())
+                        .map(|role| role.to_string())
+                        .map_err(|e| e.to_string())
+                }
+            },
+        ),
+        (
+            quote! { "Revoke an invite code; principals that already redeemed it keep their role" },
+            quote! {
+                fn revoke_invite(code: String) -> Result<::icarus_core::invites::Invite, String> {
+                    #admin_check
+                    ::icarus_core::invites::revoke_invite(code.trim(), caller).map_err(|e| e.to_string())
+                }
+            },
+        ),
+        (
+            quote! { "List invite codes with their roles, uses and expiry, newest first" },
+            quote! {
+                fn list_invites() -> Result<Vec<::icarus_core::invites::Invite>, String> {
+                    #admin_check
+                    let _ = caller;
+                    Ok(::icarus_core::invites::list_invites())
+                }
+            },
+        ),
+        (
+            quote! { "Get the latest invite creations, redemptions and revocations, newest first" },
+            quote! {
+                fn get_invite_audit_log(limit: Option<u32>) -> Result<Vec<::icarus_core::invites::InviteAuditEntry>, String> {
+                    #admin_check
+                    let _ = caller;
+                    Ok(::icarus_core::invites::audit_log(limit.unwrap_or(100) as usize))
+                }
+            },
+        ),
+    ];
+
+    tools
+        .into_iter()
+        .map(|(args, function)| {
+            crate::tool::tool_impl(args, function).unwrap_or_else(|e| e.to_compile_error())
+        })
+        .collect()
+}
+
+/// Generates admin-only chunked snapshot endpoints.
+fn generate_snapshot_functions() -> TokenStream {
+    quote! {
+        /// Prepares a snapshot of all registered state (admin only)
+        #[ic_cdk::update]
+        pub fn snapshot_begin(chunk_size: Option<u32>) -> Result<::icarus_core::storage::SnapshotManifest, String> {
+            let caller = ::ic_cdk::caller();
+            if !::icarus_core::auth::has_admin_access(&caller) {
+                return Err("Admin access required".to_string());
+            }
+
+            ::icarus_core::storage::snapshot_begin(chunk_size).map_err(|e| e.to_string())
+        }
+
+        /// Returns one chunk of the prepared snapshot (admin only)
+        #[ic_cdk::query]
+        pub fn snapshot_chunk(snapshot_id: u64, index: u32) -> Result<Vec<u8>, String> {
+            let caller = ::ic_cdk::caller();
+            if !::icarus_core::auth::has_admin_access(&caller) {
+                return Err("Admin access required".to_string());
+            }
+
+            ::icarus_core::storage::snapshot_chunk(snapshot_id, index).map_err(|e| e.to_string())
+        }
+
+        /// Uploads one chunk of a snapshot, restoring once complete (admin only)
+        #[ic_cdk::update]
+        pub fn snapshot_restore_chunk(
+            manifest: ::icarus_core::storage::SnapshotManifest,
+            index: u32,
+            chunk: Vec<u8>,
+        ) -> Result<::icarus_core::storage::RestoreStatus, String> {
+            let caller = ::ic_cdk::caller();
+            if !::icarus_core::auth::has_admin_access(&caller) {
+                return Err("Admin access required".to_string());
+            }
+
+            ::icarus_core::storage::snapshot_restore_chunk(manifest, index, chunk)
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Generates admin-only endpoints that move the auth state to another canister.
+fn generate_auth_transfer_functions() -> TokenStream {
+    quote! {
+        /// Stages the key that signs and checks auth state exports, or clears
+        /// it with `None` (admin only)
+        #[ic_cdk::update]
+        pub fn set_migration_key(key: Option<String>) -> Result<(), String> {
+            let caller = ::ic_cdk::caller();
+            if !::icarus_core::auth::has_admin_access(&caller) {
+                return Err("Admin access required".to_string());
+            }
+
+            ::icarus_core::auth_state::set_migration_key(key.as_deref()).map_err(|e| e.to_string())
+        }
+
+        /// Exports users, roles and invites as a blob signed with the migration key (admin only)
+        #[ic_cdk::update]
+        pub fn export_auth_state() -> Result<Vec<u8>, String> {
+            let caller = ::ic_cdk::caller();
+            if !::icarus_core::auth::has_admin_access(&caller) {
+                return Err("Admin access required".to_string());
+            }
+
+            ::icarus_core::auth_state::export_auth_state(caller).map_err(|e| e.to_string())
+        }
+
+        /// Replaces the auth state with an export signed with the migration key (admin only)
+        #[ic_cdk::update]
+        pub fn import_auth_state(
+            blob: Vec<u8>,
+        ) -> Result<::icarus_core::auth_state::AuthTransfer, String> {
+            let caller = ::ic_cdk::caller();
+            if !::icarus_core::auth::has_admin_access(&caller) {
+                return Err("Admin access required".to_string());
+            }
+
+            ::icarus_core::auth_state::import_auth_state(&blob, caller).map_err(|e| e.to_string())
+        }
+
+        /// Lists auth state exports and imports, newest first (admin only)
+        #[ic_cdk::query]
+        pub fn list_auth_transfers() -> Result<Vec<::icarus_core::auth_state::AuthTransfer>, String> {
+            let caller = ::ic_cdk::caller();
+            if !::icarus_core::auth::has_admin_access(&caller) {
+                return Err("Admin access required".to_string());
+            }
+
+            Ok(::icarus_core::auth_state::list_transfers())
+        }
+    }
+}
+
+/// Generates an `inspect_message` hook that drops update calls from unknown
+/// principals (with auth) and calls to disabled tools, except for
+/// controllers and allowlisted methods and tools.
+fn generate_inspect_message_hook(config: &McpConfig) -> TokenStream {
+    let auth = config.auth;
+    let prefix = &config.prefix;
+    let mut allow: Vec<&str> = config.inspect_allow.iter().map(String::as_str).collect();
+    // Principals joining with an invite are not known yet
+    if auth && !allow.contains(&"redeem_invite") {
+        allow.push("redeem_invite");
+    }
+    // The HTTP gateway sends update calls as the anonymous principal
+    if config.http_tools && !allow.contains(&"http_request_update") {
+        allow.push("http_request_update");
+    }
+
+    quote! {
+        #[ic_cdk::inspect_message]
+        fn inspect_message() {
+            let caller = ::ic_cdk::caller();
+            if ::ic_cdk::api::is_controller(&caller) {
+                ::ic_cdk::api::accept_message();
+                return;
+            }
+
+            let method = ::ic_cdk::api::msg_method_name();
+            let tool = if method == ::icarus_core::inspect::CALL_TOOL_METHOD {
+                candid::decode_one::<String>(&::ic_cdk::api::msg_arg_data())
+                    .ok()
+                    .and_then(|request| ::icarus_core::inspect::requested_tool(&request, #prefix))
+            } else {
+                None
+            };
+
+            match ::icarus_core::inspect::inspect(&method, &caller, tool.as_deref(), #auth, &[#(#allow),*]) {
+                Ok(()) => ::ic_cdk::api::accept_message(),
+                Err(e) => ::ic_cdk::trap(e.to_string()),
+            }
+        }
+    }
+}
+
 /// Generates the Candid interface export.
 fn generate_candid_export() -> TokenStream {
     quote! {
@@ -504,6 +2500,37 @@ mod tests {
         assert!(!config.rate_limit);
     }
 
+    #[test]
+    fn test_macro_arguments_override_config_file() {
+        let file = config_file::parse(
+            "[service]\nname = \"weather\"\nversion = \"2.0.0\"\n\n[features]\nauth = true\nmetrics = true\n",
+        )
+        .expect("valid icarus.toml");
+
+        let mut config = McpConfig::default();
+        apply_config_file(&mut config, &file).expect("valid settings");
+        apply_mcp_args(quote! { name = "forecast", metrics = false }, &mut config)
+            .expect("valid arguments");
+
+        assert_eq!(config.name, "forecast");
+        assert_eq!(config.version, "2.0.0");
+        assert!(config.auth);
+        assert!(!config.metrics);
+
+        let bad_prefix = config_file::parse("[service]\nprefix = \"1x\"\n").expect("valid toml");
+        assert!(apply_config_file(&mut McpConfig::default(), &bad_prefix).is_err());
+
+        let limits =
+            config_file::parse("[limits]\nmax_request_bytes = 4096\n").expect("valid toml");
+        let mut config = McpConfig::default();
+        apply_config_file(&mut config, &limits).expect("valid limits");
+        assert_eq!(config.max_request_bytes, 4096);
+        assert_eq!(config.max_response_bytes, DEFAULT_MAX_RESPONSE_BYTES);
+        let too_large =
+            config_file::parse("[limits]\nmax_response_bytes = 3000000\n").expect("valid toml");
+        assert!(apply_config_file(&mut McpConfig::default(), &too_large).is_err());
+    }
+
     #[test]
     fn test_parse_config_with_name() {
         let input = quote! {
@@ -528,6 +2555,486 @@ mod tests {
         assert!(!config.rate_limit);
     }
 
+    #[test]
+    fn test_generates_memory_report_endpoint() {
+        let code = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(code.contains("mcp_memory_report"));
+        assert!(code.contains("usage_report"));
+    }
+
+    #[test]
+    fn test_generates_health_endpoint() {
+        let code = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(code.contains("fn mcp_health"));
+        assert!(code.contains("health :: check"));
+        // Uptime starts with the first update call
+        assert!(code.contains("health :: record_start"));
+    }
+
+    #[test]
+    fn test_tool_definitions_validated_without_panicking() {
+        let code = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert_eq!(code.matches("validate_tool_definitions").count(), 1);
+
+        let config = McpConfig {
+            auth: true,
+            ..McpConfig::default()
+        };
+        let with_auth = generate_mcp_server_code(&config).to_string();
+        // Checked again in init and post_upgrade, where an error fails the
+        // install or upgrade
+        assert_eq!(with_auth.matches("validate_tool_definitions").count(), 2);
+        assert_eq!(with_auth.matches("check_tool_definitions ()").count(), 3);
+        assert!(with_auth.contains("ic_cdk :: post_upgrade"));
+        assert!(with_auth.contains("ic_cdk :: trap (e . to_string ())"));
+    }
+
+    #[test]
+    fn test_snapshot_endpoints_require_auth() {
+        let without_auth = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(!without_auth.contains("snapshot_begin"));
+
+        let config = McpConfig {
+            auth: true,
+            ..McpConfig::default()
+        };
+        let with_auth = generate_mcp_server_code(&config).to_string();
+        assert!(with_auth.contains("snapshot_begin"));
+        assert!(with_auth.contains("snapshot_restore_chunk"));
+    }
+
+    #[test]
+    fn test_call_tool_charges_paid_tools() {
+        let code = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(code.contains("payments :: charge"));
+        assert!(code.contains("payments :: refund"));
+        assert!(code.contains("its refund failed"));
+        assert!(code.contains("get_revenue_report"));
+
+        let without_auth = generate_revenue_report_endpoint(false).to_string();
+        assert!(without_auth.contains("is_controller"));
+    }
+
+    #[test]
+    fn test_call_tool_records_usage() {
+        let code = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(code.contains("metrics :: record_call"));
+        assert!(code.contains("mcp_usage_stats"));
+
+        let with_auth = generate_usage_stats_endpoint(true).to_string();
+        assert!(with_auth.contains("has_admin_access"));
+        let without_auth = generate_usage_stats_endpoint(false).to_string();
+        assert!(without_auth.contains("is_controller"));
+    }
+
+    #[test]
+    fn test_guest_access_is_owner_switched() {
+        let code = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(code.contains("fn set_guest_access"));
+        assert!(code.contains("auth :: set_guest_policy (enabled , caller)"));
+
+        let with_auth = generate_guest_policy_functions(true).to_string();
+        assert!(with_auth.contains("has_admin_access"));
+        assert!(!with_auth.contains("is_controller"));
+    }
+
+    #[test]
+    fn test_invite_tools_need_auth() {
+        let without_auth = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(!without_auth.contains("redeem_invite"));
+
+        let config = parse_mcp_config(quote! { auth = true }).expect("Failed to parse config");
+        let with_auth = generate_mcp_server_code(&config).to_string();
+        assert!(
+            with_auth.contains("invites :: create_invite (role , max_uses , expires_at , caller)")
+        );
+        assert!(with_auth.contains("invites :: redeem_invite"));
+        assert!(with_auth.contains("invites :: audit_log"));
+    }
+
+    #[test]
+    fn test_auth_transfer_needs_auth() {
+        let without_auth = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(!without_auth.contains("export_auth_state"));
+
+        let config = parse_mcp_config(quote! { auth = true }).expect("Failed to parse config");
+        let with_auth = generate_mcp_server_code(&config).to_string();
+        assert!(with_auth.contains("auth_state :: set_migration_key (key . as_deref ())"));
+        assert!(with_auth.contains("auth_state :: export_auth_state (caller)"));
+        assert!(with_auth.contains("auth_state :: import_auth_state (& blob , caller)"));
+        assert!(!with_auth.contains("migration_key : String"));
+    }
+
+    #[test]
+    fn test_inspect_message_is_opt_in() {
+        let without_inspect = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(!without_inspect.contains("inspect_message"));
+
+        let config = parse_mcp_config(quote! {
+            auth = true,
+            inspect = true,
+            inspect_allow = "subscribe, mcp_sampling_result"
+        })
+        .expect("Failed to parse config");
+        assert_eq!(config.inspect_allow, ["subscribe", "mcp_sampling_result"]);
+        let code = generate_mcp_server_code(&config).to_string();
+        assert!(code.contains("# [ic_cdk :: inspect_message]"));
+        assert!(code.contains(
+            "tool . as_deref () , true , & [\"subscribe\" , \"mcp_sampling_result\" , \"redeem_invite\"]"
+        ));
+        assert!(code.contains("let caller = :: ic_cdk :: caller () ;"));
+        assert!(!code.contains("\"http_request_update\""));
+    }
+
+    #[test]
+    fn test_inspect_message_allows_gateway_updates_with_http_tools() {
+        let config = parse_mcp_config(quote! {
+            auth = true,
+            inspect = true,
+            http_tools = true
+        })
+        .expect("Failed to parse config");
+        let code = generate_mcp_server_code(&config).to_string();
+        assert!(code.contains(
+            "tool . as_deref () , true , & [\"redeem_invite\" , \"http_request_update\"]"
+        ));
+    }
+
+    #[test]
+    fn test_http_tools_are_opt_in() {
+        let without = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(!without.contains("http_request_update"));
+        assert!(!without.contains("create_api_key"));
+
+        let config = parse_mcp_config(quote! {
+            http_tools = true,
+            cors_origin = "https://app.example.com"
+        })
+        .expect("Failed to parse config");
+        let code = generate_mcp_server_code(&config).to_string();
+        assert!(code.contains("pub fn http_request"));
+        assert!(code.contains("pub async fn http_request_update"));
+        assert!(code.contains("call_tool_as (call . to_string () , caller) . await"));
+        assert!(code.contains("with_cors (response , \"https://app.example.com\")"));
+        assert!(code.contains("pub async fn create_api_key"));
+        assert!(code.contains("openapi :: document"));
+
+        assert!(parse_mcp_config(quote! { cors_origin = "" }).is_err());
+    }
+
+    #[test]
+    fn test_metrics_endpoint_is_opt_in() {
+        let without_metrics = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(!without_metrics.contains("http_request"));
+
+        let config = parse_mcp_config(quote! { metrics = true }).expect("Failed to parse config");
+        assert!(config.metrics);
+        let with_metrics = generate_mcp_server_code(&config).to_string();
+        assert!(with_metrics.contains("prometheus :: serve"));
+        assert!(with_metrics.contains("fn set_metrics_token"));
+        assert!(with_metrics.contains("fn set_metrics_principals"));
+        assert!(with_metrics.contains("context :: caller ()"));
+        assert!(with_metrics.contains("is_controller"));
+        assert!(!without_metrics.contains("set_metrics_token"));
+    }
+
+    #[test]
+    fn test_blob_endpoints_are_opt_in() {
+        let without_blobs = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(!without_blobs.contains("upload_begin"));
+
+        let config =
+            parse_mcp_config(quote! { blobs = true, auth = true }).expect("Failed to parse config");
+        assert!(config.blobs);
+        let with_blobs = generate_mcp_server_code(&config).to_string();
+        assert!(with_blobs.contains("fn upload_chunk"));
+        assert!(with_blobs.contains("has_user_access"));
+        assert!(with_blobs.contains("blobs :: serve"));
+        assert!(!with_blobs.contains("prometheus :: serve"));
+
+        let without_auth = generate_blob_functions(false).to_string();
+        assert_eq!(without_auth.matches("is_controller").count(), 4);
+    }
+
+    #[test]
+    fn test_event_endpoints_are_opt_in() {
+        let without_events = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(!without_events.contains("fn subscribe"));
+
+        let config = parse_mcp_config(quote! { events = true }).expect("Failed to parse config");
+        assert!(config.events);
+        let with_events = generate_mcp_server_code(&config).to_string();
+        assert!(with_events.contains("fn subscribe"));
+        assert!(with_events.contains("fn list_subscriptions"));
+        assert!(!with_events.contains("has_admin_access"));
+    }
+
+    #[test]
+    fn test_archive_store_endpoints_are_opt_in() {
+        let without_store = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(without_store.contains("fn get_archive_status"));
+        assert!(!without_store.contains("fn archive_segment"));
+
+        let config =
+            parse_mcp_config(quote! { archive_store = true }).expect("Failed to parse config");
+        assert!(config.archive_store);
+        let with_store = generate_mcp_server_code(&config).to_string();
+        assert!(with_store.contains("fn archive_segment"));
+        assert!(with_store.contains("fn list_archive_segments"));
+        assert!(with_store.contains("is_controller"));
+    }
+
+    #[test]
+    fn test_replication_endpoints_are_opt_in() {
+        let without = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(without.contains("replication :: primary ()"));
+        assert!(!without.contains("fn replicate_change"));
+
+        let config = parse_mcp_config(quote! { replication = true, auth = true })
+            .expect("Failed to parse config");
+        assert!(config.replication);
+        let with = generate_mcp_server_code(&config).to_string();
+        assert!(with.contains("fn replicate_change"));
+        assert!(with.contains("fn add_replica"));
+        assert!(with.contains("has_admin_access"));
+    }
+
+    #[test]
+    fn test_generates_retention_status_endpoint() {
+        let code = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(code.contains("fn get_retention_status"));
+    }
+
+    #[test]
+    fn test_call_tool_propagates_trace_context() {
+        let code = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(code.contains("TraceContext :: from_meta"));
+        assert!(code.contains("trace :: set_current"));
+    }
+
+    #[test]
+    fn test_call_tool_installs_tool_context() {
+        let code = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(code.contains("context :: ToolContext :: new (caller , tool_name)"));
+        assert!(code.contains("with_deadline (deadline)"));
+        assert!(code.contains("context :: set_current (None)"));
+    }
+
+    #[test]
+    fn test_composite_query_endpoint() {
+        let code = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(code.contains("# [ic_cdk :: query (composite = true)]"));
+        assert!(code.contains("run_tool_call (request , :: ic_cdk :: caller () , true)"));
+        assert!(
+            code.contains("composite && ! :: icarus_core :: composite :: is_composite (tool_name)")
+        );
+        assert!(code.contains("execute_tool_async (& tool_id , & arguments_str) . await"));
+
+        // Nothing is charged or recorded from a composite query
+        assert!(code.contains("if ! composite { :: icarus_core :: tool_changes :: record"));
+        assert!(code.contains(
+            "composite && :: icarus_core :: payments :: tool_price (tool_name) . is_some ()"
+        ));
+        assert!(code.contains(
+            "let receipt = if composite { None } else { match :: icarus_core :: payments :: charge"
+        ));
+        assert!(code.contains("execution . as_ref () . filter (| _ | ! composite)"));
+    }
+
+    #[test]
+    fn test_payload_limits() {
+        let code = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(code.contains("MCP_PAYLOAD_LIMITS . check_request (request . len ())"));
+        assert!(
+            code.contains("MCP_PAYLOAD_LIMITS . check_response (tool_name , response . len ())")
+        );
+        assert!(code.contains("fn get_payload_stats"));
+        assert!(code.contains(&format!(
+            "PayloadLimits :: new ({DEFAULT_MAX_REQUEST_BYTES}usize"
+        )));
+
+        let config =
+            parse_mcp_config(quote! { max_request_bytes = 65_536, max_response_bytes = "131072" })
+                .expect("Failed to parse config");
+        assert_eq!(config.max_request_bytes, 65_536);
+        assert_eq!(config.max_response_bytes, 131_072);
+
+        assert!(parse_mcp_config(quote! { max_request_bytes = 0 }).is_err());
+        assert!(parse_mcp_config(quote! { max_response_bytes = 4_000_000 }).is_err());
+        assert!(parse_mcp_config(quote! { max_request_bytes = "lots" }).is_err());
+    }
+
+    #[test]
+    fn test_call_tool_forwards_sampling_requests() {
+        let code = generate_call_tool_endpoint("").to_string();
+        assert!(code.contains("sampling :: begin"));
+        assert!(code.contains("fn mcp_sampling_result"));
+        assert!(code.contains("sampling :: resume"));
+    }
+
+    #[test]
+    fn test_generates_tools_fingerprint_endpoint() {
+        let code = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(code.contains("mcp_tools_fingerprint"));
+        assert!(code.contains("ToolRegistry :: fingerprint"));
+        assert!(code.contains("fn mcp_tools_sequence"));
+        assert!(code.contains("tool_changes :: record"));
+    }
+
+    #[test]
+    fn test_prefix_namespaces_tool_names() {
+        let config = parse_mcp_config(quote! { prefix = "memory_" }).unwrap();
+        assert_eq!(config.prefix, "memory_");
+
+        let code = generate_mcp_server_code(&config).to_string();
+        assert!(code.contains("format ! (\"{}{}\" , \"memory_\" , tool . name)"));
+        assert!(code.contains("tool_name . strip_prefix (\"memory_\")"));
+        assert!(code.contains("\"tool_prefix\" : \"memory_\""));
+
+        let unprefixed = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(!unprefixed.contains("tool_name . strip_prefix"));
+
+        assert!(parse_mcp_config(quote! { prefix = "1st" }).is_err());
+        assert!(parse_mcp_config(quote! { prefix = "my tools" }).is_err());
+    }
+
+    #[test]
+    fn test_tool_switches() {
+        let code = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(code.contains("fn set_tool_enabled"));
+        assert!(code.contains("fn get_tool_audit_log"));
+        assert!(code.contains("is_controller"));
+        assert!(code.contains("tool_switches :: is_enabled (tool_name)"));
+        assert!(code.contains("tool_switches :: is_enabled (& tool . name)"));
+
+        let with_auth = parse_mcp_config(quote! { auth = true }).unwrap();
+        let code = generate_mcp_server_code(&with_auth).to_string();
+        assert!(code.contains("has_admin_access"));
+        assert!(!code.contains("is_controller"));
+    }
+
+    #[test]
+    fn test_maintenance_mode() {
+        let code = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(code.contains("fn set_maintenance"));
+        assert!(code.contains("fn get_maintenance_status"));
+        assert!(code.contains("maintenance :: SERVICE_UNAVAILABLE_CODE"));
+        assert!(code.contains("\"maintenance\" : :: icarus_core :: maintenance :: status ()"));
+    }
+
+    #[test]
+    fn test_job_tools_are_opt_in() {
+        let without_jobs = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(!without_jobs.contains("fn get_job_status"));
+
+        let config = parse_mcp_config(quote! { jobs = true }).expect("Failed to parse config");
+        assert!(config.jobs);
+        let with_jobs = generate_mcp_server_code(&config).to_string();
+        assert!(with_jobs.contains("fn start_job"));
+        assert!(with_jobs.contains("fn get_job_status"));
+        assert!(with_jobs.contains("fn get_job_result"));
+        assert!(with_jobs.contains("jobs :: status_for"));
+    }
+
+    #[test]
+    fn test_secret_tools_are_owner_only() {
+        let without_secrets = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(!without_secrets.contains("fn set_secret"));
+
+        let config = parse_mcp_config(quote! { secrets = true }).expect("Failed to parse config");
+        assert!(config.secrets);
+        let code = generate_mcp_server_code(&config).to_string();
+        for tool in [
+            "set_secret",
+            "rotate_secret",
+            "delete_secret",
+            "list_secrets",
+        ] {
+            assert!(code.contains(&format!("fn {tool}")), "missing {tool}");
+        }
+        assert!(code.contains("Controller access required"));
+        assert!(code.contains("let caller = :: icarus_core :: context :: caller () ;"));
+        assert!(!code.contains("with_secret"));
+
+        let config = parse_mcp_config(quote! { secrets = true, auth = true }).unwrap();
+        let code = generate_mcp_server_code(&config).to_string();
+        assert!(code.contains("Admin access required"));
+    }
+
+    #[test]
+    fn test_signing_tools() {
+        let without_signing = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(!without_signing.contains("fn sign_message"));
+
+        let config = parse_mcp_config(quote! { signing = true }).expect("Failed to parse config");
+        assert!(config.signing);
+        let code = generate_mcp_server_code(&config).to_string();
+        for tool in [
+            "get_public_key",
+            "sign_message",
+            "set_signing_path",
+            "remove_signing_path",
+            "get_signing_stats",
+        ] {
+            assert!(code.contains(&format!("fn {tool}")), "missing {tool}");
+        }
+        assert!(code.contains("signing :: sign_message"));
+        assert!(code.contains("Controller access required"));
+    }
+
+    #[test]
+    fn test_factory_tools_are_opt_in() {
+        let without = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(!without.contains("fn spawn_instance"));
+
+        let config = parse_mcp_config(quote! { factory = true, auth = true })
+            .expect("Failed to parse config");
+        assert!(config.factory);
+        let code = generate_mcp_server_code(&config).to_string();
+        for tool in [
+            "spawn_instance",
+            "install_instance",
+            "top_up_instance",
+            "list_instances",
+            "get_factory_status",
+        ] {
+            assert!(code.contains(&format!("fn {tool}")), "missing {tool}");
+        }
+        assert!(code.contains("factory :: spawn_instance (owner , caller)"));
+        assert!(code.contains("Admin access required"));
+    }
+
+    #[test]
+    fn test_encryption_endpoint() {
+        let code = generate_mcp_server_code(&McpConfig::default()).to_string();
+        assert!(!code.contains("fn mcp_args_public_key"));
+        // Every call goes through the decrypt step
+        assert!(code.contains("encrypted_args :: open"));
+
+        let config =
+            parse_mcp_config(quote! { encryption = true }).expect("Failed to parse config");
+        assert!(config.encryption);
+        let code = generate_mcp_server_code(&config).to_string();
+        assert!(code.contains("fn mcp_args_public_key"));
+        assert!(code.contains("crypto :: args_public_key"));
+    }
+
+    #[test]
+    fn test_endpoints_are_left_out_of_components() {
+        let code = generate_mcp_server_code(&McpConfig::default());
+        let items = syn::parse2::<syn::File>(code.clone())
+            .expect("server code parses as items")
+            .items
+            .len();
+        let wrapped = canister_only(code).to_string();
+        assert_eq!(
+            wrapped
+                .matches("cfg (not (all (target_os = \"wasi\" , target_env = \"p2\")))")
+                .count(),
+            items
+        );
+    }
+
     #[test]
     fn test_invalid_config_key() {
         let input = quote! {
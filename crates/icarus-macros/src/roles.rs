@@ -0,0 +1,123 @@
+//! Implementation of the roles!() macro.
+//!
+//! The macro declares the hierarchy in a hidden `__icarus_roles` module at
+//! the crate root, with one constant per role. `#[tool(auth = "...")]` refers
+//! to the constant of the role it names, so naming an undeclared role is a
+//! compile error at the tool.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::Token;
+
+use crate::error::{MacroError, MacroResult};
+use crate::utils::{canister_only, to_snake_case};
+
+/// Auth levels of `#[tool]` that are not roles and cannot be declared.
+const RESERVED: &[&str] = &["none", "guest"];
+
+/// Implementation of the roles!() macro.
+pub(crate) fn roles_impl(input: TokenStream) -> MacroResult<TokenStream> {
+    let idents = Punctuated::<syn::Ident, Token![>]>::parse_separated_nonempty.parse2(input)?;
+
+    let mut names: Vec<String> = Vec::with_capacity(idents.len());
+    for ident in &idents {
+        let name = to_snake_case(&ident.to_string());
+        if RESERVED.contains(&name.as_str()) {
+            return Err(MacroError::configuration_spanned(
+                format!("'{name}' is a built-in auth level and cannot be declared as a role"),
+                ident.span(),
+            ));
+        }
+        if names.contains(&name) {
+            return Err(MacroError::configuration_spanned(
+                format!("Role '{name}' is declared twice"),
+                ident.span(),
+            ));
+        }
+        names.push(name);
+    }
+
+    let constants = idents.iter().zip(&names).map(|(ident, name)| {
+        let constant = format_ident!("{}", name.to_uppercase(), span = ident.span());
+        let doc = format!("The `{name}` role");
+        quote! {
+            #[doc = #doc]
+            pub const #constant: &str = #name;
+        }
+    });
+
+    let endpoints = canister_only(quote! {
+        /// Gives a principal a declared role (admin only)
+        #[ic_cdk::update]
+        pub fn assign_role(principal: candid::Principal, role: String) -> Result<String, String> {
+            let caller = ::ic_cdk::caller();
+            if !::icarus_core::auth::has_admin_access(&caller) {
+                return Err("Admin access required".to_string());
+            }
+
+            ::icarus_core::roles::assign_role(principal, &role, __icarus_roles::HIERARCHY, caller)
+                .map(|()| format!("Assigned {} to {}", role, principal))
+                .map_err(|e| e.to_string())
+        }
+
+        /// Takes a principal's declared role away (admin only)
+        #[ic_cdk::update]
+        pub fn unassign_role(principal: candid::Principal) -> Result<Option<String>, String> {
+            let caller = ::ic_cdk::caller();
+            if !::icarus_core::auth::has_admin_access(&caller) {
+                return Err("Admin access required".to_string());
+            }
+
+            Ok(::icarus_core::roles::unassign_role(&principal).map(|assignment| assignment.role))
+        }
+
+        /// Lists principals with declared roles (admin only)
+        #[ic_cdk::query]
+        pub fn list_role_assignments() -> Result<Vec<::icarus_core::roles::RoleAssignment>, String> {
+            let caller = ::ic_cdk::caller();
+            if !::icarus_core::auth::has_admin_access(&caller) {
+                return Err("Admin access required".to_string());
+            }
+
+            Ok(::icarus_core::roles::list_assignments())
+        }
+    });
+
+    Ok(quote! {
+        /// Roles declared with `roles!`, highest first
+        #[doc(hidden)]
+        pub mod __icarus_roles {
+            /// Role names, highest first
+            pub const HIERARCHY: &[&str] = &[#(#names),*];
+            #(#constants)*
+        }
+
+        #endpoints
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hierarchy_is_declared_in_order() {
+        let output = roles_impl(quote! { Owner > Admin > ContentEditor > Viewer })
+            .expect("valid roles")
+            .to_string();
+        assert!(output.contains(
+            "HIERARCHY : & [& str] = & [\"owner\" , \"admin\" , \"content_editor\" , \"viewer\"]"
+        ));
+        assert!(output.contains("pub const CONTENT_EDITOR : & str = \"content_editor\""));
+    }
+
+    #[test]
+    fn test_invalid_hierarchies_are_rejected() {
+        assert!(roles_impl(quote! {}).is_err());
+        assert!(roles_impl(quote! { Admin > Editor > Admin }).is_err());
+        assert!(roles_impl(quote! { Admin > Guest }).is_err());
+        assert!(roles_impl(quote! { Admin, Editor }).is_err());
+    }
+}
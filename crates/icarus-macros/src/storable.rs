@@ -0,0 +1,272 @@
+//! Implementation of the `#[derive(IcarusStorable)]` macro.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse2, parse_quote, spanned::Spanned, Attribute, Data, DeriveInput, GenericParam, LitInt,
+    LitStr,
+};
+
+use crate::error::{MacroError, MacroResult};
+
+/// Serialization format selected with `#[icarus_storable(codec = "...")]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Codec {
+    Candid,
+    Cbor,
+    Bincode,
+}
+
+impl Codec {
+    /// Bounds added to every type parameter.
+    fn bounds(self) -> TokenStream {
+        match self {
+            Self::Candid => quote! { ::candid::CandidType + ::serde::de::DeserializeOwned },
+            Self::Cbor | Self::Bincode => {
+                quote! { ::serde::Serialize + ::serde::de::DeserializeOwned }
+            }
+        }
+    }
+
+    /// Paths of the encode and decode functions.
+    ///
+    /// Candid is called directly so the default needs no `icarus_core`.
+    fn functions(self) -> (TokenStream, TokenStream) {
+        match self {
+            Self::Candid => (
+                quote! { ::candid::encode_one },
+                quote! { ::candid::decode_one },
+            ),
+            Self::Cbor => (
+                quote! { ::icarus_core::codec::cbor::encode },
+                quote! { ::icarus_core::codec::cbor::decode },
+            ),
+            Self::Bincode => (
+                quote! { ::icarus_core::codec::bincode::encode },
+                quote! { ::icarus_core::codec::bincode::decode },
+            ),
+        }
+    }
+}
+
+/// Options from the `#[icarus_storable(...)]` attributes.
+#[derive(Debug)]
+struct StorableOptions {
+    codec: Codec,
+    /// Whether values are LZ4-compressed
+    compress: bool,
+    /// Smallest encoded size that is compressed
+    compress_threshold: Option<usize>,
+}
+
+/// Parses the `#[icarus_storable(...)]` attributes.
+fn parse_options(attrs: &[Attribute]) -> MacroResult<StorableOptions> {
+    let mut options = StorableOptions {
+        codec: Codec::Candid,
+        compress: false,
+        compress_threshold: None,
+    };
+    for attr in attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("icarus_storable"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("codec") {
+                let value: LitStr = meta.value()?.parse()?;
+                options.codec = match value.value().as_str() {
+                    "candid" => Codec::Candid,
+                    "cbor" => Codec::Cbor,
+                    "bincode" => Codec::Bincode,
+                    other => {
+                        return Err(syn::Error::new(
+                            value.span(),
+                            format!("unknown codec `{other}`, expected candid, cbor or bincode"),
+                        ))
+                    }
+                };
+            } else if meta.path.is_ident("compress") {
+                let value: LitStr = meta.value()?.parse()?;
+                if value.value() != "lz4" {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        format!("unknown compression `{}`, expected lz4", value.value()),
+                    ));
+                }
+                options.compress = true;
+            } else if meta.path.is_ident("compress_threshold") {
+                let value: LitInt = meta.value()?.parse()?;
+                options.compress_threshold = Some(value.base10_parse()?);
+            } else {
+                return Err(meta.error(
+                    "unknown icarus_storable option, expected `codec`, `compress` or `compress_threshold`",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+
+    if options.compress_threshold.is_some() && !options.compress {
+        return Err(MacroError::configuration(
+            "compress_threshold requires compress = \"lz4\"",
+        ));
+    }
+    Ok(options)
+}
+
+/// Implementation of the `IcarusStorable` derive.
+pub(crate) fn storable_impl(input: TokenStream) -> MacroResult<TokenStream> {
+    let mut input: DeriveInput = parse2(input)?;
+    let options = parse_options(&input.attrs)?;
+
+    if let Data::Union(union) = &input.data {
+        return Err(MacroError::unsupported_feature_spanned(
+            "Unions",
+            "IcarusStorable can only be derived for structs and enums",
+            union.union_token.span(),
+        ));
+    }
+
+    // Every type parameter is encoded as part of the value
+    let type_params: Vec<_> = input
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) => Some(param.ident.clone()),
+            GenericParam::Lifetime(_) | GenericParam::Const(_) => None,
+        })
+        .collect();
+    let bounds = options.codec.bounds();
+    let where_clause = input.generics.make_where_clause();
+    for ident in type_params {
+        where_clause
+            .predicates
+            .push(parse_quote! { #ident: #bounds });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let decode_error = format!("failed to decode stored {name}: {{}}");
+
+    let (encode, decode) = options.codec.functions();
+
+    let (to_bytes, from_bytes) = if options.compress {
+        let type_name = name.to_string();
+        let threshold = options.compress_threshold.map_or_else(
+            || quote! { ::icarus_core::compression::DEFAULT_THRESHOLD },
+            |threshold| quote! { #threshold },
+        );
+        (
+            quote! {
+                ::icarus_core::compression::compress(
+                    #type_name,
+                    #encode(self).unwrap_or_default(),
+                    #threshold,
+                )
+            },
+            quote! {
+                ::icarus_core::compression::decompress(&bytes)
+                    .and_then(|bytes| #decode(&bytes).map_err(::std::convert::Into::into))
+                    .unwrap_or_else(|e| panic!(#decode_error, e))
+            },
+        )
+    } else {
+        (
+            quote! { #encode(self).unwrap_or_default() },
+            quote! { #decode(&bytes).unwrap_or_else(|e| panic!(#decode_error, e)) },
+        )
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::ic_stable_structures::Storable for #name #ty_generics #where_clause {
+            fn to_bytes(&self) -> ::std::borrow::Cow<'_, [u8]> {
+                ::std::borrow::Cow::Owned(#to_bytes)
+            }
+
+            fn from_bytes(bytes: ::std::borrow::Cow<'_, [u8]>) -> Self {
+                #from_bytes
+            }
+
+            fn into_bytes(self) -> ::std::vec::Vec<u8> {
+                ::std::borrow::Cow::into_owned(self.to_bytes())
+            }
+
+            const BOUND: ::ic_stable_structures::storable::Bound =
+                ::ic_stable_structures::storable::Bound::Unbounded;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounds_type_parameters_only() {
+        let code = storable_impl(quote! {
+            struct Entry<'a, T, const N: usize> where T: Clone {
+                label: Cow<'a, str>,
+                value: T,
+            }
+        })
+        .unwrap()
+        .to_string();
+
+        assert!(code.contains("T : Clone , T : :: candid :: CandidType"));
+        assert!(!code.contains("N : :: candid"));
+        assert!(code.contains("for Entry < 'a , T , N >"));
+    }
+
+    #[test]
+    fn test_codec_attribute_selects_encoding() {
+        let code = storable_impl(quote! {
+            #[icarus_storable(codec = "cbor")]
+            enum Job<T> { Pending, Done(T) }
+        })
+        .unwrap()
+        .to_string();
+        assert!(code.contains("codec :: cbor :: encode"));
+        assert!(code.contains("T : :: serde :: Serialize"));
+        assert!(!code.contains("candid"));
+
+        let err = storable_impl(quote! {
+            #[icarus_storable(codec = "json")]
+            struct Note { title: String }
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown codec `json`"));
+    }
+
+    #[test]
+    fn test_compress_option() {
+        let code = storable_impl(quote! {
+            #[icarus_storable(compress = "lz4", compress_threshold = 1024)]
+            struct Article { body: String }
+        })
+        .unwrap()
+        .to_string();
+        assert!(code.contains("compression :: compress (\"Article\""));
+        assert!(code.contains("1024usize"));
+        assert!(code.contains("compression :: decompress"));
+
+        assert!(storable_impl(quote! {
+            #[icarus_storable(compress_threshold = 64)]
+            struct Article { body: String }
+        })
+        .is_err());
+        assert!(storable_impl(quote! {
+            #[icarus_storable(compress = "gzip")]
+            struct Article { body: String }
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_rejects_unions() {
+        let err = storable_impl(quote! {
+            union Bits { int: u32, float: f32 }
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("Unions"));
+    }
+}
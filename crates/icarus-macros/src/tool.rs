@@ -6,13 +6,21 @@ use syn::{parse2, spanned::Spanned, ItemFn};
 
 use crate::error::{MacroError, MacroResult};
 use crate::utils::{
-    extract_parameters, extract_return_type, generate_function_call,
-    generate_json_schema_from_parameters, generate_param_struct_name, is_async_function,
+    bind_lifetimes, borrows_arguments, context_arg, extract_parameters, extract_return_type,
+    generate_context_value, generate_function_call, generate_json_schema_from_parameters,
+    generate_param_struct_name, is_async_function, is_content_vec, is_option_cow_str,
+    is_result_type, is_sampling_request, ParamAttributes, ParameterInfo, ARGS_LIFETIME,
 };
 
 /// Maximum number of parameters a tool function can have
 const MAX_PARAMETERS: usize = 50;
 
+/// Maximum tool name length, matching `icarus_core::MAX_TOOL_NAME_LENGTH`
+const MAX_TOOL_NAME_LENGTH: usize = 255;
+
+/// Largest page of a paginated tool, matching `icarus_core::pagination::MAX_PAGE_SIZE`
+const MAX_PAGE_SIZE: i64 = 500;
+
 /// Implementation of the #[tool] attribute macro.
 pub(crate) fn tool_impl(args: TokenStream, input: TokenStream) -> MacroResult<TokenStream> {
     // Parse the function
@@ -28,16 +36,97 @@ pub(crate) fn tool_impl(args: TokenStream, input: TokenStream) -> MacroResult<To
     // Validate the function signature
     validate_function_signature(&function)?;
 
-    // Extract function information
-    let fn_name = &function.sig.ident;
+    let generated = generate_tool_items(
+        tool_config,
+        &function.attrs,
+        &function.sig,
+        &function.sig.ident,
+        &ToolTarget::Function,
+    )?;
+
+    // Keep the original function unchanged
     let fn_vis = &function.vis;
     let fn_attrs = &function.attrs;
     let fn_sig = &function.sig;
     let fn_block = &function.block;
-    let is_async = is_async_function(fn_sig);
 
-    // Extract parameters and return type
-    let parameters = extract_parameters(&function.sig.inputs)?;
+    Ok(quote! {
+        #(#fn_attrs)*
+        #fn_vis #fn_sig #fn_block
+
+        #generated
+    })
+}
+
+/// What the generated wrapper calls to run a tool.
+pub(crate) enum ToolTarget<'a> {
+    /// A free function
+    Function,
+    /// A method of the canister-global instance of `self_ty`, or an
+    /// associated function when `receiver` is `None`
+    Method {
+        self_ty: &'a syn::Type,
+        receiver: Option<Receiver>,
+    },
+}
+
+/// How a tool method borrows the instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Receiver {
+    /// `&self`
+    Shared,
+    /// `&mut self`
+    Mutable,
+}
+
+/// Generates everything a tool needs except the function itself.
+///
+/// `base_name` prefixes the generated items, so methods of different types
+/// with the same name do not clash.
+pub(crate) fn generate_tool_items(
+    tool_config: ToolConfig,
+    fn_attrs: &[syn::Attribute],
+    sig: &syn::Signature,
+    base_name: &syn::Ident,
+    target: &ToolTarget<'_>,
+) -> MacroResult<TokenStream> {
+    // Extract function information
+    let fn_name = &sig.ident;
+    let is_async = is_async_function(sig);
+
+    // Determine the tool name (custom or default)
+    let default_tool_name = fn_name.to_string();
+    let tool_name = tool_config.name.as_deref().unwrap_or(&default_tool_name);
+    let name_span = tool_config.name_span.unwrap_or_else(|| fn_name.span());
+    validate_tool_name(tool_name, name_span)?;
+    let auth_span = tool_config.auth_span.unwrap_or_else(|| fn_name.span());
+    if let Some(auth_level) = tool_config.auth_level.as_deref() {
+        validate_auth_level(auth_level, auth_span)?;
+    }
+    if tool_config.composite_query {
+        validate_composite_query(&tool_config, sig.span())?;
+    }
+
+    // Extract parameters and return type; a method's receiver and an injected
+    // `ToolContext` are not parameters
+    let mut inputs: syn::punctuated::Punctuated<syn::FnArg, syn::Token![,]> = sig
+        .inputs
+        .iter()
+        .filter(|input| !matches!(input, syn::FnArg::Receiver(_)))
+        .cloned()
+        .collect();
+    let context = inputs.first().and_then(context_arg);
+    if context.is_some() {
+        inputs = inputs.into_iter().skip(1).collect();
+    }
+    let context = context.map(|arg| generate_context_value(arg, tool_name));
+    if let Some(input) = inputs.iter().find(|input| context_arg(input).is_some()) {
+        return Err(MacroError::invalid_signature_spanned(
+            "ToolContext must be the first parameter of a tool",
+            input.span(),
+        ));
+    }
+    let parameters = extract_parameters(&inputs)?;
 
     // Validate parameter count to prevent pathological cases
     if parameters.len() > MAX_PARAMETERS {
@@ -48,43 +137,130 @@ pub(crate) fn tool_impl(args: TokenStream, input: TokenStream) -> MacroResult<To
                 MAX_PARAMETERS,
                 parameters.len()
             ),
-            function.sig.span(),
+            sig.span(),
         ));
     }
 
-    let _return_type = extract_return_type(&function.sig.output);
+    // `Vec<Content>` results are passed to the client as-is rather than as
+    // text, and a `SamplingRequest` is forwarded to the client's LLM
+    let return_type = extract_return_type(&sig.output);
+    let result_metadata = if is_content_vec(&return_type) {
+        Some(quote! { ::icarus_core::content::CONTENT_METADATA })
+    } else if is_sampling_request(&return_type) {
+        Some(quote! { ::icarus_core::sampling::SAMPLING_METADATA })
+    } else {
+        None
+    };
+
+    // A paginated tool takes `cursor` and `limit` on top of its own
+    // parameters; the tool itself never sees them
+    let schema_parameters = if tool_config.paginated {
+        if tool_config.job {
+            return Err(MacroError::invalid_signature_spanned(
+                "Job tools cannot be paginated",
+                sig.span(),
+            ));
+        }
+        if let Some(param) = parameters
+            .iter()
+            .find(|param| param.name == "cursor" || param.name == "limit")
+        {
+            return Err(MacroError::invalid_signature_spanned(
+                format!(
+                    "Paginated tools get a `{}` parameter of their own; rename this one",
+                    param.name
+                ),
+                param.name.span(),
+            ));
+        }
+        let mut schema_parameters = parameters.clone();
+        schema_parameters.extend(pagination_parameters());
+        schema_parameters
+    } else {
+        parameters.clone()
+    };
 
     // Generate parameter structure
-    let param_struct_name = generate_param_struct_name(fn_name);
-    let param_struct = generate_parameter_struct(&param_struct_name, &parameters);
+    let param_struct_name = generate_param_struct_name(base_name);
+    let param_struct = generate_parameter_struct(&param_struct_name, &schema_parameters)?;
+    let borrows_args = parameters
+        .iter()
+        .try_fold(false, |borrows, param| -> MacroResult<bool> {
+            Ok(borrows_arguments(param)? || borrows)
+        })?;
+    let param_struct_ty = if borrows_args {
+        quote! { #param_struct_name<'_> }
+    } else {
+        quote! { #param_struct_name }
+    };
 
     // Generate tool wrapper function
-    let wrapper_fn_name = format_ident!("{}_tool_wrapper", fn_name);
+    let wrapper_fn_name = format_ident!("{}_tool_wrapper", base_name);
+    let fn_call = generate_target_call(fn_name, &parameters, is_async, context.as_ref(), target);
+    let fn_call = if tool_config.paginated {
+        generate_paginated_call(&fn_call, is_result_type(&return_type))
+    } else {
+        fn_call
+    };
+
+    // A job tool returns the chunk closure; calling the tool starts a job
+    // that runs it, and the tool answers with the job id
+    let job_registration = if tool_config.job {
+        if is_async {
+            return Err(MacroError::invalid_signature_spanned(
+                "Job tools cannot be async; return the chunk closure from a sync function",
+                sig.span(),
+            ));
+        }
+        if borrows_args {
+            return Err(MacroError::invalid_signature_spanned(
+                "Job tools cannot borrow their arguments, since the work outlives the call; \
+                 take owned types such as String",
+                sig.span(),
+            ));
+        }
+        generate_job_registration(tool_name, &wrapper_fn_name, &param_struct_name, &fn_call)
+    } else {
+        quote! {}
+    };
+    let fn_call = if tool_config.job {
+        quote! {{
+            // The job kind parses the arguments again when it builds the work
+            let _ = args;
+            ::icarus_core::jobs::start_job(#tool_name, args_json)
+                .map(|job_id| ::icarus_core::jobs::JobTicket { job_id })
+                .map_err(|e| e.to_string())?
+        }}
+    } else {
+        fn_call
+    };
     let tool_wrapper = generate_tool_wrapper(
         &wrapper_fn_name,
-        fn_name,
-        &param_struct_name,
-        &parameters,
+        &fn_call,
+        &param_struct_ty,
         is_async,
         tool_config.auth_level.as_deref(),
+        auth_span,
     );
 
     // Generate tool registration
-    let registration_fn_name = format_ident!("{}_tool_info", fn_name);
+    let registration_fn_name = format_ident!("{}_tool_info", base_name);
     let description = tool_config
         .description
         .or_else(|| extract_doc_comment(fn_attrs));
 
-    // Determine the tool name (custom or default)
-    let default_tool_name = fn_name.to_string();
-    let tool_name = tool_config.name.as_deref().unwrap_or(&default_tool_name);
+    // Two tools with the same name in one module fail to compile
+    let name_marker = generate_name_marker(tool_name, name_span);
 
     let tool_registration = generate_tool_info_function(
         &registration_fn_name,
         tool_name,
-        &parameters,
+        &schema_parameters,
         description.as_deref(),
         tool_config.auth_level.as_deref(),
+        tool_config.encrypted,
+        tool_config.composite_query,
+        tool_config.cache_ttl_secs,
     );
 
     // Generate linkme registration for automatic tool discovery
@@ -92,18 +268,51 @@ pub(crate) fn tool_impl(args: TokenStream, input: TokenStream) -> MacroResult<To
 
     // Generate executor registration for runtime tool execution
     let executor_registration =
-        generate_executor_registration(tool_name, &wrapper_fn_name, is_async);
+        generate_executor_registration(tool_name, &wrapper_fn_name, is_async, result_metadata);
+
+    // Register the per-call price for paid tools
+    let price_registration = tool_config
+        .paid
+        .as_deref()
+        .map(|price| generate_price_registration(tool_name, &wrapper_fn_name, price))
+        .unwrap_or_default();
+
+    // Expensive tools are rejected while cycles are below the reserve
+    let expensive_registration = if tool_config.expensive {
+        generate_expensive_registration(tool_name, &wrapper_fn_name)
+    } else {
+        quote! {}
+    };
 
-    // Keep the original function unchanged
-    let original_function = quote! {
-        #(#fn_attrs)*
-        #fn_vis #fn_sig #fn_block
+    // Encrypted tools refuse plain arguments
+    let encrypted_registration = if tool_config.encrypted {
+        generate_encrypted_registration(tool_name, &wrapper_fn_name)
+    } else {
+        quote! {}
+    };
+
+    // Composite query tools may also run from the composite query endpoint
+    let composite_registration = if tool_config.composite_query {
+        generate_composite_registration(tool_name, &wrapper_fn_name)
+    } else {
+        quote! {}
     };
 
+    // Per-tool override of the executor timeout
+    let timeout_registration = tool_config
+        .timeout_ms
+        .map(|timeout_ms| generate_timeout_registration(tool_name, &wrapper_fn_name, timeout_ms))
+        .unwrap_or_default();
+
+    // Key of translated titles and descriptions
+    let i18n_registration = tool_config
+        .i18n
+        .as_deref()
+        .map(|key| generate_i18n_registration(tool_name, &wrapper_fn_name, key))
+        .unwrap_or_default();
+
     // Combine all generated code
     Ok(quote! {
-        #original_function
-
         #param_struct
 
         #tool_wrapper
@@ -112,37 +321,175 @@ pub(crate) fn tool_impl(args: TokenStream, input: TokenStream) -> MacroResult<To
 
         #tool_registry_item
 
+        #name_marker
+
         #executor_registration
+
+        #price_registration
+
+        #expensive_registration
+
+        #encrypted_registration
+
+        #composite_registration
+
+        #timeout_registration
+
+        #i18n_registration
+
+        #job_registration
     })
 }
 
+/// The `cursor` and `limit` parameters added to a paginated tool.
+fn pagination_parameters() -> [ParameterInfo; 2] {
+    [
+        ParameterInfo {
+            name: format_ident!("cursor"),
+            ty: syn::parse_quote!(Option<String>),
+            is_optional: true,
+            attributes: ParamAttributes {
+                description: Some(
+                    "`next_cursor` of the previous page; omit for the first page".to_string(),
+                ),
+                ..ParamAttributes::default()
+            },
+        },
+        ParameterInfo {
+            name: format_ident!("limit"),
+            ty: syn::parse_quote!(Option<u32>),
+            is_optional: true,
+            attributes: ParamAttributes {
+                description: Some("Most items to return".to_string()),
+                min: Some(1),
+                max: Some(MAX_PAGE_SIZE),
+                ..ParamAttributes::default()
+            },
+        },
+    ]
+}
+
+/// Turns the items returned by `fn_call` into the page the arguments ask for.
+fn generate_paginated_call(fn_call: &TokenStream, returns_result: bool) -> TokenStream {
+    // A `Result` is itself iterable, so it is unwrapped first
+    let items = if returns_result {
+        quote! { (#fn_call).map_err(|e| e.to_string())? }
+    } else {
+        quote! { #fn_call }
+    };
+    quote! {
+        ::icarus_core::pagination::paginate(#items, args.cursor.as_deref(), args.limit)
+            .map_err(|e| e.to_string())?
+    }
+}
+
+/// Generates the expression that runs the tool with the parsed `args`.
+fn generate_target_call(
+    fn_name: &syn::Ident,
+    parameters: &[ParameterInfo],
+    is_async: bool,
+    context: Option<&TokenStream>,
+    target: &ToolTarget<'_>,
+) -> TokenStream {
+    let ToolTarget::Method { self_ty, receiver } = target else {
+        return generate_function_call(fn_name, parameters, is_async, context);
+    };
+
+    let param_names: Vec<&syn::Ident> = parameters.iter().map(|p| &p.name).collect();
+    let context = context.map(|context| quote! { #context, });
+    let await_call = is_async.then(|| quote! { .await });
+    match receiver {
+        None => quote! {
+            <#self_ty>::#fn_name(#context #(args.#param_names),*)#await_call
+        },
+        // The instance is shared through an `Arc`, so an async method holds
+        // it across awaits without blocking other calls
+        Some(Receiver::Shared) => quote! {{
+            let instance = ::icarus_runtime::state::shared::<#self_ty>();
+            instance.#fn_name(#context #(args.#param_names),*)#await_call
+        }},
+        Some(Receiver::Mutable) => quote! {
+            ::icarus_runtime::state::with_mut(|instance: &mut #self_ty| {
+                instance.#fn_name(#context #(args.#param_names),*)
+            })
+            .map_err(|e| e.to_string())?
+        },
+    }
+}
+
 /// Configuration options for the #[tool] attribute.
 #[derive(Debug, Default)]
-struct ToolConfig {
+pub(crate) struct ToolConfig {
     /// Optional custom tool name (allows kebab-case names for MCP compatibility)
     name: Option<String>,
+    /// Location of the custom name, for diagnostics
+    name_span: Option<proc_macro2::Span>,
     /// Optional custom description
     description: Option<String>,
-    /// Authentication level: "none", "user", or "admin"
+    /// Authentication level: "none", "guest", "user", "admin", or a role
+    /// declared with `roles!`
     auth_level: Option<String>,
+    /// Location of the authentication level, for diagnostics
+    auth_span: Option<proc_macro2::Span>,
+    /// Price per call, e.g. "0.1 ICP"
+    paid: Option<String>,
+    /// Rejected while the cycle balance is below the reserve
+    expensive: bool,
+    /// Takes its arguments encrypted by the bridge
+    encrypted: bool,
+    /// Overrides the executor timeout, in milliseconds
+    timeout_ms: Option<u64>,
+    /// How long the bridge caches results of this read-only tool, in seconds
+    cache_ttl_secs: Option<u64>,
+    /// Key of the tool's translations in the i18n bundles
+    i18n: Option<String>,
+    /// Runs the returned chunk closure as a long-running job
+    job: bool,
+    /// Returns its items a page at a time
+    paginated: bool,
+    /// Also runs as a composite query, so its calls to other canisters'
+    /// queries stay on the query path
+    composite_query: bool,
 }
 
 /// Parses tool attribute arguments.
-fn parse_tool_args(args: TokenStream) -> ToolConfig {
+pub(crate) fn parse_tool_args(args: TokenStream) -> ToolConfig {
     use syn::parse::{Parse, ParseStream};
     use syn::Token;
 
     struct ToolArgs {
         name: Option<String>,
+        name_span: Option<proc_macro2::Span>,
         description: Option<String>,
         auth_level: Option<String>,
+        auth_span: Option<proc_macro2::Span>,
+        paid: Option<String>,
+        expensive: bool,
+        encrypted: bool,
+        timeout_ms: Option<u64>,
+        cache_ttl_secs: Option<u64>,
+        i18n: Option<String>,
+        job: bool,
+        paginated: bool,
+        composite_query: bool,
     }
 
     impl Parse for ToolArgs {
         fn parse(input: ParseStream) -> syn::Result<Self> {
             let mut name = None;
+            let mut name_span = None;
             let mut description = None;
             let mut auth_level = None;
+            let mut auth_span = None;
+            let mut paid = None;
+            let mut expensive = false;
+            let mut encrypted = false;
+            let mut timeout_ms = None;
+            let mut cache_ttl_secs = None;
+            let mut i18n = None;
+            let mut job = false;
+            let mut paginated = false;
+            let mut composite_query = false;
 
             // Try to parse the first argument as a string literal (description)
             if input.peek(syn::LitStr) {
@@ -158,28 +505,98 @@ fn parse_tool_args(args: TokenStream) -> ToolConfig {
                     }
 
                     let ident: syn::Ident = input.parse()?;
+
+                    // Bare flags
+                    if ident == "expensive" && !input.peek(Token![=]) {
+                        expensive = true;
+                        continue;
+                    }
+                    if ident == "encrypted" && !input.peek(Token![=]) {
+                        encrypted = true;
+                        continue;
+                    }
+                    if ident == "job" && !input.peek(Token![=]) {
+                        job = true;
+                        continue;
+                    }
+                    if ident == "paginated" && !input.peek(Token![=]) {
+                        paginated = true;
+                        continue;
+                    }
+                    if ident == "composite_query" && !input.peek(Token![=]) {
+                        composite_query = true;
+                        continue;
+                    }
+
                     let _: Token![=] = input.parse()?;
+
+                    // Numeric arguments
+                    if ident == "timeout_ms" {
+                        let value: syn::LitInt = input.parse()?;
+                        timeout_ms = Some(value.base10_parse()?);
+                        continue;
+                    }
+                    if ident == "cache_ttl_secs" {
+                        let value: syn::LitInt = input.parse()?;
+                        cache_ttl_secs = Some(value.base10_parse()?);
+                        continue;
+                    }
+
                     let value: syn::LitStr = input.parse()?;
 
                     if ident == "auth" {
                         auth_level = Some(value.value());
+                        auth_span = Some(value.span());
                     } else if ident == "name" {
                         name = Some(value.value());
+                        name_span = Some(value.span());
+                    } else if ident == "paid" {
+                        paid = Some(value.value());
+                    } else if ident == "i18n" {
+                        i18n = Some(value.value());
                     }
                 }
             } else if input.peek(syn::Ident) {
                 // Parse key=value pairs when no positional description
                 while !input.is_empty() {
                     let ident: syn::Ident = input.parse()?;
-                    let _: Token![=] = input.parse()?;
-                    let value: syn::LitStr = input.parse()?;
 
-                    if ident == "name" {
-                        name = Some(value.value());
-                    } else if ident == "description" {
-                        description = Some(value.value());
-                    } else if ident == "auth" {
-                        auth_level = Some(value.value());
+                    if ident == "expensive" && !input.peek(Token![=]) {
+                        // Bare flag
+                        expensive = true;
+                    } else if ident == "encrypted" && !input.peek(Token![=]) {
+                        encrypted = true;
+                    } else if ident == "job" && !input.peek(Token![=]) {
+                        job = true;
+                    } else if ident == "paginated" && !input.peek(Token![=]) {
+                        paginated = true;
+                    } else if ident == "composite_query" && !input.peek(Token![=]) {
+                        composite_query = true;
+                    } else if ident == "timeout_ms" {
+                        let _: Token![=] = input.parse()?;
+                        let value: syn::LitInt = input.parse()?;
+                        timeout_ms = Some(value.base10_parse()?);
+                    } else if ident == "cache_ttl_secs" {
+                        let _: Token![=] = input.parse()?;
+                        let value: syn::LitInt = input.parse()?;
+                        cache_ttl_secs = Some(value.base10_parse()?);
+                    } else {
+                        let _: Token![=] = input.parse()?;
+                        let value: syn::LitStr = input.parse()?;
+
+                        if ident == "name" {
+                            name = Some(value.value());
+                            name_span = Some(value.span());
+                        } else if ident == "description" {
+                            description = Some(value.value());
+                        } else if ident == "auth" {
+                            auth_level = Some(value.value());
+                            auth_span = Some(value.span());
+                        } else if ident == "paid" {
+                            paid = Some(value.value());
+                        } else if ident == "i18n" {
+                            i18n = Some(value.value());
+                        }
                     }
 
                     // Check for trailing comma
@@ -193,22 +610,55 @@ fn parse_tool_args(args: TokenStream) -> ToolConfig {
 
             Ok(ToolArgs {
                 name,
+                name_span,
                 description,
                 auth_level,
+                auth_span,
+                paid,
+                expensive,
+                encrypted,
+                timeout_ms,
+                cache_ttl_secs,
+                i18n,
+                job,
+                paginated,
+                composite_query,
             })
         }
     }
 
     let parsed = parse2::<ToolArgs>(args).unwrap_or(ToolArgs {
         name: None,
+        name_span: None,
         description: None,
         auth_level: None,
+        auth_span: None,
+        paid: None,
+        expensive: false,
+        encrypted: false,
+        timeout_ms: None,
+        cache_ttl_secs: None,
+        i18n: None,
+        job: false,
+        paginated: false,
+        composite_query: false,
     });
 
     ToolConfig {
         name: parsed.name,
+        name_span: parsed.name_span,
         description: parsed.description,
         auth_level: parsed.auth_level,
+        auth_span: parsed.auth_span,
+        paid: parsed.paid,
+        expensive: parsed.expensive,
+        encrypted: parsed.encrypted,
+        timeout_ms: parsed.timeout_ms,
+        cache_ttl_secs: parsed.cache_ttl_secs,
+        i18n: parsed.i18n,
+        job: parsed.job,
+        paginated: parsed.paginated,
+        composite_query: parsed.composite_query,
     }
 }
 
@@ -246,28 +696,58 @@ fn validate_function_signature(function: &ItemFn) -> MacroResult<()> {
 }
 
 /// Generates a parameter structure for the tool.
+///
+/// Parameters such as `&str` or `Cow<'_, str>` become fields borrowing from
+/// the argument string the wrapper is called with, instead of each getting
+/// an allocation of its own.
 fn generate_parameter_struct(
     struct_name: &syn::Ident,
-    parameters: &[crate::utils::ParameterInfo],
-) -> TokenStream {
-    let field_definitions: Vec<TokenStream> = parameters
+    parameters: &[ParameterInfo],
+) -> MacroResult<TokenStream> {
+    let lifetime = syn::Lifetime::new(ARGS_LIFETIME, proc_macro2::Span::call_site());
+    let mut borrows_args = false;
+    let field_definitions = parameters
         .iter()
         .map(|param| {
             let name = &param.name;
-            let ty = &param.ty;
+            let mut ty = param.ty.clone();
 
-            quote! {
-                pub #name: #ty,
-            }
+            Ok(if !bind_lifetimes(&mut ty, &lifetime)? {
+                quote! {
+                    pub #name: #ty,
+                }
+            } else if is_option_cow_str(&ty) {
+                // serde only borrows a `Cow` that is the field itself
+                borrows_args = true;
+                quote! {
+                    #[serde(
+                        borrow,
+                        default,
+                        deserialize_with = "::icarus_runtime::__private::borrow_option_cow_str"
+                    )]
+                    pub #name: #ty,
+                }
+            } else {
+                borrows_args = true;
+                quote! {
+                    #[serde(borrow)]
+                    pub #name: #ty,
+                }
+            })
         })
-        .collect();
+        .collect::<MacroResult<Vec<TokenStream>>>()?;
+    let generics = if borrows_args {
+        quote! { <#lifetime> }
+    } else {
+        quote! {}
+    };
 
-    quote! {
+    Ok(quote! {
         #[derive(serde::Deserialize)]
-        struct #struct_name {
+        struct #struct_name #generics {
             #(#field_definitions)*
         }
-    }
+    })
 }
 
 /// Generates the tool wrapper function that handles MCP protocol.
@@ -278,19 +758,25 @@ fn generate_parameter_struct(
 #[allow(clippy::too_many_arguments)]
 fn generate_tool_wrapper(
     wrapper_name: &syn::Ident,
-    fn_name: &syn::Ident,
-    param_struct_name: &syn::Ident,
-    parameters: &[crate::utils::ParameterInfo],
+    fn_call: &TokenStream,
+    param_struct_ty: &TokenStream,
     is_async: bool,
     auth_level: Option<&str>,
+    auth_span: proc_macro2::Span,
 ) -> TokenStream {
-    let fn_call = generate_function_call(fn_name, parameters, is_async);
-
     // Generate auth check code if auth_level is specified
     let auth_check = match auth_level {
+        Some("guest") => quote! {
+            {
+                let caller = ::icarus_core::context::caller();
+                if !::icarus_core::auth::has_guest_access(&caller) {
+                    return Err("Authentication required: guest access is disabled".to_string());
+                }
+            }
+        },
         Some("user") => quote! {
             {
-                let caller = ::ic_cdk::caller();
+                let caller = ::icarus_core::context::caller();
                 if !::icarus_core::auth::has_user_access(&caller) {
                     return Err("Authentication required: user or admin access needed".to_string());
                 }
@@ -298,13 +784,28 @@ fn generate_tool_wrapper(
         },
         Some("admin") => quote! {
             {
-                let caller = ::ic_cdk::caller();
+                let caller = ::icarus_core::context::caller();
                 if !::icarus_core::auth::has_admin_access(&caller) {
                     return Err("Authentication required: admin access needed".to_string());
                 }
             }
         },
-        _ => quote! {}, // "none" or no auth - no check needed
+        None | Some("none") => quote! {}, // No auth - no check needed
+        Some(role) => {
+            // Fails to compile unless `roles!` declared the role
+            let role_const = syn::Ident::new(&role.to_uppercase(), auth_span);
+            quote! {
+                {
+                    let caller = ::icarus_core::context::caller();
+                    ::icarus_core::roles::require_role_or_higher(
+                        &caller,
+                        crate::__icarus_roles::#role_const,
+                        crate::__icarus_roles::HIERARCHY,
+                    )
+                    .map_err(|e| e.to_string())?;
+                }
+            }
+        }
     };
 
     if is_async {
@@ -312,7 +813,7 @@ fn generate_tool_wrapper(
             async fn #wrapper_name(args_json: &str) -> Result<String, String> {
                 #auth_check
 
-                let args: #param_struct_name = serde_json::from_str(args_json)
+                let args: #param_struct_ty = serde_json::from_str(args_json)
                     .map_err(|e| format!("Invalid arguments: {e}"))?;
 
                 let result = #fn_call;
@@ -326,7 +827,7 @@ fn generate_tool_wrapper(
             fn #wrapper_name(args_json: &str) -> Result<String, String> {
                 #auth_check
 
-                let args: #param_struct_name = serde_json::from_str(args_json)
+                let args: #param_struct_ty = serde_json::from_str(args_json)
                     .map_err(|e| format!("Invalid arguments: {e}"))?;
 
                 let result = #fn_call;
@@ -339,12 +840,16 @@ fn generate_tool_wrapper(
 }
 
 /// Generates the tool information function for registration.
+#[allow(clippy::too_many_arguments)]
 fn generate_tool_info_function(
     info_fn_name: &syn::Ident,
     tool_name: &str,
-    parameters: &[crate::utils::ParameterInfo],
+    parameters: &[ParameterInfo],
     description: Option<&str>,
     auth_level: Option<&str>,
+    encrypted: bool,
+    composite_query: bool,
+    cache_ttl_secs: Option<u64>,
 ) -> TokenStream {
     let default_description = format!("Tool: {tool_name}");
     let description = description.unwrap_or(&default_description);
@@ -352,10 +857,46 @@ fn generate_tool_info_function(
     // Generate JSON Schema for input parameters
     let input_schema = generate_json_schema_from_parameters(parameters);
 
-    // Generate annotations if auth_level is specified
-    let annotations_code = if let Some(auth) = auth_level {
+    // The bridge encrypts the arguments of tools carrying the marker
+    let encryption_marker = encrypted.then(|| {
+        quote! {
+            let mut input_schema = input_schema;
+            ::std::sync::Arc::make_mut(&mut input_schema).insert(
+                ::icarus_core::encrypted_args::SCHEMA_KEY.to_string(),
+                ::serde_json::Value::Bool(true),
+            );
+        }
+    });
+
+    // The bridge calls tools carrying the marker as composite queries
+    let composite_marker = composite_query.then(|| {
+        quote! {
+            let mut input_schema = input_schema;
+            ::std::sync::Arc::make_mut(&mut input_schema).insert(
+                ::icarus_core::composite::SCHEMA_KEY.to_string(),
+                ::serde_json::Value::Bool(true),
+            );
+        }
+    });
+
+    // The bridge caches results of read-only tools for this long
+    let cache_ttl_marker = cache_ttl_secs.map(|secs| {
+        quote! {
+            let mut input_schema = input_schema;
+            ::std::sync::Arc::make_mut(&mut input_schema).insert(
+                ::icarus_core::tool::CACHE_TTL_SCHEMA_KEY.to_string(),
+                ::serde_json::Value::from(#secs),
+            );
+        }
+    });
+
+    // Generate annotations if auth_level is specified, or the tool is a
+    // composite query, which cannot change state
+    let annotations_code = if auth_level.is_some() || composite_query {
         // Map auth_level to RMCP ToolAnnotations hints
-        let read_only = auth == "none"; // Public tools might be read-only
+        // Public and guest tools might be read-only
+        let read_only =
+            composite_query || auth_level.is_some_and(|auth| auth == "none" || auth == "guest");
 
         quote! {
             let annotations = ::icarus_core::ToolAnnotations {
@@ -374,6 +915,9 @@ fn generate_tool_info_function(
     quote! {
         fn #info_fn_name() -> ::icarus_core::Tool {
             let input_schema = #input_schema;
+            #encryption_marker
+            #composite_marker
+            #cache_ttl_marker
 
             let mut tool = ::icarus_core::Tool::new(
                 #tool_name,
@@ -388,6 +932,81 @@ fn generate_tool_info_function(
     }
 }
 
+/// Checks a tool name against the rules `icarus_core::ToolId` enforces at
+/// runtime, so a bad name is reported at the `#[tool]` instead of on the first
+/// `tools/list`.
+fn validate_tool_name(name: &str, span: proc_macro2::Span) -> MacroResult<()> {
+    let problem = if name.is_empty() {
+        Some("must not be empty".to_string())
+    } else if name.len() > MAX_TOOL_NAME_LENGTH {
+        Some(format!(
+            "must be at most {MAX_TOOL_NAME_LENGTH} characters (found {})",
+            name.len()
+        ))
+    } else if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        Some("must start with an ASCII letter".to_string())
+    } else {
+        name.chars()
+            .find(|&c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')))
+            .map(|c| format!("contains invalid character {c:?}"))
+    };
+
+    match problem {
+        Some(problem) => Err(MacroError::configuration_spanned(
+            format!(
+                "Invalid tool name '{name}': {problem}. \
+                 Use ASCII letters, digits, '_', '.' or '-'"
+            ),
+            span,
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Checks that an auth level is built in or could name a role declared with
+/// `roles!`; whether it was declared is left to the compiler.
+fn validate_auth_level(level: &str, span: proc_macro2::Span) -> MacroResult<()> {
+    let is_role_name = level.starts_with(|c: char| c.is_ascii_lowercase())
+        && level
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if is_role_name {
+        Ok(())
+    } else {
+        Err(MacroError::configuration_spanned(
+            format!(
+                "Invalid auth level '{level}'. Use \"none\", \"guest\", \"user\", \"admin\" \
+                 or the snake_case name of a role declared with roles!"
+            ),
+            span,
+        ))
+    }
+}
+
+/// Generates a constant named after the tool so that a second tool with the
+/// same name in the same module is a "defined multiple times" error at the
+/// offending name. Tools in different modules are checked by
+/// `ToolRegistry::validate` when the canister starts.
+fn generate_name_marker(tool_name: &str, span: proc_macro2::Span) -> TokenStream {
+    // Escape '_', '-' and '.' so different names never share a marker
+    let mut mangled = String::with_capacity(tool_name.len());
+    for c in tool_name.chars() {
+        match c {
+            '_' => mangled.push_str("__"),
+            '-' => mangled.push_str("_0"),
+            '.' => mangled.push_str("_1"),
+            c => mangled.push(c),
+        }
+    }
+    let marker = syn::Ident::new(&format!("__ICARUS_TOOL_NAMED_{mangled}"), span);
+
+    quote! {
+        #[doc(hidden)]
+        #[allow(non_upper_case_globals, dead_code)]
+        const #marker: () = ();
+    }
+}
+
 /// Generates linkme registration for automatic tool discovery.
 fn generate_tool_registry_item(info_fn_name: &syn::Ident) -> TokenStream {
     let registry_static_name =
@@ -408,6 +1027,7 @@ fn generate_executor_registration(
     tool_name: &str,
     wrapper_fn_name: &syn::Ident,
     is_async: bool,
+    result_metadata: Option<TokenStream>,
 ) -> TokenStream {
     // Use the wrapper function name to derive executor names to avoid conflicts
     let executor_fn_name = format_ident!("{}_executor", wrapper_fn_name);
@@ -417,6 +1037,17 @@ fn generate_executor_registration(
         wrapper_fn_name.to_string().to_uppercase()
     );
 
+    let success = if let Some(metadata) = result_metadata {
+        quote! {
+            ::icarus_core::LegacyToolResult::success_with_metadata(
+                ::std::borrow::Cow::Owned(result_json),
+                #metadata,
+            )
+        }
+    } else {
+        quote! { ::icarus_core::LegacyToolResult::success(::std::borrow::Cow::Owned(result_json)) }
+    };
+
     if is_async {
         quote! {
             fn #executor_fn_name(args: &str) -> ::std::pin::Pin<::std::boxed::Box<dyn ::std::future::Future<Output = ::icarus_runtime::RuntimeResult<::icarus_core::LegacyToolResult<'static>>> + Send>> {
@@ -424,10 +1055,10 @@ fn generate_executor_registration(
                 ::std::boxed::Box::pin(async move {
                     match #wrapper_fn_name(&args).await {
                         Ok(result_json) => {
-                            Ok(::icarus_core::LegacyToolResult::success(::std::borrow::Cow::Owned(result_json)))
+                            Ok(#success)
                         }
                         Err(error_msg) => {
-                            Ok(::icarus_core::LegacyToolResult::error(::std::borrow::Cow::Owned(error_msg)))
+                            Ok(::icarus_core::error_codes::tool_error(error_msg))
                         }
                     }
                 })
@@ -454,10 +1085,10 @@ fn generate_executor_registration(
             fn #executor_fn_name(args: &str) -> ::icarus_runtime::RuntimeResult<::icarus_core::LegacyToolResult<'static>> {
                 match #wrapper_fn_name(args) {
                     Ok(result_json) => {
-                        Ok(::icarus_core::LegacyToolResult::success(::std::borrow::Cow::Owned(result_json)))
+                        Ok(#success)
                     }
                     Err(error_msg) => {
-                        Ok(::icarus_core::LegacyToolResult::error(::std::borrow::Cow::Owned(error_msg)))
+                        Ok(::icarus_core::error_codes::tool_error(error_msg))
                     }
                 }
             }
@@ -481,6 +1112,166 @@ fn generate_executor_registration(
     }
 }
 
+/// Generates registration of a tool's price with the payments module.
+fn generate_price_registration(
+    tool_name: &str,
+    wrapper_fn_name: &syn::Ident,
+    price: &str,
+) -> TokenStream {
+    let registration_name = format_ident!(
+        "{}_PRICE_REGISTRATION",
+        wrapper_fn_name.to_string().to_uppercase()
+    );
+
+    quote! {
+        #[::linkme::distributed_slice(::icarus_runtime::EXECUTOR_INIT)]
+        static #registration_name: fn() = || {
+            ::icarus_core::payments::set_tool_price(#tool_name, #price)
+                .unwrap_or_else(|e| {
+                    ::std::panic!("Invalid price for tool '{}': {}", #tool_name, e)
+                });
+        };
+    }
+}
+
+/// Generates registration of an expensive tool with the cycles guardrail.
+fn generate_expensive_registration(tool_name: &str, wrapper_fn_name: &syn::Ident) -> TokenStream {
+    let registration_name = format_ident!(
+        "{}_EXPENSIVE_REGISTRATION",
+        wrapper_fn_name.to_string().to_uppercase()
+    );
+
+    quote! {
+        #[::linkme::distributed_slice(::icarus_runtime::EXECUTOR_INIT)]
+        static #registration_name: fn() = || {
+            ::icarus_core::cycles::mark_expensive(#tool_name);
+        };
+    }
+}
+
+/// Generates registration of a tool that only takes encrypted arguments.
+fn generate_encrypted_registration(tool_name: &str, wrapper_fn_name: &syn::Ident) -> TokenStream {
+    let registration_name = format_ident!(
+        "{}_ENCRYPTED_REGISTRATION",
+        wrapper_fn_name.to_string().to_uppercase()
+    );
+
+    quote! {
+        #[::linkme::distributed_slice(::icarus_runtime::EXECUTOR_INIT)]
+        static #registration_name: fn() = || {
+            ::icarus_core::encrypted_args::mark_encrypted(#tool_name);
+        };
+    }
+}
+
+/// Generates registration of a tool runnable as a composite query.
+fn generate_composite_registration(tool_name: &str, wrapper_fn_name: &syn::Ident) -> TokenStream {
+    let registration_name = format_ident!(
+        "{}_COMPOSITE_REGISTRATION",
+        wrapper_fn_name.to_string().to_uppercase()
+    );
+
+    quote! {
+        #[::linkme::distributed_slice(::icarus_runtime::EXECUTOR_INIT)]
+        static #registration_name: fn() = || {
+            ::icarus_core::composite::mark_composite(#tool_name);
+        };
+    }
+}
+
+/// Rejects options a composite query cannot honor: it discards what it
+/// changes and cannot call the management canister.
+fn validate_composite_query(tool_config: &ToolConfig, span: proc_macro2::Span) -> MacroResult<()> {
+    let conflict = if tool_config.job {
+        Some("job tools, which keep their work in state")
+    } else if tool_config.paid.is_some() {
+        Some("paid tools, whose charges would be discarded")
+    } else if tool_config.encrypted {
+        Some("encrypted tools, whose key comes from the management canister")
+    } else {
+        None
+    };
+
+    match conflict {
+        Some(conflict) => Err(MacroError::invalid_signature_spanned(
+            format!("Composite query tools cannot be {conflict}"),
+            span,
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Generates the factory building a job tool's work from its arguments and
+/// registers it as the job kind named after the tool.
+fn generate_job_registration(
+    tool_name: &str,
+    wrapper_fn_name: &syn::Ident,
+    param_struct_name: &syn::Ident,
+    fn_call: &TokenStream,
+) -> TokenStream {
+    let factory_fn_name = format_ident!("{}_job_factory", wrapper_fn_name);
+    let registration_name = format_ident!(
+        "{}_JOB_REGISTRATION",
+        wrapper_fn_name.to_string().to_uppercase()
+    );
+
+    quote! {
+        fn #factory_fn_name(args_json: &str) -> Result<::icarus_core::jobs::JobWork, String> {
+            let args: #param_struct_name = serde_json::from_str(args_json)
+                .map_err(|e| format!("Invalid arguments: {e}"))?;
+            Ok(::std::boxed::Box::new(#fn_call))
+        }
+
+        #[::linkme::distributed_slice(::icarus_runtime::EXECUTOR_INIT)]
+        static #registration_name: fn() = || {
+            ::icarus_core::jobs::register_job_kind(#tool_name, #factory_fn_name);
+        };
+    }
+}
+
+/// Generates registration of a tool's timeout override with the registry.
+fn generate_timeout_registration(
+    tool_name: &str,
+    wrapper_fn_name: &syn::Ident,
+    timeout_ms: u64,
+) -> TokenStream {
+    let registration_name = format_ident!(
+        "{}_TIMEOUT_REGISTRATION",
+        wrapper_fn_name.to_string().to_uppercase()
+    );
+
+    quote! {
+        #[::linkme::distributed_slice(::icarus_runtime::EXECUTOR_INIT)]
+        static #registration_name: fn() = || {
+            if let Ok(tool_id) = ::icarus_core::ToolId::new(#tool_name) {
+                let _ = ::icarus_runtime::ToolRegistry::set_tool_timeout(
+                    tool_id,
+                    ::std::time::Duration::from_millis(#timeout_ms),
+                );
+            }
+        };
+    }
+}
+
+/// Generates registration of a tool's translation key.
+fn generate_i18n_registration(
+    tool_name: &str,
+    wrapper_fn_name: &syn::Ident,
+    key: &str,
+) -> TokenStream {
+    let registration_name = format_ident!(
+        "{}_I18N_REGISTRATION",
+        wrapper_fn_name.to_string().to_uppercase()
+    );
+
+    quote! {
+        #[::linkme::distributed_slice(::icarus_runtime::EXECUTOR_INIT)]
+        static #registration_name: fn() = || {
+            ::icarus_core::i18n::set_tool_key(#tool_name, #key);
+        };
+    }
+}
+
 /// Extracts documentation comment from function attributes.
 fn extract_doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
     let mut doc_parts = Vec::new();
@@ -526,6 +1317,324 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_paid_attribute() {
+        let config = parse_tool_args(quote::quote! { "Premium forecast", paid = "0.1 ICP" });
+        assert_eq!(config.description.as_deref(), Some("Premium forecast"));
+        assert_eq!(config.paid.as_deref(), Some("0.1 ICP"));
+
+        let function: ItemFn = syn::parse_quote! {
+            fn forecast(city: String) -> String { city }
+        };
+        let output = tool_impl(
+            quote::quote! { paid = "0.1 ICP" },
+            quote::quote! { #function },
+        )
+        .unwrap()
+        .to_string();
+        assert!(output.contains("set_tool_price"));
+    }
+
+    #[test]
+    fn test_parse_expensive_flag() {
+        let config = parse_tool_args(quote::quote! { "Render a video", expensive });
+        assert!(config.expensive);
+        assert_eq!(config.description.as_deref(), Some("Render a video"));
+
+        let config = parse_tool_args(quote::quote! { expensive, auth = "user" });
+        assert!(config.expensive);
+        assert_eq!(config.auth_level.as_deref(), Some("user"));
+
+        assert!(!parse_tool_args(quote::quote! { "Cheap" }).expensive);
+    }
+
+    #[test]
+    fn test_encrypted_tool_is_marked() {
+        assert!(parse_tool_args(quote::quote! { "Store a key", encrypted }).encrypted);
+        assert!(parse_tool_args(quote::quote! { encrypted, auth = "user" }).encrypted);
+
+        let function: ItemFn = syn::parse_quote! {
+            fn store_key(key: String) -> String { key }
+        };
+        let output = tool_impl(quote::quote! { encrypted }, quote::quote! { #function })
+            .unwrap()
+            .to_string();
+        assert!(output.contains("mark_encrypted"));
+        assert!(output.contains("SCHEMA_KEY"));
+
+        let output = tool_impl(quote::quote! {}, quote::quote! { #function })
+            .unwrap()
+            .to_string();
+        assert!(!output.contains("mark_encrypted"));
+    }
+
+    #[test]
+    fn test_composite_query_tool_is_marked_read_only() {
+        assert!(parse_tool_args(quote::quote! { "Sum balances", composite_query }).composite_query);
+        assert!(parse_tool_args(quote::quote! { composite_query, auth = "user" }).composite_query);
+
+        let function: ItemFn = syn::parse_quote! {
+            async fn total_balance(account: String) -> u64 { 0 }
+        };
+        let output = tool_impl(
+            quote::quote! { composite_query, auth = "user" },
+            quote::quote! { #function },
+        )
+        .unwrap()
+        .to_string();
+        assert!(output.contains("composite :: mark_composite (\"total_balance\")"));
+        assert!(output.contains("composite :: SCHEMA_KEY"));
+        assert!(output.contains("read_only_hint : Some (true)"));
+
+        let err = tool_impl(
+            quote::quote! { composite_query, paid = "0.1 ICP" },
+            quote::quote! { #function },
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("cannot be paid tools"));
+    }
+
+    #[test]
+    fn test_paginated_tool_gets_cursor_and_limit() {
+        assert!(parse_tool_args(quote::quote! { "List notes", paginated }).paginated);
+        assert!(parse_tool_args(quote::quote! { paginated, auth = "user" }).paginated);
+
+        let function: ItemFn = syn::parse_quote! {
+            fn list_notes(tag: Option<String>) -> Vec<String> { Vec::new() }
+        };
+        let output = tool_impl(quote::quote! { paginated }, quote::quote! { #function })
+            .unwrap()
+            .to_string();
+        assert!(output.contains("pub cursor : Option < String >"));
+        assert!(output.contains("pub limit : Option < u32 >"));
+        assert!(output.contains("\"maximum\" : 500i64"));
+        assert!(output.contains(
+            "paginate (list_notes (args . tag) , args . cursor . as_deref () , args . limit)"
+        ));
+
+        let function: ItemFn = syn::parse_quote! {
+            fn search(query: String) -> Result<Vec<String>, String> { Ok(Vec::new()) }
+        };
+        let output = tool_impl(quote::quote! { paginated }, quote::quote! { #function })
+            .unwrap()
+            .to_string();
+        assert!(output.contains("paginate ((search (args . query)) . map_err"));
+
+        let function: ItemFn = syn::parse_quote! {
+            fn page(limit: u32) -> Vec<u32> { Vec::new() }
+        };
+        let err = tool_impl(quote::quote! { paginated }, quote::quote! { #function }).unwrap_err();
+        assert!(err.to_string().contains("`limit` parameter of their own"));
+    }
+
+    #[test]
+    fn test_job_tool_starts_a_job() {
+        let config = parse_tool_args(quote::quote! { "Rebuild the index", job });
+        assert!(config.job);
+        assert!(parse_tool_args(quote::quote! { job, auth = "admin" }).job);
+
+        let function: ItemFn = syn::parse_quote! {
+            fn reindex(batch: u32) -> impl FnMut() -> Chunk { move || Chunk::more() }
+        };
+        let output = tool_impl(quote::quote! { job }, quote::quote! { #function })
+            .unwrap()
+            .to_string();
+        assert!(output.contains("jobs :: start_job (\"reindex\" , args_json)"));
+        assert!(output.contains("jobs :: register_job_kind (\"reindex\""));
+        assert!(output.contains("Box :: new (reindex (args . batch))"));
+
+        let function: ItemFn = syn::parse_quote! {
+            async fn reindex(batch: u32) -> impl FnMut() -> Chunk { move || Chunk::more() }
+        };
+        assert!(tool_impl(quote::quote! { job }, quote::quote! { #function }).is_err());
+    }
+
+    #[test]
+    fn test_guest_tool_checks_guest_access() {
+        let function: ItemFn = syn::parse_quote! {
+            fn list_notes() -> Vec<String> { Vec::new() }
+        };
+        let output = tool_impl(
+            quote::quote! { "List notes", auth = "guest" },
+            quote::quote! { #function },
+        )
+        .unwrap()
+        .to_string();
+        assert!(output.contains("auth :: has_guest_access (& caller)"));
+        assert!(output.contains("read_only_hint : Some (true)"));
+    }
+
+    #[test]
+    fn test_custom_role_refers_to_declared_role() {
+        let function: ItemFn = syn::parse_quote! {
+            fn publish(id: u64) -> bool { true }
+        };
+        let output = tool_impl(
+            quote::quote! { "Publish a draft", auth = "content_editor" },
+            quote::quote! { #function },
+        )
+        .unwrap()
+        .to_string();
+        assert!(output.contains("crate :: __icarus_roles :: CONTENT_EDITOR"));
+        assert!(output.contains("roles :: require_role_or_higher"));
+
+        assert!(tool_impl(
+            quote::quote! { auth = "Content Editor" },
+            quote::quote! { #function },
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_borrowed_parameters_borrow_from_arguments() {
+        let function: ItemFn = syn::parse_quote! {
+            fn greet(name: &str, note: Option<Cow<'_, str>>, times: u32) -> String {
+                name.repeat(times as usize)
+            }
+        };
+        let output = tool_impl(quote::quote! {}, quote::quote! { #function })
+            .unwrap()
+            .to_string();
+        assert!(output.contains("struct GreetParams < '__icarus_args >"));
+        assert!(output.contains("# [serde (borrow)] pub name : & '__icarus_args str"));
+        assert!(output.contains(
+            "deserialize_with = \"::icarus_runtime::__private::borrow_option_cow_str\")] \
+             pub note : Option < Cow < '__icarus_args , str > >"
+        ));
+        assert!(!output.contains("# [serde (borrow)] pub times"));
+        assert!(
+            output.contains("let args : GreetParams < '_ > = serde_json :: from_str (args_json)")
+        );
+
+        let function: ItemFn = syn::parse_quote! {
+            fn greet(name: String) -> String { name }
+        };
+        let output = tool_impl(quote::quote! {}, quote::quote! { #function })
+            .unwrap()
+            .to_string();
+        assert!(output.contains("struct GreetParams {"));
+
+        let function: ItemFn = syn::parse_quote! {
+            fn reindex(prefix: &str) -> impl FnMut() -> Chunk { move || Chunk::more() }
+        };
+        let err = tool_impl(quote::quote! { job }, quote::quote! { #function }).unwrap_err();
+        assert!(err.to_string().contains("cannot borrow"));
+
+        // serde cannot borrow a slice of anything but bytes
+        let function: ItemFn = syn::parse_quote! {
+            fn total(amounts: &[u64]) -> u64 { amounts.iter().sum() }
+        };
+        let err = tool_impl(quote::quote! {}, quote::quote! { #function }).unwrap_err();
+        assert!(err.to_string().contains("Only &[u8]"));
+    }
+
+    #[test]
+    fn test_content_return_is_tagged() {
+        let function: ItemFn = syn::parse_quote! {
+            fn chart(title: String) -> Vec<Content> { vec![Content::text(title)] }
+        };
+        let output = tool_impl(quote::quote! {}, quote::quote! { #function })
+            .unwrap()
+            .to_string();
+        assert!(output.contains("CONTENT_METADATA"));
+
+        let function: ItemFn = syn::parse_quote! {
+            fn title(title: String) -> String { title }
+        };
+        let output = tool_impl(quote::quote! {}, quote::quote! { #function })
+            .unwrap()
+            .to_string();
+        assert!(!output.contains("CONTENT_METADATA"));
+
+        let function: ItemFn = syn::parse_quote! {
+            fn summarize(text: String) -> SamplingRequest { SamplingRequest::new("store", 100) }
+        };
+        let output = tool_impl(quote::quote! {}, quote::quote! { #function })
+            .unwrap()
+            .to_string();
+        assert!(output.contains("SAMPLING_METADATA"));
+    }
+
+    #[test]
+    fn test_context_is_injected_not_a_parameter() {
+        let function: ItemFn = syn::parse_quote! {
+            fn whoami(ctx: &ToolContext, verbose: bool) -> String { ctx.caller.to_text() }
+        };
+        let output = tool_impl(quote::quote! {}, quote::quote! { #function })
+            .unwrap()
+            .to_string();
+        assert!(output.contains(
+            "whoami (& :: icarus_core :: context :: current_for (\"whoami\") , args . verbose)"
+        ));
+        assert!(!output.contains("pub ctx"));
+
+        let function: ItemFn = syn::parse_quote! {
+            fn whoami(verbose: bool, ctx: ToolContext) -> String { ctx.caller.to_text() }
+        };
+        let err = tool_impl(quote::quote! {}, quote::quote! { #function }).unwrap_err();
+        assert!(err.to_string().contains("must be the first parameter"));
+    }
+
+    #[test]
+    fn test_parse_timeout_override() {
+        let config = parse_tool_args(quote::quote! { "Fetch a page", timeout_ms = 5000 });
+        assert_eq!(config.timeout_ms, Some(5000));
+        assert_eq!(config.description.as_deref(), Some("Fetch a page"));
+
+        let config = parse_tool_args(quote::quote! { timeout_ms = 250, auth = "user" });
+        assert_eq!(config.timeout_ms, Some(250));
+        assert_eq!(config.auth_level.as_deref(), Some("user"));
+
+        let function: ItemFn = syn::parse_quote! {
+            async fn fetch(url: String) -> String { url }
+        };
+        let output = tool_impl(
+            quote::quote! { timeout_ms = 5000 },
+            quote::quote! { #function },
+        )
+        .unwrap()
+        .to_string();
+        assert!(output.contains("set_tool_timeout"));
+    }
+
+    #[test]
+    fn test_cache_ttl_is_listed_in_the_schema() {
+        let config = parse_tool_args(quote::quote! { "Forecast", cache_ttl_secs = 300 });
+        assert_eq!(config.cache_ttl_secs, Some(300));
+        let config = parse_tool_args(quote::quote! { cache_ttl_secs = 5, auth = "none" });
+        assert_eq!(config.cache_ttl_secs, Some(5));
+
+        let function: ItemFn = syn::parse_quote! {
+            fn forecast(city: String) -> String { city }
+        };
+        let output = tool_impl(
+            quote::quote! { auth = "none", cache_ttl_secs = 300 },
+            quote::quote! { #function },
+        )
+        .unwrap()
+        .to_string();
+        assert!(output.contains("CACHE_TTL_SCHEMA_KEY"));
+        assert!(output.contains("300u64"));
+    }
+
+    #[test]
+    fn test_i18n_key() {
+        let config = parse_tool_args(quote::quote! { "Add two numbers", i18n = "tools.add" });
+        assert_eq!(config.i18n.as_deref(), Some("tools.add"));
+
+        let function: ItemFn = syn::parse_quote! {
+            fn add(a: i32, b: i32) -> i32 { a + b }
+        };
+        let output = tool_impl(
+            quote::quote! { i18n = "tools.add" },
+            quote::quote! { #function },
+        )
+        .unwrap()
+        .to_string();
+        assert!(output.contains("set_tool_key"));
+        assert!(output.contains("\"tools.add\""));
+    }
+
     #[test]
     fn test_validate_function_signature() {
         // Valid function
@@ -547,6 +1656,35 @@ mod tests {
         assert!(validate_function_signature(&generic_fn).is_err());
     }
 
+    #[test]
+    fn test_tool_name_validation() {
+        let span = proc_macro2::Span::call_site();
+        assert!(validate_tool_name("get-weather", span).is_ok());
+        assert!(validate_tool_name("notes.search_v2", span).is_ok());
+
+        let err = validate_tool_name("get weather", span).unwrap_err();
+        assert!(err.to_string().contains("invalid character ' '"));
+        assert!(validate_tool_name("", span).is_err());
+        assert!(validate_tool_name("2fa", span).is_err());
+        assert!(validate_tool_name(&"a".repeat(256), span).is_err());
+
+        let result = tool_impl(
+            quote! { name = "bad name" },
+            quote! { fn renamed() -> String { String::new() } },
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_name_markers_are_distinct() {
+        let span = proc_macro2::Span::call_site();
+        let marker = |name: &str| generate_name_marker(name, span).to_string();
+        assert_ne!(marker("a-b"), marker("a_b"));
+        assert_ne!(marker("a.b"), marker("a-b"));
+        assert_ne!(marker("a_0"), marker("a-"));
+        assert!(marker("get-weather").contains("__ICARUS_TOOL_NAMED_get_0weather"));
+    }
+
     #[test]
     fn test_parameter_count_limit() {
         // Create a function with exactly 50 parameters (should pass)
@@ -69,6 +69,24 @@ fn parse_param_attributes(attrs: &[Attribute]) -> MacroResult<ParamAttributes> {
 
             Ok(())
         })?;
+
+        // An empty range would make every call fail schema validation
+        if let (Some(min), Some(max)) = (result.min, result.max) {
+            if min > max {
+                return Err(MacroError::configuration_spanned(
+                    format!("Parameter min ({min}) is greater than max ({max})"),
+                    attr.span(),
+                ));
+            }
+        }
+        if let (Some(min), Some(max)) = (result.min_length, result.max_length) {
+            if min > max {
+                return Err(MacroError::configuration_spanned(
+                    format!("Parameter min_length ({min}) is greater than max_length ({max})"),
+                    attr.span(),
+                ));
+            }
+        }
     }
 
     Ok(result)
@@ -152,6 +170,53 @@ fn is_option_type(ty: &Type) -> bool {
     false
 }
 
+/// Checks if a type is `Vec<Content>`, i.e. a tool returning MCP content directly.
+pub(crate) fn is_content_vec(ty: &Type) -> bool {
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+    let Some(segment) = type_path.path.segments.last() else {
+        return false;
+    };
+    if segment.ident != "Vec" {
+        return false;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return false;
+    };
+
+    matches!(
+        args.args.first(),
+        Some(syn::GenericArgument::Type(Type::Path(inner)))
+            if inner.path.segments.last().is_some_and(|s| s.ident == "Content")
+    )
+}
+
+/// Checks if a type is `SamplingRequest`, i.e. a tool asking the client's LLM
+/// for a completion.
+pub(crate) fn is_sampling_request(ty: &Type) -> bool {
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+    type_path
+        .path
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "SamplingRequest")
+}
+
+/// Checks if a type is `Result<T, E>` or an alias named `Result`.
+pub(crate) fn is_result_type(ty: &Type) -> bool {
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+    type_path
+        .path
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "Result")
+}
+
 /// Extracts the return type from a function signature.
 pub(crate) fn extract_return_type(output: &ReturnType) -> Type {
     match output {
@@ -160,6 +225,123 @@ pub(crate) fn extract_return_type(output: &ReturnType) -> Type {
     }
 }
 
+/// Lifetime of the argument JSON that borrowed parameters point into.
+pub(crate) const ARGS_LIFETIME: &str = "'__icarus_args";
+
+/// Ties every non-`'static` reference and lifetime in `ty` to `lifetime`, so
+/// that a parameter such as `&str` or `Cow<'_, str>` can become a field of
+/// the generated argument struct. Returns whether `ty` borrows at all.
+///
+/// # Errors
+///
+/// Rejects slices other than `&[u8]`: serde can only borrow bytes, which
+/// come from a JSON string, so `&[T]` would fail to compile in the generated
+/// struct with an error pointing at the macro.
+pub(crate) fn bind_lifetimes(ty: &mut Type, lifetime: &syn::Lifetime) -> MacroResult<bool> {
+    match ty {
+        Type::Reference(reference) => {
+            if let Type::Slice(slice) = &*reference.elem {
+                if !is_u8(&slice.elem) {
+                    return Err(MacroError::invalid_signature_spanned(
+                        "Only &[u8] can be borrowed from the arguments; take Vec<T> instead",
+                        reference.span(),
+                    ));
+                }
+            }
+            if !matches!(&reference.lifetime, Some(l) if l.ident == "static") {
+                reference.lifetime = Some(lifetime.clone());
+                bind_lifetimes(&mut reference.elem, lifetime)?;
+                return Ok(true);
+            }
+            bind_lifetimes(&mut reference.elem, lifetime)
+        }
+        Type::Path(type_path) => {
+            let mut borrows = false;
+            for segment in &mut type_path.path.segments {
+                let syn::PathArguments::AngleBracketed(args) = &mut segment.arguments else {
+                    continue;
+                };
+                for arg in &mut args.args {
+                    match arg {
+                        syn::GenericArgument::Lifetime(l) if l.ident != "static" => {
+                            *l = lifetime.clone();
+                            borrows = true;
+                        }
+                        syn::GenericArgument::Type(inner) => {
+                            borrows |= bind_lifetimes(inner, lifetime)?;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Ok(borrows)
+        }
+        Type::Slice(slice) => bind_lifetimes(&mut slice.elem, lifetime),
+        Type::Array(array) => bind_lifetimes(&mut array.elem, lifetime),
+        Type::Paren(paren) => bind_lifetimes(&mut paren.elem, lifetime),
+        Type::Group(group) => bind_lifetimes(&mut group.elem, lifetime),
+        Type::Tuple(tuple) => tuple.elems.iter_mut().try_fold(false, |borrows, elem| {
+            Ok(bind_lifetimes(elem, lifetime)? | borrows)
+        }),
+        _ => Ok(false),
+    }
+}
+
+fn is_u8(ty: &Type) -> bool {
+    matches!(ty, Type::Path(type_path) if type_path.qself.is_none() && type_path.path.is_ident("u8"))
+}
+
+/// Checks if a parameter borrows from the argument JSON.
+pub(crate) fn borrows_arguments(param: &ParameterInfo) -> MacroResult<bool> {
+    let lifetime = syn::Lifetime::new(ARGS_LIFETIME, proc_macro2::Span::call_site());
+    bind_lifetimes(&mut param.ty.clone(), &lifetime)
+}
+
+/// Checks if a type is `Option<Cow<str>>`, whose `Cow` serde deserializes
+/// owned unless told to borrow it.
+pub(crate) fn is_option_cow_str(ty: &Type) -> bool {
+    let Some(inner) = generic_type_arg(ty, "Option") else {
+        return false;
+    };
+    generic_type_arg(inner, "Cow").is_some_and(
+        |target| matches!(target, Type::Path(type_path) if type_path.path.is_ident("str")),
+    )
+}
+
+/// Returns the type argument of `ty` if it is `name<T>`.
+fn generic_type_arg<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != name {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    args.args.iter().find_map(|arg| match arg {
+        syn::GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    })
+}
+
+/// Puts each item of `code` behind a cfg that leaves it out of WASM
+/// component builds (`wasm32-wasip2`), where canister endpoints would import
+/// the IC system API the host does not provide.
+pub(crate) fn canister_only(code: TokenStream) -> TokenStream {
+    let Ok(file) = syn::parse2::<syn::File>(code.clone()) else {
+        return code;
+    };
+    let items = file.items;
+    quote! {
+        #(
+            #[cfg(not(all(target_os = "wasi", target_env = "p2")))]
+            #items
+        )*
+    }
+}
+
 /// Generates a parameter structure name from a function name.
 pub(crate) fn generate_param_struct_name(fn_name: &Ident) -> Ident {
     format_ident!("{}Params", to_pascal_case(&fn_name.to_string()))
@@ -180,6 +362,22 @@ fn to_pascal_case(s: &str) -> String {
         .collect()
 }
 
+/// Converts `PascalCase` to `snake_case`.
+pub(crate) fn to_snake_case(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 4);
+    for (i, c) in s.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                result.push('_');
+            }
+            result.extend(c.to_lowercase());
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
 /// Generates JSON schema for a parameter type.
 #[allow(dead_code)]
 pub(crate) fn generate_param_schema(param: &ParameterInfo) -> TokenStream {
@@ -290,15 +488,31 @@ pub(crate) fn generate_json_schema_from_parameters(params: &[ParameterInfo]) ->
 
 /// Maps Rust types to JSON Schema types.
 fn get_json_type_for_rust_type(ty: &Type) -> &'static str {
+    // `&str`, `&[T]` and the like have the schema of what they point to,
+    // except that borrowed bytes come from a string
+    if let Type::Reference(reference) = ty {
+        if matches!(&*reference.elem, Type::Slice(slice) if is_u8(&slice.elem)) {
+            return "string";
+        }
+        return get_json_type_for_rust_type(&reference.elem);
+    }
+    if let Type::Slice(_) = ty {
+        return "array";
+    }
+
     // Extract the base type name from the Type
     if let Type::Path(type_path) = ty {
         if let Some(segment) = type_path.path.segments.last() {
             let type_name = segment.ident.to_string();
 
-            // Handle Option<T> - unwrap to get inner type
-            if type_name == "Option" {
+            // Handle Option<T> and Cow<T> - unwrap to get inner type
+            if type_name == "Option" || type_name == "Cow" {
                 if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
-                    if let Some(syn::GenericArgument::Type(inner_ty)) = args.args.first() {
+                    let inner_ty = args.args.iter().find_map(|arg| match arg {
+                        syn::GenericArgument::Type(inner_ty) => Some(inner_ty),
+                        _ => None,
+                    });
+                    if let Some(inner_ty) = inner_ty {
                         return get_json_type_for_rust_type(inner_ty);
                     }
                 }
@@ -372,26 +586,74 @@ pub(crate) fn generate_tool_registration(
     }
 }
 
+/// How a tool takes the injected `ToolContext`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ContextArg {
+    /// `ctx: ToolContext`
+    Owned,
+    /// `ctx: &ToolContext`
+    Borrowed,
+}
+
+/// Returns how `input` takes a `ToolContext`, if it does.
+///
+/// Only the last path segment is compared, so `ToolContext` works however
+/// it was imported.
+pub(crate) fn context_arg(input: &FnArg) -> Option<ContextArg> {
+    let FnArg::Typed(PatType { ty, .. }) = input else {
+        return None;
+    };
+    let (ty, arg) = match ty.as_ref() {
+        Type::Reference(reference) if reference.mutability.is_none() => {
+            (reference.elem.as_ref(), ContextArg::Borrowed)
+        }
+        ty => (ty, ContextArg::Owned),
+    };
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    type_path
+        .path
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "ToolContext")
+        .then_some(arg)
+}
+
+/// Generates the expression passed for an injected `ToolContext`.
+pub(crate) fn generate_context_value(arg: ContextArg, tool_name: &str) -> TokenStream {
+    let context = quote! { ::icarus_core::context::current_for(#tool_name) };
+    match arg {
+        ContextArg::Owned => context,
+        ContextArg::Borrowed => quote! { &#context },
+    }
+}
+
 /// Checks if a function is async.
 pub(crate) fn is_async_function(sig: &syn::Signature) -> bool {
     sig.asyncness.is_some()
 }
 
 /// Generates appropriate call expression for sync/async functions.
+///
+/// `context` is passed before the parsed arguments when the tool takes a
+/// `ToolContext`.
 pub(crate) fn generate_function_call(
     fn_name: &Ident,
     params: &[ParameterInfo],
     is_async: bool,
+    context: Option<&TokenStream>,
 ) -> TokenStream {
     let param_names: Vec<&Ident> = params.iter().map(|p| &p.name).collect();
+    let context = context.map(|context| quote! { #context, });
 
     if is_async {
         quote! {
-            #fn_name(#(args.#param_names),*).await
+            #fn_name(#context #(args.#param_names),*).await
         }
     } else {
         quote! {
-            #fn_name(#(args.#param_names),*)
+            #fn_name(#context #(args.#param_names),*)
         }
     }
 }
@@ -408,6 +670,28 @@ mod tests {
         assert_eq!(to_pascal_case("a_b_c"), "ABC");
     }
 
+    #[test]
+    fn test_to_snake_case() {
+        assert_eq!(to_snake_case("MemoryServer"), "memory_server");
+        assert_eq!(to_snake_case("Notes"), "notes");
+    }
+
+    #[test]
+    fn test_context_arg() {
+        let owned: FnArg = parse_quote!(ctx: ToolContext);
+        let borrowed: FnArg = parse_quote!(ctx: &icarus::context::ToolContext);
+        let mutable: FnArg = parse_quote!(ctx: &mut ToolContext);
+        let other: FnArg = parse_quote!(query: String);
+
+        assert_eq!(context_arg(&owned), Some(ContextArg::Owned));
+        assert_eq!(context_arg(&borrowed), Some(ContextArg::Borrowed));
+        assert_eq!(context_arg(&mutable), None);
+        assert_eq!(context_arg(&other), None);
+
+        let value = generate_context_value(ContextArg::Borrowed, "search").to_string();
+        assert!(value.starts_with("& :: icarus_core :: context :: current_for (\"search\")"));
+    }
+
     #[test]
     fn test_is_option_type() {
         let option_type: Type = parse_quote!(Option<String>);
@@ -417,6 +701,58 @@ mod tests {
         assert!(!is_option_type(&string_type));
     }
 
+    #[test]
+    fn test_is_content_vec() {
+        assert!(is_content_vec(&parse_quote!(Vec<Content>)));
+        assert!(is_content_vec(&parse_quote!(Vec<icarus::Content>)));
+        assert!(!is_content_vec(&parse_quote!(Vec<String>)));
+        assert!(!is_content_vec(&parse_quote!(Content)));
+    }
+
+    #[test]
+    fn test_is_sampling_request() {
+        assert!(is_sampling_request(&parse_quote!(SamplingRequest)));
+        assert!(is_sampling_request(&parse_quote!(
+            icarus::sampling::SamplingRequest
+        )));
+        assert!(!is_sampling_request(&parse_quote!(Vec<SamplingRequest>)));
+    }
+
+    #[test]
+    fn test_bind_lifetimes() {
+        let lifetime = syn::Lifetime::new(ARGS_LIFETIME, proc_macro2::Span::call_site());
+        let mut ty: Type = parse_quote!(Option<(&str, Cow<'_, str>)>);
+        assert!(bind_lifetimes(&mut ty, &lifetime).unwrap());
+        assert_eq!(
+            quote!(#ty).to_string(),
+            "Option < (& '__icarus_args str , Cow < '__icarus_args , str >) >"
+        );
+
+        let mut ty: Type = parse_quote!(Vec<String>);
+        assert!(!bind_lifetimes(&mut ty, &lifetime).unwrap());
+        let mut ty: Type = parse_quote!(&'static str);
+        assert!(!bind_lifetimes(&mut ty, &lifetime).unwrap());
+
+        let mut ty: Type = parse_quote!(Option<&[u8]>);
+        assert!(bind_lifetimes(&mut ty, &lifetime).unwrap());
+        let mut ty: Type = parse_quote!(Option<&[u32]>);
+        let err = bind_lifetimes(&mut ty, &lifetime).unwrap_err();
+        assert!(err.to_string().contains("Only &[u8]"));
+
+        assert!(is_option_cow_str(&parse_quote!(Option<Cow<'_, str>>)));
+        assert!(!is_option_cow_str(&parse_quote!(Option<Cow<'_, [u8]>>)));
+        assert!(!is_option_cow_str(&parse_quote!(Cow<'_, str>)));
+        assert!(!is_option_cow_str(&parse_quote!(Option<&str>)));
+
+        assert_eq!(get_json_type_for_rust_type(&parse_quote!(&str)), "string");
+        assert_eq!(
+            get_json_type_for_rust_type(&parse_quote!(Cow<'_, str>)),
+            "string"
+        );
+        assert_eq!(get_json_type_for_rust_type(&parse_quote!(&[u32])), "array");
+        assert_eq!(get_json_type_for_rust_type(&parse_quote!(&[u8])), "string");
+    }
+
     #[test]
     fn test_generate_param_struct_name() {
         let fn_name = format_ident!("my_function");
@@ -456,6 +792,16 @@ mod tests {
         assert_eq!(result.max, None);
     }
 
+    #[test]
+    fn test_parse_param_attributes_rejects_empty_ranges() {
+        let attrs: Vec<Attribute> = vec![parse_quote!(#[param(min = 10, max = 1)])];
+        let err = parse_param_attributes(&attrs).unwrap_err();
+        assert!(err.to_string().contains("min (10) is greater than max (1)"));
+
+        let attrs: Vec<Attribute> = vec![parse_quote!(#[param(min_length = 5, max_length = 2)])];
+        assert!(parse_param_attributes(&attrs).is_err());
+    }
+
     #[test]
     fn test_parse_param_attributes_empty() {
         let attrs: Vec<Attribute> = vec![];
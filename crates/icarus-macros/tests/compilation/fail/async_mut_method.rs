@@ -0,0 +1,17 @@
+use icarus_macros::icarus_tools;
+
+#[derive(Default)]
+struct Server {
+    log: Vec<String>,
+}
+
+#[icarus_tools]
+impl Server {
+    /// This should fail - async methods cannot take &mut self
+    #[tool]
+    async fn append(&mut self, line: String) {
+        self.log.push(line);
+    }
+}
+
+fn main() {}
\ No newline at end of file
@@ -0,0 +1,15 @@
+use icarus_macros::tool;
+
+/// Searches notes
+#[tool]
+fn search(query: String) -> String {
+    query
+}
+
+/// This should fail - another tool is already named `search`
+#[tool(name = "search")]
+fn search_archive(query: String) -> String {
+    query
+}
+
+fn main() {}
\ No newline at end of file
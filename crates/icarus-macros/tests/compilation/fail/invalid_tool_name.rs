@@ -0,0 +1,9 @@
+use icarus_macros::tool;
+
+/// This should fail - tool names cannot contain spaces
+#[tool(name = "get weather")]
+fn get_weather(city: String) -> String {
+    city
+}
+
+fn main() {}
\ No newline at end of file
@@ -0,0 +1,10 @@
+use icarus_macros::IcarusStorable;
+
+/// This should fail - unions cannot be Candid-encoded
+#[derive(IcarusStorable)]
+union Bits {
+    int: u32,
+    float: f32,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use icarus_macros::IcarusStorable;
+
+/// This should fail - json is not a supported codec
+#[derive(IcarusStorable)]
+#[icarus_storable(codec = "json")]
+struct Note {
+    title: String,
+}
+
+fn main() {}
@@ -0,0 +1,45 @@
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::Storable;
+use icarus_macros::IcarusStorable;
+use serde::Serialize;
+
+/// Large record stored as CBOR
+#[derive(Debug, PartialEq, Serialize, Deserialize, IcarusStorable)]
+#[icarus_storable(codec = "cbor")]
+struct Document {
+    title: String,
+    body: String,
+    tags: Vec<String>,
+}
+
+/// Generic enum stored with bincode; needs no CandidType
+#[derive(Debug, PartialEq, Serialize, Deserialize, IcarusStorable)]
+#[icarus_storable(codec = "bincode")]
+enum Entry<T> {
+    Value(T),
+    Tombstone { deleted_at: u64 },
+}
+
+/// The default codec can be named explicitly
+#[derive(Debug, PartialEq, CandidType, Deserialize, IcarusStorable)]
+#[icarus_storable(codec = "candid")]
+struct Counter {
+    count: u64,
+}
+
+fn main() {
+    let document = Document {
+        title: "notes".to_string(),
+        body: "text".repeat(10),
+        tags: vec!["a".to_string()],
+    };
+    assert_eq!(Document::from_bytes(document.to_bytes()), document);
+
+    let entry = Entry::Value(vec![1u8, 2, 3]);
+    assert_eq!(Entry::from_bytes(entry.to_bytes()), entry);
+    let tombstone = Entry::<u8>::Tombstone { deleted_at: 9 };
+    assert_eq!(Entry::from_bytes(tombstone.to_bytes()), tombstone);
+
+    let counter = Counter { count: 3 };
+    assert_eq!(Counter::from_bytes(counter.to_bytes()), counter);
+}
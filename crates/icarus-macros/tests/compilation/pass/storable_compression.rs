@@ -0,0 +1,39 @@
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::Storable;
+use icarus_macros::IcarusStorable;
+use serde::Serialize;
+
+/// Long text compressed once it reaches the default threshold
+#[derive(Debug, PartialEq, CandidType, Deserialize, IcarusStorable)]
+#[icarus_storable(compress = "lz4")]
+struct Article {
+    title: String,
+    body: String,
+}
+
+/// Compression combined with another codec and a custom threshold
+#[derive(Debug, PartialEq, Serialize, Deserialize, IcarusStorable)]
+#[icarus_storable(codec = "cbor", compress = "lz4", compress_threshold = 64)]
+struct LogLine {
+    message: String,
+}
+
+fn main() {
+    let article = Article {
+        title: "Compression".to_string(),
+        body: "repetitive text ".repeat(100),
+    };
+    let stored = article.to_bytes();
+    assert!(stored.len() < article.body.len());
+    assert_eq!(Article::from_bytes(stored), article);
+
+    let short = LogLine {
+        message: "ok".to_string(),
+    };
+    assert_eq!(LogLine::from_bytes(short.to_bytes()), short);
+
+    let stats = icarus_core::compression::stats();
+    assert!(stats
+        .iter()
+        .any(|s| s.name == "Article" && s.compressed_values == 1));
+}
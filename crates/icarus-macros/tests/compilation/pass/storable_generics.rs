@@ -0,0 +1,51 @@
+use std::borrow::Cow;
+use std::fmt::Debug;
+
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::Storable;
+use icarus_macros::IcarusStorable;
+
+/// Generic struct with a where-clause
+#[derive(Debug, PartialEq, CandidType, Deserialize, IcarusStorable)]
+struct Versioned<T>
+where
+    T: Debug,
+{
+    version: u64,
+    value: T,
+}
+
+/// Static borrowed data
+#[derive(Debug, PartialEq, CandidType, Deserialize, IcarusStorable)]
+struct Note {
+    title: Cow<'static, str>,
+    body: Cow<'static, str>,
+}
+
+/// Enum with data
+#[derive(Debug, PartialEq, CandidType, Deserialize, IcarusStorable)]
+enum Job<T> {
+    Pending { attempts: u32 },
+    Done(Versioned<T>),
+    Cancelled,
+}
+
+fn round_trip<T: Storable + Debug + PartialEq>(value: T) {
+    assert_eq!(T::from_bytes(value.to_bytes()), value);
+}
+
+fn main() {
+    round_trip(Versioned {
+        version: 2,
+        value: "text".to_string(),
+    });
+    round_trip(Note {
+        title: Cow::Borrowed("title"),
+        body: Cow::Owned("body".to_string()),
+    });
+    round_trip(Job::Done(Versioned {
+        version: 1,
+        value: 7u64,
+    }));
+    round_trip(Job::<u64>::Pending { attempts: 3 });
+}
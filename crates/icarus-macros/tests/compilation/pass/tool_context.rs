@@ -0,0 +1,29 @@
+use candid::Principal;
+use icarus_core::context::{self, ToolContext};
+use icarus_macros::tool;
+
+/// Reports who is calling
+#[tool]
+fn whoami(ctx: &ToolContext, greeting: String) -> String {
+    format!("{greeting}, {} via {}", ctx.caller, ctx.tool_name)
+}
+
+/// Reports whether the call has a deadline
+#[tool]
+fn bounded(ctx: ToolContext) -> bool {
+    ctx.deadline.is_some()
+}
+
+fn main() {
+    let info = whoami_tool_info();
+    let properties = &info.input_schema["properties"];
+    assert!(properties.get("greeting").is_some());
+    assert!(properties.get("ctx").is_none());
+
+    context::set_caller_override(Some(Principal::anonymous()));
+    assert_eq!(
+        whoami_tool_wrapper(r#"{"greeting":"hi"}"#).unwrap(),
+        "\"hi, 2vxsx-fae via whoami\""
+    );
+    assert_eq!(bounded_tool_wrapper("{}").unwrap(), "false");
+}
\ No newline at end of file
@@ -0,0 +1,48 @@
+use icarus_macros::icarus_tools;
+
+#[derive(Default)]
+struct MemoryServer {
+    memories: Vec<String>,
+}
+
+#[icarus_tools]
+impl MemoryServer {
+    /// Stores a memory and returns its index
+    #[tool]
+    fn remember(&mut self, text: String) -> usize {
+        self.memories.push(text);
+        self.memories.len() - 1
+    }
+
+    /// Counts the stored memories
+    #[tool(name = "count-memories")]
+    fn count(&self) -> usize {
+        self.memories.len()
+    }
+
+    /// Reports the server version
+    #[tool]
+    fn version() -> String {
+        "1.0".to_string()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.memories.is_empty()
+    }
+}
+
+fn main() {
+    let remember = memory_server_remember_tool_info();
+    assert_eq!(remember.name, "remember");
+
+    let count = memory_server_count_tool_info();
+    assert_eq!(count.name, "count-memories");
+
+    assert_eq!(
+        memory_server_remember_tool_wrapper(r#"{"text":"hello"}"#).unwrap(),
+        "0"
+    );
+    assert_eq!(memory_server_count_tool_wrapper("{}").unwrap(), "1");
+    assert!(!icarus_runtime::state::shared::<MemoryServer>().is_empty());
+    assert_eq!(memory_server_version_tool_wrapper("{}").unwrap(), "\"1.0\"");
+}
@@ -0,0 +1,167 @@
+//! Exporting the registered tools as a WASM component.
+//!
+//! Built for `wasm32-wasip2` with the `component` feature, a tool crate
+//! becomes a component of the `icarus:tools/tool-bundle` world in
+//! `wit/world.wit`, so hosts other than the Internet Computer, such as
+//! wasmtime-based agent runtimes, can embed it:
+//!
+//! ```text
+//! interface registry {
+//!     list-tools: func() -> list<tool>;
+//!     call-tool: func(name: string, arguments: string) -> result<string, call-error>;
+//! }
+//! ```
+//!
+//! `mcp!{}` invokes [`export_component!`](crate::export_component) for that
+//! target and leaves out the canister endpoints, which need the IC system
+//! API; `icarus build --component` builds such a bundle. Tools run
+//! synchronously, one call at a time, with the environment of
+//! [`icarus_core::host::NativeHost`].
+//!
+//! This module requires the `component` feature.
+
+use icarus_core::{Tool, ToolId};
+
+use crate::{RuntimeError, ToolRegistry, ToolResult};
+
+/// Bindings generated from `wit/world.wit`.
+#[doc(hidden)]
+#[allow(unsafe_code, missing_docs, clippy::all, clippy::pedantic)]
+pub mod bindings {
+    wit_bindgen::generate!({
+        world: "tool-bundle",
+        path: "wit",
+        pub_export_macro: true,
+        export_macro_name: "__export_tool_bundle",
+        default_bindings_module: "icarus_runtime::component::bindings",
+    });
+}
+
+use bindings::exports::icarus::tools::registry::{CallError, Guest, Tool as ToolInfo};
+
+/// The implementation of the `registry` interface exported by a bundle.
+pub struct ToolBundle;
+
+impl Guest for ToolBundle {
+    fn list_tools() -> Vec<ToolInfo> {
+        crate::initialize_executors();
+        crate::list_tools()
+            .iter()
+            .filter(|tool| icarus_core::tool_switches::is_enabled(&tool.name))
+            .map(tool_info)
+            .collect()
+    }
+
+    fn call_tool(name: String, arguments: String) -> Result<String, CallError> {
+        crate::initialize_executors();
+        call(&name, &arguments)
+    }
+}
+
+/// Converts a registered tool to the WIT record.
+fn tool_info(tool: &Tool) -> ToolInfo {
+    ToolInfo {
+        name: tool.name.to_string(),
+        description: tool.description.as_ref().map(ToString::to_string),
+        input_schema: serde_json::to_string(tool.input_schema.as_ref())
+            .unwrap_or_else(|_| "{}".to_string()),
+    }
+}
+
+/// Runs a tool, mapping the outcome to the WIT result.
+fn call(name: &str, arguments: &str) -> Result<String, CallError> {
+    let tool_id = ToolId::new(name).map_err(|e| CallError::NotFound(e.to_string()))?;
+    if serde_json::from_str::<serde_json::Value>(arguments).is_err() {
+        return Err(CallError::InvalidArguments(format!(
+            "Arguments of '{name}' are not valid JSON"
+        )));
+    }
+
+    match ToolRegistry::execute_tool_sync(&tool_id, arguments) {
+        None => Err(CallError::NotFound(format!("Tool not found: {name}"))),
+        Some(Err(RuntimeError::InvalidArguments { details, .. })) => {
+            Err(CallError::InvalidArguments(details))
+        }
+        Some(Err(e)) => Err(CallError::Failed(e.to_string())),
+        Some(Ok(ToolResult::Success { result, .. })) => Ok(result.into_owned()),
+        Some(Ok(ToolResult::Error { message, .. })) => Err(CallError::Failed(message.into_owned())),
+        Some(Ok(ToolResult::Pending { status, .. })) => Err(CallError::Failed(
+            status.map_or_else(|| "Tool execution pending".to_string(), |s| s.into_owned()),
+        )),
+        Some(Ok(ToolResult::Timeout { timeout_ms, .. })) => Err(CallError::Failed(format!(
+            "Tool timed out after {timeout_ms} ms"
+        ))),
+    }
+}
+
+/// Exports the registered tools as the `icarus:tools/registry` interface.
+///
+/// `mcp!{}` invokes this when building for `wasm32-wasip2`; crates without
+/// `mcp!{}` can invoke it once at their root.
+#[macro_export]
+macro_rules! export_component {
+    () => {
+        const _: () = {
+            use $crate::component::ToolBundle as IcarusToolBundle;
+            $crate::__export_tool_bundle!(IcarusToolBundle);
+        };
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn shout(args: &str) -> crate::RuntimeResult<ToolResult<'static>> {
+        let args: serde_json::Value = serde_json::from_str(args).unwrap_or_default();
+        match args["text"].as_str() {
+            Some(text) => Ok(ToolResult::success(format!("\"{}\"", text.to_uppercase()))),
+            None => Ok(ToolResult::error("text is required")),
+        }
+    }
+
+    #[test]
+    fn test_call_maps_outcomes() {
+        let tool_id = ToolId::new("component_shout").unwrap();
+        ToolRegistry::register_dynamic_tool(Tool::new(
+            "component_shout".to_string(),
+            "Shouts",
+            Arc::new(serde_json::Map::new()),
+        ))
+        .unwrap();
+        ToolRegistry::register_sync_executor(tool_id, shout).unwrap();
+
+        assert_eq!(
+            call("component_shout", r#"{"text":"hi"}"#).unwrap(),
+            "\"HI\""
+        );
+        assert!(matches!(
+            call("component_shout", "{}"),
+            Err(CallError::Failed(message)) if message == "text is required"
+        ));
+        assert!(matches!(
+            call("component_shout", "not json"),
+            Err(CallError::InvalidArguments(_))
+        ));
+        assert!(matches!(
+            call("component_missing", "{}"),
+            Err(CallError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_tool_info_carries_the_schema() {
+        let mut schema = serde_json::Map::new();
+        schema.insert("type".to_string(), serde_json::json!("object"));
+        let info = tool_info(&Tool::new(
+            "component_schema".to_string(),
+            "Has a schema",
+            Arc::new(schema),
+        ));
+        assert_eq!(info.name, "component_schema");
+        assert_eq!(info.description.as_deref(), Some("Has a schema"));
+        assert_eq!(info.input_schema, r#"{"type":"object"}"#);
+    }
+}
@@ -0,0 +1,184 @@
+//! Hot-reloadable tool registration for development (`dev` feature).
+//!
+//! Compile-time `#[tool]` functions are fixed for the lifetime of a wasm
+//! module. During development it is convenient to register tools at runtime
+//! instead, and to have them come back after every redeploy without
+//! restarting the bridge:
+//!
+//! - [`register_tool`] adds a tool together with its executor;
+//! - [`on_reload`] remembers a hook that registers tools, and [`reload`]
+//!   re-runs every hook (call it from `post_upgrade`);
+//! - [`generation`] counts registry changes within one module instance, and
+//!   [`ToolRegistry::fingerprint`] identifies the tool list across upgrades.
+//!
+//! The generated `mcp_tools_sequence` query increments whenever the
+//! fingerprint changes; the bridge polls it and sends
+//! `notifications/tools/list_changed` to the MCP client, so new tools show up
+//! without reconnecting.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_core::{LegacyToolResult, Tool, ToolId};
+//! use icarus_runtime::{dev, ToolRegistry};
+//! use std::sync::Arc;
+//!
+//! fn register_echo() {
+//!     let tool = Tool::new("dev_echo", "Echoes its input", Arc::new(serde_json::Map::new()));
+//!     dev::register_tool(tool, |args| Ok(LegacyToolResult::success(args.to_string())))
+//!         .expect("registration succeeds");
+//! }
+//!
+//! dev::on_reload(register_echo);
+//! dev::reload().unwrap();
+//! assert!(ToolRegistry::has_tool(&ToolId::new("dev_echo").unwrap()));
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+use icarus_core::{Tool, ToolId};
+
+use crate::{RuntimeError, RuntimeResult, SyncToolExecutor, ToolRegistry};
+
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+static RELOAD_HOOKS: RwLock<Vec<fn()>> = RwLock::new(Vec::new());
+
+/// Returns how many times the tool list changed since the module started.
+#[must_use]
+pub fn generation() -> u64 {
+    GENERATION.load(Ordering::Relaxed)
+}
+
+fn bump_generation() {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Registers a tool and its executor, replacing any dynamic tool of the same name.
+///
+/// # Errors
+///
+/// Returns [`RuntimeError::RegistryError`] if the tool name is invalid or the
+/// registry lock is poisoned.
+pub fn register_tool(tool: Tool, executor: SyncToolExecutor) -> RuntimeResult<()> {
+    let tool_id = ToolId::new(tool.name.as_ref())
+        .map_err(|e| RuntimeError::registry_error(format!("Invalid tool name: {e}")))?;
+
+    ToolRegistry::register_dynamic_tool(tool)?;
+    ToolRegistry::register_sync_executor(tool_id, executor);
+    bump_generation();
+    Ok(())
+}
+
+/// Removes a tool registered with [`register_tool`].
+///
+/// Returns whether a tool was removed.
+///
+/// # Errors
+///
+/// Returns [`RuntimeError::RegistryError`] if the registry lock is poisoned.
+pub fn unregister_tool(tool_id: &ToolId) -> RuntimeResult<bool> {
+    let removed = ToolRegistry::unregister_dynamic_tool(tool_id)?.is_some();
+    if removed {
+        ToolRegistry::remove_executors(tool_id);
+        bump_generation();
+    }
+    Ok(removed)
+}
+
+/// Adds a hook that [`reload`] runs to register tools.
+pub fn on_reload(hook: fn()) {
+    if let Ok(mut hooks) = RELOAD_HOOKS.write() {
+        if !hooks.contains(&hook) {
+            hooks.push(hook);
+        }
+    }
+}
+
+/// Drops all dynamic tools and re-runs the executor initializers and reload hooks.
+///
+/// Returns the number of tools registered afterwards.
+///
+/// # Errors
+///
+/// Returns [`RuntimeError::RegistryError`] if the registry lock is poisoned.
+pub fn reload() -> RuntimeResult<usize> {
+    for tool in ToolRegistry::list_dynamic_tools() {
+        if let Ok(tool_id) = ToolId::new(tool.name.as_ref()) {
+            ToolRegistry::remove_executors(&tool_id);
+        }
+    }
+    ToolRegistry::clear_dynamic_tools()?;
+
+    crate::initialize_executors();
+
+    // Copy the hooks so that a hook may call `on_reload` itself
+    let hooks = RELOAD_HOOKS
+        .read()
+        .map_err(|_| RuntimeError::registry_error("Failed to read reload hooks"))?
+        .clone();
+    for hook in hooks {
+        hook();
+    }
+
+    bump_generation();
+    Ok(ToolRegistry::list_all().len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icarus_core::LegacyToolResult;
+    use serial_test::serial;
+    use std::sync::Arc;
+
+    fn tool(name: &str) -> Tool {
+        Tool::new(
+            name.to_string(),
+            "Development tool",
+            Arc::new(serde_json::Map::new()),
+        )
+    }
+
+    fn register_reloaded() {
+        register_tool(tool("dev_reloaded"), |_| {
+            Ok(LegacyToolResult::success("reloaded"))
+        })
+        .unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_register_and_unregister_change_fingerprint() {
+        let before = ToolRegistry::fingerprint();
+        let generation_before = generation();
+
+        register_tool(tool("dev_greet"), |_| Ok(LegacyToolResult::success("hi"))).unwrap();
+        let id = ToolId::new("dev_greet").unwrap();
+        assert!(ToolRegistry::has_executor(&id));
+        assert_ne!(ToolRegistry::fingerprint(), before);
+        assert!(generation() > generation_before);
+
+        assert!(unregister_tool(&id).unwrap());
+        assert!(!ToolRegistry::has_executor(&id));
+        assert_eq!(ToolRegistry::fingerprint(), before);
+        assert!(!unregister_tool(&id).unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn test_reload_runs_hooks() {
+        register_tool(tool("dev_stale"), |_| Ok(LegacyToolResult::success("old"))).unwrap();
+        on_reload(register_reloaded);
+
+        reload().unwrap();
+
+        assert!(!ToolRegistry::has_tool(&ToolId::new("dev_stale").unwrap()));
+        let reloaded = ToolId::new("dev_reloaded").unwrap();
+        assert!(ToolRegistry::has_tool(&reloaded));
+        assert!(ToolRegistry::execute_tool_sync(&reloaded, "{}").is_some());
+
+        ToolRegistry::clear_dynamic_tools().unwrap();
+    }
+}
@@ -52,6 +52,13 @@ pub enum RuntimeError {
         message: String,
     },
 
+    /// A tool state instance is held by a pending async call
+    #[error("State '{type_name}' is in use by a pending call")]
+    StateBusy {
+        /// Name of the state type
+        type_name: String,
+    },
+
     /// Core library error
     #[error("Core error: {source}")]
     CoreError {
@@ -113,6 +120,14 @@ impl RuntimeError {
         }
     }
 
+    /// Creates a new state busy error.
+    #[inline]
+    pub fn state_busy(type_name: impl Into<String>) -> Self {
+        Self::StateBusy {
+            type_name: type_name.into(),
+        }
+    }
+
     /// Creates a new async error (only available with async feature).
     #[cfg(feature = "async")]
     pub fn async_error(message: impl Into<String>) -> Self {
@@ -131,6 +146,7 @@ impl RuntimeError {
             Self::InvalidArguments { tool_id, .. } => Some(tool_id),
             Self::JsonError { tool_id, .. } => Some(tool_id),
             Self::RegistryError { .. } => None,
+            Self::StateBusy { .. } => None,
             Self::CoreError { .. } => None,
             #[cfg(feature = "async")]
             Self::AsyncError { .. } => None,
@@ -146,6 +162,7 @@ impl RuntimeError {
             Self::InvalidArguments { .. } => "Invalid arguments provided to tool".into(),
             Self::JsonError { .. } => "Failed to parse tool arguments".into(),
             Self::RegistryError { .. } => "Internal registry error".into(),
+            Self::StateBusy { .. } => "The server is busy, try again".into(),
             Self::CoreError { .. } => "Internal system error".into(),
             #[cfg(feature = "async")]
             Self::AsyncError { .. } => "Async operation failed".into(),
@@ -162,6 +179,7 @@ impl RuntimeError {
             Self::InvalidArguments { .. } => ErrorSeverity::Warning,
             Self::JsonError { .. } => ErrorSeverity::Warning,
             Self::RegistryError { .. } => ErrorSeverity::Critical,
+            Self::StateBusy { .. } => ErrorSeverity::Warning,
             Self::CoreError { .. } => ErrorSeverity::Error,
             #[cfg(feature = "async")]
             Self::AsyncError { .. } => ErrorSeverity::Error,
@@ -3,12 +3,13 @@
 use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
 use crate::registry::{find_tool, ToolRegistry};
 use crate::{RuntimeError, RuntimeResult};
-use icarus_core::{LegacyToolCall as ToolCall, LegacyToolResult as ToolResult};
+use icarus_core::{LegacyToolCall as ToolCall, LegacyToolResult as ToolResult, ToolId};
 
 /// Type alias for async tool execution future.
 #[cfg(feature = "async")]
@@ -21,6 +22,23 @@ type ThreadSafeCache = Arc<RwLock<HashMap<String, CachedResult>>>;
 /// Type alias for thread-safe metrics storage.
 type ThreadSafeMetrics = Arc<RwLock<ExecutionMetrics>>;
 
+/// Timeout of executors created with [`ToolExecutor::new`], in milliseconds.
+static DEFAULT_TIMEOUT_MS: AtomicU64 = AtomicU64::new(30_000);
+
+/// Sets the timeout of executors created afterwards with [`ToolExecutor::new`].
+///
+/// Tools declared with `#[tool(timeout_ms = ..)]` keep their own timeout.
+#[allow(clippy::cast_possible_truncation)]
+pub fn set_default_timeout(timeout: Duration) {
+    DEFAULT_TIMEOUT_MS.store(timeout.as_millis() as u64, Ordering::Relaxed);
+}
+
+/// Returns the timeout of executors created with [`ToolExecutor::new`].
+#[must_use]
+pub fn default_timeout() -> Duration {
+    Duration::from_millis(DEFAULT_TIMEOUT_MS.load(Ordering::Relaxed))
+}
+
 /// Trait for executing tools with type-erased arguments and results.
 ///
 /// This trait provides a common interface for tool execution that can be
@@ -107,6 +125,13 @@ pub trait ToolExecutorTrait: Send + Sync {
 /// - Metrics updates are frequent but brief (minimal contention)
 /// - Consider thread-local executors for zero-contention scenarios
 ///
+/// # Timeouts
+///
+/// Each call is bounded by the tool's `#[tool(timeout_ms = ..)]` override or,
+/// without one, the executor's timeout. A call that runs over is cancelled by
+/// dropping its future and returns [`ToolResult::Timeout`] with the time spent,
+/// so the caller gets a structured result instead of waiting on a hung tool.
+///
 /// # Cache Management
 ///
 /// The executor provides LRU (Least Recently Used) cache eviction when the cache
@@ -169,14 +194,14 @@ impl ToolExecutor {
     /// Creates a new tool executor with default settings.
     ///
     /// Default configuration:
-    /// - Timeout: 30 seconds
+    /// - Timeout: [`default_timeout`] (30 seconds unless changed)
     /// - Caching: Disabled
     /// - Metrics: Enabled
     /// - Max cache size: 1000 entries
     #[must_use]
     pub fn new() -> Self {
         Self {
-            timeout: Duration::from_secs(30),
+            timeout: default_timeout(),
             enable_cache: false,
             cache: Arc::new(RwLock::new(HashMap::new())),
             metrics: Arc::new(RwLock::new(ExecutionMetrics::new())),
@@ -344,6 +369,15 @@ impl ToolExecutor {
         // Execute the tool with timeout
         let result = self.execute_with_timeout(tool_call.clone()).await?;
 
+        // Timed-out calls are not cached but still count towards timing
+        if result.is_timeout() {
+            let mut metrics = self.metrics.write().expect("Metrics lock poisoned");
+            metrics.update_timing(start_time.elapsed());
+            metrics.timeouts += 1;
+            metrics.failed_calls += 1;
+            return Ok(result);
+        }
+
         // Cache the result if caching is enabled (write lock with LRU eviction)
         if self.enable_cache {
             let cache_key = self.generate_cache_key(&tool_call);
@@ -363,19 +397,9 @@ impl ToolExecutor {
         }
 
         // Update metrics (write lock)
-        let execution_time = start_time.elapsed();
         {
             let mut metrics = self.metrics.write().expect("Metrics lock poisoned");
-            metrics.update_timing(execution_time);
-
-            if execution_time > self.timeout {
-                metrics.timeouts += 1;
-                return Err(RuntimeError::execution_failed(
-                    tool_call.name.as_str(),
-                    format!("Execution timeout after {}ms", execution_time.as_millis()),
-                ));
-            }
-
+            metrics.update_timing(start_time.elapsed());
             metrics.successful_calls += 1;
         }
 
@@ -470,12 +494,12 @@ impl ToolExecutor {
             let mut metrics = self.metrics.write().expect("Metrics lock poisoned");
             metrics.update_timing(execution_time);
 
-            if execution_time > self.timeout {
+            // Sync tools cannot be cancelled, so the timeout is applied afterwards
+            let timeout = self.timeout_for(&tool_call.name);
+            if execution_time > timeout {
                 metrics.timeouts += 1;
-                return Err(RuntimeError::execution_failed(
-                    tool_call.name.as_str(),
-                    format!("Execution timeout after {}ms", execution_time.as_millis()),
-                ));
+                metrics.failed_calls += 1;
+                return Ok(ToolResult::timeout(timeout, execution_time));
             }
 
             metrics.successful_calls += 1;
@@ -484,28 +508,113 @@ impl ToolExecutor {
         Ok(result)
     }
 
+    /// Executes tool calls concurrently, running at most `max_parallelism` at once.
+    ///
+    /// Each call is spawned onto the Tokio runtime as soon as a slot frees up and
+    /// goes through [`execute`](Self::execute), so caching, timeouts and the
+    /// executor's metrics apply as usual. Results are returned in the order of
+    /// `tool_calls`, together with metrics aggregated over just this batch. A
+    /// `max_parallelism` of 0 is treated as 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a Tokio runtime. A panicking tool does not
+    /// panic the caller; its slot holds [`RuntimeError::ExecutionFailed`].
+    #[cfg(feature = "async")]
+    pub async fn execute_concurrent(
+        &self,
+        tool_calls: Vec<ToolCall<'_>>,
+        max_parallelism: usize,
+    ) -> ConcurrentExecution {
+        let max_parallelism = max_parallelism.max(1);
+        let names: Vec<ToolId> = tool_calls.iter().map(|call| call.name.clone()).collect();
+        let mut completed: Vec<Option<(Duration, RuntimeResult<ToolResult<'static>>)>> =
+            names.iter().map(|_| None).collect();
+
+        let mut queued = tool_calls.into_iter().map(ToolCall::into_owned).enumerate();
+        let mut tasks = tokio::task::JoinSet::new();
+
+        loop {
+            while tasks.len() < max_parallelism {
+                let Some((index, tool_call)) = queued.next() else {
+                    break;
+                };
+                let mut executor = self.share();
+                tasks.spawn(async move {
+                    let started_at = Instant::now();
+                    let result = executor.execute(tool_call).await;
+                    (index, started_at.elapsed(), result)
+                });
+            }
+
+            match tasks.join_next().await {
+                Some(Ok((index, elapsed, result))) => completed[index] = Some((elapsed, result)),
+                // A panicked call leaves its slot empty and is reported below
+                Some(Err(_)) => {}
+                None => break,
+            }
+        }
+
+        let mut metrics = ExecutionMetrics::new();
+        let results = completed
+            .into_iter()
+            .zip(&names)
+            .map(|(outcome, name)| {
+                let Some((elapsed, result)) = outcome else {
+                    metrics.record(Duration::ZERO, false);
+                    return Err(RuntimeError::execution_failed(
+                        name.as_str(),
+                        "Tool task panicked",
+                    ));
+                };
+
+                let success = matches!(&result, Ok(r) if !r.is_failure());
+                metrics.record(elapsed, success);
+                if matches!(&result, Ok(r) if r.is_timeout()) {
+                    metrics.timeouts += 1;
+                }
+                result
+            })
+            .collect();
+
+        ConcurrentExecution { results, metrics }
+    }
+
+    /// Returns an executor sharing this one's cache and metrics.
+    #[cfg(feature = "async")]
+    fn share(&self) -> Self {
+        Self {
+            timeout: self.timeout,
+            enable_cache: self.enable_cache,
+            cache: Arc::clone(&self.cache),
+            metrics: Arc::clone(&self.metrics),
+            max_cache_size: self.max_cache_size,
+        }
+    }
+
     /// Executes a tool with timeout protection (async version).
+    ///
+    /// The tool's future is dropped when the timeout elapses, cancelling it at
+    /// its next `.await`.
     #[cfg(feature = "async")]
     async fn execute_with_timeout(
         &self,
         tool_call: ToolCall<'_>,
     ) -> RuntimeResult<ToolResult<'static>> {
-        use tokio::time::timeout;
-
-        let execution_future = self.execute_tool_impl(tool_call.clone());
+        let timeout = self.timeout_for(&tool_call.name);
+        let started_at = Instant::now();
 
-        match timeout(self.timeout, execution_future).await {
+        match tokio::time::timeout(timeout, self.execute_tool_impl(tool_call)).await {
             Ok(result) => result,
-            Err(_) => Err(RuntimeError::execution_failed(
-                tool_call.name.as_str(),
-                format!(
-                    "Tool execution timed out after {}ms",
-                    self.timeout.as_millis()
-                ),
-            )),
+            Err(_) => Ok(ToolResult::timeout(timeout, started_at.elapsed())),
         }
     }
 
+    /// Returns the timeout for a tool: its registered override or the executor's.
+    fn timeout_for(&self, tool_id: &ToolId) -> Duration {
+        ToolRegistry::tool_timeout(tool_id).unwrap_or(self.timeout)
+    }
+
     /// Internal tool implementation execution (async version).
     #[cfg(feature = "async")]
     async fn execute_tool_impl(
@@ -530,11 +639,16 @@ impl ToolExecutor {
             })?;
         }
 
-        // 3. Try to execute the tool using registered executor
+        // 3. Try to execute the tool using registered executor; sync tools
+        // run in place, as they do in a canister
         if let Some(result) =
             ToolRegistry::execute_tool_async(&tool_call.name, tool_call.arguments.as_ref()).await
         {
             result
+        } else if let Some(result) =
+            ToolRegistry::execute_tool_sync(&tool_call.name, tool_call.arguments.as_ref())
+        {
+            result
         } else {
             // Fallback for tools without registered executors
             Ok(ToolResult::success(format!(
@@ -630,6 +744,8 @@ impl ToolExecutor {
     }
 
     /// Returns the configured timeout duration.
+    ///
+    /// Tools with a `#[tool(timeout_ms = ..)]` override use that instead.
     #[must_use]
     pub fn timeout(&self) -> Duration {
         self.timeout
@@ -648,6 +764,16 @@ impl Default for ToolExecutor {
     }
 }
 
+/// Outcome of [`ToolExecutor::execute_concurrent`].
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub struct ConcurrentExecution {
+    /// One result per tool call, in the order the calls were given.
+    pub results: Vec<RuntimeResult<ToolResult<'static>>>,
+    /// Metrics aggregated over the calls of this batch.
+    pub metrics: ExecutionMetrics,
+}
+
 /// Cached tool execution result.
 #[derive(Debug, Clone)]
 struct CachedResult {
@@ -721,6 +847,17 @@ impl ExecutionMetrics {
         }
     }
 
+    /// Records a completed call that did not go through the cache.
+    pub(crate) fn record(&mut self, duration: Duration, success: bool) {
+        self.total_calls += 1;
+        if success {
+            self.successful_calls += 1;
+        } else {
+            self.failed_calls += 1;
+        }
+        self.update_timing(duration);
+    }
+
     fn update_timing(&mut self, duration: Duration) {
         let ms = duration.as_secs_f64() * 1000.0;
 
@@ -802,9 +939,10 @@ pub fn execute_tool(tool_call: ToolCall) -> RuntimeResult<ToolResult> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use icarus_core::ToolId;
+    use serial_test::serial;
 
     #[test]
+    #[serial]
     fn test_executor_creation() {
         let executor = ToolExecutor::new();
         assert_eq!(executor.timeout, Duration::from_secs(30));
@@ -842,6 +980,111 @@ mod tests {
         assert_eq!(key1, key2);
     }
 
+    #[test]
+    #[serial]
+    fn test_default_timeout_is_configurable() {
+        set_default_timeout(Duration::from_secs(5));
+        assert_eq!(ToolExecutor::new().timeout(), Duration::from_secs(5));
+
+        set_default_timeout(Duration::from_secs(30));
+        assert_eq!(default_timeout(), Duration::from_secs(30));
+    }
+
+    #[cfg(feature = "async")]
+    fn slow_tool(_args: &str) -> AsyncExecutionFuture<'static> {
+        Box::pin(async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(ToolResult::success("done"))
+        })
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_tool_timeout_override_cancels_call() {
+        let tool_id = ToolId::new("executor_slow_tool").expect("Valid tool ID for test");
+        ToolRegistry::register_dynamic_tool(icarus_core::Tool::new(
+            "executor_slow_tool".to_string(),
+            "Sleeps",
+            Arc::new(serde_json::Map::new()),
+        ))
+        .unwrap();
+        ToolRegistry::register_async_executor(tool_id.clone(), slow_tool).unwrap();
+        ToolRegistry::set_tool_timeout(tool_id.clone(), Duration::from_millis(20)).unwrap();
+
+        let mut executor = ToolExecutor::with_timeout(Duration::from_secs(30));
+        let result = executor.execute(ToolCall::new(tool_id)).await.unwrap();
+
+        assert!(matches!(
+            result,
+            ToolResult::Timeout { timeout_ms: 20, elapsed_ms } if elapsed_ms >= 20
+        ));
+        let metrics = executor.metrics();
+        assert_eq!(metrics.timeouts, 1);
+        assert_eq!(metrics.failed_calls, 1);
+        assert!(metrics.max_execution_time_ms >= 20.0);
+    }
+
+    #[cfg(feature = "async")]
+    fn quick_tool(args: &str) -> AsyncExecutionFuture<'static> {
+        let delay_ms = serde_json::from_str::<serde_json::Value>(args)
+            .ok()
+            .and_then(|args| args["delay_ms"].as_u64())
+            .unwrap_or_default();
+        Box::pin(async move {
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+            Ok(ToolResult::success(format!("slept {delay_ms}")))
+        })
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_execute_concurrent_preserves_order() {
+        let tool_id = ToolId::new("executor_quick_tool").expect("Valid tool ID for test");
+        ToolRegistry::register_dynamic_tool(icarus_core::Tool::new(
+            "executor_quick_tool".to_string(),
+            "Sleeps briefly",
+            Arc::new(serde_json::Map::new()),
+        ))
+        .unwrap();
+        ToolRegistry::register_async_executor(tool_id.clone(), quick_tool).unwrap();
+
+        // Later calls finish first
+        let mut calls: Vec<ToolCall> = [40, 20, 0, 10]
+            .iter()
+            .map(|delay_ms| {
+                ToolCall::new(tool_id.clone())
+                    .with_arguments(format!(r#"{{"delay_ms": {delay_ms}}}"#))
+            })
+            .collect();
+        calls.push(ToolCall::new(ToolId::new("executor_missing_tool").unwrap()));
+
+        let executor = ToolExecutor::new();
+        let batch = executor.execute_concurrent(calls, 2).await;
+
+        let outputs: Vec<String> = batch.results[..4]
+            .iter()
+            .map(|result| {
+                result
+                    .as_ref()
+                    .unwrap()
+                    .clone()
+                    .into_success()
+                    .unwrap()
+                    .into_owned()
+            })
+            .collect();
+        assert_eq!(outputs, ["slept 40", "slept 20", "slept 0", "slept 10"]);
+        assert!(matches!(
+            batch.results[4],
+            Err(RuntimeError::ToolNotFound { .. })
+        ));
+
+        assert_eq!(batch.metrics.total_calls, 5);
+        assert_eq!(batch.metrics.successful_calls, 4);
+        assert_eq!(batch.metrics.failed_calls, 1);
+        assert_eq!(executor.metrics().successful_calls, 4);
+    }
+
     #[test]
     fn test_cached_result_expiry() {
         let result = ToolResult::success("test");
@@ -11,6 +11,10 @@
 //! - **Tool Registry**: Automatic tool discovery using `linkme` distributed slices
 //! - **Execution Engine**: Type-safe tool execution with comprehensive error handling
 //! - **Async Support**: Optional async execution for I/O-bound tools (feature `async`)
+//! - **Native Serving**: An MCP stdio server for the registered tools without a
+//!   canister (feature `native`)
+//! - **Component Export**: The registered tools as a WASM component for non-IC
+//!   hosts such as wasmtime (feature `component`)
 //! - **Performance**: Zero-allocation registry access with <10ms execution times
 //! - **Memory Safety**: RAII resource management with proper cleanup
 //!
@@ -62,14 +66,29 @@
 #![warn(clippy::pedantic)]
 #![deny(unsafe_code)]
 
+#[cfg(feature = "component")]
+pub mod component;
+#[cfg(feature = "dev")]
+pub mod dev;
 mod error;
 mod executor;
+#[cfg(feature = "native")]
+pub mod native;
+pub mod prometheus;
 mod registry;
+pub mod state;
+pub mod workflow;
 
 pub use error::{ErrorSeverity, RuntimeError, RuntimeResult};
-pub use executor::{execute_tool, ExecutionMetrics, ToolExecutor, ToolExecutorTrait};
+pub use executor::{
+    default_timeout, execute_tool, set_default_timeout, ExecutionMetrics, ToolExecutor,
+    ToolExecutorTrait,
+};
 pub use registry::{find_tool, list_tools, RegistryStats, SyncToolExecutor, ToolRegistry};
+pub use workflow::{ErrorPolicy, StepCondition, Workflow, WorkflowBuilder, WorkflowStep};
 
+#[cfg(feature = "async")]
+pub use executor::ConcurrentExecution;
 #[cfg(feature = "async")]
 pub use registry::AsyncToolExecutor;
 
@@ -77,6 +96,18 @@ pub use registry::AsyncToolExecutor;
 pub use icarus_core::{IcarusError, Tool, ToolId};
 pub use icarus_core::{LegacyToolCall as ToolCall, LegacyToolResult as ToolResult};
 
+/// Fails the build of a WASM component when the `component` feature, which
+/// provides the real macro, is off.
+#[cfg(not(feature = "component"))]
+#[macro_export]
+macro_rules! export_component {
+    () => {
+        compile_error!(
+            "building a WASM component needs the `component` feature of `icarus` or `icarus-runtime`"
+        );
+    };
+}
+
 /// Runtime version information
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -106,11 +137,47 @@ pub static TOOL_REGISTRY: [fn() -> Tool] = [..];
 #[linkme::distributed_slice]
 pub static EXECUTOR_INIT: [fn()] = [..];
 
+/// Checks the `#[tool]` definitions linked into this binary.
+///
+/// Call this from `#[init]` and `#[post_upgrade]` and trap on the error, so
+/// a build with two tools of the same name fails to deploy instead of
+/// silently serving only one of them. `mcp!` does so in the `init` and
+/// `post_upgrade` it generates with `auth = true`, and `call_tool` answers
+/// with this error rather than running any tool. The result is computed
+/// once per instance.
+///
+/// # Errors
+///
+/// Returns [`RuntimeError::RegistryError`] listing every
+/// [`ToolRegistry::definition_problems`] entry.
+///
+/// # Examples
+///
+/// ```rust
+/// use icarus_runtime::validate_tool_definitions;
+///
+/// // In #[init] and #[post_upgrade]
+/// validate_tool_definitions().expect("invalid tools");
+/// ```
+pub fn validate_tool_definitions() -> RuntimeResult<()> {
+    static PROBLEMS: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
+    let problems = PROBLEMS.get_or_init(ToolRegistry::definition_problems);
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(RuntimeError::registry_error(format!(
+            "invalid #[tool] definitions:\n  - {}",
+            problems.join("\n  - ")
+        )))
+    }
+}
+
 /// Initializes all tool executors by calling their registration functions.
 ///
 /// This function should be called once during canister initialization or before
 /// the first tool execution. It iterates through all registered executor initialization
 /// functions and calls them to register executors with the `ToolRegistry`.
+/// It does not check the definitions; see [`validate_tool_definitions`].
 ///
 /// # Examples
 ///
@@ -130,6 +197,64 @@ pub fn initialize_executors() {
     }
 }
 
+/// Embeds a translation bundle and registers it for a locale.
+///
+/// The file, JSON or Fluent, is read at compile time relative to the
+/// invoking file and registered with `icarus_core::i18n::register_bundle`
+/// when the executors are initialized. Tools opt in with
+/// `#[tool(i18n = "...")]`.
+///
+/// # Panics
+///
+/// [`initialize_executors`] panics if the bundle cannot be parsed, since the
+/// file is part of the build.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// icarus_runtime::i18n_bundle!("es", "../i18n/es.ftl");
+/// icarus_runtime::i18n_bundle!("fr", "../i18n/fr.json");
+/// ```
+#[macro_export]
+macro_rules! i18n_bundle {
+    ($locale:literal, $path:literal) => {
+        const _: () = {
+            #[::linkme::distributed_slice($crate::EXECUTOR_INIT)]
+            static I18N_BUNDLE: fn() = || {
+                if let Err(e) = ::icarus_core::i18n::register_bundle($locale, include_str!($path)) {
+                    panic!("i18n bundle {} for '{}': {}", $path, $locale, e);
+                }
+            };
+        };
+    };
+}
+
+/// Support code for the `#[tool]` macro, not a public API.
+#[doc(hidden)]
+pub mod __private {
+    use std::borrow::Cow;
+
+    use serde::{Deserialize, Deserializer};
+
+    /// Deserializes an `Option<Cow<str>>` parameter, borrowing the string
+    /// unless it contains escapes; serde's own impl always copies it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the deserializer's error for anything but a string or null.
+    pub fn borrow_option_cow_str<'de: 'a, 'a, D>(
+        deserializer: D,
+    ) -> Result<Option<Cow<'a, str>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Borrowed<'a>(#[serde(borrow)] Cow<'a, str>);
+
+        Ok(Option::<Borrowed<'a>>::deserialize(deserializer)?.map(|Borrowed(text)| text))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -140,6 +265,24 @@ mod tests {
         assert!(VERSION.contains('.'));
     }
 
+    #[test]
+    fn test_option_cow_str_borrows() {
+        use std::borrow::Cow;
+
+        #[derive(serde::Deserialize)]
+        struct Params<'a> {
+            #[serde(borrow, default, deserialize_with = "__private::borrow_option_cow_str")]
+            note: Option<Cow<'a, str>>,
+        }
+
+        let plain: Params = serde_json::from_str(r#"{"note": "hi"}"#).unwrap();
+        assert!(matches!(plain.note, Some(Cow::Borrowed("hi"))));
+        let escaped: Params = serde_json::from_str(r#"{"note": "a\nb"}"#).unwrap();
+        assert!(matches!(escaped.note, Some(Cow::Owned(ref text)) if text == "a\nb"));
+        let missing: Params = serde_json::from_str("{}").unwrap();
+        assert!(missing.note.is_none());
+    }
+
     #[test]
     fn test_registry_exists() {
         // The registry should exist even if empty
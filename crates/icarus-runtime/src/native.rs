@@ -0,0 +1,340 @@
+//! Serving the registered tools from a native process.
+//!
+//! The same `#[tool]` functions that make up a canister can run natively, in
+//! unit tests or as a locally hosted MCP server with no canister at all.
+//! [`NativeServer`] answers newline-delimited JSON-RPC on stdin and stdout,
+//! the MCP stdio transport, with the tools in the registry:
+//!
+//! - `initialize`, `ping`, `tools/list` and `tools/call` are answered
+//! - notifications are accepted and ignored
+//! - anything else is a `-32601` error
+//!
+//! Tools see the environment through [`icarus_core::host`]: the system clock,
+//! unlimited cycles, log lines on stderr, and calls from the server's owner,
+//! who is also an admin, or the anonymous principal. Canister state lives in
+//! thread-locals, so every call runs on the thread that called
+//! [`NativeServer::serve_stdio`], one at a time. `icarus serve --native`
+//! builds a project's tools into such a server.
+//!
+//! This module requires the `native` feature.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use icarus_runtime::native::NativeServer;
+//!
+//! fn main() -> std::io::Result<()> {
+//!     NativeServer::new("notes", "0.1.0").serve_stdio()
+//! }
+//! ```
+
+use candid::Principal;
+use icarus_core::{context, host, CallToolResult, Content, ToolId};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::{RuntimeError, ToolCall, ToolExecutor, ToolResult};
+
+/// MCP protocol version answered when the client does not ask for one.
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// An MCP server for the registered tools, without a canister.
+pub struct NativeServer {
+    name: String,
+    version: String,
+    owner: Option<Principal>,
+    executor: ToolExecutor,
+}
+
+impl NativeServer {
+    /// Creates a server reporting `name` and `version` to clients.
+    #[must_use]
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            owner: None,
+            executor: ToolExecutor::new(),
+        }
+    }
+
+    /// Makes every call come from `owner`, an admin, as the canister's
+    /// controller would be.
+    #[must_use]
+    pub fn with_owner(mut self, owner: Principal) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    /// Serves stdin and stdout until stdin closes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime cannot start, stdio fails, or another
+    /// host was already installed.
+    pub fn serve_stdio(mut self) -> std::io::Result<()> {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(async {
+                let stdin = BufReader::new(tokio::io::stdin());
+                self.serve(stdin, tokio::io::stdout()).await
+            })
+    }
+
+    /// Serves messages from `reader`, one per line, writing the responses
+    /// to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading or writing fails, or another host was
+    /// already installed.
+    pub async fn serve<R, W>(&mut self, reader: R, mut writer: W) -> std::io::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        self.prepare()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+
+        let mut lines = reader.lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle_message(&line).await {
+                writer.write_all(response.as_bytes()).await?;
+                writer.write_all(b"\n").await?;
+                writer.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Installs the owner as the caller and registers the tool executors.
+    fn prepare(&self) -> icarus_core::Result<()> {
+        if let Some(owner) = self.owner {
+            let owner_host = host::NativeHost::new().with_caller(owner);
+            host::set_host(Box::leak(Box::new(owner_host)))?;
+            icarus_core::auth::add_admin(owner);
+        }
+        crate::validate_tool_definitions()
+            .map_err(|e| icarus_core::IcarusError::ConfigurationError(e.to_string()))?;
+        crate::initialize_executors();
+        Ok(())
+    }
+
+    /// Returns the response to one JSON-RPC message, or `None` for a
+    /// notification.
+    pub async fn handle_message(&mut self, message: &str) -> Option<String> {
+        let request: Value = match serde_json::from_str(message) {
+            Ok(request) => request,
+            Err(e) => {
+                return Some(error_response(
+                    &Value::Null,
+                    -32700,
+                    &format!("Parse error: {e}"),
+                ))
+            }
+        };
+        // Notifications carry no id and get no response
+        let id = request.get("id")?.clone();
+        let params = request.get("params").cloned().unwrap_or_else(|| json!({}));
+
+        let outcome = match request["method"].as_str().unwrap_or_default() {
+            "initialize" => Ok(self.initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(list_tools()),
+            "tools/call" => self.call_tool(&id, &params).await,
+            method => Err((-32601, format!("Method not found: {method}"))),
+        };
+        Some(match outcome {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string(),
+            Err((code, message)) => error_response(&id, code, &message),
+        })
+    }
+
+    fn initialize(&self, params: &Value) -> Value {
+        let protocol_version = params["protocolVersion"]
+            .as_str()
+            .unwrap_or(PROTOCOL_VERSION);
+        json!({
+            "protocolVersion": protocol_version,
+            "capabilities": { "tools": { "listChanged": false } },
+            "serverInfo": { "name": self.name, "version": self.version },
+        })
+    }
+
+    async fn call_tool(&mut self, id: &Value, params: &Value) -> Result<Value, (i32, String)> {
+        let name = params["name"]
+            .as_str()
+            .ok_or((-32602, "Missing tool name in params".to_string()))?;
+        let tool_id = ToolId::new(name).map_err(|e| (-32602, format!("Invalid tool name: {e}")))?;
+        if !icarus_core::tool_switches::is_enabled(name) {
+            return Err((-32601, format!("Tool disabled: {name}")));
+        }
+        let arguments = params
+            .get("arguments")
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+
+        let request_id = match id {
+            Value::String(id) => id.clone(),
+            id => id.to_string(),
+        };
+        context::set_current(Some(
+            context::ToolContext::new(context::caller(), name).with_request_id(request_id),
+        ));
+        let call = ToolCall::new(tool_id).with_arguments(arguments.to_string());
+        let outcome = self.executor.execute(call).await;
+        context::set_current(None);
+
+        match outcome {
+            Ok(result) => serde_json::to_value(call_tool_result(&result))
+                .map_err(|e| (-32603, format!("Failed to serialize result: {e}"))),
+            Err(RuntimeError::ToolNotFound { .. }) => {
+                Err((-32601, format!("Tool not found: {name}")))
+            }
+            Err(e) => Err((-32603, format!("Tool execution error: {e}"))),
+        }
+    }
+}
+
+/// Returns the enabled tools as a `tools/list` result.
+fn list_tools() -> Value {
+    let tools: Vec<_> = crate::list_tools()
+        .into_iter()
+        .filter(|tool| icarus_core::tool_switches::is_enabled(&tool.name))
+        .collect();
+    json!({ "tools": tools })
+}
+
+/// Converts a tool's result into the `tools/call` result sent to clients.
+fn call_tool_result(result: &ToolResult<'_>) -> CallToolResult {
+    let (content, is_error) = match result {
+        ToolResult::Success { .. } => (icarus_core::content::from_tool_result(result), false),
+        ToolResult::Error { message, .. } => (vec![Content::text(message.as_ref())], true),
+        ToolResult::Pending { status, .. } => (
+            vec![Content::text(
+                status.as_deref().unwrap_or("Tool execution pending"),
+            )],
+            false,
+        ),
+        ToolResult::Timeout { timeout_ms, .. } => (
+            vec![Content::text(format!(
+                "Tool timed out after {timeout_ms} ms"
+            ))],
+            true,
+        ),
+    };
+    CallToolResult {
+        content,
+        structured_content: None,
+        is_error: Some(is_error),
+        meta: None,
+    }
+}
+
+fn error_response(id: &Value, code: i32, message: &str) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::ToolRegistry;
+
+    fn echo(args: &str) -> crate::RuntimeResult<ToolResult<'static>> {
+        let args: Value = serde_json::from_str(args).unwrap_or_default();
+        Ok(ToolResult::success(format!("echo {}", args["text"])))
+    }
+
+    fn register_echo() {
+        let tool_id = ToolId::new("native_echo").unwrap();
+        let _ = ToolRegistry::register_dynamic_tool(icarus_core::Tool::new(
+            "native_echo".to_string(),
+            "Echoes its text",
+            Arc::new(serde_json::Map::new()),
+        ));
+        ToolRegistry::register_sync_executor(tool_id, echo).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_serves_lines_until_eof() {
+        register_echo();
+        let input = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-03-26"}}"#,
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"name":"native_echo","arguments":{"text":"hi"}}}"#,
+        ]
+        .join("\n");
+        let mut output = Vec::new();
+
+        NativeServer::new("notes", "0.1.0")
+            .serve(input.as_bytes(), &mut output)
+            .await
+            .unwrap();
+
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["result"]["protocolVersion"], "2025-03-26");
+        assert_eq!(responses[0]["result"]["serverInfo"]["name"], "notes");
+        assert!(responses[1]["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|tool| tool["name"] == "native_echo"));
+        assert_eq!(responses[2]["id"], 3);
+        assert_eq!(responses[2]["result"]["isError"], false);
+        assert_eq!(responses[2]["result"]["content"][0]["text"], "echo \"hi\"");
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let mut server = NativeServer::new("notes", "0.1.0");
+
+        let response: Value =
+            serde_json::from_str(&server.handle_message("{not json").await.unwrap()).unwrap();
+        assert_eq!(response["error"]["code"], -32700);
+        assert_eq!(response["id"], Value::Null);
+
+        let unknown = r#"{"jsonrpc":"2.0","id":"a","method":"resources/list"}"#;
+        let response: Value =
+            serde_json::from_str(&server.handle_message(unknown).await.unwrap()).unwrap();
+        assert_eq!(response["error"]["code"], -32601);
+        assert_eq!(response["id"], "a");
+
+        let missing =
+            r#"{"jsonrpc":"2.0","id":4,"method":"tools/call","params":{"name":"native_missing"}}"#;
+        let response: Value =
+            serde_json::from_str(&server.handle_message(missing).await.unwrap()).unwrap();
+        assert_eq!(
+            response["error"]["message"],
+            "Tool not found: native_missing"
+        );
+    }
+
+    #[test]
+    fn test_error_results_are_flagged() {
+        let result = call_tool_result(&ToolResult::error("boom"));
+        assert_eq!(result.is_error, Some(true));
+
+        let timeout = ToolResult::Timeout {
+            timeout_ms: 20,
+            elapsed_ms: 21,
+        };
+        assert_eq!(call_tool_result(&timeout).is_error, Some(true));
+    }
+}
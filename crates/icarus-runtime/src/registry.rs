@@ -5,8 +5,9 @@ use smallvec::SmallVec;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
 
-use crate::{RuntimeError, RuntimeResult, TOOL_REGISTRY};
+use crate::{ExecutionMetrics, RuntimeError, RuntimeResult, TOOL_REGISTRY};
 use icarus_core::{LegacyToolResult as ToolResult, Tool, ToolId};
 
 /// Type alias for async tool execution function.
@@ -25,6 +26,8 @@ struct ExecutorStorage {
     sync_executors: FxHashMap<ToolId, SyncToolExecutor>,
     /// Dynamic tools registered at runtime for hot-reload capability.
     dynamic_tools: FxHashMap<ToolId, Tool>,
+    /// Per-tool overrides of the executor timeout, from `#[tool(timeout_ms = ..)]`.
+    timeouts: FxHashMap<ToolId, Duration>,
 }
 
 /// Global registry for tool execution functions.
@@ -42,6 +45,16 @@ static EXECUTOR_REGISTRY: OnceLock<RwLock<ExecutorStorage>> = OnceLock::new();
 /// both the cached index and dynamic tools.
 static TOOL_INDEX: OnceLock<FxHashMap<ToolId, Tool>> = OnceLock::new();
 
+/// Metrics for calls dispatched through [`ToolRegistry::execute_tool_sync`].
+///
+/// Canisters execute tools through the registry rather than a [`ToolExecutor`](crate::ToolExecutor),
+/// so these are the execution metrics of a deployed server.
+static DISPATCH_METRICS: OnceLock<RwLock<ExecutionMetrics>> = OnceLock::new();
+
+fn dispatch_metrics() -> &'static RwLock<ExecutionMetrics> {
+    DISPATCH_METRICS.get_or_init(|| RwLock::new(ExecutionMetrics::new()))
+}
+
 /// Registry operations for tool discovery and management.
 ///
 /// The registry provides efficient access to tools registered at compile time
@@ -215,7 +228,8 @@ impl ToolRegistry {
     /// Validates registry integrity.
     ///
     /// Checks for common registry issues such as duplicate tool IDs,
-    /// invalid tool definitions, or corrupted registry state.
+    /// invalid tool definitions, or corrupted registry state. Every problem
+    /// found is reported, not just the first.
     ///
     /// # Returns
     ///
@@ -227,7 +241,7 @@ impl ToolRegistry {
     /// Returns [`RuntimeError::RegistryError`] if:
     /// - Tool count exceeds 10,000 (registry too large)
     /// - Any tool has an empty description
-    /// - Duplicate tool IDs are found
+    /// - Any of the [`definition_problems`](Self::definition_problems) are found
     ///
     /// # Examples
     ///
@@ -252,28 +266,46 @@ impl ToolRegistry {
             )));
         }
 
-        // Validate tool definitions using iterator patterns
-        if let Some(invalid_tool) = tools.iter().find(|tool| {
-            tool.description
-                .as_ref()
-                .map_or(true, |d| d.trim().is_empty())
-        }) {
-            return Err(RuntimeError::registry_error(format!(
-                "Tool '{}' has empty description",
-                invalid_tool.name.as_ref()
-            )));
-        }
+        let mut problems: Vec<String> = tools
+            .iter()
+            .filter(|tool| {
+                tool.description
+                    .as_ref()
+                    .map_or(true, |d| d.trim().is_empty())
+            })
+            .map(|tool| format!("Tool '{}' has empty description", tool.name.as_ref()))
+            .collect();
+        problems.extend(definition_problems_in(&tools));
 
-        // Check for duplicate IDs using iterator patterns
-        let mut seen_ids = std::collections::HashSet::with_capacity(tool_count);
-        if let Some(duplicate_tool) = tools.iter().find(|tool| !seen_ids.insert(&tool.name)) {
-            return Err(RuntimeError::registry_error(format!(
-                "Duplicate tool ID found: {}",
-                duplicate_tool.name.as_ref()
-            )));
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(RuntimeError::registry_error(problems.join("; ")))
         }
+    }
 
-        Ok(())
+    /// Returns the problems that make `#[tool]` definitions unusable.
+    ///
+    /// These are names that [`ToolId`] rejects, names registered by more than
+    /// one function, and input schemas that are not JSON Schema objects. The
+    /// `#[tool]` macro rejects invalid names and duplicates within a module at
+    /// compile time; this catches duplicates across modules and crates.
+    /// [`validate_tool_definitions`](crate::validate_tool_definitions)
+    /// reports them as an error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use icarus_runtime::ToolRegistry;
+    ///
+    /// for problem in ToolRegistry::definition_problems() {
+    ///     eprintln!("{problem}");
+    /// }
+    /// ```
+    #[must_use]
+    pub fn definition_problems() -> Vec<String> {
+        let tools: Vec<Tool> = TOOL_REGISTRY.iter().map(|tool_fn| tool_fn()).collect();
+        definition_problems_in(&tools)
     }
 
     /// Returns registry statistics.
@@ -377,6 +409,33 @@ impl ToolRegistry {
         Ok(())
     }
 
+    /// Overrides the execution timeout of a tool.
+    ///
+    /// [`ToolExecutor`](crate::ToolExecutor) uses this instead of its own
+    /// timeout when executing the tool. Registered by `#[tool(timeout_ms = ..)]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RuntimeError` if the registration fails due to lock contention.
+    pub fn set_tool_timeout(tool_id: ToolId, timeout: Duration) -> RuntimeResult<()> {
+        let registry = EXECUTOR_REGISTRY.get_or_init(|| RwLock::new(ExecutorStorage::default()));
+
+        let mut write_guard = registry.write().map_err(|_| {
+            RuntimeError::registry_error("Failed to acquire write lock on executor registry")
+        })?;
+
+        write_guard.timeouts.insert(tool_id, timeout);
+        Ok(())
+    }
+
+    /// Returns the timeout override of a tool, if one is registered.
+    #[must_use]
+    pub fn tool_timeout(tool_id: &ToolId) -> Option<Duration> {
+        let registry = EXECUTOR_REGISTRY.get()?;
+        let read_guard = registry.read().ok()?;
+        read_guard.timeouts.get(tool_id).copied()
+    }
+
     /// Gets an executor for a specific tool and executes it asynchronously.
     ///
     /// This method provides a safe way to execute a tool by looking up its executor
@@ -391,13 +450,18 @@ impl ToolRegistry {
     /// # Returns
     ///
     /// * `Some(Ok(ToolResult))` if the tool exists and execution succeeded
-    /// * `Some(Err(RuntimeError))` if the tool exists but execution failed
+    /// * `Some(Err(RuntimeError))` if the tool exists but execution failed,
+    ///   or the owner disabled it with `icarus_core::tool_switches`
     /// * `None` if no executor is found for this tool
     #[cfg(feature = "async")]
     pub async fn execute_tool_async(
         tool_id: &ToolId,
         arguments: &str,
     ) -> Option<RuntimeResult<ToolResult<'static>>> {
+        if !icarus_core::tool_switches::is_enabled(tool_id.as_str()) {
+            return Some(Err(disabled_error(tool_id)));
+        }
+
         let registry = EXECUTOR_REGISTRY.get()?;
         let executor = {
             let read_guard = registry.read().ok()?;
@@ -421,22 +485,49 @@ impl ToolRegistry {
     /// # Returns
     ///
     /// * `Some(Ok(ToolResult))` if the tool exists and execution succeeded
-    /// * `Some(Err(RuntimeError))` if the tool exists but execution failed
-    /// * `None` if no executor is found for this tool
+    /// * `Some(Err(RuntimeError))` if the tool exists but execution failed,
+    ///   or the owner disabled it with `icarus_core::tool_switches`
+    /// * `None` if no executor or registered workflow is found for this tool
     pub fn execute_tool_sync(
         tool_id: &ToolId,
         arguments: &str,
     ) -> Option<RuntimeResult<ToolResult<'static>>> {
+        if !icarus_core::tool_switches::is_enabled(tool_id.as_str()) {
+            return Some(Err(disabled_error(tool_id)));
+        }
+
         let registry = EXECUTOR_REGISTRY.get()?;
         let read_guard = registry.read().ok()?;
+        let started_at = icarus_core::time::now_nanos();
 
-        if let Some(&executor) = read_guard.sync_executors.get(tool_id) {
+        let outcome = if let Some(&executor) = read_guard.sync_executors.get(tool_id) {
             // Copy the function pointer and drop the guard before calling
             drop(read_guard);
-            Some(executor(arguments))
+            executor(arguments)
         } else {
-            None
+            drop(read_guard);
+            crate::workflow::execute_registered_sync(tool_id, arguments)?
+        };
+
+        let elapsed =
+            Duration::from_nanos(icarus_core::time::now_nanos().saturating_sub(started_at));
+        if let Ok(mut metrics) = dispatch_metrics().write() {
+            metrics.record(
+                elapsed,
+                matches!(&outcome, Ok(result) if !result.is_failure()),
+            );
         }
+
+        Some(outcome)
+    }
+
+    /// Returns execution metrics for calls made through [`execute_tool_sync`](Self::execute_tool_sync).
+    #[must_use]
+    pub fn execution_metrics() -> ExecutionMetrics {
+        dispatch_metrics()
+            .read()
+            .map(|metrics| metrics.clone())
+            .unwrap_or_default()
     }
 
     /// Checks if a tool exists in the registry (static or dynamic).
@@ -527,6 +618,18 @@ impl ToolRegistry {
             })
     }
 
+    /// Checks if a sync executor is registered for a tool.
+    ///
+    /// Tools with only an async executor cannot be run by
+    /// [`execute_tool_sync`](Self::execute_tool_sync).
+    #[must_use]
+    pub fn has_sync_executor(tool_id: &ToolId) -> bool {
+        EXECUTOR_REGISTRY
+            .get()
+            .and_then(|registry| registry.read().ok())
+            .is_some_and(|read_guard| read_guard.sync_executors.contains_key(tool_id))
+    }
+
     /// Initializes the executor registry.
     ///
     /// This method ensures the executor registry is initialized. It's typically
@@ -697,6 +800,99 @@ impl ToolRegistry {
         write_guard.dynamic_tools.clear();
         Ok(count)
     }
+
+    /// Returns a hash of the tool list that changes whenever a tool is added,
+    /// removed or has its schema changed.
+    ///
+    /// Clients poll this (through the generated `mcp_tools_fingerprint` query)
+    /// to detect that a redeploy changed the tools without refetching them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use icarus_runtime::ToolRegistry;
+    ///
+    /// let before = ToolRegistry::fingerprint();
+    /// assert_eq!(before, ToolRegistry::fingerprint());
+    /// ```
+    #[must_use]
+    pub fn fingerprint() -> u64 {
+        let mut tools: Vec<(String, Vec<u8>)> = Self::list_all()
+            .into_iter()
+            .map(|tool| {
+                let bytes = serde_json::to_vec(&tool).unwrap_or_default();
+                (tool.name.to_string(), bytes)
+            })
+            .collect();
+        tools.sort();
+
+        // 64-bit FNV-1a over the sorted, serialized tool definitions
+        tools
+            .iter()
+            .flat_map(|(_, bytes)| bytes.iter().chain(std::iter::once(&0)))
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+
+    /// Removes the sync and async executors of a tool.
+    #[cfg(feature = "dev")]
+    pub(crate) fn remove_executors(tool_id: &ToolId) {
+        if let Some(registry) = EXECUTOR_REGISTRY.get() {
+            if let Ok(mut write_guard) = registry.write() {
+                write_guard.sync_executors.remove(tool_id);
+                write_guard.timeouts.remove(tool_id);
+                #[cfg(feature = "async")]
+                write_guard.async_executors.remove(tool_id);
+            }
+        }
+    }
+}
+
+fn disabled_error(tool_id: &ToolId) -> RuntimeError {
+    RuntimeError::execution_failed(tool_id.as_str(), "the tool is disabled")
+}
+
+/// Checks tool names and input schemas, see [`ToolRegistry::definition_problems`].
+fn definition_problems_in(tools: &[Tool]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut counts: FxHashMap<&str, usize> = FxHashMap::default();
+
+    for tool in tools {
+        let name = tool.name.as_ref();
+        *counts.entry(name).or_default() += 1;
+
+        if let Err(e) = ToolId::new(name) {
+            problems.push(format!("Tool '{name}' has an invalid name: {e}"));
+        }
+
+        // Tools without parameters have an empty schema
+        let schema = tool.input_schema.as_ref();
+        if !schema.is_empty() && schema.get("type").and_then(|t| t.as_str()) != Some("object") {
+            problems.push(format!(
+                "Tool '{name}' has an input schema whose type is not \"object\""
+            ));
+        }
+        if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+            let properties = schema.get("properties").and_then(|p| p.as_object());
+            for field in required.iter().filter_map(|field| field.as_str()) {
+                if !properties.is_some_and(|p| p.contains_key(field)) {
+                    problems.push(format!(
+                        "Tool '{name}' requires parameter '{field}' that is not in its schema"
+                    ));
+                }
+            }
+        }
+    }
+
+    let mut duplicates: Vec<_> = counts.into_iter().filter(|&(_, n)| n > 1).collect();
+    duplicates.sort_unstable();
+    for (name, count) in duplicates {
+        problems.push(format!(
+            "Tool '{name}' is registered {count} times; each #[tool] needs a unique name"
+        ));
+    }
+    problems
 }
 
 /// Statistics about the tool registry.
@@ -778,6 +974,44 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_definition_problems() {
+        use serde_json::json;
+        use std::sync::Arc;
+
+        let schema = |value: serde_json::Value| Arc::new(value.as_object().unwrap().clone());
+        let valid = Tool::new(
+            "add",
+            "Adds",
+            schema(json!({"type": "object", "properties": {"a": {}}, "required": ["a"]})),
+        );
+        assert!(definition_problems_in(&[valid.clone()]).is_empty());
+
+        let tools = [
+            valid.clone(),
+            valid,
+            Tool::new("bad name", "Spaces", Arc::new(serde_json::Map::new())),
+            Tool::new("list", "Not an object", schema(json!({"type": "array"}))),
+            Tool::new(
+                "get",
+                "Missing property",
+                schema(json!({"type": "object", "properties": {}, "required": ["id"]})),
+            ),
+        ];
+        let problems = definition_problems_in(&tools);
+        assert_eq!(problems.len(), 4);
+        assert!(problems
+            .iter()
+            .any(|p| p.contains("'bad name' has an invalid name")));
+        assert!(problems
+            .iter()
+            .any(|p| p.contains("'list' has an input schema")));
+        assert!(problems.iter().any(|p| p.contains("parameter 'id'")));
+        assert!(problems
+            .iter()
+            .any(|p| p.contains("'add' is registered 2 times")));
+    }
+
     #[test]
     fn test_registry_stats() {
         let stats = ToolRegistry::stats();
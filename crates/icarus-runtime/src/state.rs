@@ -0,0 +1,141 @@
+//! Canister-global instances of tool state types.
+//!
+//! `#[icarus_tools]` binds the `#[tool]` methods of a type to one instance of
+//! it kept here, keyed by type. The instance is created with `Default` the
+//! first time a tool needs it, unless [`set`] stored one earlier, typically
+//! in `#[init]`. Like any heap state it does not survive an upgrade; keep
+//! data that must persist in stable structures.
+//!
+//! `&self` methods receive the instance through an `Arc`, so async methods can
+//! hold it across awaits. `&mut self` methods go through [`with_mut`], which
+//! fails with [`RuntimeError::StateBusy`] while such a call is still pending.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_runtime::state;
+//!
+//! #[derive(Default)]
+//! struct Counter {
+//!     count: u64,
+//! }
+//!
+//! state::with_mut(|counter: &mut Counter| counter.count += 1).unwrap();
+//! assert_eq!(state::shared::<Counter>().count, 1);
+//!
+//! state::set(Counter { count: 10 });
+//! assert_eq!(state::shared::<Counter>().count, 10);
+//! ```
+
+use rustc_hash::FxHashMap;
+use std::any::{type_name, Any, TypeId};
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+use crate::{RuntimeError, RuntimeResult};
+
+type Instance = Box<dyn Any + Send + Sync>;
+
+/// Instances by type; each value is an `Arc<T>` stored under `TypeId::of::<T>()`.
+static INSTANCES: OnceLock<RwLock<FxHashMap<TypeId, Instance>>> = OnceLock::new();
+
+fn instances() -> &'static RwLock<FxHashMap<TypeId, Instance>> {
+    INSTANCES.get_or_init(|| RwLock::new(FxHashMap::default()))
+}
+
+/// Replaces the instance of `T`.
+///
+/// Calls still holding the previous instance keep it until they finish.
+pub fn set<T: Send + Sync + 'static>(value: T) {
+    instances()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(TypeId::of::<T>(), Box::new(Arc::new(value)));
+}
+
+/// Returns the instance of `T`, creating it with `Default` if needed.
+#[must_use]
+pub fn shared<T: Default + Send + Sync + 'static>() -> Arc<T> {
+    let existing = instances()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&TypeId::of::<T>())
+        .and_then(|instance| instance.downcast_ref::<Arc<T>>())
+        .cloned();
+    if let Some(instance) = existing {
+        return instance;
+    }
+
+    instances()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(TypeId::of::<T>())
+        .or_insert_with(|| Box::new(Arc::new(T::default())))
+        .downcast_ref::<Arc<T>>()
+        .cloned()
+        .unwrap_or_else(|| unreachable!("instances are stored under their own TypeId"))
+}
+
+/// Runs `f` with exclusive access to the instance of `T`, creating it with
+/// `Default` if needed.
+///
+/// The instance is taken out of the map while `f` runs, so `f` may use the
+/// instances of other types.
+///
+/// # Errors
+///
+/// Returns [`RuntimeError::StateBusy`] if an async call still holds the
+/// instance.
+pub fn with_mut<T, R>(f: impl FnOnce(&mut T) -> R) -> RuntimeResult<R>
+where
+    T: Default + Send + Sync + 'static,
+{
+    let mut instance = take::<T>();
+    let Some(value) = Arc::get_mut(&mut instance) else {
+        put_back(instance);
+        return Err(RuntimeError::state_busy(type_name::<T>()));
+    };
+    let result = f(value);
+    put_back(instance);
+    Ok(result)
+}
+
+fn take<T: Default + Send + Sync + 'static>() -> Arc<T> {
+    instances()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(&TypeId::of::<T>())
+        .and_then(|instance| instance.downcast::<Arc<T>>().ok())
+        .map_or_else(|| Arc::new(T::default()), |instance| *instance)
+}
+
+fn put_back<T: Send + Sync + 'static>(instance: Arc<T>) {
+    instances()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(TypeId::of::<T>(), Box::new(instance));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Notes {
+        items: Vec<String>,
+    }
+
+    #[test]
+    fn test_mutation_waits_for_shared_holders() {
+        with_mut(|notes: &mut Notes| notes.items.push("first".to_string())).unwrap();
+
+        // A pending async `&self` call holds the instance
+        let pending = shared::<Notes>();
+        let err = with_mut(|notes: &mut Notes| notes.items.clear()).unwrap_err();
+        assert!(matches!(err, RuntimeError::StateBusy { .. }));
+        assert_eq!(pending.items, ["first"]);
+
+        drop(pending);
+        with_mut(|notes: &mut Notes| notes.items.push("second".to_string())).unwrap();
+        assert_eq!(shared::<Notes>().items.len(), 2);
+    }
+}
@@ -0,0 +1,1035 @@
+//! Composite workflow tools that chain registered tools.
+//!
+//! A [`Workflow`] is a named sequence of tool calls exposed to MCP clients as a
+//! single tool. Each step's arguments may reference the workflow input or the
+//! output of an earlier step with a JSONPath-style expression:
+//!
+//! - `$.input.user_id` - a field of the arguments passed to the workflow
+//! - `$.steps.lookup.items[0].id` - a field of the output of step `lookup`
+//!
+//! Steps can be skipped with a [`StepCondition`] and can recover from failures
+//! with an [`ErrorPolicy`].
+//!
+//! A step may call another workflow, but not one that is already running on
+//! the current chain: a workflow that reaches itself again, directly or
+//! through others, fails instead of recursing, as does a chain nested deeper
+//! than [`MAX_WORKFLOW_DEPTH`].
+//!
+//! # Rollback
+//!
+//! A step can name a compensating step with [`WorkflowStep::compensate`].
+//! When a later step fails under [`ErrorPolicy::Abort`], the compensations of
+//! the steps that already succeeded run in reverse order, and their arguments
+//! can reference the output of the step they undo. Steps without a
+//! compensation stay applied, and the error lists which steps were undone and
+//! which could not be.
+//!
+//! # Async Tools
+//!
+//! A workflow registered with [`Workflow::register`] runs through the sync
+//! executors, so [`register`](Workflow::register) refuses a workflow whose
+//! steps call a tool that only has an async executor. [`Workflow::execute`]
+//! awaits async tools when called directly.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use icarus_runtime::workflow::{ErrorPolicy, StepCondition, Workflow, WorkflowStep};
+//!
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let workflow = Workflow::builder("onboard_user")
+//!     .description("Create a user and send a welcome message")
+//!     .step(WorkflowStep::new("create", "create_user")?.arg("name", "$.input.name"))
+//!     .step(
+//!         WorkflowStep::new("welcome", "send_message")?
+//!             .arg("to", "$.steps.create.id")
+//!             .when(StepCondition::exists("$.steps.create.id"))
+//!             .on_error(ErrorPolicy::Continue),
+//!     )
+//!     .build()?;
+//!
+//! workflow.register()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use rustc_hash::FxHashMap;
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::{RuntimeError, RuntimeResult, ToolRegistry};
+use icarus_core::{LegacyToolResult as ToolResult, Tool, ToolId};
+
+/// Maximum number of steps allowed in a single workflow.
+pub const MAX_WORKFLOW_STEPS: usize = 32;
+
+/// Maximum number of workflows running inside one another.
+pub const MAX_WORKFLOW_DEPTH: usize = 8;
+
+/// Prefix marking a string argument as a path reference.
+const PATH_PREFIX: &str = "$.";
+
+/// Workflows registered as MCP tools, keyed by tool ID.
+static WORKFLOWS: OnceLock<RwLock<FxHashMap<ToolId, Arc<Workflow>>>> = OnceLock::new();
+
+thread_local! {
+    /// Workflows running synchronously on this thread, outermost first.
+    static RUNNING: RefCell<Vec<ToolId>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Condition deciding whether a step runs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepCondition {
+    /// Run only if the path resolves to a non-null value.
+    Exists(String),
+    /// Run only if the path resolves to the given value.
+    Equals(String, Value),
+    /// Run only if the path does not resolve to the given value.
+    NotEquals(String, Value),
+}
+
+impl StepCondition {
+    /// Creates an [`Exists`](Self::Exists) condition.
+    #[must_use]
+    pub fn exists(path: impl Into<String>) -> Self {
+        Self::Exists(path.into())
+    }
+
+    /// Creates an [`Equals`](Self::Equals) condition.
+    #[must_use]
+    pub fn equals(path: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::Equals(path.into(), value.into())
+    }
+
+    /// Creates a [`NotEquals`](Self::NotEquals) condition.
+    #[must_use]
+    pub fn not_equals(path: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::NotEquals(path.into(), value.into())
+    }
+
+    fn evaluate(&self, context: &Value) -> bool {
+        match self {
+            Self::Exists(path) => resolve_path(context, path).is_some_and(|v| !v.is_null()),
+            Self::Equals(path, expected) => resolve_path(context, path) == Some(expected),
+            Self::NotEquals(path, expected) => resolve_path(context, path) != Some(expected),
+        }
+    }
+}
+
+/// What to do when a step fails.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ErrorPolicy {
+    /// Stop the workflow and report the error (default).
+    #[default]
+    Abort,
+    /// Record the error as the step output and continue.
+    Continue,
+    /// Use the given value as the step output and continue.
+    Fallback(Value),
+}
+
+/// A single tool invocation within a workflow.
+#[derive(Debug, Clone)]
+pub struct WorkflowStep {
+    id: String,
+    tool: ToolId,
+    arguments: Map<String, Value>,
+    condition: Option<StepCondition>,
+    on_error: ErrorPolicy,
+    compensation: Option<Box<WorkflowStep>>,
+}
+
+impl WorkflowStep {
+    /// Creates a step with the given ID that calls `tool`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RuntimeError::InvalidArguments`] if the step ID is empty or the
+    /// tool name is not a valid [`ToolId`].
+    pub fn new(id: impl Into<String>, tool: &str) -> RuntimeResult<Self> {
+        let id = id.into();
+        if id.is_empty() {
+            return Err(RuntimeError::invalid_arguments(
+                tool,
+                "Step ID cannot be empty",
+            ));
+        }
+        let tool = ToolId::new(tool)?;
+
+        Ok(Self {
+            id,
+            tool,
+            arguments: Map::new(),
+            condition: None,
+            on_error: ErrorPolicy::default(),
+            compensation: None,
+        })
+    }
+
+    /// Adds an argument; string values starting with `$.` are resolved as paths.
+    #[must_use]
+    pub fn arg(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.arguments.insert(name.into(), value.into());
+        self
+    }
+
+    /// Only runs this step when `condition` holds.
+    #[must_use]
+    pub fn when(mut self, condition: StepCondition) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    /// Sets how failures of this step are handled.
+    #[must_use]
+    pub fn on_error(mut self, policy: ErrorPolicy) -> Self {
+        self.on_error = policy;
+        self
+    }
+
+    /// Undoes this step with `undo` if a later step aborts the workflow.
+    ///
+    /// `undo` runs only if this step succeeded, and may reference this
+    /// step's output; its own condition is honoured and its error policy is
+    /// ignored.
+    #[must_use]
+    pub fn compensate(mut self, undo: WorkflowStep) -> Self {
+        self.compensation = Some(Box::new(undo));
+        self
+    }
+
+    /// Returns the step ID.
+    #[must_use]
+    #[inline]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the tool invoked by this step.
+    #[must_use]
+    #[inline]
+    pub fn tool(&self) -> &ToolId {
+        &self.tool
+    }
+
+    /// Returns the step that undoes this one, if any.
+    #[must_use]
+    #[inline]
+    pub fn compensation(&self) -> Option<&WorkflowStep> {
+        self.compensation.as_deref()
+    }
+}
+
+/// A named sequence of tool calls exposed as a single tool.
+#[derive(Debug, Clone)]
+pub struct Workflow {
+    name: ToolId,
+    description: String,
+    steps: Vec<WorkflowStep>,
+}
+
+/// Builder for [`Workflow`].
+#[derive(Debug, Clone)]
+pub struct WorkflowBuilder {
+    name: String,
+    description: Option<String>,
+    steps: Vec<WorkflowStep>,
+}
+
+impl WorkflowBuilder {
+    /// Sets the description shown to MCP clients.
+    #[must_use]
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Appends a step.
+    #[must_use]
+    pub fn step(mut self, step: WorkflowStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Validates and builds the workflow.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RuntimeError::InvalidArguments`] if the workflow has no steps,
+    /// more than [`MAX_WORKFLOW_STEPS`] steps, duplicate step IDs, a step or
+    /// compensation that calls the workflow itself, a step that references
+    /// the output of a step that does not run before it, or a compensation
+    /// that references a step that does not run before the step it undoes.
+    pub fn build(self) -> RuntimeResult<Workflow> {
+        let name = ToolId::new(&self.name)?;
+
+        if self.steps.is_empty() {
+            return Err(RuntimeError::invalid_arguments(
+                &self.name,
+                "Workflow must contain at least one step",
+            ));
+        }
+        if self.steps.len() > MAX_WORKFLOW_STEPS {
+            return Err(RuntimeError::invalid_arguments(
+                &self.name,
+                format!("Workflow exceeds {MAX_WORKFLOW_STEPS} steps"),
+            ));
+        }
+
+        let mut seen: Vec<&str> = Vec::with_capacity(self.steps.len());
+        for step in &self.steps {
+            if step.tool == name {
+                return Err(RuntimeError::invalid_arguments(
+                    &self.name,
+                    format!("Step '{}' calls the workflow itself", step.id),
+                ));
+            }
+            if seen.contains(&step.id.as_str()) {
+                return Err(RuntimeError::invalid_arguments(
+                    &self.name,
+                    format!("Duplicate step ID '{}'", step.id),
+                ));
+            }
+            for reference in step_references(step) {
+                if !seen.contains(&reference) {
+                    return Err(RuntimeError::invalid_arguments(
+                        &self.name,
+                        format!(
+                            "Step '{}' references '{reference}' which does not run before it",
+                            step.id
+                        ),
+                    ));
+                }
+            }
+            seen.push(&step.id);
+            if let Some(undo) = &step.compensation {
+                if undo.tool == name {
+                    return Err(RuntimeError::invalid_arguments(
+                        &self.name,
+                        format!(
+                            "Compensation of step '{}' calls the workflow itself",
+                            step.id
+                        ),
+                    ));
+                }
+                if let Some(reference) = step_references(undo)
+                    .into_iter()
+                    .find(|reference| !seen.contains(reference))
+                {
+                    return Err(RuntimeError::invalid_arguments(
+                        &self.name,
+                        format!(
+                            "Compensation of step '{}' references '{reference}' which does not run before it",
+                            step.id
+                        ),
+                    ));
+                }
+            }
+        }
+
+        let description = self
+            .description
+            .unwrap_or_else(|| format!("Workflow of {} steps", self.steps.len()));
+
+        Ok(Workflow {
+            name,
+            description,
+            steps: self.steps,
+        })
+    }
+}
+
+impl Workflow {
+    /// Starts building a workflow exposed under `name`.
+    #[must_use]
+    pub fn builder(name: impl Into<String>) -> WorkflowBuilder {
+        WorkflowBuilder {
+            name: name.into(),
+            description: None,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Returns the tool name of this workflow.
+    #[must_use]
+    #[inline]
+    pub fn name(&self) -> &ToolId {
+        &self.name
+    }
+
+    /// Returns the workflow steps in execution order.
+    #[must_use]
+    #[inline]
+    pub fn steps(&self) -> &[WorkflowStep] {
+        &self.steps
+    }
+
+    /// Builds the MCP tool definition for this workflow.
+    #[must_use]
+    pub fn to_tool(&self) -> Tool {
+        let mut schema = Map::new();
+        schema.insert("type".to_string(), Value::String("object".to_string()));
+        schema.insert("additionalProperties".to_string(), Value::Bool(true));
+
+        Tool::new(
+            self.name.as_str().to_string(),
+            self.description.clone(),
+            Arc::new(schema),
+        )
+    }
+
+    /// Registers the workflow as a dynamic tool so it appears in `tools/list`
+    /// and can be invoked through the registry like any other tool.
+    ///
+    /// Registered workflows run through the sync executors, so every step and
+    /// compensation must call a sync tool or another workflow.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RuntimeError::InvalidArguments`] if a step or compensation
+    /// calls a tool that only has an async executor, or
+    /// [`RuntimeError::RegistryError`] if a registry lock cannot be acquired.
+    pub fn register(self) -> RuntimeResult<()> {
+        let calls = self
+            .steps
+            .iter()
+            .flat_map(|step| std::iter::once(step).chain(step.compensation()));
+        for step in calls {
+            if ToolRegistry::has_executor(&step.tool)
+                && !ToolRegistry::has_sync_executor(&step.tool)
+            {
+                return Err(RuntimeError::invalid_arguments(
+                    self.name.as_str(),
+                    format!(
+                        "Step '{}' calls async tool '{}', which registered workflows cannot run; \
+                         call Workflow::execute instead",
+                        step.id, step.tool
+                    ),
+                ));
+            }
+        }
+
+        let tool = self.to_tool();
+        let store = WORKFLOWS.get_or_init(|| RwLock::new(FxHashMap::default()));
+        store
+            .write()
+            .map_err(|_| RuntimeError::registry_error("Failed to acquire write lock on workflows"))?
+            .insert(self.name.clone(), Arc::new(self));
+        ToolRegistry::register_dynamic_tool(tool)
+    }
+
+    /// Removes a registered workflow and its dynamic tool.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RuntimeError::RegistryError`] if a registry lock cannot be acquired.
+    pub fn unregister(name: &ToolId) -> RuntimeResult<bool> {
+        let removed = match WORKFLOWS.get() {
+            Some(store) => store
+                .write()
+                .map_err(|_| {
+                    RuntimeError::registry_error("Failed to acquire write lock on workflows")
+                })?
+                .remove(name)
+                .is_some(),
+            None => false,
+        };
+        ToolRegistry::unregister_dynamic_tool(name)?;
+        Ok(removed)
+    }
+
+    /// Executes the workflow synchronously with the given JSON arguments.
+    ///
+    /// The result contains a JSON object with the output of every step that ran.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RuntimeError`] if the input is not valid JSON, a step with
+    /// [`ErrorPolicy::Abort`] fails, or the workflow is already running on
+    /// this chain or nested deeper than [`MAX_WORKFLOW_DEPTH`].
+    pub fn execute_sync(&self, arguments: &str) -> RuntimeResult<ToolResult<'static>> {
+        let _running = RunningWorkflow::enter(&self.name)?;
+        let mut context = self.initial_context(arguments)?;
+        let mut applied = Vec::new();
+
+        for step in &self.steps {
+            let Some(step_args) = self.prepare_step(step, &context)? else {
+                continue;
+            };
+            let outcome = ToolRegistry::execute_tool_sync(&step.tool, &step_args)
+                .unwrap_or_else(|| Err(RuntimeError::tool_not_found(step.tool.as_str())));
+            match Self::record_outcome(step, outcome, &mut context) {
+                Ok(true) => applied.push(step),
+                Ok(false) => {}
+                Err(failure) => {
+                    let mut rollback = Rollback::default();
+                    for (undo, undo_args) in self.compensations(&applied, &context) {
+                        let outcome = undo_args.and_then(|undo_args| {
+                            ToolRegistry::execute_tool_sync(&undo.tool, &undo_args).unwrap_or_else(
+                                || Err(RuntimeError::tool_not_found(undo.tool.as_str())),
+                            )
+                        });
+                        rollback.record(undo, outcome);
+                    }
+                    return Err(self.aborted(step, &failure, &rollback));
+                }
+            }
+        }
+
+        Ok(Self::finish(context))
+    }
+
+    /// Executes the workflow, awaiting async tools and falling back to sync executors.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`execute_sync`](Self::execute_sync).
+    #[cfg(feature = "async")]
+    pub async fn execute(&self, arguments: &str) -> RuntimeResult<ToolResult<'static>> {
+        let mut context = self.initial_context(arguments)?;
+        let mut applied = Vec::new();
+
+        for step in &self.steps {
+            let Some(step_args) = self.prepare_step(step, &context)? else {
+                continue;
+            };
+            let outcome = execute_step(step, &step_args).await;
+            match Self::record_outcome(step, outcome, &mut context) {
+                Ok(true) => applied.push(step),
+                Ok(false) => {}
+                Err(failure) => {
+                    let mut rollback = Rollback::default();
+                    for (undo, undo_args) in self.compensations(&applied, &context) {
+                        let outcome = match undo_args {
+                            Ok(undo_args) => execute_step(undo, &undo_args).await,
+                            Err(err) => Err(err),
+                        };
+                        rollback.record(undo, outcome);
+                    }
+                    return Err(self.aborted(step, &failure, &rollback));
+                }
+            }
+        }
+
+        Ok(Self::finish(context))
+    }
+
+    fn initial_context(&self, arguments: &str) -> RuntimeResult<Value> {
+        let input: Value = if arguments.trim().is_empty() {
+            Value::Object(Map::new())
+        } else {
+            serde_json::from_str(arguments)
+                .map_err(|e| RuntimeError::json_error(self.name.as_str(), e))?
+        };
+        Ok(serde_json::json!({ "input": input, "steps": {} }))
+    }
+
+    /// Resolves a step's arguments, or returns `None` if its condition fails.
+    fn prepare_step(&self, step: &WorkflowStep, context: &Value) -> RuntimeResult<Option<String>> {
+        if let Some(condition) = &step.condition {
+            if !condition.evaluate(context) {
+                return Ok(None);
+            }
+        }
+
+        let mut resolved = Map::with_capacity(step.arguments.len());
+        for (name, value) in &step.arguments {
+            resolved.insert(name.clone(), resolve_value(context, value));
+        }
+
+        serde_json::to_string(&Value::Object(resolved))
+            .map(Some)
+            .map_err(|e| RuntimeError::json_error(self.name.as_str(), e))
+    }
+
+    /// Records a step's output in the context.
+    ///
+    /// Returns whether the step succeeded, or the step's error if it failed
+    /// under [`ErrorPolicy::Abort`].
+    fn record_outcome(
+        step: &WorkflowStep,
+        outcome: RuntimeResult<ToolResult<'static>>,
+        context: &mut Value,
+    ) -> RuntimeResult<bool> {
+        let (output, succeeded) = match (step_output(step, outcome), &step.on_error) {
+            (Ok(output), _) => (output, true),
+            (Err(err), ErrorPolicy::Abort) => return Err(err),
+            (Err(err), ErrorPolicy::Continue) => {
+                (serde_json::json!({ "error": err.to_string() }), false)
+            }
+            (Err(_), ErrorPolicy::Fallback(value)) => (value.clone(), false),
+        };
+
+        if let Some(steps) = context.get_mut("steps").and_then(Value::as_object_mut) {
+            steps.insert(step.id.clone(), output);
+        }
+        Ok(succeeded)
+    }
+
+    /// Compensations of the applied steps, latest first, with their resolved
+    /// arguments. Compensations whose condition fails are left out.
+    fn compensations<'a>(
+        &self,
+        applied: &[&'a WorkflowStep],
+        context: &Value,
+    ) -> Vec<(&'a WorkflowStep, RuntimeResult<String>)> {
+        applied
+            .iter()
+            .rev()
+            .filter_map(|&step| step.compensation())
+            .filter_map(|undo| {
+                self.prepare_step(undo, context)
+                    .transpose()
+                    .map(|args| (undo, args))
+            })
+            .collect()
+    }
+
+    /// Error for a workflow aborted by `step`, listing what was rolled back.
+    fn aborted(
+        &self,
+        step: &WorkflowStep,
+        failure: &RuntimeError,
+        rollback: &Rollback,
+    ) -> RuntimeError {
+        let mut message = vec![format!("Step '{}' failed: {failure}", step.id)];
+        if !rollback.undone.is_empty() {
+            message.push(format!("undid {}", rollback.undone.join(", ")));
+        }
+        if !rollback.failed.is_empty() {
+            message.push(format!("could not undo {}", rollback.failed.join(", ")));
+        }
+        RuntimeError::execution_failed(self.name.as_str(), message.join("; "))
+    }
+
+    fn finish(context: Value) -> ToolResult<'static> {
+        let steps = context.get("steps").cloned().unwrap_or(Value::Null);
+        ToolResult::success(steps.to_string())
+    }
+}
+
+/// Outcome of running the compensations of an aborted workflow.
+#[derive(Default)]
+struct Rollback {
+    undone: Vec<String>,
+    failed: Vec<String>,
+}
+
+impl Rollback {
+    fn record(&mut self, undo: &WorkflowStep, outcome: RuntimeResult<ToolResult<'static>>) {
+        match step_output(undo, outcome) {
+            Ok(_) => self.undone.push(format!("'{}'", undo.id)),
+            Err(err) => self.failed.push(format!("'{}' ({err})", undo.id)),
+        }
+    }
+}
+
+/// Converts a tool's result into its JSON output, or the error it reported.
+fn step_output(
+    step: &WorkflowStep,
+    outcome: RuntimeResult<ToolResult<'static>>,
+) -> RuntimeResult<Value> {
+    outcome.and_then(|result| match result {
+        ToolResult::Success { result, .. } => Ok(parse_output(&result)),
+        ToolResult::Error { message, .. } => Err(RuntimeError::execution_failed(
+            step.tool.as_str(),
+            message.into_owned(),
+        )),
+        ToolResult::Pending { .. } => Err(RuntimeError::execution_failed(
+            step.tool.as_str(),
+            "Tool returned a pending result",
+        )),
+        ToolResult::Timeout { timeout_ms, .. } => Err(RuntimeError::execution_failed(
+            step.tool.as_str(),
+            format!("Tool timed out after {timeout_ms}ms"),
+        )),
+    })
+}
+
+/// Runs a step's tool, awaiting it if async and falling back to sync executors.
+#[cfg(feature = "async")]
+async fn execute_step(step: &WorkflowStep, arguments: &str) -> RuntimeResult<ToolResult<'static>> {
+    match ToolRegistry::execute_tool_async(&step.tool, arguments).await {
+        Some(result) => result,
+        None => ToolRegistry::execute_tool_sync(&step.tool, arguments)
+            .unwrap_or_else(|| Err(RuntimeError::tool_not_found(step.tool.as_str()))),
+    }
+}
+
+/// Marks a workflow as running on this thread until dropped.
+struct RunningWorkflow;
+
+impl RunningWorkflow {
+    fn enter(name: &ToolId) -> RuntimeResult<Self> {
+        RUNNING.with(|running| {
+            let mut running = running.borrow_mut();
+            if running.contains(name) {
+                let chain: Vec<&str> = running.iter().map(ToolId::as_str).collect();
+                return Err(RuntimeError::execution_failed(
+                    name.as_str(),
+                    format!("Workflow cycle: {} -> {name}", chain.join(" -> ")),
+                ));
+            }
+            if running.len() >= MAX_WORKFLOW_DEPTH {
+                return Err(RuntimeError::execution_failed(
+                    name.as_str(),
+                    format!("Workflows nested deeper than {MAX_WORKFLOW_DEPTH}"),
+                ));
+            }
+            running.push(name.clone());
+            Ok(Self)
+        })
+    }
+}
+
+impl Drop for RunningWorkflow {
+    fn drop(&mut self) {
+        RUNNING.with(|running| {
+            running.borrow_mut().pop();
+        });
+    }
+}
+
+/// Executes a registered workflow by tool ID, if one exists.
+pub(crate) fn execute_registered_sync(
+    tool_id: &ToolId,
+    arguments: &str,
+) -> Option<RuntimeResult<ToolResult<'static>>> {
+    let workflow = WORKFLOWS.get()?.read().ok()?.get(tool_id).cloned()?;
+    Some(workflow.execute_sync(arguments))
+}
+
+/// Resolves a JSONPath-style expression such as `$.steps.fetch.items[0].id`.
+#[must_use]
+pub fn resolve_path<'a>(context: &'a Value, path: &str) -> Option<&'a Value> {
+    let path = path.strip_prefix(PATH_PREFIX)?;
+    let mut current = context;
+
+    for segment in path.split('.') {
+        let (key, indices) = match segment.find('[') {
+            Some(pos) => segment.split_at(pos),
+            None => (segment, ""),
+        };
+        if !key.is_empty() {
+            current = current.get(key)?;
+        }
+        for index in indices.split('[').filter(|s| !s.is_empty()) {
+            let index: usize = index.strip_suffix(']')?.parse().ok()?;
+            current = current.get(index)?;
+        }
+    }
+
+    Some(current)
+}
+
+fn resolve_value(context: &Value, value: &Value) -> Value {
+    match value {
+        Value::String(s) if s.starts_with(PATH_PREFIX) => {
+            resolve_path(context, s).cloned().unwrap_or(Value::Null)
+        }
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), resolve_value(context, v)))
+                .collect(),
+        ),
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|v| resolve_value(context, v)).collect())
+        }
+        other => other.clone(),
+    }
+}
+
+fn parse_output(result: &str) -> Value {
+    serde_json::from_str(result).unwrap_or_else(|_| Value::String(result.to_string()))
+}
+
+/// Step IDs referenced by `$.steps.<id>` paths in a step's arguments and condition.
+fn step_references(step: &WorkflowStep) -> Vec<&str> {
+    fn collect<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+        match value {
+            Value::String(s) => out.extend(step_reference(s)),
+            Value::Object(map) => map.values().for_each(|v| collect(v, out)),
+            Value::Array(items) => items.iter().for_each(|v| collect(v, out)),
+            _ => {}
+        }
+    }
+
+    let mut out = Vec::new();
+    step.arguments.values().for_each(|v| collect(v, &mut out));
+    if let Some(condition) = &step.condition {
+        let path = match condition {
+            StepCondition::Exists(p)
+            | StepCondition::Equals(p, _)
+            | StepCondition::NotEquals(p, _) => p,
+        };
+        out.extend(step_reference(path));
+    }
+    out
+}
+
+fn step_reference(path: &str) -> Option<&str> {
+    let rest = path.strip_prefix("$.steps.")?;
+    let end = rest.find(['.', '[']).unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo_tool(args: &str) -> RuntimeResult<ToolResult<'static>> {
+        Ok(ToolResult::success(args.to_string()))
+    }
+
+    fn failing_tool(_args: &str) -> RuntimeResult<ToolResult<'static>> {
+        Ok(ToolResult::error("boom"))
+    }
+
+    thread_local! {
+        static BALANCE: std::cell::Cell<i64> = const { std::cell::Cell::new(0) };
+    }
+
+    fn adjust_tool(args: &str) -> RuntimeResult<ToolResult<'static>> {
+        let args: Value = serde_json::from_str(args).unwrap();
+        let amount = args["amount"].as_i64().unwrap_or_default();
+        BALANCE.with(|balance| balance.set(balance.get() + amount));
+        Ok(ToolResult::success(args.to_string()))
+    }
+
+    fn register_test_tools() {
+        ToolRegistry::register_sync_executor(ToolId::new("wf_echo").unwrap(), echo_tool).unwrap();
+        ToolRegistry::register_sync_executor(ToolId::new("wf_fail").unwrap(), failing_tool)
+            .unwrap();
+        ToolRegistry::register_sync_executor(ToolId::new("wf_adjust").unwrap(), adjust_tool)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let context = serde_json::json!({
+            "input": { "name": "alice" },
+            "steps": { "fetch": { "items": [{ "id": 7 }] } }
+        });
+
+        assert_eq!(
+            resolve_path(&context, "$.input.name"),
+            Some(&Value::from("alice"))
+        );
+        assert_eq!(
+            resolve_path(&context, "$.steps.fetch.items[0].id"),
+            Some(&Value::from(7))
+        );
+        assert_eq!(resolve_path(&context, "$.steps.missing"), None);
+        assert_eq!(resolve_path(&context, "input.name"), None);
+    }
+
+    #[test]
+    fn test_build_rejects_forward_reference() {
+        let result = Workflow::builder("bad_workflow")
+            .step(
+                WorkflowStep::new("first", "wf_echo")
+                    .unwrap()
+                    .arg("x", "$.steps.second.value"),
+            )
+            .step(WorkflowStep::new("second", "wf_echo").unwrap())
+            .build();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_build_rejects_duplicate_steps() {
+        let result = Workflow::builder("dup_workflow")
+            .step(WorkflowStep::new("a", "wf_echo").unwrap())
+            .step(WorkflowStep::new("a", "wf_echo").unwrap())
+            .build();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_execute_chains_outputs() {
+        register_test_tools();
+
+        let workflow = Workflow::builder("chain_workflow")
+            .step(
+                WorkflowStep::new("first", "wf_echo")
+                    .unwrap()
+                    .arg("value", "$.input.n"),
+            )
+            .step(
+                WorkflowStep::new("second", "wf_echo")
+                    .unwrap()
+                    .arg("previous", "$.steps.first.value"),
+            )
+            .build()
+            .unwrap();
+
+        let result = workflow.execute_sync(r#"{"n": 42}"#).unwrap();
+        let output: Value = serde_json::from_str(&result.into_success().unwrap()).unwrap();
+        assert_eq!(output["second"]["previous"], 42);
+    }
+
+    #[test]
+    fn test_conditional_step_skipped() {
+        register_test_tools();
+
+        let workflow = Workflow::builder("conditional_workflow")
+            .step(
+                WorkflowStep::new("maybe", "wf_echo")
+                    .unwrap()
+                    .when(StepCondition::equals("$.input.mode", "full")),
+            )
+            .build()
+            .unwrap();
+
+        let result = workflow.execute_sync(r#"{"mode": "quick"}"#).unwrap();
+        let output: Value = serde_json::from_str(&result.into_success().unwrap()).unwrap();
+        assert!(output.get("maybe").is_none());
+    }
+
+    #[test]
+    fn test_error_policies() {
+        register_test_tools();
+
+        let aborting = Workflow::builder("abort_workflow")
+            .step(WorkflowStep::new("fail", "wf_fail").unwrap())
+            .build()
+            .unwrap();
+        assert!(aborting.execute_sync("{}").is_err());
+
+        let recovering = Workflow::builder("fallback_workflow")
+            .step(
+                WorkflowStep::new("fail", "wf_fail")
+                    .unwrap()
+                    .on_error(ErrorPolicy::Fallback(Value::from(0))),
+            )
+            .build()
+            .unwrap();
+        let result = recovering.execute_sync("{}").unwrap();
+        let output: Value = serde_json::from_str(&result.into_success().unwrap()).unwrap();
+        assert_eq!(output["fail"], 0);
+    }
+
+    #[test]
+    fn test_failed_step_rolls_back_applied_steps() {
+        register_test_tools();
+        BALANCE.with(|balance| balance.set(0));
+
+        let workflow = Workflow::builder("rollback_workflow")
+            .step(
+                WorkflowStep::new("debit", "wf_adjust")
+                    .unwrap()
+                    .arg("amount", "$.input.amount")
+                    .compensate(
+                        WorkflowStep::new("refund", "wf_adjust")
+                            .unwrap()
+                            .arg("amount", "$.input.refund"),
+                    ),
+            )
+            .step(
+                WorkflowStep::new("kept", "wf_adjust")
+                    .unwrap()
+                    .arg("amount", 1),
+            )
+            .step(WorkflowStep::new("fail", "wf_fail").unwrap())
+            .build()
+            .unwrap();
+
+        let error = workflow
+            .execute_sync(r#"{"amount": -10, "refund": 10}"#)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Step 'fail' failed"));
+        assert!(error.contains("undid 'refund'"));
+        assert_eq!(BALANCE.with(std::cell::Cell::get), 1);
+
+        assert!(Workflow::builder("bad_rollback_workflow")
+            .step(
+                WorkflowStep::new("first", "wf_echo").unwrap().compensate(
+                    WorkflowStep::new("undo", "wf_echo")
+                        .unwrap()
+                        .arg("x", "$.steps.second.value"),
+                ),
+            )
+            .step(WorkflowStep::new("second", "wf_echo").unwrap())
+            .build()
+            .is_err());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_register_rejects_async_steps() {
+        fn async_tool(
+            _args: &str,
+        ) -> std::pin::Pin<
+            Box<dyn std::future::Future<Output = RuntimeResult<ToolResult<'static>>> + Send>,
+        > {
+            Box::pin(async { Ok(ToolResult::success("{}".to_string())) })
+        }
+        ToolRegistry::register_async_executor(ToolId::new("wf_async").unwrap(), async_tool)
+            .unwrap();
+
+        let error = Workflow::builder("async_workflow")
+            .step(WorkflowStep::new("remote", "wf_async").unwrap())
+            .build()
+            .unwrap()
+            .register()
+            .unwrap_err();
+        assert!(error.to_string().contains("async tool 'wf_async'"));
+        assert!(!ToolRegistry::has_tool(
+            &ToolId::new("async_workflow").unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_registered_workflow_runs_through_registry() {
+        register_test_tools();
+
+        Workflow::builder("registered_workflow")
+            .step(WorkflowStep::new("only", "wf_echo").unwrap().arg("k", "v"))
+            .build()
+            .unwrap()
+            .register()
+            .unwrap();
+
+        let tool_id = ToolId::new("registered_workflow").unwrap();
+        assert!(ToolRegistry::has_tool(&tool_id));
+        let result = ToolRegistry::execute_tool_sync(&tool_id, "{}").unwrap();
+        assert!(result.unwrap().is_success());
+
+        assert!(Workflow::unregister(&tool_id).unwrap());
+    }
+
+    #[test]
+    fn test_workflow_cycles_fail() {
+        register_test_tools();
+
+        assert!(Workflow::builder("self_workflow")
+            .step(WorkflowStep::new("again", "self_workflow").unwrap())
+            .build()
+            .is_err());
+
+        Workflow::builder("ping_workflow")
+            .step(WorkflowStep::new("pong", "pong_workflow").unwrap())
+            .build()
+            .unwrap()
+            .register()
+            .unwrap();
+        Workflow::builder("pong_workflow")
+            .step(WorkflowStep::new("ping", "ping_workflow").unwrap())
+            .build()
+            .unwrap()
+            .register()
+            .unwrap();
+
+        let ping = ToolId::new("ping_workflow").unwrap();
+        let error = ToolRegistry::execute_tool_sync(&ping, "{}")
+            .unwrap()
+            .unwrap_err();
+        assert!(error.to_string().contains("Workflow cycle"));
+        assert!(RUNNING.with(|running| running.borrow().is_empty()));
+
+        Workflow::unregister(&ping).unwrap();
+        Workflow::unregister(&ToolId::new("pong_workflow").unwrap()).unwrap();
+    }
+}
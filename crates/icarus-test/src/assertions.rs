@@ -0,0 +1,290 @@
+//! Golden-file snapshot assertions for MCP responses.
+//!
+//! [`assert_matches_snapshot`] normalizes a response and compares it against a
+//! committed JSON file. Normalization makes snapshots stable across runs:
+//!
+//! - object keys are sorted
+//! - JSON strings that contain JSON (such as `mcp_call_tool` output) are parsed
+//! - timestamp fields are replaced with `"[timestamp]"`
+//! - identifier fields are replaced with `"[id]"`
+//!
+//! Set `ICARUS_UPDATE_SNAPSHOTS=1` to write the current output instead of
+//! comparing, then review and commit the updated golden files.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use icarus_test::assertions::assert_matches_snapshot;
+//!
+//! #[test]
+//! fn list_tools_is_stable() {
+//!     assert_matches_snapshot(&mcp_list_tools(), "snapshots/list_tools.json");
+//! }
+//! ```
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Environment variable that switches snapshot assertions into update mode.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "ICARUS_UPDATE_SNAPSHOTS";
+
+/// Placeholder written in place of redacted timestamps.
+pub const REDACTED_TIMESTAMP: &str = "[timestamp]";
+
+/// Placeholder written in place of redacted identifiers.
+pub const REDACTED_ID: &str = "[id]";
+
+/// Errors produced by snapshot comparison.
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    /// The golden file does not exist and update mode is off.
+    #[error("Snapshot {path} does not exist; rerun with {UPDATE_SNAPSHOTS_ENV}=1 to create it")]
+    Missing {
+        /// Resolved snapshot path.
+        path: PathBuf,
+    },
+
+    /// The normalized response differs from the golden file.
+    #[error(
+        "Snapshot {path} does not match; rerun with {UPDATE_SNAPSHOTS_ENV}=1 to update it\n\
+         --- expected\n{expected}\n+++ actual\n{actual}"
+    )]
+    Mismatch {
+        /// Resolved snapshot path.
+        path: PathBuf,
+        /// Contents of the golden file.
+        expected: String,
+        /// Normalized response.
+        actual: String,
+    },
+
+    /// The response could not be serialized.
+    #[error("Failed to serialize response: {0}")]
+    Serialize(#[from] serde_json::Error),
+
+    /// The golden file could not be read or written.
+    #[error("Snapshot I/O error for {path}: {source}")]
+    Io {
+        /// Resolved snapshot path.
+        path: PathBuf,
+        /// Underlying I/O error.
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// Asserts that `response` matches the golden file at `path`.
+///
+/// Relative paths are resolved against `CARGO_MANIFEST_DIR`, so snapshots live
+/// next to the crate under test.
+///
+/// # Panics
+///
+/// Panics with a diff if the snapshot is missing or does not match.
+#[track_caller]
+pub fn assert_matches_snapshot<T: Serialize + ?Sized>(response: &T, path: impl AsRef<Path>) {
+    if let Err(err) = try_match_snapshot(response, path) {
+        panic!("{err}");
+    }
+}
+
+/// Compares `response` against the golden file at `path`.
+///
+/// In update mode the golden file is (re)written and `Ok(())` is returned.
+///
+/// # Errors
+///
+/// Returns a [`SnapshotError`] if the snapshot is missing, differs, or cannot
+/// be read or written.
+pub fn try_match_snapshot<T: Serialize + ?Sized>(
+    response: &T,
+    path: impl AsRef<Path>,
+) -> Result<(), SnapshotError> {
+    let path = resolve_path(path.as_ref());
+    let actual = render(&normalize(serde_json::to_value(response)?))?;
+
+    if update_mode() {
+        return write_snapshot(&path, &actual);
+    }
+
+    let expected = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err(SnapshotError::Missing { path });
+        }
+        Err(source) => return Err(SnapshotError::Io { path, source }),
+    };
+
+    if expected.trim_end() == actual.trim_end() {
+        Ok(())
+    } else {
+        Err(SnapshotError::Mismatch {
+            path,
+            expected,
+            actual,
+        })
+    }
+}
+
+/// Normalizes a JSON value for snapshot comparison.
+///
+/// Keys are sorted, embedded JSON strings are expanded, and timestamp and
+/// identifier fields are redacted.
+#[must_use]
+pub fn normalize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            let mut normalized = Map::new();
+            for (key, value) in entries {
+                let value = redact(&key, value);
+                normalized.insert(key, value);
+            }
+            Value::Object(normalized)
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(normalize).collect()),
+        Value::String(text) => match embedded_json(&text) {
+            Some(parsed) => normalize(parsed),
+            None => Value::String(text),
+        },
+        other => other,
+    }
+}
+
+fn redact(key: &str, value: Value) -> Value {
+    if value.is_null() {
+        return value;
+    }
+    if is_timestamp_key(key) {
+        return Value::String(REDACTED_TIMESTAMP.to_string());
+    }
+    if is_id_key(key) && !value.is_object() && !value.is_array() {
+        return Value::String(REDACTED_ID.to_string());
+    }
+    normalize(value)
+}
+
+fn is_timestamp_key(key: &str) -> bool {
+    matches!(key, "timestamp" | "time")
+        || key.ends_with("_at")
+        || key.ends_with("At")
+        || key.ends_with("_time")
+        || key.ends_with("Time")
+        || key.ends_with("_timestamp")
+        || key.ends_with("Timestamp")
+}
+
+fn is_id_key(key: &str) -> bool {
+    key == "id" || key.ends_with("_id") || key.ends_with("Id")
+}
+
+/// Parses strings that hold a JSON object or array, leaving other text untouched.
+fn embedded_json(text: &str) -> Option<Value> {
+    let trimmed = text.trim_start();
+    if !(trimmed.starts_with('{') || trimmed.starts_with('[')) {
+        return None;
+    }
+    serde_json::from_str(text).ok()
+}
+
+fn render(value: &Value) -> Result<String, SnapshotError> {
+    let mut rendered = serde_json::to_string_pretty(value)?;
+    rendered.push('\n');
+    Ok(rendered)
+}
+
+fn update_mode() -> bool {
+    std::env::var(UPDATE_SNAPSHOTS_ENV).is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+pub(crate) fn resolve_path(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    std::env::var_os("CARGO_MANIFEST_DIR")
+        .map_or_else(|| path.to_path_buf(), |dir| PathBuf::from(dir).join(path))
+}
+
+fn write_snapshot(path: &Path, contents: &str) -> Result<(), SnapshotError> {
+    let io_error = |source| SnapshotError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    fs::write(path, contents).map_err(io_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_snapshot(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("icarus-test-snapshots");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_normalize_sorts_and_redacts() {
+        let value = normalize(json!({
+            "result": { "b": 1, "a": 2 },
+            "jsonrpc": "2.0",
+            "id": "42",
+            "session_id": "abc",
+            "created_at": 1_700_000_000_000_u64,
+        }));
+
+        assert_eq!(value["id"], REDACTED_ID);
+        assert_eq!(value["session_id"], REDACTED_ID);
+        assert_eq!(value["created_at"], REDACTED_TIMESTAMP);
+
+        let rendered = serde_json::to_string(&value["result"]).unwrap();
+        assert_eq!(rendered, r#"{"a":2,"b":1}"#);
+    }
+
+    #[test]
+    fn test_normalize_expands_embedded_json() {
+        let response = r#"{"jsonrpc":"2.0","id":7,"result":{"tools":[]}}"#;
+        let value = normalize(Value::String(response.to_string()));
+        assert_eq!(value["id"], REDACTED_ID);
+        assert_eq!(value["result"]["tools"], json!([]));
+    }
+
+    #[test]
+    fn test_snapshot_match_and_mismatch() {
+        let response = json!({ "jsonrpc": "2.0", "id": 1, "result": { "ok": true } });
+        let expected = render(&normalize(response.clone())).unwrap();
+
+        let path = temp_snapshot("match.json", &expected);
+        assert!(try_match_snapshot(&response, &path).is_ok());
+
+        let path = temp_snapshot("mismatch.json", "{}\n");
+        assert!(matches!(
+            try_match_snapshot(&response, &path),
+            Err(SnapshotError::Mismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_missing_snapshot() {
+        let path = std::env::temp_dir().join("icarus-test-snapshots/does-not-exist.json");
+        if update_mode() {
+            return;
+        }
+        assert!(matches!(
+            try_match_snapshot(&json!({}), &path),
+            Err(SnapshotError::Missing { .. })
+        ));
+    }
+}
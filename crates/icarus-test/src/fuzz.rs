@@ -0,0 +1,748 @@
+//! Property-based fuzzing of tools from their JSON schemas.
+//!
+//! [`fuzz_tool`] looks up a tool in the [`ToolRegistry`], derives `proptest`
+//! strategies from its `input_schema`, and executes the tool with:
+//!
+//! - **valid** argument sets that respect `type`, `required`, `minimum`,
+//!   `maximum`, `minLength`, `maxLength` and `items`
+//! - **boundary-invalid** argument sets that drop a required field, change a
+//!   field's type, or step just outside a numeric or length bound
+//!
+//! A run fails if the tool panics, if an error breaks the error contract of
+//! Icarus tools, if a result does not match the tool's `output_schema`, or
+//! (optionally) if an invalid argument set is accepted.
+//!
+//! An error must carry a message, a `code` valid under
+//! [`error_codes::is_code`], and `details` that parse as JSON. A result is
+//! parsed as JSON, or taken as a JSON string if it is not, and checked against
+//! the `type`, `enum`, `required`, `properties`, `items` and bounds of the
+//! output schema, when the tool declares one.
+
+use proptest::prelude::*;
+use proptest::strategy::ValueTree;
+use proptest::test_runner::{Config, TestRunner};
+use serde_json::{Map, Number, Value};
+use std::panic::{self, AssertUnwindSafe};
+use thiserror::Error;
+
+use icarus_core::error_codes;
+use icarus_runtime::{ToolId, ToolRegistry, ToolResult};
+
+/// Upper bound used for unbounded string and array lengths.
+const DEFAULT_MAX_LENGTH: usize = 32;
+
+/// Configuration for a fuzzing run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuzzConfig {
+    /// Number of valid argument sets to generate.
+    pub cases: u32,
+    /// Whether to also generate boundary-invalid argument sets.
+    pub include_invalid: bool,
+    /// Whether an invalid argument set that executes successfully is a failure.
+    pub require_rejection: bool,
+}
+
+impl Default for FuzzConfig {
+    fn default() -> Self {
+        Self {
+            cases: 256,
+            include_invalid: true,
+            require_rejection: false,
+        }
+    }
+}
+
+/// Summary of a successful fuzzing run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FuzzReport {
+    /// Valid argument sets executed.
+    pub valid_cases: u32,
+    /// Boundary-invalid argument sets executed.
+    pub invalid_cases: u32,
+    /// Executions that returned an error (valid or invalid input).
+    pub errors: u32,
+    /// Invalid argument sets that the tool accepted.
+    pub accepted_invalid: u32,
+}
+
+/// Reasons a fuzzing run can fail.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FuzzFailure {
+    /// The tool is not registered or its name is invalid.
+    #[error("Tool not found: {0}")]
+    ToolNotFound(String),
+
+    /// The tool has no registered sync executor.
+    #[error("No executor registered for tool: {0}")]
+    NoExecutor(String),
+
+    /// The tool panicked.
+    #[error("Tool '{tool}' panicked with arguments {arguments}: {message}")]
+    Panic {
+        /// Tool name.
+        tool: String,
+        /// Arguments that triggered the panic.
+        arguments: String,
+        /// Panic payload.
+        message: String,
+    },
+
+    /// The tool returned an error without a message.
+    #[error("Tool '{tool}' returned an empty error for arguments {arguments}")]
+    EmptyError {
+        /// Tool name.
+        tool: String,
+        /// Arguments that produced the error.
+        arguments: String,
+    },
+
+    /// The tool returned an error that breaks the error contract.
+    #[error("Tool '{tool}' returned a malformed error for arguments {arguments}: {reason}")]
+    MalformedError {
+        /// Tool name.
+        tool: String,
+        /// Arguments that produced the error.
+        arguments: String,
+        /// What is wrong with the error.
+        reason: String,
+    },
+
+    /// The tool returned a result that does not match its output schema.
+    #[error("Tool '{tool}' returned a result outside its output schema for arguments {arguments}: {reason}")]
+    NonConformingResult {
+        /// Tool name.
+        tool: String,
+        /// Arguments that produced the result.
+        arguments: String,
+        /// Where the result breaks the schema.
+        reason: String,
+    },
+
+    /// The tool accepted arguments that violate its schema.
+    #[error("Tool '{tool}' accepted invalid arguments {arguments}")]
+    AcceptedInvalid {
+        /// Tool name.
+        tool: String,
+        /// The invalid arguments.
+        arguments: String,
+    },
+}
+
+/// Fuzzes a tool with the default configuration, panicking on failure.
+///
+/// # Panics
+///
+/// Panics with the [`FuzzFailure`] description if the run fails.
+pub fn fuzz_tool(tool_name: &str) -> FuzzReport {
+    fuzz_tool_with(tool_name, FuzzConfig::default())
+}
+
+/// Fuzzes a tool with a custom configuration, panicking on failure.
+///
+/// # Panics
+///
+/// Panics with the [`FuzzFailure`] description if the run fails.
+pub fn fuzz_tool_with(tool_name: &str, config: FuzzConfig) -> FuzzReport {
+    try_fuzz_tool(tool_name, config).unwrap_or_else(|failure| panic!("{failure}"))
+}
+
+/// Fuzzes a tool and returns the first failure instead of panicking.
+///
+/// # Errors
+///
+/// Returns a [`FuzzFailure`] describing the first problem encountered.
+pub fn try_fuzz_tool(tool_name: &str, config: FuzzConfig) -> Result<FuzzReport, FuzzFailure> {
+    let tool_id =
+        ToolId::new(tool_name).map_err(|_| FuzzFailure::ToolNotFound(tool_name.to_string()))?;
+    let tool = ToolRegistry::find_by_id(&tool_id)
+        .ok_or_else(|| FuzzFailure::ToolNotFound(tool_name.to_string()))?;
+    let schema = Value::Object((*tool.input_schema).clone());
+    let output_schema = tool
+        .output_schema
+        .as_ref()
+        .map(|schema| Value::Object((**schema).clone()));
+
+    let mut report = FuzzReport::default();
+    let mut runner = TestRunner::new(Config {
+        cases: config.cases,
+        ..Config::default()
+    });
+
+    let valid = valid_strategy(&schema);
+    for _ in 0..config.cases {
+        let arguments = sample(&mut runner, &valid);
+        run_case(&tool_id, &arguments, output_schema.as_ref(), &mut report)?;
+        report.valid_cases += 1;
+    }
+
+    if config.include_invalid {
+        for arguments in invalid_cases(&schema, &mut runner) {
+            let accepted = run_case(&tool_id, &arguments, output_schema.as_ref(), &mut report)?;
+            report.invalid_cases += 1;
+            if accepted {
+                report.accepted_invalid += 1;
+                if config.require_rejection {
+                    return Err(FuzzFailure::AcceptedInvalid {
+                        tool: tool_name.to_string(),
+                        arguments: arguments.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Executes one case, checks its outcome against the tool's contract, and
+/// returns whether the tool succeeded.
+fn run_case(
+    tool_id: &ToolId,
+    arguments: &Value,
+    output_schema: Option<&Value>,
+    report: &mut FuzzReport,
+) -> Result<bool, FuzzFailure> {
+    let args = arguments.to_string();
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        ToolRegistry::execute_tool_sync(tool_id, &args)
+    }))
+    .map_err(|payload| FuzzFailure::Panic {
+        tool: tool_id.to_string(),
+        arguments: args.clone(),
+        message: panic_message(payload.as_ref()),
+    })?
+    .ok_or_else(|| FuzzFailure::NoExecutor(tool_id.to_string()))?;
+
+    let (message, violation) = match outcome {
+        Ok(ToolResult::Error {
+            message,
+            code,
+            details,
+        }) => {
+            let violation = error_violation(code.as_deref(), details.as_deref());
+            (message.into_owned(), violation)
+        }
+        Ok(ToolResult::Success { result, .. }) => {
+            let violation = output_schema.and_then(|schema| {
+                let value = serde_json::from_str(&result)
+                    .unwrap_or_else(|_| Value::String(result.into_owned()));
+                schema_violation(&value, schema, "result")
+            });
+            return match violation {
+                Some(reason) => Err(FuzzFailure::NonConformingResult {
+                    tool: tool_id.to_string(),
+                    arguments: args,
+                    reason,
+                }),
+                None => Ok(true),
+            };
+        }
+        Ok(ToolResult::Pending { .. } | ToolResult::Timeout { .. }) => return Ok(true),
+        Err(err) => (err.to_string(), None),
+    };
+
+    report.errors += 1;
+    if message.trim().is_empty() {
+        return Err(FuzzFailure::EmptyError {
+            tool: tool_id.to_string(),
+            arguments: args,
+        });
+    }
+    if let Some(reason) = violation {
+        return Err(FuzzFailure::MalformedError {
+            tool: tool_id.to_string(),
+            arguments: args,
+            reason,
+        });
+    }
+    Ok(false)
+}
+
+/// Checks the code and details of an error result.
+fn error_violation(code: Option<&str>, details: Option<&str>) -> Option<String> {
+    if let Some(code) = code.filter(|code| !error_codes::is_code(code)) {
+        return Some(format!("'{code}' is not a valid error code"));
+    }
+    details
+        .filter(|details| serde_json::from_str::<Value>(details).is_err())
+        .map(|details| format!("details {details:?} are not JSON"))
+}
+
+/// Returns where `value` breaks `schema`, if it does.
+///
+/// Covers the same keywords the strategies are derived from, plus `enum`.
+#[must_use]
+pub fn schema_violation(value: &Value, schema: &Value, path: &str) -> Option<String> {
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            return Some(format!(
+                "{path} is not one of {}",
+                Value::Array(allowed.clone())
+            ));
+        }
+    }
+
+    let expected = schema_type(schema);
+    let matches = match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    };
+    if !matches {
+        return Some(format!("{path} is not of type {expected}"));
+    }
+
+    match value {
+        Value::Object(map) => {
+            if let Some(name) = required_fields(schema)
+                .into_iter()
+                .find(|name| !map.contains_key(name))
+            {
+                return Some(format!("{path} is missing required field '{name}'"));
+            }
+            let properties = schema.get("properties").and_then(Value::as_object)?;
+            map.iter().find_map(|(name, field)| {
+                properties.get(name).and_then(|property| {
+                    schema_violation(field, property, &format!("{path}.{name}"))
+                })
+            })
+        }
+        Value::Array(items) => {
+            let (min, max) = declared_bounds(schema, "minItems", "maxItems");
+            if let Some(reason) = length_violation(items.len(), min, max, path, "items") {
+                return Some(reason);
+            }
+            let item_schema = schema.get("items")?;
+            items
+                .iter()
+                .enumerate()
+                .find_map(|(i, item)| schema_violation(item, item_schema, &format!("{path}[{i}]")))
+        }
+        Value::String(text) => {
+            let (min, max) = declared_bounds(schema, "minLength", "maxLength");
+            length_violation(text.chars().count(), min, max, path, "characters")
+        }
+        Value::Number(number) => {
+            let n = number.as_f64()?;
+            if schema
+                .get("minimum")
+                .and_then(Value::as_f64)
+                .is_some_and(|min| n < min)
+            {
+                return Some(format!("{path} is below its minimum"));
+            }
+            schema
+                .get("maximum")
+                .and_then(Value::as_f64)
+                .filter(|max| n > *max)
+                .map(|_| format!("{path} is above its maximum"))
+        }
+        Value::Null | Value::Bool(_) => None,
+    }
+}
+
+fn declared_bounds(schema: &Value, min_key: &str, max_key: &str) -> (Option<u64>, Option<u64>) {
+    (
+        schema.get(min_key).and_then(Value::as_u64),
+        schema.get(max_key).and_then(Value::as_u64),
+    )
+}
+
+fn length_violation(
+    len: usize,
+    min: Option<u64>,
+    max: Option<u64>,
+    path: &str,
+    unit: &str,
+) -> Option<String> {
+    let len = u64::try_from(len).unwrap_or(u64::MAX);
+    if min.is_some_and(|min| len < min) {
+        return Some(format!("{path} has fewer {unit} than allowed"));
+    }
+    max.filter(|max| len > *max)
+        .map(|_| format!("{path} has more {unit} than allowed"))
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| (*s).to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string())
+}
+
+fn sample(runner: &mut TestRunner, strategy: &BoxedStrategy<Value>) -> Value {
+    strategy
+        .new_tree(runner)
+        .map(|tree| tree.current())
+        .unwrap_or(Value::Null)
+}
+
+/// Builds a strategy producing values that conform to `schema`.
+#[must_use]
+pub fn valid_strategy(schema: &Value) -> BoxedStrategy<Value> {
+    match schema_type(schema) {
+        "object" => object_strategy(schema),
+        "string" => {
+            let (min, max) = length_bounds(schema);
+            proptest::collection::vec(any::<char>(), min..=max)
+                .prop_map(|chars| Value::String(chars.into_iter().collect()))
+                .boxed()
+        }
+        "integer" => {
+            let min = schema
+                .get("minimum")
+                .and_then(Value::as_i64)
+                .unwrap_or(i64::MIN);
+            let max = schema
+                .get("maximum")
+                .and_then(Value::as_i64)
+                .unwrap_or(i64::MAX);
+            if min > max {
+                return Just(Value::Null).boxed();
+            }
+            (min..=max).prop_map(Value::from).boxed()
+        }
+        "number" => {
+            let min = schema
+                .get("minimum")
+                .and_then(Value::as_f64)
+                .unwrap_or(-1.0e12);
+            let max = schema
+                .get("maximum")
+                .and_then(Value::as_f64)
+                .unwrap_or(1.0e12);
+            if min > max {
+                return Just(Value::Null).boxed();
+            }
+            (min..=max)
+                .prop_map(|n| Number::from_f64(n).map_or(Value::Null, Value::Number))
+                .boxed()
+        }
+        "boolean" => any::<bool>().prop_map(Value::Bool).boxed(),
+        "array" => {
+            let items = schema.get("items").cloned().unwrap_or(Value::Null);
+            let (min, max) = length_bounds(schema);
+            proptest::collection::vec(valid_strategy(&items), min..=max)
+                .prop_map(Value::Array)
+                .boxed()
+        }
+        _ => prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::Bool),
+            any::<i64>().prop_map(Value::from),
+            ".{0,16}".prop_map(Value::String),
+        ]
+        .boxed(),
+    }
+}
+
+fn object_strategy(schema: &Value) -> BoxedStrategy<Value> {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return Just(Value::Object(Map::new())).boxed();
+    };
+    let required = required_fields(schema);
+
+    let mut strategy: BoxedStrategy<Map<String, Value>> = Just(Map::new()).boxed();
+    for (name, property) in properties {
+        let name = name.clone();
+        let value = valid_strategy(property);
+        let optional = !required.contains(&name);
+        strategy = (strategy, value, any::<bool>())
+            .prop_map(move |(mut map, value, include)| {
+                if include || !optional {
+                    map.insert(name.clone(), value);
+                }
+                map
+            })
+            .boxed();
+    }
+    strategy.prop_map(Value::Object).boxed()
+}
+
+/// Generates boundary-invalid argument sets for an object schema.
+fn invalid_cases(schema: &Value, runner: &mut TestRunner) -> Vec<Value> {
+    let mut cases = Vec::new();
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return cases;
+    };
+    let base = match sample(runner, &valid_strategy(schema)) {
+        Value::Object(map) => map,
+        _ => return cases,
+    };
+
+    for name in required_fields(schema) {
+        let mut missing = base.clone();
+        missing.remove(&name);
+        cases.push(Value::Object(missing));
+    }
+
+    for (name, property) in properties {
+        for invalid in boundary_values(property) {
+            let mut args = base.clone();
+            args.insert(name.clone(), invalid);
+            cases.push(Value::Object(args));
+        }
+    }
+
+    cases
+}
+
+/// Values just outside the bounds of `schema`, plus one of the wrong type.
+fn boundary_values(schema: &Value) -> Vec<Value> {
+    let mut values = Vec::new();
+    match schema_type(schema) {
+        "string" => {
+            values.push(Value::from(42));
+            if let Some(min) = schema
+                .get("minLength")
+                .and_then(Value::as_u64)
+                .filter(|m| *m > 0)
+            {
+                values.push(Value::String(
+                    "a".repeat(usize::try_from(min - 1).unwrap_or(0)),
+                ));
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                values.push(Value::String(
+                    "a".repeat(usize::try_from(max + 1).unwrap_or(0)),
+                ));
+            }
+        }
+        "integer" | "number" => {
+            values.push(Value::String("not a number".to_string()));
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                values.push(Number::from_f64(min - 1.0).map_or(Value::Null, Value::Number));
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                values.push(Number::from_f64(max + 1.0).map_or(Value::Null, Value::Number));
+            }
+        }
+        "boolean" => values.push(Value::String("true".to_string())),
+        "array" => values.push(Value::String("[]".to_string())),
+        "object" => values.push(Value::Array(Vec::new())),
+        _ => {}
+    }
+    values
+}
+
+fn schema_type(schema: &Value) -> &str {
+    schema.get("type").and_then(Value::as_str).unwrap_or("")
+}
+
+fn length_bounds(schema: &Value) -> (usize, usize) {
+    let to_usize = |key: &str| {
+        schema
+            .get(key)
+            .and_then(Value::as_u64)
+            .and_then(|n| usize::try_from(n).ok())
+    };
+    let min = to_usize("minLength")
+        .or_else(|| to_usize("minItems"))
+        .unwrap_or(0);
+    let max = to_usize("maxLength")
+        .or_else(|| to_usize("maxItems"))
+        .unwrap_or(DEFAULT_MAX_LENGTH.max(min));
+    (min, max.max(min))
+}
+
+fn required_fields(schema: &Value) -> Vec<String> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|fields| {
+            fields
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icarus_runtime::{RuntimeResult, SyncToolExecutor, Tool};
+    use std::sync::Arc;
+
+    fn bounded_schema() -> Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "minLength": 2, "maxLength": 5 },
+                "count": { "type": "integer", "minimum": 1, "maximum": 10 }
+            },
+            "required": ["name", "count"]
+        })
+    }
+
+    fn strict_tool(args: &str) -> RuntimeResult<ToolResult<'static>> {
+        let value: Value = serde_json::from_str(args).unwrap_or(Value::Null);
+        let name_ok = value["name"]
+            .as_str()
+            .is_some_and(|s| (2..=5).contains(&s.chars().count()));
+        let count_ok = value["count"]
+            .as_i64()
+            .is_some_and(|n| (1..=10).contains(&n));
+        if name_ok && count_ok {
+            Ok(ToolResult::success("ok"))
+        } else {
+            Ok(ToolResult::error("invalid arguments"))
+        }
+    }
+
+    fn panicking_tool(_args: &str) -> RuntimeResult<ToolResult<'static>> {
+        panic!("unexpected input")
+    }
+
+    fn counting_tool(args: &str) -> RuntimeResult<ToolResult<'static>> {
+        let value: Value = serde_json::from_str(args).unwrap_or(Value::Null);
+        let count = value["count"].as_i64().unwrap_or(0);
+        Ok(ToolResult::success(
+            serde_json::json!({ "total": count * 2 }).to_string(),
+        ))
+    }
+
+    fn miscoded_tool(_args: &str) -> RuntimeResult<ToolResult<'static>> {
+        Ok(ToolResult::error_with_details(
+            "no such note",
+            "not-found",
+            "{}",
+        ))
+    }
+
+    fn object(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    fn register(name: &str, executor: SyncToolExecutor) {
+        register_with_output(name, executor, None);
+    }
+
+    fn register_with_output(name: &str, executor: SyncToolExecutor, output: Option<Value>) {
+        let mut tool = Tool::new(
+            name.to_string(),
+            "fuzz test tool",
+            Arc::new(object(bounded_schema())),
+        );
+        tool.output_schema = output.map(|schema| Arc::new(object(schema)));
+        ToolRegistry::register_dynamic_tool(tool).unwrap();
+        ToolRegistry::register_sync_executor(ToolId::new(name).unwrap(), executor).unwrap();
+    }
+
+    #[test]
+    fn test_valid_strategy_respects_bounds() {
+        let mut runner = TestRunner::default();
+        let strategy = valid_strategy(&bounded_schema());
+        for _ in 0..64 {
+            let value = sample(&mut runner, &strategy);
+            let name = value["name"].as_str().unwrap();
+            assert!((2..=5).contains(&name.chars().count()));
+            assert!((1..=10).contains(&value["count"].as_i64().unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_boundary_values() {
+        let values = boundary_values(&serde_json::json!({
+            "type": "integer",
+            "minimum": 1,
+            "maximum": 10
+        }));
+        assert!(values.contains(&Value::from(0.0)));
+        assert!(values.contains(&Value::from(11.0)));
+    }
+
+    #[test]
+    fn test_schema_violation() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "total": { "type": "integer", "maximum": 10 },
+                "tags": { "type": "array", "items": { "enum": ["a", "b"] } }
+            },
+            "required": ["total"]
+        });
+        let check = |value: Value| schema_violation(&value, &schema, "result");
+
+        assert_eq!(
+            check(serde_json::json!({ "total": 4, "tags": ["a"] })),
+            None
+        );
+        assert!(check(serde_json::json!({ "tags": [] })).is_some());
+        assert!(check(serde_json::json!({ "total": 11 })).is_some());
+        assert!(check(serde_json::json!({ "total": "4" })).is_some());
+        assert!(check(serde_json::json!({ "total": 4, "tags": ["c"] })).is_some());
+        assert!(check(Value::String("4".to_string())).is_some());
+    }
+
+    #[test]
+    fn test_fuzz_checks_results_and_errors() {
+        let output = serde_json::json!({
+            "type": "object",
+            "properties": { "total": { "type": "integer", "maximum": 20 } },
+            "required": ["total"]
+        });
+        register_with_output("fuzz_counting_tool", counting_tool, Some(output.clone()));
+        let report = fuzz_tool_with(
+            "fuzz_counting_tool",
+            FuzzConfig {
+                cases: 32,
+                include_invalid: false,
+                require_rejection: false,
+            },
+        );
+        assert_eq!(report.valid_cases, 32);
+
+        let mut narrow = output;
+        narrow["properties"]["total"]["maximum"] = Value::from(1);
+        register_with_output("fuzz_overflowing_tool", counting_tool, Some(narrow));
+        let result = try_fuzz_tool("fuzz_overflowing_tool", FuzzConfig::default());
+        assert!(matches!(
+            result,
+            Err(FuzzFailure::NonConformingResult { .. })
+        ));
+
+        register("fuzz_miscoded_tool", miscoded_tool);
+        let result = try_fuzz_tool("fuzz_miscoded_tool", FuzzConfig::default());
+        assert!(matches!(result, Err(FuzzFailure::MalformedError { .. })));
+    }
+
+    #[test]
+    fn test_fuzz_strict_tool() {
+        register("fuzz_strict_tool", strict_tool);
+        let report = fuzz_tool_with(
+            "fuzz_strict_tool",
+            FuzzConfig {
+                cases: 32,
+                include_invalid: true,
+                require_rejection: true,
+            },
+        );
+        assert_eq!(report.valid_cases, 32);
+        assert!(report.invalid_cases > 0);
+        assert_eq!(report.accepted_invalid, 0);
+    }
+
+    #[test]
+    fn test_fuzz_detects_panic() {
+        register("fuzz_panicking_tool", panicking_tool);
+        let result = try_fuzz_tool("fuzz_panicking_tool", FuzzConfig::default());
+        assert!(matches!(result, Err(FuzzFailure::Panic { .. })));
+    }
+
+    #[test]
+    fn test_fuzz_unknown_tool() {
+        let result = try_fuzz_tool("fuzz_missing_tool", FuzzConfig::default());
+        assert!(matches!(result, Err(FuzzFailure::ToolNotFound(_))));
+    }
+}
@@ -0,0 +1,429 @@
+//! Record-and-replay regression tests for MCP sessions.
+//!
+//! `icarus mcp start --record session.jsonl` writes every JSON-RPC exchange
+//! passing through the bridge as one line:
+//!
+//! ```json
+//! {"request": {"jsonrpc": "2.0", "id": "1", "method": "tools/call", "params": {...}}, "response": {...}}
+//! ```
+//!
+//! [`replay`] re-executes each recorded `tools/call` request and compares the
+//! response with the recorded one after [`normalize`](crate::assertions::normalize),
+//! so ids and timestamps do not cause false mismatches. Other methods are
+//! skipped. By default requests run in-process through the same registry the
+//! `mcp!{}` endpoints use; [`replay_with`] sends them anywhere else, such as a
+//! canister installed in a local replica.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use icarus_test::harness;
+//!
+//! #[test]
+//! fn recorded_session_still_passes() {
+//!     icarus_runtime::initialize_executors();
+//!     harness::replay("tests/sessions/memorize.jsonl");
+//! }
+//! ```
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use icarus_core::{content, CallToolResult, Content};
+use icarus_runtime::{ToolId, ToolRegistry, ToolResult};
+
+use crate::assertions::{normalize, resolve_path};
+
+/// JSON-RPC method of the requests that are replayed.
+pub const TOOLS_CALL: &str = "tools/call";
+
+/// One recorded request/response pair.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RecordedExchange {
+    /// The JSON-RPC request.
+    pub request: Value,
+    /// The JSON-RPC response returned when recording.
+    pub response: Value,
+}
+
+impl RecordedExchange {
+    /// Returns the JSON-RPC method of the request.
+    #[must_use]
+    pub fn method(&self) -> Option<&str> {
+        self.request.get("method").and_then(Value::as_str)
+    }
+}
+
+/// A replayed call whose response differs from the recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayMismatch {
+    /// 1-based line of the exchange in the session file.
+    pub line: usize,
+    /// The replayed request.
+    pub request: Value,
+    /// Normalized recorded response.
+    pub expected: Value,
+    /// Normalized replayed response.
+    pub actual: Value,
+}
+
+impl fmt::Display for ReplayMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pretty = |value: &Value| {
+            serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+        };
+        write!(
+            f,
+            "line {}: {}\n--- expected\n{}\n+++ actual\n{}",
+            self.line,
+            self.request,
+            pretty(&self.expected),
+            pretty(&self.actual)
+        )
+    }
+}
+
+/// Outcome of replaying a session.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayReport {
+    /// Number of `tools/call` requests re-executed.
+    pub replayed: usize,
+    /// Number of exchanges with other methods that were skipped.
+    pub skipped: usize,
+    /// Replayed calls whose responses differ from the recording.
+    pub mismatches: Vec<ReplayMismatch>,
+}
+
+impl ReplayReport {
+    /// Returns whether every replayed call matched its recording.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Errors reading a session file.
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    /// The session file could not be read.
+    #[error("Failed to read session {path}: {source}")]
+    Io {
+        /// Resolved session path.
+        path: PathBuf,
+        /// Underlying I/O error.
+        #[source]
+        source: io::Error,
+    },
+
+    /// A line is not a recorded exchange.
+    #[error("Invalid exchange on line {line} of {path}: {source}")]
+    Parse {
+        /// Resolved session path.
+        path: PathBuf,
+        /// 1-based line number.
+        line: usize,
+        /// Underlying parse error.
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// Replays a session in-process and asserts that every response matches.
+///
+/// Relative paths are resolved against `CARGO_MANIFEST_DIR`. Executors must be
+/// registered first, e.g. with `icarus_runtime::initialize_executors()`.
+///
+/// # Panics
+///
+/// Panics if the session cannot be read or any response differs, listing
+/// every mismatch.
+#[track_caller]
+pub fn replay(path: impl AsRef<Path>) -> ReplayReport {
+    let report = match try_replay(path) {
+        Ok(report) => report,
+        Err(err) => panic!("{err}"),
+    };
+
+    if !report.is_success() {
+        let mismatches: Vec<String> = report.mismatches.iter().map(ToString::to_string).collect();
+        panic!(
+            "{} of {} replayed calls differ from the recording:\n\n{}",
+            report.mismatches.len(),
+            report.replayed,
+            mismatches.join("\n\n")
+        );
+    }
+    report
+}
+
+/// Replays a session in-process and reports mismatches instead of panicking.
+///
+/// # Errors
+///
+/// Returns a [`ReplayError`] if the session file cannot be read or parsed.
+pub fn try_replay(path: impl AsRef<Path>) -> Result<ReplayReport, ReplayError> {
+    replay_with(path, execute_request)
+}
+
+/// Replays a session, sending each `tools/call` request to `execute`.
+///
+/// `execute` receives the recorded JSON-RPC request and returns the JSON-RPC
+/// response, for example by calling `mcp_call_tool` on a local canister.
+///
+/// # Errors
+///
+/// Returns a [`ReplayError`] if the session file cannot be read or parsed.
+pub fn replay_with(
+    path: impl AsRef<Path>,
+    mut execute: impl FnMut(&Value) -> Value,
+) -> Result<ReplayReport, ReplayError> {
+    let mut report = ReplayReport::default();
+
+    for (line, exchange) in load_session(path)? {
+        if exchange.method() != Some(TOOLS_CALL) {
+            report.skipped += 1;
+            continue;
+        }
+
+        report.replayed += 1;
+        let expected = normalize(exchange.response);
+        let actual = normalize(execute(&exchange.request));
+        if expected != actual {
+            report.mismatches.push(ReplayMismatch {
+                line,
+                request: exchange.request,
+                expected,
+                actual,
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+/// Reads a session file, returning each exchange with its 1-based line number.
+///
+/// Blank lines are ignored.
+///
+/// # Errors
+///
+/// Returns a [`ReplayError`] if the file cannot be read or a line is not a
+/// recorded exchange.
+pub fn load_session(path: impl AsRef<Path>) -> Result<Vec<(usize, RecordedExchange)>, ReplayError> {
+    let path = resolve_path(path.as_ref());
+    let contents = fs::read_to_string(&path).map_err(|source| ReplayError::Io {
+        path: path.clone(),
+        source,
+    })?;
+
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(index, text)| {
+            serde_json::from_str(text)
+                .map(|exchange| (index + 1, exchange))
+                .map_err(|source| ReplayError::Parse {
+                    path: path.clone(),
+                    line: index + 1,
+                    source,
+                })
+        })
+        .collect()
+}
+
+/// Executes a `tools/call` request through the tool registry and returns the
+/// JSON-RPC response `mcp_call_tool` would produce.
+///
+/// Payments, cycle guards and usage metrics are canister concerns and are not
+/// applied.
+#[must_use]
+pub fn execute_request(request: &Value) -> Value {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+
+    if request.get("method").and_then(Value::as_str) != Some(TOOLS_CALL) {
+        return error_response(id, -32601, "Method not found".to_string());
+    }
+    let Some(params) = request.get("params") else {
+        return error_response(id, -32602, "Missing params field".to_string());
+    };
+    let Some(tool_name) = params.get("name").and_then(Value::as_str) else {
+        return error_response(id, -32602, "Missing tool name in params".to_string());
+    };
+    let tool_id = match ToolId::new(tool_name) {
+        Ok(tool_id) => tool_id,
+        Err(e) => return error_response(id, -32602, format!("Invalid tool name: {e}")),
+    };
+
+    let arguments = params
+        .get("arguments")
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+    let (content, is_error, structured_content) =
+        match ToolRegistry::execute_tool_sync(&tool_id, &arguments.to_string()) {
+            Some(Ok(success @ ToolResult::Success { .. })) => {
+                (content::from_tool_result(&success), false, None)
+            }
+            Some(Ok(ToolResult::Error { message, .. })) => {
+                (vec![Content::text(message.into_owned())], true, None)
+            }
+            Some(Ok(ToolResult::Pending { status, .. })) => (
+                vec![Content::text(status.map_or_else(
+                    || "Tool execution pending".to_string(),
+                    |s| s.into_owned(),
+                ))],
+                false,
+                None,
+            ),
+            Some(Ok(ToolResult::Timeout {
+                timeout_ms,
+                elapsed_ms,
+            })) => (
+                vec![Content::text(format!(
+                    "Tool timed out after {timeout_ms}ms"
+                ))],
+                true,
+                Some(json!({ "timeout_ms": timeout_ms, "elapsed_ms": elapsed_ms })),
+            ),
+            Some(Err(e)) => {
+                return error_response(id, -32603, format!("Tool execution error: {e}"));
+            }
+            None => return error_response(id, -32601, format!("Tool not found: {tool_name}")),
+        };
+
+    let result = CallToolResult {
+        content,
+        structured_content,
+        is_error: Some(is_error),
+        meta: None,
+    };
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error_response(id: Value, code: i32, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icarus_runtime::RuntimeResult;
+    use serial_test::serial;
+    use std::sync::Arc;
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn shout(args: &str) -> RuntimeResult<ToolResult<'static>> {
+        let args: Value = serde_json::from_str(args).unwrap_or_default();
+        let text = args["text"].as_str().unwrap_or_default().to_uppercase();
+        Ok(ToolResult::success(text))
+    }
+
+    fn register_shout() {
+        let tool = icarus_core::Tool::new(
+            "replay_shout".to_string(),
+            "Upper-cases text",
+            Arc::new(serde_json::Map::new()),
+        );
+        ToolRegistry::register_dynamic_tool(tool).unwrap();
+        ToolRegistry::register_sync_executor(ToolId::new("replay_shout").unwrap(), shout).unwrap();
+    }
+
+    fn call(id: &str, text: &str) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": { "name": "replay_shout", "arguments": { "text": text } }
+        })
+    }
+
+    fn write_session(name: &str, exchanges: &[(Value, Value)]) -> PathBuf {
+        let dir = std::env::temp_dir().join("icarus-test-sessions");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        let lines: Vec<String> = exchanges
+            .iter()
+            .map(|(request, response)| {
+                json!({ "request": request, "response": response, "recorded_at": "2025-10-01T12:00:00Z" })
+                    .to_string()
+            })
+            .collect();
+        fs::write(&path, lines.join("\n")).unwrap();
+        path
+    }
+
+    #[test]
+    #[serial]
+    fn test_replay_matches_recording() {
+        register_shout();
+        let recorded = execute_request(&call("1", "hi"));
+        assert_eq!(recorded["result"]["content"][0]["text"], "HI");
+
+        let path = write_session(
+            "match.jsonl",
+            &[
+                (json!({ "method": "ping" }), json!({ "result": "pong" })),
+                // Ids differ between sessions and are ignored
+                (call("99", "hi"), recorded),
+            ],
+        );
+
+        let report = replay(&path);
+        assert_eq!(report.replayed, 1);
+        assert_eq!(report.skipped, 1);
+    }
+
+    #[test]
+    #[serial]
+    fn test_replay_reports_mismatches() {
+        register_shout();
+        let stale = execute_request(&call("1", "bye"));
+        let path = write_session("mismatch.jsonl", &[(call("1", "hi"), stale)]);
+
+        let report = try_replay(&path).unwrap();
+        assert!(!report.is_success());
+        assert_eq!(report.mismatches[0].line, 1);
+        assert!(report.mismatches[0].to_string().contains("BYE"));
+    }
+
+    #[test]
+    fn test_replay_with_custom_executor() {
+        let response = json!({ "jsonrpc": "2.0", "id": "1", "result": { "ok": true } });
+        let path = write_session("custom.jsonl", &[(call("1", "hi"), response.clone())]);
+
+        let report = replay_with(&path, |_| response.clone()).unwrap();
+        assert!(report.is_success());
+    }
+
+    #[test]
+    fn test_invalid_session_line() {
+        let dir = std::env::temp_dir().join("icarus-test-sessions");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("invalid.jsonl");
+        fs::write(&path, "{\"request\": {}, \"response\": {}}\nnot json\n").unwrap();
+
+        assert!(matches!(
+            load_session(&path),
+            Err(ReplayError::Parse { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_unknown_tool_is_an_error_response() {
+        let response = execute_request(&json!({
+            "id": "1",
+            "method": "tools/call",
+            "params": { "name": "replay_missing" }
+        }));
+        assert_eq!(response["error"]["code"], -32601);
+    }
+}
@@ -0,0 +1,51 @@
+//! # Icarus Test
+//!
+//! Testing utilities for tools built with the Icarus CDK.
+//!
+//! This crate is intended for use as a dev-dependency and provides helpers
+//! that exercise tools through the same registry used by the `mcp!{}` endpoints:
+//!
+//! - **Property-based fuzzing**: [`fuzz_tool`] generates arguments from a tool's
+//!   JSON schema and checks that execution never panics
+//! - **Snapshot assertions**: [`assertions::assert_matches_snapshot`] compares
+//!   normalized MCP responses against committed golden files
+//! - **Mock environment**: [`mock::MockEnvironment`] controls the clock and
+//!   caller and fires timers deterministically
+//! - **HTTP mocks**: [`mock::mock_http`] serves canned responses to outcalls
+//!   and fails on unexpected requests
+//! - **Signing mocks**: [`mock::mock_signing`] stands in for threshold ECDSA
+//! - **Randomness mocks**: [`mock::mock_rand`] makes random draws reproducible
+//! - **Session replay**: [`harness::replay`] re-executes sessions recorded with
+//!   `icarus mcp start --record` and diffs the responses
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use icarus_test::fuzz_tool;
+//!
+//! #[tool]
+//! fn divide(a: f64, b: f64) -> Result<f64, String> {
+//!     if b == 0.0 { Err("Division by zero".into()) } else { Ok(a / b) }
+//! }
+//!
+//! #[test]
+//! fn divide_never_panics() {
+//!     icarus_runtime::initialize_executors();
+//!     fuzz_tool("divide");
+//! }
+//! ```
+
+#![warn(missing_docs)]
+#![warn(clippy::all)]
+#![warn(clippy::pedantic)]
+#![deny(unsafe_code)]
+
+pub mod assertions;
+pub mod fuzz;
+pub mod harness;
+pub mod mock;
+
+pub use assertions::{assert_matches_snapshot, SnapshotError};
+pub use fuzz::{fuzz_tool, fuzz_tool_with, try_fuzz_tool, FuzzConfig, FuzzFailure, FuzzReport};
+pub use harness::{replay, replay_with, try_replay, ReplayError, ReplayReport};
+pub use mock::{mock_http, mock_rand, mock_signing, MockEnvironment};
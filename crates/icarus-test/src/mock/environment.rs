@@ -0,0 +1,373 @@
+//! Deterministic clock, timers and caller.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use candid::Principal;
+use icarus_core::context::{self, set_caller_override};
+use icarus_core::time::set_time_override;
+
+/// Clock value installed by [`MockEnvironment::new`]: 2024-01-01T00:00:00Z.
+pub const DEFAULT_START_TIME_NANOS: u64 = 1_704_067_200_000_000_000;
+
+/// Identifier of a timer scheduled on a [`MockEnvironment`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimerId(u64);
+
+impl fmt::Display for TimerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "timer-{}", self.0)
+    }
+}
+
+struct Timer {
+    deadline: u64,
+    interval: Option<u64>,
+    callback: Box<dyn FnMut()>,
+}
+
+#[derive(Default)]
+struct EnvState {
+    now: u64,
+    next_id: u64,
+    timers: BTreeMap<TimerId, Timer>,
+    /// Timers cleared while their own callback was running.
+    cancelled: Vec<TimerId>,
+}
+
+thread_local! {
+    static STATE: RefCell<EnvState> = RefCell::new(EnvState::default());
+}
+
+/// Controls the clock, timers and caller seen by code under test.
+///
+/// While a `MockEnvironment` is alive, [`icarus_core::time::now_nanos`] and
+/// [`icarus_core::Timestamp::now`] return the mocked time on the current
+/// thread. Advancing time fires every timer whose deadline has passed, in
+/// deadline order, with the clock set to each timer's deadline while it runs.
+/// Tools taking a `ToolContext` see the principal set with
+/// [`set_caller`](Self::set_caller), anonymous by default.
+/// Dropping the environment restores the system clock and caller and
+/// discards timers.
+///
+/// # Examples
+///
+/// ```rust
+/// use icarus_test::mock::MockEnvironment;
+/// use std::cell::Cell;
+/// use std::rc::Rc;
+/// use std::time::Duration;
+///
+/// let env = MockEnvironment::new();
+/// let fired = Rc::new(Cell::new(0));
+///
+/// let counter = Rc::clone(&fired);
+/// env.set_timer_interval(Duration::from_secs(60), move || counter.set(counter.get() + 1));
+///
+/// env.advance_time(Duration::from_secs(150));
+/// assert_eq!(fired.get(), 2);
+/// ```
+pub struct MockEnvironment {
+    // Mock state is thread-local, so the handle must stay on its thread.
+    _not_send: PhantomData<*const ()>,
+}
+
+// The handle only scopes the thread-local state, so methods don't read `self`.
+#[allow(clippy::unused_self)]
+impl MockEnvironment {
+    /// Installs a mock clock starting at [`DEFAULT_START_TIME_NANOS`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::starting_at(DEFAULT_START_TIME_NANOS)
+    }
+
+    /// Installs a mock clock starting at `nanos` since the Unix epoch.
+    #[must_use]
+    pub fn starting_at(nanos: u64) -> Self {
+        STATE.with(|state| {
+            *state.borrow_mut() = EnvState {
+                now: nanos,
+                ..EnvState::default()
+            };
+        });
+        set_time_override(Some(nanos));
+        set_caller_override(None);
+        Self {
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Returns the mocked time in nanoseconds since the Unix epoch.
+    #[must_use]
+    pub fn time(&self) -> u64 {
+        STATE.with(|state| state.borrow().now)
+    }
+
+    /// Makes `caller` the principal calling tools, including its auth role.
+    pub fn set_caller(&self, caller: Principal) {
+        set_caller_override(Some(caller));
+    }
+
+    /// Returns the principal tools see as their caller.
+    #[must_use]
+    pub fn caller(&self) -> Principal {
+        context::caller()
+    }
+
+    /// Sets the clock to `nanos`, firing timers due at or before it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `nanos` is earlier than the current mocked time; the IC clock
+    /// is monotonic.
+    pub fn set_time(&self, nanos: u64) {
+        let now = self.time();
+        assert!(
+            nanos >= now,
+            "MockEnvironment time cannot move backwards ({now} -> {nanos})"
+        );
+        self.run_until(nanos);
+    }
+
+    /// Advances the clock by `duration`, firing timers that become due.
+    pub fn advance_time(&self, duration: Duration) {
+        let target = self.time().saturating_add(duration_nanos(duration));
+        self.run_until(target);
+    }
+
+    /// Schedules `callback` to run once after `delay`.
+    pub fn set_timer(&self, delay: Duration, callback: impl FnOnce() + 'static) -> TimerId {
+        let mut callback = Some(callback);
+        self.schedule(delay, None, move || {
+            if let Some(callback) = callback.take() {
+                callback();
+            }
+        })
+    }
+
+    /// Schedules `callback` to run every `interval`, starting one interval from now.
+    pub fn set_timer_interval(
+        &self,
+        interval: Duration,
+        callback: impl FnMut() + 'static,
+    ) -> TimerId {
+        // A zero interval would fire forever at a single instant.
+        let step = duration_nanos(interval).max(1);
+        self.schedule(interval, Some(step), callback)
+    }
+
+    /// Cancels a timer. Returns `false` if it already fired or was cleared.
+    pub fn clear_timer(&self, id: TimerId) -> bool {
+        STATE.with(|state| {
+            let mut state = state.borrow_mut();
+            if state.timers.remove(&id).is_some() {
+                true
+            } else if id.0 < state.next_id && !state.cancelled.contains(&id) {
+                // The timer may be running right now; stop it from rescheduling.
+                state.cancelled.push(id);
+                false
+            } else {
+                false
+            }
+        })
+    }
+
+    /// Returns the number of timers waiting to fire.
+    #[must_use]
+    pub fn pending_timers(&self) -> usize {
+        STATE.with(|state| state.borrow().timers.len())
+    }
+
+    /// Returns the deadline of the next timer, if any.
+    #[must_use]
+    pub fn next_deadline(&self) -> Option<u64> {
+        STATE.with(|state| state.borrow().timers.values().map(|t| t.deadline).min())
+    }
+
+    fn schedule(
+        &self,
+        delay: Duration,
+        interval: Option<u64>,
+        callback: impl FnMut() + 'static,
+    ) -> TimerId {
+        STATE.with(|state| {
+            let mut state = state.borrow_mut();
+            let id = TimerId(state.next_id);
+            state.next_id += 1;
+            let deadline = state.now.saturating_add(duration_nanos(delay));
+            state.timers.insert(
+                id,
+                Timer {
+                    deadline,
+                    interval,
+                    callback: Box::new(callback),
+                },
+            );
+            id
+        })
+    }
+
+    fn run_until(&self, target: u64) {
+        while let Some((id, mut timer)) = take_due_timer(target) {
+            set_time_override(Some(timer.deadline));
+            (timer.callback)();
+
+            if let Some(step) = timer.interval {
+                STATE.with(|state| {
+                    let mut state = state.borrow_mut();
+                    if let Some(pos) = state.cancelled.iter().position(|c| *c == id) {
+                        state.cancelled.swap_remove(pos);
+                    } else {
+                        timer.deadline = timer.deadline.saturating_add(step);
+                        state.timers.insert(id, timer);
+                    }
+                });
+            }
+        }
+
+        STATE.with(|state| state.borrow_mut().now = target);
+        set_time_override(Some(target));
+    }
+}
+
+impl Default for MockEnvironment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MockEnvironment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockEnvironment")
+            .field("time", &self.time())
+            .field("pending_timers", &self.pending_timers())
+            .finish()
+    }
+}
+
+impl Drop for MockEnvironment {
+    fn drop(&mut self) {
+        // Take the state first so timer captures drop outside the borrow.
+        let state = STATE.with(|state| std::mem::take(&mut *state.borrow_mut()));
+        drop(state);
+        set_time_override(None);
+        set_caller_override(None);
+        context::set_current(None);
+    }
+}
+
+/// Removes the earliest timer due at or before `target`, advancing the clock to it.
+fn take_due_timer(target: u64) -> Option<(TimerId, Timer)> {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let (&id, _) = state
+            .timers
+            .iter()
+            .filter(|(_, timer)| timer.deadline <= target)
+            .min_by_key(|(id, timer)| (timer.deadline, **id))?;
+        let timer = state.timers.remove(&id)?;
+        state.now = state.now.max(timer.deadline);
+        Some((id, timer))
+    })
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn duration_nanos(duration: Duration) -> u64 {
+    duration.as_nanos().min(u128::from(u64::MAX)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icarus_core::Timestamp;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_set_and_advance_time() {
+        let env = MockEnvironment::new();
+        assert_eq!(Timestamp::now().as_nanos(), DEFAULT_START_TIME_NANOS);
+
+        env.advance_time(Duration::from_secs(5));
+        assert_eq!(env.time(), DEFAULT_START_TIME_NANOS + 5_000_000_000);
+
+        env.set_time(DEFAULT_START_TIME_NANOS + 10_000_000_000);
+        assert_eq!(
+            Timestamp::now().as_secs(),
+            DEFAULT_START_TIME_NANOS / 1_000_000_000 + 10
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot move backwards")]
+    fn test_time_is_monotonic() {
+        let env = MockEnvironment::new();
+        env.set_time(0);
+    }
+
+    #[test]
+    fn test_one_shot_timer_fires_at_deadline() {
+        let env = MockEnvironment::new();
+        let seen = Rc::new(Cell::new(0));
+
+        let observed = Rc::clone(&seen);
+        env.set_timer(Duration::from_secs(30), move || {
+            observed.set(Timestamp::now().as_nanos());
+        });
+
+        env.advance_time(Duration::from_secs(29));
+        assert_eq!(seen.get(), 0);
+
+        env.advance_time(Duration::from_secs(60));
+        assert_eq!(seen.get(), DEFAULT_START_TIME_NANOS + 30_000_000_000);
+        assert_eq!(env.pending_timers(), 0);
+    }
+
+    #[test]
+    fn test_interval_timer_and_clear() {
+        let env = MockEnvironment::new();
+        let fired = Rc::new(Cell::new(0));
+
+        let counter = Rc::clone(&fired);
+        let id = env.set_timer_interval(Duration::from_secs(10), move || {
+            counter.set(counter.get() + 1);
+        });
+
+        env.advance_time(Duration::from_secs(35));
+        assert_eq!(fired.get(), 3);
+
+        assert!(env.clear_timer(id));
+        env.advance_time(Duration::from_secs(100));
+        assert_eq!(fired.get(), 3);
+    }
+
+    #[test]
+    fn test_caller_reaches_tool_context() {
+        let env = MockEnvironment::new();
+        assert_eq!(env.caller(), Principal::anonymous());
+
+        let user = Principal::from_slice(&[9]);
+        icarus_core::auth::add_user(user);
+        env.set_caller(user);
+
+        let ctx = context::current_for("notes");
+        assert_eq!(ctx.caller, user);
+        assert_eq!(ctx.role, context::Role::User);
+
+        drop(env);
+        icarus_core::auth::remove_user(&user);
+        assert_eq!(context::caller(), Principal::anonymous());
+    }
+
+    #[test]
+    fn test_drop_restores_system_clock() {
+        {
+            let _env = MockEnvironment::starting_at(7);
+            assert_eq!(Timestamp::now().as_nanos(), 7);
+        }
+        assert!(Timestamp::now().as_nanos() > DEFAULT_START_TIME_NANOS);
+    }
+}
@@ -0,0 +1,359 @@
+//! Canned responses for HTTP outcalls.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use icarus_core::http::{set_outcall_handler, HttpRequest, HttpResponse};
+use icarus_core::IcarusError;
+
+struct Route {
+    method: String,
+    pattern: String,
+    response: HttpResponse,
+    requests: RefCell<Vec<HttpRequest>>,
+}
+
+#[derive(Default)]
+struct Registry {
+    routes: Vec<Rc<Route>>,
+    unmatched: Vec<HttpRequest>,
+}
+
+/// Installs an HTTP mock for the current thread.
+///
+/// Outcalls made through `icarus_core::http` are matched against the routes
+/// registered with [`HttpMock::when`], in registration order. Requests that
+/// match no route fail with `IcarusError::ExternalServiceError` and are
+/// recorded; dropping the mock panics if any were recorded, so a test cannot
+/// silently pass while a tool hits an unexpected URL.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use icarus_test::mock::mock_http;
+///
+/// let http = mock_http();
+/// let price = http
+///     .when("GET", "https://api.coingecko.com/api/v3/simple/price*")
+///     .respond(200, r#"{"bitcoin":{"usd":45000.0}}"#);
+///
+/// let result = call_tool("get_btc_price", "{}");
+/// price.assert_called_once();
+/// ```
+#[must_use]
+pub fn mock_http() -> HttpMock {
+    let registry = Rc::new(RefCell::new(Registry::default()));
+
+    let handler_registry = Rc::clone(&registry);
+    set_outcall_handler(Some(Box::new(move |request| {
+        handle(&handler_registry, request)
+    })));
+
+    HttpMock { registry }
+}
+
+fn handle(
+    registry: &RefCell<Registry>,
+    request: &HttpRequest,
+) -> icarus_core::Result<HttpResponse> {
+    let route = registry
+        .borrow()
+        .routes
+        .iter()
+        .find(|route| {
+            route.method.eq_ignore_ascii_case(request.method.as_str())
+                && matches_pattern(&route.pattern, &request.url)
+        })
+        .cloned();
+
+    if let Some(route) = route {
+        route.requests.borrow_mut().push(request.clone());
+        Ok(route.response.clone())
+    } else {
+        registry.borrow_mut().unmatched.push(request.clone());
+        Err(IcarusError::ExternalServiceError {
+            service: request.url.clone(),
+            message: format!(
+                "No HTTP mock registered for {} {}",
+                request.method, request.url
+            ),
+        })
+    }
+}
+
+/// Handle to the HTTP mock installed by [`mock_http`].
+pub struct HttpMock {
+    registry: Rc<RefCell<Registry>>,
+}
+
+impl HttpMock {
+    /// Starts a route for `method` and a URL pattern where `*` matches any
+    /// sequence of characters.
+    pub fn when(&self, method: &str, url_pattern: &str) -> MockRequest<'_> {
+        MockRequest {
+            mock: self,
+            method: method.to_string(),
+            pattern: url_pattern.to_string(),
+        }
+    }
+
+    /// Returns requests that matched no route.
+    #[must_use]
+    pub fn unmatched(&self) -> Vec<HttpRequest> {
+        self.registry.borrow().unmatched.clone()
+    }
+
+    /// Asserts that every request matched a route.
+    ///
+    /// # Panics
+    ///
+    /// Panics listing the unmatched requests.
+    #[track_caller]
+    pub fn assert_no_unmatched(&self) {
+        let unmatched = self.unmatched();
+        assert!(
+            unmatched.is_empty(),
+            "Unmatched HTTP requests: {}",
+            describe(&unmatched)
+        );
+    }
+
+    /// Asserts that every registered route was called at least once.
+    ///
+    /// # Panics
+    ///
+    /// Panics naming the routes that were never called.
+    #[track_caller]
+    pub fn assert_all_called(&self) {
+        let uncalled: Vec<String> = self
+            .registry
+            .borrow()
+            .routes
+            .iter()
+            .filter(|route| route.requests.borrow().is_empty())
+            .map(|route| format!("{} {}", route.method, route.pattern))
+            .collect();
+        assert!(
+            uncalled.is_empty(),
+            "HTTP mocks never called: {}",
+            uncalled.join(", ")
+        );
+    }
+
+    fn register(&self, route: Route) -> MockRoute {
+        let route = Rc::new(route);
+        self.registry.borrow_mut().routes.push(Rc::clone(&route));
+        MockRoute { route }
+    }
+}
+
+impl fmt::Debug for HttpMock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let registry = self.registry.borrow();
+        f.debug_struct("HttpMock")
+            .field("routes", &registry.routes.len())
+            .field("unmatched", &registry.unmatched.len())
+            .finish()
+    }
+}
+
+impl Drop for HttpMock {
+    fn drop(&mut self) {
+        set_outcall_handler(None);
+        if !std::thread::panicking() {
+            self.assert_no_unmatched();
+        }
+    }
+}
+
+/// A route under construction; finish it with [`respond`](Self::respond).
+#[derive(Debug)]
+#[must_use = "a route is only registered once a response is set"]
+pub struct MockRequest<'a> {
+    mock: &'a HttpMock,
+    method: String,
+    pattern: String,
+}
+
+impl MockRequest<'_> {
+    /// Responds with `status` and `body`.
+    pub fn respond(self, status: u16, body: impl Into<Vec<u8>>) -> MockRoute {
+        self.respond_with(HttpResponse::new(status, body))
+    }
+
+    /// Responds with `status` and `body` serialized as JSON.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `body` cannot be serialized.
+    pub fn respond_json<T: serde::Serialize + ?Sized>(self, status: u16, body: &T) -> MockRoute {
+        let body = serde_json::to_vec(body).expect("mock response body must serialize");
+        let mut response = HttpResponse::new(status, body);
+        response
+            .headers
+            .push(("Content-Type".to_string(), "application/json".to_string()));
+        self.respond_with(response)
+    }
+
+    /// Responds with a fully built response.
+    pub fn respond_with(self, response: HttpResponse) -> MockRoute {
+        self.mock.register(Route {
+            method: self.method,
+            pattern: self.pattern,
+            response,
+            requests: RefCell::new(Vec::new()),
+        })
+    }
+}
+
+/// A registered route, used to assert how often it was called.
+#[derive(Clone)]
+pub struct MockRoute {
+    route: Rc<Route>,
+}
+
+impl MockRoute {
+    /// Returns the number of requests served by this route.
+    #[must_use]
+    pub fn call_count(&self) -> usize {
+        self.route.requests.borrow().len()
+    }
+
+    /// Returns the requests served by this route.
+    #[must_use]
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.route.requests.borrow().clone()
+    }
+
+    /// Asserts the route was called exactly `expected` times.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the call count differs.
+    #[track_caller]
+    pub fn assert_called(&self, expected: usize) {
+        let actual = self.call_count();
+        assert_eq!(
+            actual, expected,
+            "Expected {} {} to be called {expected} time(s), got {actual}",
+            self.route.method, self.route.pattern
+        );
+    }
+
+    /// Asserts the route was called exactly once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the call count is not one.
+    #[track_caller]
+    pub fn assert_called_once(&self) {
+        self.assert_called(1);
+    }
+}
+
+impl fmt::Debug for MockRoute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockRoute")
+            .field("method", &self.route.method)
+            .field("pattern", &self.route.pattern)
+            .field("calls", &self.call_count())
+            .finish()
+    }
+}
+
+fn describe(requests: &[HttpRequest]) -> String {
+    requests
+        .iter()
+        .map(|request| format!("{} {}", request.method, request.url))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Matches `text` against a pattern where `*` matches any sequence.
+fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let text = text.as_bytes();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&b| b == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icarus_core::http;
+
+    #[test]
+    fn test_pattern_matching() {
+        assert!(matches_pattern(
+            "https://api.example.com/*",
+            "https://api.example.com/v1/a"
+        ));
+        assert!(matches_pattern(
+            "*/price?*usd*",
+            "https://x.io/price?ids=btc&vs=usd"
+        ));
+        assert!(!matches_pattern(
+            "https://api.example.com/v1",
+            "https://api.example.com/v2"
+        ));
+        assert!(matches_pattern("exact", "exact"));
+    }
+
+    #[tokio::test]
+    async fn test_matched_request_is_counted() {
+        let mock = mock_http();
+        let route = mock
+            .when("GET", "https://api.example.com/*")
+            .respond(200, "ok");
+
+        let response = http::get("https://api.example.com/status").await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.text().unwrap(), "ok");
+
+        route.assert_called_once();
+        assert_eq!(route.requests()[0].url, "https://api.example.com/status");
+        mock.assert_all_called();
+    }
+
+    #[tokio::test]
+    async fn test_method_must_match() {
+        let mock = mock_http();
+        let route = mock
+            .when("POST", "https://api.example.com/*")
+            .respond(201, "");
+
+        let result = http::get("https://api.example.com/items").await;
+        assert!(result.is_err());
+        route.assert_called(0);
+        assert_eq!(mock.unmatched().len(), 1);
+
+        // Clear the failure so the drop check doesn't fire.
+        mock.registry.borrow_mut().unmatched.clear();
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "Unmatched HTTP requests")]
+    async fn test_unmatched_request_fails_on_drop() {
+        let _mock = mock_http();
+        let _ = http::get("https://unexpected.example.com").await;
+    }
+}
@@ -0,0 +1,21 @@
+//! Mocks for the canister environment.
+//!
+//! Tests run natively, so canister APIs such as the clock and timers are
+//! replaced by thread-local mocks that each test controls explicitly:
+//!
+//! - [`MockEnvironment`] pins the clock and fires timers as time advances
+//!   and sets the caller tools see in their `ToolContext`
+//! - [`mock_http`] serves canned responses to `icarus_core::http` outcalls
+//! - [`mock_signing`] answers `icarus_core::signing` with deterministic keys
+//!   and signatures
+//! - [`mock_rand`] fixes the seed of `icarus_core::rand`
+
+mod environment;
+mod http;
+mod rand;
+mod signing;
+
+pub use environment::{MockEnvironment, TimerId, DEFAULT_START_TIME_NANOS};
+pub use http::{mock_http, HttpMock, MockRequest, MockRoute};
+pub use rand::{mock_rand, RandMock};
+pub use signing::{mock_signing, SigningMock};
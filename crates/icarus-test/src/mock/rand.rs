@@ -0,0 +1,74 @@
+//! Reproducible `icarus_core::rand` draws.
+
+use icarus_core::rand::set_seed;
+use sha2::{Digest, Sha256};
+
+/// Fixes the seed of `icarus_core::rand` for the current thread.
+///
+/// Every run of a test that draws UUIDs, ranges or shuffles with the same
+/// `seed` sees the same values, as a canister does between two reseeds.
+/// Dropping the mock forgets the seed.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use icarus_test::mock::mock_rand;
+///
+/// let _rand = mock_rand(42);
+/// let first = call_tool("create_note", r#"{"text": "hi"}"#);
+///
+/// let _rand = mock_rand(42);
+/// assert_eq!(call_tool("create_note", r#"{"text": "hi"}"#), first);
+/// ```
+#[must_use]
+pub fn mock_rand(seed: u64) -> RandMock {
+    let mock = RandMock;
+    mock.reseed(seed);
+    mock
+}
+
+/// Handle to the seed installed by [`mock_rand`].
+#[derive(Debug)]
+pub struct RandMock;
+
+impl RandMock {
+    /// Restarts the generator from `seed`, as a reseed from `raw_rand` would.
+    pub fn reseed(&self, seed: u64) {
+        set_seed(Some(Sha256::digest(seed.to_le_bytes()).into()));
+    }
+}
+
+impl Drop for RandMock {
+    fn drop(&mut self) {
+        set_seed(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icarus_core::rand;
+
+    fn draws() -> (String, u32, Vec<u8>) {
+        let mut items: Vec<u8> = (0..10).collect();
+        rand::shuffle(&mut items).unwrap();
+        (
+            rand::gen_uuid().unwrap(),
+            rand::gen_range(0..1_000_000).unwrap(),
+            items,
+        )
+    }
+
+    #[test]
+    fn test_same_seed_same_draws() {
+        let mock = mock_rand(42);
+        let first = draws();
+        mock.reseed(42);
+        assert_eq!(draws(), first);
+        mock.reseed(43);
+        assert_ne!(draws(), first);
+
+        drop(mock);
+        assert!(!rand::is_seeded());
+    }
+}
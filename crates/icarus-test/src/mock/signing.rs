@@ -0,0 +1,185 @@
+//! Deterministic threshold ECDSA keys and signatures.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use icarus_core::signing::{set_ecdsa_handler, EcdsaReply, EcdsaRequest, PublicKey};
+use icarus_core::IcarusError;
+use sha2::{Digest, Sha256};
+
+#[derive(Default)]
+struct SigningState {
+    requests: Vec<EcdsaRequest>,
+    failure: Option<String>,
+}
+
+/// Installs a threshold ECDSA mock for the current thread.
+///
+/// `icarus_core::signing` calls are answered with keys and signatures derived
+/// deterministically from the key name, derivation path and hash. They are
+/// not valid secp256k1 values, so tests compare them with
+/// [`SigningMock::public_key`] and [`SigningMock::signature`] rather than
+/// verifying them. Dropping the mock removes the handler.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use icarus_test::mock::mock_signing;
+///
+/// let signer = mock_signing();
+/// let result = call_tool("sign_message", r#"{"message": "hello"}"#);
+/// assert_eq!(signer.signature_count(), 1);
+/// ```
+#[must_use]
+pub fn mock_signing() -> SigningMock {
+    let state = Rc::new(RefCell::new(SigningState::default()));
+
+    let handler_state = Rc::clone(&state);
+    set_ecdsa_handler(Some(Box::new(move |request| {
+        let mut state = handler_state.borrow_mut();
+        state.requests.push(request.clone());
+        if let Some(message) = &state.failure {
+            return Err(IcarusError::ExternalServiceError {
+                service: "threshold ECDSA".to_string(),
+                message: message.clone(),
+            });
+        }
+        Ok(match request {
+            EcdsaRequest::PublicKey {
+                key_name,
+                derivation_path,
+            } => EcdsaReply::PublicKey(SigningMock::public_key(key_name, derivation_path)),
+            EcdsaRequest::Sign {
+                key_name,
+                derivation_path,
+                message_hash,
+            } => EcdsaReply::Signature(SigningMock::signature(
+                key_name,
+                derivation_path,
+                message_hash,
+            )),
+        })
+    })));
+
+    SigningMock { state }
+}
+
+/// Handle to the threshold ECDSA mock installed by [`mock_signing`].
+pub struct SigningMock {
+    state: Rc<RefCell<SigningState>>,
+}
+
+impl SigningMock {
+    /// Returns the key the mock reports for a key name and derivation path.
+    #[must_use]
+    pub fn public_key(key_name: &str, derivation_path: &[Vec<u8>]) -> PublicKey {
+        let seed = seed(key_name, derivation_path);
+        let mut public_key = vec![0x02];
+        public_key.extend_from_slice(&seed);
+        PublicKey {
+            public_key,
+            chain_code: Sha256::digest(seed).to_vec(),
+        }
+    }
+
+    /// Returns the 64-byte signature the mock makes over `message_hash`.
+    #[must_use]
+    pub fn signature(key_name: &str, derivation_path: &[Vec<u8>], message_hash: &[u8]) -> Vec<u8> {
+        let seed = seed(key_name, derivation_path);
+        let r = Sha256::new()
+            .chain_update(seed)
+            .chain_update(message_hash)
+            .finalize();
+        let s = Sha256::new().chain_update(r).chain_update(seed).finalize();
+        [r.as_slice(), s.as_slice()].concat()
+    }
+
+    /// Makes every following call fail with `message`, as when the subnet
+    /// is unavailable or the canister cannot pay the fee.
+    pub fn fail_with(&self, message: &str) {
+        self.state.borrow_mut().failure = Some(message.to_string());
+    }
+
+    /// Returns every call made, in order.
+    #[must_use]
+    pub fn requests(&self) -> Vec<EcdsaRequest> {
+        self.state.borrow().requests.clone()
+    }
+
+    /// Returns the number of signing calls made.
+    #[must_use]
+    pub fn signature_count(&self) -> usize {
+        self.state
+            .borrow()
+            .requests
+            .iter()
+            .filter(|request| matches!(request, EcdsaRequest::Sign { .. }))
+            .count()
+    }
+}
+
+impl fmt::Debug for SigningMock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("SigningMock")
+            .field("requests", &state.requests.len())
+            .field("failure", &state.failure)
+            .finish()
+    }
+}
+
+impl Drop for SigningMock {
+    fn drop(&mut self) {
+        set_ecdsa_handler(None);
+    }
+}
+
+fn seed(key_name: &str, derivation_path: &[Vec<u8>]) -> [u8; 32] {
+    let mut hasher = Sha256::new().chain_update(key_name.as_bytes());
+    for segment in derivation_path {
+        hasher.update((segment.len() as u64).to_le_bytes());
+        hasher.update(segment);
+    }
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icarus_core::signing::{self, DEFAULT_PATH, MAINNET_KEY};
+
+    #[tokio::test]
+    async fn test_mock_keys_and_signatures() {
+        let signer = mock_signing();
+
+        let key = signing::get_public_key(DEFAULT_PATH).await.unwrap();
+        assert_eq!(key, SigningMock::public_key(MAINNET_KEY, &[]));
+        assert_eq!(key.public_key.len(), 33);
+
+        let signature = signing::sign_hash([7; 32], DEFAULT_PATH).await.unwrap();
+        assert_eq!(
+            signature,
+            SigningMock::signature(MAINNET_KEY, &[], &[7; 32])
+        );
+        assert_eq!(signature.len(), 64);
+        assert_ne!(
+            SigningMock::public_key(MAINNET_KEY, &[b"a".to_vec()]),
+            SigningMock::public_key(MAINNET_KEY, &[])
+        );
+
+        assert_eq!(signer.signature_count(), 1);
+        assert_eq!(signer.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failure_is_reported() {
+        let signer = mock_signing();
+        signer.fail_with("subnet unavailable");
+
+        let err = signing::sign_message(b"x", DEFAULT_PATH).await.unwrap_err();
+        assert!(err.to_string().contains("subnet unavailable"));
+        assert_eq!(signer.signature_count(), 1);
+        assert_eq!(signing::signing_stats().signatures, 0);
+    }
+}
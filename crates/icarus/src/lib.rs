@@ -261,6 +261,91 @@
 
 // Re-export all public APIs from core crates
 pub use icarus_core::{
+    // Per-record access grants
+    acl,
+    // Group-by and time-series reports
+    aggregate,
+    // API keys for the HTTP tool API
+    api_keys,
+    // Archiving of stable logs to blobs or another canister
+    archive,
+    // Signed auth state export and import
+    auth_state,
+    // Calendar recurrences in fixed time zones
+    calendar,
+    // Stable logs and vectors
+    collections,
+    // Composite query tools and their inter-canister calls
+    composite,
+    // Rich tool result content
+    content,
+    // Caller and request of the running tool
+    context,
+    // End-to-end encrypted tool arguments
+    encrypted_args,
+    // Stable error codes and catalogs of them
+    error_codes,
+    // Canister-to-canister publish/subscribe
+    events,
+    // Per-user instances spawned from a child canister WASM
+    factory,
+    // Directed graph of nodes and labeled edges
+    graph,
+    // Liveness and readiness reports
+    health,
+    // The canister or native environment tools run in
+    host,
+    // HTTP outcalls
+    http,
+    // JSON tool API through the HTTP gateway
+    http_tools,
+    // Translated tool titles, descriptions and messages
+    i18n,
+    // Ingress message inspection
+    inspect,
+    // Invite codes for joining the auth whitelist
+    invites,
+    // Chunked long-running jobs
+    jobs,
+    // Per-entity locks for async tools
+    locks,
+    // Maintenance mode switch
+    maintenance,
+    // Stable memory layout and canister clock
+    memory,
+    // OpenAPI documents for the HTTP tool API
+    openapi,
+    // Cursor-based pagination of tool results
+    pagination,
+    // Size limits on tool call requests and responses
+    payload_limits,
+    // Random numbers seeded from IC randomness
+    rand,
+    // Read replicas that follow a primary's changes
+    replication,
+    // Scheduled data retention
+    retention,
+    // Custom roles ordered in a hierarchy
+    roles,
+    // Completions from the client's LLM
+    sampling,
+    // Upgrade-safe polling task scheduler
+    scheduler,
+    // Named secrets for outgoing requests
+    secrets,
+    // Threshold ECDSA signatures
+    signing,
+    // Clock, date boundaries and RFC 3339 text
+    time,
+    // Tool declarations for OpenAI and Gemini
+    tool_export,
+    // Owner switches for individual tools
+    tool_switches,
+    // Stable vector index with top-k cosine search
+    vector_index,
+
+    Content,
+
     // Errors
     IcarusError,
     JsonRpcError,
@@ -289,6 +374,14 @@ pub use icarus_core::{
     VERSION,
 };
 
+// Encryption to individual principals with vetKD
+#[cfg(feature = "vetkd")]
+pub use icarus_core::crypto;
+
+// The WASI polyfill's file system, kept across upgrades in stable memory
+#[cfg(feature = "wasi")]
+pub use icarus_core::wasi;
+
 pub use icarus_runtime::{
     execute_tool,
 
@@ -296,15 +389,33 @@ pub use icarus_runtime::{
 
     // Registry operations
     list_tools,
+    // Canister-global state for #[icarus_tools] types
+    state,
     // Runtime errors
     RuntimeError,
     RuntimeResult,
     // Runtime execution
     ToolExecutor,
+    // Checking the linked #[tool] definitions in init and post_upgrade
+    validate_tool_definitions,
 };
 
+// Serving the registered tools over stdio from a native process
+#[cfg(feature = "native")]
+pub use icarus_runtime::native;
+
+// Declares catalogs of stable error codes
+pub use icarus_core::error_catalog;
+
+// Translation bundles for #[tool(i18n = "...")] tools
+pub use icarus_runtime::i18n_bundle;
+
+// Exporting the registered tools as a WASM component for non-IC hosts
+#[cfg(feature = "component")]
+pub use icarus_runtime::component;
+
 // Re-export procedural macros
-pub use icarus_macros::{mcp, tool};
+pub use icarus_macros::{icarus_tools, mcp, roles, tool, IcarusStorable};
 
 /// Prelude module for convenient imports.
 ///
@@ -327,12 +438,15 @@ pub mod prelude {
     pub use crate::{
         // Common execution functions
         execute_tool,
+        icarus_tools,
         list_tools,
 
         mcp,
+        roles,
 
         // Essential macros
         tool,
+        Content,
         // Error types
         IcarusError,
         RuntimeError,
@@ -343,6 +457,15 @@ pub mod prelude {
 
         ToolResult,
     };
+
+    // Injected into `#[tool]` functions that ask for it
+    pub use crate::context::ToolContext;
+
+    // Returned by `#[tool]` functions that ask the client's LLM
+    pub use crate::sampling::{SamplingMessage, SamplingRequest};
+
+    // Returned by the closures of `#[tool(job)]` functions
+    pub use crate::jobs::Chunk;
 }
 
 /// Runtime version of the Icarus CDK.
@@ -0,0 +1,1762 @@
+//! # Data Manager Example
+//!
+//! This example demonstrates a record store that AI agents can create, search,
+//! update and delete records in, with all data kept in stable memory.
+//!
+//! ## Features
+//! - Records persisted in `StableBTreeMap`s, surviving canister upgrades
+//! - Category allowlist and size limits validated on every write
+//! - Substring search with category filtering
+//! - Structured queries (`Eq`/`Contains`/`In`/`Range`/`And`/`Or`/`Not`) with sorting and paging
+//! - Soft delete: deleted records move to a trash and can be restored
+//! - Retention policies for the trash and revision history, enforced daily
+//! - Revision history of every update, with revert to any earlier revision
+//! - Bulk import with a dry-run mode and per-row validation report
+//! - Grouped counts and time series for dashboards via `get_report`
+//! - An async tool that awaits an HTTP outcall under a per-record lock
+//! - Private records encrypted with vetKD that only their creator can decrypt
+//!   (needs the `vetkd` feature of `icarus`)
+//! - Per-record sharing: creators grant other principals read or write access
+//!   with `share_record`, checked through `icarus::acl`
+//!
+//! ## Usage
+//!
+//! ```bash
+//! # Deploy to Internet Computer
+//! dfx start --background
+//! dfx deploy data_manager
+//!
+//! # Create a record
+//! dfx canister call data_manager call_tool '(
+//!   record {
+//!     name = "create_record";
+//!     arguments = "{\"title\": \"Q3 plan\", \"content\": \"...\", \"category\": \"notes\", \"tags\": [\"planning\"]}"
+//!   }
+//! )'
+//!
+//! # Delete it (moves it to the trash)
+//! dfx canister call data_manager call_tool '(
+//!   record {
+//!     name = "delete_record";
+//!     arguments = "{\"id\": 1}"
+//!   }
+//! )'
+//!
+//! # Bring it back
+//! dfx canister call data_manager call_tool '(
+//!   record {
+//!     name = "restore_record";
+//!     arguments = "{\"id\": 1}"
+//!   }
+//! )'
+//! ```
+//!
+//! ## Architecture
+//!
+//! ```text
+//! ┌─────────────────────────────────────┐
+//! │      Data Manager Canister          │
+//! │  ┌───────────────────────────────┐  │
+//! │  │ RECORDS (stable)              │  │
+//! │  │  id → Record                  │  │
+//! │  └──────────────┬────────────────┘  │
+//! │   delete_record │ ▲ restore_record  │
+//! │  ┌──────────────▼─┴──────────────┐  │
+//! │  │ TRASH (stable)                │  │
+//! │  │  id → Record + deleted_at     │  │
+//! │  └──────────────┬────────────────┘  │
+//! │                 │ purge_trash /     │
+//! │                 ▼ retention timer   │
+//! │  ┌───────────────────────────────┐  │
+//! │  │ HISTORY (stable)              │  │
+//! │  │  (id, revision) → changes     │  │
+//! │  └───────────────────────────────┘  │
+//! └─────────────────────────────────────┘
+//! ```
+
+use candid::{CandidType, Decode, Encode};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use icarus::acl::{self, Grant, Permission};
+use icarus::aggregate::{AggregateReport, Aggregator, Metric, TimeBucket};
+use icarus::crypto;
+use icarus::http;
+use icarus::locks::{self, with_entity_lock};
+use icarus::memory::{self, StableMemory, FIRST_USER_MEMORY_ID};
+use icarus::retention::{self, RetentionPolicy};
+use icarus_macros::tool;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::time::Duration;
+
+/// Categories records may be filed under
+const ALLOWED_CATEGORIES: &[&str] = &["notes", "contacts", "tasks", "documents"];
+
+/// Maximum title length in bytes
+const MAX_TITLE_BYTES: usize = 200;
+
+/// Maximum content length in bytes
+const MAX_CONTENT_BYTES: usize = 64 * 1024;
+
+/// Maximum number of tags per record
+const MAX_TAGS: usize = 16;
+
+/// Maximum number of records accepted by one `bulk_import` call
+const MAX_IMPORT_RECORDS: usize = 500;
+
+/// Page size used by `query_records` when none is given
+const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Largest page `query_records` returns
+const MAX_PAGE_SIZE: usize = 200;
+
+/// Record fields that filters and sorting can refer to
+const QUERY_FIELDS: &[&str] = &[
+    "id",
+    "title",
+    "content",
+    "category",
+    "tags",
+    "created_at",
+    "updated_at",
+];
+
+/// Days a deleted record stays in the trash before the scheduled purge
+const TRASH_RETENTION_DAYS: u64 = 30;
+
+/// Revisions kept per record; older ones are trimmed by the retention timer
+const MAX_REVISIONS_PER_RECORD: u64 = 100;
+
+/// How often retention policies are enforced
+const RETENTION_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// A stored record
+#[derive(Debug, Clone, PartialEq, CandidType, Serialize, Deserialize)]
+struct Record {
+    id: u64,
+    title: String,
+    content: String,
+    category: String,
+    tags: Vec<String>,
+    created_at: u64,
+    updated_at: u64,
+    /// `content` is hex-encoded vetKD ciphertext only the creator can decrypt
+    #[serde(default)]
+    is_encrypted: bool,
+}
+
+/// A deleted record waiting in the trash
+#[derive(Debug, Clone, PartialEq, CandidType, Serialize, Deserialize)]
+struct TrashedRecord {
+    record: Record,
+    deleted_at: u64,
+}
+
+/// One field changed by an update, with values as text (tags as JSON)
+#[derive(Debug, Clone, PartialEq, CandidType, Serialize, Deserialize)]
+struct FieldChange {
+    field: String,
+    old: String,
+    new: String,
+}
+
+/// A stored update to a record
+#[derive(Debug, Clone, PartialEq, CandidType, Serialize, Deserialize)]
+struct Revision {
+    /// Revision number, starting at 1 for the first update
+    revision: u64,
+    /// Only the fields that changed
+    changes: Vec<FieldChange>,
+    timestamp: u64,
+    /// Principal of the caller that made the change
+    principal: String,
+}
+
+/// One row of a `bulk_import` payload
+#[derive(Debug, Clone, Deserialize)]
+struct ImportRow {
+    title: String,
+    #[serde(default)]
+    content: String,
+    category: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// A row rejected by `bulk_import`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ImportFailure {
+    /// Zero-based position in the submitted array
+    index: usize,
+    error: String,
+}
+
+/// Outcome of a `bulk_import` call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ImportReport {
+    /// Whether this was a validation-only run
+    dry_run: bool,
+    /// Number of rows submitted
+    total: usize,
+    /// Number of rows that passed validation
+    valid: usize,
+    /// Ids of the created records, in row order (empty for a dry run)
+    imported: Vec<u64>,
+    /// Rows that failed validation
+    failures: Vec<ImportFailure>,
+}
+
+/// A structured filter over record fields
+///
+/// Text fields compare as strings, `id` and the timestamps as numbers. On
+/// `tags`, `eq` and `in` match if any tag matches, and `contains` matches a
+/// substring of any tag.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Filter {
+    /// Field equals `value`
+    Eq {
+        field: String,
+        value: serde_json::Value,
+    },
+    /// Field contains `value` as a case-insensitive substring
+    Contains { field: String, value: String },
+    /// Field equals one of `values`
+    In {
+        field: String,
+        values: Vec<serde_json::Value>,
+    },
+    /// Field lies between `min` and `max`, both inclusive and optional
+    Range {
+        field: String,
+        min: Option<serde_json::Value>,
+        max: Option<serde_json::Value>,
+    },
+    /// All filters match
+    And { filters: Vec<Filter> },
+    /// Any filter matches
+    Or { filters: Vec<Filter> },
+    /// The filter does not match
+    Not { filter: Box<Filter> },
+}
+
+/// Sort order for `query_records`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Sort {
+    field: String,
+    #[serde(default)]
+    descending: bool,
+}
+
+/// Page selection for `query_records`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Page {
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+/// One page of `query_records` results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct QueryResult {
+    records: Vec<Record>,
+    /// Number of matching records across all pages
+    total: usize,
+    /// Offset of the next page, if there is one
+    next_offset: Option<usize>,
+}
+
+impl Filter {
+    /// Rejects unknown field names before any record is scanned.
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Eq { field, .. }
+            | Self::Contains { field, .. }
+            | Self::In { field, .. }
+            | Self::Range { field, .. } => check_field(field),
+            Self::And { filters } | Self::Or { filters } => {
+                filters.iter().try_for_each(Self::validate)
+            }
+            Self::Not { filter } => filter.validate(),
+        }
+    }
+
+    /// Returns the inclusive id range outside of which the filter cannot
+    /// match, so the scan can use the record map's key order.
+    fn id_bounds(&self) -> (u64, u64) {
+        match self {
+            Self::Eq { field, value } if field == "id" => {
+                value.as_u64().map_or((1, 0), |id| (id, id))
+            }
+            Self::Range { field, min, max } if field == "id" => (
+                min.as_ref()
+                    .and_then(serde_json::Value::as_u64)
+                    .unwrap_or(0),
+                max.as_ref()
+                    .and_then(serde_json::Value::as_u64)
+                    .unwrap_or(u64::MAX),
+            ),
+            Self::And { filters } => filters
+                .iter()
+                .map(Self::id_bounds)
+                .fold((0, u64::MAX), |(low, high), (min, max)| {
+                    (low.max(min), high.min(max))
+                }),
+            _ => (0, u64::MAX),
+        }
+    }
+
+    fn matches(&self, record: &Record) -> bool {
+        match self {
+            Self::Eq { field, value } => field_values(record, field)
+                .iter()
+                .any(|candidate| compare(candidate, value) == Some(Ordering::Equal)),
+            Self::Contains { field, value } => {
+                let needle = value.to_lowercase();
+                field_values(record, field).iter().any(|candidate| {
+                    candidate
+                        .as_str()
+                        .is_some_and(|text| text.to_lowercase().contains(&needle))
+                })
+            }
+            Self::In { field, values } => field_values(record, field).iter().any(|candidate| {
+                values
+                    .iter()
+                    .any(|value| compare(candidate, value) == Some(Ordering::Equal))
+            }),
+            Self::Range { field, min, max } => {
+                field_values(record, field).iter().any(|candidate| {
+                    let above = min.as_ref().map_or(true, |min| {
+                        compare(candidate, min).is_some_and(Ordering::is_ge)
+                    });
+                    let below = max.as_ref().map_or(true, |max| {
+                        compare(candidate, max).is_some_and(Ordering::is_le)
+                    });
+                    above && below
+                })
+            }
+            Self::And { filters } => filters.iter().all(|filter| filter.matches(record)),
+            Self::Or { filters } => filters.iter().any(|filter| filter.matches(record)),
+            Self::Not { filter } => !filter.matches(record),
+        }
+    }
+}
+
+/// Search criteria for `search_records`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SearchQuery {
+    /// Case-insensitive substring matched against title, content and tags
+    text: Option<String>,
+    /// Only return records in this category
+    category: Option<String>,
+    /// Maximum number of results (default 50)
+    limit: Option<usize>,
+}
+
+impl Storable for Record {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("Failed to encode record"))
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).expect("Failed to decode record")
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).expect("Failed to encode record")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for TrashedRecord {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("Failed to encode trashed record"))
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).expect("Failed to decode trashed record")
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).expect("Failed to encode trashed record")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for Revision {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("Failed to encode revision"))
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).expect("Failed to decode revision")
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).expect("Failed to encode revision")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Stable state (persists across canister upgrades)
+thread_local! {
+    /// Live records by id
+    static RECORDS: RefCell<StableBTreeMap<u64, Record, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(FIRST_USER_MEMORY_ID))
+    );
+
+    /// Soft-deleted records by id
+    static TRASH: RefCell<StableBTreeMap<u64, TrashedRecord, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(FIRST_USER_MEMORY_ID + 1))
+    );
+
+    /// Revisions by (record id, revision number)
+    static HISTORY: RefCell<StableBTreeMap<(u64, u64), Revision, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(FIRST_USER_MEMORY_ID + 2))
+    );
+}
+
+/// Returns the next unused id, counting records in the trash as used.
+fn next_id() -> u64 {
+    let last_record = RECORDS.with(|records| records.borrow().last_key_value().map(|(id, _)| id));
+    let last_trashed = TRASH.with(|trash| trash.borrow().last_key_value().map(|(id, _)| id));
+    last_record.max(last_trashed).unwrap_or(0) + 1
+}
+
+/// Checks a record's fields against the category allowlist and size limits.
+fn validate(title: &str, content: &str, category: &str, tags: &[String]) -> Result<(), String> {
+    if title.trim().is_empty() {
+        return Err("Title cannot be empty".to_string());
+    }
+    if title.len() > MAX_TITLE_BYTES {
+        return Err(format!("Title exceeds {MAX_TITLE_BYTES} bytes"));
+    }
+    if content.len() > MAX_CONTENT_BYTES {
+        return Err(format!("Content exceeds {MAX_CONTENT_BYTES} bytes"));
+    }
+    if !ALLOWED_CATEGORIES.contains(&category) {
+        return Err(format!(
+            "Unknown category '{category}', expected one of: {}",
+            ALLOWED_CATEGORIES.join(", ")
+        ));
+    }
+    if tags.len() > MAX_TAGS {
+        return Err(format!("At most {MAX_TAGS} tags are allowed"));
+    }
+    Ok(())
+}
+
+/// ACL resource id of a record.
+fn resource_id(id: u64) -> String {
+    format!("record:{id}")
+}
+
+/// Checks that the caller holds `permission` on record `id`.
+fn require(id: u64, permission: Permission) -> Result<(), String> {
+    acl::require_permission(&resource_id(id), permission).map_err(|e| e.to_string())
+}
+
+/// Whether the caller may see record `id`.
+fn can_read(id: u64) -> bool {
+    require(id, Permission::Read).is_ok()
+}
+
+/// Gives the caller every permission on a record it just created.
+fn grant_creator(id: u64) {
+    acl::grant(
+        &resource_id(id),
+        icarus::context::caller(),
+        &Permission::ALL,
+    )
+    .expect("record resource ids are valid");
+}
+
+/// Returns the principal of the current caller as text.
+fn caller() -> String {
+    icarus::context::caller().to_text()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>, String> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return Err("Invalid hex string".to_string());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| "Invalid hex string".to_string())
+        })
+        .collect()
+}
+
+/// Key of a record's lock; see [`append_from_url`].
+fn lock_key(id: u64) -> String {
+    format!("record:{id}")
+}
+
+/// Lists the fields that differ between two versions of a record.
+fn diff(old: &Record, new: &Record) -> Vec<FieldChange> {
+    let tags = |tags: &[String]| serde_json::to_string(tags).expect("Failed to serialize tags");
+    [
+        ("title", old.title.clone(), new.title.clone()),
+        ("content", old.content.clone(), new.content.clone()),
+        ("category", old.category.clone(), new.category.clone()),
+        ("tags", tags(&old.tags), tags(&new.tags)),
+    ]
+    .into_iter()
+    .filter(|(_, old, new)| old != new)
+    .map(|(field, old, new)| FieldChange {
+        field: field.to_string(),
+        old,
+        new,
+    })
+    .collect()
+}
+
+/// Sets a field from its text value as stored in a [`FieldChange`].
+fn set_field(record: &mut Record, field: &str, value: &str) {
+    match field {
+        "title" => record.title = value.to_string(),
+        "content" => record.content = value.to_string(),
+        "category" => record.category = value.to_string(),
+        "tags" => record.tags = serde_json::from_str(value).expect("Failed to parse stored tags"),
+        _ => {}
+    }
+}
+
+/// Returns the revisions of a record, oldest first.
+fn revisions(id: u64) -> Vec<Revision> {
+    HISTORY.with(|history| {
+        history
+            .borrow()
+            .range((id, 0)..=(id, u64::MAX))
+            .map(|entry| entry.value())
+            .collect()
+    })
+}
+
+/// Validates and stores an updated record, recording the changed fields as
+/// a new revision. Saving an unchanged record records nothing.
+fn save_revision(old: &Record, mut new: Record) -> Result<Record, String> {
+    validate(&new.title, &new.content, &new.category, &new.tags)?;
+
+    let changes = diff(old, &new);
+    if changes.is_empty() {
+        return Ok(new);
+    }
+
+    let id = new.id;
+    let revision = revisions(id).last().map_or(0, |last| last.revision) + 1;
+    new.updated_at = icarus::time::now_nanos();
+    HISTORY.with(|history| {
+        history.borrow_mut().insert(
+            (id, revision),
+            Revision {
+                revision,
+                changes,
+                timestamp: new.updated_at,
+                principal: caller(),
+            },
+        )
+    });
+    RECORDS.with(|records| records.borrow_mut().insert(id, new.clone()));
+    Ok(new)
+}
+
+fn check_field(field: &str) -> Result<(), String> {
+    if QUERY_FIELDS.contains(&field) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unknown field '{field}', expected one of: {}",
+            QUERY_FIELDS.join(", ")
+        ))
+    }
+}
+
+/// Returns a field's values for filtering: one value, or one per tag.
+fn field_values(record: &Record, field: &str) -> Vec<serde_json::Value> {
+    use serde_json::Value;
+
+    match field {
+        "id" => vec![Value::from(record.id)],
+        "title" => vec![Value::from(record.title.as_str())],
+        "content" => vec![Value::from(record.content.as_str())],
+        "category" => vec![Value::from(record.category.as_str())],
+        "tags" => record
+            .tags
+            .iter()
+            .map(|tag| Value::from(tag.as_str()))
+            .collect(),
+        "created_at" => vec![Value::from(record.created_at)],
+        "updated_at" => vec![Value::from(record.updated_at)],
+        _ => Vec::new(),
+    }
+}
+
+/// Compares two numbers or two strings; other combinations are unordered.
+fn compare(left: &serde_json::Value, right: &serde_json::Value) -> Option<Ordering> {
+    if let (Some(left), Some(right)) = (left.as_u64(), right.as_u64()) {
+        return Some(left.cmp(&right));
+    }
+    if let (Some(left), Some(right)) = (left.as_f64(), right.as_f64()) {
+        return left.partial_cmp(&right);
+    }
+    Some(left.as_str()?.cmp(right.as_str()?))
+}
+
+/// Create a new record.
+///
+/// # Parameters
+/// - `title`: Short title (required)
+/// - `content`: Record body
+/// - `category`: One of `notes`, `contacts`, `tasks`, `documents`
+/// - `tags`: Free-form labels
+///
+/// # Returns
+/// The stored record with its assigned id
+///
+/// # Example
+/// ```json
+/// {
+///   "title": "Q3 plan",
+///   "content": "Ship the importer",
+///   "category": "notes",
+///   "tags": ["planning"]
+/// }
+/// ```
+#[tool("Create a new record")]
+fn create_record(
+    title: String,
+    content: String,
+    category: String,
+    tags: Vec<String>,
+) -> Result<Record, String> {
+    validate(&title, &content, &category, &tags)?;
+
+    let now = icarus::time::now_nanos();
+    let record = Record {
+        id: next_id(),
+        title,
+        content,
+        category,
+        tags,
+        created_at: now,
+        updated_at: now,
+        is_encrypted: false,
+    };
+    RECORDS.with(|records| records.borrow_mut().insert(record.id, record.clone()));
+    grant_creator(record.id);
+    Ok(record)
+}
+
+/// Create a record whose content only the caller can read.
+///
+/// The content is encrypted with vetKD to the caller's principal before it is
+/// stored, so neither other callers nor the canister can read it afterwards.
+/// The title, category and tags stay searchable in the clear.
+///
+/// To read the content, the client generates a transport key pair, calls
+/// `get_decryption_key` with the public half, decrypts the returned vetKey
+/// with the private half (verifying it against `get_encryption_public_key`)
+/// and decrypts the content with it, e.g. with `@dfinity/vetkeys`.
+///
+/// # Parameters
+/// - `title`, `category`, `tags`: As for `create_record`
+/// - `content`: Plain text to encrypt
+///
+/// # Returns
+/// The record with `is_encrypted: true` and hex-encoded ciphertext as content
+///
+/// # Example
+/// ```json
+/// {
+///   "title": "Bank details",
+///   "content": "IBAN ...",
+///   "category": "documents",
+///   "tags": ["private"]
+/// }
+/// ```
+#[tool("Create a record whose content only you can decrypt")]
+async fn create_encrypted_record(
+    title: String,
+    content: String,
+    category: String,
+    tags: Vec<String>,
+) -> Result<Record, String> {
+    validate(&title, &content, &category, &tags)?;
+    let ciphertext = crypto::encrypt_for_caller(content.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let now = icarus::time::now_nanos();
+    let record = Record {
+        id: next_id(),
+        title,
+        content: to_hex(&ciphertext),
+        category,
+        tags,
+        created_at: now,
+        updated_at: now,
+        is_encrypted: true,
+    };
+    RECORDS.with(|records| records.borrow_mut().insert(record.id, record.clone()));
+    grant_creator(record.id);
+    Ok(record)
+}
+
+/// Get the canister's vetKD public key, hex-encoded, for verifying the keys
+/// returned by `get_decryption_key`.
+#[tool("Get the public key for verifying decryption keys")]
+async fn get_encryption_public_key() -> Result<String, String> {
+    crypto::public_key()
+        .await
+        .map(|key| to_hex(&key))
+        .map_err(|e| e.to_string())
+}
+
+/// Get the caller's decryption key for encrypted records.
+///
+/// # Parameters
+/// - `transport_public_key`: Hex-encoded public key the returned key is
+///   encrypted to; the canister never sees the key in the clear
+///
+/// # Returns
+/// The caller's vetKey, encrypted to the transport key and hex-encoded
+#[tool("Get your key for decrypting encrypted records")]
+async fn get_decryption_key(transport_public_key: String) -> Result<String, String> {
+    let transport_public_key = from_hex(&transport_public_key)?;
+    crypto::request_decryption_key(transport_public_key)
+        .await
+        .map(|key| to_hex(&key))
+        .map_err(|e| e.to_string())
+}
+
+/// Import many records in one call.
+///
+/// Every row is validated like `create_record`. Valid rows are inserted
+/// together within this one update call, so either all of them are stored or,
+/// if the call traps, none are; invalid rows are skipped and reported.
+///
+/// # Parameters
+/// - `records_json`: JSON array of `{title, content?, category, tags?}` objects,
+///   at most `MAX_IMPORT_RECORDS` long
+/// - `dry_run`: Only validate and report, without storing anything
+///
+/// # Returns
+/// A report with the created ids and the reason each rejected row failed
+///
+/// # Example
+/// ```json
+/// {
+///   "records_json": "[{\"title\": \"Alice\", \"category\": \"contacts\"}, {\"title\": \"\", \"category\": \"notes\"}]",
+///   "dry_run": false
+/// }
+/// ```
+/// Returns: `{"dry_run": false, "total": 2, "valid": 1, "imported": [7], "failures": [{"index": 1, "error": "Title cannot be empty"}]}`
+#[tool("Import many records at once, with a validation report")]
+fn bulk_import(records_json: String, dry_run: bool) -> Result<ImportReport, String> {
+    let rows: Vec<serde_json::Value> =
+        serde_json::from_str(&records_json).map_err(|e| format!("Invalid JSON array: {e}"))?;
+    if rows.len() > MAX_IMPORT_RECORDS {
+        return Err(format!(
+            "{} records submitted, at most {MAX_IMPORT_RECORDS} are allowed per call",
+            rows.len()
+        ));
+    }
+
+    // Validate everything before writing anything
+    let mut valid = Vec::new();
+    let mut failures = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        let checked = serde_json::from_value::<ImportRow>(row.clone())
+            .map_err(|e| format!("Invalid record: {e}"))
+            .and_then(|row| {
+                validate(&row.title, &row.content, &row.category, &row.tags).map(|()| row)
+            });
+        match checked {
+            Ok(row) => valid.push(row),
+            Err(error) => failures.push(ImportFailure { index, error }),
+        }
+    }
+
+    let mut report = ImportReport {
+        dry_run,
+        total: rows.len(),
+        valid: valid.len(),
+        imported: Vec::new(),
+        failures,
+    };
+    if dry_run {
+        return Ok(report);
+    }
+
+    let now = icarus::time::now_nanos();
+    let first_id = next_id();
+    RECORDS.with(|records| {
+        let mut records = records.borrow_mut();
+        for (id, row) in (first_id..).zip(valid) {
+            records.insert(
+                id,
+                Record {
+                    id,
+                    title: row.title,
+                    content: row.content,
+                    category: row.category,
+                    tags: row.tags,
+                    created_at: now,
+                    updated_at: now,
+                    is_encrypted: false,
+                },
+            );
+            report.imported.push(id);
+        }
+    });
+    for id in &report.imported {
+        grant_creator(*id);
+    }
+    Ok(report)
+}
+
+/// Get a record by id.
+///
+/// # Parameters
+/// - `id`: The record id
+///
+/// # Returns
+/// The record, or `null` if it doesn't exist, is in the trash or was not
+/// shared with the caller
+///
+/// # Example
+/// ```json
+/// {
+///   "id": 1
+/// }
+/// ```
+#[tool("Get a record by id")]
+fn get_record(id: u64) -> Option<Record> {
+    if !can_read(id) {
+        return None;
+    }
+    RECORDS.with(|records| records.borrow().get(&id))
+}
+
+/// Search records by text and category.
+///
+/// # Parameters
+/// - `query`: Search criteria; all fields are optional
+///
+/// # Returns
+/// Matching records readable by the caller, in id order
+///
+/// # Example
+/// ```json
+/// {
+///   "query": { "text": "plan", "category": "notes", "limit": 10 }
+/// }
+/// ```
+#[tool("Search records by text and category")]
+fn search_records(query: SearchQuery) -> Vec<Record> {
+    let text = query.text.map(|text| text.to_lowercase());
+    let limit = query.limit.unwrap_or(50);
+
+    RECORDS.with(|records| {
+        records
+            .borrow()
+            .iter()
+            .map(|entry| entry.value())
+            .filter(|record| can_read(record.id))
+            .filter(|record| {
+                query
+                    .category
+                    .as_ref()
+                    .map_or(true, |category| &record.category == category)
+            })
+            .filter(|record| {
+                text.as_ref().map_or(true, |text| {
+                    record.title.to_lowercase().contains(text)
+                        || record.content.to_lowercase().contains(text)
+                        || record
+                            .tags
+                            .iter()
+                            .any(|tag| tag.to_lowercase().contains(text))
+                })
+            })
+            .take(limit)
+            .collect()
+    })
+}
+
+/// Query records with a structured filter, sorting and paging.
+///
+/// # Parameters
+/// - `filter`: Filter tree; omit to match every record
+/// - `sort`: Field and direction to sort by; defaults to ascending `id`
+/// - `page`: `offset` and `limit` (default 50, max 200)
+///
+/// # Returns
+/// The requested page, the total match count and the next page's offset
+///
+/// # Example
+/// ```json
+/// {
+///   "filter": {
+///     "op": "and",
+///     "filters": [
+///       { "op": "in", "field": "category", "values": ["notes", "tasks"] },
+///       { "op": "not", "filter": { "op": "eq", "field": "tags", "value": "archived" } },
+///       { "op": "range", "field": "updated_at", "min": 1700000000000000000 }
+///     ]
+///   },
+///   "sort": { "field": "updated_at", "descending": true },
+///   "page": { "offset": 0, "limit": 20 }
+/// }
+/// ```
+#[tool("Query records with structured filters, sorting and paging")]
+fn query_records(
+    filter: Option<Filter>,
+    sort: Option<Sort>,
+    page: Option<Page>,
+) -> Result<QueryResult, String> {
+    if let Some(filter) = &filter {
+        filter.validate()?;
+    }
+    if let Some(sort) = &sort {
+        check_field(&sort.field)?;
+    }
+    let page = page.unwrap_or_default();
+    let limit = page.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+
+    let (low, high) = filter.as_ref().map_or((0, u64::MAX), Filter::id_bounds);
+    let mut matches: Vec<Record> = if low > high {
+        Vec::new()
+    } else {
+        RECORDS.with(|records| {
+            records
+                .borrow()
+                .range(low..=high)
+                .map(|entry| entry.value())
+                .filter(|record| can_read(record.id))
+                .filter(|record| {
+                    filter
+                        .as_ref()
+                        .map_or(true, |filter| filter.matches(record))
+                })
+                .collect()
+        })
+    };
+
+    // Records come out of the map in id order, which is the default sort
+    if let Some(sort) = sort {
+        matches.sort_by(|left, right| {
+            let left = field_values(left, &sort.field);
+            let right = field_values(right, &sort.field);
+            let order = match (left.first(), right.first()) {
+                (Some(left), Some(right)) => compare(left, right).unwrap_or(Ordering::Equal),
+                (left, right) => left.is_some().cmp(&right.is_some()),
+            };
+            if sort.descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+    }
+
+    let total = matches.len();
+    let records: Vec<Record> = matches.into_iter().skip(page.offset).take(limit).collect();
+    let end = page.offset + records.len();
+    Ok(QueryResult {
+        records,
+        total,
+        next_offset: (end < total).then_some(end),
+    })
+}
+
+/// Report record counts or content sizes by group and over time.
+///
+/// # Parameters
+/// - `group_by`: `category`, `tags` (a record counts once per tag) or `none`
+/// - `metric`: `count` (default), or `sum`/`average`/`min`/`max` of content
+///   length in bytes
+/// - `time_bucket`: `hour`, `day` or `week` to add a series by creation time
+///
+/// # Returns
+/// Per-group values sorted by key and, with a bucket, a per-bucket series
+///
+/// # Example
+/// ```json
+/// {
+///   "group_by": "category",
+///   "time_bucket": "day"
+/// }
+/// ```
+/// Returns: `{"metric": "count", "total": 12, "groups": [{"key": "notes", "count": 9, "value": 9.0}, ...], "series": [...]}`
+#[tool("Report grouped counts and time series of records")]
+fn get_report(
+    group_by: String,
+    metric: Option<Metric>,
+    time_bucket: Option<TimeBucket>,
+) -> Result<AggregateReport, String> {
+    let mut aggregator = match group_by.as_str() {
+        "category" => Aggregator::new().group_by(|record: &Record| vec![record.category.clone()]),
+        "tags" => Aggregator::new().group_by(|record: &Record| record.tags.clone()),
+        "none" => Aggregator::new(),
+        _ => {
+            return Err(format!(
+                "Unknown group_by '{group_by}', expected category, tags or none"
+            ))
+        }
+    };
+    if let Some(metric) = metric.filter(|metric| *metric != Metric::Count) {
+        aggregator = aggregator.metric(metric, |record| record.content.len() as f64);
+    }
+    if let Some(bucket) = time_bucket {
+        aggregator = aggregator.time_series(|record| record.created_at, bucket);
+    }
+
+    Ok(RECORDS.with(|records| {
+        aggregator.run(
+            records
+                .borrow()
+                .iter()
+                .map(|entry| entry.value())
+                .filter(|record| can_read(record.id)),
+        )
+    }))
+}
+
+/// Update fields of an existing record.
+///
+/// # Parameters
+/// - `id`: The record id
+/// - `title`, `content`, `category`, `tags`: New values; omitted fields are kept
+///
+/// # Returns
+/// The updated record. The changed fields are kept as a new revision.
+///
+/// # Example
+/// ```json
+/// {
+///   "id": 1,
+///   "content": "Ship the importer and the exporter"
+/// }
+/// ```
+#[tool("Update fields of an existing record")]
+fn update_record(
+    id: u64,
+    title: Option<String>,
+    content: Option<String>,
+    category: Option<String>,
+    tags: Option<Vec<String>>,
+) -> Result<Record, String> {
+    // A synchronous tool cannot wait for the lock, so it refuses instead of
+    // changing a record that an async tool is about to write back
+    if locks::is_locked(lock_key(id)) {
+        return Err(format!("Record {id} is being updated, try again"));
+    }
+    require(id, Permission::Write)?;
+    let old = get_record(id).ok_or_else(|| format!("Record {id} not found"))?;
+    let mut record = old.clone();
+
+    if let Some(title) = title {
+        record.title = title;
+    }
+    if let Some(content) = content {
+        if old.is_encrypted {
+            return Err(format!(
+                "Record {id} is encrypted; create a new encrypted record instead"
+            ));
+        }
+        record.content = content;
+    }
+    if let Some(category) = category {
+        record.category = category;
+    }
+    if let Some(tags) = tags {
+        record.tags = tags;
+    }
+    save_revision(&old, record)
+}
+
+/// Append the text of a web page to a record's content.
+///
+/// The record is read before the HTTP outcall and written after it. Other
+/// calls run while the outcall is in flight, so without the lock two appends
+/// to the same record would both start from the old content and the first
+/// one would be lost. `with_entity_lock` makes the second call wait at the
+/// lock until the first has saved; appends to other records are not held up.
+///
+/// # Parameters
+/// - `id`: The record id
+/// - `url`: Page whose body is appended as text
+///
+/// # Example
+/// ```json
+/// {
+///   "id": 1,
+///   "url": "https://example.com/notes.txt"
+/// }
+/// ```
+#[tool("Append the text of a web page to a record")]
+async fn append_from_url(id: u64, url: String) -> Result<Record, String> {
+    require(id, Permission::Write)?;
+    with_entity_lock(lock_key(id), async {
+        let old = get_record(id).ok_or_else(|| format!("Record {id} not found"))?;
+        if old.is_encrypted {
+            return Err(format!(
+                "Record {id} is encrypted and cannot be appended to"
+            ));
+        }
+        let response = http::get(&url).await.map_err(|e| e.to_string())?;
+        let text = response.text().map_err(|e| e.to_string())?;
+
+        let mut record = old.clone();
+        if !record.content.is_empty() {
+            record.content.push_str("\n\n");
+        }
+        record.content.push_str(&text);
+        save_revision(&old, record)
+    })
+    .await
+}
+
+/// Get the revision history of a record.
+///
+/// # Parameters
+/// - `id`: The record id
+/// - `limit`: Maximum number of revisions to return
+///
+/// # Returns
+/// Revisions newest first, each with the changed fields, timestamp and caller
+///
+/// # Example
+/// ```json
+/// {
+///   "id": 1,
+///   "limit": 5
+/// }
+/// ```
+/// Returns: `[{"revision": 2, "changes": [{"field": "title", "old": "Q3", "new": "Q3 plan"}], ...}]`
+#[tool("Get the revision history of a record")]
+fn get_record_history(id: u64, limit: usize) -> Vec<Revision> {
+    if !can_read(id) {
+        return Vec::new();
+    }
+    let mut history = revisions(id);
+    history.reverse();
+    history.truncate(limit);
+    history
+}
+
+/// Revert a record to how it was after `revision`.
+///
+/// Later changes are undone field by field. The revert is itself recorded as
+/// a new revision, so it can be undone too.
+///
+/// # Parameters
+/// - `id`: The record id
+/// - `revision`: Revision to go back to; `0` is the record as created
+///
+/// # Returns
+/// The reverted record
+///
+/// # Example
+/// ```json
+/// {
+///   "id": 1,
+///   "revision": 2
+/// }
+/// ```
+#[tool("Revert a record to an earlier revision")]
+fn revert_record(id: u64, revision: u64) -> Result<Record, String> {
+    require(id, Permission::Write)?;
+    let old = get_record(id).ok_or_else(|| format!("Record {id} not found"))?;
+    let history = revisions(id);
+    let latest = history.last().map_or(0, |last| last.revision);
+    if revision > latest {
+        return Err(format!(
+            "Record {id} has no revision {revision} (latest is {latest})"
+        ));
+    }
+
+    let mut record = old.clone();
+    for undone in history.iter().rev().take_while(|r| r.revision > revision) {
+        for change in &undone.changes {
+            set_field(&mut record, &change.field, &change.old);
+        }
+    }
+    save_revision(&old, record)
+}
+
+/// Delete a record by moving it to the trash.
+///
+/// Trashed records can be brought back with `restore_record` until they are
+/// purged, either by `purge_trash` or by the daily purge after
+/// `TRASH_RETENTION_DAYS` days.
+///
+/// # Parameters
+/// - `id`: The record id
+///
+/// # Returns
+/// The deletion timestamp in nanoseconds
+///
+/// # Example
+/// ```json
+/// {
+///   "id": 1
+/// }
+/// ```
+#[tool("Delete a record (moves it to the trash)")]
+fn delete_record(id: u64) -> Result<u64, String> {
+    require(id, Permission::Write)?;
+    let record = RECORDS
+        .with(|records| records.borrow_mut().remove(&id))
+        .ok_or_else(|| format!("Record {id} not found"))?;
+
+    let deleted_at = icarus::time::now_nanos();
+    TRASH.with(|trash| {
+        trash
+            .borrow_mut()
+            .insert(id, TrashedRecord { record, deleted_at })
+    });
+    Ok(deleted_at)
+}
+
+/// List records in the trash.
+///
+/// # Returns
+/// Trashed records with their deletion timestamps, in id order
+///
+/// # Example
+/// ```json
+/// {}
+/// ```
+#[tool("List records in the trash")]
+fn list_trash() -> Vec<TrashedRecord> {
+    TRASH.with(|trash| {
+        trash
+            .borrow()
+            .iter()
+            .map(|entry| entry.value())
+            .filter(|entry| can_read(entry.record.id))
+            .collect()
+    })
+}
+
+/// Restore a record from the trash.
+///
+/// # Parameters
+/// - `id`: The id of the deleted record
+///
+/// # Returns
+/// The restored record
+///
+/// # Example
+/// ```json
+/// {
+///   "id": 1
+/// }
+/// ```
+#[tool("Restore a deleted record from the trash")]
+fn restore_record(id: u64) -> Result<Record, String> {
+    require(id, Permission::Write)?;
+    let entry = TRASH
+        .with(|trash| trash.borrow_mut().remove(&id))
+        .ok_or_else(|| format!("Record {id} is not in the trash"))?;
+
+    RECORDS.with(|records| records.borrow_mut().insert(id, entry.record.clone()));
+    Ok(entry.record)
+}
+
+/// Share a record with another principal.
+///
+/// Replaces any permissions the principal already had on the record; `Read`
+/// is always included. Only principals holding `Share` on the record (its
+/// creator, unless shared onward) and admins may share it.
+///
+/// # Parameters
+/// - `id`: The record id
+/// - `principal`: Principal to share with, in text form
+/// - `permissions`: Any of `Read`, `Write` and `Share`; empty grants `Read`
+///
+/// # Returns
+/// Everyone's permissions on the record after the change
+///
+/// # Example
+/// ```json
+/// {
+///   "id": 1,
+///   "principal": "2vxsx-fae",
+///   "permissions": ["Read"]
+/// }
+/// ```
+#[tool("Share a record with another principal")]
+fn share_record(
+    id: u64,
+    principal: String,
+    permissions: Vec<Permission>,
+) -> Result<Vec<Grant>, String> {
+    require(id, Permission::Share)?;
+    if !RECORDS.with(|records| records.borrow().contains_key(&id)) {
+        return Err(format!("Record {id} not found"));
+    }
+    let principal = candid::Principal::from_text(&principal)
+        .map_err(|e| format!("Invalid principal '{principal}': {e}"))?;
+
+    let mut permissions = permissions;
+    if !permissions.contains(&Permission::Read) {
+        permissions.push(Permission::Read);
+    }
+    acl::grant(&resource_id(id), principal, &permissions).map_err(|e| e.to_string())?;
+    Ok(acl::grants(&resource_id(id)))
+}
+
+/// Stop sharing a record with a principal.
+///
+/// # Parameters
+/// - `id`: The record id
+/// - `principal`: Principal to remove, in text form
+///
+/// # Returns
+/// Whether the principal had any access
+///
+/// # Example
+/// ```json
+/// {
+///   "id": 1,
+///   "principal": "2vxsx-fae"
+/// }
+/// ```
+#[tool("Stop sharing a record with a principal")]
+fn revoke_share(id: u64, principal: String) -> Result<bool, String> {
+    require(id, Permission::Share)?;
+    let principal = candid::Principal::from_text(&principal)
+        .map_err(|e| format!("Invalid principal '{principal}': {e}"))?;
+    Ok(acl::revoke(&resource_id(id), &principal))
+}
+
+/// List who a record is shared with.
+///
+/// # Parameters
+/// - `id`: The record id
+///
+/// # Returns
+/// Each principal with access and its permissions
+///
+/// # Example
+/// ```json
+/// {
+///   "id": 1
+/// }
+/// ```
+#[tool("List who a record is shared with")]
+fn list_shares(id: u64) -> Result<Vec<Grant>, String> {
+    require(id, Permission::Share)?;
+    Ok(acl::grants(&resource_id(id)))
+}
+
+/// Permanently delete trash entries older than `older_than_days` days.
+///
+/// Only records the caller holds `Write` on are purged; the rest stay in the
+/// trash for their owners or the daily purge.
+///
+/// # Parameters
+/// - `older_than_days`: Minimum age in days; `0` empties the trash
+///
+/// # Returns
+/// The number of records purged
+///
+/// # Example
+/// ```json
+/// {
+///   "older_than_days": 7
+/// }
+/// ```
+#[tool("Permanently delete old records from the trash")]
+fn purge_trash(older_than_days: u64) -> u64 {
+    purge_trash_where(
+        icarus::time::now_nanos().saturating_sub(older_than_days.saturating_mul(NANOS_PER_DAY)),
+        |id| require(id, Permission::Write).is_ok(),
+    )
+}
+
+/// Permanently deletes every record trashed at or before `cutoff`, with its
+/// history. Not a tool: the retention timer runs it for all owners.
+fn purge_trash_before(cutoff: u64) -> u64 {
+    purge_trash_where(cutoff, |_| true)
+}
+
+/// Permanently deletes the records trashed at or before `cutoff` that
+/// `allowed` accepts, with their history.
+fn purge_trash_where(cutoff: u64, allowed: impl Fn(u64) -> bool) -> u64 {
+    TRASH.with(|trash| {
+        let mut trash = trash.borrow_mut();
+        let expired: Vec<u64> = trash
+            .iter()
+            .filter(|entry| entry.value().deleted_at <= cutoff)
+            .map(|entry| *entry.key())
+            .filter(|id| allowed(*id))
+            .collect();
+        for id in &expired {
+            trash.remove(id);
+            acl::revoke_all(&resource_id(*id));
+            HISTORY.with(|history| {
+                let mut history = history.borrow_mut();
+                let revisions: Vec<(u64, u64)> = history
+                    .range((*id, 0)..=(*id, u64::MAX))
+                    .map(|entry| *entry.key())
+                    .collect();
+                for key in revisions {
+                    history.remove(&key);
+                }
+            });
+        }
+        expired.len() as u64
+    })
+}
+
+/// Deletes all but the newest `max` revisions of every record.
+fn trim_history(max: u64) -> u64 {
+    HISTORY.with(|history| {
+        let mut history = history.borrow_mut();
+        let mut counts: std::collections::BTreeMap<u64, u64> = std::collections::BTreeMap::new();
+        for entry in history.iter() {
+            *counts.entry(entry.key().0).or_default() += 1;
+        }
+
+        let mut trimmed = 0;
+        for (id, count) in counts {
+            let excess: Vec<(u64, u64)> = history
+                .range((id, 0)..=(id, u64::MAX))
+                .take(count.saturating_sub(max) as usize)
+                .map(|entry| *entry.key())
+                .collect();
+            for key in excess {
+                history.remove(&key);
+                trimmed += 1;
+            }
+        }
+        trimmed
+    })
+}
+
+/// Registers the retention policies and starts enforcing them daily.
+///
+/// Timers don't survive upgrades, so this runs from both `init` and
+/// `post_upgrade`. `get_retention_status` reports each run.
+fn start_retention() {
+    retention::register_policy(RetentionPolicy::max_age(
+        "trash",
+        Duration::from_secs(TRASH_RETENTION_DAYS * 24 * 60 * 60),
+        purge_trash_before,
+    ));
+    retention::register_policy(RetentionPolicy::max_count(
+        "revisions_per_record",
+        MAX_REVISIONS_PER_RECORD,
+        trim_history,
+    ));
+    retention::start_enforcement(RETENTION_INTERVAL);
+}
+
+/// Fails the install or upgrade if two tools share a name.
+fn check_tools() {
+    if let Err(e) = icarus::validate_tool_definitions() {
+        ic_cdk::trap(e.to_string());
+    }
+}
+
+#[ic_cdk::init]
+fn init() {
+    check_tools();
+    start_retention();
+}
+
+#[ic_cdk::post_upgrade]
+fn post_upgrade() {
+    check_tools();
+    start_retention();
+}
+
+// Generate MCP server endpoints
+icarus_macros::mcp! {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(title: &str) -> Record {
+        create_record(
+            title.to_string(),
+            "body".to_string(),
+            "notes".to_string(),
+            vec![],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_create_rejects_unknown_category() {
+        let result = create_record(
+            "x".to_string(),
+            String::new(),
+            "recipes".to_string(),
+            vec![],
+        );
+        assert!(result.unwrap_err().contains("Unknown category"));
+    }
+
+    #[test]
+    fn test_delete_and_restore() {
+        let record = note("keep me");
+
+        delete_record(record.id).unwrap();
+        assert!(get_record(record.id).is_none());
+        assert!(list_trash()
+            .iter()
+            .any(|entry| entry.record.id == record.id));
+
+        assert_eq!(restore_record(record.id).unwrap(), record);
+        assert_eq!(get_record(record.id), Some(record));
+    }
+
+    #[test]
+    fn test_sharing_controls_access() {
+        let record = note("shared");
+        let bob = candid::Principal::from_slice(&[42]);
+        let act_as = |principal| icarus::context::set_caller_override(principal);
+
+        act_as(Some(bob));
+        assert!(get_record(record.id).is_none());
+        assert!(share_record(record.id, bob.to_text(), vec![Permission::Read]).is_err());
+
+        act_as(None);
+        share_record(record.id, bob.to_text(), vec![Permission::Read]).unwrap();
+        act_as(Some(bob));
+        assert_eq!(get_record(record.id), Some(record.clone()));
+        assert!(update_record(record.id, Some("mine".to_string()), None, None, None).is_err());
+        assert!(delete_record(record.id).is_err());
+
+        act_as(None);
+        let shares = share_record(record.id, bob.to_text(), vec![Permission::Write]).unwrap();
+        assert_eq!(shares.len(), 2);
+        act_as(Some(bob));
+        update_record(record.id, Some("edited".to_string()), None, None, None).unwrap();
+        assert!(list_shares(record.id).is_err());
+
+        act_as(None);
+        assert!(revoke_share(record.id, bob.to_text()).unwrap());
+        assert_eq!(list_shares(record.id).unwrap().len(), 1);
+        act_as(Some(bob));
+        assert!(search_records(SearchQuery::default()).is_empty());
+        act_as(None);
+    }
+
+    #[test]
+    fn test_purge_trash_respects_age() {
+        let record = note("old news");
+        delete_record(record.id).unwrap();
+
+        assert_eq!(purge_trash(TRASH_RETENTION_DAYS), 0);
+        assert_eq!(purge_trash(0), 1);
+        assert!(restore_record(record.id).is_err());
+    }
+
+    #[test]
+    fn test_purge_trash_needs_write() {
+        let record = note("not yours");
+        delete_record(record.id).unwrap();
+
+        icarus::context::set_caller_override(Some(candid::Principal::from_slice(&[42])));
+        assert_eq!(purge_trash(0), 0);
+        icarus::context::set_caller_override(None);
+        restore_record(record.id).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_content_is_not_overwritten() {
+        let mut record = note("private");
+        assert!(!record.is_encrypted);
+
+        record.is_encrypted = true;
+        record.content = to_hex(b"ciphertext");
+        RECORDS.with(|records| records.borrow_mut().insert(record.id, record.clone()));
+
+        let result = update_record(record.id, None, Some("plain".to_string()), None, None);
+        assert!(result.unwrap_err().contains("encrypted"));
+        let renamed =
+            update_record(record.id, Some("renamed".to_string()), None, None, None).unwrap();
+        assert_eq!(renamed.content, record.content);
+
+        assert_eq!(from_hex(&record.content).unwrap(), b"ciphertext");
+        assert!(from_hex("abc").is_err());
+    }
+
+    #[test]
+    fn test_retention_trims_history() {
+        let record = note("draft");
+        for title in ["v2", "v3", "v4"] {
+            update_record(record.id, Some(title.to_string()), None, None, None).unwrap();
+        }
+
+        retention::register_policy(RetentionPolicy::max_count("revisions", 1, trim_history));
+        let status = retention::enforce_now();
+        assert_eq!(status.policies[0].last_purged, 2);
+
+        let history = get_record_history(record.id, 10);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].revision, 3);
+    }
+
+    #[test]
+    fn test_history_and_revert() {
+        let record = note("Q3");
+        update_record(record.id, Some("Q3 plan".to_string()), None, None, None).unwrap();
+        update_record(
+            record.id,
+            None,
+            Some("Ship the importer".to_string()),
+            None,
+            Some(vec!["planning".to_string()]),
+        )
+        .unwrap();
+
+        let history = get_record_history(record.id, 10);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].revision, 2);
+        assert_eq!(history[0].changes.len(), 2);
+        assert_eq!(history[1].changes[0].old, "Q3");
+
+        let reverted = revert_record(record.id, 0).unwrap();
+        assert_eq!(reverted.title, "Q3");
+        assert_eq!(reverted.content, record.content);
+        assert!(reverted.tags.is_empty());
+        assert_eq!(get_record_history(record.id, 1)[0].revision, 3);
+
+        assert!(revert_record(record.id, 9).is_err());
+    }
+
+    #[test]
+    fn test_bulk_import_reports_failures() {
+        let payload = serde_json::json!([
+            {"title": "Alice", "category": "contacts"},
+            {"title": "", "category": "notes"},
+            {"title": "Lunch", "category": "recipes"},
+            {"category": "notes"},
+            {"title": "Standup", "content": "10am", "category": "tasks", "tags": ["daily"]}
+        ])
+        .to_string();
+
+        let dry = bulk_import(payload.clone(), true).unwrap();
+        assert_eq!((dry.total, dry.valid), (5, 2));
+        assert!(dry.imported.is_empty());
+        let failed: Vec<usize> = dry.failures.iter().map(|f| f.index).collect();
+        assert_eq!(failed, [1, 2, 3]);
+
+        let report = bulk_import(payload, false).unwrap();
+        assert_eq!(report.imported.len(), 2);
+        assert_eq!(get_record(report.imported[1]).unwrap().title, "Standup");
+
+        let too_many =
+            serde_json::to_string(&vec![serde_json::json!({}); MAX_IMPORT_RECORDS + 1]).unwrap();
+        assert!(bulk_import(too_many, true).is_err());
+        assert!(bulk_import("not json".to_string(), true).is_err());
+    }
+
+    #[test]
+    fn test_query_records_filters_sorts_and_pages() {
+        let ids: Vec<u64> = ["alpha", "beta", "gamma"]
+            .into_iter()
+            .map(|title| {
+                create_record(
+                    title.to_string(),
+                    String::new(),
+                    "tasks".to_string(),
+                    vec!["query-test".to_string()],
+                )
+                .unwrap()
+                .id
+            })
+            .collect();
+
+        let filter: Filter = serde_json::from_value(serde_json::json!({
+            "op": "and",
+            "filters": [
+                { "op": "eq", "field": "tags", "value": "query-test" },
+                { "op": "range", "field": "id", "min": ids[0], "max": ids[2] },
+                { "op": "not", "filter": { "op": "contains", "field": "title", "value": "ET" } }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(filter.id_bounds(), (ids[0], ids[2]));
+
+        let sort = Sort {
+            field: "title".to_string(),
+            descending: true,
+        };
+        let page = Page {
+            offset: 0,
+            limit: Some(1),
+        };
+        let first = query_records(Some(filter.clone()), Some(sort.clone()), Some(page)).unwrap();
+        assert_eq!(first.total, 2);
+        assert_eq!(first.records[0].title, "gamma");
+        assert_eq!(first.next_offset, Some(1));
+
+        let page = Page {
+            offset: 1,
+            limit: Some(1),
+        };
+        let second = query_records(Some(filter), Some(sort), Some(page)).unwrap();
+        assert_eq!(second.records[0].title, "alpha");
+        assert_eq!(second.next_offset, None);
+
+        let unknown = Filter::Eq {
+            field: "owner".to_string(),
+            value: serde_json::json!("me"),
+        };
+        assert!(query_records(Some(unknown), None, None).is_err());
+    }
+
+    #[test]
+    fn test_get_report_groups_by_tag() {
+        for tags in [vec!["report-x"], vec!["report-x", "report-y"]] {
+            create_record(
+                "tagged".to_string(),
+                "1234".to_string(),
+                "notes".to_string(),
+                tags.into_iter().map(String::from).collect(),
+            )
+            .unwrap();
+        }
+
+        let report =
+            get_report("tags".to_string(), Some(Metric::Sum), Some(TimeBucket::Day)).unwrap();
+        let x = report.groups.iter().find(|g| g.key == "report-x").unwrap();
+        assert_eq!((x.count, x.value as u64), (2, 8));
+        assert!(!report.series.is_empty());
+
+        assert!(get_report("owner".to_string(), None, None).is_err());
+    }
+
+    #[test]
+    fn test_search_records() {
+        note("Quarterly PLAN");
+        let results = search_records(SearchQuery {
+            text: Some("plan".to_string()),
+            ..SearchQuery::default()
+        });
+        assert!(results
+            .iter()
+            .any(|record| record.title == "Quarterly PLAN"));
+    }
+}
@@ -0,0 +1,358 @@
+//! # Knowledge Graph Example
+//!
+//! This example demonstrates a knowledge graph that AI agents can build and
+//! explore: entities are nodes, relations are labeled edges, and both live in
+//! stable memory through `icarus::graph::StableGraph`.
+//!
+//! ## Features
+//! - Entities with a kind and description, persisted across upgrades
+//! - Directed, labeled relations between entities (`works_at`, `cites`, ...)
+//! - Neighbor lookup in either direction
+//! - Breadth-first exploration with a result limit
+//! - Removing an entity removes its relations
+//!
+//! ## Usage
+//!
+//! ```bash
+//! # Deploy to Internet Computer
+//! dfx start --background
+//! dfx deploy knowledge_graph
+//!
+//! # Add two entities
+//! dfx canister call knowledge_graph call_tool '(
+//!   record {
+//!     name = "add_entity";
+//!     arguments = "{\"name\": \"Ada Lovelace\", \"kind\": \"person\", \"description\": \"\"}"
+//!   }
+//! )'
+//! dfx canister call knowledge_graph call_tool '(
+//!   record {
+//!     name = "add_entity";
+//!     arguments = "{\"name\": \"Analytical Engine\", \"kind\": \"machine\", \"description\": \"\"}"
+//!   }
+//! )'
+//!
+//! # Relate them
+//! dfx canister call knowledge_graph call_tool '(
+//!   record {
+//!     name = "relate";
+//!     arguments = "{\"from\": 0, \"to\": 1, \"relation\": \"wrote_about\"}"
+//!   }
+//! )'
+//!
+//! # Everything within reach of Ada
+//! dfx canister call knowledge_graph call_tool '(
+//!   record {
+//!     name = "explore";
+//!     arguments = "{\"start\": 0, \"direction\": \"Both\", \"limit\": 20}"
+//!   }
+//! )'
+//! ```
+//!
+//! ## Architecture
+//!
+//! ```text
+//! ┌─────────────────────────────────────┐
+//! │     Knowledge Graph Canister        │
+//! │  ┌───────────────────────────────┐  │
+//! │  │ GRAPH (stable)                │  │
+//! │  │  node id → Entity             │  │
+//! │  │  (from, to) → relation        │  │
+//! │  │  (to, from) → reverse marker  │  │
+//! │  └───────────────────────────────┘  │
+//! └─────────────────────────────────────┘
+//! ```
+
+use candid::{CandidType, Decode, Encode};
+use ic_stable_structures::{storable::Bound, Storable};
+use icarus::graph::{Direction, StableGraph};
+use icarus::memory::FIRST_USER_MEMORY_ID;
+use icarus_macros::tool;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Most entities `explore` returns in one call
+const MAX_EXPLORE_RESULTS: u32 = 200;
+
+/// Longest accepted relation name
+const MAX_RELATION_LENGTH: usize = 64;
+
+/// A stored entity
+#[derive(Debug, Clone, PartialEq, CandidType, Serialize, Deserialize)]
+struct Entity {
+    name: String,
+    /// Free-form type, e.g. "person" or "paper"
+    kind: String,
+    description: String,
+}
+
+/// An entity with its id, as returned by tools
+#[derive(Debug, Clone, PartialEq, CandidType, Serialize, Deserialize)]
+struct EntityView {
+    id: u64,
+    name: String,
+    kind: String,
+    description: String,
+}
+
+/// An entity next to another, with the relation connecting them
+#[derive(Debug, Clone, PartialEq, CandidType, Serialize, Deserialize)]
+struct Neighbor {
+    id: u64,
+    name: String,
+    relation: String,
+}
+
+/// An entity reached by `explore`, with its distance from the start
+#[derive(Debug, Clone, PartialEq, CandidType, Serialize, Deserialize)]
+struct Reached {
+    id: u64,
+    name: String,
+    depth: u32,
+}
+
+impl Storable for Entity {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("Failed to encode entity"))
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).expect("Failed to decode entity")
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).expect("Failed to encode entity")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Stable state (persists across canister upgrades)
+thread_local! {
+    /// Entities and the relations between them
+    static GRAPH: StableGraph<Entity, String> = StableGraph::init(FIRST_USER_MEMORY_ID);
+}
+
+fn entity_name(id: u64) -> String {
+    GRAPH.with(|graph| graph.node(id).map(|entity| entity.name).unwrap_or_default())
+}
+
+/// Add an entity to the graph.
+///
+/// # Returns
+/// The stored entity with its new id
+///
+/// # Example
+/// ```json
+/// {
+///   "name": "Ada Lovelace",
+///   "kind": "person",
+///   "description": "Wrote the first published algorithm"
+/// }
+/// ```
+#[tool("Add an entity to the knowledge graph")]
+fn add_entity(name: String, kind: String, description: String) -> Result<EntityView, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Entity name must not be empty".to_string());
+    }
+
+    let entity = Entity {
+        name,
+        kind,
+        description,
+    };
+    let id = GRAPH.with(|graph| graph.add_node(entity.clone()));
+    Ok(EntityView {
+        id,
+        name: entity.name,
+        kind: entity.kind,
+        description: entity.description,
+    })
+}
+
+/// Get an entity by id.
+#[tool("Get an entity by id")]
+fn get_entity(id: u64) -> Result<EntityView, String> {
+    let entity = GRAPH
+        .with(|graph| graph.node(id))
+        .ok_or_else(|| format!("Entity {id} not found"))?;
+    Ok(EntityView {
+        id,
+        name: entity.name,
+        kind: entity.kind,
+        description: entity.description,
+    })
+}
+
+/// Relate two entities, replacing any relation already between them in
+/// that direction.
+///
+/// # Example
+/// ```json
+/// {
+///   "from": 0,
+///   "to": 1,
+///   "relation": "wrote_about"
+/// }
+/// ```
+#[tool("Add a directed relation between two entities")]
+fn relate(from: u64, to: u64, relation: String) -> Result<(), String> {
+    let relation = relation.trim().to_string();
+    if relation.is_empty() || relation.len() > MAX_RELATION_LENGTH {
+        return Err(format!(
+            "Relation must be 1 to {MAX_RELATION_LENGTH} characters"
+        ));
+    }
+    if from == to {
+        return Err("An entity cannot be related to itself".to_string());
+    }
+
+    GRAPH
+        .with(|graph| graph.add_edge(from, to, relation))
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Remove the relation from one entity to another.
+///
+/// # Returns
+/// The name of the removed relation
+#[tool("Remove the relation from one entity to another")]
+fn unrelate(from: u64, to: u64) -> Result<String, String> {
+    GRAPH
+        .with(|graph| graph.remove_edge(from, to))
+        .ok_or_else(|| format!("No relation from {from} to {to}"))
+}
+
+/// List the entities directly related to one, following relations in
+/// `direction` (`Outgoing`, `Incoming` or `Both`).
+#[tool("List the entities directly related to an entity")]
+fn neighbors(id: u64, direction: Direction) -> Result<Vec<Neighbor>, String> {
+    if !GRAPH.with(|graph| graph.contains_node(id)) {
+        return Err(format!("Entity {id} not found"));
+    }
+
+    Ok(GRAPH
+        .with(|graph| graph.neighbors(id, direction))
+        .into_iter()
+        .map(|(id, relation)| Neighbor {
+            id,
+            name: entity_name(id),
+            relation,
+        })
+        .collect())
+}
+
+/// Explore the graph breadth-first from an entity.
+///
+/// # Returns
+/// Up to `limit` entities (at most 200), nearest first, starting with
+/// `start` itself at depth 0
+///
+/// # Example
+/// ```json
+/// {
+///   "start": 0,
+///   "direction": "Both",
+///   "limit": 20
+/// }
+/// ```
+#[tool("Explore the entities reachable from an entity, nearest first")]
+fn explore(start: u64, direction: Direction, limit: u32) -> Result<Vec<Reached>, String> {
+    if !GRAPH.with(|graph| graph.contains_node(start)) {
+        return Err(format!("Entity {start} not found"));
+    }
+
+    let limit = limit.min(MAX_EXPLORE_RESULTS) as usize;
+    Ok(GRAPH
+        .with(|graph| graph.bfs(start, direction, limit))
+        .into_iter()
+        .map(|(id, depth)| Reached {
+            id,
+            name: entity_name(id),
+            depth,
+        })
+        .collect())
+}
+
+/// Remove an entity and all of its relations.
+#[tool("Remove an entity and its relations")]
+fn remove_entity(id: u64) -> Result<(), String> {
+    GRAPH
+        .with(|graph| graph.remove_node(id))
+        .map(|_| ())
+        .ok_or_else(|| format!("Entity {id} not found"))
+}
+
+/// Count entities and relations.
+///
+/// # Returns
+/// `(entities, relations)`
+#[tool("Count entities and relations in the graph")]
+fn graph_stats() -> (u64, u64) {
+    GRAPH.with(|graph| (graph.node_count(), graph.edge_count()))
+}
+
+// Generate MCP server endpoints
+icarus_macros::mcp! {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(name: &str) -> u64 {
+        add_entity(name.to_string(), "test".to_string(), String::new())
+            .unwrap()
+            .id
+    }
+
+    #[test]
+    fn test_relate_and_explore() {
+        let ada = entity("Ada");
+        let charles = entity("Charles");
+        let engine = entity("Engine");
+
+        relate(ada, charles, "worked_with".to_string()).unwrap();
+        relate(charles, engine, "designed".to_string()).unwrap();
+        assert!(relate(ada, ada, "self".to_string()).is_err());
+        assert!(relate(ada, u64::MAX, "knows".to_string()).is_err());
+
+        let incoming = neighbors(charles, Direction::Incoming).unwrap();
+        assert_eq!(
+            incoming,
+            [Neighbor {
+                id: ada,
+                name: "Ada".to_string(),
+                relation: "worked_with".to_string(),
+            }]
+        );
+
+        let reached: Vec<(String, u32)> = explore(ada, Direction::Outgoing, 10)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.name, r.depth))
+            .collect();
+        assert_eq!(
+            reached,
+            [
+                ("Ada".to_string(), 0),
+                ("Charles".to_string(), 1),
+                ("Engine".to_string(), 2)
+            ]
+        );
+        assert_eq!(explore(ada, Direction::Outgoing, 2).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_remove_entity_removes_relations() {
+        let paper = entity("Paper");
+        let author = entity("Author");
+        relate(author, paper, "wrote".to_string()).unwrap();
+
+        remove_entity(author).unwrap();
+        assert!(neighbors(paper, Direction::Both).unwrap().is_empty());
+        assert!(get_entity(author).is_err());
+        assert!(remove_entity(author).is_err());
+    }
+}
@@ -9,6 +9,7 @@
 //! - Multiple counters with independent state
 //! - Atomic increment/decrement operations
 //! - State inspection and reset capabilities
+//! - Append-only activity log in stable memory with bounded retention
 //!
 //! ## Usage
 //!
@@ -87,10 +88,15 @@
 //! └─────────────────────────────────────┘
 //! ```
 
+use icarus::collections::StorageLog;
+use icarus::memory::FIRST_USER_MEMORY_ID;
 use icarus_macros::tool;
 use std::cell::RefCell;
 use std::collections::HashMap;
 
+/// Number of activity entries kept before the oldest are evicted
+const MAX_ACTIVITY_ENTRIES: u64 = 1_000;
+
 // Thread-local state (volatile - lost on canister upgrade)
 thread_local! {
     /// Global counter - simple single value
@@ -98,6 +104,15 @@ thread_local! {
 
     /// Named counters - multiple independent counters
     static NAMED_COUNTERS: RefCell<HashMap<String, u64>> = RefCell::new(HashMap::new());
+
+    /// Activity log - stable, so each append writes one entry and survives upgrades
+    static ACTIVITY: StorageLog<String> =
+        StorageLog::init(FIRST_USER_MEMORY_ID).with_max_entries(MAX_ACTIVITY_ENTRIES);
+}
+
+/// Records a change to the global counter in the activity log.
+fn record_activity(action: &str, value: u64) {
+    ACTIVITY.with(|log| log.append(format!("{action} -> {value}")));
 }
 
 /// Increment the global counter by 1.
@@ -115,6 +130,7 @@ fn increment() -> u64 {
     COUNTER.with(|counter| {
         let mut count = counter.borrow_mut();
         *count += 1;
+        record_activity("increment", *count);
         *count
     })
 }
@@ -134,6 +150,7 @@ fn decrement() -> u64 {
     COUNTER.with(|counter| {
         let mut count = counter.borrow_mut();
         *count = count.saturating_sub(1); // Prevent underflow
+        record_activity("decrement", *count);
         *count
     })
 }
@@ -169,6 +186,7 @@ fn reset() -> u64 {
         let mut count = counter.borrow_mut();
         let old_value = *count;
         *count = 0;
+        record_activity("reset", 0);
         old_value
     })
 }
@@ -197,10 +215,36 @@ fn add(amount: i64) -> u64 {
         } else {
             *count = count.saturating_sub(amount.abs() as u64);
         }
+        record_activity(&format!("add {amount}"), *count);
         *count
     })
 }
 
+/// List the most recent changes to the global counter.
+///
+/// # Parameters
+/// - `limit`: Maximum number of entries to return, newest first
+///
+/// # Returns
+/// Activity entries in the form `"<action> -> <new value>"`
+///
+/// # Example
+/// ```json
+/// {
+///   "limit": 2
+/// }
+/// ```
+/// Returns: `["add 10 -> 52", "increment -> 42"]`
+#[tool("List recent changes to the global counter")]
+fn recent_activity(limit: u64) -> Vec<String> {
+    ACTIVITY.with(|log| {
+        log.latest(limit)
+            .into_iter()
+            .map(|(_, entry)| entry)
+            .collect()
+    })
+}
+
 /// Increment a named counter (creates if doesn't exist).
 ///
 /// Named counters allow you to track multiple independent counters.
@@ -380,4 +424,11 @@ mod tests {
         let value = get_named(test_name.clone());
         assert_eq!(value, 2);
     }
+
+    #[test]
+    fn test_recent_activity() {
+        let value = increment();
+        let activity = recent_activity(1);
+        assert_eq!(activity, [format!("increment -> {value}")]);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,484 @@
+//! # Task Scheduler Example
+//!
+//! This example demonstrates a job scheduler AI agents can use to set up
+//! one-off and repeating reminders, with jobs and their run history kept in
+//! stable memory.
+//!
+//! ## Features
+//! - Jobs scheduled with `icarus::scheduler`, surviving canister upgrades
+//! - One-off jobs after a delay and repeating jobs on an interval
+//! - Every run logged to a `StableBTreeMap`, trimmed to a fixed size
+//! - Grouped counts and time series for dashboards via `get_report`
+//!
+//! ## Usage
+//!
+//! ```bash
+//! # Deploy to Internet Computer
+//! dfx start --background
+//! dfx deploy task_scheduler
+//!
+//! # Remind every hour
+//! dfx canister call task_scheduler call_tool '(
+//!   record {
+//!     name = "schedule_job";
+//!     arguments = "{\"name\": \"stretch\", \"message\": \"Stand up\", \"interval_seconds\": 3600}"
+//!   }
+//! )'
+//!
+//! # Runs per job and per day
+//! dfx canister call task_scheduler call_tool '(
+//!   record {
+//!     name = "get_report";
+//!     arguments = "{\"group_by\": \"job\", \"time_bucket\": \"day\"}"
+//!   }
+//! )'
+//! ```
+//!
+//! ## Architecture
+//!
+//! ```text
+//! ┌─────────────────────────────────────┐
+//! │     Task Scheduler Canister         │
+//! │  ┌───────────────────────────────┐  │
+//! │  │ JOBS (stable)                 │  │
+//! │  │  name → Job                   │  │
+//! │  └──────────────┬────────────────┘  │
+//! │  icarus::scheduler tick timer       │
+//! │  ┌──────────────▼────────────────┐  │
+//! │  │ RUNS (stable)                 │  │
+//! │  │  id → JobRun                  │  │
+//! │  └──────────────┬────────────────┘  │
+//! │                 ▼ get_report        │
+//! └─────────────────────────────────────┘
+//! ```
+
+use candid::{CandidType, Decode, Encode};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use icarus::aggregate::{AggregateReport, Aggregator, Metric, TimeBucket};
+use icarus::memory::{self, StableMemory, FIRST_USER_MEMORY_ID};
+use icarus::scheduler::{self, ScheduledTask, TaskContext};
+use icarus_macros::tool;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::time::Duration;
+
+/// Maximum job name length in bytes
+const MAX_NAME_BYTES: usize = 64;
+
+/// Maximum message length in bytes
+const MAX_MESSAGE_BYTES: usize = 1024;
+
+/// Maximum number of scheduled jobs
+const MAX_JOBS: u64 = 100;
+
+/// Shortest repeat interval
+const MIN_INTERVAL_SECONDS: u64 = 60;
+
+/// Runs kept in the log; the oldest are dropped first
+const MAX_RUNS: u64 = 10_000;
+
+/// Runs returned by `job_history` when no limit is given
+const DEFAULT_HISTORY_LIMIT: usize = 50;
+
+/// How often the scheduler looks for due jobs
+const TICK: Duration = Duration::from_secs(30);
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// A job and the message it delivers
+#[derive(Debug, Clone, PartialEq, CandidType, Serialize, Deserialize)]
+struct Job {
+    name: String,
+    message: String,
+    /// Repeat interval, `None` for a one-off job
+    interval_seconds: Option<u64>,
+    created_at: u64,
+}
+
+/// One run of a job
+#[derive(Debug, Clone, PartialEq, CandidType, Serialize, Deserialize)]
+struct JobRun {
+    id: u64,
+    job: String,
+    message: String,
+    /// Whether the job repeats
+    repeating: bool,
+    /// When the run was due
+    scheduled_for: u64,
+    /// When it ran
+    ran_at: u64,
+}
+
+impl JobRun {
+    /// `repeating` or `one_off`.
+    fn kind(&self) -> &'static str {
+        if self.repeating {
+            "repeating"
+        } else {
+            "one_off"
+        }
+    }
+
+    /// Seconds between the due time and the run.
+    fn delay_seconds(&self) -> f64 {
+        self.ran_at.saturating_sub(self.scheduled_for) as f64 / NANOS_PER_SECOND as f64
+    }
+}
+
+impl Storable for Job {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("Failed to encode job"))
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).expect("Failed to decode job")
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).expect("Failed to encode job")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for JobRun {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("Failed to encode job run"))
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Decode!(&bytes, Self).expect("Failed to decode job run")
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Encode!(&self).expect("Failed to encode job run")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+thread_local! {
+    /// Scheduled jobs by name
+    static JOBS: RefCell<StableBTreeMap<String, Job, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(FIRST_USER_MEMORY_ID))
+    );
+
+    /// Run log by id, oldest first
+    static RUNS: RefCell<StableBTreeMap<u64, JobRun, StableMemory>> = RefCell::new(
+        StableBTreeMap::init(memory::get_memory(FIRST_USER_MEMORY_ID + 1))
+    );
+}
+
+/// Checks a job's name and message against the size limits.
+fn validate(name: &str, message: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Name cannot be empty".to_string());
+    }
+    if name.len() > MAX_NAME_BYTES {
+        return Err(format!("Name exceeds {MAX_NAME_BYTES} bytes"));
+    }
+    if message.len() > MAX_MESSAGE_BYTES {
+        return Err(format!("Message exceeds {MAX_MESSAGE_BYTES} bytes"));
+    }
+    Ok(())
+}
+
+/// Registers the scheduler handler that runs the job named `name`.
+///
+/// Handlers live on the heap, so `post_upgrade` registers every stored job
+/// again.
+fn register(name: &str) {
+    scheduler::register_task_handler(name, |context: TaskContext| async move {
+        record_run(&context.name, context.scheduled_for).map(|_| ())
+    });
+}
+
+/// Logs a run of `name` and forgets one-off jobs once they have run.
+fn record_run(name: &str, scheduled_for: u64) -> Result<JobRun, String> {
+    let job = JOBS
+        .with(|jobs| jobs.borrow().get(&name.to_string()))
+        .ok_or_else(|| format!("Job '{name}' no longer exists"))?;
+
+    let run = RUNS.with(|runs| {
+        let mut runs = runs.borrow_mut();
+        let run = JobRun {
+            id: runs.last_key_value().map_or(1, |(id, _)| id + 1),
+            job: job.name.clone(),
+            message: job.message.clone(),
+            repeating: job.interval_seconds.is_some(),
+            scheduled_for,
+            ran_at: icarus::time::now_nanos(),
+        };
+        runs.insert(run.id, run.clone());
+        while runs.len() > MAX_RUNS {
+            runs.pop_first();
+        }
+        run
+    });
+
+    // The scheduler drops a one-off task before running it
+    if scheduler::task(name).is_none() {
+        JOBS.with(|jobs| jobs.borrow_mut().remove(&name.to_string()));
+        scheduler::unregister_handler(name);
+    }
+    Ok(run)
+}
+
+/// Schedule a reminder job.
+///
+/// # Parameters
+/// - `name`: Unique job name
+/// - `message`: What the job delivers when it runs
+/// - `delay_seconds`: Seconds until a one-off run (default: next tick)
+/// - `interval_seconds`: Repeat interval; the job repeats until cancelled
+///
+/// # Returns
+/// The scheduled task with its next run time
+///
+/// # Example
+/// ```json
+/// {
+///   "name": "stretch",
+///   "message": "Stand up",
+///   "interval_seconds": 3600
+/// }
+/// ```
+#[tool("Schedule a one-off or repeating reminder job")]
+fn schedule_job(
+    name: String,
+    message: String,
+    delay_seconds: Option<u64>,
+    interval_seconds: Option<u64>,
+) -> Result<ScheduledTask, String> {
+    validate(&name, &message)?;
+    if JOBS.with(|jobs| jobs.borrow().contains_key(&name)) {
+        return Err(format!("Job '{name}' already exists"));
+    }
+    if JOBS.with(|jobs| jobs.borrow().len()) >= MAX_JOBS {
+        return Err(format!("At most {MAX_JOBS} jobs can be scheduled"));
+    }
+
+    match (delay_seconds, interval_seconds) {
+        (Some(_), Some(_)) => {
+            return Err("Give either delay_seconds or interval_seconds, not both".to_string())
+        }
+        (_, Some(interval)) if interval < MIN_INTERVAL_SECONDS => {
+            return Err(format!(
+                "Interval must be at least {MIN_INTERVAL_SECONDS} seconds"
+            ))
+        }
+        (_, Some(interval)) => scheduler::schedule_every(&name, Duration::from_secs(interval)),
+        (delay, None) => scheduler::schedule_at(
+            &name,
+            icarus::time::now_nanos()
+                .saturating_add(delay.unwrap_or(0).saturating_mul(NANOS_PER_SECOND)),
+        ),
+    }
+
+    let job = Job {
+        name: name.clone(),
+        message,
+        interval_seconds,
+        created_at: icarus::time::now_nanos(),
+    };
+    JOBS.with(|jobs| jobs.borrow_mut().insert(name.clone(), job));
+    register(&name);
+
+    scheduler::task(&name).ok_or_else(|| format!("Job '{name}' was not scheduled"))
+}
+
+/// Cancel a job before its next run.
+///
+/// # Parameters
+/// - `name`: The job name
+///
+/// # Returns
+/// Nothing on success. Its past runs stay in the log.
+#[tool("Cancel a scheduled job")]
+fn cancel_job(name: String) -> Result<(), String> {
+    if JOBS.with(|jobs| jobs.borrow_mut().remove(&name)).is_none() {
+        return Err(format!("Job '{name}' not found"));
+    }
+    scheduler::cancel(&name);
+    scheduler::unregister_handler(&name);
+    Ok(())
+}
+
+/// List scheduled jobs with their next run times.
+///
+/// # Returns
+/// The scheduler's tasks for every job, sorted by name
+#[tool("List scheduled jobs")]
+fn list_jobs() -> Vec<ScheduledTask> {
+    JOBS.with(|jobs| {
+        jobs.borrow()
+            .iter()
+            .filter_map(|entry| scheduler::task(entry.key()))
+            .collect()
+    })
+}
+
+/// Show the most recent runs.
+///
+/// # Parameters
+/// - `job`: Only runs of this job (optional)
+/// - `limit`: Maximum number of runs (default: 50)
+///
+/// # Returns
+/// Runs newest first
+#[tool("Show the most recent job runs")]
+fn job_history(job: Option<String>, limit: Option<usize>) -> Vec<JobRun> {
+    RUNS.with(|runs| {
+        runs.borrow()
+            .iter()
+            .rev()
+            .map(|entry| entry.value())
+            .filter(|run| job.as_ref().map_or(true, |job| run.job == *job))
+            .take(limit.unwrap_or(DEFAULT_HISTORY_LIMIT))
+            .collect()
+    })
+}
+
+/// Report job runs by group and over time.
+///
+/// # Parameters
+/// - `group_by`: `job`, `kind` (`one_off` or `repeating`) or `none`
+/// - `metric`: `count` (default), or `sum`/`average`/`min`/`max` of how many
+///   seconds after its due time each run started
+/// - `time_bucket`: `hour`, `day` or `week` to add a series by run time
+///
+/// # Returns
+/// Per-group values sorted by key and, with a bucket, a per-bucket series
+///
+/// # Example
+/// ```json
+/// {
+///   "group_by": "job",
+///   "metric": "average",
+///   "time_bucket": "day"
+/// }
+/// ```
+/// Returns: `{"metric": "average", "total": 48, "groups": [{"key": "stretch", "count": 24, "value": 12.5}, ...], "series": [...]}`
+#[tool("Report grouped counts and time series of job runs")]
+fn get_report(
+    group_by: String,
+    metric: Option<Metric>,
+    time_bucket: Option<TimeBucket>,
+) -> Result<AggregateReport, String> {
+    let mut aggregator = match group_by.as_str() {
+        "job" => Aggregator::new().group_by(|run: &JobRun| vec![run.job.clone()]),
+        "kind" => Aggregator::new().group_by(|run: &JobRun| vec![run.kind().to_string()]),
+        "none" => Aggregator::new(),
+        _ => {
+            return Err(format!(
+                "Unknown group_by '{group_by}', expected job, kind or none"
+            ))
+        }
+    };
+    if let Some(metric) = metric.filter(|metric| *metric != Metric::Count) {
+        aggregator = aggregator.metric(metric, JobRun::delay_seconds);
+    }
+    if let Some(bucket) = time_bucket {
+        aggregator = aggregator.time_series(|run| run.ran_at, bucket);
+    }
+
+    Ok(RUNS.with(|runs| aggregator.run(runs.borrow().iter().map(|entry| entry.value()))))
+}
+
+/// Fails the install or upgrade if two tools share a name.
+fn check_tools() {
+    if let Err(e) = icarus::validate_tool_definitions() {
+        ic_cdk::trap(e.to_string());
+    }
+}
+
+#[ic_cdk::init]
+fn init() {
+    check_tools();
+    scheduler::start(TICK);
+}
+
+#[ic_cdk::post_upgrade]
+fn post_upgrade() {
+    check_tools();
+    // Tasks are still stored; only their handlers and the tick start over
+    JOBS.with(|jobs| {
+        for entry in jobs.borrow().iter() {
+            register(entry.key());
+        }
+    });
+    scheduler::start(TICK);
+}
+
+// Generate MCP server endpoints
+icarus_macros::mcp! {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 60 * 60 * NANOS_PER_SECOND;
+
+    #[test]
+    fn test_schedule_job_validates_input() {
+        let both = schedule_job("both".to_string(), String::new(), Some(1), Some(3600));
+        assert!(both.unwrap_err().contains("not both"));
+
+        let fast = schedule_job("fast".to_string(), String::new(), None, Some(1));
+        assert!(fast.unwrap_err().contains("at least"));
+
+        schedule_job("twice".to_string(), String::new(), Some(60), None).unwrap();
+        let again = schedule_job("twice".to_string(), String::new(), Some(60), None);
+        assert!(again.unwrap_err().contains("already exists"));
+
+        cancel_job("twice".to_string()).unwrap();
+        assert!(cancel_job("twice".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_one_off_job_is_forgotten_after_running() {
+        schedule_job("once".to_string(), "hi".to_string(), Some(60), None).unwrap();
+        scheduler::cancel("once");
+
+        let run = record_run("once", 0).unwrap();
+        assert_eq!(run.message, "hi");
+        assert!(!run.repeating);
+        assert!(list_jobs().iter().all(|task| task.name != "once"));
+        assert!(record_run("once", 0).is_err());
+    }
+
+    #[test]
+    fn test_report_groups_runs() {
+        icarus::time::set_time_override(Some(10 * HOUR));
+        schedule_job("hourly".to_string(), "ping".to_string(), None, Some(3600)).unwrap();
+        record_run("hourly", 10 * HOUR - 4 * NANOS_PER_SECOND).unwrap();
+        icarus::time::set_time_override(Some(11 * HOUR));
+        record_run("hourly", 11 * HOUR - 2 * NANOS_PER_SECOND).unwrap();
+        icarus::time::set_time_override(None);
+
+        let report = get_report(
+            "job".to_string(),
+            Some(Metric::Average),
+            Some(TimeBucket::Hour),
+        )
+        .unwrap();
+        let hourly = report
+            .groups
+            .iter()
+            .find(|group| group.key == "hourly")
+            .unwrap();
+        assert_eq!(hourly.count, 2);
+        assert!((hourly.value - 3.0).abs() < f64::EPSILON);
+        assert!(report
+            .series
+            .iter()
+            .any(|bucket| bucket.bucket_start == 10 * HOUR));
+
+        let history = job_history(Some("hourly".to_string()), Some(1));
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].ran_at, 11 * HOUR);
+
+        assert!(get_report("owner".to_string(), None, None).is_err());
+    }
+}
@@ -0,0 +1,606 @@
+//! # Web3 Wallet Example
+//!
+//! This example demonstrates a canister that holds Bitcoin and Ethereum
+//! addresses without holding any private key: both are derived from the
+//! subnet's threshold ECDSA key through `icarus::signing`.
+//!
+//! ## Features
+//! - BTC (P2PKH) and ETH addresses derived from threshold ECDSA public keys
+//! - Balances queried with HTTP outcalls to a block explorer and a JSON-RPC node
+//! - ETH transfers built, signed and broadcast by the canister
+//! - A dry-run mode that shows nonce, gas and total cost without signing
+//! - Per-transaction spending limit
+//! - `auth = "user"` on read tools and `auth = "admin"` on `send_transaction`
+//!
+//! ## Dependencies
+//!
+//! Besides `icarus`, the template uses `k256` (features `ecdsa`) to recover
+//! signatures and decompress keys, `sha3` for Keccak-256 and `ripemd` for
+//! Bitcoin addresses.
+//!
+//! ## Usage
+//!
+//! ```bash
+//! # Deploy to Internet Computer, with yourself as admin
+//! dfx start --background
+//! dfx deploy web3_wallet --argument "(principal \"$(dfx identity get-principal)\")"
+//!
+//! # Get the canister's Ethereum address
+//! dfx canister call web3_wallet call_tool '(
+//!   record {
+//!     name = "get_address";
+//!     arguments = "{\"chain\": \"eth\"}"
+//!   }
+//! )'
+//!
+//! # Preview a transfer of 0.01 ETH
+//! dfx canister call web3_wallet call_tool '(
+//!   record {
+//!     name = "send_transaction";
+//!     arguments = "{\"chain\": \"eth\", \"to\": \"0x...\", \"amount\": \"10000000000000000\", \"dry_run\": true}"
+//!   }
+//! )'
+//! ```
+//!
+//! ## Architecture
+//!
+//! ```text
+//! ┌─────────────────────────────────────┐
+//! │        Web3 Wallet Canister         │
+//! │  get_address ──► public_key_at      │
+//! │  send_transaction ──► sign_hash_at  │
+//! └──────┬───────────────────┬──────────┘
+//!        │ threshold ECDSA   │ HTTP outcalls
+//! ┌──────▼──────────┐ ┌──────▼──────────┐
+//! │ Management      │ │ Block explorer  │
+//! │ canister        │ │ ETH JSON-RPC    │
+//! └─────────────────┘ └─────────────────┘
+//! ```
+//!
+//! ## Important Notes
+//!
+//! - Every signature costs cycles; dry runs never sign
+//! - Switch `KEY_NAME` to `signing::LOCAL_KEY` for a local replica and to
+//!   `signing::TEST_KEY` with test networks before risking real funds
+//! - Sending BTC needs UTXO selection and BIP-143 signing; see the DFINITY
+//!   `basic_bitcoin` example for that part
+//! - JSON-RPC responses must agree across replicas; use a node that returns
+//!   identical bodies or add a transform
+
+use candid::CandidType;
+use icarus::http;
+use icarus::signing;
+use icarus_macros::tool;
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use ripemd::Ripemd160;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+
+/// Threshold ECDSA key; `signing::LOCAL_KEY` on a local replica
+const KEY_NAME: &str = signing::MAINNET_KEY;
+
+/// Derivation path of the Bitcoin address
+const BTC_PATH: &[u8] = b"btc";
+
+/// Derivation path of the Ethereum address
+const ETH_PATH: &[u8] = b"eth";
+
+/// Esplora API used for Bitcoin balances
+const BTC_API_URL: &str = "https://blockstream.info/api";
+
+/// Ethereum JSON-RPC endpoint
+const ETH_RPC_URL: &str = "https://cloudflare-eth.com";
+
+/// EIP-155 chain id (1 = mainnet)
+const ETH_CHAIN_ID: u64 = 1;
+
+/// Gas used by a plain ETH transfer
+const TRANSFER_GAS: u128 = 21_000;
+
+/// Largest transfer `send_transaction` accepts, in wei (0.1 ETH)
+const MAX_SEND_WEI: u128 = 100_000_000_000_000_000;
+
+/// Supported chains
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Chain {
+    Btc,
+    Eth,
+}
+
+impl Chain {
+    fn parse(chain: &str) -> Result<Self, String> {
+        match chain.to_ascii_lowercase().as_str() {
+            "btc" | "bitcoin" => Ok(Self::Btc),
+            "eth" | "ethereum" => Ok(Self::Eth),
+            _ => Err(format!(
+                "Unsupported chain '{chain}'; use \"btc\" or \"eth\""
+            )),
+        }
+    }
+
+    fn path(self) -> Vec<Vec<u8>> {
+        match self {
+            Self::Btc => vec![BTC_PATH.to_vec()],
+            Self::Eth => vec![ETH_PATH.to_vec()],
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Btc => "btc",
+            Self::Eth => "eth",
+        }
+    }
+}
+
+/// A balance in the chain's smallest unit
+#[derive(Debug, Clone, PartialEq, CandidType, Serialize, Deserialize)]
+struct Balance {
+    chain: String,
+    address: String,
+    /// Decimal string, since wei amounts overflow JSON numbers
+    amount: String,
+    unit: String,
+}
+
+/// Outcome of `send_transaction`
+#[derive(Debug, Clone, PartialEq, CandidType, Serialize, Deserialize)]
+struct Transfer {
+    chain: String,
+    from: String,
+    to: String,
+    amount: String,
+    nonce: u64,
+    gas_price: String,
+    /// Most the transfer can cost in fees, in wei
+    max_fee: String,
+    dry_run: bool,
+    /// Hash of the broadcast transaction; absent for dry runs
+    tx_hash: Option<String>,
+}
+
+/// Selects the configured key; the choice is lost on upgrade.
+fn select_key() {
+    if signing::key_name() != KEY_NAME {
+        signing::set_key_name(KEY_NAME);
+    }
+}
+
+async fn public_key(chain: Chain) -> Result<Vec<u8>, String> {
+    select_key();
+    signing::public_key_at(chain.path())
+        .await
+        .map(|key| key.public_key)
+        .map_err(|e| e.to_string())
+}
+
+/// Get the canister's address on a chain.
+///
+/// # Parameters
+/// - `chain`: `"btc"` or `"eth"`
+///
+/// # Returns
+/// A P2PKH address for Bitcoin or an EIP-55 checksummed address for Ethereum
+#[tool("Get the wallet's Bitcoin or Ethereum address", auth = "user")]
+async fn get_address(chain: String) -> Result<String, String> {
+    let chain = Chain::parse(&chain)?;
+    let key = public_key(chain).await?;
+    match chain {
+        Chain::Btc => btc_address(&key),
+        Chain::Eth => eth_address(&key),
+    }
+}
+
+/// Get the wallet's confirmed balance on a chain.
+///
+/// # Parameters
+/// - `chain`: `"btc"` or `"eth"`
+///
+/// # Returns
+/// The balance in satoshi or wei
+#[tool("Get the wallet's confirmed balance in satoshi or wei", auth = "user")]
+async fn get_balance(chain: String) -> Result<Balance, String> {
+    let chain = Chain::parse(&chain)?;
+    let key = public_key(chain).await?;
+    match chain {
+        Chain::Btc => {
+            let address = btc_address(&key)?;
+            let amount = btc_balance(&address).await?;
+            Ok(Balance {
+                chain: chain.name().to_string(),
+                address,
+                amount: amount.to_string(),
+                unit: "sat".to_string(),
+            })
+        }
+        Chain::Eth => {
+            let address = eth_address(&key)?;
+            let amount = eth_rpc_quantity("eth_getBalance", &[&address, "latest"]).await?;
+            Ok(Balance {
+                chain: chain.name().to_string(),
+                address,
+                amount: amount.to_string(),
+                unit: "wei".to_string(),
+            })
+        }
+    }
+}
+
+/// Send ETH from the wallet.
+///
+/// With `dry_run` the transfer is priced but neither signed nor sent, so it
+/// costs no signing fee. Transfers above `MAX_SEND_WEI` are refused.
+///
+/// # Parameters
+/// - `chain`: Must be `"eth"`
+/// - `to`: Recipient address, `0x` followed by 40 hex digits
+/// - `amount`: Amount in wei, as a decimal string
+/// - `dry_run`: Preview without signing (default: true)
+///
+/// # Returns
+/// The transfer details, with the transaction hash once broadcast
+#[tool(
+    "Send ETH from the wallet; previews without signing unless dry_run is false",
+    auth = "admin",
+    expensive
+)]
+async fn send_transaction(
+    chain: String,
+    to: String,
+    amount: String,
+    dry_run: Option<bool>,
+) -> Result<Transfer, String> {
+    if Chain::parse(&chain)? != Chain::Eth {
+        return Err("This template only sends ETH".to_string());
+    }
+    let to_bytes = parse_eth_address(&to)?;
+    let value: u128 = amount
+        .parse()
+        .map_err(|_| format!("Invalid amount '{amount}'; use a whole number of wei"))?;
+    if value == 0 || value > MAX_SEND_WEI {
+        return Err(format!("Amount must be between 1 and {MAX_SEND_WEI} wei"));
+    }
+
+    let key = public_key(Chain::Eth).await?;
+    let from = eth_address(&key)?;
+    let nonce = eth_rpc_quantity("eth_getTransactionCount", &[&from, "pending"]).await?;
+    let nonce = u64::try_from(nonce).map_err(|_| "Nonce out of range".to_string())?;
+    let gas_price = eth_rpc_quantity("eth_gasPrice", &[]).await?;
+    let max_fee = gas_price.saturating_mul(TRANSFER_GAS);
+
+    let mut transfer = Transfer {
+        chain: Chain::Eth.name().to_string(),
+        from,
+        to,
+        amount: value.to_string(),
+        nonce,
+        gas_price: gas_price.to_string(),
+        max_fee: max_fee.to_string(),
+        dry_run: dry_run.unwrap_or(true),
+        tx_hash: None,
+    };
+    if transfer.dry_run {
+        return Ok(transfer);
+    }
+
+    let tx = LegacyTransaction {
+        nonce,
+        gas_price,
+        gas: TRANSFER_GAS,
+        to: to_bytes,
+        value,
+    };
+    let hash: [u8; 32] = Keccak256::digest(tx.signing_payload(ETH_CHAIN_ID)).into();
+    select_key();
+    let signature = signing::sign_hash_at(hash, Chain::Eth.path(), Chain::Eth.name())
+        .await
+        .map_err(|e| e.to_string())?;
+    let raw = tx.signed(ETH_CHAIN_ID, &hash, &signature, &key)?;
+
+    let tx_hash = eth_rpc("eth_sendRawTransaction", &[&format!("0x{}", to_hex(&raw))]).await?;
+    transfer.tx_hash = Some(tx_hash);
+    Ok(transfer)
+}
+
+/// A pre-EIP-1559 transaction, which every EVM chain accepts
+struct LegacyTransaction {
+    nonce: u64,
+    gas_price: u128,
+    gas: u128,
+    to: [u8; 20],
+    value: u128,
+}
+
+impl LegacyTransaction {
+    fn fields(&self) -> Vec<Vec<u8>> {
+        vec![
+            rlp_bytes(&be_bytes(u128::from(self.nonce))),
+            rlp_bytes(&be_bytes(self.gas_price)),
+            rlp_bytes(&be_bytes(self.gas)),
+            rlp_bytes(&self.to),
+            rlp_bytes(&be_bytes(self.value)),
+            rlp_bytes(&[]),
+        ]
+    }
+
+    /// RLP payload hashed for signing, per EIP-155
+    fn signing_payload(&self, chain_id: u64) -> Vec<u8> {
+        let mut fields = self.fields();
+        fields.push(rlp_bytes(&be_bytes(u128::from(chain_id))));
+        fields.push(rlp_bytes(&[]));
+        fields.push(rlp_bytes(&[]));
+        rlp_list(&fields)
+    }
+
+    /// Raw signed transaction, ready for `eth_sendRawTransaction`
+    fn signed(
+        &self,
+        chain_id: u64,
+        hash: &[u8; 32],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        let signature = Signature::from_slice(signature).map_err(|e| e.to_string())?;
+        // Ethereum only accepts low-s signatures
+        let signature = signature.normalize_s().unwrap_or(signature);
+        let expected = VerifyingKey::from_sec1_bytes(public_key).map_err(|e| e.to_string())?;
+        let recovery_id = (0..2)
+            .filter_map(RecoveryId::from_byte)
+            .find(|&id| {
+                VerifyingKey::recover_from_prehash(hash, &signature, id)
+                    .is_ok_and(|key| key == expected)
+            })
+            .ok_or("Signature does not match the wallet key")?;
+
+        let v = u128::from(chain_id) * 2 + 35 + u128::from(recovery_id.to_byte());
+        let (r, s) = signature.split_bytes();
+        let mut fields = self.fields();
+        fields.push(rlp_bytes(&be_bytes(v)));
+        fields.push(rlp_bytes(trim_zeros(&r)));
+        fields.push(rlp_bytes(trim_zeros(&s)));
+        Ok(rlp_list(&fields))
+    }
+}
+
+/// Bitcoin mainnet P2PKH address of a compressed public key
+fn btc_address(public_key: &[u8]) -> Result<String, String> {
+    if public_key.len() != 33 {
+        return Err("Expected a compressed public key".to_string());
+    }
+    let mut payload = vec![0x00];
+    payload.extend(Ripemd160::digest(Sha256::digest(public_key)));
+    let checksum = Sha256::digest(Sha256::digest(&payload));
+    payload.extend(&checksum[..4]);
+    Ok(base58(&payload))
+}
+
+/// EIP-55 checksummed Ethereum address of a public key
+fn eth_address(public_key: &[u8]) -> Result<String, String> {
+    let key = k256::PublicKey::from_sec1_bytes(public_key).map_err(|e| e.to_string())?;
+    let point = key.to_encoded_point(false);
+    let hash = Keccak256::digest(&point.as_bytes()[1..]);
+    Ok(checksum_address(&hash[12..]))
+}
+
+fn checksum_address(address: &[u8]) -> String {
+    let lower = to_hex(address);
+    let hash = Keccak256::digest(lower.as_bytes());
+    let checksummed: String = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+    format!("0x{checksummed}")
+}
+
+fn parse_eth_address(address: &str) -> Result<[u8; 20], String> {
+    let hex = address
+        .strip_prefix("0x")
+        .ok_or_else(|| format!("Invalid address '{address}'"))?;
+    let bytes = from_hex(hex)?;
+    bytes
+        .try_into()
+        .map_err(|_| format!("Invalid address '{address}'"))
+}
+
+/// Confirmed balance of a Bitcoin address from the Esplora API
+async fn btc_balance(address: &str) -> Result<u64, String> {
+    #[derive(Deserialize)]
+    struct Stats {
+        funded_txo_sum: u64,
+        spent_txo_sum: u64,
+    }
+    #[derive(Deserialize)]
+    struct AddressInfo {
+        chain_stats: Stats,
+    }
+
+    let response = http::get(&format!("{BTC_API_URL}/address/{address}"))
+        .await
+        .map_err(|e| e.to_string())?;
+    let info: AddressInfo = response.json().map_err(|e| e.to_string())?;
+    Ok(info
+        .chain_stats
+        .funded_txo_sum
+        .saturating_sub(info.chain_stats.spent_txo_sum))
+}
+
+/// Calls an Ethereum JSON-RPC method and returns its string result
+async fn eth_rpc(method: &str, params: &[&str]) -> Result<String, String> {
+    #[derive(Deserialize)]
+    struct RpcError {
+        message: String,
+    }
+    #[derive(Deserialize)]
+    struct RpcResponse {
+        result: Option<String>,
+        error: Option<RpcError>,
+    }
+
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    });
+    let response = http::post_json(ETH_RPC_URL, &body)
+        .await
+        .map_err(|e| e.to_string())?;
+    let response: RpcResponse = response.json().map_err(|e| e.to_string())?;
+    match (response.result, response.error) {
+        (Some(result), _) => Ok(result),
+        (None, Some(error)) => Err(format!("{method} failed: {}", error.message)),
+        (None, None) => Err(format!("{method} returned no result")),
+    }
+}
+
+async fn eth_rpc_quantity(method: &str, params: &[&str]) -> Result<u128, String> {
+    parse_quantity(&eth_rpc(method, params).await?)
+}
+
+/// Parses a JSON-RPC quantity such as `"0x1bc16d674ec80000"`
+fn parse_quantity(quantity: &str) -> Result<u128, String> {
+    let digits = quantity
+        .strip_prefix("0x")
+        .ok_or_else(|| format!("Invalid quantity '{quantity}'"))?;
+    u128::from_str_radix(digits, 16).map_err(|_| format!("Invalid quantity '{quantity}'"))
+}
+
+fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return bytes.to_vec();
+    }
+    let mut encoded = rlp_length(bytes.len(), 0x80);
+    encoded.extend_from_slice(bytes);
+    encoded
+}
+
+fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload = items.concat();
+    let mut encoded = rlp_length(payload.len(), 0xc0);
+    encoded.extend(payload);
+    encoded
+}
+
+fn rlp_length(len: usize, offset: u8) -> Vec<u8> {
+    if len < 56 {
+        // Fits in the prefix byte
+        return vec![offset + len as u8];
+    }
+    let len_bytes = be_bytes(len as u128);
+    let mut prefix = vec![offset + 55 + len_bytes.len() as u8];
+    prefix.extend(len_bytes);
+    prefix
+}
+
+/// Minimal big-endian encoding; zero is empty
+fn be_bytes(value: u128) -> Vec<u8> {
+    trim_zeros(&value.to_be_bytes()).to_vec()
+}
+
+fn trim_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+fn base58(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in bytes {
+        let mut carry = u32::from(byte);
+        for digit in &mut digits {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let zeros = bytes.iter().take_while(|&&b| b == 0).count();
+    std::iter::repeat('1')
+        .take(zeros)
+        .chain(digits.iter().rev().map(|&d| ALPHABET[d as usize] as char))
+        .collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>, String> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return Err("Invalid hex string".to_string());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| "Invalid hex string".to_string())
+        })
+        .collect()
+}
+
+// Generate MCP server endpoints; `init` takes the admin principal
+icarus_macros::mcp! { auth = true }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compressed public key of the private key 1 (the generator point)
+    const GENERATOR: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    #[test]
+    fn test_addresses_from_public_key() {
+        let key = from_hex(GENERATOR).unwrap();
+        assert_eq!(
+            btc_address(&key).unwrap(),
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"
+        );
+        assert_eq!(
+            eth_address(&key).unwrap(),
+            "0x7E5F4552091A69125d5DfCd7b8C2659029395Bdf"
+        );
+        assert!(btc_address(&key[1..]).is_err());
+    }
+
+    #[test]
+    fn test_rlp_encoding() {
+        assert_eq!(rlp_bytes(b"dog"), [0x83, b'd', b'o', b'g']);
+        assert_eq!(rlp_bytes(&[]), [0x80]);
+        assert_eq!(rlp_bytes(&[0x0f]), [0x0f]);
+        assert_eq!(rlp_bytes(&be_bytes(1024)), [0x82, 0x04, 0x00]);
+        assert_eq!(
+            rlp_list(&[rlp_bytes(b"cat"), rlp_bytes(b"dog")]),
+            [0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g']
+        );
+        let long = rlp_bytes(&[b'a'; 56]);
+        assert_eq!(&long[..2], [0xb8, 56]);
+    }
+
+    #[test]
+    fn test_parse_inputs() {
+        assert_eq!(
+            parse_quantity("0x1bc16d674ec80000").unwrap(),
+            2 * 10u128.pow(18)
+        );
+        assert!(parse_quantity("1234").is_err());
+        assert!(parse_eth_address("0x7E5F4552091A69125d5DfCd7b8C2659029395Bdf").is_ok());
+        assert!(parse_eth_address("7E5F4552091A69125d5DfCd7b8C2659029395Bdf").is_err());
+        assert!(parse_eth_address("0x1234").is_err());
+        assert_eq!(Chain::parse("Bitcoin").unwrap(), Chain::Btc);
+        assert!(Chain::parse("sol").is_err());
+    }
+}